@@ -0,0 +1,379 @@
+//! Camada de desenho simples sobre quadros capturados
+//!
+//! Retângulos, destaques, círculos, setas e texto desenhados direto nos pixels do `Frame`, para
+//! ferramentas de relato de bugs marcarem regiões antes de salvar. As formas são rasterizadas
+//! na CPU; o texto usa uma fonte do sistema via GDI, sem dependências de edição de imagem.
+//! Coordenadas são relativas ao quadro (origem no canto superior esquerdo de `frame.region`).
+
+use std::ffi::c_void;
+use std::ptr;
+use std::slice;
+
+use windows::core::PCWSTR;
+use windows::Win32::Foundation::{COLORREF, HANDLE, SIZE};
+use windows::Win32::Graphics::Dxgi::Common::{DXGI_FORMAT_B8G8R8A8_UNORM, DXGI_FORMAT_R8G8B8A8_UNORM};
+use windows::Win32::Graphics::Gdi::*;
+
+use crate::frame::Frame;
+use crate::geometry::{Point, Rect, Size};
+use crate::Result;
+
+/// Fonte usada para o texto das anotações
+const FONT_FACE: &str = "Segoe UI";
+/// Comprimento das pontas da seta em múltiplos da espessura da linha
+const ARROW_HEAD_SCALE: f64 = 4.0;
+/// Margem entre o texto e o fundo da etiqueta
+pub(crate) const LABEL_PADDING: i32 = 4;
+
+/// Cor RGBA; o alfa controla a opacidade do desenho sobre o quadro
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    pub const RED: Color = Color::rgb(255, 0, 0);
+    pub const GREEN: Color = Color::rgb(0, 200, 0);
+    pub const YELLOW: Color = Color::rgb(255, 220, 0);
+    pub const WHITE: Color = Color::rgb(255, 255, 255);
+    pub const BLACK: Color = Color::rgb(0, 0, 0);
+
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b, a: 255 }
+    }
+
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self { r, g, b, a }
+    }
+
+    /// A mesma cor com outra opacidade
+    pub const fn with_alpha(self, a: u8) -> Self {
+        Self { a, ..self }
+    }
+}
+
+/// Elemento desenhado por `annotate`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "snake_case"))]
+pub enum Annotation {
+    /// Contorno de retângulo
+    Rect { rect: Rect, color: Color, thickness: u32 },
+    /// Retângulo preenchido; com alfa baixo funciona como marca-texto
+    Highlight { rect: Rect, color: Color },
+    /// Seta de `from` até `to`, com a ponta em `to`
+    Arrow { from: Point, to: Point, color: Color, thickness: u32 },
+    /// Círculo de raio `radius`; com `thickness` 0 é preenchido, senão só o contorno
+    Circle { center: Point, radius: u32, color: Color, thickness: u32 },
+    /// Texto com o canto superior esquerdo em `at`; `background` desenha uma etiqueta atrás
+    Text {
+        at: Point,
+        text: String,
+        color: Color,
+        size: u32,
+        background: Option<Color>,
+    },
+}
+
+/// Desenha as anotações em ordem sobre o quadro (B8G8R8A8 ou R8G8B8A8).
+///
+/// Partes fora do quadro são recortadas.
+pub fn annotate(frame: &mut Frame, annotations: &[Annotation]) -> Result<()> {
+    let channels = match frame.format {
+        DXGI_FORMAT_B8G8R8A8_UNORM => [2, 1, 0],
+        DXGI_FORMAT_R8G8B8A8_UNORM => [0, 1, 2],
+        format => return Err(format!("Formato não suportado para anotações: {:?}", format).into()),
+    };
+
+    let (width, height) = (frame.width() as usize, frame.height() as usize);
+    if frame.data.len() < frame.stride() * height {
+        return Err("Buffer menor que o quadro".into());
+    }
+
+    let mut canvas = Canvas {
+        data: &mut frame.data,
+        width: width as i64,
+        height: height as i64,
+        channels,
+    };
+
+    for annotation in annotations {
+        match annotation {
+            Annotation::Rect { rect, color, thickness } => canvas.stroke_rect(*rect, *color, (*thickness).max(1) as i64),
+            Annotation::Highlight { rect, color } => canvas.fill_rect(*rect, *color),
+            Annotation::Arrow { from, to, color, thickness } => canvas.arrow(*from, *to, *color, (*thickness).max(1)),
+            Annotation::Circle {
+                center,
+                radius,
+                color,
+                thickness,
+            } => canvas.circle(*center, *radius, *color, *thickness),
+            Annotation::Text {
+                at,
+                text,
+                color,
+                size,
+                background,
+            } => canvas.text(*at, text, *color, *size, *background)?,
+        }
+    }
+
+    Ok(())
+}
+
+struct Canvas<'a> {
+    data: &'a mut [u8],
+    width: i64,
+    height: i64,
+    // Posição de R, G e B dentro do pixel
+    channels: [usize; 3],
+}
+
+impl Canvas<'_> {
+    /// Mistura `color` no pixel com a opacidade da cor multiplicada por `coverage`
+    fn blend(&mut self, x: i64, y: i64, color: Color, coverage: u8) {
+        if x < 0 || y < 0 || x >= self.width || y >= self.height {
+            return;
+        }
+
+        let alpha = color.a as u32 * coverage as u32 / 255;
+        if alpha == 0 {
+            return;
+        }
+
+        let offset = (y * self.width + x) as usize * 4;
+        for (channel, value) in self.channels.iter().zip([color.r, color.g, color.b]) {
+            let pixel = &mut self.data[offset + channel];
+            *pixel = ((value as u32 * alpha + *pixel as u32 * (255 - alpha) + 127) / 255) as u8;
+        }
+    }
+
+    fn fill_rect(&mut self, rect: Rect, color: Color) {
+        let left = (rect.left as i64).max(0);
+        let top = (rect.top as i64).max(0);
+        let right = rect.right().min(self.width);
+        let bottom = rect.bottom().min(self.height);
+
+        for y in top..bottom {
+            for x in left..right {
+                self.blend(x, y, color, 255);
+            }
+        }
+    }
+
+    /// Contorno com a espessura para dentro do retângulo
+    fn stroke_rect(&mut self, rect: Rect, color: Color, thickness: i64) {
+        let (left, top, right, bottom) = (rect.left as i64, rect.top as i64, rect.right(), rect.bottom());
+        let band = |left: i64, top: i64, right: i64, bottom: i64| {
+            Rect::new(left as i32, top as i32, (right - left).max(0) as u32, (bottom - top).max(0) as u32)
+        };
+
+        // Faixas sem sobreposição, para que cores translúcidas não escureçam os cantos
+        let inner_top = (top + thickness).min(bottom);
+        let inner_bottom = (bottom - thickness).max(inner_top);
+        self.fill_rect(band(left, top, right, inner_top), color);
+        self.fill_rect(band(left, inner_bottom, right, bottom), color);
+        self.fill_rect(band(left, inner_top, (left + thickness).min(right), inner_bottom), color);
+        self.fill_rect(band((right - thickness).max(left), inner_top, right, inner_bottom), color);
+    }
+
+    /// Segmento com pontas arredondadas: cobre os pixels a até `thickness / 2` do segmento
+    fn line(&mut self, from: (f64, f64), to: (f64, f64), color: Color, thickness: f64) {
+        let radius = thickness / 2.0;
+        let min_x = (from.0.min(to.0) - radius).floor() as i64;
+        let max_x = (from.0.max(to.0) + radius).ceil() as i64;
+        let min_y = (from.1.min(to.1) - radius).floor() as i64;
+        let max_y = (from.1.max(to.1) + radius).ceil() as i64;
+
+        let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+        let length_sq = dx * dx + dy * dy;
+
+        for y in min_y.max(0)..=max_y.min(self.height - 1) {
+            for x in min_x.max(0)..=max_x.min(self.width - 1) {
+                let (px, py) = (x as f64 + 0.5, y as f64 + 0.5);
+                let t = if length_sq > 0.0 {
+                    (((px - from.0) * dx + (py - from.1) * dy) / length_sq).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                let (cx, cy) = (from.0 + t * dx, from.1 + t * dy);
+                let distance = ((px - cx).powi(2) + (py - cy).powi(2)).sqrt();
+
+                // Meio pixel de borda suavizada
+                let coverage = (radius + 0.5 - distance).clamp(0.0, 1.0);
+                if coverage > 0.0 {
+                    self.blend(x, y, color, (coverage * 255.0) as u8);
+                }
+            }
+        }
+    }
+
+    /// Disco ou anel centrado em `center`, com meio pixel de borda suavizada dos dois lados
+    fn circle(&mut self, center: Point, radius: u32, color: Color, thickness: u32) {
+        let (cx, cy, outer) = (center.x as f64, center.y as f64, radius as f64);
+        let inner = if thickness == 0 { None } else { Some((outer - thickness as f64).max(0.0)) };
+        let reach = outer.ceil() as i64 + 1;
+
+        for y in (center.y as i64 - reach).max(0)..=(center.y as i64 + reach).min(self.height - 1) {
+            for x in (center.x as i64 - reach).max(0)..=(center.x as i64 + reach).min(self.width - 1) {
+                let distance = ((x as f64 + 0.5 - cx).powi(2) + (y as f64 + 0.5 - cy).powi(2)).sqrt();
+                let mut coverage = (outer + 0.5 - distance).clamp(0.0, 1.0);
+                if let Some(inner) = inner {
+                    coverage = coverage.min((distance - inner + 0.5).clamp(0.0, 1.0));
+                }
+                if coverage > 0.0 {
+                    self.blend(x, y, color, (coverage * 255.0) as u8);
+                }
+            }
+        }
+    }
+
+    fn arrow(&mut self, from: Point, to: Point, color: Color, thickness: u32) {
+        let thickness = thickness as f64;
+        let (from, to) = ((from.x as f64, from.y as f64), (to.x as f64, to.y as f64));
+        self.line(from, to, color, thickness);
+
+        let angle = (from.1 - to.1).atan2(from.0 - to.0);
+        let head = thickness * ARROW_HEAD_SCALE + 6.0;
+        for side in [-1.0, 1.0] {
+            let barb = angle + side * std::f64::consts::FRAC_PI_6;
+            self.line(to, (to.0 + head * barb.cos(), to.1 + head * barb.sin()), color, thickness);
+        }
+    }
+
+    /// Renderiza o texto em tons de cinza com GDI e usa o resultado como cobertura
+    fn text(&mut self, at: Point, text: &str, color: Color, size: u32, background: Option<Color>) -> Result<()> {
+        let wide: Vec<u16> = text.encode_utf16().collect();
+        if wide.is_empty() {
+            return Ok(());
+        }
+
+        unsafe {
+            let (coverage, width, height) = with_font(size, |dc| render_coverage(dc, &wide))?;
+            if let Some(background) = background {
+                let label = Rect::new(
+                    at.x - LABEL_PADDING,
+                    at.y - LABEL_PADDING,
+                    width as u32 + 2 * LABEL_PADDING as u32,
+                    height as u32 + 2 * LABEL_PADDING as u32,
+                );
+                self.fill_rect(label, background);
+            }
+
+            for y in 0..height {
+                for x in 0..width {
+                    let value = coverage[(y * width + x) as usize];
+                    if value != 0 {
+                        self.blend(at.x as i64 + x, at.y as i64 + y, color, value);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Tamanho em pixels que `Annotation::Text` ocupa com `size` (sem a etiqueta de fundo)
+pub(crate) fn measure_text(text: &str, size: u32) -> Result<Size> {
+    let wide: Vec<u16> = text.encode_utf16().collect();
+    if wide.is_empty() {
+        return Ok(Size::new(0, 0));
+    }
+    unsafe {
+        with_font(size, |dc| {
+            let mut extent = SIZE::default();
+            if !GetTextExtentPoint32W(dc, &wide, &mut extent).as_bool() {
+                return Err("Falha ao medir o texto".into());
+            }
+            Ok(Size::new(extent.cx.max(0) as u32, extent.cy.max(0) as u32))
+        })
+    }
+}
+
+/// Executa `f` com um DC de memória usando a fonte das anotações em `size` pixels
+unsafe fn with_font<T>(size: u32, f: impl FnOnce(HDC) -> Result<T>) -> Result<T> {
+    let face: Vec<u16> = FONT_FACE.encode_utf16().chain(Some(0)).collect();
+    let dc = CreateCompatibleDC(HDC::default());
+    if dc.is_invalid() {
+        return Err("Falha ao criar DC para o texto".into());
+    }
+    let font = CreateFontW(
+        -(size.max(1) as i32),
+        0,
+        0,
+        0,
+        FW_SEMIBOLD.0 as i32,
+        0,
+        0,
+        0,
+        DEFAULT_CHARSET.0 as u32,
+        OUT_DEFAULT_PRECIS.0 as u32,
+        CLIP_DEFAULT_PRECIS.0 as u32,
+        ANTIALIASED_QUALITY.0 as u32,
+        (DEFAULT_PITCH.0 | FF_SWISS.0) as u32,
+        PCWSTR(face.as_ptr()),
+    );
+    let previous_font = SelectObject(dc, font);
+
+    let result = f(dc);
+
+    SelectObject(dc, previous_font);
+    DeleteObject(font);
+    DeleteDC(dc);
+    result
+}
+
+/// Desenha o texto em branco sobre preto em uma DIB do tamanho do texto e retorna a
+/// cobertura (0-255) por pixel, a largura e a altura
+unsafe fn render_coverage(dc: HDC, text: &[u16]) -> Result<(Vec<u8>, i64, i64)> {
+    let mut extent = SIZE::default();
+    if !GetTextExtentPoint32W(dc, text, &mut extent).as_bool() || extent.cx <= 0 || extent.cy <= 0 {
+        return Err("Falha ao medir o texto".into());
+    }
+    let (width, height) = (extent.cx as i64, extent.cy as i64);
+
+    let info = BITMAPINFO {
+        bmiHeader: BITMAPINFOHEADER {
+            biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+            biWidth: extent.cx,
+            // Altura negativa: linhas de cima para baixo
+            biHeight: -extent.cy,
+            biPlanes: 1,
+            biBitCount: 32,
+            biCompression: BI_RGB.0,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let mut bits: *mut c_void = ptr::null_mut();
+    let bitmap = CreateDIBSection(dc, &info, DIB_RGB_COLORS, &mut bits, HANDLE::default(), 0)?;
+    let previous_bitmap = SelectObject(dc, bitmap);
+
+    // A DIB começa zerada (fundo preto)
+    SetBkMode(dc, TRANSPARENT);
+    SetTextColor(dc, COLORREF(0x00FF_FFFF));
+    let drawn = TextOutW(dc, 0, 0, text).as_bool();
+    let _ = GdiFlush();
+
+    let coverage = if drawn && !bits.is_null() {
+        let pixels = slice::from_raw_parts(bits as *const u8, (width * height * 4) as usize);
+        // Com antialiasing em tons de cinza os três canais são iguais; o verde basta
+        pixels.chunks_exact(4).map(|pixel| pixel[1]).collect()
+    } else {
+        Vec::new()
+    };
+
+    SelectObject(dc, previous_bitmap);
+    DeleteObject(bitmap);
+
+    if coverage.is_empty() {
+        return Err("Falha ao desenhar o texto".into());
+    }
+    Ok((coverage, width, height))
+}
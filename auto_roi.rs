@@ -0,0 +1,135 @@
+//! Região de captura que acompanha o conteúdo animado
+//!
+//! `AutoRoi` parte de uma região semente e cresce para cobrir as áreas que a duplicação informa
+//! como alteradas (dirty rects e destinos de move rects), com uma margem em volta. A cada
+//! `shrink_after` quadros com mudanças ela volta à semente mais a atividade observada nesse
+//! intervalo, então uma animação que parou deixa de ser lida. Ferramentas que só se interessam
+//! pela parte da tela que está mudando leem o mínimo de pixels sem calcular regiões por conta
+//! própria; `DxgiCapture::capture_auto` atualiza a região antes de cada cópia.
+//!
+//! Quadros em que a área alterada cobre o monitor inteiro (primeiro quadro da duplicação, troca
+//! de modo, redesenho completo) não são atividade e não fazem a região crescer.
+
+use crate::geometry::{Rect, RectError, Size};
+
+/// Como a região cresce e encolhe
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct AutoRoiOptions {
+    /// Pixels acrescentados em volta de cada área alterada
+    pub margin: u32,
+    /// Quadros com mudanças até a região ser recalculada a partir da semente (0 = nunca encolhe)
+    pub shrink_after: u32,
+}
+
+impl Default for AutoRoiOptions {
+    fn default() -> Self {
+        Self {
+            margin: 32,
+            shrink_after: 120,
+        }
+    }
+}
+
+/// Região que cresce e encolhe conforme as áreas alteradas do monitor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AutoRoi {
+    seed: Rect,
+    region: Rect,
+    bounds: Size,
+    options: AutoRoiOptions,
+    // Atividade desde o último recálculo e quadros com mudanças contados nesse intervalo
+    activity: Option<Rect>,
+    active_frames: u32,
+}
+
+impl AutoRoi {
+    /// Região que começa em `seed` em um monitor de tamanho `bounds`
+    pub fn new(seed: Rect, bounds: Size, options: AutoRoiOptions) -> Result<Self, RectError> {
+        seed.validate_within(bounds)?;
+        Ok(Self {
+            seed,
+            region: seed,
+            bounds,
+            options,
+            activity: None,
+            active_frames: 0,
+        })
+    }
+
+    /// Região a capturar agora; sempre contém a semente
+    pub fn region(&self) -> Rect {
+        self.region
+    }
+
+    pub fn seed(&self) -> Rect {
+        self.seed
+    }
+
+    /// Tamanho do monitor a que a região se limita
+    pub fn bounds(&self) -> Size {
+        self.bounds
+    }
+
+    pub fn options(&self) -> &AutoRoiOptions {
+        &self.options
+    }
+
+    /// Volta à semente e descarta a atividade observada
+    pub fn reset(&mut self) {
+        self.region = self.seed;
+        self.activity = None;
+        self.active_frames = 0;
+    }
+
+    /// Ajusta a região às áreas alteradas de um quadro (`DxgiCapture::changed_rects`); `None`
+    /// (a duplicação não informou) mantém a região. Retorna a nova região.
+    pub fn update(&mut self, changed: Option<&[Rect]>) -> Rect {
+        let full = Rect::from_size(self.bounds);
+        let changes = match changed {
+            Some(rects) if !rects.is_empty() => rects,
+            _ => return self.region,
+        };
+        if changes.iter().any(|rect| rect.contains_rect(&full)) {
+            return self.region;
+        }
+
+        let margin = self.options.margin as i64;
+        for rect in changes {
+            let grown = match inflate(rect, margin).clamp_to(self.bounds) {
+                Some(grown) => grown,
+                None => continue,
+            };
+            self.region = self.region.union(&grown);
+            self.activity = Some(self.activity.map_or(grown, |activity| activity.union(&grown)));
+        }
+
+        self.active_frames += 1;
+        if self.options.shrink_after != 0 && self.active_frames >= self.options.shrink_after {
+            self.region = self.activity.take().map_or(self.seed, |activity| self.seed.union(&activity));
+            self.active_frames = 0;
+        }
+        self.region
+    }
+
+    /// Muda o tamanho do monitor (ex.: após uma troca de resolução); a semente é recortada aos
+    /// novos limites e a região volta a ela
+    pub fn set_bounds(&mut self, bounds: Size) -> Result<(), RectError> {
+        let seed = self.seed.clamp_to(bounds).ok_or(RectError::OutOfBounds { region: self.seed, bounds })?;
+        self.seed = seed;
+        self.bounds = bounds;
+        self.reset();
+        Ok(())
+    }
+}
+
+/// `rect` com `margin` pixels a mais de cada lado
+fn inflate(rect: &Rect, margin: i64) -> Rect {
+    let left = (rect.left as i64 - margin).max(i32::MIN as i64);
+    let top = (rect.top as i64 - margin).max(i32::MIN as i64);
+    let grow = |length: u32, start: i32, new_start: i64| {
+        (length as i64 + (start as i64 - new_start) + margin).min(u32::MAX as i64) as u32
+    };
+    Rect::new(left as i32, top as i32, grow(rect.width, rect.left, left), grow(rect.height, rect.top, top))
+}
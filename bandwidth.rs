@@ -0,0 +1,259 @@
+//! Controle adaptativo de banda para as saídas de rede
+//!
+//! O `BandwidthController` recebe das saídas de rede os bytes enviados, o RTT e a ocupação da
+//! fila de envio, e a cada `interval` decide o bitrate do encoder: reduz em proporção quando há
+//! sinal de congestionamento (fila crescendo, RTT subindo acima do mínimo, vazão acima do
+//! orçamento) e volta a subir aos poucos enquanto a rede responde bem. FPS e escala seguem
+//! o bitrate por degraus, como em `webrtc_source`.
+//!
+//! Um `CaptureStream` com `StreamOptions::bandwidth` aplica o FPS na própria captura e lê a fila
+//! da saída indicada; a escala e o bitrate ficam em `settings()` para o encoder aplicar.
+
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// Fator aplicado ao bitrate a cada intervalo congestionado
+const DECREASE_FACTOR: f64 = 0.7;
+/// Fração do orçamento somada ao bitrate a cada intervalo sem congestionamento
+const INCREASE_STEP: f64 = 0.05;
+/// Intervalos sem congestionamento antes de subir um degrau de FPS/escala
+const RECOVER_INTERVALS: u32 = 3;
+
+/// Degraus de qualidade: (fração mínima do orçamento, escala, fração do FPS máximo)
+const QUALITY_LADDER: [(f64, f32, f64); 5] = [
+    (0.6, 1.0, 1.0),
+    (0.35, 1.0, 0.5),
+    (0.2, 0.75, 0.5),
+    (0.1, 0.5, 0.5),
+    (0.0, 0.5, 0.25),
+];
+
+/// Limites do controlador
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct BandwidthOptions {
+    /// Banda disponível em bits por segundo
+    pub budget: u32,
+    pub max_fps: u32,
+    pub min_fps: u32,
+    /// Menor escala da região capturada que o controlador pode sugerir
+    pub min_scale: f32,
+    pub min_bitrate: u32,
+    /// Quadros na fila de envio acima dos quais a rede é considerada congestionada
+    pub max_queue_depth: usize,
+    /// Aumento do RTT sobre o menor observado que indica congestionamento
+    pub rtt_tolerance: Duration,
+    /// Intervalo entre reavaliações
+    pub interval: Duration,
+    /// Saída do `CaptureStream` (nome de `add_sink`) cuja fila o stream informa ao controlador
+    pub sink: Option<String>,
+}
+
+impl Default for BandwidthOptions {
+    fn default() -> Self {
+        Self {
+            budget: 5_000_000,
+            max_fps: 60,
+            min_fps: 5,
+            min_scale: 0.5,
+            min_bitrate: 300_000,
+            max_queue_depth: 2,
+            rtt_tolerance: Duration::from_millis(50),
+            interval: Duration::from_secs(1),
+            sink: None,
+        }
+    }
+}
+
+/// Parâmetros que a captura e o encoder devem aplicar no momento
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BandwidthSettings {
+    pub fps: u32,
+    /// Fator de escala da região capturada antes da codificação
+    pub scale: f32,
+    /// Bitrate alvo do encoder em bits por segundo
+    pub bitrate: u32,
+}
+
+/// Estado atual do controlador
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BandwidthStats {
+    pub settings: BandwidthSettings,
+    /// Vazão medida no último intervalo, em bits por segundo
+    pub throughput: u64,
+    /// Último RTT informado e o menor desde o início
+    pub rtt: Option<Duration>,
+    pub min_rtt: Option<Duration>,
+    /// Maior ocupação da fila de envio no último intervalo
+    pub queue_depth: usize,
+    /// Intervalos em que houve congestionamento
+    pub congestion_events: u64,
+}
+
+#[derive(Debug)]
+struct State {
+    options: BandwidthOptions,
+    bitrate: u32,
+    level: usize,
+    clean_intervals: u32,
+    // Medições do intervalo corrente
+    interval_start: Instant,
+    sent_bytes: u64,
+    max_depth: usize,
+    stats: BandwidthStats,
+}
+
+impl State {
+    fn settings(&self) -> BandwidthSettings {
+        let (_, scale, fps) = QUALITY_LADDER[self.level];
+        let options = &self.options;
+        BandwidthSettings {
+            fps: ((options.max_fps as f64 * fps).round() as u32).clamp(options.min_fps.min(options.max_fps), options.max_fps),
+            scale: scale.max(options.min_scale).min(1.0),
+            bitrate: self.bitrate,
+        }
+    }
+
+    /// Primeiro degrau que cabe no bitrate atual
+    fn level_for_bitrate(&self) -> usize {
+        let fraction = self.bitrate as f64 / self.options.budget.max(1) as f64;
+        QUALITY_LADDER
+            .iter()
+            .position(|(minimum, _, _)| fraction >= *minimum)
+            .unwrap_or(QUALITY_LADDER.len() - 1)
+    }
+
+    fn evaluate(&mut self, now: Instant) -> bool {
+        let elapsed = now.duration_since(self.interval_start);
+        let throughput = (self.sent_bytes as f64 * 8.0 / elapsed.as_secs_f64().max(1e-3)) as u64;
+        let budget = self.options.budget.max(self.options.min_bitrate);
+
+        let queue_growing = self.max_depth > self.options.max_queue_depth;
+        let rtt_rising = match (self.stats.rtt, self.stats.min_rtt) {
+            (Some(rtt), Some(min)) => rtt > min + self.options.rtt_tolerance,
+            _ => false,
+        };
+        let over_budget = throughput > budget as u64 + budget as u64 / 10;
+
+        let previous = self.settings();
+        if queue_growing || rtt_rising || over_budget {
+            self.bitrate = ((self.bitrate as f64 * DECREASE_FACTOR) as u32).max(self.options.min_bitrate);
+            self.clean_intervals = 0;
+            self.stats.congestion_events += 1;
+            // Descer logo; subir só depois de alguns intervalos limpos
+            self.level = self.level.max(self.level_for_bitrate());
+            debug!(
+                "Rede congestionada (fila {}, RTT {:?}, {} bps), bitrate reduzido para {}",
+                self.max_depth, self.stats.rtt, throughput, self.bitrate
+            );
+        } else {
+            self.bitrate = (self.bitrate + (budget as f64 * INCREASE_STEP) as u32).min(budget);
+            self.clean_intervals += 1;
+            let target = self.level_for_bitrate();
+            if target < self.level && self.clean_intervals >= RECOVER_INTERVALS {
+                self.level -= 1;
+                self.clean_intervals = 0;
+            } else if target > self.level {
+                self.level = target;
+            }
+        }
+
+        self.stats.throughput = throughput;
+        self.stats.queue_depth = self.max_depth;
+        self.stats.settings = self.settings();
+        self.interval_start = now;
+        self.sent_bytes = 0;
+        self.max_depth = 0;
+        self.stats.settings != previous
+    }
+}
+
+/// Controlador compartilhado entre a captura e as saídas de rede; clones usam o mesmo estado
+#[derive(Debug, Clone)]
+pub struct BandwidthController {
+    state: Arc<Mutex<State>>,
+}
+
+impl BandwidthController {
+    /// Começa no orçamento inteiro, na melhor qualidade
+    pub fn new(options: BandwidthOptions) -> Self {
+        let bitrate = options.budget.max(options.min_bitrate);
+        let mut state = State {
+            options,
+            bitrate,
+            level: 0,
+            clean_intervals: 0,
+            interval_start: Instant::now(),
+            sent_bytes: 0,
+            max_depth: 0,
+            stats: BandwidthStats {
+                settings: BandwidthSettings {
+                    fps: 0,
+                    scale: 1.0,
+                    bitrate,
+                },
+                throughput: 0,
+                rtt: None,
+                min_rtt: None,
+                queue_depth: 0,
+                congestion_events: 0,
+            },
+        };
+        state.level = state.level_for_bitrate();
+        state.stats.settings = state.settings();
+        Self {
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// FPS, escala e bitrate a aplicar agora
+    pub fn settings(&self) -> BandwidthSettings {
+        self.lock().stats.settings
+    }
+
+    pub fn stats(&self) -> BandwidthStats {
+        self.lock().stats
+    }
+
+    /// Muda o orçamento (ex.: escolhido pelo usuário); vale a partir do próximo intervalo
+    pub fn set_budget(&self, budget: u32) {
+        let mut state = self.lock();
+        state.options.budget = budget;
+        state.bitrate = state.bitrate.min(budget.max(state.options.min_bitrate));
+    }
+
+    /// Bytes enviados pela saída de rede
+    pub fn report_sent(&self, bytes: usize) {
+        self.lock().sent_bytes += bytes as u64;
+    }
+
+    /// RTT medido (ping, RTCP, ACK da aplicação)
+    pub fn report_rtt(&self, rtt: Duration) {
+        let mut state = self.lock();
+        state.stats.rtt = Some(rtt);
+        state.stats.min_rtt = Some(state.stats.min_rtt.map_or(rtt, |min| min.min(rtt)));
+    }
+
+    /// Quadros ou pacotes aguardando envio
+    pub fn report_queue_depth(&self, depth: usize) {
+        let mut state = self.lock();
+        state.max_depth = state.max_depth.max(depth);
+    }
+
+    /// Reavalia se o intervalo terminou; retorna os parâmetros novos quando mudaram
+    pub fn update(&self) -> Option<BandwidthSettings> {
+        let now = Instant::now();
+        let mut state = self.lock();
+        if now.duration_since(state.interval_start) < state.options.interval {
+            return None;
+        }
+        state.evaluate(now).then(|| state.stats.settings)
+    }
+}
@@ -0,0 +1,247 @@
+//! CLI `dxgigrab`
+//!
+//! Uso:
+//! - `dxgigrab daemon <config.toml|config.json>`
+//! - `dxgigrab record [--format y4m|nut] [--fps <n>] [--frames <n>] [--output <adaptador>:<monitor>] <arquivo|->`
+
+use std::env;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use dxgi_capture::daemon::{self, DaemonConfig};
+use dxgi_capture::nut::NutWriter;
+use dxgi_capture::y4m::Y4mWriter;
+use dxgi_capture::{CaptureError, DxgiCapture, Frame, NoFramePolicy};
+use log::{Level, Log, Metadata, Record};
+use windows::Win32::Foundation::BOOL;
+use windows::Win32::System::Console::SetConsoleCtrlHandler;
+
+static STOP: OnceLock<Arc<AtomicBool>> = OnceLock::new();
+
+/// Ctrl+C / fechamento do console encerram as capturas de forma ordenada
+unsafe extern "system" fn on_console_event(_event: u32) -> BOOL {
+    if let Some(stop) = STOP.get() {
+        stop.store(true, Ordering::SeqCst);
+    }
+    BOOL::from(true)
+}
+
+/// Escreve os diagnósticos da biblioteca em stderr; `DXGIGRAB_DEBUG` inclui o nível debug
+struct StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!("{:<5} {}", record.level(), record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+fn init_logging() {
+    static LOGGER: StderrLogger = StderrLogger;
+    let level = if env::var_os("DXGIGRAB_DEBUG").is_some() {
+        Level::Debug
+    } else {
+        Level::Info
+    };
+
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(level.to_level_filter());
+    }
+}
+
+fn install_stop_handler() -> dxgi_capture::Result<Arc<AtomicBool>> {
+    let stop = STOP.get_or_init(|| Arc::new(AtomicBool::new(false))).clone();
+
+    unsafe {
+        SetConsoleCtrlHandler(Some(on_console_event), true)?;
+    }
+
+    Ok(stop)
+}
+
+fn run_daemon(config_path: &str) -> dxgi_capture::Result<()> {
+    let config = DaemonConfig::load(config_path)?;
+    let stop = install_stop_handler()?;
+    daemon::run(&config, stop)
+}
+
+#[derive(Clone, Copy)]
+enum RecordFormat {
+    Y4m,
+    Nut,
+}
+
+struct RecordOptions {
+    format: RecordFormat,
+    /// 0 usa a taxa de atualização do monitor
+    fps: u32,
+    /// 0 grava até Ctrl+C ou até o leitor fechar a saída
+    frames: u64,
+    adapter: u32,
+    output: u32,
+    path: String,
+}
+
+fn parse_record_args(args: &[String]) -> Result<RecordOptions, String> {
+    let mut options = RecordOptions {
+        format: RecordFormat::Y4m,
+        fps: 0,
+        frames: 0,
+        adapter: 0,
+        output: 0,
+        path: String::new(),
+    };
+    let mut path = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            if path.replace(arg.clone()).is_some() {
+                return Err(format!("argumento inesperado: {}", arg));
+            }
+            continue;
+        }
+
+        let value = args.next().ok_or_else(|| format!("{} sem valor", arg))?;
+        let invalid = || format!("valor inválido para {}: {}", arg, value);
+        match arg.as_str() {
+            "--format" => {
+                options.format = match value.as_str() {
+                    "y4m" => RecordFormat::Y4m,
+                    "nut" => RecordFormat::Nut,
+                    _ => return Err(invalid()),
+                }
+            }
+            "--fps" => options.fps = value.parse().map_err(|_| invalid())?,
+            "--frames" => options.frames = value.parse().map_err(|_| invalid())?,
+            "--output" => {
+                let (adapter, output) = value.split_once(':').ok_or_else(|| format!("--output espera <adaptador>:<monitor>, não {}", value))?;
+                options.adapter = adapter.parse().map_err(|_| format!("adaptador inválido: {}", adapter))?;
+                options.output = output.parse().map_err(|_| format!("monitor inválido: {}", output))?;
+            }
+            _ => return Err(format!("opção desconhecida: {}", arg)),
+        }
+    }
+
+    options.path = path.ok_or("falta o arquivo de saída (use - para stdout)")?;
+    Ok(options)
+}
+
+enum RecordWriter {
+    Y4m(Y4mWriter<Box<dyn Write>>),
+    Nut(NutWriter<Box<dyn Write>>),
+}
+
+impl RecordWriter {
+    fn write_frame(&mut self, frame: &Frame) -> dxgi_capture::Result<()> {
+        match self {
+            RecordWriter::Y4m(writer) => writer.write_frame(&frame.data, frame.format),
+            RecordWriter::Nut(writer) => writer.write_frame(&frame.data),
+        }
+    }
+
+    fn flush(&mut self) -> dxgi_capture::Result<()> {
+        match self {
+            RecordWriter::Y4m(writer) => writer.flush(),
+            RecordWriter::Nut(writer) => writer.flush(),
+        }
+    }
+}
+
+/// O leitor do outro lado do pipe (ex.: ffmpeg) fechou a entrada
+fn is_broken_pipe(error: &(dyn std::error::Error + 'static)) -> bool {
+    error.downcast_ref::<io::Error>().is_some_and(|e| e.kind() == io::ErrorKind::BrokenPipe)
+}
+
+/// Captura o monitor inteiro a taxa constante; sem quadro novo, repete o anterior
+fn run_record(options: &RecordOptions) -> dxgi_capture::Result<()> {
+    let stop = install_stop_handler()?;
+    let mut capture = DxgiCapture::builder()
+        .output(options.adapter, options.output)
+        .no_frame_policy(NoFramePolicy::LastFrame)
+        .build()?;
+
+    let region = capture.bounds();
+    let fps = if options.fps == 0 { capture.default_fps() } else { options.fps };
+    let output: Box<dyn Write> = if options.path == "-" {
+        Box::new(BufWriter::new(io::stdout().lock()))
+    } else {
+        Box::new(BufWriter::new(File::create(&options.path)?))
+    };
+    let mut writer = match options.format {
+        RecordFormat::Y4m => RecordWriter::Y4m(Y4mWriter::new(output, region.width, region.height, fps, 1)?),
+        RecordFormat::Nut => RecordWriter::Nut(NutWriter::new(output, capture.format(), region.width, region.height, fps, 1)?),
+    };
+    log::info!("Gravando {:?} a {} fps", region, fps);
+
+    let interval = Duration::from_secs(1) / fps.max(1);
+    let mut written = 0u64;
+    while !stop.load(Ordering::SeqCst) && (options.frames == 0 || written < options.frames) {
+        let tick = Instant::now();
+        match capture.capture_frame(region) {
+            Ok(frame) => {
+                if let Err(e) = writer.write_frame(&frame) {
+                    if is_broken_pipe(e.as_ref()) {
+                        return Ok(());
+                    }
+                    return Err(e);
+                }
+                written += 1;
+            }
+            // Ainda não houve o primeiro quadro
+            Err(CaptureError::NoNewFrame) => {}
+            Err(e) => return Err(e.into()),
+        }
+
+        if let Some(remaining) = interval.checked_sub(tick.elapsed()) {
+            thread::sleep(remaining);
+        }
+    }
+
+    match writer.flush() {
+        Err(e) if !is_broken_pipe(e.as_ref()) => Err(e),
+        _ => Ok(()),
+    }
+}
+
+const USAGE: &str = "uso: dxgigrab daemon <config.toml|config.json>
+       dxgigrab record [--format y4m|nut] [--fps <n>] [--frames <n>] [--output <adaptador>:<monitor>] <arquivo|->";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    init_logging();
+
+    let result = match args.as_slice() {
+        [command, config] if command == "daemon" => run_daemon(config),
+        [command, rest @ ..] if command == "record" => match parse_record_args(rest) {
+            Ok(options) => run_record(&options),
+            Err(e) => {
+                eprintln!("dxgigrab: {}\n{}", e, USAGE);
+                return ExitCode::from(2);
+            }
+        },
+        _ => {
+            eprintln!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("dxgigrab: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,271 @@
+//! Teste de longa duração `dxgigrab-soak` (feature `soak`)
+//!
+//! Uso: `dxgigrab-soak [--hours <h>] [--interval <s>] [--recreate-every <quadros>] [--output <adaptador>:<monitor>]`
+//!
+//! Captura o monitor inteiro na taxa de atualização por horas enquanto o operador troca
+//! resolução, conecta monitores e bloqueia/desbloqueia a sessão. A cada intervalo imprime
+//! handles, objetos GDI/USER, RSS, memória privada e VRAM e compara com a primeira amostra;
+//! crescimento acima dos limites é tratado como vazamento. Ao final descarta o capturador e
+//! lista os objetos DXGI/D3D11 vivos (com a camada de depuração ativa). Sai com código 1 se
+//! houver suspeita de vazamento.
+
+use std::env;
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use dxgi_capture::leak_check::{report_live_objects, ResourceUsage};
+use dxgi_capture::{CaptureError, DxgiCapture};
+use log::{Level, Log, Metadata, Record};
+use windows::Win32::Foundation::BOOL;
+use windows::Win32::System::Console::SetConsoleCtrlHandler;
+
+/// Crescimento tolerado em relação à primeira amostra
+const MAX_HANDLE_GROWTH: u32 = 64;
+const MAX_GUI_OBJECT_GROWTH: u32 = 16;
+const MAX_PRIVATE_GROWTH: u64 = 64 * 1024 * 1024;
+const MAX_VIDEO_MEMORY_GROWTH: u64 = 64 * 1024 * 1024;
+/// Espera antes de tentar de novo quando a captura não pode ser criada ou a sessão caiu
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+static STOP: AtomicBool = AtomicBool::new(false);
+
+unsafe extern "system" fn on_console_event(_event: u32) -> BOOL {
+    STOP.store(true, Ordering::SeqCst);
+    BOOL::from(true)
+}
+
+/// Diagnósticos da biblioteca (reinicializações, perdas de acesso) em stderr
+struct StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!("{:<5} {}", record.level(), record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+struct Options {
+    duration: Duration,
+    interval: Duration,
+    /// Quadros entre recriações do capturador (0 = nunca), para exercitar o caminho de criação
+    recreate_every: u64,
+    adapter: u32,
+    output: u32,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            duration: Duration::from_secs(8 * 3600),
+            interval: Duration::from_secs(60),
+            recreate_every: 0,
+            adapter: 0,
+            output: 0,
+        }
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let value = args.next().ok_or_else(|| format!("{} sem valor", flag))?;
+        let invalid = || format!("valor inválido para {}: {}", flag, value);
+        match flag.as_str() {
+            "--hours" => options.duration = Duration::from_secs_f64(value.parse::<f64>().map_err(|_| invalid())? * 3600.0),
+            "--interval" => options.interval = Duration::from_secs(value.parse().map_err(|_| invalid())?),
+            "--recreate-every" => options.recreate_every = value.parse().map_err(|_| invalid())?,
+            "--output" => {
+                let (adapter, output) = value.split_once(':').ok_or_else(|| format!("--output espera <adaptador>:<monitor>, não {}", value))?;
+                options.adapter = adapter.parse().map_err(|_| format!("adaptador inválido: {}", adapter))?;
+                options.output = output.parse().map_err(|_| format!("monitor inválido: {}", output))?;
+            }
+            _ => return Err(format!("opção desconhecida: {}", flag)),
+        }
+    }
+    Ok(options)
+}
+
+/// Resultados das capturas desde o início
+#[derive(Default)]
+struct Counters {
+    frames: u64,
+    unchanged: u64,
+    access_lost: u64,
+    disconnected: u64,
+    errors: u64,
+    recreated: u64,
+}
+
+fn create(options: &Options) -> Option<DxgiCapture> {
+    let built = DxgiCapture::builder()
+        .output(options.adapter, options.output)
+        .retry_after_reinit(true)
+        .wait_for_duplication(Duration::from_secs(30))
+        .build();
+    match built {
+        Ok(capture) => Some(capture),
+        Err(e) => {
+            eprintln!("WARN  falha ao criar a captura: {}", e);
+            None
+        }
+    }
+}
+
+/// Imprime a amostra e retorna os limites ultrapassados em relação à linha de base
+fn check_sample(elapsed: Duration, usage: &ResourceUsage, video_memory: u64, baseline: &(ResourceUsage, u64), counters: &Counters) -> Vec<String> {
+    println!(
+        "{:>8.1} min  handles {}  gdi {}  user {}  rss {} MiB  privada {} MiB  vram {} MiB  quadros {}  sem mudança {}  perdas de acesso {}  desconexões {}  erros {}  recriações {}",
+        elapsed.as_secs_f64() / 60.0,
+        usage.handles,
+        usage.gdi_objects,
+        usage.user_objects,
+        usage.working_set >> 20,
+        usage.private_bytes >> 20,
+        video_memory >> 20,
+        counters.frames,
+        counters.unchanged,
+        counters.access_lost,
+        counters.disconnected,
+        counters.errors,
+        counters.recreated,
+    );
+
+    let growth = usage.growth_since(&baseline.0);
+    let mut exceeded = Vec::new();
+    if growth.handles > MAX_HANDLE_GROWTH {
+        exceeded.push(format!("handles +{}", growth.handles));
+    }
+    if growth.gdi_objects > MAX_GUI_OBJECT_GROWTH || growth.user_objects > MAX_GUI_OBJECT_GROWTH {
+        exceeded.push(format!("objetos GDI +{} / USER +{}", growth.gdi_objects, growth.user_objects));
+    }
+    if growth.private_bytes > MAX_PRIVATE_GROWTH {
+        exceeded.push(format!("memória privada +{} MiB", growth.private_bytes >> 20));
+    }
+    if video_memory.saturating_sub(baseline.1) > MAX_VIDEO_MEMORY_GROWTH {
+        exceeded.push(format!("VRAM +{} MiB", video_memory.saturating_sub(baseline.1) >> 20));
+    }
+    exceeded
+}
+
+fn run(options: &Options) -> bool {
+    let start = Instant::now();
+    let mut counters = Counters::default();
+    let mut capture = create(options);
+    let mut baseline: Option<(ResourceUsage, u64)> = None;
+    let mut next_sample = start + options.interval;
+    let mut video_memory = 0;
+    let mut leak_suspected = false;
+    let mut attempts = 0u64;
+
+    while !STOP.load(Ordering::SeqCst) && start.elapsed() < options.duration {
+        let Some(current) = capture.as_mut() else {
+            thread::sleep(RETRY_DELAY);
+            capture = create(options);
+            continue;
+        };
+
+        let tick = Instant::now();
+        attempts += 1;
+        match current.capture_frame(current.bounds()) {
+            Ok(_) => counters.frames += 1,
+            Err(CaptureError::NoNewFrame) => counters.unchanged += 1,
+            Err(CaptureError::AccessLost { .. }) => counters.access_lost += 1,
+            Err(CaptureError::SessionDisconnected { .. }) => {
+                counters.disconnected += 1;
+                thread::sleep(RETRY_DELAY);
+            }
+            Err(e) => {
+                counters.errors += 1;
+                eprintln!("WARN  falha na captura: {}", e);
+            }
+        }
+        // A VRAM só pode ser lida com uma duplicação ativa
+        if let Ok(status) = current.video_memory() {
+            video_memory = status.local.usage + status.non_local.usage;
+        }
+        if let Some(remaining) = current.frame_interval().checked_sub(tick.elapsed()) {
+            thread::sleep(remaining);
+        }
+
+        if options.recreate_every != 0 && attempts % options.recreate_every == 0 {
+            drop(capture.take());
+            capture = create(options);
+            counters.recreated += 1;
+        }
+
+        if Instant::now() >= next_sample {
+            next_sample += options.interval;
+            let usage = match ResourceUsage::sample() {
+                Ok(usage) => usage,
+                Err(e) => {
+                    eprintln!("WARN  falha ao amostrar os recursos do processo: {}", e);
+                    continue;
+                }
+            };
+            // A primeira amostra, com os caches já aquecidos, é a linha de base
+            let Some(reference) = baseline.as_ref() else {
+                baseline = Some((usage, video_memory));
+                println!("linha de base: {:?}, vram {} MiB", usage, video_memory >> 20);
+                continue;
+            };
+            let exceeded = check_sample(start.elapsed(), &usage, video_memory, reference, &counters);
+            if !exceeded.is_empty() {
+                eprintln!("WARN  possível vazamento: {}", exceeded.join(", "));
+                leak_suspected = true;
+            }
+        }
+    }
+
+    drop(capture);
+    match report_live_objects() {
+        Ok(objects) if objects.is_empty() => println!("nenhum objeto DXGI/D3D11 vivo reportado"),
+        Ok(objects) => {
+            println!("{} objetos DXGI/D3D11 vivos após descartar a captura:", objects.len());
+            for object in &objects {
+                println!("  {}", object);
+            }
+            leak_suspected = true;
+        }
+        Err(e) => println!("relatório de objetos vivos indisponível (camada de depuração ausente): {}", e),
+    }
+    !leak_suspected
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("dxgigrab-soak: {}", e);
+            eprintln!("uso: dxgigrab-soak [--hours <h>] [--interval <s>] [--recreate-every <quadros>] [--output <adaptador>:<monitor>]");
+            return ExitCode::from(2);
+        }
+    };
+
+    static LOGGER: StderrLogger = StderrLogger;
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(Level::Info.to_level_filter());
+    }
+    unsafe {
+        if let Err(e) = SetConsoleCtrlHandler(Some(on_console_event), true) {
+            eprintln!("dxgigrab-soak: {}", e);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    if run(&options) {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
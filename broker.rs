@@ -0,0 +1,518 @@
+//! Coordenação entre processos da captura de um monitor (feature `broker`)
+//!
+//! Cada monitor aceita poucas duplicações simultâneas (`CaptureError::DuplicationLimitReached`),
+//! e componentes de um mesmo aplicativo em processos separados (pré-visualização, gravador,
+//! OCR) esgotam esse limite rapidamente. Com `SharedCapture`, o primeiro processo a tomar o
+//! mutex nomeado do monitor (`OutputLock`) cria a duplicação e publica cada quadro em memória
+//! compartilhada (`FramePublisher`); os demais apenas leem o quadro mais recente dali
+//! (`FrameSubscriber`), sem duplicação própria. Se o dono encerrar ou travar, o mutex é
+//! liberado pelo sistema e o próximo assinante que ficar sem quadros assume a captura.
+//!
+//! Os objetos ficam no namespace `Local\` e só são vistos por processos da mesma sessão. O
+//! mutex pertence à thread que o adquiriu, por isso `OutputLock` e `SharedCapture` não são
+//! `Send`: use-os na thread que os criou.
+//!
+//! A publicação usa um contador de geração (ímpar durante a escrita) no cabeçalho da seção: o
+//! leitor descarta cópias feitas enquanto o dono escrevia e tenta de novo, sem travas entre
+//! processos no caminho de cada quadro.
+
+use std::marker::PhantomData;
+use std::ptr::{self, addr_of, addr_of_mut};
+use std::sync::atomic::{fence, AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use windows::core::HSTRING;
+use windows::Win32::Foundation::{
+    CloseHandle, GetLastError, ERROR_ALREADY_EXISTS, HANDLE, INVALID_HANDLE_VALUE, WAIT_ABANDONED, WAIT_OBJECT_0,
+    WAIT_TIMEOUT,
+};
+use windows::Win32::Graphics::Dxgi::Common::{DXGI_COLOR_SPACE_TYPE, DXGI_FORMAT};
+use windows::Win32::System::Memory::{
+    CreateFileMappingW, MapViewOfFile, OpenFileMappingW, UnmapViewOfFile, FILE_MAP_ALL_ACCESS, FILE_MAP_READ,
+    MEMORY_MAPPED_VIEW_ADDRESS, PAGE_READWRITE,
+};
+use windows::Win32::System::Threading::{
+    CreateEventW, CreateMutexW, OpenEventW, ReleaseMutex, ResetEvent, SetEvent, WaitForSingleObject,
+    SYNCHRONIZATION_SYNCHRONIZE,
+};
+
+use crate::config::CaptureBuilder;
+use crate::error::{CaptureError, CaptureResult};
+use crate::frame::{Frame, FrameMetadata};
+use crate::geometry::{Point, Rect};
+use crate::outputs::ColorSpace;
+use crate::DxgiCapture;
+
+/// Identifica a seção como nossa ("DXGB") e a versão do layout
+const SECTION_MAGIC: u32 = 0x4247_5844;
+const SECTION_VERSION: u32 = 1;
+/// Os pixels começam alinhados a uma linha de cache depois do cabeçalho
+const DATA_OFFSET: usize = (std::mem::size_of::<SectionHeader>() + 63) & !63;
+/// Bytes por pixel reservados na seção: cobre FP16, o maior formato capturado
+const RESERVED_BYTES_PER_PIXEL: usize = 8;
+/// Tentativas de cópia quando o dono escreve ao mesmo tempo
+const READ_ATTEMPTS: u32 = 8;
+/// Quanto `SharedCapture::open` espera o dono criar a seção antes de desistir
+const OPEN_TIMEOUT: Duration = Duration::from_secs(5);
+const OPEN_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Nome de um objeto do monitor no namespace da sessão
+fn object_name(adapter: u32, output: u32, kind: &str) -> HSTRING {
+    HSTRING::from(format!("Local\\dxgi_capture.{}.{}.{}", adapter, output, kind))
+}
+
+fn wait_millis(timeout: Duration) -> u32 {
+    timeout.as_millis().min(u32::MAX as u128 - 1) as u32
+}
+
+/// Mutex nomeado que elege o único processo que duplica um monitor.
+///
+/// Liberado no `Drop` ou, se o processo terminar sem liberá-lo, pelo sistema.
+#[derive(Debug)]
+pub struct OutputLock {
+    handle: HANDLE,
+    // O mutex pertence à thread que o adquiriu
+    _not_send: PhantomData<*const ()>,
+}
+
+impl OutputLock {
+    /// Tenta adquirir o monitor sem esperar; `None` se outro processo já o tem
+    pub fn try_acquire(adapter: u32, output: u32) -> CaptureResult<Option<Self>> {
+        Self::acquire(adapter, output, Duration::ZERO)
+    }
+
+    /// Espera até `timeout` pelo monitor; `None` se outro processo continuou com ele
+    pub fn acquire(adapter: u32, output: u32, timeout: Duration) -> CaptureResult<Option<Self>> {
+        let handle = unsafe { CreateMutexW(None, false, &object_name(adapter, output, "lock"))? };
+        let result = unsafe { WaitForSingleObject(handle, wait_millis(timeout)) };
+        if result == WAIT_OBJECT_0 || result == WAIT_ABANDONED {
+            if result == WAIT_ABANDONED {
+                warn!("o dono anterior da captura do monitor {}:{} terminou sem liberá-la", adapter, output);
+            }
+            return Ok(Some(Self {
+                handle,
+                _not_send: PhantomData,
+            }));
+        }
+        unsafe {
+            let _ = CloseHandle(handle);
+        }
+        if result == WAIT_TIMEOUT {
+            Ok(None)
+        } else {
+            Err(CaptureError::Windows(windows::core::Error::from_win32()))
+        }
+    }
+}
+
+impl Drop for OutputLock {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = ReleaseMutex(self.handle);
+            let _ = CloseHandle(self.handle);
+        }
+    }
+}
+
+/// Início da seção compartilhada; os pixels vêm em `DATA_OFFSET`
+#[repr(C)]
+struct SectionHeader {
+    magic: u32,
+    version: u32,
+    /// Bytes disponíveis para os pixels
+    capacity: u64,
+    /// Ímpar enquanto o dono escreve; muda a cada quadro publicado
+    generation: AtomicU64,
+    frame: FrameHeader,
+}
+
+/// Campos de `Frame` além dos pixels, em layout fixo
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct FrameHeader {
+    left: i32,
+    top: i32,
+    width: u32,
+    height: u32,
+    format: u32,
+    color_space: i32,
+    present_time: i64,
+    sequence: u64,
+    data_len: u64,
+    acquire_time: i64,
+    /// -1 quando a duplicação não informou
+    dirty_rects: i64,
+    move_rects: i64,
+    cursor_visible: u32,
+    cursor_x: i32,
+    cursor_y: i32,
+    accumulated_frames: u32,
+}
+
+impl FrameHeader {
+    fn from_frame(frame: &Frame) -> Self {
+        let cursor = frame.metadata.cursor;
+        Self {
+            left: frame.region.left,
+            top: frame.region.top,
+            width: frame.region.width,
+            height: frame.region.height,
+            format: frame.format.0,
+            color_space: frame.color_space.to_dxgi().0,
+            present_time: frame.present_time,
+            sequence: frame.sequence,
+            data_len: frame.data.len() as u64,
+            acquire_time: frame.metadata.acquire_time,
+            dirty_rects: frame.metadata.dirty_rects.map_or(-1, i64::from),
+            move_rects: frame.metadata.move_rects.map_or(-1, i64::from),
+            cursor_visible: cursor.is_some() as u32,
+            cursor_x: cursor.map_or(0, |point| point.x),
+            cursor_y: cursor.map_or(0, |point| point.y),
+            accumulated_frames: frame.metadata.accumulated_frames,
+        }
+    }
+
+    fn into_frame(self, data: Vec<u8>) -> Frame {
+        let count = |value: i64| u32::try_from(value).ok();
+        Frame {
+            data,
+            region: Rect::new(self.left, self.top, self.width, self.height),
+            format: DXGI_FORMAT(self.format),
+            color_space: ColorSpace::from_dxgi(DXGI_COLOR_SPACE_TYPE(self.color_space)),
+            present_time: self.present_time,
+            sequence: self.sequence,
+            metadata: FrameMetadata {
+                acquire_time: self.acquire_time,
+                dirty_rects: count(self.dirty_rects),
+                move_rects: count(self.move_rects),
+                cursor: (self.cursor_visible != 0).then(|| Point::new(self.cursor_x, self.cursor_y)),
+                accumulated_frames: self.accumulated_frames,
+            },
+        }
+    }
+}
+
+/// Seção mapeada e o evento de quadro novo, comuns ao dono e aos assinantes
+struct Section {
+    mapping: HANDLE,
+    view: MEMORY_MAPPED_VIEW_ADDRESS,
+    event: HANDLE,
+}
+
+impl Section {
+    fn header(&self) -> *mut SectionHeader {
+        self.view.Value as *mut SectionHeader
+    }
+
+    fn data(&self) -> *mut u8 {
+        unsafe { (self.view.Value as *mut u8).add(DATA_OFFSET) }
+    }
+
+    fn generation(&self) -> &AtomicU64 {
+        unsafe { &*addr_of!((*self.header()).generation) }
+    }
+
+    fn capacity(&self) -> usize {
+        unsafe { ptr::read_volatile(addr_of!((*self.header()).capacity)) as usize }
+    }
+}
+
+impl Drop for Section {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = UnmapViewOfFile(self.view);
+            let _ = CloseHandle(self.mapping);
+            let _ = CloseHandle(self.event);
+        }
+    }
+}
+
+/// Lado do dono: publica os quadros da sua duplicação para os outros processos
+pub struct FramePublisher {
+    section: Section,
+}
+
+impl FramePublisher {
+    /// Cria (ou reaproveita, se assinantes a mantêm aberta) a seção do monitor com espaço para
+    /// `capacity` bytes de pixels
+    pub fn create(adapter: u32, output: u32, capacity: usize) -> CaptureResult<Self> {
+        let size = (DATA_OFFSET + capacity) as u64;
+        let name = object_name(adapter, output, "frames");
+        let mapping = unsafe {
+            CreateFileMappingW(INVALID_HANDLE_VALUE, None, PAGE_READWRITE, (size >> 32) as u32, size as u32, &name)?
+        };
+        let existed = matches!(unsafe { GetLastError() }, Err(err) if err.code() == ERROR_ALREADY_EXISTS.to_hresult());
+        let view = unsafe { MapViewOfFile(mapping, FILE_MAP_ALL_ACCESS, 0, 0, 0) };
+        if view.Value.is_null() {
+            let error = windows::core::Error::from_win32();
+            unsafe {
+                let _ = CloseHandle(mapping);
+            }
+            return Err(CaptureError::Windows(error));
+        }
+        let event = match unsafe { CreateEventW(None, true, false, &object_name(adapter, output, "ready")) } {
+            Ok(event) => event,
+            Err(e) => {
+                unsafe {
+                    let _ = UnmapViewOfFile(view);
+                    let _ = CloseHandle(mapping);
+                }
+                return Err(e.into());
+            }
+        };
+        let section = Section { mapping, view, event };
+
+        let header = section.header();
+        let valid = existed
+            && unsafe { ptr::read_volatile(addr_of!((*header).magic)) } == SECTION_MAGIC
+            && unsafe { ptr::read_volatile(addr_of!((*header).version)) } == SECTION_VERSION;
+        if valid {
+            // Seção do dono anterior: o tamanho é o dela, e uma escrita interrompida fica par
+            let generation = section.generation();
+            if generation.load(Ordering::Acquire) % 2 == 1 {
+                generation.fetch_add(1, Ordering::AcqRel);
+            }
+        } else {
+            unsafe {
+                ptr::write_volatile(addr_of_mut!((*header).capacity), capacity as u64);
+                section.generation().store(0, Ordering::Release);
+                ptr::write_volatile(addr_of_mut!((*header).version), SECTION_VERSION);
+                ptr::write_volatile(addr_of_mut!((*header).magic), SECTION_MAGIC);
+            }
+        }
+        Ok(Self { section })
+    }
+
+    /// Bytes de pixels que cabem em um quadro publicado
+    pub fn capacity(&self) -> usize {
+        self.section.capacity()
+    }
+
+    /// Publica `frame` como o quadro mais recente; `false` se ele não cabe na seção (o monitor
+    /// cresceu depois da criação)
+    pub fn publish(&mut self, frame: &Frame) -> CaptureResult<bool> {
+        if frame.data.len() > self.capacity() {
+            return Ok(false);
+        }
+        let header = self.section.header();
+        let generation = self.section.generation();
+        unsafe {
+            ResetEvent(self.section.event)?;
+            generation.fetch_add(1, Ordering::AcqRel);
+            fence(Ordering::Release);
+            ptr::write_volatile(addr_of_mut!((*header).frame), FrameHeader::from_frame(frame));
+            ptr::copy_nonoverlapping(frame.data.as_ptr(), self.section.data(), frame.data.len());
+            generation.fetch_add(1, Ordering::Release);
+            SetEvent(self.section.event)?;
+        }
+        Ok(true)
+    }
+}
+
+/// Lado do assinante: lê os quadros publicados pelo dono do monitor
+pub struct FrameSubscriber {
+    section: Section,
+    last_generation: u64,
+}
+
+impl FrameSubscriber {
+    /// Abre a seção do monitor; `None` se nenhum processo a publicou ainda
+    pub fn open(adapter: u32, output: u32) -> CaptureResult<Option<Self>> {
+        let mapping = match unsafe { OpenFileMappingW(FILE_MAP_READ.0, false, &object_name(adapter, output, "frames")) } {
+            Ok(mapping) => mapping,
+            Err(_) => return Ok(None),
+        };
+        let view = unsafe { MapViewOfFile(mapping, FILE_MAP_READ, 0, 0, 0) };
+        if view.Value.is_null() {
+            let error = windows::core::Error::from_win32();
+            unsafe {
+                let _ = CloseHandle(mapping);
+            }
+            return Err(CaptureError::Windows(error));
+        }
+        let event = match unsafe { OpenEventW(SYNCHRONIZATION_SYNCHRONIZE, false, &object_name(adapter, output, "ready")) } {
+            Ok(event) => event,
+            Err(_) => {
+                unsafe {
+                    let _ = UnmapViewOfFile(view);
+                    let _ = CloseHandle(mapping);
+                }
+                return Ok(None);
+            }
+        };
+        let section = Section { mapping, view, event };
+        // Seção criada mas ainda não inicializada pelo dono
+        if unsafe { ptr::read_volatile(addr_of!((*section.header()).magic)) } != SECTION_MAGIC {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            section,
+            last_generation: 0,
+        }))
+    }
+
+    /// O quadro mais recente, se foi publicado depois do último lido
+    pub fn try_recv(&mut self) -> Option<Frame> {
+        let header = self.section.header();
+        let generation = self.section.generation();
+        for _ in 0..READ_ATTEMPTS {
+            let before = generation.load(Ordering::Acquire);
+            if before == self.last_generation {
+                return None;
+            }
+            if before % 2 == 1 {
+                thread::yield_now();
+                continue;
+            }
+            let frame = unsafe { ptr::read_volatile(addr_of!((*header).frame)) };
+            let length = (frame.data_len as usize).min(self.section.capacity());
+            let mut data = vec![0u8; length];
+            unsafe { ptr::copy_nonoverlapping(self.section.data(), data.as_mut_ptr(), length) };
+            fence(Ordering::Acquire);
+            if generation.load(Ordering::Acquire) == before {
+                self.last_generation = before;
+                return Some(frame.into_frame(data));
+            }
+        }
+        None
+    }
+
+    /// Espera até `timeout` por um quadro publicado depois do último lido
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Option<Frame> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(frame) = self.try_recv() {
+                return Some(frame);
+            }
+            let remaining = deadline.checked_duration_since(Instant::now())?;
+            if remaining.is_zero() {
+                return None;
+            }
+            // O evento só acorda o leitor; a geração decide se há quadro novo. Ele fica sinalizado
+            // entre publicações, então uma espera que retorna sem quadro novo dorme um pouco
+            if unsafe { WaitForSingleObject(self.section.event, wait_millis(remaining)) } == WAIT_OBJECT_0
+                && self.section.generation().load(Ordering::Acquire) == self.last_generation
+            {
+                thread::sleep(remaining.min(Duration::from_millis(1)));
+            }
+        }
+    }
+}
+
+/// Papel do processo em uma `SharedCapture`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum BrokerRole {
+    /// Este processo duplica o monitor e publica os quadros
+    Owner,
+    /// Este processo lê os quadros publicados por outro
+    Subscriber,
+}
+
+enum Role {
+    Owner {
+        capture: DxgiCapture,
+        publisher: FramePublisher,
+        // Descartado por último: os assinantes só assumem depois que a duplicação foi liberada
+        _lock: OutputLock,
+    },
+    Subscriber(FrameSubscriber),
+}
+
+/// Captura de um monitor compartilhada entre processos, obtida com
+/// `CaptureBuilder::build_shared`
+pub struct SharedCapture {
+    adapter: u32,
+    output: u32,
+    builder: CaptureBuilder,
+    role: Role,
+}
+
+impl SharedCapture {
+    /// Assume a captura do monitor se nenhum processo a tem, ou assina a do dono atual.
+    /// `builder` cria a duplicação quando este processo é (ou passa a ser) o dono.
+    pub fn open(adapter: u32, output: u32, builder: CaptureBuilder) -> CaptureResult<Self> {
+        let deadline = Instant::now() + OPEN_TIMEOUT;
+        loop {
+            if let Some(lock) = OutputLock::try_acquire(adapter, output)? {
+                let role = Self::become_owner(adapter, output, &builder, lock)?;
+                return Ok(Self { adapter, output, builder, role });
+            }
+            // O dono pode ainda estar criando a seção
+            if let Some(subscriber) = FrameSubscriber::open(adapter, output)? {
+                return Ok(Self {
+                    adapter,
+                    output,
+                    builder,
+                    role: Role::Subscriber(subscriber),
+                });
+            }
+            if Instant::now() >= deadline {
+                return Err(CaptureError::NotInitialized);
+            }
+            thread::sleep(OPEN_POLL_INTERVAL);
+        }
+    }
+
+    fn become_owner(adapter: u32, output: u32, builder: &CaptureBuilder, lock: OutputLock) -> CaptureResult<Role> {
+        let capture = builder.clone().build()?;
+        let size = capture.output_size();
+        let capacity = size.width as usize * size.height as usize * RESERVED_BYTES_PER_PIXEL;
+        let publisher = FramePublisher::create(adapter, output, capacity)?;
+        info!("captura do monitor {}:{} compartilhada por este processo", adapter, output);
+        Ok(Role::Owner {
+            capture,
+            publisher,
+            _lock: lock,
+        })
+    }
+
+    pub fn role(&self) -> BrokerRole {
+        match self.role {
+            Role::Owner { .. } => BrokerRole::Owner,
+            Role::Subscriber(_) => BrokerRole::Subscriber,
+        }
+    }
+
+    /// Próximo quadro do monitor inteiro.
+    ///
+    /// Como dono, captura e publica o quadro (os assinantes só recebem quadros enquanto o dono
+    /// chama `capture`). Como assinante, espera até `timeout` por um quadro publicado; sem
+    /// nenhum, tenta assumir a captura caso o dono tenha encerrado, e retorna
+    /// `CaptureError::NoNewFrame` se ele continua ativo.
+    pub fn capture(&mut self, timeout: Duration) -> CaptureResult<Frame> {
+        match &mut self.role {
+            Role::Owner { capture, publisher, .. } => {
+                let frame = capture.capture_frame(capture.bounds())?;
+                if !publisher.publish(&frame)? {
+                    warn!(
+                        "quadro de {} bytes não cabe na área compartilhada ({} bytes); reabra a captura compartilhada",
+                        frame.data.len(),
+                        publisher.capacity()
+                    );
+                }
+                Ok(frame)
+            }
+            Role::Subscriber(subscriber) => {
+                if let Some(frame) = subscriber.recv_timeout(timeout) {
+                    return Ok(frame);
+                }
+                if let Some(lock) = OutputLock::try_acquire(self.adapter, self.output)? {
+                    self.role = Self::become_owner(self.adapter, self.output, &self.builder, lock)?;
+                    return self.capture(timeout);
+                }
+                Err(CaptureError::NoNewFrame)
+            }
+        }
+    }
+
+    /// A duplicação deste processo, quando ele é o dono
+    pub fn owned_capture(&mut self) -> Option<&mut DxgiCapture> {
+        match &mut self.role {
+            Role::Owner { capture, .. } => Some(capture),
+            Role::Subscriber(_) => None,
+        }
+    }
+}
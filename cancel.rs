@@ -0,0 +1,116 @@
+//! Cancelamento cooperativo de esperas e da thread de captura
+//!
+//! Um `CancellationToken` é compartilhado (clonado) entre quem pede o encerramento e quem
+//! espera. As esperas internas do capturador usam `wait_timeout` em vez de `thread::sleep`,
+//! então o cancelamento as interrompe na hora, sem esperar o intervalo terminar.
+
+use std::fmt;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use crate::error::{CaptureError, CaptureResult};
+
+type Callback = Box<dyn FnOnce() + Send>;
+
+#[derive(Default)]
+struct State {
+    cancelled: bool,
+    // Chamados uma vez no cancelamento, para acordar esperas que não olham o token
+    callbacks: Vec<Callback>,
+}
+
+#[derive(Default)]
+struct Inner {
+    state: Mutex<State>,
+    cancelled: Condvar,
+}
+
+/// Sinal de cancelamento compartilhado; clones observam o mesmo estado
+#[derive(Clone, Default)]
+pub struct CancellationToken {
+    inner: Arc<Inner>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancela o token e acorda todas as esperas; chamadas repetidas não têm efeito
+    pub fn cancel(&self) {
+        let callbacks = {
+            let mut state = self.lock();
+            if state.cancelled {
+                return;
+            }
+            state.cancelled = true;
+            std::mem::take(&mut state.callbacks)
+        };
+        self.inner.cancelled.notify_all();
+
+        for callback in callbacks {
+            callback();
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.lock().cancelled
+    }
+
+    /// Espera até `timeout` ou até o cancelamento; retorna `true` se foi cancelado
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.lock();
+        while !state.cancelled {
+            let Some(remaining) = deadline.checked_duration_since(Instant::now()) else {
+                break;
+            };
+            state = self
+                .inner
+                .cancelled
+                .wait_timeout(state, remaining)
+                .map(|(state, _)| state)
+                .unwrap_or_else(|e| e.into_inner().0);
+        }
+        state.cancelled
+    }
+
+    /// Espera sem prazo até o cancelamento
+    pub fn wait(&self) {
+        let mut state = self.lock();
+        while !state.cancelled {
+            state = self.inner.cancelled.wait(state).unwrap_or_else(|e| e.into_inner());
+        }
+    }
+
+    /// `CaptureError::Cancelled` se o token já foi cancelado
+    pub fn check(&self) -> CaptureResult<()> {
+        if self.is_cancelled() {
+            Err(CaptureError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Executa `callback` no cancelamento (ou agora, se já foi cancelado)
+    pub(crate) fn on_cancel(&self, callback: impl FnOnce() + Send + 'static) {
+        {
+            let mut state = self.lock();
+            if !state.cancelled {
+                state.callbacks.push(Box::new(callback));
+                return;
+            }
+        }
+        callback();
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.inner.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl fmt::Debug for CancellationToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CancellationToken").field("cancelled", &self.is_cancelled()).finish()
+    }
+}
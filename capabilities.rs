@@ -0,0 +1,132 @@
+//! Detecção da versão do Windows e dos recursos de captura disponíveis
+
+use windows::core::*;
+use windows::Win32::Graphics::Dxgi::Common::DXGI_COLOR_SPACE_RGB_FULL_G2084_NONE_P2020;
+use windows::Win32::Graphics::Dxgi::*;
+use windows::Win32::System::SystemInformation::OSVERSIONINFOW;
+
+use crate::error::CaptureResult;
+
+/// Build do Windows 10 1703, que introduziu `IDXGIOutput5::DuplicateOutput1` e `IDXGIOutput6`
+pub const BUILD_DUPLICATE_OUTPUT1: u32 = 15063;
+/// Build do Windows 10 1803, a partir da qual `Windows.Graphics.Capture` captura monitores
+pub const BUILD_GRAPHICS_CAPTURE: u32 = 17134;
+/// Limite de aplicativos duplicando o mesmo output ao mesmo tempo, imposto pelo DXGI
+pub const MAX_DUPLICATIONS_PER_OUTPUT: u32 = 4;
+
+#[link(name = "ntdll")]
+extern "system" {
+    // `GetVersionExW` mente para processos sem manifesto; `RtlGetVersion` não
+    fn RtlGetVersion(info: *mut OSVERSIONINFOW) -> i32;
+}
+
+/// Versão do Windows em execução
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OsVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub build: u32,
+}
+
+impl OsVersion {
+    /// Versão do sistema atual
+    pub fn current() -> Self {
+        let mut info = OSVERSIONINFOW {
+            dwOSVersionInfoSize: std::mem::size_of::<OSVERSIONINFOW>() as u32,
+            ..Default::default()
+        };
+
+        unsafe {
+            RtlGetVersion(&mut info);
+        }
+
+        Self {
+            major: info.dwMajorVersion,
+            minor: info.dwMinorVersion,
+            build: info.dwBuildNumber,
+        }
+    }
+
+    /// Windows 10 ou 11 (ambos reportam 10.0) com build pelo menos `build`
+    pub fn is_win10_build(&self, build: u32) -> bool {
+        self.major >= 10 && self.build >= build
+    }
+}
+
+/// API usada para criar a duplicação
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DuplicationApi {
+    /// `IDXGIOutput5::DuplicateOutput1`, com negociação de formato (BGRA, RGBA, FP16)
+    DuplicateOutput1,
+    /// `IDXGIOutput1::DuplicateOutput` (Windows 8 a 10 1607), sempre BGRA
+    DuplicateOutput,
+}
+
+/// Recursos de captura disponíveis neste sistema
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Capabilities {
+    pub os_version: OsVersion,
+    /// API de duplicação que será usada
+    pub duplication_api: DuplicationApi,
+    /// Captura em FP16 (`DXGI_FORMAT_R16G16B16A16_FLOAT`) possível
+    pub fp16_capture: bool,
+    /// Algum monitor está com HDR ativo
+    pub hdr_active: bool,
+    /// `Windows.Graphics.Capture` disponível para captura de monitores
+    pub graphics_capture: bool,
+    /// `WDA_EXCLUDEFROMCAPTURE` disponível (ver `window_exclusion`)
+    pub exclude_from_capture: bool,
+    /// Duplicações simultâneas permitidas por monitor (somando todos os aplicativos)
+    pub max_duplications_per_output: u32,
+}
+
+/// Verifica o que este sistema suporta, consultando a versão e os monitores presentes
+pub fn capabilities() -> CaptureResult<Capabilities> {
+    let os_version = OsVersion::current();
+    let factory: IDXGIFactory1 = unsafe { CreateDXGIFactory1()? };
+
+    let mut duplicate_output1 = false;
+    let mut hdr_active = false;
+    let mut any_output = false;
+
+    let mut adapter_index = 0;
+    while let Ok(adapter) = unsafe { factory.EnumAdapters1(adapter_index) } {
+        let mut output_index = 0;
+        while let Ok(output) = unsafe { adapter.EnumOutputs(output_index) } {
+            any_output = true;
+            duplicate_output1 |= output.cast::<IDXGIOutput5>().is_ok();
+
+            if let Ok(output6) = output.cast::<IDXGIOutput6>() {
+                let mut desc = DXGI_OUTPUT_DESC1::default();
+                if unsafe { output6.GetDesc1(&mut desc) }.is_ok() {
+                    hdr_active |= desc.ColorSpace == DXGI_COLOR_SPACE_RGB_FULL_G2084_NONE_P2020;
+                }
+            }
+
+            output_index += 1;
+        }
+        adapter_index += 1;
+    }
+
+    // Sem monitores enumeráveis (ex.: sessão sem console), decidir só pela versão
+    if !any_output {
+        duplicate_output1 = os_version.is_win10_build(BUILD_DUPLICATE_OUTPUT1);
+    }
+
+    Ok(Capabilities {
+        os_version,
+        duplication_api: if duplicate_output1 {
+            DuplicationApi::DuplicateOutput1
+        } else {
+            DuplicationApi::DuplicateOutput
+        },
+        fp16_capture: duplicate_output1,
+        hdr_active,
+        graphics_capture: os_version.is_win10_build(BUILD_GRAPHICS_CAPTURE),
+        exclude_from_capture: os_version.is_win10_build(crate::window_exclusion::BUILD_EXCLUDE_FROM_CAPTURE),
+        max_duplications_per_output: MAX_DUPLICATIONS_PER_OUTPUT,
+    })
+}
@@ -1,90 +1,671 @@
 use std::error::Error;
-use std::ptr;
+use std::slice;
+use std::sync::mpsc::Receiver;
+use std::time::{Duration, Instant};
 use windows::core::*;
-use windows::Win32::Graphics::Direct3D::{D3D_DRIVER_TYPE_HARDWARE, D3D_FEATURE_LEVEL_11_0};
+use windows::Win32::Foundation::{E_FAIL, HWND, POINT, RECT};
+use windows::Win32::Graphics::Direct3D::{D3D_DRIVER_TYPE_UNKNOWN, D3D_FEATURE_LEVEL_11_0};
 use windows::Win32::Graphics::Direct3D11::D3D11_SDK_VERSION;
 use windows::Win32::Graphics::Direct3D11::*;
 use windows::Win32::Graphics::Dxgi::Common::*;
 use windows::Win32::Graphics::Dxgi::*;
+use windows::Win32::Graphics::Gdi::HMONITOR;
+use windows::Win32::System::Performance::QueryPerformanceCounter;
+use windows::Win32::UI::WindowsAndMessaging::GetPhysicalCursorPos;
+
+#[macro_use]
+mod diagnostics;
+
+pub mod annotate;
+pub mod auto_roi;
+pub mod bandwidth;
+pub mod cancel;
+pub mod capabilities;
+pub mod change_alert;
+pub mod com;
+pub mod compare;
+pub mod config;
+pub mod convert;
+pub mod coords;
+pub mod cursor;
+pub mod error;
+pub mod events;
+pub mod frame;
+pub mod frame_log;
+pub mod fullscreen;
+pub mod geometry;
+pub mod gpu_stats;
+pub mod hdr_metadata;
+pub mod monitor_identity;
+pub mod occlusion;
+pub mod outputs;
+pub mod overlay;
+pub mod pixel_format;
+pub mod pixel_watch;
+pub mod priority;
+pub mod probe;
+pub mod regions;
+pub mod scale;
+pub mod scroll_capture;
+pub mod session;
+pub mod sink;
+pub mod snapshot;
+pub mod source;
+pub mod stitch;
+pub mod stream;
+pub mod template_match;
+pub mod test_pattern;
+pub mod timecode;
+pub mod video_memory;
+pub mod video_processor;
+pub mod window_exclusion;
+
+#[cfg(feature = "serde")]
+mod serde_support;
+
+#[cfg(feature = "broker")]
+pub mod broker;
+#[cfg(feature = "recording")]
+pub mod cfr;
+#[cfg(feature = "input")]
+pub mod click_highlight;
+#[cfg(feature = "consent")]
+pub mod consent;
+#[cfg(feature = "d3d12")]
+pub mod d3d12;
+#[cfg(feature = "daemon")]
+pub mod daemon;
+#[cfg(feature = "encryption")]
+pub mod encryption;
+#[cfg(feature = "fault-injection")]
+pub mod fault_injection;
+#[cfg(feature = "ffmpeg")]
+pub mod ffmpeg_encoder;
+#[cfg(feature = "gpu-jpeg")]
+pub mod gpu_jpeg;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+#[cfg(feature = "hw-encoder")]
+pub mod hw_encoder;
+#[cfg(feature = "icc")]
+pub mod icc;
+#[cfg(feature = "input")]
+pub mod input;
+#[cfg(feature = "jpeg")]
+pub mod jpeg_encoder;
+#[cfg(feature = "soak")]
+pub mod leak_check;
+#[cfg(feature = "recording")]
+pub mod nut;
+#[cfg(feature = "onnx")]
+pub mod onnx_tensor;
+#[cfg(feature = "streaming")]
+pub mod pipe;
+#[cfg(feature = "png")]
+pub mod png_encoder;
+#[cfg(feature = "recording")]
+pub mod raw_sequence;
+#[cfg(feature = "recording")]
+pub mod recording;
+#[cfg(feature = "recording")]
+pub mod replay;
+#[cfg(feature = "streaming")]
+pub mod rtp;
+#[cfg(any(feature = "png", feature = "jpeg"))]
+pub mod screenshot_writer;
+#[cfg(feature = "uia")]
+pub mod uia;
+#[cfg(feature = "uitest")]
+pub mod uitest;
+#[cfg(feature = "softcam")]
+pub mod virtual_camera;
+#[cfg(feature = "webrtc")]
+pub mod webrtc_source;
+#[cfg(feature = "winrt")]
+pub mod winrt_interop;
+#[cfg(feature = "recording")]
+pub mod y4m;
 
 pub type Result<T> = std::result::Result<T, Box<dyn Error>>;
 
+pub use auto_roi::{AutoRoi, AutoRoiOptions};
+pub use bandwidth::{BandwidthController, BandwidthOptions, BandwidthSettings, BandwidthStats};
+pub use cancel::CancellationToken;
+pub use capabilities::{capabilities, Capabilities, DuplicationApi};
+#[cfg(feature = "recording")]
+pub use cfr::{CfrConformer, CfrFrame, CfrStats};
+pub use change_alert::{ChangeEvent, ChangeOptions, ChangeSink};
+pub use com::{ensure_com_initialized, ComApartment, ComGuard};
+pub use config::{CaptureBuilder, CaptureConfig, NoFramePolicy};
+pub use cursor::{CursorShape, CursorShapeKind, CursorUpdate};
+pub use error::{CaptureError, CaptureResult};
+pub use events::CaptureEvent;
+#[cfg(feature = "fault-injection")]
+pub use fault_injection::{FaultInjector, FaultPlan, FaultySource, InjectedFault};
+pub use frame::{Frame, FrameMetadata, FrameView, MipLevel};
+pub use frame_log::FrameLog;
+pub use fullscreen::{FullscreenApp, FullscreenState};
+pub use geometry::{Anchor, Point, Rect, RectError, RegionSpec, Size};
+pub use gpu_stats::{ColorRange, Histogram};
+pub use hdr_metadata::HdrMetadata;
+pub use monitor_identity::{monitor_identities, MonitorIdentity};
+pub use occlusion::{Occluder, Occlusion, OcclusionMonitor};
+#[cfg(feature = "onnx")]
+pub use onnx_tensor::{ChannelOrder, Normalization, OnnxTensor, TensorLayout};
+pub use outputs::{Cicp, ColorSpace, DuplicationInfo, OutputColorInfo, Rotation};
+pub use overlay::{OverlayCorner, OverlayOptions, OverlayToggle, StatsOverlay};
+pub use pixel_format::PixelFormat;
+pub use pixel_watch::{PixelCondition, PixelEvent, PixelWatcher, WatchId};
+pub use priority::{MmcssPriority, MmcssTask, RealtimePriority};
+pub use probe::{probe, CapabilityReport, OutputProbe, ProbeFailure};
+pub use regions::RegionRegistry;
+pub use scale::ScaleFilter;
+pub use session::{CompanionProcess, SessionInfo, SessionState};
+pub use sink::{FanOut, FrameSink, SinkStats};
+pub use snapshot::{snapshot_all, SnapshotOptions};
+pub use source::{Backend, ScreenSource};
+pub use stream::{CaptureStream, QueuePolicy, StreamOptions, StreamStats};
+pub use template_match::{find_in_frame, ImageMatch};
+pub use timecode::Timecode;
+pub use video_memory::{BudgetNotification, VideoMemoryInfo, VideoMemoryStatus};
+pub use video_processor::{OutputFit, OutputSpec};
+use events::EventBus;
+use gpu_stats::{ColorRangePass, HistogramPass};
+#[cfg(feature = "onnx")]
+use onnx_tensor::TensorPass;
+use scale::ScalePass;
+use template_match::{Gray, Needle, TemplatePass, GPU_MAX_TEMPLATE_PIXELS};
+use video_processor::VideoConverter;
+
+/// Espera máxima pelo primeiro quadro da duplicação recriada após uma perda de acesso
+const REINIT_RETRY_TIMEOUT_MS: u32 = 100;
+/// Intervalo entre tentativas de criar a duplicação enquanto o monitor estiver ocupado
+const DUPLICATION_WAIT_INTERVAL: Duration = Duration::from_millis(250);
+/// Taxa assumida quando o driver não informa a taxa de atualização do monitor
+const FALLBACK_REFRESH_HZ: f64 = 60.0;
+/// Texturas de saída do video processor de `capture_converted_texture`
+const CONVERTER_POOL_SIZE: usize = 2;
+/// Video processors mantidos ao mesmo tempo, um por tamanho e formato de saída
+const CONVERTER_CACHE_SIZE: usize = 4;
+/// Texturas de staging de ROI mantidas ao mesmo tempo, uma por tamanho e formato
+const ROI_CACHE_SIZE: usize = 4;
+
+/// Recursos de uma duplicação ativa, criados e descartados sempre juntos
+struct Resources {
+    device: ID3D11Device,
+    context: ID3D11DeviceContext,
+    adapter: IDXGIAdapter1,
+    // Mantido vivo enquanto a duplicação existir
+    _output: IDXGIOutput,
+    duplication: IDXGIOutputDuplication,
+    // Mudanças do orçamento de memória de vídeo, com `trim_on_memory_pressure`
+    budget_events: Option<BudgetNotification>,
+    // Mensagens da camada de depuração, com `debug_layer`
+    info_queue: Option<ID3D11InfoQueue>,
+    // A duplicação entrega a imagem do desktop em memória do sistema (algumas GPUs integradas,
+    // RDP): `capture_region` a lê com `MapDesktopSurface`, sem cópia nem staging. Desligado se
+    // o mapeamento falhar
+    system_memory: bool,
+    // Última região lida por `MapDesktopSurface`, para `NoFramePolicy::LastFrame`
+    system_memory_copy: Option<(Rect, Vec<u8>)>,
+    
+    // Stagings das últimas ROIs usadas, uma por (largura, altura, formato); a menos usada
+    // recentemente é substituída quando todas estão ocupadas
+    roi_textures: Vec<Option<CachedTexture>>,
+    // Reutilizada entre capturas enquanto o tamanho da ROI não mudar
+    gpu_texture: Option<CachedTexture>,
+    
+    // Recorte + escala + conversão em uma passada, um video processor por saída (o menos usado
+    // recentemente é substituído), e uma staging por posição de saída para ler o resultado
+    converters: Vec<CachedConverter>,
+    converted_staging: Vec<Option<CachedTexture>>,
+    
+    // Cadeia de mips completa da ROI, sua view para `GenerateMips` e uma staging por nível lido
+    mip_texture: Option<CachedTexture>,
+    mip_view: Option<(ID3D11Texture2D, ID3D11ShaderResourceView)>,
+    mip_staging: Vec<Option<CachedTexture>>,
+    
+    // Regiões pequenas de `capture_patches`, lado a lado em uma única staging
+    patch_staging: Option<CachedTexture>,
+    
+    // Compute shaders de estatísticas, compilados no primeiro uso
+    histogram: Option<HistogramPass>,
+    color_range: Option<ColorRangePass>,
+    // Busca de imagens na GPU; `Some(None)` se o shader não pôde ser compilado
+    template: Option<Option<TemplatePass>>,
+    
+    // Escala com filtro escolhido: shaders, textura de saída (UAV) e staging para lê-la
+    scale: Option<ScalePass>,
+    scaled_texture: Option<CachedTexture>,
+    scaled_staging: Option<CachedTexture>,
+    
+    // Tensores de `capture_tensor`: shaders, parâmetros e buffer de saída
+    #[cfg(feature = "onnx")]
+    tensor: Option<TensorPass>,
+    
+    // Contexto diferido de `capture_regions`; `Some(None)` se o driver não o suporta
+    deferred: Option<Option<ID3D11DeviceContext>>,
+}
+
+/// Video processor de `capture_converted` e os parâmetros com que foi criado
+struct CachedConverter {
+    converter: VideoConverter,
+    input: Size,
+    output: Size,
+    format: DXGI_FORMAT,
+    // Última textura convertida, a região de origem e o ajuste, reaproveitada quando a tela não mudou
+    last_output: Option<(ID3D11Texture2D, Rect, OutputFit)>,
+    last_used: Instant,
+}
+
+/// Textura intermediária e o tamanho com que foi criada
+struct CachedTexture {
+    texture: ID3D11Texture2D,
+    width: u32,
+    height: u32,
+    format: DXGI_FORMAT,
+    // Memória aproximada ocupada na GPU, para o orçamento de `texture_budget_bytes`
+    bytes: u64,
+    last_used: Instant,
+    // Região do desktop copiada por último para a textura
+    contents: Option<Rect>,
+}
+
+impl CachedTexture {
+    fn matches(&self, desc: &D3D11_TEXTURE2D_DESC) -> bool {
+        self.width == desc.Width && self.height == desc.Height && self.format == desc.Format
+    }
+}
+
 pub struct DxgiCapture {
-    // Recursos que podem ser recriados
-    duplication: Option<IDXGIOutputDuplication>,
-    d3d_device: Option<ID3D11Device>,
-    d3d_context: Option<ID3D11DeviceContext>,
-    dxgi_output5: Option<IDXGIOutput5>,
-    roi_texture: Option<ID3D11Texture2D>,
+    // Recursos que podem ser recriados (`None` apenas se uma reinicialização falhou)
+    resources: Option<Resources>,
     
     // Informações que persistem
+    config: CaptureConfig,
     pub output_width: u32,
     pub output_height: u32,
+    output_origin: Point,
     chosen_format: DXGI_FORMAT,
-    
-    // Cache do tamanho da ROI para reutilização
-    roi_cached_width: u32,
-    roi_cached_height: u32,
+    duplication_api: DuplicationApi,
+    color: Option<OutputColorInfo>,
+    duplication_info: Option<DuplicationInfo>,
+    monitor: HMONITOR,
+    last_present_time: i64,
+    // Última vez que a duplicação perdeu o acesso (indício de tela cheia exclusiva)
+    last_access_lost: Option<Instant>,
+    // Próximo número sequencial de `capture_frame`
+    next_sequence: u64,
+    // Metadados do último quadro adquirido; o cursor persiste entre quadros sem movimento
+    last_metadata: FrameMetadata,
+    // Presents juntados pela duplicação sem terem sido vistos, desde a criação
+    missed_presents: u64,
+    // Áreas alteradas no último quadro adquirido (dirty rects e destinos de move rects)
+    last_changes: Option<Vec<Rect>>,
+    // Última forma do cursor, QPC da última atualização do mouse e o que mudou desde o último
+    // `capture_cursor`
+    cursor_shape: Option<CursorShape>,
+    last_mouse_update: i64,
+    cursor_moved: bool,
+    cursor_shape_changed: bool,
+    // Interrompe a espera pela duplicação e faz as capturas falharem com `Cancelled`
+    cancel: CancellationToken,
+    // A sessão foi desconectada; a duplicação só é recriada quando ela voltar
+    session_disconnected: bool,
+    // Regiões de `capture_named`
+    regions: RegionRegistry,
+    // Assinantes de `subscribe_events` e se `SessionLocked` já foi emitido
+    events: EventBus,
+    suspended: bool,
+    // Erros agendados por `inject_faults`
+    #[cfg(feature = "fault-injection")]
+    faults: Option<FaultInjector>,
 }
 
 impl DxgiCapture {
-    pub fn new() -> Result<Self> {
-        let mut capture = Self {
-            duplication: None,
-            d3d_device: None,
-            d3d_context: None,
-            dxgi_output5: None,
-            roi_texture: None,
+    /// Captura o monitor principal do primeiro adaptador
+    pub fn new() -> CaptureResult<Self> {
+        Self::for_output(0, 0)
+    }
+    
+    /// Captura um monitor específico (índices de `outputs::enumerate_outputs`)
+    pub fn for_output(adapter_index: u32, output_index: u32) -> CaptureResult<Self> {
+        Self::builder().output(adapter_index, output_index).build()
+    }
+    
+    /// Captura o monitor com o nome estável `name` (`MonitorIdentity::display_name`, ex.:
+    /// "DELL U2720Q #2", ou `device_path`), localizado de novo a cada reinicialização
+    pub fn for_output_by_name(name: &str) -> CaptureResult<Self> {
+        Self::builder().output_by_name(name).build()
+    }
+    
+    /// Builder para configurar o capturador antes de criá-lo
+    pub fn builder() -> CaptureBuilder {
+        CaptureBuilder::default()
+    }
+    
+    /// Cria o capturador a partir de uma configuração completa
+    pub fn with_config(config: CaptureConfig) -> CaptureResult<Self> {
+        Self::with_cancellation(config, CancellationToken::new())
+    }
+    
+    /// Como `with_config`, com a espera por `wait_for_duplication_ms` e as capturas
+    /// interrompidas por `cancel`
+    pub fn with_cancellation(config: CaptureConfig, cancel: CancellationToken) -> CaptureResult<Self> {
+        let mut capture = Self::uninitialized(config, cancel);
+        
+        // Na sessão 0 a duplicação falharia com erros genéricos; melhor explicar logo
+        session::ensure_interactive()?;
+        capture.initialize_waiting()?;
+        Ok(capture)
+    }
+    
+    /// Capturador ainda sem duplicação; `resources()` a cria na primeira captura
+    fn uninitialized(config: CaptureConfig, cancel: CancellationToken) -> Self {
+        Self {
+            resources: None,
+            config,
             output_width: 0,
             output_height: 0,
+            output_origin: Point::default(),
             chosen_format: DXGI_FORMAT_B8G8R8A8_UNORM,
-            roi_cached_width: 0,
-            roi_cached_height: 0,
-        };
+            duplication_api: DuplicationApi::DuplicateOutput1,
+            color: None,
+            duplication_info: None,
+            monitor: HMONITOR::default(),
+            last_present_time: 0,
+            last_access_lost: None,
+            next_sequence: 0,
+            last_metadata: FrameMetadata::default(),
+            missed_presents: 0,
+            last_changes: None,
+            cursor_shape: None,
+            last_mouse_update: 0,
+            cursor_moved: false,
+            cursor_shape_changed: false,
+            cancel,
+            session_disconnected: false,
+            regions: RegionRegistry::new(),
+            events: EventBus::default(),
+            suspended: false,
+            #[cfg(feature = "fault-injection")]
+            faults: None,
+        }
+    }
+    
+    /// Configuração com que o capturador foi criado
+    pub fn config(&self) -> &CaptureConfig {
+        &self.config
+    }
+    
+    /// Token que interrompe as esperas e capturas deste capturador
+    pub fn cancellation(&self) -> &CancellationToken {
+        &self.cancel
+    }
+    
+    /// Troca o token de cancelamento (ex.: por um compartilhado com outras threads)
+    pub fn set_cancellation(&mut self, cancel: CancellationToken) {
+        self.cancel = cancel;
+    }
+    
+    /// Formato de pixel negociado com a duplicação
+    pub fn format(&self) -> DXGI_FORMAT {
+        self.chosen_format
+    }
+    
+    /// API usada pela duplicação atual (a legada não negocia formatos nem HDR)
+    pub fn duplication_api(&self) -> DuplicationApi {
+        self.duplication_api
+    }
+    
+    /// Espaço de cor e luminância do monitor, lidos na última (re)inicialização
+    pub fn color_info(&self) -> Option<&OutputColorInfo> {
+        self.color.as_ref()
+    }
+    
+    /// Metadados HDR10 do monitor para os encoders (`HwEncoderConfig::hdr10`,
+    /// `FfmpegEncoderConfig::hdr10`); `None` com HDR desligado. Monitores que não informam
+    /// primárias ou luminância recebem as primárias BT.2020 com pico de 1000 nits
+    pub fn hdr_metadata(&self) -> Option<HdrMetadata> {
+        let color = self.color.as_ref().filter(|color| color.is_hdr())?;
+        let metadata = HdrMetadata::from_output(color);
+        Some(if metadata.is_valid() { metadata } else { HdrMetadata::bt2020(1000.0) })
+    }
+    
+    /// Modo de vídeo, taxa de atualização e rotação da duplicação, lidos na última
+    /// (re)inicialização
+    pub fn duplication_info(&self) -> Option<&DuplicationInfo> {
+        self.duplication_info.as_ref()
+    }
+    
+    /// Intervalo sugerido entre capturas: um período de atualização do monitor (60 Hz se o
+    /// driver não informar). Capturar mais rápido só repete quadros; mais devagar perde quadros
+    /// que a tela mostrou
+    pub fn frame_interval(&self) -> Duration {
+        let refresh = self.duplication_info.and_then(|info| info.refresh_rate()).unwrap_or(FALLBACK_REFRESH_HZ);
+        Duration::from_secs_f64(1.0 / refresh)
+    }
+    
+    /// Taxa de atualização do monitor arredondada, a taxa de captura padrão de `CaptureStream` e
+    /// do modo serviço
+    pub fn default_fps(&self) -> u32 {
+        (1.0 / self.frame_interval().as_secs_f64()).round() as u32
+    }
+    
+    /// Espaço de cor dos quadros capturados, combinando o do monitor com o formato da
+    /// duplicação: em 8 bits é sempre sRGB, em FP16 scRGB e em 10 bits o sinal do monitor
+    pub fn color_space(&self) -> ColorSpace {
+        ColorSpace::of_capture(self.output_color_space(), self.chosen_format)
+    }
+    
+    /// Espaço de cor ativo do monitor (sRGB se o sistema não informar)
+    pub fn output_color_space(&self) -> ColorSpace {
+        self.color.map_or(ColorSpace::Srgb, |color| color.color_space)
+    }
+    
+    /// Dimensões do monitor capturado
+    pub fn output_size(&self) -> Size {
+        Size::new(self.output_width, self.output_height)
+    }
+    
+    /// Monitor capturado
+    #[cfg(feature = "icc")]
+    pub(crate) fn monitor(&self) -> HMONITOR {
+        self.monitor
+    }
+    
+    /// Canto superior esquerdo do monitor no desktop virtual
+    pub fn output_origin(&self) -> Point {
+        self.output_origin
+    }
+    
+    /// Retângulo cobrindo o monitor inteiro
+    pub fn bounds(&self) -> Rect {
+        Rect::from_size(self.output_size())
+    }
+    
+    /// Resolve uma região declarativa (ancorada, em porcentagem ou seguindo o cursor) contra
+    /// o tamanho atual do monitor e a posição atual do cursor
+    pub fn resolve_region(&self, spec: &RegionSpec) -> CaptureResult<Rect> {
+        let cursor = if spec.follows_cursor() { self.cursor_position() } else { None };
+        Ok(spec.resolve_at(self.output_size(), cursor)?)
+    }
+    
+    /// Retângulo visível da janela relativo ao monitor capturado; pode sair do monitor, então
+    /// recorte com `clamp_to(capture.output_size())` antes de capturar
+    pub fn window_region(&self, hwnd: HWND) -> CaptureResult<Rect> {
+        let rect = coords::window_rect(hwnd)?;
+        Ok(Rect::from_virtual(Point::new(rect.left, rect.top), rect.size(), self.output_origin)?)
+    }
+    
+    /// Área cliente da janela relativa ao monitor capturado (mesmas ressalvas de `window_region`)
+    pub fn client_region(&self, hwnd: HWND) -> CaptureResult<Rect> {
+        let rect = coords::client_rect(hwnd)?;
+        Ok(Rect::from_virtual(Point::new(rect.left, rect.top), rect.size(), self.output_origin)?)
+    }
+    
+    /// Ponto da área cliente da janela em coordenadas do monitor capturado
+    pub fn client_to_output(&self, hwnd: HWND, point: Point) -> CaptureResult<Point> {
+        coords::client_to_output(hwnd, point, self.output_origin)
+    }
+    
+    /// Ponto do monitor capturado (ex.: de `find_image`) em coordenadas da área cliente
+    pub fn output_to_client(&self, hwnd: HWND, point: Point) -> CaptureResult<Point> {
+        coords::output_to_client(hwnd, point, self.output_origin)
+    }
+    
+    /// Regiões nomeadas usadas por `capture_named`
+    pub fn regions(&self) -> &RegionRegistry {
+        &self.regions
+    }
+    
+    pub fn regions_mut(&mut self) -> &mut RegionRegistry {
+        &mut self.regions
+    }
+    
+    /// Substitui as regiões nomeadas (ex.: por um registro carregado de arquivo)
+    pub fn set_regions(&mut self, regions: RegionRegistry) {
+        self.regions = regions;
+    }
+    
+    /// Resolve a região registrada como `name` contra o monitor atual
+    pub fn resolve_named(&self, name: &str) -> CaptureResult<Rect> {
+        let spec = self.regions.get(name).ok_or_else(|| CaptureError::UnknownRegion { name: name.to_owned() })?;
+        self.resolve_region(spec)
+    }
+    
+    /// Posição do cursor relativa ao monitor capturado, em pixels físicos; pode estar fora do
+    /// monitor. `None` se o sistema não informar (ex.: na área de trabalho segura)
+    pub fn cursor_position(&self) -> Option<Point> {
+        let mut point = POINT::default();
+        unsafe { GetPhysicalCursorPos(&mut point) }.ok()?;
+        Some(Point::new(
+            point.x.saturating_sub(self.output_origin.x),
+            point.y.saturating_sub(self.output_origin.y),
+        ))
+    }
+    
+    /// Valor de QPC do último present capturado (0 se a tela não mudou desde a criação)
+    pub fn last_present_time(&self) -> i64 {
+        self.last_present_time
+    }
+    
+    /// Idade da imagem mais recente da duplicação (agora menos `last_present_time`); `None`
+    /// antes do primeiro present
+    pub fn frame_age(&self) -> Option<Duration> {
+        timecode::present_age(self.last_present_time)
+    }
+    
+    /// Metadados (retângulos alterados, cursor, instante de aquisição) do último quadro adquirido
+    pub fn frame_metadata(&self) -> FrameMetadata {
+        self.last_metadata
+    }
+    
+    /// Total de presents que a duplicação juntou a outros quadros sem que a captura os visse
+    /// (soma de `AccumulatedFrames - 1`); capturando abaixo da taxa de atualização do monitor
+    /// eles são esperados
+    pub fn missed_presents(&self) -> u64 {
+        self.missed_presents
+    }
+    
+    /// Áreas alteradas no último quadro adquirido (dirty rects e destinos de move rects), em
+    /// coordenadas do monitor; vazio se a tela não mudou e `None` se a duplicação não informou
+    pub fn changed_rects(&self) -> Option<&[Rect]> {
+        self.last_changes.as_deref()
+    }
+    
+    /// Última forma do cursor informada pela duplicação
+    pub fn cursor_shape(&self) -> Option<&CursorShape> {
+        self.cursor_shape.as_ref()
+    }
+    
+    /// A duplicação atual entrega a imagem do desktop em memória do sistema, lida por
+    /// `MapDesktopSurface` em `capture_region` em vez de cópia na GPU e staging
+    pub fn desktop_in_system_memory(&self) -> bool {
+        self.resources.as_ref().is_some_and(|resources| resources.system_memory)
+    }
+    
+    /// Aplicativo em tela cheia no monitor capturado, se houver
+    pub fn fullscreen_app(&self) -> Option<FullscreenApp> {
+        fullscreen::detect(self.monitor, self.last_access_lost)
+    }
+    
+    /// Se o monitor está ocupado por um aplicativo em tela cheia (exclusiva ou sem bordas)
+    pub fn fullscreen_state(&self) -> FullscreenState {
+        self.fullscreen_app().map_or(FullscreenState::Windowed, |app| app.state)
+    }
+    
+    /// Inicializa a duplicação, repetindo enquanto o monitor estiver temporariamente
+    /// indisponível e o prazo de `wait_for_duplication_ms` não tiver passado
+    fn initialize_waiting(&mut self) -> CaptureResult<()> {
+        let deadline = Instant::now() + Duration::from_millis(self.config.wait_for_duplication_ms);
         
-        capture.initialize_duplication()?;
-        Ok(capture)
+        loop {
+            self.cancel.check()?;
+            match self.initialize_duplication() {
+                Err(e) if e.is_temporarily_unavailable() && Instant::now() < deadline => {
+                    debug!("Duplicação indisponível, tentando de novo: {}", e);
+                    if self.cancel.wait_timeout(DUPLICATION_WAIT_INTERVAL) {
+                        return Err(CaptureError::Cancelled);
+                    }
+                }
+                result => return result,
+            }
+        }
     }
     
     /// Inicializa ou reinicializa todos os recursos DXGI
-    fn initialize_duplication(&mut self) -> Result<()> {
+    fn initialize_duplication(&mut self) -> CaptureResult<()> {
         // Limpar recursos anteriores
-        self.release_resources();
+        self.resources = None;
+        
+        // Monitor escolhido pelo nome: os índices podem ter mudado desde a última inicialização
+        if let Some(name) = &self.config.output_name {
+            let output = outputs::find_output_by_name(name)?;
+            if (output.adapter_index, output.output_index) != (self.config.adapter_index, self.config.output_index) {
+                debug!("Monitor \"{}\" agora em {}/{}", name, output.adapter_index, output.output_index);
+            }
+            self.config.adapter_index = output.adapter_index;
+            self.config.output_index = output.output_index;
+        }
         
-        // Criar o dispositivo D3D11
-        let driver_types = [D3D_DRIVER_TYPE_HARDWARE];
+        // Localizar o adaptador e o output escolhidos
+        let (dxgi_adapter, dxgi_output) = outputs::find_output(self.config.adapter_index, self.config.output_index)?;
+        
+        // Criar o dispositivo D3D11 no adaptador do output
         let mut d3d_device: Option<ID3D11Device> = None;
         let mut d3d_context: Option<ID3D11DeviceContext> = None;
         let feature_levels = [D3D_FEATURE_LEVEL_11_0];
+        let mut create_device = |flags: D3D11_CREATE_DEVICE_FLAG| unsafe {
+            D3D11CreateDevice(
+                &dxgi_adapter,
+                D3D_DRIVER_TYPE_UNKNOWN,
+                None,
+                flags,
+                Some(&feature_levels),
+                D3D11_SDK_VERSION,
+                Some(&mut d3d_device),
+                None,
+                Some(&mut d3d_context),
+            )
+        };
         
-        for &driver_type in &driver_types {
-            unsafe {
-                let hr = D3D11CreateDevice(
-                    None,
-                    driver_type,
-                    None,
-                    D3D11_CREATE_DEVICE_BGRA_SUPPORT,
-                    Some(&feature_levels),
-                    D3D11_SDK_VERSION,
-                    Some(&mut d3d_device),
-                    None,
-                    Some(&mut d3d_context),
-                );
-                
-                if hr.is_ok() {
-                    break;
-                }
-            }
+        let mut created = if self.config.debug_layer {
+            create_device(D3D11_CREATE_DEVICE_BGRA_SUPPORT | D3D11_CREATE_DEVICE_DEBUG)
+        } else {
+            create_device(D3D11_CREATE_DEVICE_BGRA_SUPPORT)
+        };
+        // Sem as Graphics Tools (SDK layers) a criação com depuração falha; seguir sem ela
+        if matches!(&created, Err(e) if e.code() == DXGI_ERROR_SDK_COMPONENT_MISSING) {
+            warn!("Camada de depuração do D3D11 indisponível (Graphics Tools não instaladas), criando o dispositivo sem ela");
+            created = create_device(D3D11_CREATE_DEVICE_BGRA_SUPPORT);
         }
+        created.map_err(|e| CaptureError::DeviceCreation { hresult: Some(e.code()) })?;
         
-        let d3d_device = d3d_device.ok_or("Falha ao criar o dispositivo D3D11")?;
-        let d3d_context = d3d_context.ok_or("Falha ao criar o contexto D3D11")?;
-        
-        // Obter o adaptador e output
-        let dxgi_device: IDXGIDevice = d3d_device.cast()?;
-        let dxgi_adapter: IDXGIAdapter = unsafe { dxgi_device.GetAdapter()? };
-        let dxgi_output: IDXGIOutput = unsafe { dxgi_adapter.EnumOutputs(0)? };
-        let dxgi_output5: IDXGIOutput5 = dxgi_output.cast()?;
+        let (d3d_device, d3d_context) = match (d3d_device, d3d_context) {
+            (Some(device), Some(context)) => (device, context),
+            _ => return Err(CaptureError::DeviceCreation { hresult: None }),
+        };
         
         // Obter dimensões
         let mut output_desc = DXGI_OUTPUT_DESC::default();
@@ -94,6 +675,9 @@ impl DxgiCapture {
         
         self.output_width = (output_desc.DesktopCoordinates.right - output_desc.DesktopCoordinates.left) as u32;
         self.output_height = (output_desc.DesktopCoordinates.bottom - output_desc.DesktopCoordinates.top) as u32;
+        self.output_origin = Point::new(output_desc.DesktopCoordinates.left, output_desc.DesktopCoordinates.top);
+        self.color = OutputColorInfo::query(&dxgi_output);
+        self.monitor = output_desc.Monitor;
         
         // Criar duplicação
         let supported_formats = [
@@ -103,232 +687,1923 @@ impl DxgiCapture {
         ];
         
         let mut duplication: Option<IDXGIOutputDuplication> = None;
+        let mut last_error: Option<HRESULT> = None;
         
-        unsafe {
+        // `IDXGIOutput5` só existe a partir do Windows 10 1703
+        let dxgi_output5 = match dxgi_output.cast::<IDXGIOutput5>() {
+            Ok(output5) => Some(output5),
+            Err(_) => {
+                info!("IDXGIOutput5 indisponível ({:?}), usando DuplicateOutput", capabilities::OsVersion::current());
+                None
+            }
+        };
+        
+        if let Some(dxgi_output5) = &dxgi_output5 {
+            self.duplication_api = DuplicationApi::DuplicateOutput1;
             for &format in &supported_formats {
-                let result = dxgi_output5.DuplicateOutput1(
-                    &d3d_device,
-                    0,
-                    &[format],
-                );
+                let result = unsafe {
+                    dxgi_output5.DuplicateOutput1(
+                        &d3d_device,
+                        0,
+                        &[format],
+                    )
+                };
                 
-                if let Ok(dupl) = result {
+                match result {
+                    Ok(dupl) => {
+                        duplication = Some(dupl);
+                        self.chosen_format = format;
+                        break;
+                    }
+                    Err(e) => {
+                        debug!("Formato {:?} recusado pela duplicação: {}", format, e);
+                        last_error = Some(e.code());
+                        
+                        // Limite de duplicações ou desktop seguro: outro formato não ajuda
+                        if CaptureError::from_duplication(last_error).is_temporarily_unavailable() {
+                            break;
+                        }
+                    }
+                }
+            }
+        } else {
+            // A duplicação legada sempre entrega BGRA
+            self.duplication_api = DuplicationApi::DuplicateOutput;
+            let dxgi_output1: IDXGIOutput1 = dxgi_output.cast()?;
+            match unsafe { dxgi_output1.DuplicateOutput(&d3d_device) } {
+                Ok(dupl) => {
                     duplication = Some(dupl);
-                    self.chosen_format = format;
-                    break;
+                    self.chosen_format = DXGI_FORMAT_B8G8R8A8_UNORM;
                 }
+                Err(e) => last_error = Some(e.code()),
             }
         }
         
-        let duplication = duplication.ok_or("Falha ao criar a duplicação de saída")?;
+        let duplication = duplication.ok_or_else(|| CaptureError::from_duplication(last_error))?;
+        
+        let mut duplication_desc = DXGI_OUTDUPL_DESC::default();
+        unsafe {
+            duplication.GetDesc(&mut duplication_desc);
+        }
+        let duplication_info = DuplicationInfo::from_dxgi(&duplication_desc);
+        self.duplication_info = Some(duplication_info);
+        let system_memory = duplication_info.desktop_in_system_memory;
+        if system_memory {
+            info!("Imagem do desktop em memória do sistema, lendo regiões com MapDesktopSurface");
+        }
+        info!(
+            "Duplicação criada para o monitor {}/{} ({}x{}, {:?})",
+            self.config.adapter_index,
+            self.config.output_index,
+            self.output_width,
+            self.output_height,
+            self.chosen_format
+        );
+        
+        let budget_events = if self.config.trim_on_memory_pressure {
+            BudgetNotification::register(&dxgi_adapter)
+                .map_err(|e| debug!("Notificações de orçamento de memória indisponíveis: {}", e))
+                .ok()
+        } else {
+            None
+        };
         
-        // Armazenar recursos (sem criar textura ROI ainda)
-        self.d3d_device = Some(d3d_device);
-        self.d3d_context = Some(d3d_context);
-        self.dxgi_output5 = Some(dxgi_output5);
-        self.duplication = Some(duplication);
+        // Só existe quando o dispositivo foi criado com a camada de depuração
+        let info_queue = d3d_device.cast::<ID3D11InfoQueue>().ok();
+        if info_queue.is_some() {
+            info!("Camada de depuração do D3D11 ativa, mensagens repassadas ao log");
+        }
         
-        // Resetar cache da ROI
-        self.roi_cached_width = 0;
-        self.roi_cached_height = 0;
+        // Armazenar recursos (sem criar texturas da ROI ainda)
+        self.resources = Some(Resources {
+            device: d3d_device,
+            context: d3d_context,
+            adapter: dxgi_adapter,
+            _output: dxgi_output,
+            duplication,
+            budget_events,
+            info_queue,
+            system_memory,
+            system_memory_copy: None,
+            roi_textures: Vec::with_capacity(ROI_CACHE_SIZE),
+            gpu_texture: None,
+            converters: Vec::with_capacity(CONVERTER_CACHE_SIZE),
+            converted_staging: Vec::new(),
+            mip_texture: None,
+            mip_view: None,
+            mip_staging: Vec::new(),
+            patch_staging: None,
+            histogram: None,
+            color_range: None,
+            template: None,
+            scale: None,
+            scaled_texture: None,
+            scaled_staging: None,
+            #[cfg(feature = "onnx")]
+            tensor: None,
+            deferred: None,
+        });
         
         Ok(())
     }
     
-    /// Cria ou recria a textura ROI se necessário
-    fn ensure_roi_texture(&mut self, width: u32, height: u32) -> Result<()> {
-        // Se já temos uma textura com o tamanho correto, reutilizar
-        if self.roi_texture.is_some() && 
-           self.roi_cached_width == width && 
-           self.roi_cached_height == height {
-            return Ok(());
+    /// Recursos da duplicação ativa, reinicializando se uma tentativa anterior falhou
+    fn resources(&mut self) -> CaptureResult<&mut Resources> {
+        if self.resources.is_none() {
+            // Enquanto a sessão estiver desconectada a criação falharia; não tocar no driver
+            if self.session_disconnected {
+                if session::current_session_disconnected() {
+                    return Err(CaptureError::SessionDisconnected { hresult: DXGI_ERROR_SESSION_DISCONNECTED });
+                }
+                info!("Sessão reconectada, recriando a duplicação");
+                self.session_disconnected = false;
+            }
+            self.reinitialize()?;
         }
         
-        // Limpar textura antiga
-        self.roi_texture = None;
-        
-        // Criar nova textura com o tamanho exato necessário
-        let roi_texture_desc = D3D11_TEXTURE2D_DESC {
-            Width: width,
-            Height: height,
-            MipLevels: 1,
-            ArraySize: 1,
-            Format: self.chosen_format,
-            SampleDesc: DXGI_SAMPLE_DESC {
-                Count: 1,
-                Quality: 0,
-            },
-            Usage: D3D11_USAGE_STAGING,
-            BindFlags: D3D11_BIND_FLAG(0),
-            CPUAccessFlags: D3D11_CPU_ACCESS_FLAG(D3D11_CPU_ACCESS_READ.0),
-            MiscFlags: D3D11_RESOURCE_MISC_FLAG(0),
-        };
-        
-        let mut roi_texture: Option<ID3D11Texture2D> = None;
-        unsafe {
-            if let Some(device) = &self.d3d_device {
-                device.CreateTexture2D(
-                    &roi_texture_desc,
-                    None,
-                    Some(&mut roi_texture),
-                )?;
+        self.resources.as_mut().ok_or(CaptureError::NotInitialized)
+    }
+    
+    /// Recria a duplicação e avisa os assinantes de `subscribe_events` do que mudou
+    fn reinitialize(&mut self) -> CaptureResult<()> {
+        let previous_bounds = self.desktop_rect();
+        let previous_format = self.chosen_format;
+        if let Err(e) = self.initialize_duplication() {
+            self.session_disconnected = matches!(e, CaptureError::SessionDisconnected { .. });
+            // Tela de bloqueio e UAC negam a duplicação até o usuário voltar
+            if matches!(e, CaptureError::AccessDenied { .. } | CaptureError::SessionDisconnected { .. }) {
+                self.suspend();
             }
+            return Err(e);
         }
         
-        self.roi_texture = roi_texture;
-        self.roi_cached_width = width;
-        self.roi_cached_height = height;
-        
+        self.events.emit(CaptureEvent::Reinitialized);
+        let current_bounds = self.desktop_rect();
+        if current_bounds != previous_bounds {
+            self.events.emit(CaptureEvent::DisplayChanged { previous: previous_bounds, current: current_bounds });
+        }
+        if self.chosen_format != previous_format {
+            self.events.emit(CaptureEvent::FormatChanged { previous: previous_format, current: self.chosen_format });
+        }
+        if self.suspended {
+            self.suspended = false;
+            self.events.emit(CaptureEvent::SessionUnlocked);
+        }
         Ok(())
     }
     
-    /// Libera todos os recursos DXGI
-    fn release_resources(&mut self) {
-        self.duplication = None;
-        self.roi_texture = None;
-        self.dxgi_output5 = None;
-        self.d3d_context = None;
-        self.d3d_device = None;
-        self.roi_cached_width = 0;
-        self.roi_cached_height = 0;
+    /// Emite `SessionLocked` uma vez por suspensão
+    fn suspend(&mut self) {
+        if !self.suspended {
+            self.suspended = true;
+            self.events.emit(CaptureEvent::SessionLocked);
+        }
+    }
+    
+    /// Área do monitor no desktop virtual
+    fn desktop_rect(&self) -> Rect {
+        Rect::new(self.output_origin.x, self.output_origin.y, self.output_width, self.output_height)
     }
     
-    pub fn capture_region(&mut self, left: u32, top: u32, width: u32, height: u32) -> Result<Vec<u8>> {
-        if left + width > self.output_width || top + height > self.output_height {
-            return Err("Região solicitada fora dos limites da tela".into());
-        }
+    /// Canal com os eventos de saúde da captura (reinicializações, trocas de modo, bloqueio da
+    /// sessão) emitidos a partir de agora
+    pub fn subscribe_events(&self) -> Receiver<CaptureEvent> {
+        self.events.subscribe()
+    }
+    
+    /// Passa a injetar os erros de `plan` nas próximas capturas, contando os quadros a partir
+    /// delas (veja `fault_injection`); substitui um plano anterior
+    #[cfg(feature = "fault-injection")]
+    pub fn inject_faults(&mut self, plan: FaultPlan) {
+        self.faults = Some(FaultInjector::new(plan));
+    }
+    
+    /// Plano em andamento e erros já injetados
+    #[cfg(feature = "fault-injection")]
+    pub fn fault_injector(&self) -> Option<&FaultInjector> {
+        self.faults.as_ref()
+    }
+    
+    /// Faz a captura emitir os eventos para os assinantes de `events` (o `CaptureStream` dono)
+    pub(crate) fn share_events(&mut self, events: EventBus) {
+        self.events = events;
+    }
+    
+    /// Dispositivo D3D11 atual (muda após uma reinicialização)
+    pub fn device(&self) -> Option<&ID3D11Device> {
+        self.resources.as_ref().map(|resources| &resources.device)
+    }
+    
+    /// Contexto imediato do dispositivo D3D11 atual
+    pub fn context(&self) -> Option<&ID3D11DeviceContext> {
+        self.resources.as_ref().map(|resources| &resources.context)
+    }
+    
+    /// Adquire o próximo quadro da duplicação, reinicializando em caso de perda de acesso.
+    ///
+    /// Com `retry_after_reinit`, uma reinicialização bem-sucedida é seguida de uma nova
+    /// tentativa na mesma chamada, de modo que trocas de modo de tela passem despercebidas.
+    ///
+    /// O quadro é liberado quando o `AcquiredFrame` retornado sai de escopo, inclusive
+    /// quando a duplicação não entregou textura ou alguma etapa posterior falha.
+    fn acquire_frame(&mut self) -> CaptureResult<AcquiredFrame> {
+        let mut timeout = 0;
+        let mut retried = false;
+        // Prazo da espera por um present novo quando a imagem passou de `max_frame_age_ms`
+        let mut stale_deadline: Option<Instant> = None;
         
-        // Verificar se temos uma duplicação válida
-        if self.duplication.is_none() {
-            self.initialize_duplication()?;
+        // Mensagens da camada de depuração geradas desde a captura anterior
+        if let Some(resources) = self.resources.as_ref() {
+            resources.forward_debug_messages();
         }
         
-        // Garantir que temos uma textura ROI do tamanho correto
-        self.ensure_roi_texture(width, height)?;
-        
-        let mut frame_resource: Option<IDXGIResource> = None;
-        let mut frame_info = DXGI_OUTDUPL_FRAME_INFO::default();
+        #[cfg(feature = "fault-injection")]
+        if let Some(faults) = self.faults.as_mut() {
+            faults.begin_frame();
+        }
         
-        unsafe {
-            let duplication = self.duplication.as_ref().unwrap();
-            let result = duplication.AcquireNextFrame(
-                0,
-                &mut frame_info,
-                &mut frame_resource,
-            );
+        loop {
+            self.cancel.check()?;
+            let mut frame_resource: Option<IDXGIResource> = None;
+            let mut frame_info = DXGI_OUTDUPL_FRAME_INFO::default();
+            let duplication = self.resources()?.duplication.clone();
+            
+            let result = match self.injected_fault() {
+                Some(hresult) => Err(windows::core::Error::from(hresult)),
+                None => unsafe {
+                    duplication.AcquireNextFrame(
+                        timeout,
+                        &mut frame_info,
+                        &mut frame_resource,
+                    )
+                },
+            };
             
             if let Err(err) = result {
                 let error_code = err.code();
                 
+                if error_code == DXGI_ERROR_WAIT_TIMEOUT && stale_deadline.is_some() {
+                    return Err(CaptureError::StaleFrame { age: timecode::present_age(self.last_present_time) });
+                }
+                
+                // Sessão desconectada (RDP fechado, troca de usuário): a duplicação só pode ser
+                // recriada quando ela voltar, o que `resources()` verifica a cada captura
+                if error_code == DXGI_ERROR_SESSION_DISCONNECTED {
+                    warn!("Sessão desconectada, captura suspensa até a reconexão");
+                    self.resources = None;
+                    self.session_disconnected = true;
+                    self.suspend();
+                    return Err(CaptureError::SessionDisconnected { hresult: error_code });
+                }
+                
                 // Erros que requerem reinicialização
                 if error_code == DXGI_ERROR_ACCESS_LOST || 
                    error_code == DXGI_ERROR_DEVICE_REMOVED || 
-                   error_code == DXGI_ERROR_DEVICE_RESET ||
-                   error_code == DXGI_ERROR_SESSION_DISCONNECTED {
+                   error_code == DXGI_ERROR_DEVICE_RESET {
                     
-                    // Tentar reinicializar
-                    match self.initialize_duplication() {
-                        Ok(_) => {
-                            // Após reinicialização, precisamos recriar a textura ROI
-                            self.ensure_roi_texture(width, height)?;
-                        }
-                        Err(e) => {
-                            eprintln!("Falha ao reinicializar: {}", e);
-                            return Err(e);
-                        }
+                    if error_code == DXGI_ERROR_ACCESS_LOST {
+                        self.last_access_lost = Some(Instant::now());
+                    } else {
+                        // O motivo real (travamento, atualização do driver) fica no dispositivo
+                        let reason = self.resources.as_ref().map_or(error_code, |resources| {
+                            unsafe { resources.device.GetDeviceRemovedReason() }.err().map_or(error_code, |e| e.code())
+                        });
+                        self.events.emit(CaptureEvent::DeviceRemoved { reason });
+                    }
+                    
+                    // Tentar reinicializar; as texturas da ROI são recriadas na próxima captura
+                    warn!("Acesso à duplicação perdido ({:?}), reinicializando", error_code);
+                    if let Err(e) = self.reinitialize() {
+                        error!("Falha ao reinicializar: {}", e);
+                        return Err(e);
                     }
+                    
+                    // A nova duplicação entrega a imagem atual do desktop em seguida
+                    if self.config.retry_after_reinit && !retried {
+                        debug!("Repetindo a aquisição com a nova duplicação");
+                        retried = true;
+                        timeout = REINIT_RETRY_TIMEOUT_MS;
+                        continue;
+                    }
+                    
+                    return Err(CaptureError::AccessLost { hresult: error_code });
                 }
                 
                 // Para outros erros, apenas retornar
-                return Err(format!("Erro ao adquirir frame: {:?}", error_code).into());
-            }
-        }
-        
-        let frame_resource = match frame_resource {
-            Some(resource) => resource,
-            None => {
-                unsafe { 
-                    if let Some(dup) = &self.duplication {
-                        let _ = dup.ReleaseFrame();
-                    }
-                }
-                return Ok(vec![0u8; (height as usize) * (width as usize) * 4]);
+                return Err(CaptureError::AcquireFailed { hresult: error_code });
             }
-        };
-        
-        // Obter a textura e copiar região
-        let acquired_texture: ID3D11Texture2D = frame_resource.cast()?;
-        
-        unsafe {
-            let src_box = D3D11_BOX {
-                left,
-                top,
-                front: 0,
-                right: left + width,
-                bottom: top + height,
-                back: 1,
+            
+            // A partir daqui o quadro está adquirido e será liberado no drop do guard
+            let frame = AcquiredFrame {
+                duplication,
+                texture: None,
             };
             
-            if let (Some(context), Some(roi_texture)) = (&self.d3d_context, &self.roi_texture) {
-                context.CopySubresourceRegion(
-                    roi_texture,
-                    0,
-                    0,
-                    0,
-                    0,
-                    &acquired_texture,
-                    0,
-                    Some(&src_box),
-                );
+            if frame_info.LastPresentTime != 0 {
+                self.last_present_time = frame_info.LastPresentTime;
+            }
+            self.update_metadata(&frame.duplication, &frame_info);
+            
+            // Nada apresentado desde a última aquisição e a imagem atual já passou da idade máxima
+            let age = timecode::present_age(self.last_present_time);
+            let expired = Some(Duration::from_millis(self.config.max_frame_age_ms))
+                .filter(|max_age| frame_info.LastPresentTime == 0 && !max_age.is_zero())
+                .filter(|&max_age| !age.is_some_and(|age| age <= max_age))
+                .map(|max_age| (age, max_age));
+            
+            match accept_frame(frame, frame_resource, expired, &mut stale_deadline)? {
+                FrameDecision::Ready(frame) => return Ok(frame),
+                FrameDecision::Wait(remaining) => timeout = remaining,
             }
         }
+    }
+    
+    /// Erro do `FaultPlan` agendado para esta tentativa de aquisição
+    #[cfg(feature = "fault-injection")]
+    fn injected_fault(&mut self) -> Option<HRESULT> {
+        let fault = self.faults.as_mut()?.take()?;
+        warn!("Erro injetado em AcquireNextFrame: {:?}", fault);
+        Some(fault.hresult())
+    }
+    
+    #[cfg(not(feature = "fault-injection"))]
+    fn injected_fault(&mut self) -> Option<HRESULT> {
+        None
+    }
+    
+    /// Registra os metadados do quadro recém-adquirido (precisa ser chamado antes de `ReleaseFrame`)
+    fn update_metadata(&mut self, duplication: &IDXGIOutputDuplication, info: &DXGI_OUTDUPL_FRAME_INFO) {
+        let metadata = &mut self.last_metadata;
+        let mut now = 0i64;
+        if unsafe { QueryPerformanceCounter(&mut now) }.is_ok() {
+            metadata.acquire_time = now;
+        }
         
-        // Liberar o frame
-        unsafe {
-            if let Some(dup) = &self.duplication {
-                let _ = dup.ReleaseFrame();
+        metadata.accumulated_frames = info.AccumulatedFrames;
+        if info.AccumulatedFrames > 1 {
+            self.missed_presents += u64::from(info.AccumulatedFrames - 1);
+            if self.config.log_missed_presents {
+                warn!("{} presents perdidos antes deste quadro", info.AccumulatedFrames - 1);
             }
         }
         
-        // Mapear e copiar dados
-        let mut mapped_resource = D3D11_MAPPED_SUBRESOURCE::default();
-        unsafe {
-            if let (Some(context), Some(roi_texture)) = (&self.d3d_context, &self.roi_texture) {
-                context.Map(
-                    roi_texture,
-                    0,
-                    D3D11_MAP_READ,
-                    0,
-                    Some(&mut mapped_resource),
-                )?;
-            }
+        if info.LastPresentTime == 0 {
+            metadata.dirty_rects = Some(0);
+            metadata.move_rects = Some(0);
+            self.last_changes = Some(Vec::new());
+        } else if info.TotalMetadataBufferSize == 0 {
+            metadata.dirty_rects = None;
+            metadata.move_rects = None;
+            self.last_changes = None;
+        } else {
+            let (moves, dirty) = frame_changes(duplication, info.TotalMetadataBufferSize);
+            metadata.move_rects = moves.as_ref().map(|rects| rects.len() as u32);
+            metadata.dirty_rects = dirty.as_ref().map(|rects| rects.len() as u32);
+            self.last_changes = match (moves, dirty) {
+                (Some(mut moves), Some(dirty)) => {
+                    moves.extend(dirty);
+                    Some(moves)
+                }
+                _ => None,
+            };
         }
         
-        let row_pitch = mapped_resource.RowPitch;
-        let mut buffer = vec![0u8; (height as usize) * (width as usize) * 4];
+        // Posição só é informada quando o mouse se moveu ou mudou de visibilidade
+        if info.LastMouseUpdateTime != 0 {
+            let pointer = info.PointerPosition;
+            metadata.cursor = pointer.Visible.as_bool().then(|| Point::new(pointer.Position.x, pointer.Position.y));
+            self.last_mouse_update = info.LastMouseUpdateTime;
+            self.cursor_moved = true;
+        }
         
-        unsafe {
-            let src_ptr = mapped_resource.pData as *const u8;
-            
-            for y in 0..height as usize {
-                let src_row = src_ptr.add(y * row_pitch as usize);
-                let dst_row = buffer.as_mut_ptr().add(y * width as usize * 4);
-                ptr::copy_nonoverlapping(src_row, dst_row, width as usize * 4);
-            }
-            
-            if let Some(context) = &self.d3d_context {
-                if let Some(roi_texture) = &self.roi_texture {
-                    context.Unmap(roi_texture, 0);
+        // A forma só vem no quadro em que mudou, então é guardada até ser consultada
+        if info.PointerShapeBufferSize != 0 {
+            match pointer_shape(duplication, info.PointerShapeBufferSize) {
+                Ok(shape) => {
+                    self.cursor_shape = Some(shape);
+                    self.cursor_shape_changed = true;
                 }
+                Err(e) => debug!("Falha ao ler a forma do cursor: {}", e),
             }
         }
+    }
+    
+    /// Valida a região e garante uma duplicação ativa
+    fn prepare_capture(&mut self, region: Rect) -> CaptureResult<()> {
+        region.validate_within(self.output_size())?;
+        let budget = self.config.texture_budget_bytes;
+        let idle = self.config.texture_idle_release_ms;
+        let resources = self.resources()?;
         
-        Ok(buffer)
+        // Liberar antes de alocar as texturas desta captura
+        if idle != 0 {
+            resources.release_idle(Duration::from_millis(idle));
+        }
+        if budget != 0 && resources.cached_bytes() > budget {
+            debug!("Texturas em cache ({} bytes) acima do orçamento de {} bytes, liberando", resources.cached_bytes(), budget);
+            resources.trim();
+        }
+        if let Some(events) = resources.budget_events.as_ref().filter(|events| events.changed()) {
+            match events.status() {
+                Ok(status) if status.local.over_budget() => {
+                    warn!(
+                        "Memória de vídeo acima do orçamento ({} de {} bytes), liberando {} bytes em cache",
+                        status.local.usage,
+                        status.local.budget,
+                        resources.cached_bytes()
+                    );
+                    resources.trim();
+                }
+                Ok(_) => {}
+                Err(e) => debug!("Falha ao consultar a memória de vídeo: {}", e),
+            }
+        }
+        Ok(())
     }
-}
-
-impl Drop for DxgiCapture {
-    fn drop(&mut self) {
-        self.release_resources();
+    
+    /// Libera as texturas intermediárias e o video processor em cache, mantendo a duplicação.
+    ///
+    /// Útil depois de uma captura grande isolada (ex.: 4K inteiro) seguida de regiões pequenas;
+    /// a próxima captura recria só o que precisar.
+    pub fn trim(&mut self) {
+        if let Some(resources) = self.resources.as_mut() {
+            resources.trim();
+        }
+    }
+    
+    /// Memória aproximada ocupada pelas texturas intermediárias em cache
+    pub fn cached_texture_bytes(&self) -> u64 {
+        self.resources.as_ref().map_or(0, |resources| resources.cached_bytes())
+    }
+    
+    /// Uso e orçamento de memória de vídeo do processo no adaptador da captura (Windows 10+)
+    pub fn video_memory(&self) -> CaptureResult<VideoMemoryStatus> {
+        let resources = self.resources.as_ref().ok_or(CaptureError::NotInitialized)?;
+        video_memory::query(&resources.adapter)
+    }
+    
+    /// Evento próprio de mudanças do orçamento de memória de vídeo, para monitorar a pressão
+    /// de memória de outra thread; continua válido após reinicializações da duplicação
+    pub fn budget_notification(&self) -> CaptureResult<BudgetNotification> {
+        let resources = self.resources.as_ref().ok_or(CaptureError::NotInitialized)?;
+        BudgetNotification::register(&resources.adapter)
+    }
+    
+    /// Captura a região para uma textura na GPU, sem leitura para a CPU.
+    ///
+    /// A textura é reutilizada entre chamadas; se a tela não mudou, ela mantém o conteúdo
+    /// da captura anterior (ou retorna `CaptureError::NoNewFrame` com `NoFramePolicy::NoChange`).
+    /// Pertence ao dispositivo retornado por `device()`.
+    pub fn capture_texture(&mut self, region: Rect) -> CaptureResult<ID3D11Texture2D> {
+        self.prepare_capture(region)?;
+        let frame = self.acquire_frame()?;
+        
+        // Uma reinicialização durante a aquisição pode ter mudado o dispositivo e a resolução
+        region.validate_within(self.output_size())?;
+        let format = self.chosen_format;
+        let no_frame_policy = self.config.no_frame_policy;
+        let resources = self.resources()?;
+        let gpu_texture = resources.gpu_texture(format, region.width, region.height)?;
+        
+        match frame.texture() {
+            Some(acquired_texture) => resources.copy_region(acquired_texture, &gpu_texture, region),
+            None if no_frame_policy == NoFramePolicy::NoChange => return Err(CaptureError::NoNewFrame),
+            None => {}
+        }
+        drop(frame);
+        
+        Ok(gpu_texture)
     }
-}
\ No newline at end of file
+    
+    /// Adquire o próximo quadro só pelas informações do cursor, sem copiar nem ler pixels do
+    /// desktop.
+    ///
+    /// `moved` e `shape` acumulam as mudanças de todas as aquisições (inclusive de outras
+    /// capturas) desde a chamada anterior, então um cliente que desenha o cursor em uma camada
+    /// própria não perde uma troca de forma. Não espera: se nada mudou, `is_empty()` é verdadeiro.
+    pub fn capture_cursor(&mut self) -> CaptureResult<CursorUpdate> {
+        let frame = self.acquire_frame()?;
+        drop(frame);
+        
+        let shape_changed = std::mem::take(&mut self.cursor_shape_changed);
+        Ok(CursorUpdate {
+            position: self.last_metadata.cursor,
+            moved: std::mem::take(&mut self.cursor_moved),
+            shape: self.cursor_shape.clone().filter(|_| shape_changed),
+            update_time: self.last_mouse_update,
+        })
+    }
+    
+    /// Captura a região registrada como `name` em `regions()`
+    pub fn capture_named(&mut self, name: &str) -> CaptureResult<Frame> {
+        let region = self.resolve_named(name)?;
+        self.capture_frame(region)
+    }
+    
+    /// Como `capture_region`, mas com os metadados do quadro (formato, espaço de cor, QPC)
+    pub fn capture_frame(&mut self, region: Rect) -> CaptureResult<Frame> {
+        let data = self.capture_region(region)?;
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        
+        Ok(Frame {
+            data,
+            region,
+            format: self.chosen_format,
+            color_space: self.color_space(),
+            present_time: self.last_present_time,
+            sequence,
+            metadata: self.last_metadata,
+        })
+    }
+    
+    /// Captura a região do monitor e retorna os pixels sem padding entre linhas
+    pub fn capture_region(&mut self, region: Rect) -> CaptureResult<Vec<u8>> {
+        self.prepare_capture(region)?;
+        let frame = self.acquire_frame()?;
+        self.read_acquired(frame, region)
+    }
+    
+    /// Captura a região de `roi`, ajustada antes da cópia às áreas alteradas do quadro.
+    ///
+    /// A região cresce para cobrir o conteúdo que está mudando e volta à semente quando ele
+    /// para (veja `AutoRoi`), então só a parte animada da tela é lida. `frame.region` informa
+    /// a região efetivamente capturada. Após uma troca de resolução a região volta à semente,
+    /// recortada ao novo tamanho do monitor.
+    pub fn capture_auto(&mut self, roi: &mut AutoRoi) -> CaptureResult<Frame> {
+        if roi.bounds() != self.output_size() {
+            roi.set_bounds(self.output_size())?;
+        }
+        self.prepare_capture(roi.region())?;
+        let frame = self.acquire_frame()?;
+        
+        // Uma reinicialização durante a aquisição pode ter mudado a resolução
+        if roi.bounds() != self.output_size() {
+            roi.set_bounds(self.output_size())?;
+        }
+        let region = roi.update(self.changed_rects());
+        let data = self.read_acquired(frame, region)?;
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        
+        Ok(Frame {
+            data,
+            region,
+            format: self.chosen_format,
+            color_space: self.color_space(),
+            present_time: self.last_present_time,
+            sequence,
+            metadata: self.last_metadata,
+        })
+    }
+    
+    /// Copia `region` do quadro adquirido e a lê para a CPU, liberando o quadro
+    fn read_acquired(&mut self, frame: AcquiredFrame, region: Rect) -> CaptureResult<Vec<u8>> {
+        let (width, height) = (region.width, region.height);
+        
+        // Garantir que temos uma textura ROI do tamanho correto no dispositivo atual
+        region.validate_within(self.output_size())?;
+        let format = self.chosen_format;
+        let no_frame_policy = self.config.no_frame_policy;
+        let resources = self.resources()?;
+        
+        if resources.system_memory {
+            match read_system_memory(resources, &frame, format, region, no_frame_policy) {
+                Err(CaptureError::Windows(e)) => {
+                    warn!("MapDesktopSurface falhou, voltando à cópia pela GPU: {}", e);
+                    resources.system_memory = false;
+                    resources.system_memory_copy = None;
+                }
+                result => return result,
+            }
+        }
+        
+        let (slot, roi_texture) = resources.roi_texture(format, width, height)?;
+        
+        match frame.texture() {
+            // Copiar região e liberar o frame
+            Some(acquired_texture) => {
+                resources.copy_region(acquired_texture, &roi_texture, region);
+                if let Some(cached) = resources.roi_textures[slot].as_mut() {
+                    cached.contents = Some(region);
+                }
+            }
+            None => match no_frame_policy {
+                NoFramePolicy::ZeroFill => {
+                    let (row_bytes, rows) = convert::frame_layout(format, width, height);
+                    return Ok(vec![0u8; row_bytes * rows]);
+                }
+                NoFramePolicy::NoChange => return Err(CaptureError::NoNewFrame),
+                // A textura ROI ainda guarda a última cópia se ela foi desta mesma região
+                NoFramePolicy::LastFrame => {
+                    let contents = resources.roi_textures[slot].as_ref().and_then(|cached| cached.contents);
+                    if contents != Some(region) {
+                        return Err(CaptureError::NoNewFrame);
+                    }
+                }
+            },
+        }
+        drop(frame);
+        
+        resources.read_texture(&roi_texture, format, width, height)
+    }
+    
+    /// Captura várias regiões do mesmo quadro do desktop.
+    ///
+    /// As cópias de todas as regiões são gravadas em um contexto diferido e enviadas à GPU
+    /// em uma única lista de comandos, com uma só aquisição; depois cada região é lida. Os
+    /// quadros retornados compartilham `present_time` e `sequence`. Sem suporte a contextos
+    /// diferidos as cópias vão direto para o contexto imediato.
+    pub fn capture_regions(&mut self, regions: &[Rect]) -> CaptureResult<Vec<Frame>> {
+        for region in regions {
+            self.prepare_capture(*region)?;
+        }
+        let frame = self.acquire_frame()?;
+        
+        let output_size = self.output_size();
+        for region in regions {
+            region.validate_within(output_size)?;
+        }
+        let format = self.chosen_format;
+        let no_frame_policy = self.config.no_frame_policy;
+        let resources = self.resources()?;
+        
+        // Stagings de todas as regiões antes das cópias; os clones as mantêm vivas mesmo se
+        // houver mais tamanhos que o cache de ROIs comporta
+        let mut stagings = Vec::with_capacity(regions.len());
+        for region in regions {
+            stagings.push(resources.roi_texture(format, region.width, region.height)?);
+        }
+        
+        match frame.texture() {
+            Some(acquired_texture) => {
+                let copies: Vec<_> = stagings.iter().map(|(_, texture)| texture).zip(regions.iter().copied()).collect();
+                resources.copy_regions(acquired_texture, &copies)?;
+                for ((slot, texture), region) in stagings.iter().zip(regions) {
+                    if let Some(cached) = resources.roi_textures[*slot].as_mut().filter(|cached| cached.texture == *texture) {
+                        cached.contents = Some(*region);
+                    }
+                }
+            }
+            None => match no_frame_policy {
+                NoFramePolicy::ZeroFill => {}
+                NoFramePolicy::NoChange => return Err(CaptureError::NoNewFrame),
+                NoFramePolicy::LastFrame => {
+                    let stale = stagings.iter().zip(regions).any(|((slot, _), region)| {
+                        resources.roi_textures[*slot].as_ref().and_then(|cached| cached.contents) != Some(*region)
+                    });
+                    if stale {
+                        return Err(CaptureError::NoNewFrame);
+                    }
+                }
+            },
+        }
+        let zero_fill = frame.texture().is_none() && no_frame_policy == NoFramePolicy::ZeroFill;
+        drop(frame);
+        
+        let mut data = Vec::with_capacity(regions.len());
+        for ((_, texture), region) in stagings.iter().zip(regions) {
+            data.push(if zero_fill {
+                let (row_bytes, rows) = convert::frame_layout(format, region.width, region.height);
+                vec![0u8; row_bytes * rows]
+            } else {
+                resources.read_texture(texture, format, region.width, region.height)?
+            });
+        }
+        
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        let color_space = self.color_space();
+        
+        Ok(data
+            .into_iter()
+            .zip(regions)
+            .map(|(data, region)| Frame {
+                data,
+                region: *region,
+                format,
+                color_space,
+                present_time: self.last_present_time,
+                sequence,
+                metadata: self.last_metadata,
+            })
+            .collect())
+    }
+    
+    /// Captura regiões pequenas (pixels observados, indicadores de jogo) do mesmo quadro.
+    ///
+    /// As regiões são copiadas lado a lado em uma única staging e lidas com um só `Map`, o que
+    /// torna dezenas de regiões de poucos pixels quase tão baratas quanto uma. A soma das
+    /// larguras não pode passar de 16384. Sem quadro novo retorna `CaptureError::NoNewFrame`
+    /// independente de `NoFramePolicy`, já que nada mudou desde a última leitura.
+    pub fn capture_patches(&mut self, regions: &[Rect]) -> CaptureResult<Vec<Frame>> {
+        if regions.is_empty() {
+            return Ok(Vec::new());
+        }
+        for region in regions {
+            self.prepare_capture(*region)?;
+        }
+        
+        let total_width: u64 = regions.iter().map(|region| region.width as u64).sum();
+        let height = regions.iter().map(|region| region.height).max().unwrap_or(1);
+        if total_width > D3D11_REQ_TEXTURE2D_U_OR_V_DIMENSION as u64 {
+            let limit = D3D11_REQ_TEXTURE2D_U_OR_V_DIMENSION;
+            return Err(RectError::OutOfBounds {
+                region: Rect::new(0, 0, total_width.min(u32::MAX as u64) as u32, height),
+                bounds: Size::new(limit, limit),
+            }
+            .into());
+        }
+        let total_width = total_width as u32;
+        
+        let frame = self.acquire_frame()?;
+        let Some(acquired_texture) = frame.texture() else {
+            return Err(CaptureError::NoNewFrame);
+        };
+        
+        // Uma reinicialização durante a aquisição pode ter mudado a resolução
+        let output_size = self.output_size();
+        for region in regions {
+            region.validate_within(output_size)?;
+        }
+        let format = self.chosen_format;
+        let resources = self.resources()?;
+        let staging = resources.patch_staging(format, total_width, height)?;
+        let mut x = 0;
+        for region in regions {
+            record_copy_at(&resources.context, acquired_texture, &staging, *region, x);
+            x += region.width;
+        }
+        drop(frame);
+        
+        let data = resources.read_texture(&staging, format, total_width, height)?;
+        let (row_bytes, _) = convert::frame_layout(format, total_width, height);
+        let pixel_bytes = row_bytes / total_width as usize;
+        
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        let color_space = self.color_space();
+        
+        let mut x = 0;
+        let mut frames = Vec::with_capacity(regions.len());
+        for region in regions {
+            let start = x * pixel_bytes;
+            let width_bytes = region.width as usize * pixel_bytes;
+            let mut patch = Vec::with_capacity(width_bytes * region.height as usize);
+            for row in data.chunks_exact(row_bytes).take(region.height as usize) {
+                patch.extend_from_slice(&row[start..start + width_bytes]);
+            }
+            x += region.width as usize;
+            
+            frames.push(Frame {
+                data: patch,
+                region: *region,
+                format,
+                color_space,
+                present_time: self.last_present_time,
+                sequence,
+                metadata: self.last_metadata,
+            });
+        }
+        Ok(frames)
+    }
+    
+    /// Captura a região uma vez, gera a cadeia de mips na GPU e lê os níveis pedidos.
+    ///
+    /// Um consumidor que precisa da imagem completa e de uma miniatura pede `&[0, 3]` e recebe
+    /// ambas da mesma captura (a miniatura com 1/8 da largura e da altura). Níveis além do
+    /// último (1x1) são limitados a ele.
+    pub fn capture_mips(&mut self, region: Rect, levels: &[u32]) -> CaptureResult<Vec<MipLevel>> {
+        self.prepare_capture(region)?;
+        let frame = self.acquire_frame()?;
+        
+        region.validate_within(self.output_size())?;
+        let format = self.chosen_format;
+        let no_frame_policy = self.config.no_frame_policy;
+        let resources = self.resources()?;
+        let mip_texture = resources.mip_texture(format, region.width, region.height)?;
+        let last_level = mip_count(region.size()) - 1;
+        
+        match frame.texture() {
+            Some(acquired_texture) => {
+                resources.copy_region(acquired_texture, &mip_texture, region);
+                let view = resources.mip_view(&mip_texture)?;
+                unsafe {
+                    resources.context.GenerateMips(&view);
+                }
+                if let Some(cached) = resources.mip_texture.as_mut() {
+                    cached.contents = Some(region);
+                }
+            }
+            None => match no_frame_policy {
+                NoFramePolicy::ZeroFill => {
+                    return Ok(levels
+                        .iter()
+                        .map(|&level| {
+                            let size = mip_size(region.size(), level.min(last_level));
+                            let (row_bytes, rows) = convert::frame_layout(format, size.width, size.height);
+                            MipLevel {
+                                level: level.min(last_level),
+                                size,
+                                data: vec![0u8; row_bytes * rows],
+                            }
+                        })
+                        .collect());
+                }
+                NoFramePolicy::NoChange => return Err(CaptureError::NoNewFrame),
+                // A cadeia ainda guarda os mips da última captura se ela foi desta mesma região
+                NoFramePolicy::LastFrame => {
+                    let contents = resources.mip_texture.as_ref().and_then(|cached| cached.contents);
+                    if contents != Some(region) {
+                        return Err(CaptureError::NoNewFrame);
+                    }
+                }
+            },
+        }
+        drop(frame);
+        
+        levels
+            .iter()
+            .map(|&level| {
+                let level = level.min(last_level);
+                let size = mip_size(region.size(), level);
+                let staging = resources.mip_staging(level, format, size)?;
+                unsafe {
+                    resources.context.CopySubresourceRegion(&staging, 0, 0, 0, 0, &mip_texture, level, None);
+                }
+                let data = resources.read_texture(&staging, format, size.width, size.height)?;
+                Ok(MipLevel { level, size, data })
+            })
+            .collect()
+    }
+    
+    /// Histograma de luminância da região em 256 faixas, calculado por um compute shader.
+    ///
+    /// Só os 1 KB do histograma são lidos para a CPU, então auto-exposição, análise de HDR e
+    /// detecção de troca de cena podem rodar a cada quadro. A região passa por `capture_texture`,
+    /// e portanto segue a `NoFramePolicy` dela.
+    pub fn luminance_histogram(&mut self, region: Rect) -> CaptureResult<Histogram> {
+        let texture = self.capture_texture(region)?;
+        let resources = self.resources()?;
+        
+        if resources.histogram.is_none() {
+            resources.histogram = Some(HistogramPass::new(&resources.device)?);
+            debug!("Compute shader do histograma compilado");
+        }
+        let pass = resources.histogram.as_ref().ok_or(CaptureError::NotInitialized)?;
+        pass.run(&resources.device, &resources.context, &texture)
+    }
+    
+    /// Menor e maior valor de cada canal RGB da região, por uma redução na GPU
+    pub fn color_range(&mut self, region: Rect) -> CaptureResult<ColorRange> {
+        let texture = self.capture_texture(region)?;
+        let resources = self.resources()?;
+        
+        if resources.color_range.is_none() {
+            resources.color_range = Some(ColorRangePass::new(&resources.device)?);
+        }
+        let pass = resources.color_range.as_ref().ok_or(CaptureError::NotInitialized)?;
+        pass.run(&resources.device, &resources.context, &texture)
+    }
+    
+    /// A região está toda preta ou de uma única cor (tolerância `gpu_stats::BLANK_TOLERANCE`).
+    ///
+    /// Indica conteúdo protegido (DRM), monitor em repouso ou falha de captura; gravadores
+    /// podem usar isto para alertar o usuário em vez de gravar quadros vazios.
+    pub fn is_blank(&mut self, region: Rect) -> CaptureResult<bool> {
+        Ok(self.color_range(region)?.is_uniform(gpu_stats::BLANK_TOLERANCE))
+    }
+    
+    /// Procura `needle` na região por correlação cruzada normalizada e retorna as posições com
+    /// correlação `>= threshold` (ex.: 0.9), da melhor para a pior, em coordenadas do monitor.
+    ///
+    /// Em desktops de 8 bits e referências de até 64x64 pixels a busca roda em um compute
+    /// shader e só as pontuações são lidas; nos demais casos a região é capturada e a busca
+    /// feita na CPU (`template_match::find_in_frame`).
+    pub fn find_image(&mut self, needle: &Frame, region: Rect, threshold: f32) -> CaptureResult<Vec<ImageMatch>> {
+        let template = Needle::from_frame(needle)?;
+        template.check_fits(region.width as usize, region.height as usize)?;
+        
+        let eight_bit = matches!(pixel_format::layout_of(self.chosen_format), PixelFormat::Bgra8 | PixelFormat::Rgba8);
+        let use_gpu = eight_bit
+            && template.pixels() <= GPU_MAX_TEMPLATE_PIXELS
+            && Self::ensure_template_pass(self.resources()?).is_some();
+        let matches = if use_gpu {
+            let texture = self.capture_texture(region)?;
+            let resources = self.resources()?;
+            let pass = resources.template.as_mut().and_then(Option::as_mut).ok_or(CaptureError::NotInitialized)?;
+            let size = (region.width as usize, region.height as usize);
+            pass.run(&resources.device, &resources.context, &texture, size, &template, threshold)?
+        } else {
+            let frame = self.capture_frame(region)?;
+            template_match::search(&Gray::from_frame(&frame)?, &template, threshold)
+        };
+        
+        Ok(matches
+            .into_iter()
+            .map(|found| ImageMatch {
+                rect: Rect::new(found.rect.left + region.left, found.rect.top + region.top, found.rect.width, found.rect.height),
+                ..found
+            })
+            .collect())
+    }
+    
+    /// Compila o shader de busca no primeiro uso; uma falha é registrada e a busca fica na CPU
+    fn ensure_template_pass(resources: &mut Resources) -> Option<&mut TemplatePass> {
+        if resources.template.is_none() {
+            resources.template = Some(match TemplatePass::new(&resources.device) {
+                Ok(pass) => Some(pass),
+                Err(e) => {
+                    warn!("Busca de imagens na GPU indisponível, usando a CPU: {}", e);
+                    None
+                }
+            });
+        }
+        resources.template.as_mut().and_then(Option::as_mut)
+    }
+    
+    /// Recorta a região, escala para `output_size` e converte para `format` (ex.: NV12) em uma
+    /// única passada do video processor, lendo direto da textura do desktop.
+    ///
+    /// Nenhuma cópia intermediária da região é feita; a textura retornada pertence a um pool
+    /// circular e continua válida até a próxima chamada com os mesmos parâmetros.
+    pub fn capture_converted_texture(&mut self, region: Rect, output_size: Size, format: DXGI_FORMAT) -> CaptureResult<ID3D11Texture2D> {
+        let mut textures = self.capture_outputs_texture(region, &[OutputSpec::new(output_size, format)])?;
+        textures.pop().ok_or(CaptureError::NotInitialized)
+    }
+    
+    /// Como `capture_converted_texture`, lendo o resultado para a CPU.
+    ///
+    /// Em formatos planares (NV12, P010) o buffer traz o plano Y seguido do plano UV.
+    /// É o caminho para 4K→1080p NV12 a 60 fps: só os bytes finais atravessam o barramento.
+    pub fn capture_converted(&mut self, region: Rect, output_size: Size, format: DXGI_FORMAT) -> CaptureResult<Vec<u8>> {
+        let mut buffers = self.capture_outputs(region, &[OutputSpec::new(output_size, format)])?;
+        buffers.pop().ok_or(CaptureError::NotInitialized)
+    }
+    
+    /// Produz várias saídas da mesma região (ex.: NV12 em resolução cheia para o encoder e uma
+    /// miniatura RGBA para a interface) a partir de uma única aquisição do quadro.
+    ///
+    /// Cada saída é uma passada do video processor lendo direto da textura do desktop, todas
+    /// enviadas à GPU em sequência, sem cópia intermediária da região nem capturas repetidas.
+    /// As texturas seguem as regras de `capture_converted_texture`, na ordem de `outputs`; até
+    /// quatro combinações de tamanho e formato ficam em cache sem recriar o video processor.
+    pub fn capture_outputs_texture(&mut self, region: Rect, outputs: &[OutputSpec]) -> CaptureResult<Vec<ID3D11Texture2D>> {
+        for output in outputs {
+            if output.size.is_empty() {
+                return Err(RectError::Empty.into());
+            }
+        }
+        self.prepare_capture(region)?;
+        let frame = self.acquire_frame()?;
+        
+        region.validate_within(self.output_size())?;
+        let desktop_size = self.output_size();
+        let no_frame_policy = self.config.no_frame_policy;
+        let resources = self.resources()?;
+        
+        let mut textures = Vec::with_capacity(outputs.len());
+        for output in outputs {
+            let converter = resources.converter(desktop_size, output.size, output.format)?;
+            let texture = match frame.texture() {
+                Some(acquired_texture) => {
+                    converter.converter.set_fit(output.fit);
+                    let texture = converter
+                        .converter
+                        .convert_rect(acquired_texture, Some(region))
+                        .map_err(|e| CaptureError::Conversion(e.to_string()))?;
+                    converter.last_output = Some((texture.clone(), region, output.fit));
+                    texture
+                }
+                None if no_frame_policy == NoFramePolicy::NoChange => return Err(CaptureError::NoNewFrame),
+                // A última saída do pool ainda guarda a conversão anterior
+                None => match &converter.last_output {
+                    Some((texture, last_region, fit)) if *last_region == region && *fit == output.fit => texture.clone(),
+                    _ => return Err(CaptureError::NoNewFrame),
+                },
+            };
+            textures.push(texture);
+        }
+        drop(frame);
+        
+        Ok(textures)
+    }
+    
+    /// Como `capture_outputs_texture`, lendo cada saída para a CPU; as cópias para as stagings
+    /// são todas enviadas antes da primeira leitura
+    pub fn capture_outputs(&mut self, region: Rect, outputs: &[OutputSpec]) -> CaptureResult<Vec<Vec<u8>>> {
+        let no_frame_policy = self.config.no_frame_policy;
+        
+        let textures = match self.capture_outputs_texture(region, outputs) {
+            Ok(textures) => textures,
+            Err(CaptureError::NoNewFrame) if no_frame_policy == NoFramePolicy::ZeroFill => {
+                return Ok(outputs
+                    .iter()
+                    .map(|output| {
+                        let (row_bytes, rows) = convert::frame_layout(output.format, output.size.width, output.size.height);
+                        vec![0u8; row_bytes * rows]
+                    })
+                    .collect());
+            }
+            Err(e) => return Err(e),
+        };
+        
+        let resources = self.resources()?;
+        let mut stagings = Vec::with_capacity(outputs.len());
+        for (index, (output, texture)) in outputs.iter().zip(&textures).enumerate() {
+            let staging = resources.converted_staging(index, output.format, output.size)?;
+            unsafe {
+                resources.context.CopyResource(&staging, texture);
+            }
+            stagings.push(staging);
+        }
+        
+        outputs
+            .iter()
+            .zip(&stagings)
+            .map(|(output, staging)| resources.read_texture(staging, output.format, output.size.width, output.size.height))
+            .collect()
+    }
+    
+    /// Captura a região e a escala na GPU para `output_size` com `filter`, sem passar pela CPU.
+    ///
+    /// Para lupas: `ScaleFilter::Nearest` amplia com pixels nítidos e `Bicubic` suaviza sem
+    /// borrar como o video processor. A textura é R8G8B8A8_UNORM (capturas de 8 bits) ou
+    /// R16G16B16A16_FLOAT e é reutilizada na próxima chamada. A região passa por
+    /// `capture_texture`, e portanto segue a `NoFramePolicy` dela.
+    pub fn capture_scaled_texture(&mut self, region: Rect, output_size: Size, filter: ScaleFilter) -> CaptureResult<ID3D11Texture2D> {
+        self.scale_region(region, output_size, filter, false)
+    }
+    
+    /// Como `capture_scaled_texture`, lendo a imagem final para a CPU no formato da captura
+    pub fn capture_scaled(&mut self, region: Rect, output_size: Size, filter: ScaleFilter) -> CaptureResult<Vec<u8>> {
+        let format = self.chosen_format;
+        // BGRA é gravado com vermelho e azul trocados na textura RGBA, já na ordem de bytes final
+        let scaled = self.scale_region(region, output_size, filter, format == DXGI_FORMAT_B8G8R8A8_UNORM)?;
+        
+        let resources = self.resources()?;
+        let staging = resources.scaled_staging(scaled_format(format), output_size)?;
+        unsafe {
+            resources.context.CopyResource(&staging, &scaled);
+        }
+        
+        resources.read_texture(&staging, format, output_size.width, output_size.height)
+    }
+    
+    fn scale_region(&mut self, region: Rect, output_size: Size, filter: ScaleFilter, swap_rb: bool) -> CaptureResult<ID3D11Texture2D> {
+        if output_size.is_empty() {
+            return Err(RectError::Empty.into());
+        }
+        
+        let input = self.capture_texture(region)?;
+        let format = scaled_format(self.chosen_format);
+        let resources = self.resources()?;
+        let output = resources.scaled_texture(format, output_size)?;
+        
+        let pass = resources.scale.get_or_insert_with(ScalePass::default);
+        pass.run(&resources.device, &resources.context, filter, swap_rb, &input, &output)?;
+        Ok(output)
+    }
+}
+
+impl Resources {
+    /// Repassa ao log as mensagens acumuladas pela camada de depuração e esvazia a fila
+    fn forward_debug_messages(&self) {
+        let Some(queue) = self.info_queue.as_ref() else {
+            return;
+        };
+        
+        unsafe {
+            for index in 0..queue.GetNumStoredMessages() {
+                let mut length = 0usize;
+                if queue.GetMessage(index, None, &mut length).is_err() || length == 0 {
+                    continue;
+                }
+                // Buffer alinhado para a estrutura, seguida da descrição
+                let mut buffer = vec![0u64; length.div_ceil(8)];
+                let message = buffer.as_mut_ptr() as *mut D3D11_MESSAGE;
+                if queue.GetMessage(index, Some(message), &mut length).is_err() || (*message).pDescription.is_null() {
+                    continue;
+                }
+                let message = &*message;
+                let description = slice::from_raw_parts(message.pDescription, message.DescriptionByteLength);
+                let text = String::from_utf8_lossy(description);
+                let text = text.trim_end_matches('\0');
+                match message.Severity {
+                    D3D11_MESSAGE_SEVERITY_CORRUPTION | D3D11_MESSAGE_SEVERITY_ERROR => error!("D3D11: {}", text),
+                    D3D11_MESSAGE_SEVERITY_WARNING => warn!("D3D11: {}", text),
+                    _ => debug!("D3D11: {}", text),
+                }
+            }
+            queue.ClearStoredMessages();
+        }
+    }
+    
+    /// Staging para a ROI, reaproveitando a do mesmo tamanho e formato se estiver em cache;
+    /// retorna também o índice em `roi_textures`
+    fn roi_texture(&mut self, format: DXGI_FORMAT, width: u32, height: u32) -> CaptureResult<(usize, ID3D11Texture2D)> {
+        let desc = D3D11_TEXTURE2D_DESC {
+            Width: width,
+            Height: height,
+            MipLevels: 1,
+            ArraySize: 1,
+            Format: format,
+            SampleDesc: DXGI_SAMPLE_DESC {
+                Count: 1,
+                Quality: 0,
+            },
+            Usage: D3D11_USAGE_STAGING,
+            BindFlags: 0,
+            CPUAccessFlags: D3D11_CPU_ACCESS_READ.0 as u32,
+            MiscFlags: 0,
+        };
+        
+        let matching = self.roi_textures.iter().position(|slot| slot.as_ref().is_some_and(|cached| cached.matches(&desc)));
+        let slot = match matching.or_else(|| self.roi_textures.iter().position(Option::is_none)) {
+            Some(slot) => slot,
+            None if self.roi_textures.len() < ROI_CACHE_SIZE => {
+                self.roi_textures.push(None);
+                self.roi_textures.len() - 1
+            }
+            // Cache cheio: substituir a menos usada recentemente
+            None => self
+                .roi_textures
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, slot)| slot.as_ref().map(|cached| cached.last_used))
+                .map_or(0, |(index, _)| index),
+        };
+        
+        let texture = Self::ensure_texture(&self.device, &mut self.roi_textures[slot], &desc)?;
+        Ok((slot, texture))
+    }
+    
+    /// Staging para ler a saída de posição `index` de `capture_outputs`
+    fn converted_staging(&mut self, index: usize, format: DXGI_FORMAT, size: Size) -> CaptureResult<ID3D11Texture2D> {
+        let desc = D3D11_TEXTURE2D_DESC {
+            Width: size.width,
+            Height: size.height,
+            MipLevels: 1,
+            ArraySize: 1,
+            Format: format,
+            SampleDesc: DXGI_SAMPLE_DESC {
+                Count: 1,
+                Quality: 0,
+            },
+            Usage: D3D11_USAGE_STAGING,
+            BindFlags: 0,
+            CPUAccessFlags: D3D11_CPU_ACCESS_READ.0 as u32,
+            MiscFlags: 0,
+        };
+        
+        if self.converted_staging.len() <= index {
+            self.converted_staging.resize_with(index + 1, || None);
+        }
+        Self::ensure_texture(&self.device, &mut self.converted_staging[index], &desc)
+    }
+    
+    /// Video processor para a conversão pedida, reaproveitado do cache se já existir
+    fn converter(&mut self, input: Size, output: Size, format: DXGI_FORMAT) -> CaptureResult<&mut CachedConverter> {
+        let cached = self
+            .converters
+            .iter()
+            .position(|cached| cached.input == input && cached.output == output && cached.format == format);
+        
+        let index = match cached {
+            Some(index) => index,
+            None => {
+                if self.converters.len() >= CONVERTER_CACHE_SIZE {
+                    let oldest = (0..self.converters.len()).min_by_key(|&i| self.converters[i].last_used).unwrap_or(0);
+                    self.converters.swap_remove(oldest);
+                }
+                let converter = VideoConverter::new(&self.device, &self.context, input, output, format, CONVERTER_POOL_SIZE)
+                    .map_err(|e| CaptureError::Conversion(e.to_string()))?;
+                debug!("Video processor criado: {}x{} -> {}x{} {:?}", input.width, input.height, output.width, output.height, format);
+                self.converters.push(CachedConverter {
+                    converter,
+                    input,
+                    output,
+                    format,
+                    last_output: None,
+                    last_used: Instant::now(),
+                });
+                self.converters.len() - 1
+            }
+        };
+        
+        let cached = &mut self.converters[index];
+        cached.last_used = Instant::now();
+        Ok(cached)
+    }
+    
+    /// Textura padrão, utilizável como entrada de shaders e do video processor
+    fn gpu_texture(&mut self, format: DXGI_FORMAT, width: u32, height: u32) -> CaptureResult<ID3D11Texture2D> {
+        let desc = D3D11_TEXTURE2D_DESC {
+            Width: width,
+            Height: height,
+            MipLevels: 1,
+            ArraySize: 1,
+            Format: format,
+            SampleDesc: DXGI_SAMPLE_DESC {
+                Count: 1,
+                Quality: 0,
+            },
+            Usage: D3D11_USAGE_DEFAULT,
+            BindFlags: (D3D11_BIND_SHADER_RESOURCE.0 | D3D11_BIND_RENDER_TARGET.0) as u32,
+            CPUAccessFlags: 0,
+            MiscFlags: 0,
+        };
+        
+        Self::ensure_texture(&self.device, &mut self.gpu_texture, &desc)
+    }
+    
+    /// Saída da escala com filtro, gravável por compute shader
+    fn scaled_texture(&mut self, format: DXGI_FORMAT, size: Size) -> CaptureResult<ID3D11Texture2D> {
+        let desc = D3D11_TEXTURE2D_DESC {
+            Width: size.width,
+            Height: size.height,
+            MipLevels: 1,
+            ArraySize: 1,
+            Format: format,
+            SampleDesc: DXGI_SAMPLE_DESC {
+                Count: 1,
+                Quality: 0,
+            },
+            Usage: D3D11_USAGE_DEFAULT,
+            BindFlags: (D3D11_BIND_SHADER_RESOURCE.0 | D3D11_BIND_UNORDERED_ACCESS.0) as u32,
+            CPUAccessFlags: 0,
+            MiscFlags: 0,
+        };
+        
+        Self::ensure_texture(&self.device, &mut self.scaled_texture, &desc)
+    }
+    
+    /// Staging para ler a saída da escala
+    fn scaled_staging(&mut self, format: DXGI_FORMAT, size: Size) -> CaptureResult<ID3D11Texture2D> {
+        let desc = D3D11_TEXTURE2D_DESC {
+            Width: size.width,
+            Height: size.height,
+            MipLevels: 1,
+            ArraySize: 1,
+            Format: format,
+            SampleDesc: DXGI_SAMPLE_DESC {
+                Count: 1,
+                Quality: 0,
+            },
+            Usage: D3D11_USAGE_STAGING,
+            BindFlags: 0,
+            CPUAccessFlags: D3D11_CPU_ACCESS_READ.0 as u32,
+            MiscFlags: 0,
+        };
+        
+        Self::ensure_texture(&self.device, &mut self.scaled_staging, &desc)
+    }
+    
+    /// Staging de `capture_patches`, com as regiões lado a lado
+    fn patch_staging(&mut self, format: DXGI_FORMAT, width: u32, height: u32) -> CaptureResult<ID3D11Texture2D> {
+        let desc = D3D11_TEXTURE2D_DESC {
+            Width: width,
+            Height: height,
+            MipLevels: 1,
+            ArraySize: 1,
+            Format: format,
+            SampleDesc: DXGI_SAMPLE_DESC {
+                Count: 1,
+                Quality: 0,
+            },
+            Usage: D3D11_USAGE_STAGING,
+            BindFlags: 0,
+            CPUAccessFlags: D3D11_CPU_ACCESS_READ.0 as u32,
+            MiscFlags: 0,
+        };
+        
+        Self::ensure_texture(&self.device, &mut self.patch_staging, &desc)
+    }
+    
+    /// Textura com a cadeia de mips completa da ROI, preenchível por `GenerateMips`
+    fn mip_texture(&mut self, format: DXGI_FORMAT, width: u32, height: u32) -> CaptureResult<ID3D11Texture2D> {
+        let desc = D3D11_TEXTURE2D_DESC {
+            Width: width,
+            Height: height,
+            MipLevels: 0,
+            ArraySize: 1,
+            Format: format,
+            SampleDesc: DXGI_SAMPLE_DESC {
+                Count: 1,
+                Quality: 0,
+            },
+            Usage: D3D11_USAGE_DEFAULT,
+            BindFlags: (D3D11_BIND_SHADER_RESOURCE.0 | D3D11_BIND_RENDER_TARGET.0) as u32,
+            CPUAccessFlags: 0,
+            MiscFlags: D3D11_RESOURCE_MISC_GENERATE_MIPS.0 as u32,
+        };
+        
+        Self::ensure_texture(&self.device, &mut self.mip_texture, &desc)
+    }
+    
+    /// Shader resource view de `texture`, recriada quando a textura de mips é recriada
+    fn mip_view(&mut self, texture: &ID3D11Texture2D) -> CaptureResult<ID3D11ShaderResourceView> {
+        if let Some((cached, view)) = &self.mip_view {
+            if cached == texture {
+                return Ok(view.clone());
+            }
+        }
+        
+        let mut view: Option<ID3D11ShaderResourceView> = None;
+        unsafe {
+            self.device.CreateShaderResourceView(texture, None, Some(&mut view))?;
+        }
+        let view = view.ok_or(CaptureError::Windows(windows::core::Error::from(E_FAIL)))?;
+        
+        self.mip_view = Some((texture.clone(), view.clone()));
+        Ok(view)
+    }
+    
+    /// Staging do tamanho de um nível de mip, uma por nível para não recriar ao alternar
+    fn mip_staging(&mut self, level: u32, format: DXGI_FORMAT, size: Size) -> CaptureResult<ID3D11Texture2D> {
+        let desc = D3D11_TEXTURE2D_DESC {
+            Width: size.width,
+            Height: size.height,
+            MipLevels: 1,
+            ArraySize: 1,
+            Format: format,
+            SampleDesc: DXGI_SAMPLE_DESC {
+                Count: 1,
+                Quality: 0,
+            },
+            Usage: D3D11_USAGE_STAGING,
+            BindFlags: 0,
+            CPUAccessFlags: D3D11_CPU_ACCESS_READ.0 as u32,
+            MiscFlags: 0,
+        };
+        
+        let index = level as usize;
+        if self.mip_staging.len() <= index {
+            self.mip_staging.resize_with(index + 1, || None);
+        }
+        Self::ensure_texture(&self.device, &mut self.mip_staging[index], &desc)
+    }
+    
+    /// Todos os slots de textura intermediária
+    fn texture_slots(&mut self) -> impl Iterator<Item = &mut Option<CachedTexture>> {
+        [
+            &mut self.gpu_texture,
+            &mut self.mip_texture,
+            &mut self.scaled_texture,
+            &mut self.scaled_staging,
+            &mut self.patch_staging,
+        ]
+        .into_iter()
+        .chain(self.roi_textures.iter_mut())
+        .chain(self.mip_staging.iter_mut())
+        .chain(self.converted_staging.iter_mut())
+    }
+    
+    fn cached_bytes(&self) -> u64 {
+        [
+            &self.gpu_texture,
+            &self.mip_texture,
+            &self.scaled_texture,
+            &self.scaled_staging,
+            &self.patch_staging,
+        ]
+        .into_iter()
+        .chain(self.roi_textures.iter())
+        .chain(self.mip_staging.iter())
+        .chain(self.converted_staging.iter())
+        .flatten()
+        .map(|cached| cached.bytes)
+        .sum()
+    }
+    
+    /// Descarta todas as texturas intermediárias e o video processor
+    fn trim(&mut self) {
+        self.texture_slots().for_each(|slot| *slot = None);
+        self.roi_textures.clear();
+        self.mip_staging.clear();
+        self.converted_staging.clear();
+        self.mip_view = None;
+        self.converters.clear();
+        self.system_memory_copy = None;
+        #[cfg(feature = "onnx")]
+        {
+            self.tensor = None;
+        }
+    }
+    
+    /// Descarta as texturas não usadas há mais de `max_idle`
+    fn release_idle(&mut self, max_idle: Duration) {
+        for slot in self.texture_slots() {
+            if slot.as_ref().is_some_and(|cached| cached.last_used.elapsed() > max_idle) {
+                *slot = None;
+            }
+        }
+        
+        // A view e o video processor mantêm suas texturas vivas
+        if self.mip_texture.is_none() {
+            self.mip_view = None;
+        }
+        self.converters.retain(|cached| cached.last_used.elapsed() <= max_idle);
+    }
+    
+    /// Reutiliza a textura em cache se tiver o tamanho de `desc`; caso contrário a recria
+    fn ensure_texture(device: &ID3D11Device, cache: &mut Option<CachedTexture>, desc: &D3D11_TEXTURE2D_DESC) -> CaptureResult<ID3D11Texture2D> {
+        if let Some(cached) = cache {
+            if cached.matches(desc) {
+                cached.last_used = Instant::now();
+                return Ok(cached.texture.clone());
+            }
+        }
+        
+        // Limpar textura antiga antes de alocar a nova
+        *cache = None;
+        
+        let mut texture: Option<ID3D11Texture2D> = None;
+        unsafe {
+            device.CreateTexture2D(desc, None, Some(&mut texture))?;
+        }
+        let texture = texture.ok_or(CaptureError::Windows(windows::core::Error::from(E_FAIL)))?;
+        
+        let (row_bytes, rows) = convert::frame_layout(desc.Format, desc.Width, desc.Height);
+        let mut bytes = (row_bytes * rows) as u64;
+        if desc.MipLevels != 1 {
+            // A cadeia completa soma cerca de 1/3 a mais que o nível 0
+            bytes += bytes / 3;
+        }
+        
+        *cache = Some(CachedTexture {
+            texture: texture.clone(),
+            width: desc.Width,
+            height: desc.Height,
+            format: desc.Format,
+            bytes,
+            last_used: Instant::now(),
+            contents: None,
+        });
+        
+        Ok(texture)
+    }
+    
+    /// Lê uma textura de staging para um buffer sem padding entre linhas
+    fn read_texture(&self, texture: &ID3D11Texture2D, format: DXGI_FORMAT, width: u32, height: u32) -> CaptureResult<Vec<u8>> {
+        let mapped = MappedTexture::read(&self.context, texture)?;
+        let mapped_resource = mapped.resource;
+        
+        let row_pitch = mapped_resource.RowPitch as usize;
+        let (row_bytes, rows) = convert::frame_layout(format, width, height);
+        convert::check_pitch(row_pitch, row_bytes)?;
+        let mut buffer = vec![0u8; rows * row_bytes];
+        
+        unsafe {
+            if !mapped_resource.pData.is_null() && rows > 0 {
+                let src_len = row_pitch * (rows - 1) + row_bytes;
+                let src = slice::from_raw_parts(mapped_resource.pData as *const u8, src_len);
+                convert::copy_rows(src, row_pitch, &mut buffer, row_bytes);
+            }
+        }
+        
+        Ok(buffer)
+    }
+    
+    /// Copia a região do quadro adquirido para `destination`
+    fn copy_region(&self, acquired_texture: &ID3D11Texture2D, destination: &ID3D11Texture2D, region: Rect) {
+        record_copy(&self.context, acquired_texture, destination, region);
+    }
+    
+    /// Grava as cópias em um contexto diferido e as envia em uma única lista de comandos
+    fn copy_regions(&mut self, acquired_texture: &ID3D11Texture2D, copies: &[(&ID3D11Texture2D, Rect)]) -> CaptureResult<()> {
+        let deferred = match &self.deferred {
+            Some(deferred) => deferred.clone(),
+            None => {
+                let mut deferred = None;
+                if let Err(e) = unsafe { self.device.CreateDeferredContext(0, Some(&mut deferred)) } {
+                    debug!("Contexto diferido indisponível, usando o imediato: {}", e);
+                }
+                self.deferred = Some(deferred.clone());
+                deferred
+            }
+        };
+        
+        let Some(deferred) = deferred else {
+            for (destination, region) in copies {
+                self.copy_region(acquired_texture, destination, *region);
+            }
+            return Ok(());
+        };
+        
+        for (destination, region) in copies {
+            record_copy(&deferred, acquired_texture, destination, *region);
+        }
+        
+        let mut command_list: Option<ID3D11CommandList> = None;
+        unsafe {
+            deferred.FinishCommandList(false, Some(&mut command_list))?;
+            if let Some(command_list) = command_list {
+                self.context.ExecuteCommandList(&command_list, false);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Resources {
+    /// Deixa a duplicação livre para a próxima instância mesmo se a captura foi abandonada no
+    /// meio (pânico entre `AcquireNextFrame` e a cópia, thread encerrada)
+    fn drop(&mut self) {
+        unsafe {
+            // Sem quadro adquirido retorna DXGI_ERROR_INVALID_CALL, que é inofensivo
+            let _ = self.duplication.ReleaseFrame();
+            
+            // Desfaz os binds dos shaders e envia as cópias pendentes antes de soltar o dispositivo
+            self.context.ClearState();
+            self.context.Flush();
+        }
+        self.forward_debug_messages();
+        debug!("Recursos da duplicação liberados");
+    }
+}
+
+/// Copia `region` da textura do desktop para o canto superior esquerdo de `destination`
+fn record_copy(context: &ID3D11DeviceContext, acquired_texture: &ID3D11Texture2D, destination: &ID3D11Texture2D, region: Rect) {
+    record_copy_at(context, acquired_texture, destination, region, 0);
+}
+
+/// Como `record_copy`, gravando a região a partir da coluna `x` do destino
+fn record_copy_at(context: &ID3D11DeviceContext, acquired_texture: &ID3D11Texture2D, destination: &ID3D11Texture2D, region: Rect, x: u32) {
+    // A região já foi validada por `prepare_capture`, então cabe em u32
+    let src_box = D3D11_BOX {
+        left: region.left as u32,
+        top: region.top as u32,
+        front: 0,
+        right: region.right() as u32,
+        bottom: region.bottom() as u32,
+        back: 1,
+    };
+    
+    unsafe {
+        context.CopySubresourceRegion(
+            destination,
+            0,
+            x,
+            0,
+            0,
+            acquired_texture,
+            0,
+            Some(&src_box),
+        );
+    }
+}
+
+/// Formato da saída da escala: B8G8R8A8 não tem escrita por UAV garantida no D3D11
+fn scaled_format(format: DXGI_FORMAT) -> DXGI_FORMAT {
+    match format {
+        DXGI_FORMAT_R16G16B16A16_FLOAT => DXGI_FORMAT_R16G16B16A16_FLOAT,
+        _ => DXGI_FORMAT_R8G8B8A8_UNORM,
+    }
+}
+
+/// Número de níveis da cadeia de mips completa de uma textura de `size`
+fn mip_count(size: Size) -> u32 {
+    32 - size.width.max(size.height).max(1).leading_zeros()
+}
+
+/// Tamanho de um nível de mip (nunca menor que 1x1)
+fn mip_size(size: Size, level: u32) -> Size {
+    Size::new((size.width >> level).max(1), (size.height >> level).max(1))
+}
+
+/// Destinos dos retângulos movidos e retângulos alterados do quadro adquirido
+fn frame_changes(duplication: &IDXGIOutputDuplication, buffer_size: u32) -> (Option<Vec<Rect>>, Option<Vec<Rect>>) {
+    let to_rect = |rect: &RECT| Rect::from_edges(rect.left, rect.top, rect.right, rect.bottom).ok();
+    let mut used = 0u32;
+    
+    let mut moves = vec![DXGI_OUTDUPL_MOVE_RECT::default(); buffer_size as usize / std::mem::size_of::<DXGI_OUTDUPL_MOVE_RECT>() + 1];
+    let moves = unsafe { duplication.GetFrameMoveRects(buffer_size, moves.as_mut_ptr(), &mut used) }.ok().map(|_| {
+        let count = used as usize / std::mem::size_of::<DXGI_OUTDUPL_MOVE_RECT>();
+        moves[..count].iter().filter_map(|moved| to_rect(&moved.DestinationRect)).collect::<Vec<_>>()
+    });
+    
+    let mut dirty = vec![RECT::default(); buffer_size as usize / std::mem::size_of::<RECT>() + 1];
+    let dirty = unsafe { duplication.GetFrameDirtyRects(buffer_size, dirty.as_mut_ptr(), &mut used) }.ok().map(|_| {
+        let count = used as usize / std::mem::size_of::<RECT>();
+        dirty[..count].iter().filter_map(to_rect).collect::<Vec<_>>()
+    });
+    
+    (moves, dirty)
+}
+
+/// Forma do cursor do quadro adquirido
+fn pointer_shape(duplication: &IDXGIOutputDuplication, buffer_size: u32) -> CaptureResult<CursorShape> {
+    let mut data = vec![0u8; buffer_size as usize];
+    let mut required = 0u32;
+    let mut info = DXGI_OUTDUPL_POINTER_SHAPE_INFO::default();
+    unsafe {
+        duplication.GetFramePointerShape(buffer_size, data.as_mut_ptr().cast(), &mut required, &mut info)?;
+    }
+    data.truncate(required as usize);
+    
+    let kind = match DXGI_OUTDUPL_POINTER_SHAPE_TYPE(info.Type as i32) {
+        DXGI_OUTDUPL_POINTER_SHAPE_TYPE_MONOCHROME => CursorShapeKind::Monochrome,
+        DXGI_OUTDUPL_POINTER_SHAPE_TYPE_MASKED_COLOR => CursorShapeKind::MaskedColor,
+        _ => CursorShapeKind::Color,
+    };
+    Ok(CursorShape {
+        kind,
+        width: info.Width,
+        height: info.Height,
+        pitch: info.Pitch,
+        hotspot: Point::new(info.HotSpot.x, info.HotSpot.y),
+        data,
+    })
+}
+
+/// Liberação do quadro adquirido, separada da `IDXGIOutputDuplication` para que os testes
+/// contem as liberações com uma duplicação falsa
+trait FrameRelease {
+    fn release_frame(&self);
+}
+
+impl FrameRelease for IDXGIOutputDuplication {
+    fn release_frame(&self) {
+        unsafe {
+            let _ = self.ReleaseFrame();
+        }
+    }
+}
+
+/// Quadro adquirido da duplicação; chama `ReleaseFrame` ao sair de escopo
+struct AcquiredFrame<D: FrameRelease = IDXGIOutputDuplication> {
+    duplication: D,
+    texture: Option<ID3D11Texture2D>,
+}
+
+impl<D: FrameRelease> AcquiredFrame<D> {
+    /// Textura do desktop, ou `None` quando a duplicação só atualizou metadados (ex.: cursor)
+    fn texture(&self) -> Option<&ID3D11Texture2D> {
+        self.texture.as_ref()
+    }
+}
+
+impl<D: FrameRelease> Drop for AcquiredFrame<D> {
+    fn drop(&mut self) {
+        self.duplication.release_frame();
+    }
+}
+
+/// Destino de um quadro recém-adquirido
+enum FrameDecision<D: FrameRelease> {
+    /// Pronto para a cópia
+    Ready(AcquiredFrame<D>),
+    /// Descartado (e já liberado); aguardar um present novo por até este timeout em ms
+    Wait(u32),
+}
+
+/// Entrega o quadro com a textura do desktop, ou o descarta quando a imagem passou da idade
+/// máxima (`expired` traz a idade atual e o limite). Em todo retorno sem `Ready`, inclusive
+/// nos erros, o quadro é liberado pelo drop do guard.
+fn accept_frame<D: FrameRelease>(
+    mut frame: AcquiredFrame<D>,
+    resource: Option<IDXGIResource>,
+    expired: Option<(Option<Duration>, Duration)>,
+    stale_deadline: &mut Option<Instant>,
+) -> CaptureResult<FrameDecision<D>> {
+    if let Some((age, max_age)) = expired {
+        let deadline = *stale_deadline.get_or_insert_with(|| Instant::now() + max_age);
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(CaptureError::StaleFrame { age });
+        }
+        return Ok(FrameDecision::Wait(remaining.as_millis().max(1) as u32));
+    }
+    
+    if let Some(resource) = resource {
+        frame.texture = Some(resource.cast::<ID3D11Texture2D>()?);
+    }
+    Ok(FrameDecision::Ready(frame))
+}
+
+/// Lê a região da imagem do desktop em memória do sistema, aplicando a `NoFramePolicy` quando
+/// a duplicação não entregou imagem nova
+fn read_system_memory(
+    resources: &mut Resources,
+    frame: &AcquiredFrame,
+    format: DXGI_FORMAT,
+    region: Rect,
+    no_frame_policy: NoFramePolicy,
+) -> CaptureResult<Vec<u8>> {
+    if frame.texture().is_none() {
+        return match no_frame_policy {
+            NoFramePolicy::ZeroFill => {
+                let (row_bytes, rows) = convert::frame_layout(format, region.width, region.height);
+                Ok(vec![0u8; row_bytes * rows])
+            }
+            NoFramePolicy::NoChange => Err(CaptureError::NoNewFrame),
+            NoFramePolicy::LastFrame => match &resources.system_memory_copy {
+                Some((last_region, data)) if *last_region == region => Ok(data.clone()),
+                _ => Err(CaptureError::NoNewFrame),
+            },
+        };
+    }
+    
+    let mapped = MappedDesktop::map(&frame.duplication)?;
+    let (pixel_bytes, _) = convert::frame_layout(format, 1, 1);
+    let (row_bytes, rows) = convert::frame_layout(format, region.width, region.height);
+    let pitch = mapped.rect.Pitch as usize;
+    convert::check_pitch(pitch, row_bytes)?;
+    let mut buffer = vec![0u8; rows * row_bytes];
+    
+    unsafe {
+        if !mapped.rect.pBits.is_null() && rows > 0 {
+            let offset = region.top as usize * pitch + region.left as usize * pixel_bytes;
+            let src = slice::from_raw_parts(mapped.rect.pBits.add(offset) as *const u8, pitch * (rows - 1) + row_bytes);
+            convert::copy_rows(src, pitch, &mut buffer, row_bytes);
+        }
+    }
+    drop(mapped);
+    
+    if no_frame_policy == NoFramePolicy::LastFrame {
+        resources.system_memory_copy = Some((region, buffer.clone()));
+    }
+    Ok(buffer)
+}
+
+/// Imagem do desktop mapeada por `MapDesktopSurface`; desfaz o mapeamento ao sair de escopo,
+/// antes do `ReleaseFrame` do quadro
+struct MappedDesktop<'a> {
+    duplication: &'a IDXGIOutputDuplication,
+    rect: DXGI_MAPPED_RECT,
+}
+
+impl<'a> MappedDesktop<'a> {
+    fn map(duplication: &'a IDXGIOutputDuplication) -> CaptureResult<Self> {
+        let rect = unsafe { duplication.MapDesktopSurface()? };
+        Ok(Self { duplication, rect })
+    }
+}
+
+impl Drop for MappedDesktop<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = self.duplication.UnMapDesktopSurface();
+        }
+    }
+}
+
+/// Subrecurso 0 de uma textura mapeado para leitura; chama `Unmap` ao sair de escopo,
+/// inclusive em pânico durante a cópia
+pub(crate) struct MappedTexture<'a> {
+    context: &'a ID3D11DeviceContext,
+    texture: &'a ID3D11Texture2D,
+    pub(crate) resource: D3D11_MAPPED_SUBRESOURCE,
+}
+
+impl<'a> MappedTexture<'a> {
+    pub(crate) fn read(context: &'a ID3D11DeviceContext, texture: &'a ID3D11Texture2D) -> CaptureResult<Self> {
+        let mut resource = D3D11_MAPPED_SUBRESOURCE::default();
+        unsafe { context.Map(texture, 0, D3D11_MAP_READ, 0, Some(&mut resource))? };
+        Ok(Self {
+            context,
+            texture,
+            resource,
+        })
+    }
+}
+
+impl Drop for MappedTexture<'_> {
+    fn drop(&mut self) {
+        unsafe { self.context.Unmap(self.texture, 0) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use windows::Win32::Graphics::Direct3D::D3D_DRIVER_TYPE_WARP;
+
+    /// Duplicação falsa que só conta as chamadas a `ReleaseFrame`
+    #[derive(Clone, Default)]
+    struct CountingDuplication(Rc<Cell<u32>>);
+
+    impl CountingDuplication {
+        fn acquire(&self) -> AcquiredFrame<CountingDuplication> {
+            AcquiredFrame {
+                duplication: self.clone(),
+                texture: None,
+            }
+        }
+
+        fn releases(&self) -> u32 {
+            self.0.get()
+        }
+    }
+
+    impl FrameRelease for CountingDuplication {
+        fn release_frame(&self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    /// Um buffer é um `IDXGIResource`, mas não uma `ID3D11Texture2D`
+    fn buffer_resource() -> IDXGIResource {
+        let mut device: Option<ID3D11Device> = None;
+        let desc = D3D11_BUFFER_DESC {
+            ByteWidth: 16,
+            Usage: D3D11_USAGE_DEFAULT,
+            BindFlags: D3D11_BIND_VERTEX_BUFFER.0 as u32,
+            CPUAccessFlags: 0,
+            MiscFlags: 0,
+            StructureByteStride: 0,
+        };
+        let mut buffer: Option<ID3D11Buffer> = None;
+        unsafe {
+            D3D11CreateDevice(
+                None,
+                D3D_DRIVER_TYPE_WARP,
+                None,
+                D3D11_CREATE_DEVICE_FLAG(0),
+                None,
+                D3D11_SDK_VERSION,
+                Some(&mut device),
+                None,
+                None,
+            )
+            .expect("dispositivo WARP");
+            device
+                .expect("dispositivo WARP")
+                .CreateBuffer(&desc, None, Some(&mut buffer))
+                .expect("buffer");
+        }
+        buffer.expect("buffer").cast().expect("IDXGIResource")
+    }
+
+    /// Capturador cuja reinicialização sempre falha: o adaptador não existe
+    fn capture_with_failing_reinit() -> DxgiCapture {
+        let config = CaptureConfig {
+            adapter_index: 99,
+            ..CaptureConfig::default()
+        };
+        let mut capture = DxgiCapture::uninitialized(config, CancellationToken::new());
+        (capture.output_width, capture.output_height) = (640, 480);
+        capture
+    }
+
+    #[test]
+    fn capture_after_a_failed_reinit_returns_an_error() {
+        let mut capture = capture_with_failing_reinit();
+        let region = Rect::new(0, 0, 64, 64);
+
+        assert!(matches!(capture.reinitialize(), Err(CaptureError::AdapterNotFound { adapter: 99 })));
+        assert!(capture.resources.is_none());
+        assert!(matches!(capture.capture_region(region), Err(CaptureError::AdapterNotFound { .. })));
+        // A falha não deixa estado parcial: a próxima captura tenta de novo e falha igual
+        assert!(matches!(capture.capture_frame(region), Err(CaptureError::AdapterNotFound { .. })));
+        assert!(capture.resources.is_none());
+    }
+
+    #[test]
+    fn ready_frame_is_released_when_dropped() {
+        let duplication = CountingDuplication::default();
+        let decision = accept_frame(duplication.acquire(), None, None, &mut None);
+
+        let Ok(FrameDecision::Ready(frame)) = decision else {
+            panic!("o quadro deveria estar pronto");
+        };
+        assert_eq!(duplication.releases(), 0);
+        drop(frame);
+        assert_eq!(duplication.releases(), 1);
+    }
+
+    #[test]
+    fn frame_is_released_when_waiting_for_a_newer_present() {
+        let duplication = CountingDuplication::default();
+        let mut deadline = None;
+        let expired = Some((None, Duration::from_secs(10)));
+        let decision = accept_frame(duplication.acquire(), None, expired, &mut deadline);
+
+        assert!(matches!(decision, Ok(FrameDecision::Wait(timeout)) if timeout > 0));
+        assert!(deadline.is_some());
+        assert_eq!(duplication.releases(), 1);
+    }
+
+    #[test]
+    fn frame_is_released_when_stale_past_the_deadline() {
+        let duplication = CountingDuplication::default();
+        let mut deadline = Some(Instant::now());
+        let expired = Some((Some(Duration::from_secs(5)), Duration::from_millis(100)));
+        let decision = accept_frame(duplication.acquire(), None, expired, &mut deadline);
+
+        assert!(matches!(decision, Err(CaptureError::StaleFrame { .. })));
+        assert_eq!(duplication.releases(), 1);
+    }
+
+    #[test]
+    fn frame_is_released_when_the_resource_is_not_a_texture() {
+        let duplication = CountingDuplication::default();
+        let decision = accept_frame(duplication.acquire(), Some(buffer_resource()), None, &mut None);
+
+        assert!(matches!(decision, Err(CaptureError::Windows(_))));
+        assert_eq!(duplication.releases(), 1);
+    }
+}
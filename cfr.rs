@@ -0,0 +1,139 @@
+//! Conversão da taxa variável da duplicação para uma taxa de quadros constante
+//!
+//! A duplicação só entrega quadros quando a tela muda, então a taxa varia de 0 a centenas de
+//! quadros por segundo. Muxers e plataformas que exigem CFR recebem aqui exatamente um
+//! quadro a cada `1/fps` segundos: cada posição mostra o último quadro apresentado até o
+//! seu instante, repetindo quadros em telas paradas e descartando os excedentes.
+
+use std::sync::Arc;
+
+use crate::frame::Frame;
+use crate::timecode::{Timecode, TICKS_PER_SECOND};
+use crate::Result;
+
+/// Quadro na posição `index` da sequência de taxa constante
+#[derive(Debug, Clone)]
+pub struct CfrFrame {
+    pub index: u64,
+    /// Instante da posição em unidades de 100 ns (`index / fps`)
+    pub timestamp: i64,
+    pub frame: Arc<Frame>,
+    /// O mesmo quadro já foi emitido em uma posição anterior
+    pub duplicate: bool,
+}
+
+/// Contadores de `CfrConformer`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CfrStats {
+    /// Quadros recebidos
+    pub received: u64,
+    /// Posições emitidas
+    pub emitted: u64,
+    /// Posições preenchidas repetindo um quadro
+    pub duplicated: u64,
+    /// Quadros substituídos por um mais novo antes de ocuparem alguma posição
+    pub dropped: u64,
+}
+
+/// Converte quadros de taxa variável (com present times) em uma sequência de `fps` exatos
+pub struct CfrConformer {
+    fps: u32,
+    timecode: Timecode,
+    next_index: u64,
+    // Último quadro recebido e se ele já ocupou alguma posição
+    current: Option<(Arc<Frame>, bool)>,
+    stats: CfrStats,
+}
+
+impl CfrConformer {
+    pub fn new(fps: u32) -> Result<Self> {
+        Ok(Self::with_timecode(fps, Timecode::new()?))
+    }
+
+    /// Usa uma linha do tempo existente (ex.: frequência de QPC conhecida)
+    pub fn with_timecode(fps: u32, timecode: Timecode) -> Self {
+        Self {
+            fps: fps.max(1),
+            timecode,
+            next_index: 0,
+            current: None,
+            stats: CfrStats::default(),
+        }
+    }
+
+    pub fn fps(&self) -> u32 {
+        self.fps
+    }
+
+    pub fn stats(&self) -> CfrStats {
+        self.stats
+    }
+
+    /// Instante da posição `index` em unidades de 100 ns
+    pub fn slot_timestamp(&self, index: u64) -> i64 {
+        slot_timestamp(self.fps, index)
+    }
+
+    /// Recebe um quadro e retorna as posições que ficaram definidas até o seu present time.
+    ///
+    /// As posições anteriores ao quadro mostram o quadro anterior; o próprio quadro só é
+    /// emitido quando chegar um mais novo ou em `fill_until`/`finish`.
+    pub fn push(&mut self, frame: Frame) -> Vec<CfrFrame> {
+        self.stats.received += 1;
+        let timestamp = self.timecode.timestamp(frame.present_time);
+        let emitted = self.emit_before(timestamp);
+
+        if let Some((_, false)) = self.current {
+            self.stats.dropped += 1;
+        }
+        self.current = Some((Arc::new(frame), false));
+        emitted
+    }
+
+    /// Emite as posições anteriores a `present_time` (QPC atual) repetindo o último quadro;
+    /// para telas paradas, em que a duplicação não entrega quadros novos
+    pub fn fill_until(&mut self, present_time: i64) -> Vec<CfrFrame> {
+        let timestamp = self.timecode.peek(present_time);
+        self.emit_before(timestamp)
+    }
+
+    /// Emite a posição do último quadro recebido, se ele ainda não ocupou nenhuma
+    pub fn finish(&mut self) -> Vec<CfrFrame> {
+        match self.current {
+            Some((_, false)) => {
+                let timestamp = self.slot_timestamp(self.next_index) + 1;
+                self.emit_before(timestamp)
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Emite todas as posições com instante anterior a `timestamp` com o quadro atual
+    fn emit_before(&mut self, timestamp: i64) -> Vec<CfrFrame> {
+        let mut emitted = Vec::new();
+        let Some((frame, used)) = self.current.as_mut() else {
+            return emitted;
+        };
+
+        while slot_timestamp(self.fps, self.next_index) < timestamp {
+            emitted.push(CfrFrame {
+                index: self.next_index,
+                timestamp: slot_timestamp(self.fps, self.next_index),
+                frame: frame.clone(),
+                duplicate: *used,
+            });
+            if *used {
+                self.stats.duplicated += 1;
+            }
+            *used = true;
+            self.stats.emitted += 1;
+            self.next_index += 1;
+        }
+        emitted
+    }
+}
+
+fn slot_timestamp(fps: u32, index: u64) -> i64 {
+    (index as i128 * TICKS_PER_SECOND as i128 / fps as i128) as i64
+}
@@ -0,0 +1,233 @@
+//! Alertas disparados por mudanças na tela
+//!
+//! `ChangeSink` é uma saída (`FrameSink`) que compara cada quadro com o último quadro de
+//! referência usando `compare::compare_frames` e, quando a fração de pixels alterados na região
+//! observada passa de `min_changed`, chama um callback com o relatório e a imagem da região.
+//! Serve para monitorar painéis, vigiar quiosques e alimentar ferramentas de alerta. Com a feature
+//! `webhook`, `ChangeSink::webhook` envia o alerta por HTTP com a captura em PNG anexada.
+//!
+//! A referência é o quadro do último alerta, então mudanças lentas se acumulam até disparar, e
+//! `cooldown` limita a frequência dos alertas enquanto a tela continua mudando.
+
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::compare::{compare_frames, DiffReport, Tolerance};
+use crate::frame::Frame;
+use crate::geometry::Rect;
+use crate::sink::FrameSink;
+use crate::Result;
+
+/// Quando uma mudança merece alerta
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct ChangeOptions {
+    /// Região observada, relativa ao monitor (`None` = o quadro inteiro)
+    pub region: Option<Rect>,
+    /// Diferença por canal abaixo da qual o pixel não conta como alterado (ruído, cursor piscando)
+    pub tolerance: Tolerance,
+    /// Fração dos pixels da região que precisa mudar para disparar (0.0 a 1.0)
+    pub min_changed: f64,
+    /// Intervalo mínimo entre dois alertas
+    pub cooldown: Duration,
+}
+
+impl Default for ChangeOptions {
+    fn default() -> Self {
+        Self {
+            region: None,
+            tolerance: Tolerance::color(16),
+            min_changed: 0.01,
+            cooldown: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Mudança detectada
+#[derive(Debug, Clone)]
+pub struct ChangeEvent {
+    /// Diferenças em relação à referência; `diff_bounds` é relativo à região observada
+    pub report: DiffReport,
+    /// Imagem atual da região observada
+    pub frame: Frame,
+    /// Região observada, relativa ao monitor
+    pub region: Rect,
+    pub sequence: u64,
+    /// Horário do alerta, para mensagens e registros
+    pub time: SystemTime,
+}
+
+type Callback = Box<dyn FnMut(&ChangeEvent) -> Result<()> + Send>;
+
+/// Saída que dispara um callback quando a região observada muda
+pub struct ChangeSink {
+    options: ChangeOptions,
+    callback: Callback,
+    reference: Option<Frame>,
+    last_alert: Option<Instant>,
+    alerts: u64,
+}
+
+impl ChangeSink {
+    pub fn new<F>(options: ChangeOptions, callback: F) -> Self
+    where
+        F: FnMut(&ChangeEvent) -> Result<()> + Send + 'static,
+    {
+        Self {
+            options,
+            callback: Box::new(callback),
+            reference: None,
+            last_alert: None,
+            alerts: 0,
+        }
+    }
+
+    /// Alertas disparados até agora
+    pub fn alerts(&self) -> u64 {
+        self.alerts
+    }
+
+    /// Descarta a referência; o próximo quadro passa a ser a nova referência sem disparar
+    pub fn reset(&mut self) {
+        self.reference = None;
+    }
+
+    /// Compara o quadro com a referência e dispara o callback se a mudança for significativa
+    pub fn check(&mut self, frame: &Frame) -> Result<Option<ChangeEvent>> {
+        // A duplicação informou que nada mudou: não há o que comparar
+        if self.reference.is_some() && frame.metadata.dirty_rects == Some(0) && frame.metadata.move_rects == Some(0) {
+            return Ok(None);
+        }
+        if self.last_alert.is_some_and(|last| last.elapsed() < self.options.cooldown) {
+            return Ok(None);
+        }
+
+        let region = match self.options.region {
+            Some(region) => region.intersect(&frame.region).ok_or("Região observada fora do quadro")?,
+            None => frame.region,
+        };
+        let current = crop(frame, region)?;
+        let Some(reference) = self.reference.as_ref().filter(|reference| reference.region == region) else {
+            self.reference = Some(current);
+            return Ok(None);
+        };
+
+        let report = compare_frames(reference, &current, self.options.tolerance)?;
+        if report.differing == 0 || report.differing_ratio() < self.options.min_changed {
+            return Ok(None);
+        }
+
+        debug!(
+            "Mudança em {:.1}% da região {:?} (quadro {})",
+            report.differing_ratio() * 100.0,
+            region,
+            frame.sequence
+        );
+        let event = ChangeEvent {
+            report,
+            frame: current.clone(),
+            region,
+            sequence: frame.sequence,
+            time: SystemTime::now(),
+        };
+        self.reference = Some(current);
+        self.last_alert = Some(Instant::now());
+        self.alerts += 1;
+        (self.callback)(&event)?;
+        Ok(Some(event))
+    }
+}
+
+impl FrameSink for ChangeSink {
+    fn on_frame(&mut self, frame: &Frame) -> Result<()> {
+        self.check(frame).map(|_| ())
+    }
+}
+
+/// Cópia de `region` (relativa ao monitor) de um quadro que a contém
+fn crop(frame: &Frame, region: Rect) -> Result<Frame> {
+    if region == frame.region {
+        return Ok(frame.clone());
+    }
+    let rect = Rect::new(region.left - frame.region.left, region.top - frame.region.top, region.width, region.height);
+    Ok(frame.crop(rect)?.to_frame())
+}
+
+#[cfg(feature = "webhook")]
+mod webhook {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::ChangeEvent;
+    use crate::png_encoder::encode_png;
+    use crate::Result;
+
+    const BOUNDARY: &str = "dxgi-capture-change-alert";
+
+    /// Envia o alerta como `multipart/form-data`: a parte `event` com os dados em JSON e a parte
+    /// `screenshot` com a região em PNG
+    pub(super) fn post(agent: &ureq::Agent, url: &str, event: &ChangeEvent) -> Result<()> {
+        let png = encode_png(&event.frame)?;
+        let mut body = Vec::with_capacity(png.len() + 1024);
+        body.extend_from_slice(
+            format!(
+                "--{BOUNDARY}\r\nContent-Disposition: form-data; name=\"event\"\r\nContent-Type: application/json\r\n\r\n{}\r\n",
+                event_json(event)
+            )
+            .as_bytes(),
+        );
+        body.extend_from_slice(
+            format!(
+                "--{BOUNDARY}\r\nContent-Disposition: form-data; name=\"screenshot\"; filename=\"change-{}.png\"\r\n\
+                 Content-Type: image/png\r\n\r\n",
+                event.sequence
+            )
+            .as_bytes(),
+        );
+        body.extend_from_slice(&png);
+        body.extend_from_slice(format!("\r\n--{BOUNDARY}--\r\n").as_bytes());
+
+        agent
+            .post(url)
+            .set("Content-Type", &format!("multipart/form-data; boundary={BOUNDARY}"))
+            .send_bytes(&body)?;
+        Ok(())
+    }
+
+    pub(super) fn agent(timeout: Duration) -> ureq::Agent {
+        ureq::AgentBuilder::new().timeout(timeout).build()
+    }
+
+    fn event_json(event: &ChangeEvent) -> String {
+        let region = event.region;
+        let bounds = match event.report.diff_bounds {
+            Some(b) => format!("{{\"left\":{},\"top\":{},\"width\":{},\"height\":{}}}", b.left, b.top, b.width, b.height),
+            None => "null".to_string(),
+        };
+        let time = event.time.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+        format!(
+            "{{\"sequence\":{},\"time_ms\":{},\"region\":{{\"left\":{},\"top\":{},\"width\":{},\"height\":{}}},\
+             \"changed\":{:.6},\"differing\":{},\"pixels\":{},\"diff_bounds\":{}}}",
+            event.sequence,
+            time,
+            region.left,
+            region.top,
+            region.width,
+            region.height,
+            event.report.differing_ratio(),
+            event.report.differing,
+            event.report.pixels,
+            bounds
+        )
+    }
+}
+
+#[cfg(feature = "webhook")]
+impl ChangeSink {
+    /// Envia cada alerta por POST para `url` com a captura em PNG; falhas de rede são
+    /// registradas como erros da saída e não interrompem a observação
+    pub fn webhook(url: &str, options: ChangeOptions) -> Self {
+        let url = url.to_string();
+        let agent = webhook::agent(Duration::from_secs(10));
+        Self::new(options, move |event| webhook::post(&agent, &url, event))
+    }
+}
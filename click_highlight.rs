@@ -0,0 +1,163 @@
+//! Destaque dos cliques do mouse em gravações de tutoriais (feature `input`)
+//!
+//! Cada clique registrado por `InputRecorder` vira um círculo que se expande e some ao longo
+//! de `duration`, desenhado nos quadros antes da entrega, como nos gravadores de screencast.
+//! O tempo da animação segue o relógio QPC dos quadros, então a gravação mostra o efeito na
+//! mesma velocidade em que foi capturada, mesmo que o processamento atrase.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use windows::Win32::System::Performance::{QueryPerformanceCounter, QueryPerformanceFrequency};
+
+use crate::annotate::{annotate, Annotation, Color};
+use crate::frame::Frame;
+use crate::geometry::Point;
+use crate::input::{InputEvent, InputKind, MouseButton};
+use crate::Result;
+
+/// Fração do raio final com que o círculo começa
+const START_SCALE: f64 = 0.4;
+
+/// Aparência do destaque de cliques
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct ClickHighlightOptions {
+    /// Raio final do círculo em pixels
+    pub radius: u32,
+    /// Cor do contorno; a opacidade diminui até zero ao longo de `duration`
+    pub color: Color,
+    /// Preenchimento translúcido dentro do contorno
+    pub fill: Option<Color>,
+    pub thickness: u32,
+    pub duration: Duration,
+    /// Cor por botão; os botões ausentes usam `color`
+    pub right_button: Option<Color>,
+}
+
+impl Default for ClickHighlightOptions {
+    fn default() -> Self {
+        Self {
+            radius: 28,
+            color: Color::YELLOW,
+            fill: Some(Color::YELLOW.with_alpha(60)),
+            thickness: 3,
+            duration: Duration::from_millis(500),
+            right_button: Some(Color::rgb(0, 160, 255)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Click {
+    position: Point,
+    time: i64,
+    button: MouseButton,
+}
+
+/// Guarda os cliques recentes e os desenha sobre os quadros
+#[derive(Debug)]
+pub struct ClickHighlighter {
+    options: ClickHighlightOptions,
+    clicks: VecDeque<Click>,
+    frequency: i64,
+}
+
+impl ClickHighlighter {
+    pub fn new(options: ClickHighlightOptions) -> Self {
+        let mut frequency = 0i64;
+        if unsafe { QueryPerformanceFrequency(&mut frequency) }.is_err() {
+            frequency = 0;
+        }
+        Self {
+            options,
+            clicks: VecDeque::new(),
+            frequency: frequency.max(1),
+        }
+    }
+
+    /// Adiciona os cliques entre os eventos (ex.: de `InputRecorder::take_for_frame`)
+    pub fn push_events(&mut self, events: &[InputEvent]) {
+        for event in events {
+            if let InputKind::MouseDown { button } = event.kind {
+                self.clicks.push_back(Click {
+                    position: event.position,
+                    time: event.time,
+                    button,
+                });
+            }
+        }
+    }
+
+    /// Cliques ainda visíveis
+    pub fn active(&self) -> usize {
+        self.clicks.len()
+    }
+
+    /// Desenha os cliques ainda visíveis no quadro.
+    ///
+    /// `origin` é a posição no desktop virtual do monitor capturado (`DxgiCapture::output_origin`),
+    /// usada para converter as posições dos eventos para coordenadas do quadro.
+    pub fn draw(&mut self, frame: &mut Frame, origin: Point) -> Result<()> {
+        let now = frame_time(frame);
+        let duration = (self.options.duration.as_secs_f64() * self.frequency as f64) as i64;
+        while self.clicks.front().is_some_and(|click| now - click.time > duration) {
+            self.clicks.pop_front();
+        }
+        if self.clicks.is_empty() {
+            return Ok(());
+        }
+
+        let mut annotations = Vec::new();
+        for click in &self.clicks {
+            // Cliques posteriores ao quadro aparecem só nos próximos
+            if click.time > now {
+                continue;
+            }
+            let progress = (now - click.time) as f64 / duration.max(1) as f64;
+            let fade = 1.0 - progress;
+            let radius = (self.options.radius as f64 * (START_SCALE + (1.0 - START_SCALE) * progress)).round() as u32;
+            let center = Point::new(
+                click.position.x - origin.x - frame.region.left,
+                click.position.y - origin.y - frame.region.top,
+            );
+            let color = match click.button {
+                MouseButton::Right => self.options.right_button.unwrap_or(self.options.color),
+                _ => self.options.color,
+            };
+
+            if let Some(fill) = self.options.fill {
+                annotations.push(Annotation::Circle {
+                    center,
+                    radius,
+                    color: fill.with_alpha((fill.a as f64 * fade) as u8),
+                    thickness: 0,
+                });
+            }
+            annotations.push(Annotation::Circle {
+                center,
+                radius,
+                color: color.with_alpha((color.a as f64 * fade) as u8),
+                thickness: self.options.thickness.max(1),
+            });
+        }
+
+        annotate(frame, &annotations)
+    }
+}
+
+/// Instante do quadro em QPC: aquisição, apresentação ou agora, nessa ordem
+fn frame_time(frame: &Frame) -> i64 {
+    if frame.metadata.acquire_time != 0 {
+        return frame.metadata.acquire_time;
+    }
+    if frame.present_time != 0 {
+        return frame.present_time;
+    }
+    let mut now = 0i64;
+    unsafe {
+        let _ = QueryPerformanceCounter(&mut now);
+    }
+    now
+}
@@ -0,0 +1,88 @@
+//! Inicialização do COM para os caminhos que dependem dele (Media Foundation, WIC, UI Automation)
+//!
+//! A estratégia é manter o apartamento multithread (MTA) do processo vivo com
+//! `CoIncrementMTAUsage` enquanto houver algum `ComGuard`: threads que nunca chamaram
+//! `CoInitializeEx` passam a estar no MTA implícito, e threads que o aplicativo já colocou em
+//! um STA continuam como estão. Nada é inicializado ou desfeito por thread, então combinar o
+//! crate com outro código COM não gera `RPC_E_CHANGED_MODE` nem `CoUninitialize` fora de ordem.
+
+use std::sync::Mutex;
+
+use windows::Win32::System::Com::{
+    CoDecrementMTAUsage, CoGetApartmentType, CoIncrementMTAUsage, APTTYPE, APTTYPEQUALIFIER, APTTYPEQUALIFIER_IMPLICIT_MTA,
+    APTTYPE_MAINSTA, APTTYPE_MTA, APTTYPE_NA, APTTYPE_STA, CO_MTA_USAGE_COOKIE,
+};
+
+use crate::error::CaptureResult;
+
+/// Guards vivos e o cookie do `CoIncrementMTAUsage` que mantém o MTA
+static MTA_USAGE: Mutex<(usize, Option<CO_MTA_USAGE_COOKIE>)> = Mutex::new((0, None));
+
+/// Apartamento COM de uma thread, de `current_apartment`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComApartment {
+    /// COM não está disponível na thread
+    None,
+    /// Single-threaded (inclusive o STA principal); objetos COM ficam presos à thread
+    Sta,
+    /// Inicializada explicitamente no MTA com `CoInitializeEx`
+    Mta,
+    /// No MTA sem ter chamado `CoInitializeEx`, porque algum `ComGuard` o mantém vivo
+    ImplicitMta,
+    /// Neutral-threaded (dentro de um objeto NTA)
+    Neutral,
+}
+
+/// Mantém o MTA do processo vivo; o último guard a sair de escopo o libera.
+///
+/// Pode ser movido entre threads e guardado por quanto tempo os objetos COM forem usados.
+#[derive(Debug)]
+pub struct ComGuard {
+    _private: (),
+}
+
+/// Garante que o COM possa ser usado a partir de qualquer thread que não seja STA.
+///
+/// Contado por referência no processo: várias chamadas (de threads diferentes, inclusive)
+/// compartilham o mesmo MTA.
+pub fn ensure_com_initialized() -> CaptureResult<ComGuard> {
+    let mut usage = MTA_USAGE.lock().unwrap_or_else(|e| e.into_inner());
+    if usage.0 == 0 {
+        usage.1 = Some(unsafe { CoIncrementMTAUsage()? });
+        debug!("MTA do COM mantido para o processo");
+    }
+    usage.0 += 1;
+    Ok(ComGuard { _private: () })
+}
+
+impl Drop for ComGuard {
+    fn drop(&mut self) {
+        let mut usage = MTA_USAGE.lock().unwrap_or_else(|e| e.into_inner());
+        usage.0 -= 1;
+        if usage.0 == 0 {
+            if let Some(cookie) = usage.1.take() {
+                unsafe {
+                    let _ = CoDecrementMTAUsage(cookie);
+                }
+                debug!("MTA do COM liberado");
+            }
+        }
+    }
+}
+
+/// Apartamento COM da thread atual, para diagnosticar problemas de threading
+pub fn current_apartment() -> ComApartment {
+    let mut kind = APTTYPE::default();
+    let mut qualifier = APTTYPEQUALIFIER::default();
+    if unsafe { CoGetApartmentType(&mut kind, &mut qualifier) }.is_err() {
+        return ComApartment::None;
+    }
+
+    match kind {
+        APTTYPE_STA | APTTYPE_MAINSTA => ComApartment::Sta,
+        APTTYPE_MTA if qualifier == APTTYPEQUALIFIER_IMPLICIT_MTA => ComApartment::ImplicitMta,
+        APTTYPE_MTA => ComApartment::Mta,
+        APTTYPE_NA => ComApartment::Neutral,
+        _ => ComApartment::None,
+    }
+}
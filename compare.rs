@@ -0,0 +1,256 @@
+//! Comparação de quadros com imagens de referência (golden images)
+//!
+//! Para testes de regressão de interface: `compare_frames` compara dois quadros do mesmo
+//! tamanho canal a canal, com tolerância por canal, e informa onde está a maior diferença.
+//! `compare_frames_with_diff` também gera uma imagem de diferenças para inspeção.
+//! `compare_perceptual` troca a tolerância por canal pela distância de cor no espaço YIQ
+//! (a métrica do pixelmatch), que aceita variações que o olho não percebe, como o
+//! antisserrilhamento de texto em outra cor, e rejeita mudanças de brilho visíveis.
+
+use windows::Win32::Graphics::Dxgi::Common::DXGI_FORMAT_B8G8R8A8_UNORM;
+
+use crate::convert::rgba8_reader;
+use crate::frame::Frame;
+use crate::geometry::{Point, Rect};
+use crate::pixel_format::PixelFormat;
+use crate::Result;
+
+/// Diferença máxima aceita por canal, em níveis de 8 bits
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Tolerance {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Tolerance {
+    /// Pixels precisam ser idênticos
+    pub const EXACT: Tolerance = Tolerance::uniform(0);
+
+    /// A mesma tolerância em todos os canais
+    pub const fn uniform(value: u8) -> Self {
+        Self {
+            r: value,
+            g: value,
+            b: value,
+            a: value,
+        }
+    }
+
+    /// Mesma tolerância nas cores e alfa ignorado (capturas da área de trabalho têm alfa indefinido)
+    pub const fn color(value: u8) -> Self {
+        Self {
+            r: value,
+            g: value,
+            b: value,
+            a: u8::MAX,
+        }
+    }
+
+    fn channels(&self) -> [u8; 4] {
+        [self.r, self.g, self.b, self.a]
+    }
+}
+
+/// Resultado de `compare_frames`
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DiffReport {
+    /// Pixels comparados
+    pub pixels: u64,
+    /// Pixels com algum canal acima da tolerância
+    pub differing: u64,
+    /// Maior diferença de cada canal (R, G, B, A), inclusive as dentro da tolerância
+    pub max_diff: [u8; 4],
+    /// Pixel com a maior diferença em um canal, relativo ao quadro (`None` se forem idênticos)
+    pub max_diff_at: Option<Point>,
+    /// Menor retângulo com todos os pixels acima da tolerância
+    pub diff_bounds: Option<Rect>,
+    /// Diferença média por canal de cor, em níveis de 8 bits
+    pub mean_diff: f64,
+}
+
+impl DiffReport {
+    /// Nenhum pixel acima da tolerância
+    pub fn passed(&self) -> bool {
+        self.differing == 0
+    }
+
+    /// Fração dos pixels acima da tolerância (0.0 a 1.0)
+    pub fn differing_ratio(&self) -> f64 {
+        if self.pixels == 0 {
+            0.0
+        } else {
+            self.differing as f64 / self.pixels as f64
+        }
+    }
+}
+
+/// Compara dois quadros do mesmo tamanho, em qualquer formato com leitura em CPU
+/// (B8G8R8A8, R8G8B8A8 ou R16G16B16A16_FLOAT em SDR)
+pub fn compare_frames(a: &Frame, b: &Frame, tolerance: Tolerance) -> Result<DiffReport> {
+    compare(a, b, Criterion::Channels(tolerance.channels()), None)
+}
+
+/// Como `compare_frames`, e também retorna uma imagem B8G8R8A8 das diferenças: pixels dentro
+/// da tolerância aparecem em cinza esmaecido (a partir de `a`), e os demais em vermelho, mais
+/// intenso quanto maior a diferença
+pub fn compare_frames_with_diff(a: &Frame, b: &Frame, tolerance: Tolerance) -> Result<(DiffReport, Frame)> {
+    let mut image = Vec::with_capacity(PixelFormat::Bgra8.buffer_size(a.width(), a.height()));
+    let report = compare(a, b, Criterion::Channels(tolerance.channels()), Some(&mut image))?;
+    Ok((report, diff_frame(a, image)))
+}
+
+/// Compara dois quadros pela diferença perceptual de cor: um pixel difere quando a distância
+/// YIQ passa de `threshold` (0.0 a 1.0, fração da maior distância possível; 0.1 é um bom
+/// ponto de partida). O alfa é ignorado.
+pub fn compare_perceptual(a: &Frame, b: &Frame, threshold: f64) -> Result<DiffReport> {
+    compare(a, b, Criterion::Perceptual(threshold), None)
+}
+
+/// Como `compare_perceptual`, com a imagem de diferenças de `compare_frames_with_diff`
+pub fn compare_perceptual_with_diff(a: &Frame, b: &Frame, threshold: f64) -> Result<(DiffReport, Frame)> {
+    let mut image = Vec::with_capacity(PixelFormat::Bgra8.buffer_size(a.width(), a.height()));
+    let report = compare(a, b, Criterion::Perceptual(threshold), Some(&mut image))?;
+    Ok((report, diff_frame(a, image)))
+}
+
+/// Quando um pixel conta como diferente
+#[derive(Clone, Copy)]
+enum Criterion {
+    /// Diferença máxima por canal (R, G, B, A)
+    Channels([u8; 4]),
+    /// Fração da maior distância YIQ
+    Perceptual(f64),
+}
+
+/// Maior distância YIQ ao quadrado entre duas cores de 8 bits
+const MAX_YIQ_DELTA: f64 = 35215.0;
+
+/// Distância YIQ ao quadrado entre duas cores, ponderada como no pixelmatch
+fn yiq_delta(a: [u8; 4], b: [u8; 4]) -> f64 {
+    let (r, g, bl) = (a[0] as f64 - b[0] as f64, a[1] as f64 - b[1] as f64, a[2] as f64 - b[2] as f64);
+    let y = r * 0.298_895_31 + g * 0.586_622_47 + bl * 0.114_482_23;
+    let i = r * 0.595_977_99 - g * 0.274_176_10 - bl * 0.321_801_89;
+    let q = r * 0.211_470_17 - g * 0.522_617_11 + bl * 0.311_146_94;
+    0.5053 * y * y + 0.299 * i * i + 0.1957 * q * q
+}
+
+fn diff_frame(a: &Frame, image: Vec<u8>) -> Frame {
+    Frame {
+        data: image,
+        region: Rect::from_size(a.size()),
+        format: DXGI_FORMAT_B8G8R8A8_UNORM,
+        color_space: a.color_space,
+        present_time: a.present_time,
+        sequence: a.sequence,
+        metadata: a.metadata,
+    }
+}
+
+fn compare(a: &Frame, b: &Frame, criterion: Criterion, mut image: Option<&mut Vec<u8>>) -> Result<DiffReport> {
+    if a.size() != b.size() {
+        return Err(format!(
+            "Quadros de tamanhos diferentes: {}x{} e {}x{}",
+            a.width(),
+            a.height(),
+            b.width(),
+            b.height()
+        )
+        .into());
+    }
+
+    let read_a = rgba8_reader(a.format).ok_or_else(|| format!("Formato não suportado na comparação: {:?}", a.format))?;
+    let read_b = rgba8_reader(b.format).ok_or_else(|| format!("Formato não suportado na comparação: {:?}", b.format))?;
+    let (stride_a, stride_b) = (a.stride(), b.stride());
+    let (width, height) = (a.width() as usize, a.height() as usize);
+    if a.data.len() < stride_a * height || b.data.len() < stride_b * height {
+        return Err("Quadro menor que a região informada".into());
+    }
+
+    let (pixel_a, pixel_b) = (stride_a / width.max(1), stride_b / width.max(1));
+    let (limits, perceptual_limit) = match criterion {
+        Criterion::Channels(limits) => (limits, None),
+        // O critério perceptual decide sozinho; o alfa nunca conta
+        Criterion::Perceptual(threshold) => ([u8::MAX; 4], Some(MAX_YIQ_DELTA * threshold * threshold)),
+    };
+    let mut report = DiffReport::default();
+    let mut largest = 0u8;
+    let mut color_sum = 0u64;
+    let mut bounds: Option<(usize, usize, usize, usize)> = None;
+
+    for y in 0..height {
+        let row_a = &a.data[y * stride_a..(y + 1) * stride_a];
+        let row_b = &b.data[y * stride_b..(y + 1) * stride_b];
+        for x in 0..width {
+            let pa = read_a(&row_a[x * pixel_a..(x + 1) * pixel_a]);
+            let pb = read_b(&row_b[x * pixel_b..(x + 1) * pixel_b]);
+
+            let mut over = 0u8;
+            let mut pixel_max = 0u8;
+            for channel in 0..4 {
+                let diff = pa[channel].abs_diff(pb[channel]);
+                report.max_diff[channel] = report.max_diff[channel].max(diff);
+                if diff > limits[channel] {
+                    over = over.max(diff);
+                }
+                // O alfa ignorado não conta para a localização da maior diferença
+                if channel < 3 || limits[3] < u8::MAX {
+                    pixel_max = pixel_max.max(diff);
+                }
+                if channel < 3 {
+                    color_sum += diff as u64;
+                }
+            }
+            if let Some(limit) = perceptual_limit {
+                let delta = yiq_delta(pa, pb);
+                // Intensidade na imagem de diferenças proporcional à distância
+                over = if delta > limit {
+                    ((delta / MAX_YIQ_DELTA).sqrt() * 255.0).clamp(1.0, 255.0) as u8
+                } else {
+                    0
+                };
+            }
+
+            if pixel_max > largest {
+                largest = pixel_max;
+                report.max_diff_at = Some(Point::new(x as i32, y as i32));
+            }
+            if over > 0 {
+                report.differing += 1;
+                bounds = Some(match bounds {
+                    Some((left, top, right, bottom)) => (left.min(x), top.min(y), right.max(x), bottom.max(y)),
+                    None => (x, y, x, y),
+                });
+            }
+
+            if let Some(image) = image.as_deref_mut() {
+                if over > 0 {
+                    image.extend_from_slice(&[0, 0, 128u8.saturating_add(over / 2), 255]);
+                } else {
+                    let gray = ((pa[0] as u32 * 77 + pa[1] as u32 * 150 + pa[2] as u32 * 29) >> 8) as u8;
+                    let dimmed = gray / 3 + 40;
+                    image.extend_from_slice(&[dimmed, dimmed, dimmed, 255]);
+                }
+            }
+        }
+    }
+
+    report.pixels = (width * height) as u64;
+    if report.pixels > 0 {
+        report.mean_diff = color_sum as f64 / (report.pixels * 3) as f64;
+    }
+    report.diff_bounds = bounds.map(|(left, top, right, bottom)| {
+        Rect::new(left as i32, top as i32, (right - left + 1) as u32, (bottom - top + 1) as u32)
+    });
+
+    if !report.passed() {
+        debug!(
+            "{} de {} pixels diferentes (máximo {:?} em {:?})",
+            report.differing, report.pixels, report.max_diff, report.max_diff_at
+        );
+    }
+    Ok(report)
+}
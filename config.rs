@@ -0,0 +1,277 @@
+//! Configuração do capturador e o builder de `DxgiCapture`
+
+use std::time::Duration;
+
+#[cfg(feature = "broker")]
+use crate::broker::SharedCapture;
+use crate::cancel::CancellationToken;
+#[cfg(feature = "consent")]
+use crate::consent::{request_consent, ConsentRecord, ConsentRequest};
+use crate::error::CaptureResult;
+#[cfg(feature = "fault-injection")]
+use crate::fault_injection::FaultPlan;
+use crate::regions::RegionRegistry;
+use crate::source::{Backend, ScreenSource};
+use crate::stream::{CaptureStream, StreamOptions};
+use crate::DxgiCapture;
+
+/// O que `capture_region` retorna quando a duplicação não entrega imagem nova
+/// (a tela não mudou ou só o cursor se moveu)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum NoFramePolicy {
+    /// Buffer preenchido com zeros (comportamento original)
+    #[default]
+    ZeroFill,
+    /// Os pixels da última captura da mesma região; `CaptureError::NoNewFrame` se ainda não houver
+    LastFrame,
+    /// Sempre o erro `CaptureError::NoNewFrame`, para o chamador decidir
+    NoChange,
+}
+
+/// Opções de criação e comportamento de um `DxgiCapture`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct CaptureConfig {
+    /// Índice do adaptador (de `outputs::enumerate_outputs`)
+    pub adapter_index: u32,
+    /// Índice do monitor dentro do adaptador
+    pub output_index: u32,
+    /// Nome estável do monitor (`MonitorIdentity::display_name` ou `device_path`); quando
+    /// presente, localiza o monitor a cada (re)inicialização e atualiza os índices acima
+    pub output_name: Option<String>,
+    /// Após reinicializar por perda de acesso, repete a aquisição uma vez na mesma chamada
+    pub retry_after_reinit: bool,
+    /// Comportamento quando a duplicação não entrega imagem nova
+    pub no_frame_policy: NoFramePolicy,
+    /// Por quanto tempo (ms) continuar tentando criar a duplicação enquanto o monitor estiver
+    /// ocupado por outros aplicativos ou na área de trabalho segura; 0 falha na primeira tentativa
+    pub wait_for_duplication_ms: u64,
+    /// Limite (bytes) das texturas intermediárias mantidas entre capturas; acima dele o cache é
+    /// esvaziado antes da próxima captura. 0 não limita
+    pub texture_budget_bytes: u64,
+    /// Texturas intermediárias não usadas há mais que isto (ms) são liberadas; 0 mantém para sempre
+    pub texture_idle_release_ms: u64,
+    /// Libera as texturas em cache quando o sistema avisa que o processo passou do orçamento de
+    /// memória de vídeo (Windows 10+)
+    pub trim_on_memory_pressure: bool,
+    /// Idade máxima (ms) da imagem entregue: se o último present for mais antigo, a captura espera
+    /// até este tempo por um present novo e falha com `CaptureError::StaleFrame` se ele não vier.
+    /// 0 entrega a imagem mais recente, seja qual for a idade
+    pub max_frame_age_ms: u64,
+    /// Registra um aviso quando a duplicação junta vários presents em uma aquisição
+    /// (`FrameMetadata::accumulated_frames` > 1), ou seja, quando a captura perdeu quadros
+    pub log_missed_presents: bool,
+    /// Cria o dispositivo com a camada de depuração do D3D11 (`D3D11_CREATE_DEVICE_DEBUG`) e
+    /// repassa as mensagens dela ao log; ignorado, com um aviso, se as Graphics Tools não
+    /// estiverem instaladas. Deixa tudo mais lento: só para diagnóstico
+    pub debug_layer: bool,
+}
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        Self {
+            adapter_index: 0,
+            output_index: 0,
+            output_name: None,
+            retry_after_reinit: true,
+            no_frame_policy: NoFramePolicy::ZeroFill,
+            wait_for_duplication_ms: 0,
+            texture_budget_bytes: 0,
+            texture_idle_release_ms: 0,
+            trim_on_memory_pressure: false,
+            max_frame_age_ms: 0,
+            log_missed_presents: false,
+            debug_layer: false,
+        }
+    }
+}
+
+/// Builder de `DxgiCapture`, obtido com `DxgiCapture::builder()`
+#[derive(Debug, Clone, Default)]
+pub struct CaptureBuilder {
+    config: CaptureConfig,
+    cancel: CancellationToken,
+    regions: RegionRegistry,
+    #[cfg(feature = "fault-injection")]
+    faults: Option<FaultPlan>,
+}
+
+impl CaptureBuilder {
+    /// Parte de uma configuração existente (ex.: carregada de um arquivo)
+    pub fn from_config(config: CaptureConfig) -> Self {
+        Self {
+            config,
+            cancel: CancellationToken::new(),
+            regions: RegionRegistry::new(),
+            #[cfg(feature = "fault-injection")]
+            faults: None,
+        }
+    }
+
+    /// Monitor a capturar (índices de `outputs::enumerate_outputs`)
+    pub fn output(mut self, adapter_index: u32, output_index: u32) -> Self {
+        self.config.adapter_index = adapter_index;
+        self.config.output_index = output_index;
+        self.config.output_name = None;
+        self
+    }
+
+    /// Monitor a capturar pelo nome estável (ex.: "DELL U2720Q #2"), que continua apontando para
+    /// o mesmo monitor físico quando os índices mudam; ver `monitor_identity`
+    pub fn output_by_name(mut self, name: impl Into<String>) -> Self {
+        self.config.output_name = Some(name.into());
+        self
+    }
+
+    /// Repetir a aquisição logo após uma reinicialização bem-sucedida (padrão: ativado)
+    pub fn retry_after_reinit(mut self, retry: bool) -> Self {
+        self.config.retry_after_reinit = retry;
+        self
+    }
+
+    /// O que retornar quando a tela não mudou (padrão: `NoFramePolicy::ZeroFill`)
+    pub fn no_frame_policy(mut self, policy: NoFramePolicy) -> Self {
+        self.config.no_frame_policy = policy;
+        self
+    }
+
+    /// Esperar até `timeout` pela liberação do monitor quando a duplicação estiver indisponível
+    /// (`CaptureError::DuplicationLimitReached` ou `AccessDenied`) (padrão: falhar imediatamente)
+    pub fn wait_for_duplication(mut self, timeout: Duration) -> Self {
+        self.config.wait_for_duplication_ms = timeout.as_millis() as u64;
+        self
+    }
+
+    /// Memória máxima das texturas intermediárias em cache (padrão: sem limite)
+    pub fn texture_budget(mut self, bytes: u64) -> Self {
+        self.config.texture_budget_bytes = bytes;
+        self
+    }
+
+    /// Liberar texturas intermediárias não usadas por `idle` (padrão: manter até `trim`)
+    pub fn release_idle_textures(mut self, idle: Duration) -> Self {
+        self.config.texture_idle_release_ms = idle.as_millis() as u64;
+        self
+    }
+
+    /// Liberar o cache de texturas quando o processo passar do orçamento de memória de vídeo
+    /// (padrão: desativado)
+    pub fn trim_on_memory_pressure(mut self, enabled: bool) -> Self {
+        self.config.trim_on_memory_pressure = enabled;
+        self
+    }
+
+    /// Idade máxima da imagem entregue (padrão: sem limite); ver `CaptureConfig::max_frame_age_ms`
+    pub fn max_frame_age(mut self, age: Duration) -> Self {
+        self.config.max_frame_age_ms = age.as_millis() as u64;
+        self
+    }
+
+    /// Avisa no log quando a captura perde presents (padrão: desligado); ver
+    /// `CaptureConfig::log_missed_presents`
+    pub fn log_missed_presents(mut self, enabled: bool) -> Self {
+        self.config.log_missed_presents = enabled;
+        self
+    }
+
+    /// Camada de depuração do D3D11 com as mensagens no log (padrão: desligada); ver
+    /// `CaptureConfig::debug_layer`
+    pub fn debug_layer(mut self, enabled: bool) -> Self {
+        self.config.debug_layer = enabled;
+        self
+    }
+
+    /// Token que interrompe a criação (inclusive a espera de `wait_for_duplication`), as
+    /// capturas e a thread de `stream` (padrão: um token próprio, nunca cancelado)
+    pub fn cancellation(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    pub fn config(&self) -> &CaptureConfig {
+        &self.config
+    }
+
+    /// Regiões nomeadas de `DxgiCapture::capture_named`
+    pub fn regions(mut self, regions: RegionRegistry) -> Self {
+        self.regions = regions;
+        self
+    }
+
+    /// Erros a injetar nas aquisições do capturador criado por `build`, para testar a recuperação
+    /// (veja `fault_injection`); também valem para `build_source` nos backends D3D11 e D3D12,
+    /// mas `stream` não os usa
+    #[cfg(feature = "fault-injection")]
+    pub fn inject_faults(mut self, plan: FaultPlan) -> Self {
+        self.faults = Some(plan);
+        self
+    }
+
+    /// Cria o capturador e inicializa a duplicação
+    pub fn build(self) -> CaptureResult<DxgiCapture> {
+        let mut capture = DxgiCapture::with_cancellation(self.config, self.cancel)?;
+        capture.set_regions(self.regions);
+        #[cfg(feature = "fault-injection")]
+        if let Some(plan) = self.faults {
+            capture.inject_faults(plan);
+        }
+        Ok(capture)
+    }
+
+    /// Captura compartilhada entre processos: duplica o monitor configurado se nenhum outro
+    /// processo o faz, ou lê os quadros publicados pelo dono atual (veja `broker`)
+    #[cfg(feature = "broker")]
+    pub fn build_shared(self) -> CaptureResult<SharedCapture> {
+        let builder = self.resolve_output_name()?;
+        SharedCapture::open(builder.config.adapter_index, builder.config.output_index, builder)
+    }
+
+    /// Mostra a caixa de diálogo de consentimento para o monitor configurado e cria a captura só
+    /// se o usuário concordar; o comprovante assinado com `key` acompanha o capturador
+    #[cfg(feature = "consent")]
+    pub fn build_with_consent(
+        self,
+        mut request: ConsentRequest,
+        key: &[u8],
+    ) -> CaptureResult<(DxgiCapture, ConsentRecord)> {
+        let builder = self.resolve_output_name()?;
+        request.adapter_index = builder.config.adapter_index;
+        request.output_index = builder.config.output_index;
+        let record = request_consent(&request, key)?;
+        Ok((builder.build()?, record))
+    }
+
+    /// Como `build`, entregando os quadros pelo backend escolhido
+    pub fn build_source(self, backend: Backend) -> CaptureResult<Box<dyn ScreenSource>> {
+        match backend {
+            Backend::D3D11 => Ok(Box::new(self.build()?)),
+            #[cfg(feature = "d3d12")]
+            Backend::D3D12 => Ok(Box::new(crate::d3d12::D3D12Capture::new(self.build()?)?)),
+            // Não há monitor a configurar: a reprodução é aberta com `ReplaySource::open`
+            #[cfg(feature = "recording")]
+            Backend::Replay => Err(crate::error::CaptureError::Replay(
+                "use ReplaySource::open to play back a sequence".to_owned(),
+            )),
+        }
+    }
+
+    /// Índices atuais do monitor de `output_by_name`, para os caminhos que usam os índices
+    /// antes de criar a duplicação
+    #[cfg(any(feature = "broker", feature = "consent"))]
+    fn resolve_output_name(mut self) -> CaptureResult<Self> {
+        if let Some(name) = &self.config.output_name {
+            let output = crate::outputs::find_output_by_name(name)?;
+            self.config.adapter_index = output.adapter_index;
+            self.config.output_index = output.output_index;
+        }
+        Ok(self)
+    }
+
+    /// Cria o capturador em uma thread dedicada que entrega quadros por uma fila
+    pub fn stream(self, options: StreamOptions) -> CaptureResult<CaptureStream> {
+        CaptureStream::start_with_cancellation(self.config, options, self.cancel)
+    }
+}
@@ -0,0 +1,193 @@
+//! Consentimento do usuário antes da captura (feature `consent`)
+//!
+//! Aplicativos sujeitos a políticas de privacidade precisam mostrar ao usuário o que será
+//! gravado e obter a concordância antes da primeira captura. `request_consent` exibe a caixa de
+//! diálogo padrão do Windows com o aplicativo, a finalidade, o monitor, a região e a duração, e
+//! devolve um `ConsentRecord` assinado com HMAC-SHA256 (CNG) sob uma chave do aplicativo, que pode
+//! ser guardado como comprovante e conferido depois com `verify`. A recusa falha com
+//! `CaptureError::ConsentDenied`. `CaptureBuilder::build_with_consent` pede o consentimento e
+//! cria a captura em seguida.
+//!
+//! A duração é informativa: cabe ao aplicativo encerrar a captura quando `is_active` deixar de
+//! ser verdadeiro.
+
+use std::env;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use windows::core::HSTRING;
+use windows::Win32::Foundation::HWND;
+use windows::Win32::Security::Cryptography::{BCryptHash, BCRYPT_HMAC_SHA256_ALG_HANDLE};
+use windows::Win32::UI::WindowsAndMessaging::{
+    MessageBoxW, IDYES, MB_DEFBUTTON2, MB_ICONQUESTION, MB_SETFOREGROUND, MB_TOPMOST, MB_YESNO,
+};
+
+use crate::error::{CaptureError, CaptureResult, Language};
+use crate::geometry::Rect;
+use crate::outputs::enumerate_outputs;
+
+/// O que será capturado, como mostrado ao usuário
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConsentRequest {
+    /// Nome do aplicativo que vai capturar
+    pub application: String,
+    /// Para que a captura será usada
+    pub purpose: String,
+    pub adapter_index: u32,
+    pub output_index: u32,
+    /// Região do monitor; `None` é o monitor inteiro
+    pub region: Option<Rect>,
+    /// Por quanto tempo a captura vai durar; `None` é até o aplicativo encerrá-la
+    pub duration: Option<Duration>,
+    /// Idioma do texto da caixa de diálogo
+    pub language: Language,
+}
+
+impl ConsentRequest {
+    pub fn new(application: impl Into<String>, purpose: impl Into<String>) -> Self {
+        Self {
+            application: application.into(),
+            purpose: purpose.into(),
+            adapter_index: 0,
+            output_index: 0,
+            region: None,
+            duration: None,
+            language: Language::English,
+        }
+    }
+}
+
+/// Comprovante de consentimento assinado
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConsentRecord {
+    pub application: String,
+    pub purpose: String,
+    /// Monitor mostrado ao usuário (nome GDI, ex.: `\\.\DISPLAY1`)
+    pub monitor: String,
+    pub region: Option<Rect>,
+    pub duration_ms: Option<u64>,
+    /// Conta do Windows que concordou
+    pub user: String,
+    /// Momento da concordância, em ms desde a época Unix
+    pub granted_at_ms: u64,
+    /// HMAC-SHA256 dos campos acima, em hexadecimal
+    pub signature: String,
+}
+
+impl ConsentRecord {
+    /// Confere a assinatura com a chave usada em `request_consent`
+    pub fn verify(&self, key: &[u8]) -> bool {
+        match hmac_sha256(key, self.payload().as_bytes()) {
+            Ok(signature) => to_hex(&signature) == self.signature,
+            Err(_) => false,
+        }
+    }
+
+    /// O consentimento ainda cobre o instante `now` (sempre, se não há duração)
+    pub fn is_active(&self, now: SystemTime) -> bool {
+        let Some(duration_ms) = self.duration_ms else {
+            return true;
+        };
+        let now_ms = now.duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_millis() as u64);
+        now_ms < self.granted_at_ms.saturating_add(duration_ms)
+    }
+
+    /// Texto canônico assinado: um campo por linha, na ordem da estrutura
+    fn payload(&self) -> String {
+        let region = self
+            .region
+            .map_or("-".to_owned(), |region| format!("{},{},{},{}", region.left, region.top, region.width, region.height));
+        let duration = self.duration_ms.map_or("-".to_owned(), |ms| ms.to_string());
+        [
+            self.application.as_str(),
+            self.purpose.as_str(),
+            self.monitor.as_str(),
+            region.as_str(),
+            duration.as_str(),
+            self.user.as_str(),
+            &self.granted_at_ms.to_string(),
+        ]
+        .join("\n")
+    }
+}
+
+/// Mostra a caixa de diálogo de consentimento e, se o usuário concordar, devolve o comprovante
+/// assinado com `key`; a recusa falha com `CaptureError::ConsentDenied`
+pub fn request_consent(request: &ConsentRequest, key: &[u8]) -> CaptureResult<ConsentRecord> {
+    let output = enumerate_outputs()?
+        .into_iter()
+        .find(|output| output.adapter_index == request.adapter_index && output.output_index == request.output_index)
+        .ok_or(CaptureError::OutputNotFound {
+            adapter: request.adapter_index,
+            output: request.output_index,
+        })?;
+    let region = request.region.unwrap_or(Rect::new(0, 0, output.width, output.height));
+
+    let text = dialog_text(request, &output.device_name, region);
+    let title = match request.language {
+        Language::English => "Screen capture",
+        Language::Portuguese => "Captura de tela",
+    };
+    let answer = unsafe {
+        MessageBoxW(
+            HWND::default(),
+            &HSTRING::from(text),
+            &HSTRING::from(title),
+            MB_YESNO | MB_ICONQUESTION | MB_DEFBUTTON2 | MB_TOPMOST | MB_SETFOREGROUND,
+        )
+    };
+    if answer != IDYES {
+        return Err(CaptureError::ConsentDenied);
+    }
+
+    let mut record = ConsentRecord {
+        application: request.application.clone(),
+        purpose: request.purpose.clone(),
+        monitor: output.device_name,
+        region: request.region,
+        duration_ms: request.duration.map(|duration| duration.as_millis() as u64),
+        user: env::var("USERNAME").unwrap_or_default(),
+        granted_at_ms: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_millis() as u64),
+        signature: String::new(),
+    };
+    record.signature = to_hex(&hmac_sha256(key, record.payload().as_bytes())?);
+    Ok(record)
+}
+
+fn dialog_text(request: &ConsentRequest, monitor: &str, region: Rect) -> String {
+    let minutes = request.duration.map(|duration| duration.as_secs().div_ceil(60));
+    match request.language {
+        Language::English => format!(
+            "{} wants to record your screen.\n\nPurpose: {}\nMonitor: {}\nArea: {}x{} at ({}, {})\nDuration: {}\n\nAllow the capture?",
+            request.application,
+            request.purpose,
+            monitor,
+            region.width,
+            region.height,
+            region.left,
+            region.top,
+            minutes.map_or("until stopped by the application".to_owned(), |minutes| format!("{} min", minutes)),
+        ),
+        Language::Portuguese => format!(
+            "{} quer gravar a sua tela.\n\nFinalidade: {}\nMonitor: {}\nÁrea: {}x{} em ({}, {})\nDuração: {}\n\nPermitir a captura?",
+            request.application,
+            request.purpose,
+            monitor,
+            region.width,
+            region.height,
+            region.left,
+            region.top,
+            minutes.map_or("até o aplicativo encerrar".to_owned(), |minutes| format!("{} min", minutes)),
+        ),
+    }
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> CaptureResult<[u8; 32]> {
+    let mut output = [0u8; 32];
+    unsafe { BCryptHash(BCRYPT_HMAC_SHA256_ALG_HANDLE, Some(key), data, &mut output)? };
+    Ok(output)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
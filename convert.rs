@@ -0,0 +1,509 @@
+//! Caminhos de conversão em CPU (remoção de pitch e conversões de pixel)
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use windows::Win32::Graphics::Dxgi::Common::{
+    DXGI_FORMAT, DXGI_FORMAT_B8G8R8A8_UNORM, DXGI_FORMAT_R10G10B10A2_UNORM, DXGI_FORMAT_R16G16B16A16_FLOAT,
+    DXGI_FORMAT_R8G8B8A8_UNORM,
+};
+
+use crate::error::{CaptureError, CaptureResult};
+use crate::outputs::ColorSpace;
+use crate::pixel_format::{layout_of, PixelFormat};
+use crate::Result;
+
+/// Luminância de 1.0 em scRGB, em nits
+const SCRGB_WHITE_NITS: f32 = 80.0;
+
+/// Primárias BT.709 para BT.2020 (ITU-R BT.2087)
+const BT709_TO_BT2020: [[f32; 3]; 3] = [
+    [0.627_404, 0.329_283, 0.043_313],
+    [0.069_097, 0.919_540, 0.011_362],
+    [0.016_391, 0.088_013, 0.895_595],
+];
+
+/// Primárias BT.2020 para BT.709
+const BT2020_TO_BT709: [[f32; 3]; 3] = [
+    [1.660_491, -0.587_641, -0.072_850],
+    [-0.124_550, 1.132_900, -0.008_349],
+    [-0.018_151, -0.100_579, 1.118_730],
+];
+
+/// Tamanho mínimo (em bytes de saída) para dividir o trabalho entre threads
+#[cfg(feature = "rayon")]
+const PARALLEL_MIN_BYTES: usize = 3840 * 2160 * 4;
+
+/// Número mínimo de linhas por faixa horizontal
+#[cfg(feature = "rayon")]
+const MIN_BAND_ROWS: usize = 16;
+
+/// Bytes por linha e número de linhas de um quadro sem padding; nos formatos planares
+/// (NV12, P010) o plano UV vem logo após o Y, com a mesma largura em bytes
+pub(crate) fn frame_layout(format: DXGI_FORMAT, width: u32, height: u32) -> (usize, usize) {
+    let layout = layout_of(format);
+    (layout.min_stride(width), layout.rows(height))
+}
+
+/// Executa `f` sobre faixas horizontais de `dst`, em paralelo quando o quadro é grande.
+///
+/// `f` recebe o índice da primeira linha da faixa e as linhas da faixa.
+pub(crate) fn for_each_row_band<F>(dst: &mut [u8], row_bytes: usize, f: F)
+where
+    F: Fn(usize, &mut [u8]) + Send + Sync,
+{
+    if row_bytes == 0 || dst.is_empty() {
+        return;
+    }
+
+    #[cfg(feature = "rayon")]
+    {
+        let rows = dst.len() / row_bytes;
+        if dst.len() >= PARALLEL_MIN_BYTES && rows >= MIN_BAND_ROWS * 2 {
+            let band_rows = rows
+                .div_ceil(rayon::current_num_threads())
+                .max(MIN_BAND_ROWS);
+
+            dst.par_chunks_mut(band_rows * row_bytes)
+                .enumerate()
+                .for_each(|(band, chunk)| f(band * band_rows, chunk));
+            return;
+        }
+    }
+
+    f(0, dst);
+}
+
+/// Rejeita um pitch menor que a linha, que faria as linhas se sobreporem em `copy_rows`
+pub(crate) fn check_pitch(pitch: usize, row_bytes: usize) -> CaptureResult<()> {
+    if pitch < row_bytes {
+        return Err(CaptureError::Conversion(format!(
+            "mapped surface pitch ({} bytes) is smaller than the row ({} bytes)",
+            pitch, row_bytes
+        )));
+    }
+    Ok(())
+}
+
+/// Copia linhas de uma superfície mapeada para um buffer contínuo, removendo o padding do pitch
+pub(crate) fn copy_rows(src: &[u8], src_pitch: usize, dst: &mut [u8], row_bytes: usize) {
+    for_each_row_band(dst, row_bytes, |first_row, band| {
+        for (i, dst_row) in band.chunks_exact_mut(row_bytes).enumerate() {
+            let offset = (first_row + i) * src_pitch;
+            dst_row.copy_from_slice(&src[offset..offset + row_bytes]);
+        }
+    });
+}
+
+/// Tamanho em bytes de um quadro I420 (Y em resolução cheia, U e V em 2x2)
+pub fn i420_size(width: u32, height: u32) -> usize {
+    let (width, height) = (width as usize, height as usize);
+    let chroma = width.div_ceil(2) * height.div_ceil(2);
+    width * height + chroma * 2
+}
+
+/// Converte um quadro BGRA/RGBA 8-bit em I420 (BT.601, faixa limitada)
+pub fn to_i420(src: &[u8], format: DXGI_FORMAT, width: u32, height: u32, dst: &mut [u8]) -> Result<()> {
+    let (r_index, b_index) = match format {
+        DXGI_FORMAT_B8G8R8A8_UNORM => (2, 0),
+        DXGI_FORMAT_R8G8B8A8_UNORM => (0, 2),
+        _ => return Err(format!("Formato não suportado para conversão I420: {:?}", format).into()),
+    };
+
+    let (w, h) = (width as usize, height as usize);
+    if src.len() < PixelFormat::Bgra8.buffer_size(width, height) || dst.len() < i420_size(width, height) {
+        return Err("Buffer insuficiente para conversão I420".into());
+    }
+    if w == 0 || h == 0 {
+        return Ok(());
+    }
+
+    let rgb = |x: usize, y: usize| -> (i32, i32, i32) {
+        let p = &src[(y * w + x) * 4..];
+        (p[r_index] as i32, p[1] as i32, p[b_index] as i32)
+    };
+
+    let (cw, ch) = (w.div_ceil(2), h.div_ceil(2));
+    let (y_plane, chroma) = dst.split_at_mut(w * h);
+    let (u_plane, v_plane) = chroma.split_at_mut(cw * ch);
+
+    for_each_row_band(y_plane, w, |first_row, band| {
+        for (i, row) in band.chunks_exact_mut(w).enumerate() {
+            let y = first_row + i;
+            for (x, out) in row.iter_mut().enumerate() {
+                let (r, g, b) = rgb(x, y);
+                *out = (((66 * r + 129 * g + 25 * b + 128) >> 8) + 16) as u8;
+            }
+        }
+    });
+
+    // Média do bloco 2x2 (repetindo a última linha/coluna em dimensões ímpares)
+    let block_average = |cx: usize, cy: usize| -> (i32, i32, i32) {
+        let (x0, y0) = (cx * 2, cy * 2);
+        let (x1, y1) = ((x0 + 1).min(w - 1), (y0 + 1).min(h - 1));
+        let samples = [rgb(x0, y0), rgb(x1, y0), rgb(x0, y1), rgb(x1, y1)];
+        let sum = samples.iter().fold((0, 0, 0), |acc, s| (acc.0 + s.0, acc.1 + s.1, acc.2 + s.2));
+        ((sum.0 + 2) / 4, (sum.1 + 2) / 4, (sum.2 + 2) / 4)
+    };
+
+    for_each_row_band(u_plane, cw, |first_row, band| {
+        for (i, row) in band.chunks_exact_mut(cw).enumerate() {
+            for (cx, out) in row.iter_mut().enumerate() {
+                let (r, g, b) = block_average(cx, first_row + i);
+                *out = (((-38 * r - 74 * g + 112 * b + 128) >> 8) + 128) as u8;
+            }
+        }
+    });
+
+    for_each_row_band(v_plane, cw, |first_row, band| {
+        for (i, row) in band.chunks_exact_mut(cw).enumerate() {
+            for (cx, out) in row.iter_mut().enumerate() {
+                let (r, g, b) = block_average(cx, first_row + i);
+                *out = (((112 * r - 94 * g - 18 * b + 128) >> 8) + 128) as u8;
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Converte um quadro BGRA/RGBA 8-bit em BGR 24-bit (sem alfa)
+pub fn to_bgr24(src: &[u8], format: DXGI_FORMAT, width: u32, height: u32, dst: &mut [u8]) -> Result<()> {
+    let (r_index, b_index) = match format {
+        DXGI_FORMAT_B8G8R8A8_UNORM => (2, 0),
+        DXGI_FORMAT_R8G8B8A8_UNORM => (0, 2),
+        _ => return Err(format!("Formato não suportado para conversão BGR: {:?}", format).into()),
+    };
+
+    let (w, h) = (width as usize, height as usize);
+    let src_stride = PixelFormat::Bgra8.min_stride(width);
+    if src.len() < src_stride * h || dst.len() < w * h * 3 {
+        return Err("Buffer insuficiente para conversão BGR".into());
+    }
+
+    for_each_row_band(&mut dst[..w * h * 3], w * 3, |first_row, band| {
+        for (i, row) in band.chunks_exact_mut(w * 3).enumerate() {
+            let src_row = &src[(first_row + i) * src_stride..(first_row + i + 1) * src_stride];
+            for (out, pixel) in row.chunks_exact_mut(3).zip(src_row.chunks_exact(4)) {
+                out[0] = pixel[b_index];
+                out[1] = pixel[1];
+                out[2] = pixel[r_index];
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Função que lê um pixel de `format` como RGBA 8-bit sRGB; `None` para formatos sem leitor.
+///
+/// R16G16B16A16_FLOAT é tratado como linear e codificado em sRGB (só vale para conteúdo SDR).
+pub(crate) fn rgba8_reader(format: DXGI_FORMAT) -> Option<fn(&[u8]) -> [u8; 4]> {
+    match format {
+        DXGI_FORMAT_B8G8R8A8_UNORM => Some(|p| [p[2], p[1], p[0], p[3]]),
+        DXGI_FORMAT_R8G8B8A8_UNORM => Some(|p| [p[0], p[1], p[2], p[3]]),
+        DXGI_FORMAT_R16G16B16A16_FLOAT => Some(|p| {
+            let channel = |i: usize| half_to_f32(u16::from_le_bytes([p[i * 2], p[i * 2 + 1]]));
+            let alpha = (channel(3).clamp(0.0, 1.0) * 255.0).round() as u8;
+            [linear_to_srgb(channel(0)), linear_to_srgb(channel(1)), linear_to_srgb(channel(2)), alpha]
+        }),
+        _ => None,
+    }
+}
+
+/// Converte um float de meia precisão (IEEE 754 binary16) em `f32`
+pub(crate) fn half_to_f32(half: u16) -> f32 {
+    let sign = if half & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((half >> 10) & 0x1f) as i32;
+    let mantissa = (half & 0x3ff) as f32;
+    match exponent {
+        0 => sign * mantissa * 2f32.powi(-24),
+        0x1f if mantissa == 0.0 => sign * f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => sign * (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
+
+/// Codifica um valor linear em sRGB 8-bit
+pub(crate) fn linear_to_srgb(value: f32) -> u8 {
+    let value = if value.is_nan() { 0.0 } else { value.clamp(0.0, 1.0) };
+    (srgb_encode(value) * 255.0).round() as u8
+}
+
+/// Formato em que `convert_color_space` entrega cada espaço de cor
+pub fn color_space_format(color_space: ColorSpace) -> Option<DXGI_FORMAT> {
+    match color_space {
+        ColorSpace::Srgb => Some(DXGI_FORMAT_B8G8R8A8_UNORM),
+        ColorSpace::ScRgb => Some(DXGI_FORMAT_R16G16B16A16_FLOAT),
+        ColorSpace::Hdr10 => Some(DXGI_FORMAT_R10G10B10A2_UNORM),
+        ColorSpace::Other(_) => None,
+    }
+}
+
+/// Converte um quadro RGB de `from` para `to`, no formato de `color_space_format(to)`.
+///
+/// Os pixels passam por scRGB linear (1.0 = 80 nits): sRGB é decodificado pela curva sRGB,
+/// HDR10 pela PQ com as primárias convertidas de BT.2020 para BT.709. Conteúdo HDR levado
+/// para sRGB é apenas recortado em 1.0, sem tone mapping.
+pub fn convert_color_space(
+    src: &[u8],
+    format: DXGI_FORMAT,
+    from: ColorSpace,
+    to: ColorSpace,
+    width: u32,
+    height: u32,
+) -> Result<(Vec<u8>, DXGI_FORMAT)> {
+    let read = channel_reader(format)
+        .ok_or_else(|| format!("Formato não suportado para conversão de espaço de cor: {:?}", format))?;
+    let unsupported = |space: ColorSpace| format!("Espaço de cor não suportado na conversão: {:?}", space);
+    if matches!(from, ColorSpace::Other(_)) {
+        return Err(unsupported(from).into());
+    }
+    let target = color_space_format(to).ok_or_else(|| unsupported(to))?;
+
+    let (w, h) = (width as usize, height as usize);
+    let src_stride = layout_of(format).min_stride(width);
+    let dst_stride = layout_of(target).min_stride(width);
+    if src.len() < src_stride * h {
+        return Err("Buffer menor que o quadro".into());
+    }
+    let src_pixel = src_stride / w.max(1);
+    let dst_pixel = dst_stride / w.max(1);
+
+    let mut dst = vec![0u8; dst_stride * h];
+    if w == 0 || h == 0 {
+        return Ok((dst, target));
+    }
+    for_each_row_band(&mut dst, dst_stride, |first_row, band| {
+        for (i, row) in band.chunks_exact_mut(dst_stride).enumerate() {
+            let src_row = &src[(first_row + i) * src_stride..(first_row + i + 1) * src_stride];
+            for (out, pixel) in row.chunks_exact_mut(dst_pixel).zip(src_row.chunks_exact(src_pixel)) {
+                let [r, g, b, a] = read(pixel);
+                let linear = to_linear([r, g, b], from);
+                write_pixel(out, from_linear(linear, to), a, to);
+            }
+        }
+    });
+
+    Ok((dst, target))
+}
+
+/// Pixels RGB de 16 bits em big-endian (a ordem do PNG), sem alfa, codificados em `to` (sRGB ou
+/// HDR10). Preserva a precisão de quadros FP16 e de 10 bits que `rgba8_reader` truncaria.
+#[cfg(feature = "png")]
+pub(crate) fn to_rgb16(src: &[u8], format: DXGI_FORMAT, from: ColorSpace, to: ColorSpace, width: u32, height: u32) -> Result<Vec<u8>> {
+    let read = channel_reader(format).ok_or_else(|| format!("Formato não suportado para 16 bits: {:?}", format))?;
+    if !matches!(to, ColorSpace::Srgb | ColorSpace::Hdr10) || matches!(from, ColorSpace::Other(_)) {
+        return Err(format!("Conversão não suportada para 16 bits: {:?} para {:?}", from, to).into());
+    }
+
+    let (w, h) = (width as usize, height as usize);
+    let src_stride = layout_of(format).min_stride(width);
+    if src.len() < src_stride * h {
+        return Err("Buffer menor que o quadro".into());
+    }
+    let src_pixel = src_stride / w.max(1);
+
+    let mut dst = vec![0u8; w * h * 6];
+    if w == 0 || h == 0 {
+        return Ok(dst);
+    }
+    for_each_row_band(&mut dst, w * 6, |first_row, band| {
+        for (i, row) in band.chunks_exact_mut(w * 6).enumerate() {
+            let src_row = &src[(first_row + i) * src_stride..(first_row + i + 1) * src_stride];
+            for (out, pixel) in row.chunks_exact_mut(6).zip(src_row.chunks_exact(src_pixel)) {
+                let [r, g, b, _] = read(pixel);
+                // Mesmo espaço: só reescala, sem passar pela conversão em ponto flutuante
+                let rgb = if from == to { [r, g, b] } else { from_linear(to_linear([r, g, b], from), to) };
+                for (channel, value) in out.chunks_exact_mut(2).zip(rgb) {
+                    let value = if value.is_nan() { 0.0 } else { value.clamp(0.0, 1.0) };
+                    channel.copy_from_slice(&((value * 65535.0).round() as u16).to_be_bytes());
+                }
+            }
+        }
+    });
+
+    Ok(dst)
+}
+
+/// Leitor dos canais de um pixel como RGBA, normalizados em 0..1 nos formatos UNORM
+fn channel_reader(format: DXGI_FORMAT) -> Option<fn(&[u8]) -> [f32; 4]> {
+    match format {
+        DXGI_FORMAT_B8G8R8A8_UNORM => Some(|p| [p[2], p[1], p[0], p[3]].map(|v| v as f32 / 255.0)),
+        DXGI_FORMAT_R8G8B8A8_UNORM => Some(|p| [p[0], p[1], p[2], p[3]].map(|v| v as f32 / 255.0)),
+        DXGI_FORMAT_R10G10B10A2_UNORM => Some(|p| {
+            let value = u32::from_le_bytes([p[0], p[1], p[2], p[3]]);
+            let channel = |shift: u32| ((value >> shift) & 0x3ff) as f32 / 1023.0;
+            [channel(0), channel(10), channel(20), (value >> 30) as f32 / 3.0]
+        }),
+        DXGI_FORMAT_R16G16B16A16_FLOAT => Some(|p| {
+            let channel = |i: usize| half_to_f32(u16::from_le_bytes([p[i * 2], p[i * 2 + 1]]));
+            [channel(0), channel(1), channel(2), channel(3)]
+        }),
+        _ => None,
+    }
+}
+
+/// Decodifica para scRGB linear (primárias BT.709, 1.0 = 80 nits)
+fn to_linear(rgb: [f32; 3], color_space: ColorSpace) -> [f32; 3] {
+    match color_space {
+        ColorSpace::Srgb => rgb.map(srgb_to_linear),
+        ColorSpace::Hdr10 => transform(BT2020_TO_BT709, rgb.map(|v| pq_to_nits(v) / SCRGB_WHITE_NITS)),
+        ColorSpace::ScRgb | ColorSpace::Other(_) => rgb,
+    }
+}
+
+/// Codifica scRGB linear no espaço `color_space`
+fn from_linear(rgb: [f32; 3], color_space: ColorSpace) -> [f32; 3] {
+    match color_space {
+        ColorSpace::Srgb => rgb.map(|v| srgb_encode(v.clamp(0.0, 1.0))),
+        ColorSpace::Hdr10 => transform(BT709_TO_BT2020, rgb).map(|v| nits_to_pq(v * SCRGB_WHITE_NITS)),
+        ColorSpace::ScRgb | ColorSpace::Other(_) => rgb,
+    }
+}
+
+fn write_pixel(out: &mut [u8], rgb: [f32; 3], alpha: f32, color_space: ColorSpace) {
+    let unorm = |v: f32, max: f32| (v.clamp(0.0, 1.0) * max).round() as u32;
+    match color_space {
+        ColorSpace::Srgb => {
+            let channel = |v: f32| unorm(v, 255.0) as u8;
+            out.copy_from_slice(&[channel(rgb[2]), channel(rgb[1]), channel(rgb[0]), channel(alpha)]);
+        }
+        ColorSpace::Hdr10 => {
+            let channel = |v: f32| unorm(v, 1023.0);
+            let value = channel(rgb[0]) | channel(rgb[1]) << 10 | channel(rgb[2]) << 20 | unorm(alpha, 3.0) << 30;
+            out.copy_from_slice(&value.to_le_bytes());
+        }
+        ColorSpace::ScRgb | ColorSpace::Other(_) => {
+            for (channel, value) in out.chunks_exact_mut(2).zip([rgb[0], rgb[1], rgb[2], alpha]) {
+                channel.copy_from_slice(&f32_to_half(value).to_le_bytes());
+            }
+        }
+    }
+}
+
+fn transform(matrix: [[f32; 3]; 3], rgb: [f32; 3]) -> [f32; 3] {
+    matrix.map(|row| row[0] * rgb[0] + row[1] * rgb[1] + row[2] * rgb[2])
+}
+
+/// Decodifica um valor sRGB (0..1) para linear
+pub(crate) fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.040_45 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn srgb_encode(value: f32) -> f32 {
+    if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+// Constantes da curva PQ (SMPTE ST 2084)
+const PQ_M1: f32 = 0.159_301_76;
+const PQ_M2: f32 = 78.843_75;
+const PQ_C1: f32 = 0.835_937_5;
+const PQ_C2: f32 = 18.851_563;
+const PQ_C3: f32 = 18.687_5;
+
+/// Decodifica um valor PQ (0..1) em nits
+pub(crate) fn pq_to_nits(value: f32) -> f32 {
+    let p = value.clamp(0.0, 1.0).powf(1.0 / PQ_M2);
+    let y = (p - PQ_C1).max(0.0) / (PQ_C2 - PQ_C3 * p);
+    10_000.0 * y.powf(1.0 / PQ_M1)
+}
+
+/// Codifica uma luminância em nits na curva PQ (0..1)
+pub(crate) fn nits_to_pq(nits: f32) -> f32 {
+    let y = (nits / 10_000.0).clamp(0.0, 1.0).powf(PQ_M1);
+    ((PQ_C1 + PQ_C2 * y) / (1.0 + PQ_C3 * y)).powf(PQ_M2)
+}
+
+/// Converte um `f32` em float de meia precisão, arredondando para o mais próximo
+pub(crate) fn f32_to_half(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    if value.is_nan() {
+        return sign | 0x7e00;
+    }
+    let exponent = ((bits >> 23) & 0xff) as i32 - 127 + 15;
+    let mantissa = bits & 0x7f_ffff;
+    if exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+    if exponent <= 0 {
+        // Subnormal: a mantissa implícita entra no deslocamento
+        if exponent < -10 {
+            return sign;
+        }
+        let shift = (14 - exponent) as u32;
+        let mantissa = mantissa | 0x80_0000;
+        return sign | ((mantissa + (1 << (shift - 1))) >> shift) as u16;
+    }
+    // O carry do arredondamento pode subir o expoente, o que também é o resultado correto
+    (sign | (exponent as u16) << 10 | (mantissa >> 13) as u16) + ((mantissa >> 12) & 1) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Superfície com `rows` linhas de `row_bytes` bytes e `pitch - row_bytes` de padding
+    /// preenchido com 0xEE; a última linha não tem padding, como num mapeamento real
+    fn padded_surface(row_bytes: usize, pitch: usize, rows: usize) -> Vec<u8> {
+        let mut src = vec![0xEE; pitch * (rows - 1) + row_bytes];
+        for (row, line) in src.chunks_mut(pitch).enumerate() {
+            for (col, byte) in line[..row_bytes].iter_mut().enumerate() {
+                *byte = (row * 31 + col) as u8 & 0x7F;
+            }
+        }
+        src
+    }
+
+    fn packed(row_bytes: usize, rows: usize) -> Vec<u8> {
+        (0..rows)
+            .flat_map(|row| (0..row_bytes).map(move |col| (row * 31 + col) as u8 & 0x7F))
+            .collect()
+    }
+
+    #[test]
+    fn check_pitch_rejects_pitch_smaller_than_row() {
+        let err = check_pitch(15, 16).unwrap_err();
+        assert!(matches!(err, CaptureError::Conversion(_)));
+    }
+
+    #[test]
+    fn check_pitch_accepts_pitch_equal_to_row() {
+        assert!(check_pitch(16, 16).is_ok());
+        assert!(check_pitch(64, 16).is_ok());
+    }
+
+    #[test]
+    fn copy_rows_with_tight_pitch_copies_everything() {
+        let src = packed(12, 5);
+        let mut dst = vec![0; src.len()];
+        copy_rows(&src, 12, &mut dst, 12);
+        assert_eq!(dst, src);
+    }
+
+    #[test]
+    fn copy_rows_strips_pitch_padding() {
+        let src = padded_surface(12, 20, 5);
+        let mut dst = vec![0; 12 * 5];
+        copy_rows(&src, 20, &mut dst, 12);
+        assert_eq!(dst, packed(12, 5));
+    }
+
+    /// Grande o bastante para passar pelo caminho em faixas paralelas, quando ativo
+    #[test]
+    fn copy_rows_strips_pitch_padding_across_bands() {
+        let (row_bytes, pitch, rows) = (3840 * 4, 3840 * 4 + 256, 2160);
+        let src = padded_surface(row_bytes, pitch, rows);
+        let mut dst = vec![0; row_bytes * rows];
+        copy_rows(&src, pitch, &mut dst, row_bytes);
+        assert!(dst == packed(row_bytes, rows));
+    }
+}
@@ -0,0 +1,121 @@
+//! Conversões entre o desktop virtual, o monitor capturado e a área cliente de janelas
+//!
+//! A captura trabalha em pixels físicos relativos ao monitor (`Rect`), o desktop virtual tem
+//! origem no canto do monitor principal (e coordenadas negativas à esquerda/acima dele) e as APIs
+//! de janela do Win32 devolvem pixels lógicos quando a thread não tem DPI awareness por monitor.
+//! As funções daqui consultam o Windows com a thread temporariamente em
+//! `PER_MONITOR_AWARE_V2`, então sempre trabalham em pixels físicos, qualquer que seja o DPI
+//! awareness do processo ou da janela alvo. Para pixels lógicos use `window_dpi` com
+//! `Rect::physical_to_logical`/`Point::physical_to_logical`.
+
+use std::ffi::c_void;
+use std::mem;
+
+use windows::Win32::Foundation::{HWND, POINT, RECT};
+use windows::Win32::Graphics::Dwm::{DwmGetWindowAttribute, DWMWA_EXTENDED_FRAME_BOUNDS};
+use windows::Win32::Graphics::Gdi::{ClientToScreen, ScreenToClient};
+use windows::Win32::UI::HiDpi::{
+    GetDpiForWindow, SetThreadDpiAwarenessContext, DPI_AWARENESS_CONTEXT, DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2,
+};
+use windows::Win32::UI::WindowsAndMessaging::{GetClientRect, GetWindowRect};
+
+use crate::error::{CaptureError, CaptureResult};
+use crate::geometry::{Point, Rect, RectError};
+
+/// Ponto do desktop virtual em coordenadas do monitor com origem `output_origin`
+pub fn virtual_to_output(point: Point, output_origin: Point) -> Result<Point, RectError> {
+    let x = point.x.checked_sub(output_origin.x).ok_or(RectError::Overflow)?;
+    let y = point.y.checked_sub(output_origin.y).ok_or(RectError::Overflow)?;
+    Ok(Point::new(x, y))
+}
+
+/// Ponto do monitor com origem `output_origin` em coordenadas do desktop virtual
+pub fn output_to_virtual(point: Point, output_origin: Point) -> Result<Point, RectError> {
+    let x = point.x.checked_add(output_origin.x).ok_or(RectError::Overflow)?;
+    let y = point.y.checked_add(output_origin.y).ok_or(RectError::Overflow)?;
+    Ok(Point::new(x, y))
+}
+
+/// DPI do monitor em que a janela está (96 = 100%)
+pub fn window_dpi(hwnd: HWND) -> u32 {
+    unsafe { GetDpiForWindow(hwnd) }.max(1)
+}
+
+/// Retângulo visível da janela no desktop virtual. `DWMWA_EXTENDED_FRAME_BOUNDS` descarta as
+/// bordas invisíveis de redimensionamento que `GetWindowRect` inclui no Windows 10+
+pub fn window_rect(hwnd: HWND) -> CaptureResult<Rect> {
+    let _dpi = PhysicalPixels::enter();
+    let mut rect = RECT::default();
+    unsafe {
+        let attribute = &mut rect as *mut RECT as *mut c_void;
+        if DwmGetWindowAttribute(hwnd, DWMWA_EXTENDED_FRAME_BOUNDS, attribute, mem::size_of::<RECT>() as u32).is_err() {
+            GetWindowRect(hwnd, &mut rect)?;
+        }
+    }
+    Ok(Rect::from_edges(rect.left, rect.top, rect.right, rect.bottom)?)
+}
+
+/// Área cliente da janela (sem barra de título e bordas) no desktop virtual
+pub fn client_rect(hwnd: HWND) -> CaptureResult<Rect> {
+    let _dpi = PhysicalPixels::enter();
+    let mut rect = RECT::default();
+    let mut origin = POINT::default();
+    unsafe {
+        GetClientRect(hwnd, &mut rect)?;
+        if !ClientToScreen(hwnd, &mut origin).as_bool() {
+            return Err(CaptureError::Windows(windows::core::Error::from_win32()));
+        }
+    }
+    Ok(Rect::new(origin.x, origin.y, (rect.right - rect.left) as u32, (rect.bottom - rect.top) as u32))
+}
+
+/// Ponto da área cliente da janela em coordenadas do desktop virtual
+pub fn client_to_screen(hwnd: HWND, point: Point) -> CaptureResult<Point> {
+    let _dpi = PhysicalPixels::enter();
+    let mut point = POINT { x: point.x, y: point.y };
+    if !unsafe { ClientToScreen(hwnd, &mut point) }.as_bool() {
+        return Err(CaptureError::Windows(windows::core::Error::from_win32()));
+    }
+    Ok(Point::new(point.x, point.y))
+}
+
+/// Ponto do desktop virtual em coordenadas da área cliente da janela (ex.: para `PostMessage`
+/// de cliques); pode ficar fora da área cliente
+pub fn screen_to_client(hwnd: HWND, point: Point) -> CaptureResult<Point> {
+    let _dpi = PhysicalPixels::enter();
+    let mut point = POINT { x: point.x, y: point.y };
+    if !unsafe { ScreenToClient(hwnd, &mut point) }.as_bool() {
+        return Err(CaptureError::Windows(windows::core::Error::from_win32()));
+    }
+    Ok(Point::new(point.x, point.y))
+}
+
+/// Ponto da área cliente em coordenadas do monitor com origem `output_origin`
+pub fn client_to_output(hwnd: HWND, point: Point, output_origin: Point) -> CaptureResult<Point> {
+    Ok(virtual_to_output(client_to_screen(hwnd, point)?, output_origin)?)
+}
+
+/// Ponto do monitor com origem `output_origin` em coordenadas da área cliente
+pub fn output_to_client(hwnd: HWND, point: Point, output_origin: Point) -> CaptureResult<Point> {
+    screen_to_client(hwnd, output_to_virtual(point, output_origin)?)
+}
+
+/// Mantém a thread em `PER_MONITOR_AWARE_V2` e restaura o contexto anterior ao sair
+struct PhysicalPixels(DPI_AWARENESS_CONTEXT);
+
+impl PhysicalPixels {
+    fn enter() -> Self {
+        Self(unsafe { SetThreadDpiAwarenessContext(DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2) })
+    }
+}
+
+impl Drop for PhysicalPixels {
+    fn drop(&mut self) {
+        // Contexto nulo: a chamada original falhou (Windows anterior ao 10 1607) e nada mudou
+        if !self.0.is_invalid() {
+            unsafe {
+                SetThreadDpiAwarenessContext(self.0);
+            }
+        }
+    }
+}
@@ -0,0 +1,66 @@
+//! Forma e posição do cursor, sem os pixels do desktop
+//!
+//! A duplicação informa o cursor separadamente da imagem: a posição quando o mouse se move e a
+//! forma quando ela muda. Implementações de área de trabalho remota desenham o cursor como uma
+//! camada própria no cliente, então `DxgiCapture::capture_cursor` entrega só essas informações,
+//! sem copiar a textura do desktop.
+
+use crate::geometry::{Point, Size};
+
+/// Formato dos pixels de `CursorShape::data` (`DXGI_OUTDUPL_POINTER_SHAPE_TYPE`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum CursorShapeKind {
+    /// 1 bit por pixel: máscara AND na metade de cima e máscara XOR na de baixo (`height` conta as
+    /// duas metades)
+    Monochrome,
+    /// BGRA de 32 bits com alfa
+    Color,
+    /// BGRA de 32 bits em que o alfa é uma máscara: 0 substitui o pixel da tela e 0xFF faz XOR com ele
+    MaskedColor,
+}
+
+/// Imagem do cursor como a duplicação a entrega
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CursorShape {
+    pub kind: CursorShapeKind,
+    pub width: u32,
+    pub height: u32,
+    /// Bytes por linha de `data`
+    pub pitch: u32,
+    /// Ponto da imagem que corresponde à posição do cursor
+    pub hotspot: Point,
+    pub data: Vec<u8>,
+}
+
+impl CursorShape {
+    /// Tamanho do cursor na tela (sem a máscara dobrada dos cursores monocromáticos)
+    pub fn size(&self) -> Size {
+        match self.kind {
+            CursorShapeKind::Monochrome => Size::new(self.width, self.height / 2),
+            CursorShapeKind::Color | CursorShapeKind::MaskedColor => Size::new(self.width, self.height),
+        }
+    }
+}
+
+/// Estado do cursor em uma aquisição de `capture_cursor`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CursorUpdate {
+    /// Posição do canto da imagem relativa ao monitor, se o cursor está visível neste monitor;
+    /// vale o último valor informado quando ele não se moveu
+    pub position: Option<Point>,
+    /// A posição ou a visibilidade mudou nesta aquisição
+    pub moved: bool,
+    /// Nova forma, só quando ela mudou desde o último `capture_cursor`
+    pub shape: Option<CursorShape>,
+    /// Valor de QPC da última atualização do mouse informada pela duplicação (0 se nenhuma)
+    pub update_time: i64,
+}
+
+impl CursorUpdate {
+    /// Nada mudou desde o último `capture_cursor`
+    pub fn is_empty(&self) -> bool {
+        !self.moved && self.shape.is_none()
+    }
+}
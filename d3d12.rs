@@ -0,0 +1,493 @@
+//! Backend experimental que entrega os quadros em um dispositivo D3D12 (feature `d3d12`)
+//!
+//! A duplicação continua em D3D11: a região é copiada para uma textura criada com
+//! `SHARED_NTHANDLE` e aberta no dispositivo D3D12 por handle compartilhado. Um fence
+//! compartilhado ordena a cópia do D3D11 antes de qualquer trabalho na fila D3D12. Para leitura
+//! na CPU, a textura é copiada para um buffer em heap de readback.
+
+use std::mem::ManuallyDrop;
+use std::slice;
+
+use windows::core::*;
+use windows::Win32::Foundation::{CloseHandle, GENERIC_ALL, HANDLE};
+use windows::Win32::Graphics::Direct3D::D3D_FEATURE_LEVEL_11_0;
+use windows::Win32::Graphics::Direct3D11::*;
+use windows::Win32::Graphics::Direct3D12::*;
+use windows::Win32::Graphics::Dxgi::Common::*;
+use windows::Win32::Graphics::Dxgi::*;
+
+use crate::convert;
+use crate::error::{CaptureError, CaptureResult};
+use crate::frame::Frame;
+use crate::geometry::{Rect, Size};
+#[cfg(feature = "onnx")]
+use crate::onnx_tensor::{self, Normalization, TensorLayout};
+use crate::source::{Backend, ScreenSource};
+use crate::DxgiCapture;
+
+/// Textura compartilhada entre os dois dispositivos e o fence que sincroniza o acesso
+struct SharedSurface {
+    // Dispositivo D3D11 em que a textura foi criada; muda após uma reinicialização
+    owner: ID3D11Device,
+    size: Size,
+    format: DXGI_FORMAT,
+    d3d11_texture: ID3D11Texture2D,
+    d3d12_resource: ID3D12Resource,
+    d3d11_fence: ID3D11Fence,
+    d3d12_fence: ID3D12Fence,
+    fence_value: u64,
+}
+
+/// Buffer do tensor criado no D3D12 e aberto no D3D11, onde o compute shader o grava
+#[cfg(feature = "onnx")]
+struct SharedTensor {
+    owner: ID3D11Device,
+    len: usize,
+    d3d12_resource: ID3D12Resource,
+    d3d11_view: ID3D11UnorderedAccessView,
+    d3d11_fence: ID3D11Fence,
+    d3d12_fence: ID3D12Fence,
+    fence_value: u64,
+}
+
+/// Buffer de readback com o layout de cópia da textura compartilhada
+struct Readback {
+    buffer: ID3D12Resource,
+    footprint: D3D12_PLACED_SUBRESOURCE_FOOTPRINT,
+    size: Size,
+    format: DXGI_FORMAT,
+}
+
+/// Captura cujos quadros ficam disponíveis como `ID3D12Resource`.
+///
+/// Criada a partir de uma `DxgiCapture`, que continua responsável pela duplicação e pelas
+/// reinicializações; o recurso retornado por `capture_resource` pertence ao dispositivo D3D12
+/// de `device()` e é reutilizado entre capturas do mesmo tamanho.
+pub struct D3D12Capture {
+    capture: DxgiCapture,
+    device: ID3D12Device,
+    queue: ID3D12CommandQueue,
+    allocator: ID3D12CommandAllocator,
+    list: ID3D12GraphicsCommandList,
+    // Conclusão das cópias para o readback
+    fence: ID3D12Fence,
+    fence_value: u64,
+    shared: Option<SharedSurface>,
+    readback: Option<Readback>,
+    #[cfg(feature = "onnx")]
+    tensor: Option<SharedTensor>,
+    next_sequence: u64,
+}
+
+impl D3D12Capture {
+    /// Cria um dispositivo D3D12 no mesmo adaptador da duplicação
+    pub fn new(capture: DxgiCapture) -> CaptureResult<Self> {
+        let d3d11_device = capture.device().cloned().ok_or(CaptureError::NotInitialized)?;
+        let adapter = unsafe { d3d11_device.cast::<IDXGIDevice>()?.GetAdapter()? };
+
+        let mut device: Option<ID3D12Device> = None;
+        unsafe {
+            D3D12CreateDevice(&adapter, D3D_FEATURE_LEVEL_11_0, &mut device)?;
+        }
+        let device = device.ok_or(CaptureError::NotInitialized)?;
+        Self::with_device(capture, device)
+    }
+
+    /// Usa o dispositivo D3D12 do aplicativo, que precisa estar no adaptador da duplicação
+    pub fn with_device(capture: DxgiCapture, device: ID3D12Device) -> CaptureResult<Self> {
+        let queue_desc = D3D12_COMMAND_QUEUE_DESC {
+            Type: D3D12_COMMAND_LIST_TYPE_DIRECT,
+            ..Default::default()
+        };
+
+        unsafe {
+            let queue: ID3D12CommandQueue = device.CreateCommandQueue(&queue_desc)?;
+            let allocator: ID3D12CommandAllocator = device.CreateCommandAllocator(D3D12_COMMAND_LIST_TYPE_DIRECT)?;
+            let list: ID3D12GraphicsCommandList = device.CreateCommandList(0, D3D12_COMMAND_LIST_TYPE_DIRECT, &allocator, None)?;
+            list.Close()?;
+            let fence: ID3D12Fence = device.CreateFence(0, D3D12_FENCE_FLAG_NONE)?;
+
+            info!("Backend D3D12 criado");
+            Ok(Self {
+                capture,
+                device,
+                queue,
+                allocator,
+                list,
+                fence,
+                fence_value: 0,
+                shared: None,
+                readback: None,
+                #[cfg(feature = "onnx")]
+                tensor: None,
+                next_sequence: 0,
+            })
+        }
+    }
+
+    /// Dispositivo D3D12 dos recursos retornados
+    pub fn device(&self) -> &ID3D12Device {
+        &self.device
+    }
+
+    /// Fila em que as cópias são executadas
+    pub fn queue(&self) -> &ID3D12CommandQueue {
+        &self.queue
+    }
+
+    /// Captura D3D11 subjacente (formato, cor, tela cheia...)
+    pub fn capture(&self) -> &DxgiCapture {
+        &self.capture
+    }
+
+    pub fn capture_mut(&mut self) -> &mut DxgiCapture {
+        &mut self.capture
+    }
+
+    /// Captura a região para um recurso D3D12 (estado `COMMON`).
+    ///
+    /// A fila de `queue()` já aguarda a cópia do D3D11; comandos em outras filas devem
+    /// esperar por ela com um fence próprio.
+    pub fn capture_resource(&mut self, region: Rect) -> CaptureResult<ID3D12Resource> {
+        let texture = self.capture.capture_texture(region)?;
+        let format = self.capture.format();
+        let d3d11_device = self.capture.device().cloned().ok_or(CaptureError::NotInitialized)?;
+        let context = self.capture.context().cloned().ok_or(CaptureError::NotInitialized)?;
+
+        let reusable = self
+            .shared
+            .as_ref()
+            .is_some_and(|shared| shared.owner == d3d11_device && shared.size == region.size() && shared.format == format);
+        if !reusable {
+            self.shared = None;
+            self.shared = Some(self.create_shared(&d3d11_device, region.size(), format)?);
+        }
+        let shared = self.shared.as_mut().ok_or(CaptureError::NotInitialized)?;
+
+        shared.fence_value += 1;
+        unsafe {
+            context.CopyResource(&shared.d3d11_texture, &texture);
+            context.cast::<ID3D11DeviceContext4>()?.Signal(&shared.d3d11_fence, shared.fence_value)?;
+            self.queue.Wait(&shared.d3d12_fence, shared.fence_value)?;
+        }
+
+        Ok(shared.d3d12_resource.clone())
+    }
+
+    /// Cria a textura e o fence compartilhados no dispositivo D3D11 atual e os abre no D3D12
+    fn create_shared(&self, owner: &ID3D11Device, size: Size, format: DXGI_FORMAT) -> CaptureResult<SharedSurface> {
+        let desc = D3D11_TEXTURE2D_DESC {
+            Width: size.width,
+            Height: size.height,
+            MipLevels: 1,
+            ArraySize: 1,
+            Format: format,
+            SampleDesc: DXGI_SAMPLE_DESC {
+                Count: 1,
+                Quality: 0,
+            },
+            Usage: D3D11_USAGE_DEFAULT,
+            BindFlags: D3D11_BIND_SHADER_RESOURCE.0 as u32,
+            CPUAccessFlags: 0,
+            MiscFlags: (D3D11_RESOURCE_MISC_SHARED.0 | D3D11_RESOURCE_MISC_SHARED_NTHANDLE.0) as u32,
+        };
+
+        unsafe {
+            let mut d3d11_texture: Option<ID3D11Texture2D> = None;
+            owner.CreateTexture2D(&desc, None, Some(&mut d3d11_texture))?;
+            let d3d11_texture = d3d11_texture.ok_or(CaptureError::NotInitialized)?;
+            let handle = d3d11_texture.cast::<IDXGIResource1>()?.CreateSharedHandle(
+                None,
+                DXGI_SHARED_RESOURCE_READ | DXGI_SHARED_RESOURCE_WRITE,
+                PCWSTR::null(),
+            )?;
+            let d3d12_resource = open_shared::<ID3D12Resource>(&self.device, handle)?;
+
+            let d3d11_fence = create_shared_fence(owner)?;
+            let handle = d3d11_fence.CreateSharedHandle(None, GENERIC_ALL.0, PCWSTR::null())?;
+            let d3d12_fence = open_shared::<ID3D12Fence>(&self.device, handle)?;
+
+            debug!("Superfície D3D11/D3D12 compartilhada criada: {}x{} {:?}", size.width, size.height, format);
+            Ok(SharedSurface {
+                owner: owner.clone(),
+                size,
+                format,
+                d3d11_texture,
+                d3d12_resource,
+                d3d11_fence,
+                d3d12_fence,
+                fence_value: 0,
+            })
+        }
+    }
+
+    /// Tensor f32 da região escalada para `size` (feature `onnx`, ver
+    /// `DxgiCapture::capture_tensor`) em um buffer D3D12 de `size.width * size.height * 3`
+    /// elementos, com a forma de `layout.shape(size)`.
+    ///
+    /// O buffer permite UAV e fica no estado `COMMON`, como o DirectML e o provedor DirectML do
+    /// ONNX Runtime (`CreateGPUAllocationFromD3DResource`) esperam; a fila de `queue()` já aguarda
+    /// o compute shader. É reutilizado enquanto o tamanho não mudar: a inferência precisa terminar
+    /// antes da próxima captura.
+    #[cfg(feature = "onnx")]
+    pub fn capture_tensor_resource(
+        &mut self,
+        region: Rect,
+        size: Size,
+        layout: TensorLayout,
+        normalization: Normalization,
+    ) -> CaptureResult<ID3D12Resource> {
+        let len = onnx_tensor::tensor_len(size)?;
+        // A captura pode reinicializar e trocar o dispositivo D3D11; o buffer é aberto depois dela
+        let texture = self.capture.capture_texture(region)?;
+        let d3d11_device = self.capture.device().cloned().ok_or(CaptureError::NotInitialized)?;
+        let context = self.capture.context().cloned().ok_or(CaptureError::NotInitialized)?;
+
+        let reusable = self
+            .tensor
+            .as_ref()
+            .is_some_and(|tensor| tensor.owner == d3d11_device && tensor.len == len);
+        if !reusable {
+            self.tensor = None;
+            self.tensor = Some(self.create_shared_tensor(&d3d11_device, len)?);
+        }
+        let tensor = self.tensor.as_mut().ok_or(CaptureError::NotInitialized)?;
+
+        self.capture.write_tensor(&texture, size, layout, normalization, &tensor.d3d11_view)?;
+        tensor.fence_value += 1;
+        unsafe {
+            context.cast::<ID3D11DeviceContext4>()?.Signal(&tensor.d3d11_fence, tensor.fence_value)?;
+            self.queue.Wait(&tensor.d3d12_fence, tensor.fence_value)?;
+        }
+
+        Ok(tensor.d3d12_resource.clone())
+    }
+
+    /// Cria o buffer compartilhado no D3D12 (o D3D11 não compartilha buffers) e o abre no
+    /// dispositivo D3D11 atual, com um fence compartilhado
+    #[cfg(feature = "onnx")]
+    fn create_shared_tensor(&self, owner: &ID3D11Device, len: usize) -> CaptureResult<SharedTensor> {
+        let heap = D3D12_HEAP_PROPERTIES {
+            Type: D3D12_HEAP_TYPE_DEFAULT,
+            ..Default::default()
+        };
+        let desc = D3D12_RESOURCE_DESC {
+            Dimension: D3D12_RESOURCE_DIMENSION_BUFFER,
+            Width: (len * 4) as u64,
+            Height: 1,
+            DepthOrArraySize: 1,
+            MipLevels: 1,
+            Format: DXGI_FORMAT_UNKNOWN,
+            SampleDesc: DXGI_SAMPLE_DESC {
+                Count: 1,
+                Quality: 0,
+            },
+            Layout: D3D12_TEXTURE_LAYOUT_ROW_MAJOR,
+            Flags: D3D12_RESOURCE_FLAG_ALLOW_UNORDERED_ACCESS,
+            ..Default::default()
+        };
+
+        unsafe {
+            let mut d3d12_resource: Option<ID3D12Resource> = None;
+            self.device.CreateCommittedResource(
+                &heap,
+                D3D12_HEAP_FLAG_SHARED,
+                &desc,
+                D3D12_RESOURCE_STATE_COMMON,
+                None,
+                &mut d3d12_resource,
+            )?;
+            let d3d12_resource = d3d12_resource.ok_or(CaptureError::NotInitialized)?;
+            let handle = self.device.CreateSharedHandle(&d3d12_resource, None, GENERIC_ALL.0, PCWSTR::null())?;
+            let opened = owner.cast::<ID3D11Device1>()?.OpenSharedResource1::<_, ID3D11Buffer>(handle);
+            let _ = CloseHandle(handle);
+            let d3d11_view = onnx_tensor::float_view(owner, &opened?, len)?;
+
+            let d3d11_fence = create_shared_fence(owner)?;
+            let handle = d3d11_fence.CreateSharedHandle(None, GENERIC_ALL.0, PCWSTR::null())?;
+            let d3d12_fence = open_shared::<ID3D12Fence>(&self.device, handle)?;
+
+            debug!("Buffer de tensor D3D12/D3D11 compartilhado criado: {} elementos", len);
+            Ok(SharedTensor {
+                owner: owner.clone(),
+                len,
+                d3d12_resource,
+                d3d11_view,
+                d3d11_fence,
+                d3d12_fence,
+                fence_value: 0,
+            })
+        }
+    }
+
+    /// Captura a região e a lê pela heap de readback, retornando os pixels sem padding
+    pub fn capture_region(&mut self, region: Rect) -> CaptureResult<Vec<u8>> {
+        let resource = self.capture_resource(region)?;
+        let format = self.capture.format();
+        let size = region.size();
+
+        let reusable = self.readback.as_ref().is_some_and(|readback| readback.size == size && readback.format == format);
+        if !reusable {
+            self.readback = None;
+            self.readback = Some(self.create_readback(&resource, size, format)?);
+        }
+        let readback = self.readback.as_ref().ok_or(CaptureError::NotInitialized)?;
+
+        let source = D3D12_TEXTURE_COPY_LOCATION {
+            pResource: ManuallyDrop::new(Some(resource)),
+            Type: D3D12_TEXTURE_COPY_TYPE_SUBRESOURCE_INDEX,
+            Anonymous: D3D12_TEXTURE_COPY_LOCATION_0 { SubresourceIndex: 0 },
+        };
+        let destination = D3D12_TEXTURE_COPY_LOCATION {
+            pResource: ManuallyDrop::new(Some(readback.buffer.clone())),
+            Type: D3D12_TEXTURE_COPY_TYPE_PLACED_FOOTPRINT,
+            Anonymous: D3D12_TEXTURE_COPY_LOCATION_0 {
+                PlacedFootprint: readback.footprint,
+            },
+        };
+
+        let recorded = self.record_copy(&source, &destination);
+
+        // As referências dentro de `ManuallyDrop` não são liberadas pela estrutura
+        let _ = ManuallyDrop::into_inner(source.pResource);
+        let _ = ManuallyDrop::into_inner(destination.pResource);
+        recorded?;
+
+        self.fence_value += 1;
+        unsafe {
+            self.queue.ExecuteCommandLists(&[Some(self.list.cast::<ID3D12CommandList>()?)]);
+            self.queue.Signal(&self.fence, self.fence_value)?;
+            // Sem evento, a chamada só retorna quando o fence alcançar o valor
+            self.fence.SetEventOnCompletion(self.fence_value, HANDLE::default())?;
+        }
+
+        let row_pitch = readback.footprint.Footprint.RowPitch as usize;
+        let (row_bytes, rows) = convert::frame_layout(format, size.width, size.height);
+        convert::check_pitch(row_pitch, row_bytes)?;
+        let mut buffer = vec![0u8; rows * row_bytes];
+        if rows == 0 {
+            return Ok(buffer);
+        }
+
+        let src_len = row_pitch * (rows - 1) + row_bytes;
+        let read_range = D3D12_RANGE {
+            Begin: 0,
+            End: src_len,
+        };
+        let mut data = std::ptr::null_mut();
+        unsafe {
+            readback.buffer.Map(0, Some(&read_range), Some(&mut data))?;
+            if !data.is_null() {
+                let src = slice::from_raw_parts(data as *const u8, src_len);
+                convert::copy_rows(src, row_pitch, &mut buffer, row_bytes);
+            }
+            // Nada foi escrito pela CPU
+            readback.buffer.Unmap(0, Some(&D3D12_RANGE::default()));
+        }
+
+        Ok(buffer)
+    }
+
+    fn record_copy(&self, source: &D3D12_TEXTURE_COPY_LOCATION, destination: &D3D12_TEXTURE_COPY_LOCATION) -> Result<()> {
+        unsafe {
+            self.allocator.Reset()?;
+            self.list.Reset(&self.allocator, None)?;
+            // COMMON é promovido implicitamente para COPY_SOURCE
+            self.list.CopyTextureRegion(destination, 0, 0, 0, source, None);
+            self.list.Close()
+        }
+    }
+
+    fn create_readback(&self, resource: &ID3D12Resource, size: Size, format: DXGI_FORMAT) -> CaptureResult<Readback> {
+        let desc = unsafe { resource.GetDesc() };
+        let mut footprint = D3D12_PLACED_SUBRESOURCE_FOOTPRINT::default();
+        let mut total_bytes = 0u64;
+        unsafe {
+            self.device.GetCopyableFootprints(&desc, 0, 1, 0, Some(&mut footprint), None, None, Some(&mut total_bytes));
+        }
+
+        let heap = D3D12_HEAP_PROPERTIES {
+            Type: D3D12_HEAP_TYPE_READBACK,
+            ..Default::default()
+        };
+        let buffer_desc = D3D12_RESOURCE_DESC {
+            Dimension: D3D12_RESOURCE_DIMENSION_BUFFER,
+            Width: total_bytes,
+            Height: 1,
+            DepthOrArraySize: 1,
+            MipLevels: 1,
+            Format: DXGI_FORMAT_UNKNOWN,
+            SampleDesc: DXGI_SAMPLE_DESC {
+                Count: 1,
+                Quality: 0,
+            },
+            Layout: D3D12_TEXTURE_LAYOUT_ROW_MAJOR,
+            ..Default::default()
+        };
+
+        let mut buffer: Option<ID3D12Resource> = None;
+        unsafe {
+            self.device.CreateCommittedResource(
+                &heap,
+                D3D12_HEAP_FLAG_NONE,
+                &buffer_desc,
+                D3D12_RESOURCE_STATE_COPY_DEST,
+                None,
+                &mut buffer,
+            )?;
+        }
+
+        Ok(Readback {
+            buffer: buffer.ok_or(CaptureError::NotInitialized)?,
+            footprint,
+            size,
+            format,
+        })
+    }
+}
+
+/// Abre um handle NT compartilhado no dispositivo D3D12 e o fecha
+unsafe fn open_shared<T: ComInterface>(device: &ID3D12Device, handle: HANDLE) -> CaptureResult<T> {
+    let mut opened: Option<T> = None;
+    let result = device.OpenSharedHandle(handle, &mut opened);
+    let _ = CloseHandle(handle);
+    result?;
+    opened.ok_or(CaptureError::NotInitialized)
+}
+
+/// Cerca D3D11 que pode ser compartilhada com o dispositivo D3D12
+unsafe fn create_shared_fence(device: &ID3D11Device) -> CaptureResult<ID3D11Fence> {
+    let mut fence: Option<ID3D11Fence> = None;
+    device.cast::<ID3D11Device5>()?.CreateFence(0, D3D11_FENCE_FLAG_SHARED, &mut fence)?;
+    fence.ok_or(CaptureError::NotInitialized)
+}
+
+impl ScreenSource for D3D12Capture {
+    fn backend(&self) -> Backend {
+        Backend::D3D12
+    }
+
+    fn output_size(&self) -> Size {
+        self.capture.output_size()
+    }
+
+    fn format(&self) -> DXGI_FORMAT {
+        self.capture.format()
+    }
+
+    fn capture_frame(&mut self, region: Rect) -> CaptureResult<Frame> {
+        let data = self.capture_region(region)?;
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+
+        Ok(Frame {
+            data,
+            region,
+            format: self.capture.format(),
+            color_space: self.capture.color_space(),
+            present_time: self.capture.last_present_time(),
+            sequence,
+            metadata: self.capture.frame_metadata(),
+        })
+    }
+}
@@ -0,0 +1,397 @@
+//! Modo serviço: capturas de longa duração descritas em um arquivo de configuração (feature `daemon`)
+//!
+//! A configuração (TOML ou JSON) define quais monitores/regiões capturar, em que horários,
+//! para quais saídas e como se recuperar de falhas. Cada captura roda em sua própria thread,
+//! e cada saída em outra (`sink::FanOut`), para que um cliente lento não atrase os arquivos.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use windows::Win32::Graphics::Dxgi::Common::DXGI_FORMAT;
+use windows::Win32::System::SystemInformation::GetLocalTime;
+
+use crate::frame::Frame;
+use crate::pipe::{PipeServer, PipeSession, StreamFormat};
+use crate::priority::{boost_current_thread, RealtimePriority};
+use crate::raw_sequence::{RawSequenceHeader, RawSequenceWriter, SegmentPolicy, SegmentedRawWriter};
+use crate::sink::{FanOut, FrameSink};
+use crate::stream::QueuePolicy;
+use crate::y4m::Y4mWriter;
+use crate::{DxgiCapture, NoFramePolicy, Rect, RegionSpec, Result};
+
+/// Quadros que uma saída em arquivo pode acumular antes de segurar a captura
+const FILE_SINK_QUEUE_DEPTH: usize = 8;
+
+/// Configuração completa do serviço
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DaemonConfig {
+    pub captures: Vec<CaptureJob>,
+    #[serde(default)]
+    pub recovery: RecoveryPolicy,
+}
+
+/// Uma captura contínua de um monitor ou região
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CaptureJob {
+    pub name: String,
+    #[serde(default)]
+    pub adapter: u32,
+    #[serde(default)]
+    pub output: u32,
+    /// Região relativa ao monitor, resolvida ao (re)criar a captura; ausente captura o monitor inteiro
+    #[serde(default)]
+    pub region: Option<RegionSpec>,
+    /// Capturas por segundo; ausente ou 0 usa a taxa de atualização do monitor
+    #[serde(default)]
+    pub fps: u32,
+    #[serde(default)]
+    pub schedule: Option<Schedule>,
+    /// Registra a thread da captura no MMCSS (ex.: `{ task = "capture", priority = "high" }`)
+    #[serde(default)]
+    pub realtime: Option<RealtimePriority>,
+    pub sinks: Vec<SinkConfig>,
+}
+
+/// Saídas disponíveis para uma captura
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SinkConfig {
+    /// Sequência bruta indexada (`raw_sequence`); com `segments`, dividida em vários arquivos
+    /// a partir de `path`
+    RawFile {
+        path: PathBuf,
+        #[serde(default)]
+        segments: Option<SegmentPolicy>,
+    },
+    /// Fluxo YUV4MPEG2 (`y4m`)
+    Y4m { path: PathBuf },
+    /// Servidor de named pipe (`pipe`); aceita um cliente por vez
+    Pipe { name: String },
+}
+
+/// Janela diária (horário local, "HH:MM") em que a captura fica ativa
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Schedule {
+    #[serde(default)]
+    pub start: Option<String>,
+    #[serde(default)]
+    pub end: Option<String>,
+    /// Duração máxima da captura desde o início do serviço
+    #[serde(default)]
+    pub max_duration_secs: Option<u64>,
+}
+
+/// Como reagir a falhas ao criar ou manter a captura
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecoveryPolicy {
+    /// Tentativas de criação da captura (0 = sem limite)
+    pub max_retries: u32,
+    pub retry_delay_ms: u64,
+    /// Recria a captura se nenhum quadro chegar por este tempo (0 = nunca)
+    pub recreate_after_secs: u64,
+}
+
+impl Default for RecoveryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 0,
+            retry_delay_ms: 1000,
+            recreate_after_secs: 0,
+        }
+    }
+}
+
+impl DaemonConfig {
+    /// Carrega a configuração; o formato é escolhido pela extensão (`.toml` ou `.json`)
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Ok(serde_json::from_str(&text)?),
+            Some("toml") => Ok(toml::from_str(&text)?),
+            _ => Err(format!("Extensão de configuração não suportada: {}", path.display()).into()),
+        }
+    }
+}
+
+impl Schedule {
+    fn parse_time(value: &str) -> Result<u32> {
+        let (hours, minutes) = value.split_once(':').ok_or("Horário deve estar no formato HH:MM")?;
+        let hours: u32 = hours.trim().parse()?;
+        let minutes: u32 = minutes.trim().parse()?;
+        if hours > 23 || minutes > 59 {
+            return Err(format!("Horário inválido: {}", value).into());
+        }
+        Ok(hours * 60 + minutes)
+    }
+
+    fn is_active(&self, started: Instant) -> Result<bool> {
+        if let Some(max) = self.max_duration_secs {
+            if started.elapsed() >= Duration::from_secs(max) {
+                return Ok(false);
+            }
+        }
+
+        let now = unsafe { GetLocalTime() };
+        let minute = now.wHour as u32 * 60 + now.wMinute as u32;
+        let start = self.start.as_deref().map(Self::parse_time).transpose()?.unwrap_or(0);
+        let end = self.end.as_deref().map(Self::parse_time).transpose()?.unwrap_or(24 * 60);
+
+        // Janelas que atravessam a meia-noite (ex.: 22:00-06:00)
+        Ok(if start <= end {
+            minute >= start && minute < end
+        } else {
+            minute >= start || minute < end
+        })
+    }
+}
+
+enum RawFileWriter {
+    Single(RawSequenceWriter),
+    Segmented(SegmentedRawWriter),
+}
+
+/// A sequência é finalizada em `finish`, e não no `Drop` do escritor, para que falhas ao gravar
+/// o índice ou ao renomear o arquivo apareçam em `SinkStats::last_error`
+struct RawFileSink(Option<RawFileWriter>);
+
+impl FrameSink for RawFileSink {
+    fn on_frame(&mut self, frame: &Frame) -> Result<()> {
+        match self.0.as_mut() {
+            Some(RawFileWriter::Single(writer)) => writer.write_frame(&frame.data, frame.present_time),
+            Some(RawFileWriter::Segmented(writer)) => writer.write_frame(&frame.data, frame.present_time),
+            None => Err("Sequência já finalizada".into()),
+        }
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        match self.0.take() {
+            Some(RawFileWriter::Single(writer)) => writer.finish().map(drop),
+            Some(RawFileWriter::Segmented(writer)) => writer.finish().map(drop),
+            None => Ok(()),
+        }
+    }
+}
+
+struct Y4mSink(Y4mWriter<fs::File>);
+
+impl FrameSink for Y4mSink {
+    fn on_frame(&mut self, frame: &Frame) -> Result<()> {
+        self.0.write_frame(&frame.data, frame.format)
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.0.flush()
+    }
+}
+
+/// Aceita clientes em segundo plano e descarta quadros enquanto ninguém está conectado
+struct PipeSink {
+    session: Arc<Mutex<Option<PipeSession>>>,
+    server: Arc<PipeServer>,
+    offer: StreamFormat,
+    accepting: Arc<AtomicBool>,
+}
+
+impl PipeSink {
+    fn new(name: &str, offer: StreamFormat) -> Self {
+        let sink = Self {
+            session: Arc::new(Mutex::new(None)),
+            server: Arc::new(PipeServer::new(name)),
+            offer,
+            accepting: Arc::new(AtomicBool::new(false)),
+        };
+        sink.start_accept();
+        sink
+    }
+
+    fn start_accept(&self) {
+        if self.accepting.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let (session, server, accepting, offer) =
+            (self.session.clone(), self.server.clone(), self.accepting.clone(), self.offer);
+        thread::spawn(move || {
+            if let Ok(accepted) = server.accept(offer) {
+                *session.lock().unwrap_or_else(|e| e.into_inner()) = Some(accepted);
+            }
+            accepting.store(false, Ordering::SeqCst);
+        });
+    }
+}
+
+impl FrameSink for PipeSink {
+    fn on_frame(&mut self, frame: &Frame) -> Result<()> {
+        let mut session = self.session.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(active) = session.as_mut() {
+            if active.send_frame(&frame.data, frame.present_time).is_err() {
+                // Cliente saiu: voltar a aguardar conexões
+                *session = None;
+                drop(session);
+                self.start_accept();
+            }
+        } else {
+            drop(session);
+            self.start_accept();
+        }
+        Ok(())
+    }
+}
+
+/// Abre a saída e a registra; arquivos nunca perdem quadros, o pipe fica só com o mais recente
+fn open_sink(
+    sinks: &mut FanOut,
+    name: &str,
+    config: &SinkConfig,
+    format: DXGI_FORMAT,
+    width: u32,
+    height: u32,
+    fps: u32,
+) -> Result<()> {
+    match config {
+        SinkConfig::RawFile { path, segments } => {
+            let header = RawSequenceHeader::new(format, width, height);
+            let writer = match segments {
+                Some(policy) => RawFileWriter::Segmented(SegmentedRawWriter::create(path, header, *policy)?),
+                None => RawFileWriter::Single(RawSequenceWriter::create(path, header)?),
+            };
+            sinks.add(name, RawFileSink(Some(writer)), QueuePolicy::Blocking(FILE_SINK_QUEUE_DEPTH))
+        }
+        SinkConfig::Y4m { path } => sinks.add(
+            name,
+            Y4mSink(Y4mWriter::new(fs::File::create(path)?, width, height, fps, 1)?),
+            QueuePolicy::Blocking(FILE_SINK_QUEUE_DEPTH),
+        ),
+        SinkConfig::Pipe { name: pipe } => sinks.add(
+            name,
+            PipeSink::new(pipe, StreamFormat { format, width, height, fps }),
+            QueuePolicy::LatestOnly,
+        ),
+    }
+    Ok(())
+}
+
+/// Cria a captura respeitando a política de novas tentativas; com a tela parada as saídas
+/// recebem o último quadro repetido, não quadros pretos
+fn create_capture(job: &CaptureJob, recovery: &RecoveryPolicy, stop: &AtomicBool) -> Result<DxgiCapture> {
+    let mut attempt = 0;
+    loop {
+        let built = DxgiCapture::builder()
+            .output(job.adapter, job.output)
+            .no_frame_policy(NoFramePolicy::LastFrame)
+            .build();
+        match built {
+            Ok(capture) => return Ok(capture),
+            Err(e) => {
+                attempt += 1;
+                if (recovery.max_retries != 0 && attempt >= recovery.max_retries) || stop.load(Ordering::SeqCst) {
+                    return Err(e.into());
+                }
+                warn!("[{}] Falha ao criar captura (tentativa {}): {}", job.name, attempt, e);
+                thread::sleep(Duration::from_millis(recovery.retry_delay_ms));
+            }
+        }
+    }
+}
+
+fn resolve_region(job: &CaptureJob, capture: &DxgiCapture) -> Result<Rect> {
+    match &job.region {
+        Some(spec) => Ok(capture.resolve_region(spec)?),
+        None => Ok(capture.bounds()),
+    }
+}
+
+fn run_job(job: &CaptureJob, recovery: &RecoveryPolicy, stop: &AtomicBool) -> Result<()> {
+    let started = Instant::now();
+    let _boost = job.realtime.map(boost_current_thread);
+    let mut capture = create_capture(job, recovery, stop)?;
+
+    let mut region = resolve_region(job, &capture)?;
+    let fps = if job.fps == 0 { capture.default_fps() } else { job.fps };
+    info!("[{}] Captura iniciada: {:?} a {} fps", job.name, region, fps);
+
+    let mut sinks = FanOut::new();
+    for (index, sink) in job.sinks.iter().enumerate() {
+        let name = format!("{}/{}", job.name, index);
+        open_sink(&mut sinks, &name, sink, capture.format(), region.width, region.height, fps)?;
+    }
+
+    let interval = Duration::from_secs(1) / fps.max(1);
+    let mut last_frame = Instant::now();
+
+    while !stop.load(Ordering::SeqCst) {
+        let tick = Instant::now();
+
+        let active = match &job.schedule {
+            Some(schedule) => schedule.is_active(started)?,
+            None => true,
+        };
+
+        if active {
+            // Regiões que seguem o cursor mudam só de posição a cada quadro
+            if job.region.as_ref().is_some_and(RegionSpec::follows_cursor) {
+                if let Ok(resolved) = resolve_region(job, &capture) {
+                    if resolved.size() == region.size() {
+                        region = resolved;
+                    }
+                }
+            }
+
+            if let Ok(frame) = capture.capture_frame(region) {
+                last_frame = Instant::now();
+                sinks.dispatch(&Arc::new(frame));
+            } else if recovery.recreate_after_secs != 0
+                && last_frame.elapsed() >= Duration::from_secs(recovery.recreate_after_secs)
+            {
+                warn!("[{}] Nenhum quadro há {}s, recriando captura", job.name, recovery.recreate_after_secs);
+                drop(capture);
+                capture = create_capture(job, recovery, stop)?;
+                last_frame = Instant::now();
+
+                // As saídas foram abertas com o tamanho da região; só a posição pode mudar
+                match resolve_region(job, &capture) {
+                    Ok(resolved) if resolved.size() == region.size() => region = resolved,
+                    Ok(resolved) => warn!("[{}] Região mudou de tamanho para {:?}, mantendo {:?}", job.name, resolved, region),
+                    Err(e) => warn!("[{}] Região inválida na resolução atual: {}", job.name, e),
+                }
+            }
+        }
+
+        if let Some(remaining) = interval.checked_sub(tick.elapsed()) {
+            thread::sleep(remaining);
+        }
+    }
+
+    Ok(())
+}
+
+/// Executa todas as capturas até `stop` ser sinalizado
+pub fn run(config: &DaemonConfig, stop: Arc<AtomicBool>) -> Result<()> {
+    let handles: Vec<_> = config
+        .captures
+        .iter()
+        .cloned()
+        .map(|job| {
+            let (recovery, stop) = (config.recovery, stop.clone());
+            thread::spawn(move || {
+                if let Err(e) = run_job(&job, &recovery, &stop) {
+                    error!("[{}] Captura encerrada: {}", job.name, e);
+                }
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        let _ = handle.join();
+    }
+
+    Ok(())
+}
@@ -0,0 +1,31 @@
+//! Diagnósticos internos pela fachada `log` (feature `log`)
+//!
+//! Sem a feature, as mensagens são descartadas sem custo; com ela, o aplicativo decide
+//! para onde vão escolhendo a implementação de logger (`env_logger`, `tracing`, ...).
+
+macro_rules! log_event {
+    ($level:ident, $($arg:tt)+) => {{
+        #[cfg(feature = "log")]
+        log::$level!(target: "dxgi_capture", $($arg)+);
+        #[cfg(not(feature = "log"))]
+        {
+            let _ = format_args!($($arg)+);
+        }
+    }};
+}
+
+macro_rules! debug {
+    ($($arg:tt)+) => { log_event!(debug, $($arg)+) };
+}
+
+macro_rules! info {
+    ($($arg:tt)+) => { log_event!(info, $($arg)+) };
+}
+
+macro_rules! warn {
+    ($($arg:tt)+) => { log_event!(warn, $($arg)+) };
+}
+
+macro_rules! error {
+    ($($arg:tt)+) => { log_event!(error, $($arg)+) };
+}
@@ -0,0 +1,330 @@
+//! Criptografia AES-256-GCM dos quadros enviados por IPC e rede (feature `encryption`)
+//!
+//! Em produtos de suporte remoto a tela do usuário passa por named pipes e pela LAN; quem
+//! interceptar esse tráfego não deve conseguir ver os pixels. `FrameCipher` cifra cada quadro
+//! com uma chave obtida de um `KeyProvider` (chave fixa, cofre do sistema, servidor de chaves)
+//! e autentica junto o número de sequência e o timestamp, então quadros trocados ou alterados
+//! são rejeitados na decifração. O cifrador que decifra guarda a última sequência aceita e
+//! recusa quadros repetidos ou fora de ordem.
+//!
+//! Formato de um quadro cifrado: id da chave (u32 LE), nonce (12 bytes), texto cifrado e a tag
+//! de 16 bytes. O nonce é um prefixo aleatório por cifrador mais um contador, nunca repetido
+//! com a mesma chave.
+
+use std::fmt;
+use std::sync::Arc;
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+
+use crate::frame::Frame;
+use crate::sink::FrameSink;
+use crate::Result;
+
+/// Tamanho da chave AES-256
+pub const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+/// Bytes que a cifra acrescenta a cada quadro (cabeçalho e tag)
+pub const SEALED_OVERHEAD: usize = 4 + NONCE_LEN + TAG_LEN;
+
+/// Origem das chaves de `FrameCipher`.
+///
+/// `current_key` é consultada a cada quadro cifrado, então deve ser barata (devolver uma chave
+/// em memória); trocar o id faz os quadros seguintes usarem a nova chave, e o lado que decifra
+/// recebe o id em cada quadro para pedir a chave certa a `key`.
+pub trait KeyProvider: Send + Sync {
+    /// Id e chave para cifrar os próximos quadros
+    fn current_key(&self) -> Result<(u32, [u8; KEY_LEN])>;
+
+    /// Chave com o id, para decifrar
+    fn key(&self, id: u32) -> Result<[u8; KEY_LEN]>;
+}
+
+/// Uma única chave fixa, combinada fora de banda entre as duas pontas
+#[derive(Clone)]
+pub struct StaticKey {
+    id: u32,
+    key: [u8; KEY_LEN],
+}
+
+impl StaticKey {
+    pub fn new(key: [u8; KEY_LEN]) -> Self {
+        Self::with_id(0, key)
+    }
+
+    pub fn with_id(id: u32, key: [u8; KEY_LEN]) -> Self {
+        Self { id, key }
+    }
+
+    /// Chave aleatória do gerador do sistema; `bytes()` a exporta para a outra ponta
+    pub fn generate() -> Self {
+        let mut key = [0u8; KEY_LEN];
+        OsRng.fill_bytes(&mut key);
+        Self::new(key)
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn bytes(&self) -> &[u8; KEY_LEN] {
+        &self.key
+    }
+}
+
+impl fmt::Debug for StaticKey {
+    // Nunca escrever a chave em logs
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StaticKey").field("id", &self.id).finish_non_exhaustive()
+    }
+}
+
+impl KeyProvider for StaticKey {
+    fn current_key(&self) -> Result<(u32, [u8; KEY_LEN])> {
+        Ok((self.id, self.key))
+    }
+
+    fn key(&self, id: u32) -> Result<[u8; KEY_LEN]> {
+        if id != self.id {
+            return Err(format!("Chave {} desconhecida (esperada {})", id, self.id).into());
+        }
+        Ok(self.key)
+    }
+}
+
+/// Cifra e decifra quadros com AES-256-GCM
+pub struct FrameCipher {
+    provider: Arc<dyn KeyProvider>,
+    // Cifrador da última chave usada, reaproveitado enquanto o id não mudar
+    cipher: Option<(u32, Aes256Gcm)>,
+    nonce_prefix: [u8; 4],
+    counter: u64,
+    // Última sequência decifrada; `open` só aceita sequências maiores
+    last_opened: Option<u64>,
+}
+
+impl FrameCipher {
+    pub fn new(provider: Arc<dyn KeyProvider>) -> Self {
+        Self {
+            provider,
+            cipher: None,
+            nonce_prefix: random_prefix(),
+            counter: 0,
+            last_opened: None,
+        }
+    }
+
+    /// Cifra `data`, autenticando também `sequence` e `timestamp`
+    pub fn seal(&mut self, sequence: u64, timestamp: i64, data: &[u8]) -> Result<Vec<u8>> {
+        let (id, key) = self.provider.current_key()?;
+        if self.cipher.as_ref().map(|(current, _)| *current) != Some(id) {
+            // Chave nova: nonces recomeçam com outro prefixo
+            self.cipher = Some((id, Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))));
+            self.nonce_prefix = random_prefix();
+            self.counter = 0;
+        }
+
+        let mut nonce = [0u8; NONCE_LEN];
+        nonce[..4].copy_from_slice(&self.nonce_prefix);
+        nonce[4..].copy_from_slice(&self.counter.to_le_bytes());
+        self.counter += 1;
+
+        let aad = associated_data(sequence, timestamp);
+        let (_, cipher) = self.cipher.as_ref().ok_or("Cifrador não inicializado")?;
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce), Payload { msg: data, aad: &aad })
+            .map_err(|_| "Falha ao cifrar o quadro")?;
+
+        let mut sealed = Vec::with_capacity(4 + NONCE_LEN + ciphertext.len());
+        sealed.extend_from_slice(&id.to_le_bytes());
+        sealed.extend_from_slice(&nonce);
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+
+    /// Decifra um quadro de `seal`; falha se a chave, a sequência, o timestamp ou os dados não
+    /// conferem, ou se `sequence` não é maior que a do último quadro decifrado (repetição ou
+    /// reordenação)
+    pub fn open(&mut self, sequence: u64, timestamp: i64, sealed: &[u8]) -> Result<Vec<u8>> {
+        if sealed.len() < SEALED_OVERHEAD {
+            return Err("Quadro cifrado truncado".into());
+        }
+        let id = u32::from_le_bytes(sealed[0..4].try_into()?);
+        let nonce = &sealed[4..4 + NONCE_LEN];
+        let ciphertext = &sealed[4 + NONCE_LEN..];
+
+        if self.cipher.as_ref().map(|(current, _)| *current) != Some(id) {
+            let key = self.provider.key(id)?;
+            self.cipher = Some((id, Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))));
+        }
+        let (_, cipher) = self.cipher.as_ref().ok_or("Cifrador não inicializado")?;
+
+        let aad = associated_data(sequence, timestamp);
+        let data = cipher
+            .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: &aad })
+            .map_err(|_| "Quadro cifrado não autenticado (chave errada ou dados alterados)")?;
+
+        // Só depois de autenticado, para que um quadro forjado não avance a sequência
+        if let Some(last) = self.last_opened {
+            if sequence <= last {
+                return Err(format!("Quadro cifrado repetido ou fora de ordem: sequência {} após {}", sequence, last).into());
+            }
+        }
+        self.last_opened = Some(sequence);
+        Ok(data)
+    }
+
+    /// Cópia do quadro com os pixels cifrados
+    pub fn seal_frame(&mut self, frame: &Frame) -> Result<Frame> {
+        let data = self.seal(frame.sequence, frame.present_time, &frame.data)?;
+        Ok(with_data(frame, data))
+    }
+
+    /// Inverso de `seal_frame`
+    pub fn open_frame(&mut self, frame: &Frame) -> Result<Frame> {
+        let data = self.open(frame.sequence, frame.present_time, &frame.data)?;
+        Ok(with_data(frame, data))
+    }
+}
+
+impl fmt::Debug for FrameCipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FrameCipher")
+            .field("key_id", &self.cipher.as_ref().map(|(id, _)| *id))
+            .field("sealed", &self.counter)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Saída que cifra os pixels antes de repassar o quadro a outra saída (um transporte de rede
+/// próprio, um arquivo); região, formato e timestamps seguem em claro
+pub struct EncryptedSink<S> {
+    inner: S,
+    cipher: FrameCipher,
+}
+
+impl<S: FrameSink> EncryptedSink<S> {
+    pub fn new(inner: S, provider: Arc<dyn KeyProvider>) -> Self {
+        Self {
+            inner,
+            cipher: FrameCipher::new(provider),
+        }
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: FrameSink> FrameSink for EncryptedSink<S> {
+    fn on_frame(&mut self, frame: &Frame) -> Result<()> {
+        let sealed = self.cipher.seal_frame(frame)?;
+        self.inner.on_frame(&sealed)
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.inner.finish()
+    }
+}
+
+/// O quadro com outros pixels, sem copiar os originais
+fn with_data(frame: &Frame, data: Vec<u8>) -> Frame {
+    Frame {
+        data,
+        region: frame.region,
+        format: frame.format,
+        color_space: frame.color_space,
+        present_time: frame.present_time,
+        sequence: frame.sequence,
+        metadata: frame.metadata,
+    }
+}
+
+/// Sequência e timestamp, autenticados junto com os pixels
+fn associated_data(sequence: u64, timestamp: i64) -> [u8; 16] {
+    let mut aad = [0u8; 16];
+    aad[..8].copy_from_slice(&sequence.to_le_bytes());
+    aad[8..].copy_from_slice(&timestamp.to_le_bytes());
+    aad
+}
+
+fn random_prefix() -> [u8; 4] {
+    let mut prefix = [0u8; 4];
+    OsRng.fill_bytes(&mut prefix);
+    prefix
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: [u8; KEY_LEN] = [7; KEY_LEN];
+
+    fn cipher_with(id: u32) -> FrameCipher {
+        FrameCipher::new(Arc::new(StaticKey::with_id(id, KEY)))
+    }
+
+    #[test]
+    fn sealed_frame_opens_with_the_same_key() {
+        let sealed = cipher_with(1).seal(5, 1234, b"pixels").unwrap();
+        assert_eq!(sealed.len(), b"pixels".len() + SEALED_OVERHEAD);
+        assert_eq!(cipher_with(1).open(5, 1234, &sealed).unwrap(), b"pixels");
+    }
+
+    #[test]
+    fn flipped_byte_is_rejected() {
+        let mut sealed = cipher_with(1).seal(5, 1234, b"pixels").unwrap();
+        let last = sealed.len() - 1;
+        sealed[last] ^= 1;
+        assert!(cipher_with(1).open(5, 1234, &sealed).is_err());
+
+        let mut sealed = cipher_with(1).seal(5, 1234, b"pixels").unwrap();
+        sealed[4 + NONCE_LEN] ^= 0x80;
+        assert!(cipher_with(1).open(5, 1234, &sealed).is_err());
+    }
+
+    #[test]
+    fn unknown_key_id_is_rejected() {
+        let sealed = cipher_with(1).seal(5, 1234, b"pixels").unwrap();
+        assert!(cipher_with(2).open(5, 1234, &sealed).is_err());
+    }
+
+    #[test]
+    fn wrong_sequence_or_timestamp_is_rejected() {
+        let sealed = cipher_with(1).seal(5, 1234, b"pixels").unwrap();
+        assert!(cipher_with(1).open(6, 1234, &sealed).is_err());
+        assert!(cipher_with(1).open(5, 1235, &sealed).is_err());
+    }
+
+    #[test]
+    fn replayed_and_reordered_frames_are_rejected() {
+        let mut sender = cipher_with(1);
+        let first = sender.seal(1, 0, b"a").unwrap();
+        let second = sender.seal(2, 0, b"b").unwrap();
+
+        let mut receiver = cipher_with(1);
+        assert_eq!(receiver.open(2, 0, &second).unwrap(), b"b");
+        assert!(receiver.open(2, 0, &second).is_err());
+        assert!(receiver.open(1, 0, &first).is_err());
+    }
+
+    #[test]
+    fn rejected_frame_does_not_advance_the_sequence() {
+        let mut sender = cipher_with(1);
+        let first = sender.seal(1, 0, b"a").unwrap();
+        let mut forged = sender.seal(9, 0, b"z").unwrap();
+        forged[4 + NONCE_LEN] ^= 1;
+
+        let mut receiver = cipher_with(1);
+        assert!(receiver.open(9, 0, &forged).is_err());
+        assert_eq!(receiver.open(1, 0, &first).unwrap(), b"a");
+    }
+
+    #[test]
+    fn truncated_frame_is_rejected() {
+        let sealed = cipher_with(1).seal(5, 1234, b"pixels").unwrap();
+        assert!(cipher_with(1).open(5, 1234, &sealed[..SEALED_OVERHEAD - 1]).is_err());
+    }
+}
@@ -0,0 +1,304 @@
+//! Erros tipados do capturador
+//!
+//! `Display` produz mensagens em inglês prefixadas por um código estável (`DXGI-E001`...),
+//! adequado para logs e buscas; `localized` traz a mesma mensagem em português. O HRESULT
+//! original, quando existe, fica acessível por `hresult()`.
+
+use std::error::Error;
+use std::fmt;
+use std::time::Duration;
+
+use windows::core::HRESULT;
+use windows::Win32::Foundation::E_ACCESSDENIED;
+use windows::Win32::Graphics::Dxgi::{DXGI_ERROR_NOT_CURRENTLY_AVAILABLE, DXGI_ERROR_SESSION_DISCONNECTED, DXGI_ERROR_UNSUPPORTED};
+
+use crate::geometry::RectError;
+use crate::session;
+
+pub type CaptureResult<T> = std::result::Result<T, CaptureError>;
+
+/// Idioma das mensagens de `CaptureError::localized`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    English,
+    Portuguese,
+}
+
+/// Falha ao criar ou usar a duplicação de um monitor
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum CaptureError {
+    /// Nenhum adaptador com o índice pedido
+    AdapterNotFound { adapter: u32 },
+    /// Nenhum monitor com o índice pedido no adaptador
+    OutputNotFound { adapter: u32, output: u32 },
+    /// `D3D11CreateDevice` falhou ou não retornou dispositivo/contexto
+    DeviceCreation { hresult: Option<HRESULT> },
+    /// Nenhum dos formatos suportados foi aceito por `DuplicateOutput1`
+    DuplicationUnavailable { hresult: Option<HRESULT> },
+    /// A duplicação foi perdida (troca de modo, UAC, dispositivo removido)
+    AccessLost { hresult: HRESULT },
+    /// `AcquireNextFrame` falhou por outro motivo (inclusive timeout)
+    AcquireFailed { hresult: HRESULT },
+    /// A duplicação não está ativa (uma reinicialização anterior falhou)
+    NotInitialized,
+    /// Região vazia, invertida ou fora do monitor
+    InvalidRegion(RectError),
+    /// Nenhum quadro novo desde a última captura (ver `NoFramePolicy`)
+    NoNewFrame,
+    /// Outra chamada do Windows falhou
+    Windows(windows::core::Error),
+    /// O monitor já tem o máximo de duplicações ativas (OBS, Teams, outro capturador)
+    DuplicationLimitReached { hresult: HRESULT },
+    /// Duplicação negada: área de trabalho segura (UAC, tela de bloqueio) ou sessão sem desktop
+    AccessDenied { hresult: HRESULT },
+    /// O video processor não suporta a conversão pedida ou falhou ao executá-la
+    Conversion(String),
+    /// Falha ao compilar um shader da GPU (mensagem do compilador)
+    Shader(String),
+    /// A operação foi interrompida por um `CancellationToken`
+    Cancelled,
+    /// Imagem de referência inválida para a busca (vazia, sem variação, maior que a região ou
+    /// em formato não suportado)
+    InvalidTemplate(String),
+    /// O processo está fora da sessão interativa do usuário (serviço na sessão 0 ou window
+    /// station sem desktop visível), onde a duplicação não funciona
+    NonInteractiveSession { session_id: u32, window_station: String },
+    /// A sessão do usuário está desconectada (cliente de Área de Trabalho Remota fechado, console
+    /// trocado para outra sessão); a captura volta quando a sessão for reconectada
+    SessionDisconnected { hresult: HRESULT },
+    /// O driver de vídeo da sessão remota não permite duplicação
+    RemoteSessionUnsupported { hresult: HRESULT },
+    /// Nenhuma região registrada com o nome pedido (`DxgiCapture::capture_named`)
+    UnknownRegion { name: String },
+    /// A imagem mais recente é mais antiga que `max_frame_age_ms` e nenhum present novo chegou
+    /// durante a espera (tela parada ou DWM travado); `age` é `None` se nunca houve present
+    StaleFrame { age: Option<Duration> },
+    /// O usuário recusou a captura na caixa de diálogo de consentimento (`consent::request_consent`)
+    ConsentDenied,
+    /// A sequência reproduzida por `replay::ReplaySource` chegou ao fim
+    ReplayFinished { frames: u64 },
+    /// Falha ao ler a sequência reproduzida
+    Replay(String),
+    /// Nenhum monitor conectado tem o nome pedido (`outputs::find_output_by_name`)
+    OutputNameNotFound { name: String },
+}
+
+impl CaptureError {
+    /// Código estável do erro, independente do idioma
+    pub fn code(&self) -> &'static str {
+        match self {
+            CaptureError::AdapterNotFound { .. } => "DXGI-E001",
+            CaptureError::OutputNotFound { .. } => "DXGI-E002",
+            CaptureError::DeviceCreation { .. } => "DXGI-E003",
+            CaptureError::DuplicationUnavailable { .. } => "DXGI-E004",
+            CaptureError::AccessLost { .. } => "DXGI-E005",
+            CaptureError::AcquireFailed { .. } => "DXGI-E006",
+            CaptureError::NotInitialized => "DXGI-E007",
+            CaptureError::InvalidRegion(_) => "DXGI-E008",
+            CaptureError::NoNewFrame => "DXGI-E009",
+            CaptureError::Windows(_) => "DXGI-E010",
+            CaptureError::DuplicationLimitReached { .. } => "DXGI-E011",
+            CaptureError::AccessDenied { .. } => "DXGI-E012",
+            CaptureError::Conversion(_) => "DXGI-E013",
+            CaptureError::Shader(_) => "DXGI-E014",
+            CaptureError::Cancelled => "DXGI-E015",
+            CaptureError::InvalidTemplate(_) => "DXGI-E016",
+            CaptureError::NonInteractiveSession { .. } => "DXGI-E017",
+            CaptureError::SessionDisconnected { .. } => "DXGI-E018",
+            CaptureError::RemoteSessionUnsupported { .. } => "DXGI-E019",
+            CaptureError::UnknownRegion { .. } => "DXGI-E020",
+            CaptureError::StaleFrame { .. } => "DXGI-E021",
+            CaptureError::ConsentDenied => "DXGI-E022",
+            CaptureError::ReplayFinished { .. } => "DXGI-E023",
+            CaptureError::Replay(_) => "DXGI-E024",
+            CaptureError::OutputNameNotFound { .. } => "DXGI-E025",
+        }
+    }
+
+    /// HRESULT retornado pelo Windows, se o erro veio de uma chamada de API
+    pub fn hresult(&self) -> Option<HRESULT> {
+        match self {
+            CaptureError::DeviceCreation { hresult } | CaptureError::DuplicationUnavailable { hresult } => *hresult,
+            CaptureError::AccessLost { hresult }
+            | CaptureError::AcquireFailed { hresult }
+            | CaptureError::DuplicationLimitReached { hresult }
+            | CaptureError::AccessDenied { hresult }
+            | CaptureError::SessionDisconnected { hresult }
+            | CaptureError::RemoteSessionUnsupported { hresult } => Some(*hresult),
+            CaptureError::Windows(error) => Some(error.code()),
+            _ => None,
+        }
+    }
+
+    /// Conflito que costuma se resolver sozinho (outro aplicativo libera o monitor,
+    /// o UAC é fechado, a sessão é reconectada); vale tentar criar a duplicação de novo mais tarde
+    pub fn is_temporarily_unavailable(&self) -> bool {
+        matches!(
+            self,
+            CaptureError::DuplicationLimitReached { .. } | CaptureError::AccessDenied { .. } | CaptureError::SessionDisconnected { .. }
+        )
+    }
+
+    /// Erro correspondente à falha de `DuplicateOutput`/`DuplicateOutput1`
+    pub(crate) fn from_duplication(hresult: Option<HRESULT>) -> Self {
+        match hresult {
+            Some(hresult) if hresult == DXGI_ERROR_NOT_CURRENTLY_AVAILABLE => {
+                CaptureError::DuplicationLimitReached { hresult }
+            }
+            Some(hresult) if hresult == E_ACCESSDENIED => CaptureError::AccessDenied { hresult },
+            Some(hresult) if hresult == DXGI_ERROR_SESSION_DISCONNECTED => CaptureError::SessionDisconnected { hresult },
+            Some(hresult) if hresult == DXGI_ERROR_UNSUPPORTED && session::is_remote_session() => {
+                CaptureError::RemoteSessionUnsupported { hresult }
+            }
+            hresult => CaptureError::DuplicationUnavailable { hresult },
+        }
+    }
+
+    /// Mensagem no idioma pedido, prefixada pelo código
+    pub fn localized(&self, language: Language) -> String {
+        let message = match language {
+            Language::English => self.message_en(),
+            Language::Portuguese => self.message_pt(),
+        };
+
+        match self.hresult() {
+            Some(hresult) => format!("[{}] {} (HRESULT 0x{:08X})", self.code(), message, hresult.0 as u32),
+            None => format!("[{}] {}", self.code(), message),
+        }
+    }
+
+    fn message_en(&self) -> String {
+        match self {
+            CaptureError::AdapterNotFound { adapter } => format!("adapter {} not found", adapter),
+            CaptureError::OutputNotFound { adapter, output } => {
+                format!("output {} not found on adapter {}", output, adapter)
+            }
+            CaptureError::DeviceCreation { .. } => "failed to create the D3D11 device".to_owned(),
+            CaptureError::DuplicationUnavailable { .. } => "failed to create the output duplication".to_owned(),
+            CaptureError::AccessLost { .. } => "access to the output duplication was lost".to_owned(),
+            CaptureError::AcquireFailed { .. } => "failed to acquire the next frame".to_owned(),
+            CaptureError::NotInitialized => "output duplication is not initialized".to_owned(),
+            CaptureError::InvalidRegion(error) => error.to_string(),
+            CaptureError::NoNewFrame => "no new frame since the last capture".to_owned(),
+            CaptureError::Windows(error) => format!("Windows API call failed: {}", error.message()),
+            CaptureError::DuplicationLimitReached { .. } => {
+                "the output already has the maximum number of active duplications; close other capture applications (OBS, screen sharing) and try again".to_owned()
+            }
+            CaptureError::AccessDenied { .. } => {
+                "desktop duplication was denied (secure desktop such as UAC or the lock screen, or a session without a desktop)".to_owned()
+            }
+            CaptureError::Conversion(message) => format!("GPU conversion failed: {}", message),
+            CaptureError::Shader(message) => format!("failed to compile GPU shader: {}", message),
+            CaptureError::Cancelled => "operation cancelled".to_owned(),
+            CaptureError::InvalidTemplate(message) => format!("invalid template image: {}", message),
+            CaptureError::NonInteractiveSession { session_id, window_station } => format!(
+                "desktop duplication is not available in session {} (window station {}); Windows services run in the isolated session 0, so capture from a process in the user's session (see session::launch_in_user_session and the pipe protocol)",
+                session_id, window_station
+            ),
+            CaptureError::SessionDisconnected { .. } => {
+                "the user session is disconnected (remote desktop client closed or the console switched to another session); capture resumes when the session reconnects".to_owned()
+            }
+            CaptureError::RemoteSessionUnsupported { .. } => {
+                "desktop duplication is not supported by the display driver of this remote desktop session; capture from the console session instead".to_owned()
+            }
+            CaptureError::UnknownRegion { name } => format!("no region named \"{}\" is registered", name),
+            CaptureError::StaleFrame { age: Some(age) } => format!(
+                "the latest desktop image is {} ms old and no new present arrived within the maximum frame age",
+                age.as_millis()
+            ),
+            CaptureError::StaleFrame { age: None } => "no desktop image has been presented yet".to_owned(),
+            CaptureError::ConsentDenied => "the user declined the screen capture".to_owned(),
+            CaptureError::ReplayFinished { frames } => format!("the replayed sequence ended after {} frames", frames),
+            CaptureError::Replay(message) => format!("failed to read the replayed sequence: {}", message),
+            CaptureError::OutputNameNotFound { name } => {
+                format!("no connected monitor is named \"{}\"; the names are listed in OutputInfo::identity", name)
+            }
+        }
+    }
+
+    fn message_pt(&self) -> String {
+        match self {
+            CaptureError::AdapterNotFound { adapter } => format!("Adaptador {} não encontrado", adapter),
+            CaptureError::OutputNotFound { adapter, output } => {
+                format!("Monitor {} não encontrado no adaptador {}", output, adapter)
+            }
+            CaptureError::DeviceCreation { .. } => "Falha ao criar o dispositivo D3D11".to_owned(),
+            CaptureError::DuplicationUnavailable { .. } => "Falha ao criar a duplicação de saída".to_owned(),
+            CaptureError::AccessLost { .. } => "Acesso à duplicação perdido".to_owned(),
+            CaptureError::AcquireFailed { .. } => "Erro ao adquirir frame".to_owned(),
+            CaptureError::NotInitialized => "Duplicação não inicializada".to_owned(),
+            CaptureError::InvalidRegion(error) => match error {
+                RectError::Empty => "Região vazia (largura ou altura zero)".to_owned(),
+                RectError::Inverted => "Região invertida (borda final antes da inicial)".to_owned(),
+                RectError::Overflow => "Coordenadas da região fora do intervalo representável".to_owned(),
+                RectError::OutOfBounds { region, bounds } => format!(
+                    "Região {}x{} em ({}, {}) fora dos limites da tela {}x{}",
+                    region.width, region.height, region.left, region.top, bounds.width, bounds.height
+                ),
+            },
+            CaptureError::NoNewFrame => "Nenhum quadro novo desde a última captura".to_owned(),
+            CaptureError::Windows(error) => format!("Falha em chamada do Windows: {}", error.message()),
+            CaptureError::DuplicationLimitReached { .. } => {
+                "O monitor já tem o máximo de duplicações ativas; feche outros aplicativos de captura (OBS, compartilhamento de tela) e tente de novo".to_owned()
+            }
+            CaptureError::AccessDenied { .. } => {
+                "Duplicação negada (área de trabalho segura, como UAC ou tela de bloqueio, ou sessão sem desktop)".to_owned()
+            }
+            CaptureError::Conversion(message) => format!("Falha na conversão pela GPU: {}", message),
+            CaptureError::Shader(message) => format!("Falha ao compilar shader da GPU: {}", message),
+            CaptureError::Cancelled => "Operação cancelada".to_owned(),
+            CaptureError::InvalidTemplate(message) => format!("Imagem de referência inválida: {}", message),
+            CaptureError::NonInteractiveSession { session_id, window_station } => format!(
+                "Duplicação indisponível na sessão {} (window station {}); serviços do Windows rodam na sessão 0, isolada, então capture a partir de um processo na sessão do usuário (veja session::launch_in_user_session e o protocolo de pipe)",
+                session_id, window_station
+            ),
+            CaptureError::SessionDisconnected { .. } => {
+                "A sessão do usuário está desconectada (cliente de Área de Trabalho Remota fechado ou console trocado para outra sessão); a captura volta quando a sessão for reconectada".to_owned()
+            }
+            CaptureError::RemoteSessionUnsupported { .. } => {
+                "O driver de vídeo desta sessão de Área de Trabalho Remota não permite duplicação; capture a partir da sessão do console".to_owned()
+            }
+            CaptureError::UnknownRegion { name } => format!("Nenhuma região registrada com o nome \"{}\"", name),
+            CaptureError::StaleFrame { age: Some(age) } => format!(
+                "A imagem mais recente da tela tem {} ms e nenhum present novo chegou dentro da idade máxima",
+                age.as_millis()
+            ),
+            CaptureError::StaleFrame { age: None } => "Nenhuma imagem da tela foi apresentada ainda".to_owned(),
+            CaptureError::ConsentDenied => "O usuário recusou a captura da tela".to_owned(),
+            CaptureError::ReplayFinished { frames } => format!("A sequência reproduzida terminou após {} quadros", frames),
+            CaptureError::Replay(message) => format!("Falha ao ler a sequência reproduzida: {}", message),
+            CaptureError::OutputNameNotFound { name } => {
+                format!("Nenhum monitor conectado com o nome \"{}\"; os nomes estão em OutputInfo::identity", name)
+            }
+        }
+    }
+}
+
+impl fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.localized(Language::English))
+    }
+}
+
+impl Error for CaptureError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CaptureError::InvalidRegion(error) => Some(error),
+            CaptureError::Windows(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<windows::core::Error> for CaptureError {
+    fn from(error: windows::core::Error) -> Self {
+        CaptureError::Windows(error)
+    }
+}
+
+impl From<RectError> for CaptureError {
+    fn from(error: RectError) -> Self {
+        CaptureError::InvalidRegion(error)
+    }
+}
@@ -0,0 +1,55 @@
+//! Eventos de saúde da captura para interfaces gráficas
+//!
+//! Reinicializações, trocas de modo e bloqueios de sessão são tratados internamente pelo
+//! capturador e aparecem para o chamador, quando aparecem, como erros de uma captura. Uma UI
+//! que quer mostrar "captura pausada" ou "resolução alterada" assina os eventos com
+//! `DxgiCapture::subscribe_events` (ou `CaptureStream::subscribe_events`) e os recebe por um
+//! canal, sem interpretar erros. Cada assinante recebe todos os eventos emitidos depois da
+//! assinatura; assinantes descartados são esquecidos no próximo evento.
+
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+use windows::core::HRESULT;
+use windows::Win32::Graphics::Dxgi::Common::DXGI_FORMAT;
+
+use crate::geometry::Rect;
+
+/// Mudança no estado da captura
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CaptureEvent {
+    /// A duplicação foi recriada (perda de acesso, troca de modo, reconexão da sessão)
+    Reinitialized,
+    /// Posição ou resolução do monitor no desktop virtual mudou
+    DisplayChanged { previous: Rect, current: Rect },
+    /// A captura ficou suspensa: tela de bloqueio, desktop seguro (UAC) ou sessão desconectada
+    SessionLocked,
+    /// A captura voltou depois de `SessionLocked`
+    SessionUnlocked,
+    /// O dispositivo D3D11 foi removido ou reiniciado pelo driver; `reason` vem de
+    /// `GetDeviceRemovedReason` (ex.: `DXGI_ERROR_DEVICE_HUNG`, `DXGI_ERROR_DRIVER_INTERNAL_ERROR`)
+    DeviceRemoved { reason: HRESULT },
+    /// A duplicação recriada entrega outro formato de pixel (ex.: HDR ligado ou desligado)
+    FormatChanged { previous: DXGI_FORMAT, current: DXGI_FORMAT },
+}
+
+/// Assinantes dos eventos de um capturador; clones compartilham a mesma lista
+#[derive(Debug, Clone, Default)]
+pub(crate) struct EventBus {
+    subscribers: Arc<Mutex<Vec<Sender<CaptureEvent>>>>,
+}
+
+impl EventBus {
+    pub(crate) fn subscribe(&self) -> Receiver<CaptureEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers.lock().unwrap_or_else(|e| e.into_inner()).push(sender);
+        receiver
+    }
+
+    pub(crate) fn emit(&self, event: CaptureEvent) {
+        debug!("Evento da captura: {:?}", event);
+        let mut subscribers = self.subscribers.lock().unwrap_or_else(|e| e.into_inner());
+        subscribers.retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+}
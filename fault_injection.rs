@@ -0,0 +1,218 @@
+//! Injeção de falhas da duplicação para testes (feature `fault-injection`)
+//!
+//! Perda de acesso, remoção do dispositivo e timeouts são raros e difíceis de provocar sob
+//! demanda, mas são justamente os casos em que a lógica de recuperação de uma aplicação mais
+//! precisa funcionar. Um `FaultPlan` diz em quais quadros (0 = primeira captura) cada erro
+//! aparece; no `DxgiCapture` (`CaptureBuilder::inject_faults`) o erro substitui o resultado de
+//! `AcquireNextFrame` e segue pelo mesmo caminho de um erro real, com reinicialização, eventos e
+//! nova tentativa. Para as demais fontes (`ReplaySource`, `D3D12Capture`, implementações de
+//! teste de `ScreenSource`), `FaultySource` devolve o `CaptureError` que a captura ao vivo
+//! devolveria.
+//!
+//! Vários erros no mesmo quadro são consumidos em ordem pelas tentativas daquela captura: com
+//! `retry_after_reinit`, `at(5, AccessLost)` duas vezes faz a repetição também falhar.
+//! Não deve ser ligada em builds de produção.
+
+use windows::core::HRESULT;
+use windows::Win32::Graphics::Dxgi::Common::DXGI_FORMAT;
+use windows::Win32::Graphics::Dxgi::{
+    DXGI_ERROR_ACCESS_LOST, DXGI_ERROR_DEVICE_REMOVED, DXGI_ERROR_DEVICE_RESET,
+    DXGI_ERROR_SESSION_DISCONNECTED, DXGI_ERROR_WAIT_TIMEOUT,
+};
+
+use crate::error::{CaptureError, CaptureResult};
+use crate::frame::Frame;
+use crate::geometry::{Rect, Size};
+use crate::source::{Backend, ScreenSource};
+
+/// Erro a injetar
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InjectedFault {
+    /// `DXGI_ERROR_ACCESS_LOST`: troca de modo, tela cheia exclusiva, área de trabalho segura
+    AccessLost,
+    /// `DXGI_ERROR_DEVICE_REMOVED`: travamento ou atualização do driver
+    DeviceRemoved,
+    /// `DXGI_ERROR_DEVICE_RESET`
+    DeviceReset,
+    /// `DXGI_ERROR_WAIT_TIMEOUT`: nenhum present dentro do timeout
+    WaitTimeout,
+    /// `DXGI_ERROR_SESSION_DISCONNECTED`: RDP fechado ou troca de usuário
+    SessionDisconnected,
+    /// Qualquer outro HRESULT devolvido por `AcquireNextFrame`
+    Hresult(HRESULT),
+}
+
+impl InjectedFault {
+    pub fn hresult(self) -> HRESULT {
+        match self {
+            InjectedFault::AccessLost => DXGI_ERROR_ACCESS_LOST,
+            InjectedFault::DeviceRemoved => DXGI_ERROR_DEVICE_REMOVED,
+            InjectedFault::DeviceReset => DXGI_ERROR_DEVICE_RESET,
+            InjectedFault::WaitTimeout => DXGI_ERROR_WAIT_TIMEOUT,
+            InjectedFault::SessionDisconnected => DXGI_ERROR_SESSION_DISCONNECTED,
+            InjectedFault::Hresult(hresult) => hresult,
+        }
+    }
+
+    /// Erro que `DxgiCapture::capture_frame` devolve quando a recuperação não resolve a falha
+    pub fn to_error(self) -> CaptureError {
+        let hresult = self.hresult();
+        match self {
+            InjectedFault::AccessLost | InjectedFault::DeviceRemoved | InjectedFault::DeviceReset => {
+                CaptureError::AccessLost { hresult }
+            }
+            InjectedFault::SessionDisconnected => CaptureError::SessionDisconnected { hresult },
+            InjectedFault::WaitTimeout | InjectedFault::Hresult(_) => CaptureError::AcquireFailed { hresult },
+        }
+    }
+}
+
+/// Quais erros aparecem em quais quadros
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FaultPlan {
+    // Ordenado pelo quadro; erros do mesmo quadro na ordem em que foram adicionados
+    faults: Vec<(u64, InjectedFault)>,
+}
+
+impl FaultPlan {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Injeta `fault` na captura número `frame` (0 = primeira)
+    pub fn at(mut self, frame: u64, fault: InjectedFault) -> Self {
+        let index = self.faults.partition_point(|&(scheduled, _)| scheduled <= frame);
+        self.faults.insert(index, (frame, fault));
+        self
+    }
+
+    /// Injeta `fault` em cada um dos quadros de `frames`
+    pub fn at_frames(self, frames: impl IntoIterator<Item = u64>, fault: InjectedFault) -> Self {
+        frames.into_iter().fold(self, |plan, frame| plan.at(frame, fault))
+    }
+
+    /// Erros agendados, ordenados pelo quadro
+    pub fn faults(&self) -> &[(u64, InjectedFault)] {
+        &self.faults
+    }
+
+    pub fn len(&self) -> usize {
+        self.faults.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.faults.is_empty()
+    }
+}
+
+/// Estado de um `FaultPlan` durante a captura: quadro atual e erros já injetados
+#[derive(Debug, Clone, Default)]
+pub struct FaultInjector {
+    plan: FaultPlan,
+    // Próximo erro do plano ainda não injetado
+    next: usize,
+    // Quadro em andamento; `None` antes da primeira captura
+    frame: Option<u64>,
+    injected: Vec<(u64, InjectedFault)>,
+}
+
+impl FaultInjector {
+    pub fn new(plan: FaultPlan) -> Self {
+        Self {
+            plan,
+            ..Self::default()
+        }
+    }
+
+    pub fn plan(&self) -> &FaultPlan {
+        &self.plan
+    }
+
+    /// Começa a próxima captura e retorna o número dela
+    pub fn begin_frame(&mut self) -> u64 {
+        let frame = self.frame.map_or(0, |frame| frame + 1);
+        self.frame = Some(frame);
+        // Erros de quadros que não chegaram a ser tentados não valem para os seguintes
+        while self.plan.faults.get(self.next).is_some_and(|&(scheduled, _)| scheduled < frame) {
+            self.next += 1;
+        }
+        frame
+    }
+
+    /// Próximo erro agendado para a captura em andamento, se houver
+    pub fn take(&mut self) -> Option<InjectedFault> {
+        let frame = self.frame?;
+        let &(scheduled, fault) = self.plan.faults.get(self.next)?;
+        if scheduled != frame {
+            return None;
+        }
+        self.next += 1;
+        self.injected.push((frame, fault));
+        Some(fault)
+    }
+
+    /// Erros injetados até agora, com o quadro de cada um
+    pub fn injected(&self) -> &[(u64, InjectedFault)] {
+        &self.injected
+    }
+
+    /// Todos os erros do plano já passaram
+    pub fn is_exhausted(&self) -> bool {
+        self.next >= self.plan.faults.len()
+    }
+}
+
+/// Fonte que devolve os erros do plano no lugar dos quadros de outra fonte
+pub struct FaultySource<S> {
+    source: S,
+    injector: FaultInjector,
+}
+
+impl<S: ScreenSource> FaultySource<S> {
+    pub fn new(source: S, plan: FaultPlan) -> Self {
+        Self {
+            source,
+            injector: FaultInjector::new(plan),
+        }
+    }
+
+    pub fn injector(&self) -> &FaultInjector {
+        &self.injector
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.source
+    }
+
+    pub fn inner_mut(&mut self) -> &mut S {
+        &mut self.source
+    }
+
+    pub fn into_inner(self) -> S {
+        self.source
+    }
+}
+
+impl<S: ScreenSource> ScreenSource for FaultySource<S> {
+    fn backend(&self) -> Backend {
+        self.source.backend()
+    }
+
+    fn output_size(&self) -> Size {
+        self.source.output_size()
+    }
+
+    fn format(&self) -> DXGI_FORMAT {
+        self.source.format()
+    }
+
+    /// O erro agendado para esta captura ou o quadro da fonte; a captura com falha não chega
+    /// à fonte, como um `AcquireNextFrame` que falhou
+    fn capture_frame(&mut self, region: Rect) -> CaptureResult<Frame> {
+        self.injector.begin_frame();
+        match self.injector.take() {
+            Some(fault) => Err(fault.to_error()),
+            None => self.source.capture_frame(region),
+        }
+    }
+}
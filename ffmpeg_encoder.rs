@@ -0,0 +1,356 @@
+//! Codificação via ffmpeg (libav) para qualquer contêiner/codec suportado (feature `ffmpeg`)
+//!
+//! Útil onde o Media Foundation não está disponível ou quando é preciso um codec/contêiner
+//! específico. Encoders de hardware do ffmpeg (`h264_nvenc`, `hevc_amf`, `h264_qsv`...)
+//! são selecionados pelo nome.
+//!
+//! Com `FfmpegEncoderConfig::hdr10`, quadros R10G10B10A2 em HDR10 são codificados em 10 bits com
+//! PQ e primárias BT.2020, e os `HdrMetadata` seguem como side data do stream (caixas `mdcv` e
+//! `clli` do MP4/MKV) e dos quadros (SEIs dos encoders HEVC que as leem, além dos parâmetros
+//! `master-display`/`max-cll` passados ao `libx265`).
+
+use std::mem;
+use std::path::Path;
+
+use ffmpeg_next as ffmpeg;
+use ffmpeg::format::Pixel;
+use ffmpeg::software::scaling;
+use ffmpeg::{codec, color, encoder, ffi, format, frame, Dictionary, Packet, Rational};
+use windows::Win32::Graphics::Dxgi::Common::{
+    DXGI_FORMAT, DXGI_FORMAT_B8G8R8A8_UNORM, DXGI_FORMAT_R10G10B10A2_UNORM, DXGI_FORMAT_R8G8B8A8_UNORM,
+};
+
+use crate::hdr_metadata::HdrMetadata;
+use crate::pixel_format::PixelFormat;
+use crate::recording::{Marker, RecordingControl};
+use crate::timecode::{Timecode, TICKS_PER_SECOND};
+use crate::Result;
+
+/// Base de tempo do encoder: unidades de 100 ns
+const TIME_BASE: Rational = Rational(1, TICKS_PER_SECOND as i32);
+
+/// Configuração do encoder ffmpeg
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FfmpegEncoderConfig {
+    /// Nome do encoder (`libx264`, `h264_nvenc`, `hevc_qsv`...)
+    pub codec: String,
+    pub width: u32,
+    pub height: u32,
+    /// Taxa nominal, usada apenas como dica para o encoder/muxer
+    pub frame_rate: u32,
+    pub bitrate: Option<usize>,
+    /// Opções privadas do encoder (`preset`, `crf`, `tune`...)
+    pub options: Vec<(String, String)>,
+    /// Codifica em HDR10 com estes metadados; `None` codifica em SDR
+    pub hdr: Option<HdrMetadata>,
+}
+
+impl FfmpegEncoderConfig {
+    pub fn new(codec: &str, width: u32, height: u32, frame_rate: u32) -> Self {
+        Self {
+            codec: codec.to_string(),
+            width,
+            height,
+            frame_rate,
+            bitrate: None,
+            options: Vec::new(),
+            hdr: None,
+        }
+    }
+
+    /// Vídeo HDR10 em 10 bits (ex.: com `DxgiCapture::hdr_metadata`); exige um encoder com
+    /// formato de 10 bits (`libx265`, `hevc_nvenc`, `hevc_qsv`...) e quadros R10G10B10A2 em HDR10
+    pub fn hdr10(mut self, metadata: HdrMetadata) -> Self {
+        self.hdr = Some(metadata);
+        self
+    }
+}
+
+/// Codifica quadros capturados e os multiplexa em um arquivo
+pub struct FfmpegEncoder {
+    output: format::context::Output,
+    encoder: encoder::video::Encoder,
+    scaler: Option<scaling::Context>,
+    scaler_input: Pixel,
+    source: frame::Video,
+    converted: frame::Video,
+    stream_index: usize,
+    stream_time_base: Rational,
+    hdr: bool,
+    timecode: Timecode,
+    control: RecordingControl,
+}
+
+impl FfmpegEncoder {
+    pub fn create<P: AsRef<Path>>(path: P, config: &FfmpegEncoderConfig) -> Result<Self> {
+        ffmpeg::init()?;
+
+        let mut output = format::output(&path)?;
+        let global_header = output.format().flags().contains(format::Flags::GLOBAL_HEADER);
+
+        let codec = encoder::find_by_name(&config.codec)
+            .ok_or_else(|| format!("Encoder ffmpeg não encontrado: {}", config.codec))?;
+        let (semi_planar, planar) = match config.hdr {
+            Some(_) => (Pixel::P010LE, Pixel::YUV420P10LE),
+            None => (Pixel::NV12, Pixel::YUV420P),
+        };
+        let supported = codec
+            .video()?
+            .formats()
+            .and_then(|mut formats| formats.find(|&f| f == semi_planar || f == planar));
+        let pixel_format = match (supported, &config.hdr) {
+            (Some(format), _) => format,
+            (None, None) => Pixel::YUV420P,
+            (None, Some(_)) => {
+                return Err(format!("Encoder ffmpeg sem formato de 10 bits para HDR10: {}", config.codec).into())
+            }
+        };
+
+        let mut stream = output.add_stream(codec)?;
+        let mut context = codec::context::Context::new_with_codec(codec).encoder().video()?;
+        context.set_width(config.width);
+        context.set_height(config.height);
+        context.set_format(pixel_format);
+        context.set_time_base(TIME_BASE);
+        context.set_frame_rate(Some(Rational(config.frame_rate as i32, 1)));
+        if let Some(bitrate) = config.bitrate {
+            context.set_bit_rate(bitrate);
+        }
+        // VUI da saída do swscale: imagem sRGB convertida com a matriz BT.601 em faixa limitada,
+        // ou HDR10 (PQ) com a matriz e as primárias BT.2020
+        let (space, primaries, transfer) = match config.hdr {
+            Some(_) => (color::Space::BT2020NCL, color::Primaries::BT2020, color::TransferCharacteristic::SMPTE2084),
+            None => (color::Space::SMPTE170M, color::Primaries::BT709, color::TransferCharacteristic::IEC61966_2_1),
+        };
+        context.set_colorspace(space);
+        context.set_color_range(color::Range::MPEG);
+        unsafe {
+            let raw = context.as_mut_ptr();
+            (*raw).color_primaries = primaries.into();
+            (*raw).color_trc = transfer.into();
+        }
+        if global_header {
+            context.set_flags(codec::Flags::GLOBAL_HEADER);
+        }
+
+        let mut options = Dictionary::new();
+        for (key, value) in &config.options {
+            options.set(key, value);
+        }
+        // O libx265 só grava as SEIs de HDR pelos próprios parâmetros
+        if let (Some(hdr), "libx265") = (&config.hdr, config.codec.as_str()) {
+            let params = match options.get("x265-params") {
+                Some(existing) => format!("{}:{}", existing, hdr.x265_params()),
+                None => hdr.x265_params(),
+            };
+            options.set("x265-params", &params);
+        }
+
+        let encoder = context.open_as_with(codec, options)?;
+        stream.set_parameters(&encoder);
+        stream.set_time_base(TIME_BASE);
+        if let Some(hdr) = &config.hdr {
+            unsafe { add_stream_hdr_metadata(stream.as_mut_ptr(), hdr)? };
+        }
+        let stream_index = stream.index();
+
+        output.write_header()?;
+        let stream_time_base = output
+            .stream(stream_index)
+            .ok_or("Stream de saída não encontrado")?
+            .time_base();
+
+        let mut converted = frame::Video::new(pixel_format, config.width, config.height);
+        if let Some(hdr) = &config.hdr {
+            unsafe { add_frame_hdr_metadata(converted.as_mut_ptr(), hdr)? };
+        }
+
+        Ok(Self {
+            output,
+            encoder,
+            scaler: None,
+            scaler_input: Pixel::BGRA,
+            source: frame::Video::new(Pixel::BGRA, config.width, config.height),
+            converted,
+            stream_index,
+            stream_time_base,
+            hdr: config.hdr.is_some(),
+            timecode: Timecode::new()?,
+            control: RecordingControl::new(),
+        })
+    }
+
+    /// Codifica um quadro com o `LastPresentTime` (QPC) em que ele foi apresentado
+    pub fn push_frame(&mut self, pixels: &[u8], format: DXGI_FORMAT, present_time: i64) -> Result<()> {
+        let input = match format {
+            DXGI_FORMAT_B8G8R8A8_UNORM if !self.hdr => Pixel::BGRA,
+            DXGI_FORMAT_R8G8B8A8_UNORM if !self.hdr => Pixel::RGBA,
+            DXGI_FORMAT_R10G10B10A2_UNORM => Pixel::X2BGR10LE,
+            _ if self.hdr => {
+                return Err("O encoder HDR10 recebe quadros R10G10B10A2; converta com \
+                            `Frame::to_color_space(ColorSpace::Hdr10)`"
+                    .into())
+            }
+            _ => return Err(format!("Formato não suportado pelo encoder ffmpeg: {:?}", format).into()),
+        };
+
+        // Durante a pausa o quadro é descartado antes de qualquer conversão
+        let Some(pts) = self.control.map(self.timecode.timestamp(present_time)) else {
+            return Ok(());
+        };
+
+        let (width, height) = (self.encoder.width(), self.encoder.height());
+        let row_bytes = PixelFormat::Bgra8.min_stride(width);
+        if pixels.len() < row_bytes * height as usize {
+            return Err("Buffer menor que o quadro configurado no encoder".into());
+        }
+
+        if self.scaler.is_none() || self.scaler_input != input {
+            let mut scaler = scaling::Context::get(
+                input,
+                width,
+                height,
+                self.encoder.format(),
+                width,
+                height,
+                scaling::Flags::BILINEAR,
+            )?;
+            if self.hdr {
+                // O padrão do swscale é a matriz BT.601; HDR10 usa a BT.2020 em faixa limitada
+                unsafe {
+                    let coefficients = ffi::sws_getCoefficients(ffi::SWS_CS_BT2020 as i32);
+                    ffi::sws_setColorspaceDetails(
+                        scaler.as_mut_ptr(),
+                        coefficients,
+                        1,
+                        coefficients,
+                        0,
+                        0,
+                        1 << 16,
+                        1 << 16,
+                    );
+                }
+            }
+            self.scaler = Some(scaler);
+            self.source = frame::Video::new(input, width, height);
+            self.scaler_input = input;
+        }
+
+        let stride = self.source.stride(0);
+        let data = self.source.data_mut(0);
+        for (y, row) in pixels.chunks_exact(row_bytes).take(height as usize).enumerate() {
+            data[y * stride..y * stride + row_bytes].copy_from_slice(row);
+        }
+
+        if let Some(scaler) = self.scaler.as_mut() {
+            scaler.run(&self.source, &mut self.converted)?;
+        }
+
+        self.converted.set_pts(Some(pts));
+        self.encoder.send_frame(&self.converted)?;
+        self.drain_packets()
+    }
+
+    /// Descarta os quadros recebidos até `resume`
+    pub fn pause(&mut self) {
+        self.control.pause();
+    }
+
+    /// Volta a codificar; o tempo em pausa não aparece no vídeo
+    pub fn resume(&mut self) {
+        self.control.resume();
+    }
+
+    /// Marcador de capítulo na posição do último quadro codificado (PTS em 100 ns)
+    pub fn add_marker(&mut self, label: &str) {
+        self.control.add_marker(label);
+    }
+
+    pub fn markers(&self) -> &[Marker] {
+        self.control.markers()
+    }
+
+    /// Esvazia o encoder e finaliza o contêiner, gravando os marcadores como capítulos
+    /// (em contêineres que os suportam, como MP4 e MKV)
+    pub fn finish(mut self) -> Result<()> {
+        self.encoder.send_eof()?;
+        self.drain_packets()?;
+
+        let markers = self.control.markers();
+        let end = self.control.position();
+        for (index, marker) in markers.iter().enumerate() {
+            let chapter_end = markers.get(index + 1).map_or(end, |next| next.timestamp).max(marker.timestamp);
+            self.output
+                .add_chapter(index as i64, TIME_BASE, marker.timestamp, chapter_end, &marker.label)?;
+        }
+
+        self.output.write_trailer()?;
+        Ok(())
+    }
+
+    fn drain_packets(&mut self) -> Result<()> {
+        let mut packet = Packet::empty();
+        while self.encoder.receive_packet(&mut packet).is_ok() {
+            packet.set_stream(self.stream_index);
+            packet.rescale_ts(TIME_BASE, self.stream_time_base);
+            packet.write_interleaved(&mut self.output)?;
+        }
+        Ok(())
+    }
+}
+
+/// Volume de cor do mastering display com as coordenadas e luminâncias em frações do ffmpeg
+fn mastering_display(hdr: &HdrMetadata, metadata: &mut ffi::AVMasteringDisplayMetadata) {
+    let chromaticity = |value: f32| ffi::AVRational { num: (value * 50_000.0).round() as i32, den: 50_000 };
+    let luminance = |value: f32| ffi::AVRational { num: (value * 10_000.0).round() as i32, den: 10_000 };
+    // O ffmpeg lista as primárias na ordem vermelho, verde, azul
+    let primaries = [hdr.red_primary, hdr.green_primary, hdr.blue_primary];
+    for (target, xy) in metadata.display_primaries.iter_mut().zip(primaries) {
+        *target = xy.map(chromaticity);
+    }
+    metadata.white_point = hdr.white_point.map(chromaticity);
+    metadata.min_luminance = luminance(hdr.min_mastering_luminance);
+    metadata.max_luminance = luminance(hdr.max_mastering_luminance);
+    metadata.has_primaries = 1;
+    metadata.has_luminance = 1;
+}
+
+/// Side data do stream, de onde os muxers gravam `mdcv`/`clli` (MP4) ou `MasteringMetadata` (MKV)
+unsafe fn add_stream_hdr_metadata(stream: *mut ffi::AVStream, hdr: &HdrMetadata) -> Result<()> {
+    let mastering = ffi::av_stream_new_side_data(
+        stream,
+        ffi::AVPacketSideDataType::AV_PKT_DATA_MASTERING_DISPLAY_METADATA,
+        mem::size_of::<ffi::AVMasteringDisplayMetadata>(),
+    ) as *mut ffi::AVMasteringDisplayMetadata;
+    let light_level = ffi::av_stream_new_side_data(
+        stream,
+        ffi::AVPacketSideDataType::AV_PKT_DATA_CONTENT_LIGHT_LEVEL,
+        mem::size_of::<ffi::AVContentLightMetadata>(),
+    ) as *mut ffi::AVContentLightMetadata;
+    if mastering.is_null() || light_level.is_null() {
+        return Err("Falha ao alocar os metadados HDR do stream".into());
+    }
+
+    mastering.write(mem::zeroed());
+    mastering_display(hdr, &mut *mastering);
+    light_level.write(ffi::AVContentLightMetadata {
+        MaxCLL: hdr.max_content_light_level as _,
+        MaxFALL: hdr.max_frame_average_light_level as _,
+    });
+    Ok(())
+}
+
+/// Side data dos quadros, de onde os encoders HEVC geram as SEIs
+unsafe fn add_frame_hdr_metadata(frame: *mut ffi::AVFrame, hdr: &HdrMetadata) -> Result<()> {
+    let mastering = ffi::av_mastering_display_metadata_create_side_data(frame);
+    let light_level = ffi::av_content_light_metadata_create_side_data(frame);
+    if mastering.is_null() || light_level.is_null() {
+        return Err("Falha ao alocar os metadados HDR do quadro".into());
+    }
+
+    mastering_display(hdr, &mut *mastering);
+    (*light_level).MaxCLL = hdr.max_content_light_level as _;
+    (*light_level).MaxFALL = hdr.max_frame_average_light_level as _;
+    Ok(())
+}
@@ -0,0 +1,298 @@
+//! Quadro capturado com seus metadados
+
+use std::time::Duration;
+
+use windows::Win32::Graphics::Dxgi::Common::DXGI_FORMAT;
+
+use crate::convert::{color_space_format, convert_color_space};
+use crate::geometry::{Point, Rect, Size};
+use crate::outputs::ColorSpace;
+use crate::pixel_format::{layout_of, PixelFormat};
+use crate::timecode::present_age;
+use crate::Result;
+
+/// Pixels amostrados por eixo em `Frame::is_blank`
+const BLANK_SAMPLES: usize = 64;
+
+/// Pixels de uma região do monitor e as informações de quando e como foram capturados
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    /// Pixels sem padding entre linhas
+    pub data: Vec<u8>,
+    /// Região capturada, relativa ao monitor
+    pub region: Rect,
+    pub format: DXGI_FORMAT,
+    pub color_space: ColorSpace,
+    /// Valor de QPC do present que produziu a imagem
+    pub present_time: i64,
+    /// Número sequencial atribuído pelo capturador, começando em 0
+    pub sequence: u64,
+    pub metadata: FrameMetadata,
+}
+
+/// Informações da duplicação sobre o quadro, além dos pixels
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FrameMetadata {
+    /// Valor de QPC de quando o quadro foi obtido da duplicação (0 se não veio dela)
+    pub acquire_time: i64,
+    /// Retângulos alterados desde o quadro anterior; `Some(0)` se a tela não mudou e `None` se
+    /// a duplicação não informou
+    pub dirty_rects: Option<u32>,
+    /// Áreas movidas (ex.: rolagem, janela arrastada) desde o quadro anterior
+    pub move_rects: Option<u32>,
+    /// Posição do cursor relativa ao monitor, se estava visível
+    pub cursor: Option<Point>,
+    /// Presents juntados neste quadro (`AccumulatedFrames`): 1 é o esperado, mais que 1 indica
+    /// quadros que a captura não viu e 0 que a tela não mudou
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub accumulated_frames: u32,
+}
+
+impl FrameMetadata {
+    /// Presents que a duplicação juntou a este quadro sem que a captura os visse
+    pub fn missed_presents(&self) -> u32 {
+        self.accumulated_frames.saturating_sub(1)
+    }
+}
+
+impl Frame {
+    pub fn width(&self) -> u32 {
+        self.region.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.region.height
+    }
+
+    pub fn size(&self) -> Size {
+        self.region.size()
+    }
+
+    /// Verificação amostrada na CPU: o quadro inteiro é de uma única cor (ex.: todo preto).
+    ///
+    /// Compara até 64x64 pixels distribuídos pelo quadro, ignorando o alfa; barata o suficiente
+    /// para rodar em cada quadro de um `FrameSink`. Para a verificação completa na GPU use
+    /// `DxgiCapture::is_blank`.
+    pub fn is_blank(&self) -> bool {
+        let layout = layout_of(self.format);
+        if layout.is_planar() {
+            return false;
+        }
+        let pixel_bytes = layout.bytes_per_pixel() as usize;
+        let color_bytes = pixel_bytes / 4 * 3;
+        let (width, height) = (self.width() as usize, self.height() as usize);
+        if width == 0 || height == 0 || self.data.len() < width * height * pixel_bytes {
+            return false;
+        }
+
+        let pixel = |x: usize, y: usize| {
+            let offset = (y * width + x) * pixel_bytes;
+            &self.data[offset..offset + color_bytes]
+        };
+
+        let first = pixel(0, 0);
+        let (step_x, step_y) = (width.div_ceil(BLANK_SAMPLES).max(1), height.div_ceil(BLANK_SAMPLES).max(1));
+        (0..height)
+            .step_by(step_y)
+            .all(|y| (0..width).step_by(step_x).all(|x| pixel(x, y) == first))
+    }
+
+    /// Formato de `data`, se for um dos descritos por `PixelFormat`
+    pub fn pixel_format(&self) -> Option<PixelFormat> {
+        PixelFormat::from_dxgi(self.format)
+    }
+
+    /// Idade dos pixels: tempo desde o present que os produziu; `None` se o quadro não veio de
+    /// um present conhecido
+    pub fn age(&self) -> Option<Duration> {
+        present_age(self.present_time)
+    }
+
+    /// Bytes por linha de `data`
+    pub fn stride(&self) -> usize {
+        layout_of(self.format).min_stride(self.region.width)
+    }
+
+    /// Linhas de `data`, sem padding (nos formatos planares, só as do plano Y)
+    pub fn rows(&self) -> impl ExactSizeIterator<Item = &[u8]> + '_ {
+        self.data.chunks_exact(self.stride().max(1)).take(self.height() as usize)
+    }
+
+    /// O quadro inteiro como `FrameView`
+    pub fn view(&self) -> Result<FrameView<'_>> {
+        self.crop(Rect::from_size(self.size()))
+    }
+
+    /// Vista de `rect` (relativo ao quadro, com (0, 0) no seu canto superior esquerdo) que
+    /// empresta os pixels em vez de copiá-los. Formatos planares não podem ser recortados.
+    pub fn crop(&self, rect: Rect) -> Result<FrameView<'_>> {
+        if layout_of(self.format).is_planar() {
+            return Err(format!("Formato planar não pode ser recortado: {:?}", self.format).into());
+        }
+        if self.data.len() < self.stride() * self.height() as usize {
+            return Err("Buffer menor que o quadro".into());
+        }
+        rect.validate_within(self.size())?;
+        Ok(FrameView { frame: self, rect })
+    }
+
+    /// Divide o quadro em uma grade de `n` x `n` vistas, da esquerda para a direita e de cima
+    /// para baixo; as larguras e alturas diferem em no máximo um pixel
+    pub fn split_tiles(&self, n: u32) -> Result<Vec<FrameView<'_>>> {
+        self.view()?.split_tiles(n)
+    }
+
+    /// Cópia do quadro convertida para `color_space`, em B8G8R8A8 (sRGB), R16G16B16A16_FLOAT
+    /// (scRGB) ou R10G10B10A2 (HDR10). HDR levado para sRGB é recortado, sem tone mapping.
+    pub fn to_color_space(&self, color_space: ColorSpace) -> Result<Frame> {
+        if color_space == self.color_space && Some(self.format) == color_space_format(color_space) {
+            return Ok(self.clone());
+        }
+        let (data, format) = convert_color_space(
+            &self.data,
+            self.format,
+            self.color_space,
+            color_space,
+            self.region.width,
+            self.region.height,
+        )?;
+        Ok(Frame {
+            data,
+            region: self.region,
+            format,
+            color_space,
+            present_time: self.present_time,
+            sequence: self.sequence,
+            metadata: self.metadata,
+        })
+    }
+}
+
+/// Sub-região de um `Frame` que empresta os pixels do quadro, para usar um quadro capturado
+/// em várias finalidades (recortes, blocos para threads, linhas) sem cópia nem nova captura
+#[derive(Debug, Clone, Copy)]
+pub struct FrameView<'a> {
+    frame: &'a Frame,
+    /// Relativo ao quadro
+    rect: Rect,
+}
+
+impl<'a> FrameView<'a> {
+    /// Quadro de origem
+    pub fn frame(&self) -> &'a Frame {
+        self.frame
+    }
+
+    /// Posição da vista no quadro
+    pub fn rect(&self) -> Rect {
+        self.rect
+    }
+
+    /// Posição da vista no monitor, como `Frame::region`
+    pub fn region(&self) -> Rect {
+        Rect::new(
+            self.frame.region.left + self.rect.left,
+            self.frame.region.top + self.rect.top,
+            self.rect.width,
+            self.rect.height,
+        )
+    }
+
+    pub fn width(&self) -> u32 {
+        self.rect.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.rect.height
+    }
+
+    pub fn size(&self) -> Size {
+        self.rect.size()
+    }
+
+    /// Bytes de cada linha da vista
+    pub fn row_bytes(&self) -> usize {
+        layout_of(self.frame.format).min_stride(self.rect.width)
+    }
+
+    /// Linha `y` da vista, `None` fora dela
+    pub fn row(&self, y: u32) -> Option<&'a [u8]> {
+        if y >= self.rect.height {
+            return None;
+        }
+        let stride = self.frame.stride();
+        let start = (self.rect.top as usize + y as usize) * stride + self.column_offset();
+        self.frame.data.get(start..start + self.row_bytes())
+    }
+
+    /// Linhas da vista, de cima para baixo
+    pub fn rows(&self) -> impl ExactSizeIterator<Item = &'a [u8]> + 'a {
+        let (offset, row_bytes) = (self.column_offset(), self.row_bytes());
+        self.frame
+            .data
+            .chunks_exact(self.frame.stride().max(1))
+            .skip(self.rect.top as usize)
+            .take(self.rect.height as usize)
+            .map(move |row| &row[offset..offset + row_bytes])
+    }
+
+    /// Sub-vista de `rect`, relativo a esta vista
+    pub fn crop(&self, rect: Rect) -> Result<FrameView<'a>> {
+        rect.validate_within(self.size())?;
+        Ok(FrameView {
+            frame: self.frame,
+            rect: Rect::new(self.rect.left + rect.left, self.rect.top + rect.top, rect.width, rect.height),
+        })
+    }
+
+    /// Divide a vista em uma grade de `n` x `n`, como `Frame::split_tiles`
+    pub fn split_tiles(&self, n: u32) -> Result<Vec<FrameView<'a>>> {
+        if n == 0 || n > self.rect.width || n > self.rect.height {
+            let (width, height) = (self.rect.width, self.rect.height);
+            return Err(format!("Não é possível dividir {}x{} em {}x{} blocos", width, height, n, n).into());
+        }
+        let edge = |length: u32, index: u32| (length as u64 * index as u64 / n as u64) as u32;
+
+        let mut tiles = Vec::with_capacity((n * n) as usize);
+        for row in 0..n {
+            let (top, bottom) = (edge(self.rect.height, row), edge(self.rect.height, row + 1));
+            for column in 0..n {
+                let (left, right) = (edge(self.rect.width, column), edge(self.rect.width, column + 1));
+                tiles.push(self.crop(Rect::new(left as i32, top as i32, right - left, bottom - top))?);
+            }
+        }
+        Ok(tiles)
+    }
+
+    /// Cópia da vista como quadro independente, com a região no monitor e os metadados do original
+    pub fn to_frame(&self) -> Frame {
+        let mut data = Vec::with_capacity(self.row_bytes() * self.rect.height as usize);
+        for row in self.rows() {
+            data.extend_from_slice(row);
+        }
+        Frame {
+            data,
+            region: self.region(),
+            format: self.frame.format,
+            color_space: self.frame.color_space,
+            present_time: self.frame.present_time,
+            sequence: self.frame.sequence,
+            metadata: self.frame.metadata,
+        }
+    }
+
+    fn column_offset(&self) -> usize {
+        layout_of(self.frame.format).min_stride(self.rect.left as u32)
+    }
+}
+
+/// Um nível da cadeia de mips de `DxgiCapture::capture_mips`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MipLevel {
+    /// 0 é a resolução original; cada nível tem metade da largura e da altura do anterior
+    pub level: u32,
+    pub size: Size,
+    /// Pixels sem padding entre linhas, no formato da captura
+    pub data: Vec<u8>,
+}
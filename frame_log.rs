@@ -0,0 +1,120 @@
+//! Registro por quadro em JSON Lines ao lado de uma gravação
+//!
+//! Cada linha descreve um quadro entregue: número sequencial, timestamps, retângulos
+//! alterados, cursor e quantos quadros foram perdidos antes dele. Serve para análise
+//! posterior e para alinhar a gravação com logs externos pelo horário de parede.
+//!
+//! ```text
+//! {"sequence":41,"timestamp":13666666,"present_time":88112233,"acquire_time":88112301,"wall_time_ms":1700000000123,"dirty_rects":3,"move_rects":0,"cursor":[812,440],"accumulated_frames":1,"dropped":0}
+//! ```
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use windows::Win32::System::Performance::QueryPerformanceCounter;
+
+use crate::frame::Frame;
+use crate::sink::FrameSink;
+use crate::timecode::Timecode;
+use crate::Result;
+
+/// Escreve uma linha JSON por quadro; também é um `FrameSink`
+pub struct FrameLog<W: Write> {
+    writer: W,
+    timecode: Timecode,
+    // Horário de parede (ms desde a época Unix) e QPC no mesmo instante, para converter QPC
+    wall_base: (i64, i64),
+    last_sequence: Option<u64>,
+    dropped: u64,
+}
+
+impl FrameLog<BufWriter<File>> {
+    /// Cria o registro em `path` (ver `frame_log_path`)
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+}
+
+impl<W: Write> FrameLog<W> {
+    pub fn new(writer: W) -> Result<Self> {
+        let mut qpc = 0i64;
+        unsafe { QueryPerformanceCounter(&mut qpc)? };
+        let wall = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_millis() as i64);
+
+        Ok(Self {
+            writer,
+            timecode: Timecode::new()?,
+            wall_base: (wall, qpc),
+            last_sequence: None,
+            dropped: 0,
+        })
+    }
+
+    /// Quadros perdidos até agora (saltos na numeração sequencial)
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Registra o quadro. `timestamp` usa a mesma linha do tempo de 100 ns dos gravadores, e
+    /// `dropped` conta os quadros pulados desde o anterior registrado.
+    pub fn write_frame(&mut self, frame: &Frame) -> Result<()> {
+        let dropped = match self.last_sequence {
+            Some(last) => frame.sequence.saturating_sub(last + 1),
+            None => 0,
+        };
+        self.dropped += dropped;
+        self.last_sequence = Some(frame.sequence);
+
+        let metadata = &frame.metadata;
+        let (wall, base_qpc) = self.wall_base;
+        let wall_time = if metadata.acquire_time != 0 {
+            wall + self.timecode.qpc_to_ticks(metadata.acquire_time - base_qpc) / 10_000
+        } else {
+            SystemTime::now().duration_since(UNIX_EPOCH).map_or(wall, |elapsed| elapsed.as_millis() as i64)
+        };
+
+        writeln!(
+            self.writer,
+            "{{\"sequence\":{},\"timestamp\":{},\"present_time\":{},\"acquire_time\":{},\"wall_time_ms\":{},\"dirty_rects\":{},\"move_rects\":{},\"cursor\":{},\"accumulated_frames\":{},\"dropped\":{}}}",
+            frame.sequence,
+            self.timecode.timestamp(frame.present_time),
+            frame.present_time,
+            metadata.acquire_time,
+            wall_time,
+            json_count(metadata.dirty_rects),
+            json_count(metadata.move_rects),
+            metadata.cursor.map_or("null".to_owned(), |cursor| format!("[{},{}]", cursor.x, cursor.y)),
+            metadata.accumulated_frames,
+            dropped
+        )?;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+impl<W: Write + Send> FrameSink for FrameLog<W> {
+    fn on_frame(&mut self, frame: &Frame) -> Result<()> {
+        self.write_frame(frame)
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.flush()
+    }
+}
+
+/// Caminho do registro por quadro de uma gravação (`<arquivo>.frames.jsonl`)
+pub fn frame_log_path(recording: &Path) -> PathBuf {
+    let mut name = recording.as_os_str().to_os_string();
+    name.push(".frames.jsonl");
+    PathBuf::from(name)
+}
+
+fn json_count(count: Option<u32>) -> String {
+    count.map_or("null".to_owned(), |count| count.to_string())
+}
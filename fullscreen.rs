@@ -0,0 +1,89 @@
+//! Detecção de aplicativos em tela cheia no monitor capturado
+//!
+//! Em tela cheia exclusiva o jogo assume o controle do output: cada troca de modo derruba a
+//! duplicação (`DXGI_ERROR_ACCESS_LOST`) e, conforme o driver, os quadros podem vir pretos ou
+//! parados enquanto o aplicativo estiver em primeiro plano. Em tela cheia sem bordas
+//! (flip model) o DWM continua compondo e a captura funciona normalmente.
+
+use std::time::{Duration, Instant};
+
+use windows::Win32::Foundation::{HWND, RECT};
+use windows::Win32::Graphics::Gdi::*;
+use windows::Win32::UI::Shell::{SHQueryUserNotificationState, QUNS_RUNNING_D3D_FULL_SCREEN};
+use windows::Win32::UI::WindowsAndMessaging::*;
+
+/// Perdas de acesso mais recentes que isto contam como indício de tela cheia exclusiva
+pub const ACCESS_LOST_WINDOW: Duration = Duration::from_secs(2);
+
+/// Ocupação do monitor pela janela em primeiro plano
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FullscreenState {
+    /// Nenhuma janela cobre o monitor inteiro
+    Windowed,
+    /// Janela sem bordas cobrindo o monitor; o DWM ainda compõe a tela
+    Borderless,
+    /// Aplicativo Direct3D em tela cheia exclusiva
+    Exclusive,
+}
+
+/// Aplicativo em tela cheia detectado no monitor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FullscreenApp {
+    pub state: FullscreenState,
+    pub hwnd: HWND,
+    pub process_id: u32,
+}
+
+/// Verifica se a janela em primeiro plano ocupa `monitor` inteiro.
+///
+/// `last_access_lost` é o instante da última perda de acesso da duplicação do monitor;
+/// junto com `SHQueryUserNotificationState` distingue a tela cheia exclusiva da sem bordas.
+pub fn detect(monitor: HMONITOR, last_access_lost: Option<Instant>) -> Option<FullscreenApp> {
+    let hwnd = unsafe { GetForegroundWindow() };
+    if hwnd.0 == 0 || hwnd == unsafe { GetShellWindow() } || hwnd == unsafe { GetDesktopWindow() } {
+        return None;
+    }
+
+    if unsafe { MonitorFromWindow(hwnd, MONITOR_DEFAULTTONULL) } != monitor {
+        return None;
+    }
+
+    let mut monitor_info = MONITORINFO {
+        cbSize: std::mem::size_of::<MONITORINFO>() as u32,
+        ..Default::default()
+    };
+    let mut window_rect = RECT::default();
+    unsafe {
+        if !GetMonitorInfoW(monitor, &mut monitor_info).as_bool() || GetWindowRect(hwnd, &mut window_rect).is_err() {
+            return None;
+        }
+    }
+
+    let screen = monitor_info.rcMonitor;
+    let covers = window_rect.left <= screen.left
+        && window_rect.top <= screen.top
+        && window_rect.right >= screen.right
+        && window_rect.bottom >= screen.bottom;
+    if !covers {
+        return None;
+    }
+
+    let d3d_fullscreen = unsafe { SHQueryUserNotificationState() }
+        .map_or(false, |state| state == QUNS_RUNNING_D3D_FULL_SCREEN);
+    let recent_access_lost = last_access_lost.map_or(false, |at| at.elapsed() < ACCESS_LOST_WINDOW);
+
+    let mut process_id = 0;
+    unsafe {
+        GetWindowThreadProcessId(hwnd, Some(&mut process_id));
+    }
+
+    Some(FullscreenApp {
+        state: if d3d_fullscreen || recent_access_lost {
+            FullscreenState::Exclusive
+        } else {
+            FullscreenState::Borderless
+        },
+        hwnd,
+        process_id,
+    })
+}
@@ -0,0 +1,446 @@
+//! Tipos geométricos usados pelas APIs de captura
+//!
+//! `Rect` é sempre relativo ao monitor capturado (origem no canto superior esquerdo do output).
+//! Coordenadas do desktop virtual, que podem ser negativas, usam `Point`.
+
+use std::error::Error;
+use std::fmt;
+
+/// Ponto no desktop virtual ou em um monitor
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Point {
+    pub const fn new(x: i32, y: i32) -> Self {
+        Self { x, y }
+    }
+
+    /// Converte de pixels lógicos para físicos dado o DPI do monitor (96 = 100%)
+    pub fn logical_to_physical(&self, dpi: u32) -> Self {
+        let factor = dpi as f64 / 96.0;
+        Self::new((self.x as f64 * factor).round() as i32, (self.y as f64 * factor).round() as i32)
+    }
+
+    /// Converte de pixels físicos para lógicos dado o DPI do monitor (96 = 100%)
+    pub fn physical_to_logical(&self, dpi: u32) -> Self {
+        let factor = 96.0 / dpi.max(1) as f64;
+        Self::new((self.x as f64 * factor).round() as i32, (self.y as f64 * factor).round() as i32)
+    }
+}
+
+/// Dimensões em pixels
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Size {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Size {
+    pub const fn new(width: u32, height: u32) -> Self {
+        Self { width, height }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    /// Número de pixels da área
+    pub fn area(&self) -> u64 {
+        self.width as u64 * self.height as u64
+    }
+
+    /// Escala as dimensões (ex.: de pixels lógicos para físicos), arredondando
+    pub fn scale(&self, factor: f64) -> Self {
+        Self {
+            width: (self.width as f64 * factor).round() as u32,
+            height: (self.height as f64 * factor).round() as u32,
+        }
+    }
+}
+
+/// Motivo pelo qual um retângulo não pode ser usado
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RectError {
+    /// Largura ou altura zero
+    Empty,
+    /// Borda direita/inferior antes da esquerda/superior
+    Inverted,
+    /// Coordenadas não representáveis (estouro aritmético)
+    Overflow,
+    /// Parte do retângulo fica fora dos limites do monitor
+    OutOfBounds { region: Rect, bounds: Size },
+}
+
+impl fmt::Display for RectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RectError::Empty => write!(f, "region is empty (zero width or height)"),
+            RectError::Inverted => write!(f, "region is inverted (end edge before start edge)"),
+            RectError::Overflow => write!(f, "region coordinates are out of the representable range"),
+            RectError::OutOfBounds { region, bounds } => write!(
+                f,
+                "region {}x{} at ({}, {}) exceeds the {}x{} output",
+                region.width, region.height, region.left, region.top, bounds.width, bounds.height
+            ),
+        }
+    }
+}
+
+impl Error for RectError {}
+
+/// Retângulo relativo ao monitor capturado.
+///
+/// A origem é com sinal para representar regiões parcialmente à esquerda/acima do monitor;
+/// as bordas são calculadas em `i64`, então nenhuma combinação de valores estoura.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rect {
+    pub left: i32,
+    pub top: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    pub const fn new(left: i32, top: i32, width: u32, height: u32) -> Self {
+        Self { left, top, width, height }
+    }
+
+    /// Retângulo com origem em (0, 0) cobrindo `size`
+    pub const fn from_size(size: Size) -> Self {
+        Self::new(0, 0, size.width, size.height)
+    }
+
+    /// Cria a partir das bordas (direita/inferior exclusivas), como um `RECT` do Win32
+    pub fn from_edges(left: i32, top: i32, right: i32, bottom: i32) -> Result<Self, RectError> {
+        if right < left || bottom < top {
+            return Err(RectError::Inverted);
+        }
+
+        let width = (right as i64 - left as i64) as u32;
+        let height = (bottom as i64 - top as i64) as u32;
+        Ok(Self::new(left, top, width, height))
+    }
+
+    /// Converte um retângulo do desktop virtual para coordenadas do monitor com origem `output_origin`
+    pub fn from_virtual(origin: Point, size: Size, output_origin: Point) -> Result<Self, RectError> {
+        let left = origin.x.checked_sub(output_origin.x).ok_or(RectError::Overflow)?;
+        let top = origin.y.checked_sub(output_origin.y).ok_or(RectError::Overflow)?;
+        Ok(Self::new(left, top, size.width, size.height))
+    }
+
+    /// Canto superior esquerdo no desktop virtual
+    pub fn to_virtual(&self, output_origin: Point) -> Result<Point, RectError> {
+        let x = output_origin.x.checked_add(self.left).ok_or(RectError::Overflow)?;
+        let y = output_origin.y.checked_add(self.top).ok_or(RectError::Overflow)?;
+        Ok(Point::new(x, y))
+    }
+
+    pub fn size(&self) -> Size {
+        Size::new(self.width, self.height)
+    }
+
+    /// Coordenada X logo após a última coluna
+    pub fn right(&self) -> i64 {
+        self.left as i64 + self.width as i64
+    }
+
+    /// Coordenada Y logo após a última linha
+    pub fn bottom(&self) -> i64 {
+        self.top as i64 + self.height as i64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    pub fn contains(&self, point: Point) -> bool {
+        point.x >= self.left
+            && point.y >= self.top
+            && (point.x as i64) < self.right()
+            && (point.y as i64) < self.bottom()
+    }
+
+    /// Verifica se `other` está inteiramente dentro deste retângulo
+    pub fn contains_rect(&self, other: &Rect) -> bool {
+        other.left >= self.left && other.top >= self.top && other.right() <= self.right() && other.bottom() <= self.bottom()
+    }
+
+    /// Garante que a região não é vazia e cabe inteira em um monitor de tamanho `bounds`
+    pub fn validate_within(&self, bounds: Size) -> Result<(), RectError> {
+        if self.is_empty() {
+            return Err(RectError::Empty);
+        }
+
+        if !Rect::from_size(bounds).contains_rect(self) {
+            return Err(RectError::OutOfBounds { region: *self, bounds });
+        }
+
+        Ok(())
+    }
+
+    /// Área comum aos dois retângulos, ou `None` se não se sobrepõem
+    pub fn intersect(&self, other: &Rect) -> Option<Rect> {
+        let left = self.left.max(other.left);
+        let top = self.top.max(other.top);
+        let right = self.right().min(other.right());
+        let bottom = self.bottom().min(other.bottom());
+
+        if right <= left as i64 || bottom <= top as i64 {
+            return None;
+        }
+
+        Some(Rect::new(left, top, (right - left as i64) as u32, (bottom - top as i64) as u32))
+    }
+
+    /// Menor retângulo que contém os dois
+    pub fn union(&self, other: &Rect) -> Rect {
+        if self.is_empty() {
+            return *other;
+        }
+        if other.is_empty() {
+            return *self;
+        }
+
+        let left = self.left.min(other.left);
+        let top = self.top.min(other.top);
+        let right = self.right().max(other.right());
+        let bottom = self.bottom().max(other.bottom());
+        Rect::new(left, top, (right - left as i64).min(u32::MAX as i64) as u32, (bottom - top as i64).min(u32::MAX as i64) as u32)
+    }
+
+    /// Retângulo de tamanho `size` centrado em `center` e deslocado para caber inteiro em um
+    /// monitor de tamanho `bounds` (o tamanho é reduzido só se for maior que o monitor)
+    pub fn follow(center: Point, size: Size, bounds: Size) -> Result<Rect, RectError> {
+        let width = size.width.min(bounds.width);
+        let height = size.height.min(bounds.height);
+        if width == 0 || height == 0 {
+            return Err(RectError::Empty);
+        }
+
+        let place = |center: i32, length: u32, total: u32| {
+            (center as i64 - length as i64 / 2).clamp(0, total as i64 - length as i64) as i32
+        };
+        Ok(Rect::new(place(center.x, width, bounds.width), place(center.y, height, bounds.height), width, height))
+    }
+
+    /// Recorta o retângulo aos limites de um monitor de tamanho `bounds`
+    pub fn clamp_to(&self, bounds: Size) -> Option<Rect> {
+        self.intersect(&Rect::from_size(bounds))
+    }
+
+    /// Escala posição e tamanho (ex.: de pixels lógicos para físicos), arredondando
+    pub fn scale(&self, factor: f64) -> Self {
+        let scale = |value: f64| (value * factor).round();
+        Self::new(
+            scale(self.left as f64) as i32,
+            scale(self.top as f64) as i32,
+            scale(self.width as f64) as u32,
+            scale(self.height as f64) as u32,
+        )
+    }
+
+    /// Converte de pixels lógicos para físicos dado o DPI do monitor (96 = 100%)
+    pub fn logical_to_physical(&self, dpi: u32) -> Self {
+        self.scale(dpi as f64 / 96.0)
+    }
+
+    /// Converte de pixels físicos para lógicos dado o DPI do monitor (96 = 100%)
+    pub fn physical_to_logical(&self, dpi: u32) -> Self {
+        self.scale(96.0 / dpi.max(1) as f64)
+    }
+}
+
+/// Região de tamanho fixo presa a uma borda, canto, ao centro do monitor ou ao cursor.
+///
+/// `margin` é a distância até as bordas às quais a região está presa (ignorada no eixo
+/// centralizado). Em monitores menores que a região ela é recortada às bordas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Anchor {
+    TopLeft {
+        width: u32,
+        height: u32,
+        #[cfg_attr(feature = "serde", serde(default))]
+        margin: u32,
+    },
+    Top {
+        width: u32,
+        height: u32,
+        #[cfg_attr(feature = "serde", serde(default))]
+        margin: u32,
+    },
+    TopRight {
+        width: u32,
+        height: u32,
+        #[cfg_attr(feature = "serde", serde(default))]
+        margin: u32,
+    },
+    Left {
+        width: u32,
+        height: u32,
+        #[cfg_attr(feature = "serde", serde(default))]
+        margin: u32,
+    },
+    Center {
+        width: u32,
+        height: u32,
+    },
+    Right {
+        width: u32,
+        height: u32,
+        #[cfg_attr(feature = "serde", serde(default))]
+        margin: u32,
+    },
+    BottomLeft {
+        width: u32,
+        height: u32,
+        #[cfg_attr(feature = "serde", serde(default))]
+        margin: u32,
+    },
+    Bottom {
+        width: u32,
+        height: u32,
+        #[cfg_attr(feature = "serde", serde(default))]
+        margin: u32,
+    },
+    BottomRight {
+        width: u32,
+        height: u32,
+        #[cfg_attr(feature = "serde", serde(default))]
+        margin: u32,
+    },
+    /// Centrada no cursor e deslocada (sem mudar de tamanho) para não sair do monitor: a
+    /// vista de lupa de ferramentas de anotação e acessibilidade
+    Cursor {
+        width: u32,
+        height: u32,
+    },
+}
+
+/// Posição em um eixo: junto à borda inicial, centralizado ou junto à borda final
+#[derive(Clone, Copy)]
+enum Align {
+    Start,
+    Center,
+    End,
+}
+
+impl Anchor {
+    /// Retângulo da região em um monitor de tamanho `output`, recortado às bordas.
+    ///
+    /// `Anchor::Cursor` fica no centro do monitor; use `resolve_at` com a posição do cursor.
+    pub fn resolve(&self, output: Size) -> Result<Rect, RectError> {
+        self.resolve_at(output, None)
+    }
+
+    /// Como `resolve`, com a posição do cursor relativa ao monitor (pode estar fora dele)
+    pub fn resolve_at(&self, output: Size, cursor: Option<Point>) -> Result<Rect, RectError> {
+        use Align::*;
+        let (horizontal, vertical, width, height, margin) = match *self {
+            Anchor::TopLeft { width, height, margin } => (Start, Start, width, height, margin),
+            Anchor::Top { width, height, margin } => (Center, Start, width, height, margin),
+            Anchor::TopRight { width, height, margin } => (End, Start, width, height, margin),
+            Anchor::Left { width, height, margin } => (Start, Center, width, height, margin),
+            Anchor::Center { width, height } => (Center, Center, width, height, 0),
+            Anchor::Right { width, height, margin } => (End, Center, width, height, margin),
+            Anchor::BottomLeft { width, height, margin } => (Start, End, width, height, margin),
+            Anchor::Bottom { width, height, margin } => (Center, End, width, height, margin),
+            Anchor::BottomRight { width, height, margin } => (End, End, width, height, margin),
+            Anchor::Cursor { width, height } => {
+                let center = cursor.unwrap_or(Point::new(output.width as i32 / 2, output.height as i32 / 2));
+                return Rect::follow(center, Size::new(width, height), output);
+            }
+        };
+
+        let place = |align: Align, length: u32, total: u32| -> i64 {
+            match align {
+                Start => margin as i64,
+                Center => (total as i64 - length as i64) / 2,
+                End => total as i64 - length as i64 - margin as i64,
+            }
+        };
+        let left = place(horizontal, width, output.width);
+        let top = place(vertical, height, output.height);
+        let left = i32::try_from(left).map_err(|_| RectError::Overflow)?;
+        let top = i32::try_from(top).map_err(|_| RectError::Overflow)?;
+
+        let region = Rect::new(left, top, width, height);
+        if region.is_empty() {
+            return Err(RectError::Empty);
+        }
+        region.clamp_to(output).ok_or(RectError::OutOfBounds { region, bounds: output })
+    }
+}
+
+/// Região declarativa, resolvida contra o tamanho atual do monitor a cada captura, para
+/// continuar apontando para a mesma área da interface depois de mudanças de resolução
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(untagged))]
+pub enum RegionSpec {
+    /// Retângulo fixo em pixels; precisa caber inteiro no monitor
+    Fixed(Rect),
+    /// Tamanho fixo preso a uma borda, canto ou ao centro
+    Anchored(Anchor),
+    /// Posição e tamanho em porcentagem (0 a 100) das dimensões do monitor
+    Percent { x: f64, y: f64, width: f64, height: f64 },
+}
+
+impl RegionSpec {
+    /// Região de `width`x`height` que acompanha o cursor
+    pub const fn follow_cursor(width: u32, height: u32) -> Self {
+        RegionSpec::Anchored(Anchor::Cursor { width, height })
+    }
+
+    /// A região depende da posição do cursor e precisa ser resolvida a cada quadro
+    pub fn follows_cursor(&self) -> bool {
+        matches!(self, RegionSpec::Anchored(Anchor::Cursor { .. }))
+    }
+
+    /// Retângulo da região em um monitor de tamanho `output`
+    pub fn resolve(&self, output: Size) -> Result<Rect, RectError> {
+        self.resolve_at(output, None)
+    }
+
+    /// Como `resolve`, com a posição do cursor relativa ao monitor para `Anchor::Cursor`
+    pub fn resolve_at(&self, output: Size, cursor: Option<Point>) -> Result<Rect, RectError> {
+        match *self {
+            RegionSpec::Fixed(rect) => {
+                rect.validate_within(output)?;
+                Ok(rect)
+            }
+            RegionSpec::Anchored(anchor) => anchor.resolve_at(output, cursor),
+            RegionSpec::Percent { x, y, width, height } => {
+                // Arredondar as bordas (e não o tamanho) mantém regiões vizinhas sem vãos
+                let edge = |percent: f64, total: u32| (percent.clamp(0.0, 100.0) / 100.0 * total as f64).round() as i32;
+                let region = Rect::from_edges(
+                    edge(x, output.width),
+                    edge(y, output.height),
+                    edge(x + width, output.width),
+                    edge(y + height, output.height),
+                )?;
+                region.validate_within(output)?;
+                Ok(region)
+            }
+        }
+    }
+}
+
+impl From<Rect> for RegionSpec {
+    fn from(rect: Rect) -> Self {
+        RegionSpec::Fixed(rect)
+    }
+}
+
+impl From<Anchor> for RegionSpec {
+    fn from(anchor: Anchor) -> Self {
+        RegionSpec::Anchored(anchor)
+    }
+}
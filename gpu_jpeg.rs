@@ -0,0 +1,413 @@
+//! Encoder JPEG com a transformada na GPU (feature `gpu-jpeg`)
+//!
+//! Em MJPEG a 4K a conversão de cor, a DCT e a quantização dominam o custo do encoder em
+//! CPU. Aqui um compute shader faz essas etapas direto na textura capturada (YCbCr BT.601
+//! em faixa completa, croma 4:2:0, um grupo de 16x16 threads por MCU) e só os coeficientes
+//! quantizados, já em zigue-zague, são lidos para a CPU, que faz a codificação de Huffman
+//! com as tabelas padrão (Anexo K da ITU-T T.81) e monta o arquivo JFIF baseline.
+//!
+//! A entrada deve ser B8G8R8A8 ou R8G8B8A8; quadros HDR devem ser convertidos antes.
+
+use windows::core::*;
+use windows::Win32::Foundation::E_FAIL;
+use windows::Win32::Graphics::Direct3D11::*;
+use windows::Win32::Graphics::Dxgi::Common::{
+    DXGI_FORMAT_B8G8R8A8_UNORM, DXGI_FORMAT_B8G8R8A8_UNORM_SRGB, DXGI_FORMAT_R8G8B8A8_UNORM,
+    DXGI_FORMAT_R8G8B8A8_UNORM_SRGB,
+};
+
+use crate::error::CaptureError;
+use crate::geometry::Rect;
+use crate::gpu_stats::{compile_compute_shader, dispatch_over_texture, ReadbackBuffer};
+use crate::{DxgiCapture, Result};
+
+/// Qualidade padrão, a mesma da libjpeg
+pub const DEFAULT_QUALITY: u8 = 75;
+
+/// Coeficientes por MCU: quatro blocos de luminância, Cb e Cr, 64 cada
+const COEFFICIENTS_PER_MCU: usize = 6 * 64;
+
+/// Posição natural (linha * 8 + coluna) de cada índice do zigue-zague
+const ZIGZAG: [usize; 64] = [
+    0, 1, 8, 16, 9, 2, 3, 10, 17, 24, 32, 25, 18, 11, 4, 5, 12, 19, 26, 33, 40, 48, 41, 34, 27, 20, 13, 6, 7, 14, 21,
+    28, 35, 42, 49, 56, 57, 50, 43, 36, 29, 22, 15, 23, 30, 37, 44, 51, 58, 59, 52, 45, 38, 31, 39, 46, 53, 60, 61,
+    54, 47, 55, 62, 63,
+];
+
+/// Tabelas de quantização de referência (Anexo K.1), em ordem natural
+const LUMA_QUANT: [u16; 64] = [
+    16, 11, 10, 16, 24, 40, 51, 61, 12, 12, 14, 19, 26, 58, 60, 55, 14, 13, 16, 24, 40, 57, 69, 56, 14, 17, 22, 29, 51,
+    87, 80, 62, 18, 22, 37, 56, 68, 109, 103, 77, 24, 35, 55, 64, 81, 104, 113, 92, 49, 64, 78, 87, 103, 121, 120, 101,
+    72, 92, 95, 98, 112, 100, 103, 99,
+];
+const CHROMA_QUANT: [u16; 64] = [
+    17, 18, 24, 47, 99, 99, 99, 99, 18, 21, 26, 66, 99, 99, 99, 99, 24, 26, 56, 99, 99, 99, 99, 99, 47, 66, 99, 99, 99,
+    99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99,
+    99, 99, 99, 99, 99, 99,
+];
+
+/// Tabelas de Huffman padrão (Anexo K.3): códigos por comprimento (1 a 16) e símbolos
+const DC_LUMA_BITS: [u8; 16] = [0, 1, 5, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0];
+const DC_CHROMA_BITS: [u8; 16] = [0, 3, 1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0];
+const DC_VALUES: [u8; 12] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11];
+const AC_LUMA_BITS: [u8; 16] = [0, 2, 1, 3, 3, 2, 4, 3, 5, 5, 4, 4, 0, 0, 1, 0x7d];
+const AC_LUMA_VALUES: [u8; 162] = [
+    0x01, 0x02, 0x03, 0x00, 0x04, 0x11, 0x05, 0x12, 0x21, 0x31, 0x41, 0x06, 0x13, 0x51, 0x61, 0x07, 0x22, 0x71, 0x14,
+    0x32, 0x81, 0x91, 0xa1, 0x08, 0x23, 0x42, 0xb1, 0xc1, 0x15, 0x52, 0xd1, 0xf0, 0x24, 0x33, 0x62, 0x72, 0x82, 0x09,
+    0x0a, 0x16, 0x17, 0x18, 0x19, 0x1a, 0x25, 0x26, 0x27, 0x28, 0x29, 0x2a, 0x34, 0x35, 0x36, 0x37, 0x38, 0x39, 0x3a,
+    0x43, 0x44, 0x45, 0x46, 0x47, 0x48, 0x49, 0x4a, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5a, 0x63, 0x64, 0x65,
+    0x66, 0x67, 0x68, 0x69, 0x6a, 0x73, 0x74, 0x75, 0x76, 0x77, 0x78, 0x79, 0x7a, 0x83, 0x84, 0x85, 0x86, 0x87, 0x88,
+    0x89, 0x8a, 0x92, 0x93, 0x94, 0x95, 0x96, 0x97, 0x98, 0x99, 0x9a, 0xa2, 0xa3, 0xa4, 0xa5, 0xa6, 0xa7, 0xa8, 0xa9,
+    0xaa, 0xb2, 0xb3, 0xb4, 0xb5, 0xb6, 0xb7, 0xb8, 0xb9, 0xba, 0xc2, 0xc3, 0xc4, 0xc5, 0xc6, 0xc7, 0xc8, 0xc9, 0xca,
+    0xd2, 0xd3, 0xd4, 0xd5, 0xd6, 0xd7, 0xd8, 0xd9, 0xda, 0xe1, 0xe2, 0xe3, 0xe4, 0xe5, 0xe6, 0xe7, 0xe8, 0xe9, 0xea,
+    0xf1, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8, 0xf9, 0xfa,
+];
+const AC_CHROMA_BITS: [u8; 16] = [0, 2, 1, 2, 4, 4, 3, 4, 7, 5, 4, 4, 0, 1, 2, 0x77];
+const AC_CHROMA_VALUES: [u8; 162] = [
+    0x00, 0x01, 0x02, 0x03, 0x11, 0x04, 0x05, 0x21, 0x31, 0x06, 0x12, 0x41, 0x51, 0x07, 0x61, 0x71, 0x13, 0x22, 0x32,
+    0x81, 0x08, 0x14, 0x42, 0x91, 0xa1, 0xb1, 0xc1, 0x09, 0x23, 0x33, 0x52, 0xf0, 0x15, 0x62, 0x72, 0xd1, 0x0a, 0x16,
+    0x24, 0x34, 0xe1, 0x25, 0xf1, 0x17, 0x18, 0x19, 0x1a, 0x26, 0x27, 0x28, 0x29, 0x2a, 0x35, 0x36, 0x37, 0x38, 0x39,
+    0x3a, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48, 0x49, 0x4a, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5a, 0x63, 0x64,
+    0x65, 0x66, 0x67, 0x68, 0x69, 0x6a, 0x73, 0x74, 0x75, 0x76, 0x77, 0x78, 0x79, 0x7a, 0x82, 0x83, 0x84, 0x85, 0x86,
+    0x87, 0x88, 0x89, 0x8a, 0x92, 0x93, 0x94, 0x95, 0x96, 0x97, 0x98, 0x99, 0x9a, 0xa2, 0xa3, 0xa4, 0xa5, 0xa6, 0xa7,
+    0xa8, 0xa9, 0xaa, 0xb2, 0xb3, 0xb4, 0xb5, 0xb6, 0xb7, 0xb8, 0xb9, 0xba, 0xc2, 0xc3, 0xc4, 0xc5, 0xc6, 0xc7, 0xc8,
+    0xc9, 0xca, 0xd2, 0xd3, 0xd4, 0xd5, 0xd6, 0xd7, 0xd8, 0xd9, 0xda, 0xe2, 0xe3, 0xe4, 0xe5, 0xe6, 0xe7, 0xe8, 0xe9,
+    0xea, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8, 0xf9, 0xfa,
+];
+
+/// Um grupo de 16x16 threads por MCU. Cada thread converte um pixel (repetindo a borda fora
+/// da imagem); as médias 2x2 dão o croma; depois as 192 primeiras threads calculam dois
+/// coeficientes consecutivos do zigue-zague cada e gravam o par quantizado em um `uint`
+/// (primeiro coeficiente nos 16 bits baixos). `QUANT` (luma e croma, ordem natural) e
+/// `ZIGZAG` são prefixados ao código.
+const JPEG_SHADER: &str = r#"
+Texture2D<float4> Input : register(t0);
+RWStructuredBuffer<uint> Output : register(u0);
+
+groupshared float Samples[384];
+groupshared float Cb[256];
+groupshared float Cr[256];
+groupshared float Basis[64];
+
+[numthreads(16, 16, 1)]
+void main(uint3 id : SV_DispatchThreadID, uint3 local : SV_GroupThreadID, uint3 group : SV_GroupID, uint index : SV_GroupIndex)
+{
+    uint width, height;
+    Input.GetDimensions(width, height);
+
+    float3 rgb = saturate(Input[min(id.xy, uint2(width - 1, height - 1))].rgb) * 255.0;
+    uint block = (local.y / 8) * 2 + local.x / 8;
+    Samples[block * 64 + (local.y % 8) * 8 + local.x % 8] = dot(rgb, float3(0.299, 0.587, 0.114)) - 128.0;
+    Cb[index] = dot(rgb, float3(-0.168736, -0.331264, 0.5));
+    Cr[index] = dot(rgb, float3(0.5, -0.418688, -0.081312));
+    if (index < 64) {
+        // Basis[u * 8 + x] = C(u) / 2 * cos((2x + 1) u pi / 16)
+        uint u = index / 8;
+        uint x = index % 8;
+        Basis[index] = (u == 0 ? 0.353553391 : 0.5) * cos((2.0 * x + 1.0) * u * 0.196349541);
+    }
+    GroupMemoryBarrierWithGroupSync();
+
+    if (local.x % 2 == 0 && local.y % 2 == 0) {
+        uint chroma = (local.y / 2) * 8 + local.x / 2;
+        Samples[256 + chroma] = (Cb[index] + Cb[index + 1] + Cb[index + 16] + Cb[index + 17]) * 0.25;
+        Samples[320 + chroma] = (Cr[index] + Cr[index + 1] + Cr[index + 16] + Cr[index + 17]) * 0.25;
+    }
+    GroupMemoryBarrierWithGroupSync();
+
+    if (index >= 192) {
+        return;
+    }
+    uint target = index / 32;
+    uint quant = target < 4 ? 0 : 64;
+    int pair[2];
+    [unroll] for (uint k = 0; k < 2; k++) {
+        uint natural = ZIGZAG[(index % 32) * 2 + k];
+        uint u = natural % 8;
+        uint v = natural / 8;
+        float sum = 0.0;
+        for (uint y = 0; y < 8; y++) {
+            float row = 0.0;
+            for (uint x = 0; x < 8; x++) {
+                row += Samples[target * 64 + y * 8 + x] * Basis[u * 8 + x];
+            }
+            sum += row * Basis[v * 8 + y];
+        }
+        pair[k] = (int)round(sum / QUANT[quant + natural]);
+    }
+
+    uint mcu = group.y * ((width + 15) / 16) + group.x;
+    Output[mcu * 192 + index] = ((uint)pair[0] & 0xFFFF) | ((uint)pair[1] << 16);
+}
+"#;
+
+/// Encoder JPEG com shader e buffer de coeficientes reutilizados entre quadros.
+///
+/// Como o `HwEncoder`, pertence ao dispositivo da captura e deve ser recriado após uma
+/// reinicialização que troque o dispositivo.
+pub struct GpuJpegEncoder {
+    device: ID3D11Device,
+    context: ID3D11DeviceContext,
+    quality: u8,
+    shader: ID3D11ComputeShader,
+    coefficients: Option<ReadbackBuffer>,
+    packed: Vec<u32>,
+}
+
+impl GpuJpegEncoder {
+    /// Cria o encoder com `quality` de 1 a 100 (escala da libjpeg)
+    pub fn new(device: &ID3D11Device, context: &ID3D11DeviceContext, quality: u8) -> Result<Self> {
+        let quality = quality.clamp(1, 100);
+        Ok(Self {
+            device: device.clone(),
+            context: context.clone(),
+            quality,
+            shader: compile_compute_shader(device, &shader_source(quality))?,
+            coefficients: None,
+            packed: Vec::new(),
+        })
+    }
+
+    pub fn quality(&self) -> u8 {
+        self.quality
+    }
+
+    /// Troca a qualidade; as tabelas fazem parte do shader, que é recompilado
+    pub fn set_quality(&mut self, quality: u8) -> Result<()> {
+        let quality = quality.clamp(1, 100);
+        if quality != self.quality {
+            self.shader = compile_compute_shader(&self.device, &shader_source(quality))?;
+            self.quality = quality;
+        }
+        Ok(())
+    }
+
+    /// Codifica a textura inteira (ex.: de `DxgiCapture::capture_texture`) em um JPEG
+    pub fn encode(&mut self, texture: &ID3D11Texture2D) -> Result<Vec<u8>> {
+        let mut desc = D3D11_TEXTURE2D_DESC::default();
+        unsafe { texture.GetDesc(&mut desc) };
+        if !matches!(
+            desc.Format,
+            DXGI_FORMAT_B8G8R8A8_UNORM
+                | DXGI_FORMAT_B8G8R8A8_UNORM_SRGB
+                | DXGI_FORMAT_R8G8B8A8_UNORM
+                | DXGI_FORMAT_R8G8B8A8_UNORM_SRGB
+        ) {
+            let message = format!("GPU JPEG requires an 8-bit texture, got {:?}", desc.Format);
+            return Err(CaptureError::Conversion(message).into());
+        }
+        if desc.Width > u16::MAX as u32 || desc.Height > u16::MAX as u32 {
+            let message = format!("JPEG is limited to 65535x65535, got {}x{}", desc.Width, desc.Height);
+            return Err(CaptureError::Conversion(message).into());
+        }
+
+        let len = mcu_count(desc.Width, desc.Height) * COEFFICIENTS_PER_MCU / 2;
+        if self.packed.len() != len {
+            self.coefficients = Some(ReadbackBuffer::new(&self.device, len)?);
+            self.packed.resize(len, 0);
+        }
+        let coefficients = self.coefficients.as_ref().ok_or(CaptureError::Windows(Error::from(E_FAIL)))?;
+
+        dispatch_over_texture(&self.device, &self.context, &self.shader, texture, coefficients)?;
+        coefficients.read(&self.context, &mut self.packed)?;
+
+        let mut blocks = Vec::with_capacity(self.packed.len() * 2);
+        for pair in &self.packed {
+            blocks.push(*pair as u16 as i16);
+            blocks.push((*pair >> 16) as u16 as i16);
+        }
+        Ok(write_jpeg(desc.Width as u16, desc.Height as u16, self.quality, &blocks))
+    }
+
+    /// Captura `region` e a codifica sem leitura da imagem para a CPU
+    pub fn capture(&mut self, capture: &mut DxgiCapture, region: Rect) -> Result<Vec<u8>> {
+        let texture = capture.capture_texture(region)?;
+        self.encode(&texture)
+    }
+}
+
+fn mcu_count(width: u32, height: u32) -> usize {
+    width.div_ceil(16) as usize * height.div_ceil(16) as usize
+}
+
+/// Tabela de referência escalada pela qualidade, como na libjpeg
+fn scaled_quant(base: &[u16; 64], quality: u8) -> [u16; 64] {
+    let quality = quality.clamp(1, 100) as u32;
+    let scale = if quality < 50 { 5000 / quality } else { 200 - quality * 2 };
+    base.map(|value| ((value as u32 * scale + 50) / 100).clamp(1, 255) as u16)
+}
+
+fn shader_source(quality: u8) -> String {
+    let quant: Vec<String> = scaled_quant(&LUMA_QUANT, quality)
+        .iter()
+        .chain(scaled_quant(&CHROMA_QUANT, quality).iter())
+        .map(|value| format!("{}.0", value))
+        .collect();
+    let zigzag: Vec<String> = ZIGZAG.iter().map(|index| index.to_string()).collect();
+    format!(
+        "static const float QUANT[128] = {{ {} }};\nstatic const uint ZIGZAG[64] = {{ {} }};\n{}",
+        quant.join(", "),
+        zigzag.join(", "),
+        JPEG_SHADER
+    )
+}
+
+/// Código de Huffman (bits, comprimento) de cada símbolo
+struct HuffmanTable {
+    codes: [(u16, u8); 256],
+}
+
+impl HuffmanTable {
+    /// Códigos canônicos a partir da contagem por comprimento (Anexo C)
+    fn new(bits: &[u8; 16], values: &[u8]) -> Self {
+        let mut codes = [(0u16, 0u8); 256];
+        let mut code = 0u16;
+        let mut next = 0;
+        for (length, &count) in bits.iter().enumerate() {
+            for _ in 0..count {
+                codes[values[next] as usize] = (code, length as u8 + 1);
+                code += 1;
+                next += 1;
+            }
+            code <<= 1;
+        }
+        Self { codes }
+    }
+}
+
+/// Bits do fluxo entrópico, com o byte 0x00 inserido após cada 0xFF
+struct BitWriter {
+    out: Vec<u8>,
+    buffer: u32,
+    count: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, bits: u16, length: u8) {
+        self.buffer = (self.buffer << length) | (bits as u32 & ((1 << length) - 1));
+        self.count += length as u32;
+        while self.count >= 8 {
+            self.count -= 8;
+            let byte = (self.buffer >> self.count) as u8;
+            self.out.push(byte);
+            if byte == 0xFF {
+                self.out.push(0x00);
+            }
+        }
+        self.buffer &= (1 << self.count) - 1;
+    }
+
+    fn huffman(&mut self, table: &HuffmanTable, symbol: u8) {
+        let (code, length) = table.codes[symbol as usize];
+        self.write(code, length);
+    }
+
+    /// Completa o último byte com uns
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            let padding = 8 - self.count as u8;
+            self.write(0xFF, padding);
+        }
+        self.out
+    }
+}
+
+/// Categoria (número de bits) e bits adicionais de um coeficiente (F.1.2.1)
+fn magnitude(value: i16) -> (u8, u16) {
+    let category = (16 - value.unsigned_abs().leading_zeros()) as u8;
+    let bits = if value < 0 { (value as i32 - 1) as u16 } else { value as u16 };
+    (category, bits)
+}
+
+fn encode_block(writer: &mut BitWriter, block: &[i16], previous_dc: &mut i16, dc: &HuffmanTable, ac: &HuffmanTable) {
+    let (category, bits) = magnitude(block[0].wrapping_sub(*previous_dc));
+    *previous_dc = block[0];
+    writer.huffman(dc, category);
+    writer.write(bits, category);
+
+    let mut zeros = 0u8;
+    for &coefficient in &block[1..] {
+        if coefficient == 0 {
+            zeros += 1;
+            continue;
+        }
+        while zeros >= 16 {
+            writer.huffman(ac, 0xF0);
+            zeros -= 16;
+        }
+        let (category, bits) = magnitude(coefficient);
+        writer.huffman(ac, (zeros << 4) | category);
+        writer.write(bits, category);
+        zeros = 0;
+    }
+    if zeros > 0 {
+        writer.huffman(ac, 0x00);
+    }
+}
+
+fn write_segment(out: &mut Vec<u8>, marker: u8, payload: &[u8]) {
+    out.extend_from_slice(&[0xFF, marker]);
+    out.extend_from_slice(&(payload.len() as u16 + 2).to_be_bytes());
+    out.extend_from_slice(payload);
+}
+
+/// Monta o JFIF baseline a partir dos coeficientes quantizados de cada MCU (Y0 Y1 Y2 Y3 Cb Cr,
+/// em zigue-zague)
+fn write_jpeg(width: u16, height: u16, quality: u8, blocks: &[i16]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(blocks.len() / 2 + 1024);
+    out.extend_from_slice(&[0xFF, 0xD8]);
+    write_segment(&mut out, 0xE0, b"JFIF\0\x01\x01\x00\x00\x01\x00\x01\x00\x00");
+
+    for (id, base) in [LUMA_QUANT, CHROMA_QUANT].iter().enumerate() {
+        let table = scaled_quant(base, quality);
+        let mut payload = vec![id as u8];
+        payload.extend(ZIGZAG.iter().map(|&natural| table[natural] as u8));
+        write_segment(&mut out, 0xDB, &payload);
+    }
+
+    let [width_hi, width_lo] = width.to_be_bytes();
+    let [height_hi, height_lo] = height.to_be_bytes();
+    write_segment(
+        &mut out,
+        0xC0,
+        &[8, height_hi, height_lo, width_hi, width_lo, 3, 1, 0x22, 0, 2, 0x11, 1, 3, 0x11, 1],
+    );
+
+    for (class_id, bits, values) in [
+        (0x00, &DC_LUMA_BITS, &DC_VALUES[..]),
+        (0x10, &AC_LUMA_BITS, &AC_LUMA_VALUES[..]),
+        (0x01, &DC_CHROMA_BITS, &DC_VALUES[..]),
+        (0x11, &AC_CHROMA_BITS, &AC_CHROMA_VALUES[..]),
+    ] {
+        let mut payload = vec![class_id];
+        payload.extend_from_slice(bits);
+        payload.extend_from_slice(values);
+        write_segment(&mut out, 0xC4, &payload);
+    }
+
+    write_segment(&mut out, 0xDA, &[3, 1, 0x00, 2, 0x11, 3, 0x11, 0, 63, 0]);
+
+    let dc_luma = HuffmanTable::new(&DC_LUMA_BITS, &DC_VALUES);
+    let ac_luma = HuffmanTable::new(&AC_LUMA_BITS, &AC_LUMA_VALUES);
+    let dc_chroma = HuffmanTable::new(&DC_CHROMA_BITS, &DC_VALUES);
+    let ac_chroma = HuffmanTable::new(&AC_CHROMA_BITS, &AC_CHROMA_VALUES);
+    let mut writer = BitWriter {
+        out,
+        buffer: 0,
+        count: 0,
+    };
+    let mut previous_dc = [0i16; 3];
+    for mcu in blocks.chunks_exact(COEFFICIENTS_PER_MCU) {
+        for (index, block) in mcu.chunks_exact(64).enumerate() {
+            match index {
+                0..=3 => encode_block(&mut writer, block, &mut previous_dc[0], &dc_luma, &ac_luma),
+                4 => encode_block(&mut writer, block, &mut previous_dc[1], &dc_chroma, &ac_chroma),
+                _ => encode_block(&mut writer, block, &mut previous_dc[2], &dc_chroma, &ac_chroma),
+            }
+        }
+    }
+
+    let mut out = writer.finish();
+    out.extend_from_slice(&[0xFF, 0xD9]);
+    out
+}
@@ -0,0 +1,365 @@
+//! Estatísticas de quadro calculadas na GPU por compute shaders
+//!
+//! Só o resultado (alguns bytes) é lido para a CPU, então a análise pode rodar a cada quadro
+//! sem baixar a imagem. Os shaders são compilados em tempo de execução com `D3DCompile`
+//! (`d3dcompiler_47.dll`, presente desde o Windows 8.1).
+
+use std::ffi::c_void;
+
+use windows::core::*;
+use windows::Win32::Foundation::E_FAIL;
+use windows::Win32::Graphics::Direct3D::Fxc::{D3DCompile, D3DCOMPILE_OPTIMIZATION_LEVEL3};
+use windows::Win32::Graphics::Direct3D::ID3DBlob;
+use windows::Win32::Graphics::Direct3D11::*;
+
+use crate::error::{CaptureError, CaptureResult};
+
+/// Número de faixas do histograma de luminância
+pub const HISTOGRAM_BINS: usize = 256;
+
+/// Um grupo de 16x16 threads, uma por faixa, acumula em memória compartilhada e soma no buffer
+const HISTOGRAM_SHADER: &str = r#"
+Texture2D<float4> Input : register(t0);
+RWStructuredBuffer<uint> Bins : register(u0);
+groupshared uint LocalBins[256];
+
+[numthreads(16, 16, 1)]
+void main(uint3 id : SV_DispatchThreadID, uint index : SV_GroupIndex)
+{
+    LocalBins[index] = 0;
+    GroupMemoryBarrierWithGroupSync();
+
+    uint width, height;
+    Input.GetDimensions(width, height);
+    if (id.x < width && id.y < height) {
+        float3 rgb = saturate(Input[id.xy].rgb);
+        float luminance = dot(rgb, float3(0.2126, 0.7152, 0.0722));
+        InterlockedAdd(LocalBins[(uint)(luminance * 255.0 + 0.5)], 1);
+    }
+    GroupMemoryBarrierWithGroupSync();
+
+    if (LocalBins[index] != 0) {
+        InterlockedAdd(Bins[index], LocalBins[index]);
+    }
+}
+"#;
+
+/// Mínimo e máximo de cada canal; os mínimos são guardados invertidos (255 - v) para que
+/// tudo seja acumulado com `InterlockedMax` a partir de zero
+const COLOR_RANGE_SHADER: &str = r#"
+Texture2D<float4> Input : register(t0);
+RWStructuredBuffer<uint> Range : register(u0);
+groupshared uint LocalRange[6];
+
+[numthreads(16, 16, 1)]
+void main(uint3 id : SV_DispatchThreadID, uint index : SV_GroupIndex)
+{
+    if (index < 6) {
+        LocalRange[index] = 0;
+    }
+    GroupMemoryBarrierWithGroupSync();
+
+    uint width, height;
+    Input.GetDimensions(width, height);
+    if (id.x < width && id.y < height) {
+        uint3 color = (uint3)(saturate(Input[id.xy].rgb) * 255.0 + 0.5);
+        InterlockedMax(LocalRange[0], 255 - color.r);
+        InterlockedMax(LocalRange[1], 255 - color.g);
+        InterlockedMax(LocalRange[2], 255 - color.b);
+        InterlockedMax(LocalRange[3], color.r);
+        InterlockedMax(LocalRange[4], color.g);
+        InterlockedMax(LocalRange[5], color.b);
+    }
+    GroupMemoryBarrierWithGroupSync();
+
+    if (index < 6) {
+        InterlockedMax(Range[index], LocalRange[index]);
+    }
+}
+"#;
+
+/// Diferença máxima entre canais com que `is_blank` ainda considera a região de uma cor só
+pub const BLANK_TOLERANCE: u8 = 2;
+
+/// Distribuição da luminância (BT.709) de uma região, em 256 faixas de 0.0 a 1.0.
+///
+/// Em capturas 8-bit a luminância é calculada sobre os valores com gama, como em um
+/// histograma de editor de imagem; em FP16 (scRGB linear) valores acima do branco SDR
+/// caem na última faixa.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Histogram {
+    pub bins: [u32; HISTOGRAM_BINS],
+}
+
+impl Histogram {
+    /// Pixels contados
+    pub fn total(&self) -> u64 {
+        self.bins.iter().map(|&count| count as u64).sum()
+    }
+
+    /// Luminância média, de 0.0 a 1.0
+    pub fn mean(&self) -> f32 {
+        let total = self.total();
+        if total == 0 {
+            return 0.0;
+        }
+        let weighted: u64 = self.bins.iter().enumerate().map(|(bin, &count)| bin as u64 * count as u64).sum();
+        weighted as f32 / total as f32 / (HISTOGRAM_BINS - 1) as f32
+    }
+
+    /// Faixa abaixo da qual estão `fraction` (0.0 a 1.0) dos pixels
+    pub fn percentile(&self, fraction: f32) -> u8 {
+        let target = (self.total() as f64 * fraction.clamp(0.0, 1.0) as f64).ceil() as u64;
+        let mut accumulated = 0u64;
+        for (bin, &count) in self.bins.iter().enumerate() {
+            accumulated += count as u64;
+            if accumulated >= target.max(1) {
+                return bin as u8;
+            }
+        }
+        u8::MAX
+    }
+
+    /// Fração dos pixels nas faixas `bin` e acima (ex.: 255 mede o quanto está estourado)
+    pub fn fraction_at_or_above(&self, bin: u8) -> f32 {
+        let total = self.total();
+        if total == 0 {
+            return 0.0;
+        }
+        let above: u64 = self.bins[bin as usize..].iter().map(|&count| count as u64).sum();
+        above as f32 / total as f32
+    }
+}
+
+/// Menor e maior valor (0 a 255) de cada canal RGB de uma região
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ColorRange {
+    pub min: [u8; 3],
+    pub max: [u8; 3],
+}
+
+impl ColorRange {
+    /// Maior diferença entre máximo e mínimo entre os canais
+    pub fn spread(&self) -> u8 {
+        (0..3).map(|channel| self.max[channel] - self.min[channel]).max().unwrap_or(0)
+    }
+
+    /// Região inteira da mesma cor, a menos de `tolerance`
+    pub fn is_uniform(&self, tolerance: u8) -> bool {
+        self.spread() <= tolerance
+    }
+
+    /// Região inteira preta, a menos de `tolerance`
+    pub fn is_black(&self, tolerance: u8) -> bool {
+        self.max.iter().all(|&value| value <= tolerance)
+    }
+}
+
+/// Compila um compute shader HLSL (`cs_5_0`, ponto de entrada `main`)
+pub(crate) fn compile_compute_shader(device: &ID3D11Device, source: &str) -> CaptureResult<ID3D11ComputeShader> {
+    let mut code: Option<ID3DBlob> = None;
+    let mut errors: Option<ID3DBlob> = None;
+
+    let compiled = unsafe {
+        D3DCompile(
+            source.as_ptr() as *const c_void,
+            source.len(),
+            PCSTR::null(),
+            None,
+            None,
+            s!("main"),
+            s!("cs_5_0"),
+            D3DCOMPILE_OPTIMIZATION_LEVEL3,
+            0,
+            &mut code,
+            Some(&mut errors),
+        )
+    };
+
+    if let Err(e) = compiled {
+        let message = errors
+            .map(|blob| unsafe {
+                let bytes = std::slice::from_raw_parts(blob.GetBufferPointer() as *const u8, blob.GetBufferSize());
+                String::from_utf8_lossy(bytes).trim_end_matches('\0').to_owned()
+            })
+            .unwrap_or_else(|| e.message().to_string());
+        return Err(CaptureError::Shader(message));
+    }
+
+    let code = code.ok_or(CaptureError::Shader("D3DCompile returned no bytecode".to_owned()))?;
+    let mut shader: Option<ID3D11ComputeShader> = None;
+    unsafe {
+        let bytecode = std::slice::from_raw_parts(code.GetBufferPointer() as *const u8, code.GetBufferSize());
+        device.CreateComputeShader(bytecode, None, Some(&mut shader))?;
+    }
+
+    shader.ok_or(CaptureError::Windows(Error::from(E_FAIL)))
+}
+
+/// Buffer estruturado de `u32` gravável pelo shader e a staging para lê-lo
+pub(crate) struct ReadbackBuffer {
+    buffer: ID3D11Buffer,
+    view: ID3D11UnorderedAccessView,
+    staging: ID3D11Buffer,
+    len: usize,
+}
+
+impl ReadbackBuffer {
+    pub(crate) fn new(device: &ID3D11Device, len: usize) -> CaptureResult<Self> {
+        let mut desc = D3D11_BUFFER_DESC {
+            ByteWidth: (len * 4) as u32,
+            Usage: D3D11_USAGE_DEFAULT,
+            BindFlags: D3D11_BIND_UNORDERED_ACCESS.0 as u32,
+            CPUAccessFlags: 0,
+            MiscFlags: D3D11_RESOURCE_MISC_BUFFER_STRUCTURED.0 as u32,
+            StructureByteStride: 4,
+        };
+
+        let mut buffer: Option<ID3D11Buffer> = None;
+        let mut view: Option<ID3D11UnorderedAccessView> = None;
+        let mut staging: Option<ID3D11Buffer> = None;
+        unsafe {
+            device.CreateBuffer(&desc, None, Some(&mut buffer))?;
+            let created = buffer.as_ref().ok_or(CaptureError::Windows(Error::from(E_FAIL)))?;
+            device.CreateUnorderedAccessView(created, None, Some(&mut view))?;
+
+            desc.Usage = D3D11_USAGE_STAGING;
+            desc.BindFlags = 0;
+            desc.CPUAccessFlags = D3D11_CPU_ACCESS_READ.0 as u32;
+            device.CreateBuffer(&desc, None, Some(&mut staging))?;
+        }
+
+        match (buffer, view, staging) {
+            (Some(buffer), Some(view), Some(staging)) => Ok(Self {
+                buffer,
+                view,
+                staging,
+                len,
+            }),
+            _ => Err(CaptureError::Windows(Error::from(E_FAIL))),
+        }
+    }
+
+    pub(crate) fn view(&self) -> &ID3D11UnorderedAccessView {
+        &self.view
+    }
+
+    /// Preenche todos os elementos com `value`
+    pub(crate) fn fill(&self, context: &ID3D11DeviceContext, value: u32) {
+        unsafe {
+            context.ClearUnorderedAccessViewUint(&self.view, &[value; 4]);
+        }
+    }
+
+    /// Copia o buffer para a CPU, aguardando o shader terminar
+    pub(crate) fn read(&self, context: &ID3D11DeviceContext, out: &mut [u32]) -> CaptureResult<()> {
+        let mut mapped = D3D11_MAPPED_SUBRESOURCE::default();
+        unsafe {
+            context.CopyResource(&self.staging, &self.buffer);
+            context.Map(&self.staging, 0, D3D11_MAP_READ, 0, Some(&mut mapped))?;
+            if !mapped.pData.is_null() {
+                let values = std::slice::from_raw_parts(mapped.pData as *const u32, self.len);
+                let count = out.len().min(self.len);
+                out[..count].copy_from_slice(&values[..count]);
+            }
+            context.Unmap(&self.staging, 0);
+        }
+        Ok(())
+    }
+}
+
+/// Executa `shader` sobre `input` em grupos de 16x16, com `output` no slot u0
+pub(crate) fn dispatch_over_texture(
+    device: &ID3D11Device,
+    context: &ID3D11DeviceContext,
+    shader: &ID3D11ComputeShader,
+    input: &ID3D11Texture2D,
+    output: &ReadbackBuffer,
+) -> CaptureResult<()> {
+    let mut desc = D3D11_TEXTURE2D_DESC::default();
+    let mut view: Option<ID3D11ShaderResourceView> = None;
+    unsafe {
+        input.GetDesc(&mut desc);
+        device.CreateShaderResourceView(input, None, Some(&mut view))?;
+    }
+    let view = view.ok_or(CaptureError::Windows(Error::from(E_FAIL)))?;
+
+    unsafe {
+        context.CSSetShader(shader, None);
+        context.CSSetShaderResources(0, Some(&[Some(view)]));
+        context.CSSetUnorderedAccessViews(0, 1, Some(&Some(output.view().clone())), None);
+        context.Dispatch(desc.Width.div_ceil(16), desc.Height.div_ceil(16), 1);
+
+        // Desvincular para que a textura possa voltar a ser destino de cópias
+        context.CSSetShaderResources(0, Some(&[None]));
+        context.CSSetUnorderedAccessViews(0, 1, Some(&None), None);
+        context.CSSetShader(None, None);
+    }
+
+    Ok(())
+}
+
+/// Histograma de luminância de uma textura, com shader e buffers reutilizados entre quadros
+pub(crate) struct HistogramPass {
+    shader: ID3D11ComputeShader,
+    bins: ReadbackBuffer,
+}
+
+impl HistogramPass {
+    pub(crate) fn new(device: &ID3D11Device) -> CaptureResult<Self> {
+        Ok(Self {
+            shader: compile_compute_shader(device, HISTOGRAM_SHADER)?,
+            bins: ReadbackBuffer::new(device, HISTOGRAM_BINS)?,
+        })
+    }
+
+    pub(crate) fn run(
+        &self,
+        device: &ID3D11Device,
+        context: &ID3D11DeviceContext,
+        input: &ID3D11Texture2D,
+    ) -> CaptureResult<Histogram> {
+        self.bins.fill(context, 0);
+        dispatch_over_texture(device, context, &self.shader, input, &self.bins)?;
+
+        let mut histogram = Histogram {
+            bins: [0; HISTOGRAM_BINS],
+        };
+        self.bins.read(context, &mut histogram.bins)?;
+        Ok(histogram)
+    }
+}
+
+/// Faixa de cores de uma textura, usada para detectar quadros pretos ou de uma cor só
+pub(crate) struct ColorRangePass {
+    shader: ID3D11ComputeShader,
+    range: ReadbackBuffer,
+}
+
+impl ColorRangePass {
+    pub(crate) fn new(device: &ID3D11Device) -> CaptureResult<Self> {
+        Ok(Self {
+            shader: compile_compute_shader(device, COLOR_RANGE_SHADER)?,
+            range: ReadbackBuffer::new(device, 6)?,
+        })
+    }
+
+    pub(crate) fn run(
+        &self,
+        device: &ID3D11Device,
+        context: &ID3D11DeviceContext,
+        input: &ID3D11Texture2D,
+    ) -> CaptureResult<ColorRange> {
+        self.range.fill(context, 0);
+        dispatch_over_texture(device, context, &self.shader, input, &self.range)?;
+
+        let mut values = [0u32; 6];
+        self.range.read(context, &mut values)?;
+        let channel = |index: usize| values[index].min(255) as u8;
+        Ok(ColorRange {
+            min: [255 - channel(0), 255 - channel(1), 255 - channel(2)],
+            max: [channel(3), channel(4), channel(5)],
+        })
+    }
+}
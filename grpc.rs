@@ -0,0 +1,190 @@
+//! Serviço gRPC de captura (feature `grpc`)
+//!
+//! Expõe `ListMonitors`, `Screenshot` e `StreamFrames` conforme `proto/capture.proto`, para
+//! que serviços em outras linguagens e ferramentas de automação controlem a captura.
+//! Cada chamada cria sua própria `DxgiCapture` em uma thread dedicada, já que os objetos
+//! COM não podem atravessar threads do runtime assíncrono.
+
+use std::net::SocketAddr;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+
+use crate::outputs::enumerate_outputs;
+use crate::{CaptureError, CaptureResult, DxgiCapture, NoFramePolicy, Rect, Result};
+
+pub mod proto {
+    tonic::include_proto!("dxgi_capture");
+}
+
+use proto::capture_server::{Capture, CaptureServer};
+use proto::{Frame, ListMonitorsRequest, ListMonitorsResponse, Monitor, Region, ScreenshotRequest, StreamFramesRequest};
+
+/// Tempo máximo aguardando o primeiro quadro de uma duplicação nova
+const SCREENSHOT_TIMEOUT: Duration = Duration::from_secs(2);
+const STREAM_QUEUE_DEPTH: usize = 4;
+const DEFAULT_STREAM_FPS: u32 = 30;
+
+/// Implementação do serviço `dxgi_capture.Capture`
+#[derive(Debug, Default)]
+pub struct CaptureService;
+
+/// Inicia o servidor gRPC no endereço informado
+pub async fn serve(address: SocketAddr) -> Result<()> {
+    tonic::transport::Server::builder()
+        .add_service(CaptureServer::new(CaptureService))
+        .serve(address)
+        .await?;
+    Ok(())
+}
+
+/// Resolve a região pedida; largura/altura zero significam o monitor inteiro
+fn resolve_region(capture: &DxgiCapture, region: Option<Region>) -> Rect {
+    match region {
+        Some(r) if r.width > 0 && r.height > 0 => Rect::new(r.left, r.top, r.width, r.height),
+        _ => capture.bounds(),
+    }
+}
+
+fn grab_frame(capture: &mut DxgiCapture, region: Rect) -> CaptureResult<Frame> {
+    let data = capture.capture_region(region)?;
+
+    Ok(Frame {
+        width: region.width,
+        height: region.height,
+        format: capture.format().0,
+        timestamp: capture.last_present_time(),
+        data,
+        color_space: capture.color_space().to_dxgi().0,
+    })
+}
+
+/// Converte a falha da captura no status gRPC correspondente
+fn capture_status(error: CaptureError) -> Status {
+    match error {
+        CaptureError::AdapterNotFound { .. } | CaptureError::OutputNotFound { .. } => Status::not_found(error.to_string()),
+        CaptureError::InvalidRegion(_) => Status::invalid_argument(error.to_string()),
+        _ => Status::unavailable(error.to_string()),
+    }
+}
+
+/// Captura um único quadro, aguardando a duplicação entregar a imagem do desktop
+fn screenshot(request: ScreenshotRequest) -> std::result::Result<Frame, Status> {
+    let mut capture = DxgiCapture::for_output(request.adapter_index, request.output_index).map_err(capture_status)?;
+    let region = resolve_region(&capture, request.region);
+    let deadline = Instant::now() + SCREENSHOT_TIMEOUT;
+
+    loop {
+        let result = grab_frame(&mut capture, region);
+        match result {
+            Ok(frame) if capture.last_present_time() != 0 => return Ok(frame),
+            Err(e) if Instant::now() >= deadline => return Err(Status::unavailable(e.to_string())),
+            _ if Instant::now() >= deadline => return Err(Status::deadline_exceeded("Nenhum quadro recebido")),
+            _ => thread::sleep(Duration::from_millis(10)),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl Capture for CaptureService {
+    async fn list_monitors(
+        &self,
+        _request: Request<ListMonitorsRequest>,
+    ) -> std::result::Result<Response<ListMonitorsResponse>, Status> {
+        let monitors = tokio::task::spawn_blocking(|| {
+            enumerate_outputs()
+                .map(|outputs| {
+                    outputs
+                        .into_iter()
+                        .map(|output| Monitor {
+                            adapter_index: output.adapter_index,
+                            output_index: output.output_index,
+                            adapter_name: output.adapter_name,
+                            device_name: output.device_name,
+                            left: output.left,
+                            top: output.top,
+                            width: output.width,
+                            height: output.height,
+                            hdr: output.color.map_or(false, |color| color.is_hdr()),
+                            max_luminance: output.color.map_or(0.0, |color| color.max_luminance),
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .map_err(|e| Status::internal(e.to_string()))
+        })
+        .await
+        .map_err(|e| Status::internal(e.to_string()))??;
+
+        Ok(Response::new(ListMonitorsResponse { monitors }))
+    }
+
+    async fn screenshot(&self, request: Request<ScreenshotRequest>) -> std::result::Result<Response<Frame>, Status> {
+        let request = request.into_inner();
+        let frame = tokio::task::spawn_blocking(move || screenshot(request))
+            .await
+            .map_err(|e| Status::internal(e.to_string()))??;
+
+        Ok(Response::new(frame))
+    }
+
+    type StreamFramesStream = ReceiverStream<std::result::Result<Frame, Status>>;
+
+    async fn stream_frames(
+        &self,
+        request: Request<StreamFramesRequest>,
+    ) -> std::result::Result<Response<Self::StreamFramesStream>, Status> {
+        let request = request.into_inner();
+        let (sender, receiver) = mpsc::channel(STREAM_QUEUE_DEPTH);
+
+        thread::spawn(move || {
+            // Com `NoChange` a tela parada vira `NoNewFrame` em vez de um quadro preto
+            let built = DxgiCapture::builder()
+                .output(request.adapter_index, request.output_index)
+                .no_frame_policy(NoFramePolicy::NoChange)
+                .build();
+            let mut capture = match built {
+                Ok(capture) => capture,
+                Err(e) => {
+                    let _ = sender.blocking_send(Err(capture_status(e)));
+                    return;
+                }
+            };
+
+            let region = resolve_region(&capture, request.region);
+            let fps = if request.fps == 0 { DEFAULT_STREAM_FPS } else { request.fps };
+            let interval = Duration::from_secs(1) / fps;
+            let mut next = Instant::now();
+
+            while !sender.is_closed() {
+                match grab_frame(&mut capture, region) {
+                    Ok(frame) => {
+                        if sender.blocking_send(Ok(frame)).is_err() {
+                            break;
+                        }
+                    }
+                    // Sem quadro novo (tela parada) não há o que enviar neste intervalo; perdas de
+                    // acesso e sessões desconectadas são recuperadas pela própria captura
+                    Err(CaptureError::NoNewFrame | CaptureError::AccessLost { .. } | CaptureError::SessionDisconnected { .. }) => {}
+                    // Região inválida, monitor desconectado...: o cliente recebe o status e o fluxo termina
+                    Err(e) => {
+                        let _ = sender.blocking_send(Err(capture_status(e)));
+                        break;
+                    }
+                }
+
+                next += interval;
+                let now = Instant::now();
+                if next > now {
+                    thread::sleep(next - now);
+                } else {
+                    next = now;
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(receiver)))
+    }
+}
@@ -0,0 +1,217 @@
+//! Metadados estáticos do HDR10 (SMPTE ST 2086 e CTA-861.3) para os encoders
+//!
+//! Um vídeo PQ sem o volume de cor do mastering display e sem MaxCLL/MaxFALL é reproduzido
+//! com suposições do player, em geral com tone mapping errado. Na captura o conteúdo já foi
+//! composto pelo DWM para o monitor, então os dados de `IDXGIOutput6::GetDesc1` descrevem o
+//! mastering display (`DxgiCapture::hdr_metadata`). Quem renderiza o próprio conteúdo pode passar
+//! o mesmo `DXGI_HDR_METADATA_HDR10` entregue à sua swap chain (`from_dxgi`): os metadados de
+//! swap chains de outros processos não são acessíveis.
+//!
+//! Os encoders (`ffmpeg_encoder`, `hw_encoder`) levam os metadados para as mensagens SEI do HEVC
+//! e para as caixas `mdcv`/`clli` do MP4.
+
+use windows::Win32::Graphics::Dxgi::DXGI_HDR_METADATA_HDR10;
+
+use crate::outputs::OutputColorInfo;
+
+/// Unidades das coordenadas xy no SEI e no `DXGI_HDR_METADATA_HDR10` (0,00002)
+const CHROMATICITY_SCALE: f32 = 50_000.0;
+/// Unidades das luminâncias do mastering display (0,0001 nit)
+const LUMINANCE_SCALE: f32 = 10_000.0;
+
+/// `nal_unit_type` de uma SEI de prefixo do HEVC
+const HEVC_PREFIX_SEI: u8 = 39;
+const SEI_MASTERING_DISPLAY_COLOUR_VOLUME: u8 = 137;
+const SEI_CONTENT_LIGHT_LEVEL_INFO: u8 = 144;
+
+/// Volume de cor do mastering display e níveis de luz do conteúdo
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HdrMetadata {
+    /// Primárias e ponto branco do mastering display em coordenadas CIE xy
+    pub red_primary: [f32; 2],
+    pub green_primary: [f32; 2],
+    pub blue_primary: [f32; 2],
+    pub white_point: [f32; 2],
+    /// Luminâncias do mastering display em nits
+    pub min_mastering_luminance: f32,
+    pub max_mastering_luminance: f32,
+    /// MaxCLL: pixel mais claro do conteúdo, em nits; 0 = desconhecido
+    pub max_content_light_level: u16,
+    /// MaxFALL: maior média de luminância de um quadro, em nits; 0 = desconhecido
+    pub max_frame_average_light_level: u16,
+}
+
+impl HdrMetadata {
+    /// Metadados do monitor capturado: o conteúdo não passa da luminância de pico (MaxCLL) nem da
+    /// de quadro inteiro (MaxFALL) que ele informa
+    pub fn from_output(info: &OutputColorInfo) -> Self {
+        Self {
+            red_primary: info.red_primary,
+            green_primary: info.green_primary,
+            blue_primary: info.blue_primary,
+            white_point: info.white_point,
+            min_mastering_luminance: info.min_luminance,
+            max_mastering_luminance: info.max_luminance,
+            max_content_light_level: nits(info.max_luminance),
+            max_frame_average_light_level: nits(info.max_full_frame_luminance),
+        }
+    }
+
+    /// Metadados passados a `IDXGISwapChain4::SetHDRMetaData`, com as luminâncias do mastering
+    /// display em 0,0001 nit como no SEI
+    pub fn from_dxgi(metadata: &DXGI_HDR_METADATA_HDR10) -> Self {
+        let xy = |value: [u16; 2]| value.map(|v| v as f32 / CHROMATICITY_SCALE);
+        Self {
+            red_primary: xy(metadata.RedPrimary),
+            green_primary: xy(metadata.GreenPrimary),
+            blue_primary: xy(metadata.BluePrimary),
+            white_point: xy(metadata.WhitePoint),
+            min_mastering_luminance: metadata.MinMasteringLuminance as f32 / LUMINANCE_SCALE,
+            max_mastering_luminance: metadata.MaxMasteringLuminance as f32 / LUMINANCE_SCALE,
+            max_content_light_level: metadata.MaxContentLightLevel,
+            max_frame_average_light_level: metadata.MaxFrameAverageLightLevel,
+        }
+    }
+
+    pub fn to_dxgi(&self) -> DXGI_HDR_METADATA_HDR10 {
+        DXGI_HDR_METADATA_HDR10 {
+            RedPrimary: self.red_primary.map(chromaticity),
+            GreenPrimary: self.green_primary.map(chromaticity),
+            BluePrimary: self.blue_primary.map(chromaticity),
+            WhitePoint: self.white_point.map(chromaticity),
+            MaxMasteringLuminance: luminance(self.max_mastering_luminance),
+            MinMasteringLuminance: luminance(self.min_mastering_luminance),
+            MaxContentLightLevel: self.max_content_light_level,
+            MaxFrameAverageLightLevel: self.max_frame_average_light_level,
+        }
+    }
+
+    /// Primárias BT.2020 e ponto branco D65, para quando o monitor não informa as suas
+    pub fn bt2020(max_mastering_luminance: f32) -> Self {
+        Self {
+            red_primary: [0.708, 0.292],
+            green_primary: [0.170, 0.797],
+            blue_primary: [0.131, 0.046],
+            white_point: [0.3127, 0.3290],
+            min_mastering_luminance: 0.0001,
+            max_mastering_luminance,
+            max_content_light_level: 0,
+            max_frame_average_light_level: 0,
+        }
+    }
+
+    /// O monitor informou primárias e luminância (alguns drivers deixam tudo zerado)
+    pub fn is_valid(&self) -> bool {
+        self.max_mastering_luminance > 0.0 && self.white_point != [0.0, 0.0]
+    }
+
+    /// Parâmetros `master-display`/`max-cll` do x265 (`x265-params`)
+    pub fn x265_params(&self) -> String {
+        let [gx, gy] = self.green_primary.map(chromaticity);
+        let [bx, by] = self.blue_primary.map(chromaticity);
+        let [rx, ry] = self.red_primary.map(chromaticity);
+        let [wx, wy] = self.white_point.map(chromaticity);
+        format!(
+            "master-display=G({},{})B({},{})R({},{})WP({},{})L({},{}):max-cll={},{}",
+            gx,
+            gy,
+            bx,
+            by,
+            rx,
+            ry,
+            wx,
+            wy,
+            luminance(self.max_mastering_luminance),
+            luminance(self.min_mastering_luminance),
+            self.max_content_light_level,
+            self.max_frame_average_light_level,
+        )
+    }
+
+    /// NAL de SEI do HEVC (com o start code do Annex-B) com as mensagens de mastering display e
+    /// de níveis de luz, para anteceder as fatias de cada quadro-chave
+    pub fn hevc_sei(&self) -> Vec<u8> {
+        let mut mastering = Vec::with_capacity(24);
+        // O HEVC lista as primárias na ordem verde, azul, vermelho
+        for xy in [self.green_primary, self.blue_primary, self.red_primary, self.white_point] {
+            for value in xy {
+                mastering.extend_from_slice(&chromaticity(value).to_be_bytes());
+            }
+        }
+        mastering.extend_from_slice(&luminance(self.max_mastering_luminance).to_be_bytes());
+        mastering.extend_from_slice(&luminance(self.min_mastering_luminance).to_be_bytes());
+
+        let mut light_level = Vec::with_capacity(4);
+        light_level.extend_from_slice(&self.max_content_light_level.to_be_bytes());
+        light_level.extend_from_slice(&self.max_frame_average_light_level.to_be_bytes());
+
+        let mut rbsp = Vec::with_capacity(36);
+        for (payload_type, payload) in [
+            (SEI_MASTERING_DISPLAY_COLOUR_VOLUME, mastering),
+            (SEI_CONTENT_LIGHT_LEVEL_INFO, light_level),
+        ] {
+            rbsp.push(payload_type);
+            rbsp.push(payload.len() as u8);
+            rbsp.extend_from_slice(&payload);
+        }
+        // rbsp_trailing_bits
+        rbsp.push(0x80);
+
+        let mut nal = vec![0, 0, 0, 1, HEVC_PREFIX_SEI << 1, 1];
+        let mut zeros = 0;
+        for byte in rbsp {
+            // Prevenção de emulação de start code
+            if zeros == 2 && byte <= 3 {
+                nal.push(3);
+                zeros = 0;
+            }
+            nal.push(byte);
+            zeros = if byte == 0 { zeros + 1 } else { 0 };
+        }
+        nal
+    }
+
+    /// Access unit HEVC em Annex-B com `hevc_sei` antes da primeira fatia (depois de AUD, VPS,
+    /// SPS e PPS, onde as SEIs de prefixo precisam ficar)
+    pub fn insert_hevc_sei(&self, access_unit: &[u8]) -> Vec<u8> {
+        let sei = self.hevc_sei();
+        let position = first_slice_offset(access_unit).unwrap_or(access_unit.len());
+        let mut output = Vec::with_capacity(access_unit.len() + sei.len());
+        output.extend_from_slice(&access_unit[..position]);
+        output.extend_from_slice(&sei);
+        output.extend_from_slice(&access_unit[position..]);
+        output
+    }
+}
+
+/// Início (incluindo o start code) da primeira NAL de fatia (`nal_unit_type` < 32)
+fn first_slice_offset(data: &[u8]) -> Option<usize> {
+    let mut index = 0;
+    while index + 3 < data.len() {
+        if data[index..index + 3] == [0, 0, 1] {
+            let nal_type = (data[index + 3] >> 1) & 0x3F;
+            if nal_type < 32 {
+                // Start code de 4 bytes: o zero extra fica com a NAL da fatia
+                let start = if index > 0 && data[index - 1] == 0 { index - 1 } else { index };
+                return Some(start);
+            }
+            index += 3;
+        } else {
+            index += 1;
+        }
+    }
+    None
+}
+
+fn chromaticity(value: f32) -> u16 {
+    (value * CHROMATICITY_SCALE).round().clamp(0.0, CHROMATICITY_SCALE) as u16
+}
+
+fn luminance(value: f32) -> u32 {
+    (value.max(0.0) as f64 * LUMINANCE_SCALE as f64).round().min(u32::MAX as f64) as u32
+}
+
+fn nits(value: f32) -> u16 {
+    value.round().clamp(0.0, u16::MAX as f32) as u16
+}
@@ -0,0 +1,517 @@
+//! Encoder de hardware (NVENC/AMF/QuickSync) via MFTs do Media Foundation (feature `hw-encoder`)
+//!
+//! A textura capturada é convertida para NV12 pelo video processor e entregue ao MFT de
+//! hardware do mesmo adaptador como superfície DXGI, sem nenhuma cópia de pixels para a CPU.
+//! A saída são unidades NAL em Annex-B (H.264 ou HEVC).
+//!
+//! Com `HwEncoderConfig::hdr10`, o HEVC sai em Main10 (P010) com PQ e primárias BT.2020, e cada
+//! quadro-chave leva as SEIs de mastering display e de níveis de luz dos `HdrMetadata`.
+
+use std::mem::ManuallyDrop;
+use std::ptr;
+use std::slice;
+
+use windows::core::*;
+use windows::Win32::Graphics::Direct3D11::*;
+use windows::Win32::Graphics::Dxgi::Common::{
+    DXGI_COLOR_SPACE_RGB_FULL_G10_NONE_P709, DXGI_COLOR_SPACE_RGB_FULL_G2084_NONE_P2020,
+    DXGI_COLOR_SPACE_RGB_FULL_G22_NONE_P709, DXGI_COLOR_SPACE_TYPE, DXGI_COLOR_SPACE_YCBCR_STUDIO_G2084_LEFT_P2020,
+    DXGI_FORMAT, DXGI_FORMAT_NV12, DXGI_FORMAT_P010, DXGI_FORMAT_R10G10B10A2_UNORM, DXGI_FORMAT_R16G16B16A16_FLOAT,
+    DXGI_FORMAT_UNKNOWN,
+};
+use windows::Win32::Graphics::Dxgi::{IDXGIDevice, DXGI_ADAPTER_DESC};
+use windows::Win32::Media::MediaFoundation::*;
+use windows::Win32::System::Com::CoTaskMemFree;
+
+use crate::com::{ensure_com_initialized, ComGuard};
+use crate::geometry::Size;
+use crate::hdr_metadata::HdrMetadata;
+use crate::video_processor::VideoConverter;
+use crate::Result;
+
+/// Texturas NV12 em circulação enquanto o MFT processa de forma assíncrona
+const INPUT_POOL_SIZE: usize = 4;
+
+/// Codec de saída do encoder de hardware
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum HwCodec {
+    H264,
+    Hevc,
+}
+
+impl HwCodec {
+    fn subtype(self) -> GUID {
+        match self {
+            HwCodec::H264 => MFVideoFormat_H264,
+            HwCodec::Hevc => MFVideoFormat_HEVC,
+        }
+    }
+}
+
+/// Fabricante do encoder selecionado, identificado pelo vendor id do adaptador
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HwVendor {
+    Nvidia,
+    Amd,
+    Intel,
+    Other(u32),
+}
+
+impl HwVendor {
+    fn from_vendor_id(vendor_id: u32) -> Self {
+        match vendor_id {
+            0x10DE => HwVendor::Nvidia,
+            0x1002 => HwVendor::Amd,
+            0x8086 => HwVendor::Intel,
+            other => HwVendor::Other(other),
+        }
+    }
+}
+
+/// Configuração do encoder de hardware
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HwEncoderConfig {
+    pub codec: HwCodec,
+    pub width: u32,
+    pub height: u32,
+    pub frame_rate: u32,
+    pub bitrate: u32,
+    /// Modo de baixa latência (sem B-frames, GOP curto)
+    pub low_latency: bool,
+    /// Codifica em HDR10 com estes metadados (só HEVC); `None` codifica em SDR
+    pub hdr: Option<HdrMetadata>,
+}
+
+impl HwEncoderConfig {
+    pub fn new(codec: HwCodec, width: u32, height: u32, frame_rate: u32, bitrate: u32) -> Self {
+        Self {
+            codec,
+            width,
+            height,
+            frame_rate,
+            bitrate,
+            low_latency: true,
+            hdr: None,
+        }
+    }
+
+    /// HEVC Main10 em HDR10 (ex.: com `DxgiCapture::hdr_metadata`); as texturas de entrada podem
+    /// ser R10G10B10A2 (HDR10), FP16 (scRGB) ou de 8 bits (sRGB, levado para PQ)
+    pub fn hdr10(mut self, metadata: HdrMetadata) -> Self {
+        self.hdr = Some(metadata);
+        self
+    }
+}
+
+/// Pacote comprimido em Annex-B
+#[derive(Debug, Clone)]
+pub struct EncodedPacket {
+    pub data: Vec<u8>,
+    /// Tempo de apresentação em unidades de 100 ns
+    pub timestamp: i64,
+    pub keyframe: bool,
+}
+
+/// Encoder H.264/HEVC de hardware alimentado diretamente com texturas D3D11
+pub struct HwEncoder {
+    transform: IMFTransform,
+    events: IMFMediaEventGenerator,
+    _device_manager: IMFDXGIDeviceManager,
+    converter: VideoConverter,
+    vendor: HwVendor,
+    name: String,
+    frame_duration: i64,
+    pending_input_requests: u32,
+    output_provides_samples: bool,
+    hdr: Option<HdrMetadata>,
+    // Formato da última textura de entrada, cujo espaço de cor foi passado ao video processor
+    input_format: DXGI_FORMAT,
+    // O MFT assíncrono chama de volta a partir de threads do MTA
+    _com: ComGuard,
+}
+
+impl HwEncoder {
+    /// Cria o encoder para o adaptador do `device`, que deve ser o mesmo da captura
+    pub fn new(device: &ID3D11Device, context: &ID3D11DeviceContext, config: &HwEncoderConfig) -> Result<Self> {
+        if config.hdr.is_some() && config.codec != HwCodec::Hevc {
+            return Err("HDR10 só é suportado com HEVC".into());
+        }
+
+        let com = ensure_com_initialized()?;
+        unsafe {
+            MFStartup(MF_VERSION, MFSTARTUP_FULL)?;
+        }
+
+        // O MFT usa o dispositivo a partir de outras threads
+        let multithread: ID3D11Multithread = context.cast()?;
+        unsafe {
+            multithread.SetMultithreadProtected(true);
+        }
+
+        let mut reset_token = 0u32;
+        let mut device_manager: Option<IMFDXGIDeviceManager> = None;
+        unsafe {
+            MFCreateDXGIDeviceManager(&mut reset_token, &mut device_manager)?;
+        }
+        let device_manager = device_manager.ok_or("Falha ao criar o gerenciador de dispositivo DXGI")?;
+        unsafe {
+            device_manager.ResetDevice(device, reset_token)?;
+        }
+
+        let adapter_vendor_id = unsafe {
+            let dxgi_device: IDXGIDevice = device.cast()?;
+            let mut desc = DXGI_ADAPTER_DESC::default();
+            dxgi_device.GetAdapter()?.GetDesc(&mut desc)?;
+            desc.VendorId
+        };
+
+        let (transform, name) = activate_hardware_encoder(config.codec, adapter_vendor_id)?;
+
+        unsafe {
+            // MFTs de hardware são assíncronos e precisam ser desbloqueados explicitamente
+            let attributes = transform.GetAttributes()?;
+            attributes.SetUINT32(&MF_TRANSFORM_ASYNC_UNLOCK, 1)?;
+            if config.low_latency {
+                attributes.SetUINT32(&MF_LOW_LATENCY, 1)?;
+            }
+
+            transform.ProcessMessage(MFT_MESSAGE_SET_D3D_MANAGER, device_manager.as_raw() as usize)?;
+        }
+
+        configure_media_types(&transform, config)?;
+
+        let output_provides_samples = unsafe {
+            let info = transform.GetOutputStreamInfo(0)?;
+            info.dwFlags & (MFT_OUTPUT_STREAM_PROVIDES_SAMPLES.0 | MFT_OUTPUT_STREAM_CAN_PROVIDE_SAMPLES.0) as u32 != 0
+        };
+
+        unsafe {
+            transform.ProcessMessage(MFT_MESSAGE_NOTIFY_BEGIN_STREAMING, 0)?;
+            transform.ProcessMessage(MFT_MESSAGE_NOTIFY_START_OF_STREAM, 0)?;
+        }
+
+        let converter = VideoConverter::new(
+            device,
+            context,
+            Size::new(config.width, config.height),
+            Size::new(config.width, config.height),
+            if config.hdr.is_some() { DXGI_FORMAT_P010 } else { DXGI_FORMAT_NV12 },
+            INPUT_POOL_SIZE,
+        )?;
+
+        Ok(Self {
+            events: transform.cast()?,
+            transform,
+            _device_manager: device_manager,
+            converter,
+            vendor: HwVendor::from_vendor_id(adapter_vendor_id),
+            name,
+            frame_duration: 10_000_000 / config.frame_rate.max(1) as i64,
+            pending_input_requests: 0,
+            output_provides_samples,
+            hdr: config.hdr,
+            input_format: DXGI_FORMAT_UNKNOWN,
+            _com: com,
+        })
+    }
+
+    pub fn vendor(&self) -> HwVendor {
+        self.vendor
+    }
+
+    /// Nome amigável do MFT selecionado
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Codifica uma textura BGRA (por exemplo, de `DxgiCapture::capture_texture`)
+    ///
+    /// `timestamp` está em unidades de 100 ns. Retorna os pacotes que ficaram prontos.
+    pub fn encode(&mut self, texture: &ID3D11Texture2D, timestamp: i64) -> Result<Vec<EncodedPacket>> {
+        if self.hdr.is_some() {
+            let mut desc = D3D11_TEXTURE2D_DESC::default();
+            unsafe {
+                texture.GetDesc(&mut desc);
+            }
+            if desc.Format != self.input_format {
+                self.converter
+                    .set_color_spaces(input_color_space(desc.Format), DXGI_COLOR_SPACE_YCBCR_STUDIO_G2084_LEFT_P2020)?;
+                self.input_format = desc.Format;
+            }
+        }
+        let surface = self.converter.convert(texture)?;
+        let mut packets = Vec::new();
+
+        // Aguardar o MFT pedir entrada, aproveitando as saídas que chegarem nesse meio tempo
+        while self.pending_input_requests == 0 {
+            self.handle_event(true, &mut packets)?;
+        }
+
+        let sample = unsafe {
+            let buffer = MFCreateDXGISurfaceBuffer(&ID3D11Texture2D::IID, &surface, 0, false)?;
+            let buffer_2d: IMF2DBuffer = buffer.cast()?;
+            buffer.SetCurrentLength(buffer_2d.GetContiguousLength()?)?;
+
+            let sample = MFCreateSample()?;
+            sample.AddBuffer(&buffer)?;
+            sample.SetSampleTime(timestamp)?;
+            sample.SetSampleDuration(self.frame_duration)?;
+            sample
+        };
+
+        unsafe {
+            self.transform.ProcessInput(0, &sample, 0)?;
+        }
+        self.pending_input_requests -= 1;
+
+        while self.handle_event(false, &mut packets)? {}
+        Ok(packets)
+    }
+
+    /// Esvazia o encoder, retornando os pacotes restantes
+    // Os tipos de evento do Media Foundation são constantes com nomes em CamelCase
+    #[allow(non_upper_case_globals)]
+    pub fn flush(&mut self) -> Result<Vec<EncodedPacket>> {
+        let mut packets = Vec::new();
+        unsafe {
+            self.transform.ProcessMessage(MFT_MESSAGE_NOTIFY_END_OF_STREAM, 0)?;
+            self.transform.ProcessMessage(MFT_MESSAGE_COMMAND_DRAIN, 0)?;
+        }
+
+        loop {
+            let event = unsafe { self.events.GetEvent(MEDIA_EVENT_GENERATOR_GET_EVENT_FLAGS(0))? };
+            match MF_EVENT_TYPE(unsafe { event.GetType()? } as i32) {
+                METransformHaveOutput => self.collect_output(&mut packets)?,
+                METransformDrainComplete => break,
+                _ => {}
+            }
+        }
+
+        Ok(packets)
+    }
+
+    /// Processa um evento do MFT; retorna `false` se não havia evento (modo sem espera)
+    #[allow(non_upper_case_globals)]
+    fn handle_event(&mut self, wait: bool, packets: &mut Vec<EncodedPacket>) -> Result<bool> {
+        let flags = if wait { 0 } else { MF_EVENT_FLAG_NO_WAIT.0 };
+        let event = match unsafe { self.events.GetEvent(MEDIA_EVENT_GENERATOR_GET_EVENT_FLAGS(flags)) } {
+            Ok(event) => event,
+            Err(err) if err.code() == MF_E_NO_EVENTS_AVAILABLE => return Ok(false),
+            Err(err) => return Err(err.into()),
+        };
+
+        match MF_EVENT_TYPE(unsafe { event.GetType()? } as i32) {
+            METransformNeedInput => self.pending_input_requests += 1,
+            METransformHaveOutput => self.collect_output(packets)?,
+            _ => {}
+        }
+
+        Ok(true)
+    }
+
+    fn collect_output(&mut self, packets: &mut Vec<EncodedPacket>) -> Result<()> {
+        let sample = if self.output_provides_samples {
+            None
+        } else {
+            let info = unsafe { self.transform.GetOutputStreamInfo(0)? };
+            unsafe {
+                let buffer = MFCreateMemoryBuffer(info.cbSize)?;
+                let sample = MFCreateSample()?;
+                sample.AddBuffer(&buffer)?;
+                Some(sample)
+            }
+        };
+
+        let mut output = [MFT_OUTPUT_DATA_BUFFER {
+            dwStreamID: 0,
+            pSample: ManuallyDrop::new(sample),
+            dwStatus: 0,
+            pEvents: ManuallyDrop::new(None),
+        }];
+        let mut status = 0u32;
+
+        let result = unsafe { self.transform.ProcessOutput(0, &mut output, &mut status) };
+        let sample = unsafe { ManuallyDrop::take(&mut output[0].pSample) };
+        unsafe {
+            ManuallyDrop::drop(&mut output[0].pEvents);
+        }
+
+        if let Err(err) = result {
+            if err.code() == MF_E_TRANSFORM_STREAM_CHANGE {
+                // O encoder alterou o tipo de saída (ex.: cabeçalhos); renegociar e seguir
+                unsafe {
+                    let output_type = self.transform.GetOutputAvailableType(0, 0)?;
+                    self.transform.SetOutputType(0, &output_type, 0)?;
+                }
+                return Ok(());
+            }
+            return Err(err.into());
+        }
+
+        if let Some(sample) = sample {
+            let mut packet = read_packet(&sample)?;
+            if let Some(hdr) = self.hdr.as_ref().filter(|_| packet.keyframe) {
+                packet.data = hdr.insert_hevc_sei(&packet.data);
+            }
+            packets.push(packet);
+        }
+        Ok(())
+    }
+}
+
+impl Drop for HwEncoder {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = self.transform.ProcessMessage(MFT_MESSAGE_NOTIFY_END_STREAMING, 0);
+            let _ = MFShutdown();
+        }
+    }
+}
+
+/// Espaço de cor das texturas capturadas em `format` (ver `ColorSpace::of_capture`)
+fn input_color_space(format: DXGI_FORMAT) -> DXGI_COLOR_SPACE_TYPE {
+    match format {
+        DXGI_FORMAT_R16G16B16A16_FLOAT => DXGI_COLOR_SPACE_RGB_FULL_G10_NONE_P709,
+        DXGI_FORMAT_R10G10B10A2_UNORM => DXGI_COLOR_SPACE_RGB_FULL_G2084_NONE_P2020,
+        _ => DXGI_COLOR_SPACE_RGB_FULL_G22_NONE_P709,
+    }
+}
+
+/// Procura um MFT de hardware para o codec, preferindo o do fabricante do adaptador de captura
+fn activate_hardware_encoder(codec: HwCodec, adapter_vendor_id: u32) -> Result<(IMFTransform, String)> {
+    let output_info = MFT_REGISTER_TYPE_INFO {
+        guidMajorType: MFMediaType_Video,
+        guidSubtype: codec.subtype(),
+    };
+
+    let mut activates: *mut Option<IMFActivate> = ptr::null_mut();
+    let mut count = 0u32;
+    unsafe {
+        MFTEnumEx(
+            MFT_CATEGORY_VIDEO_ENCODER,
+            MFT_ENUM_FLAG_HARDWARE | MFT_ENUM_FLAG_SORTANDFILTER,
+            None,
+            Some(&output_info as *const _),
+            &mut activates,
+            &mut count,
+        )?;
+    }
+
+    if activates.is_null() || count == 0 {
+        return Err("Nenhum encoder de hardware disponível para o codec".into());
+    }
+
+    // Assumir a posse de todos os IMFActivate para liberá-los ao final
+    let candidates: Vec<IMFActivate> = unsafe {
+        let list = slice::from_raw_parts_mut(activates, count as usize);
+        let owned = list.iter_mut().filter_map(|activate| activate.take()).collect();
+        CoTaskMemFree(Some(activates as *const _));
+        owned
+    };
+
+    let vendor_tag = format!("VEN_{:04X}", adapter_vendor_id);
+    let chosen = candidates
+        .iter()
+        .find(|activate| read_string(activate, &MFT_ENUM_HARDWARE_VENDOR_ID_Attribute).as_deref() == Some(vendor_tag.as_str()))
+        .or_else(|| candidates.first())
+        .ok_or("Nenhum encoder de hardware disponível para o codec")?;
+
+    let name = read_string(chosen, &MFT_FRIENDLY_NAME_Attribute).unwrap_or_default();
+    let transform: IMFTransform = unsafe { chosen.ActivateObject()? };
+    Ok((transform, name))
+}
+
+fn read_string(attributes: &IMFActivate, key: &GUID) -> Option<String> {
+    unsafe {
+        let mut value = PWSTR::null();
+        let mut length = 0u32;
+        attributes.GetAllocatedString(key, &mut value, &mut length).ok()?;
+        let text = value.to_string().ok();
+        CoTaskMemFree(Some(value.0 as *const _));
+        text
+    }
+}
+
+fn configure_media_types(transform: &IMFTransform, config: &HwEncoderConfig) -> Result<()> {
+    let frame_size = ((config.width as u64) << 32) | config.height as u64;
+    let frame_rate = ((config.frame_rate as u64) << 32) | 1;
+
+    unsafe {
+        // Encoders exigem o tipo de saída antes do de entrada
+        let output_type = MFCreateMediaType()?;
+        output_type.SetGUID(&MF_MT_MAJOR_TYPE, &MFMediaType_Video)?;
+        output_type.SetGUID(&MF_MT_SUBTYPE, &config.codec.subtype())?;
+        output_type.SetUINT32(&MF_MT_AVG_BITRATE, config.bitrate)?;
+        output_type.SetUINT64(&MF_MT_FRAME_SIZE, frame_size)?;
+        output_type.SetUINT64(&MF_MT_FRAME_RATE, frame_rate)?;
+        output_type.SetUINT64(&MF_MT_PIXEL_ASPECT_RATIO, (1u64 << 32) | 1)?;
+        output_type.SetUINT32(&MF_MT_INTERLACE_MODE, MFVideoInterlace_Progressive.0 as u32)?;
+        output_type.SetUINT32(&MF_MT_VIDEO_NOMINAL_RANGE, MFNominalRange_16_235.0 as u32)?;
+        if let Some(hdr) = &config.hdr {
+            // VUI do P010 gerado pelo video processor: PQ, primárias e matriz BT.2020
+            output_type.SetUINT32(&MF_MT_MPEG2_PROFILE, eAVEncH265VProfile_Main_420_10.0 as u32)?;
+            output_type.SetUINT32(&MF_MT_VIDEO_PRIMARIES, MFVideoPrimaries_BT2020.0 as u32)?;
+            output_type.SetUINT32(&MF_MT_TRANSFER_FUNCTION, MFVideoTransFunc_2084.0 as u32)?;
+            output_type.SetUINT32(&MF_MT_YUV_MATRIX, MFVideoTransferMatrix_BT2020_10.0 as u32)?;
+            // Nem todo MFT usa estes atributos; as SEIs de `collect_output` garantem os metadados
+            output_type.SetUINT32(&MF_MT_MAX_MASTERING_LUMINANCE, hdr.max_mastering_luminance.round() as u32)?;
+            output_type.SetUINT32(
+                &MF_MT_MIN_MASTERING_LUMINANCE,
+                (hdr.min_mastering_luminance * 10_000.0).round() as u32,
+            )?;
+            output_type.SetUINT32(&MF_MT_MAX_LUMINANCE_LEVEL, hdr.max_content_light_level as u32)?;
+            output_type.SetUINT32(
+                &MF_MT_MAX_FRAME_AVERAGE_LUMINANCE_LEVEL,
+                hdr.max_frame_average_light_level as u32,
+            )?;
+        } else {
+            // VUI do NV12 gerado pelo video processor: imagem sRGB, matriz BT.709 em faixa limitada
+            output_type.SetUINT32(&MF_MT_VIDEO_PRIMARIES, MFVideoPrimaries_BT709.0 as u32)?;
+            output_type.SetUINT32(&MF_MT_TRANSFER_FUNCTION, MFVideoTransFunc_sRGB.0 as u32)?;
+            output_type.SetUINT32(&MF_MT_YUV_MATRIX, MFVideoTransferMatrix_BT709.0 as u32)?;
+        }
+        transform.SetOutputType(0, &output_type, 0)?;
+
+        let input_subtype = if config.hdr.is_some() { MFVideoFormat_P010 } else { MFVideoFormat_NV12 };
+
+        let mut index = 0;
+        loop {
+            let input_type = transform.GetInputAvailableType(0, index)?;
+            if input_type.GetGUID(&MF_MT_SUBTYPE)? == input_subtype {
+                input_type.SetUINT64(&MF_MT_FRAME_SIZE, frame_size)?;
+                input_type.SetUINT64(&MF_MT_FRAME_RATE, frame_rate)?;
+                transform.SetInputType(0, &input_type, 0)?;
+                break;
+            }
+            index += 1;
+        }
+    }
+
+    Ok(())
+}
+
+/// Copia os bytes comprimidos de uma amostra de saída
+fn read_packet(sample: &IMFSample) -> Result<EncodedPacket> {
+    unsafe {
+        let buffer = sample.ConvertToContiguousBuffer()?;
+        let mut data_ptr: *mut u8 = ptr::null_mut();
+        let mut length = 0u32;
+        buffer.Lock(&mut data_ptr, None, Some(&mut length))?;
+        let data = slice::from_raw_parts(data_ptr, length as usize).to_vec();
+        buffer.Unlock()?;
+
+        let timestamp = sample.GetSampleTime().unwrap_or(0);
+        let keyframe = sample
+            .GetUINT32(&MFSampleExtension_CleanPoint)
+            .map(|value| value != 0)
+            .unwrap_or(false);
+
+        Ok(EncodedPacket {
+            data,
+            timestamp,
+            keyframe,
+        })
+    }
+}
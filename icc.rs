@@ -0,0 +1,223 @@
+//! Conversão pelo perfil ICC do monitor (feature `icc`)
+//!
+//! Os pixels capturados são os valores enviados ao monitor: aplicativos sem gerenciamento de cor
+//! desenham em sRGB, mas o que aparece na tela depende da calibração do monitor. `IccTransform`
+//! usa o CMM do Windows (ICM) para interpretar os valores pelo perfil do monitor e convertê-los
+//! para sRGB, de modo que a captura mostre em outra tela as cores vistas na original. Serve para
+//! revisão de design e conferência de cores; só se aplica a quadros de 8 bits, já que FP16 e
+//! HDR10 não são valores de dispositivo.
+
+use std::path::{Path, PathBuf};
+
+use windows::core::{w, HSTRING, PCWSTR, PWSTR};
+use windows::Win32::Foundation::LPARAM;
+use windows::Win32::Graphics::Dxgi::Common::{DXGI_FORMAT_B8G8R8A8_UNORM, DXGI_FORMAT_R8G8B8A8_UNORM};
+use windows::Win32::Graphics::Gdi::*;
+use windows::Win32::Storage::FileSystem::{FILE_SHARE_READ, OPEN_EXISTING};
+use windows::Win32::UI::ColorSystem::*;
+
+use crate::frame::Frame;
+use crate::outputs::{wide_to_string, ColorSpace};
+use crate::{DxgiCapture, Result};
+
+/// Espaço de cor `'sRGB'` de `GetStandardColorSpaceProfileW` (`LCS_sRGB` do wingdi.h)
+const LCS_SRGB: u32 = 0x7352_4742;
+
+/// Intenção de renderização da conversão (`INTENT_*` do ICM)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum RenderingIntent {
+    Perceptual,
+    /// Preserva as cores dentro do gamut de destino e recorta as de fora; o padrão para conferência
+    #[default]
+    RelativeColorimetric,
+    Saturation,
+    /// Como a relativa, sem adaptar o ponto branco
+    AbsoluteColorimetric,
+}
+
+impl RenderingIntent {
+    fn to_icm(self) -> u32 {
+        match self {
+            RenderingIntent::Perceptual => INTENT_PERCEPTUAL,
+            RenderingIntent::RelativeColorimetric => INTENT_RELATIVE_COLORIMETRIC,
+            RenderingIntent::Saturation => INTENT_SATURATION,
+            RenderingIntent::AbsoluteColorimetric => INTENT_ABSOLUTE_COLORIMETRIC,
+        }
+    }
+}
+
+/// Perfil ICC associado a um monitor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IccProfile {
+    path: PathBuf,
+}
+
+impl IccProfile {
+    /// Perfil do monitor com o nome GDI `device_name` (ex.: `\\.\DISPLAY1`, de
+    /// `OutputInfo::device_name`); `None` se nenhum perfil estiver associado
+    pub fn for_output(device_name: &str) -> Result<Option<Self>> {
+        let dc = unsafe { CreateDCW(w!("DISPLAY"), &HSTRING::from(device_name), PCWSTR::null(), None) };
+        if dc.is_invalid() {
+            return Err(format!("Falha ao abrir o DC do monitor {}", device_name).into());
+        }
+
+        let mut path = [0u16; 260];
+        let mut size = path.len() as u32;
+        let found = unsafe { GetICMProfileW(dc, &mut size, PWSTR(path.as_mut_ptr())) }.as_bool();
+        unsafe {
+            let _ = DeleteDC(dc);
+        }
+        Ok(found.then(|| Self::from_file(wide_to_string(&path))))
+    }
+
+    /// Perfil do monitor capturado
+    pub fn for_capture(capture: &DxgiCapture) -> Result<Option<Self>> {
+        let mut info = MONITORINFOEXW::default();
+        info.monitorInfo.cbSize = std::mem::size_of::<MONITORINFOEXW>() as u32;
+        let found = unsafe { GetMonitorInfoW(capture.monitor(), &mut info as *mut MONITORINFOEXW as *mut MONITORINFO) };
+        if !found.as_bool() {
+            return Err("Monitor da captura não encontrado".into());
+        }
+        Self::for_output(&wide_to_string(&info.szDevice))
+    }
+
+    /// Perfil a partir de um arquivo `.icc`/`.icm`
+    pub fn from_file(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Caminho do arquivo do perfil, para embutir o perfil em imagens
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Conteúdo do perfil
+    pub fn data(&self) -> Result<Vec<u8>> {
+        Ok(std::fs::read(&self.path)?)
+    }
+}
+
+/// Conversão dos valores de um monitor para sRGB pelo CMM do Windows
+pub struct IccTransform {
+    transform: isize,
+    profiles: [isize; 2],
+}
+
+// Transformações do ICM podem ser usadas a partir de qualquer thread
+unsafe impl Send for IccTransform {}
+
+impl IccTransform {
+    /// Conversão do espaço descrito por `profile` para sRGB
+    pub fn to_srgb(profile: &IccProfile, intent: RenderingIntent) -> Result<Self> {
+        let mut srgb = [0u16; 260];
+        let mut size = (srgb.len() * 2) as u32;
+        unsafe { GetStandardColorSpaceProfileW(PCWSTR::null(), LCS_SRGB, PWSTR(srgb.as_mut_ptr()), &mut size) }.ok()?;
+
+        let source = open_profile(&profile.path)?;
+        let target = match open_profile(Path::new(&wide_to_string(&srgb))) {
+            Ok(target) => target,
+            Err(e) => {
+                unsafe {
+                    let _ = CloseColorProfile(source);
+                }
+                return Err(e);
+            }
+        };
+
+        let intents = [intent.to_icm(), intent.to_icm()];
+        let transform = unsafe { CreateMultiProfileTransform(&[source, target], &intents, BEST_MODE, INDEX_DONT_CARE) };
+        // Em caso de falha o Drop ainda fecha os perfis
+        let converter = Self {
+            transform,
+            profiles: [source, target],
+        };
+        if transform == 0 {
+            return Err(format!("Falha ao criar a transformação de cor para {}", profile.path.display()).into());
+        }
+        Ok(converter)
+    }
+
+    /// Conversão do perfil do monitor capturado para sRGB; `None` se o monitor não tiver perfil
+    pub fn for_capture(capture: &DxgiCapture, intent: RenderingIntent) -> Result<Option<Self>> {
+        IccProfile::for_capture(capture)?
+            .map(|profile| Self::to_srgb(&profile, intent))
+            .transpose()
+    }
+
+    /// Cópia do quadro com as cores convertidas; o alfa é preservado
+    pub fn apply(&self, frame: &Frame) -> Result<Frame> {
+        let bitmap_format = match frame.format {
+            DXGI_FORMAT_B8G8R8A8_UNORM => BM_xRGBQUADS,
+            DXGI_FORMAT_R8G8B8A8_UNORM => BM_xBGRQUADS,
+            other => return Err(format!("Formato não suportado na conversão ICC: {:?}", other).into()),
+        };
+        if frame.color_space != ColorSpace::Srgb {
+            return Err("A conversão ICC só se aplica a quadros SDR de 8 bits".into());
+        }
+        let stride = frame.stride();
+        if frame.data.len() < stride * frame.height() as usize {
+            return Err("Buffer menor que o quadro".into());
+        }
+
+        let mut data = vec![0u8; frame.data.len()];
+        unsafe {
+            TranslateBitmapBits(
+                self.transform,
+                frame.data.as_ptr().cast(),
+                bitmap_format,
+                frame.width(),
+                frame.height(),
+                stride as u32,
+                data.as_mut_ptr().cast(),
+                bitmap_format,
+                stride as u32,
+                None,
+                LPARAM(0),
+            )
+            .ok()?;
+        }
+        // O ICM ignora o quarto byte
+        for (out, pixel) in data.chunks_exact_mut(4).zip(frame.data.chunks_exact(4)) {
+            out[3] = pixel[3];
+        }
+
+        Ok(Frame {
+            data,
+            region: frame.region,
+            format: frame.format,
+            color_space: ColorSpace::Srgb,
+            present_time: frame.present_time,
+            sequence: frame.sequence,
+            metadata: frame.metadata,
+        })
+    }
+}
+
+impl Drop for IccTransform {
+    fn drop(&mut self) {
+        unsafe {
+            if self.transform != 0 {
+                let _ = DeleteColorTransform(self.transform);
+            }
+            for profile in self.profiles {
+                let _ = CloseColorProfile(profile);
+            }
+        }
+    }
+}
+
+fn open_profile(path: &Path) -> Result<isize> {
+    let name = HSTRING::from(path.as_os_str());
+    let profile = PROFILE {
+        dwType: PROFILE_FILENAME,
+        pProfileData: name.as_ptr() as *mut _,
+        cbDataSize: ((name.len() + 1) * 2) as u32,
+    };
+    let handle = unsafe { OpenColorProfileW(&profile, PROFILE_READ, FILE_SHARE_READ.0, OPEN_EXISTING.0) };
+    if handle == 0 {
+        return Err(format!("Falha ao abrir o perfil de cor {}", path.display()).into());
+    }
+    Ok(handle)
+}
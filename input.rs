@@ -0,0 +1,352 @@
+//! Registro de eventos de mouse e teclado com timestamps QPC (feature `input`)
+//!
+//! Os eventos recebem o mesmo relógio do `present_time` e do `FrameMetadata::acquire_time`
+//! dos quadros, então ferramentas de latência clique-para-fóton e gravadores de tutoriais
+//! podem associar cada clique ou tecla ao quadro em que o efeito aparece. `InputRecorder`
+//! usa hooks de baixo nível (`WH_MOUSE_LL`, `WH_KEYBOARD_LL`) em uma thread própria; para
+//! consultas pontuais, `key_down` lê o estado atual com `GetAsyncKeyState`.
+//!
+//! Só o código virtual das teclas é registrado, nunca o texto digitado.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use windows::Win32::Foundation::{HINSTANCE, HWND, LPARAM, LRESULT, WPARAM};
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::System::Performance::{QueryPerformanceCounter, QueryPerformanceFrequency};
+use windows::Win32::System::Threading::GetCurrentThreadId;
+use windows::Win32::UI::Input::KeyboardAndMouse::GetAsyncKeyState;
+use windows::Win32::UI::WindowsAndMessaging::*;
+
+use crate::frame::Frame;
+use crate::geometry::Point;
+use crate::Result;
+
+thread_local! {
+    // Destino dos eventos dos hooks instalados nesta thread
+    static RECORDER: RefCell<Option<(Arc<Shared>, InputOptions)>> = RefCell::new(None);
+}
+
+/// Botão do mouse
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    X1,
+    X2,
+}
+
+/// O que aconteceu em um `InputEvent`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "snake_case"))]
+pub enum InputKind {
+    MouseMove,
+    MouseDown { button: MouseButton },
+    MouseUp { button: MouseButton },
+    /// Roda do mouse; `delta` em múltiplos de `WHEEL_DELTA` (120) por detente
+    Wheel { delta: i32, horizontal: bool },
+    /// Tecla pressionada (inclui repetições automáticas), pelo código virtual (`VK_*`)
+    KeyDown { key: u16 },
+    KeyUp { key: u16 },
+}
+
+/// Evento de entrada com o instante em que o sistema o entregou
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InputEvent {
+    pub kind: InputKind,
+    /// Instante em QPC, comparável a `Frame::present_time`
+    pub time: i64,
+    /// Posição do cursor no desktop virtual (em eventos de teclado, a do momento da tecla)
+    pub position: Point,
+    /// Gerado por `SendInput` ou similar em vez de um dispositivo físico
+    pub injected: bool,
+}
+
+impl InputEvent {
+    pub fn is_click(&self) -> bool {
+        matches!(self.kind, InputKind::MouseDown { .. })
+    }
+
+    /// Tempo entre o evento e o instante `qpc` (ex.: o `present_time` do quadro em que o
+    /// efeito apareceu); zero se `qpc` for anterior ao evento
+    pub fn delay_until(&self, qpc: i64) -> Duration {
+        let mut frequency = 0i64;
+        if unsafe { QueryPerformanceFrequency(&mut frequency) }.is_err() || frequency <= 0 || qpc <= self.time {
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64((qpc - self.time) as f64 / frequency as f64)
+    }
+}
+
+/// O que `InputRecorder` registra
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct InputOptions {
+    /// Botões e roda do mouse
+    pub mouse: bool,
+    /// Movimentos do mouse; desligado por padrão porque mouses de alta taxa geram até 1000
+    /// eventos por segundo
+    pub mouse_moves: bool,
+    pub keyboard: bool,
+    /// Eventos guardados até serem consumidos; cheio, o mais antigo é descartado
+    pub capacity: usize,
+}
+
+impl Default for InputOptions {
+    fn default() -> Self {
+        Self {
+            mouse: true,
+            mouse_moves: false,
+            keyboard: true,
+            capacity: 4096,
+        }
+    }
+}
+
+struct Shared {
+    events: Mutex<VecDeque<InputEvent>>,
+    dropped: AtomicU64,
+}
+
+impl Shared {
+    fn events(&self) -> MutexGuard<'_, VecDeque<InputEvent>> {
+        self.events.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Registra eventos de entrada do sistema inteiro enquanto existir.
+///
+/// Os hooks rodam em uma thread com loop de mensagens própria, encerrada no `Drop`. O
+/// Windows remove hooks de baixo nível que demoram a responder, então o callback só
+/// enfileira o evento.
+pub struct InputRecorder {
+    shared: Arc<Shared>,
+    thread_id: u32,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl InputRecorder {
+    pub fn start(options: InputOptions) -> Result<Self> {
+        let shared = Arc::new(Shared {
+            events: Mutex::new(VecDeque::with_capacity(options.capacity.min(4096))),
+            dropped: AtomicU64::new(0),
+        });
+        let (sender, receiver) = mpsc::channel();
+
+        let hook_shared = shared.clone();
+        let thread = thread::Builder::new()
+            .name("input-hooks".into())
+            .spawn(move || {
+                let hooks = match unsafe { install_hooks(&options) } {
+                    Ok(hooks) => hooks,
+                    Err(e) => {
+                        let _ = sender.send(Err(e.to_string()));
+                        return;
+                    }
+                };
+                RECORDER.with(|recorder| *recorder.borrow_mut() = Some((hook_shared, options)));
+                let _ = sender.send(Ok(unsafe { GetCurrentThreadId() }));
+
+                unsafe {
+                    let mut message = MSG::default();
+                    while GetMessageW(&mut message, HWND::default(), 0, 0).as_bool() {
+                        TranslateMessage(&message);
+                        DispatchMessageW(&message);
+                    }
+                    for hook in hooks {
+                        let _ = UnhookWindowsHookEx(hook);
+                    }
+                }
+                RECORDER.with(|recorder| *recorder.borrow_mut() = None);
+            })?;
+
+        match receiver.recv() {
+            Ok(Ok(thread_id)) => {
+                debug!("Registro de entrada iniciado");
+                Ok(Self {
+                    shared,
+                    thread_id,
+                    thread: Some(thread),
+                })
+            }
+            Ok(Err(e)) => {
+                let _ = thread.join();
+                Err(format!("Falha ao instalar os hooks de entrada: {}", e).into())
+            }
+            Err(_) => {
+                let _ = thread.join();
+                Err("A thread dos hooks de entrada terminou antes de instalá-los".into())
+            }
+        }
+    }
+
+    /// Todos os eventos registrados até agora, em ordem
+    pub fn drain(&self) -> Vec<InputEvent> {
+        self.shared.events().drain(..).collect()
+    }
+
+    /// Retira os eventos ocorridos até o instante `qpc`, inclusive
+    pub fn take_until(&self, qpc: i64) -> Vec<InputEvent> {
+        let mut events = self.shared.events();
+        let count = events.iter().take_while(|event| event.time <= qpc).count();
+        events.drain(..count).collect()
+    }
+
+    /// Retira os eventos ocorridos até a aquisição do quadro, ou seja, os que podem ter
+    /// afetado o seu conteúdo
+    pub fn take_for_frame(&self, frame: &Frame) -> Vec<InputEvent> {
+        let time = if frame.metadata.acquire_time != 0 {
+            frame.metadata.acquire_time
+        } else {
+            frame.present_time
+        };
+        self.take_until(time)
+    }
+
+    /// Eventos descartados por fila cheia
+    pub fn dropped(&self) -> u64 {
+        self.shared.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for InputRecorder {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = PostThreadMessageW(self.thread_id, WM_QUIT, WPARAM(0), LPARAM(0));
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Se a tecla (código virtual `VK_*`, incluindo os botões do mouse `VK_LBUTTON`...) está
+/// pressionada agora
+pub fn key_down(key: u16) -> bool {
+    unsafe { GetAsyncKeyState(key as i32) as u16 & 0x8000 != 0 }
+}
+
+unsafe fn install_hooks(options: &InputOptions) -> windows::core::Result<Vec<HHOOK>> {
+    let instance: HINSTANCE = GetModuleHandleW(None)?.into();
+    let mut hooks = Vec::new();
+    if options.mouse || options.mouse_moves {
+        hooks.push(SetWindowsHookExW(WH_MOUSE_LL, Some(mouse_proc), instance, 0)?);
+    }
+    if options.keyboard {
+        match SetWindowsHookExW(WH_KEYBOARD_LL, Some(keyboard_proc), instance, 0) {
+            Ok(hook) => hooks.push(hook),
+            Err(e) => {
+                for hook in hooks {
+                    let _ = UnhookWindowsHookEx(hook);
+                }
+                return Err(e);
+            }
+        }
+    }
+    Ok(hooks)
+}
+
+fn record(kind: InputKind, position: Option<Point>, injected: bool) {
+    let mut time = 0i64;
+    unsafe {
+        let _ = QueryPerformanceCounter(&mut time);
+    }
+
+    RECORDER.with(|recorder| {
+        let recorder = recorder.borrow();
+        let Some((shared, options)) = recorder.as_ref() else {
+            return;
+        };
+        let wanted = match kind {
+            InputKind::MouseMove => options.mouse_moves,
+            InputKind::KeyDown { .. } | InputKind::KeyUp { .. } => options.keyboard,
+            _ => options.mouse,
+        };
+        if !wanted {
+            return;
+        }
+
+        let position = position.unwrap_or_else(cursor_position);
+        let mut events = shared.events();
+        while events.len() >= options.capacity.max(1) {
+            events.pop_front();
+            shared.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+        events.push_back(InputEvent {
+            kind,
+            time,
+            position,
+            injected,
+        });
+    });
+}
+
+fn cursor_position() -> Point {
+    let mut point = Default::default();
+    unsafe {
+        let _ = GetCursorPos(&mut point);
+    }
+    Point::new(point.x, point.y)
+}
+
+unsafe extern "system" fn mouse_proc(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if code == HC_ACTION as i32 {
+        let info = &*(lparam.0 as *const MSLLHOOKSTRUCT);
+        let high_word = (info.mouseData >> 16) as u16;
+        let x_button = if high_word == XBUTTON2 as u16 {
+            MouseButton::X2
+        } else {
+            MouseButton::X1
+        };
+        let kind = match wparam.0 as u32 {
+            WM_MOUSEMOVE => Some(InputKind::MouseMove),
+            WM_LBUTTONDOWN => Some(InputKind::MouseDown { button: MouseButton::Left }),
+            WM_LBUTTONUP => Some(InputKind::MouseUp { button: MouseButton::Left }),
+            WM_RBUTTONDOWN => Some(InputKind::MouseDown { button: MouseButton::Right }),
+            WM_RBUTTONUP => Some(InputKind::MouseUp { button: MouseButton::Right }),
+            WM_MBUTTONDOWN => Some(InputKind::MouseDown { button: MouseButton::Middle }),
+            WM_MBUTTONUP => Some(InputKind::MouseUp { button: MouseButton::Middle }),
+            WM_XBUTTONDOWN => Some(InputKind::MouseDown { button: x_button }),
+            WM_XBUTTONUP => Some(InputKind::MouseUp { button: x_button }),
+            WM_MOUSEWHEEL => Some(InputKind::Wheel {
+                delta: high_word as i16 as i32,
+                horizontal: false,
+            }),
+            WM_MOUSEHWHEEL => Some(InputKind::Wheel {
+                delta: high_word as i16 as i32,
+                horizontal: true,
+            }),
+            _ => None,
+        };
+        if let Some(kind) = kind {
+            record(kind, Some(Point::new(info.pt.x, info.pt.y)), info.flags & LLMHF_INJECTED != 0);
+        }
+    }
+    CallNextHookEx(HHOOK::default(), code, wparam, lparam)
+}
+
+unsafe extern "system" fn keyboard_proc(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if code == HC_ACTION as i32 {
+        let info = &*(lparam.0 as *const KBDLLHOOKSTRUCT);
+        let key = info.vkCode as u16;
+        let kind = match wparam.0 as u32 {
+            WM_KEYDOWN | WM_SYSKEYDOWN => Some(InputKind::KeyDown { key }),
+            WM_KEYUP | WM_SYSKEYUP => Some(InputKind::KeyUp { key }),
+            _ => None,
+        };
+        if let Some(kind) = kind {
+            record(kind, None, (info.flags & LLKHF_INJECTED).0 != 0);
+        }
+    }
+    CallNextHookEx(HHOOK::default(), code, wparam, lparam)
+}
@@ -0,0 +1,178 @@
+//! Codificação de quadros em JPEG pela libjpeg-turbo (feature `jpeg`)
+//!
+//! Os encoders JPEG em Rust puro ficam várias vezes abaixo da vazão que a captura de tela
+//! exige (4K a 30 fps ou mais); a libjpeg-turbo codifica direto do BGRA capturado com SIMD.
+//! `encode_jpeg` e `save_jpeg` servem capturas avulsas; para fluxos, `JpegEncoder` reutiliza o
+//! compressor entre quadros e `JpegSink` entrega cada quadro codificado a um callback como
+//! saída de um `CaptureStream`/`FanOut` (ex.: MJPEG por HTTP).
+//!
+//! O `turbojpeg` compila a libjpeg-turbo por padrão; uma build da mozjpeg, que mantém a mesma
+//! API TurboJPEG, pode ser ligada no lugar dela para arquivos menores ao mesmo custo de
+//! integração. Quadros FP16 e de 10 bits são levados para sRGB de 8 bits antes da codificação.
+
+use std::fs;
+use std::path::Path;
+
+use turbojpeg::{Compressor, Image, PixelFormat, Subsamp};
+use windows::Win32::Graphics::Dxgi::Common::{
+    DXGI_FORMAT_B8G8R8A8_UNORM, DXGI_FORMAT_B8G8R8A8_UNORM_SRGB, DXGI_FORMAT_R8G8B8A8_UNORM,
+    DXGI_FORMAT_R8G8B8A8_UNORM_SRGB,
+};
+
+use crate::frame::Frame;
+use crate::outputs::ColorSpace;
+use crate::sink::FrameSink;
+use crate::Result;
+
+/// Amostragem do croma
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum ChromaSubsampling {
+    /// Croma completo: texto colorido e bordas finas sem manchas
+    Yuv444,
+    /// Metade da resolução horizontal
+    Yuv422,
+    /// Metade da resolução nas duas direções, o menor arquivo (padrão)
+    #[default]
+    Yuv420,
+    /// Só luminância
+    Gray,
+}
+
+impl ChromaSubsampling {
+    fn subsamp(self) -> Subsamp {
+        match self {
+            ChromaSubsampling::Yuv444 => Subsamp::None,
+            ChromaSubsampling::Yuv422 => Subsamp::Sub2x1,
+            ChromaSubsampling::Yuv420 => Subsamp::Sub2x2,
+            ChromaSubsampling::Gray => Subsamp::Gray,
+        }
+    }
+}
+
+/// Parâmetros de codificação
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct JpegOptions {
+    /// Qualidade de 1 a 100 (escala da libjpeg)
+    pub quality: u8,
+    pub subsampling: ChromaSubsampling,
+    /// Tabelas de Huffman otimizadas por imagem: alguns por cento menor, um pouco mais lento
+    pub optimize: bool,
+}
+
+impl Default for JpegOptions {
+    fn default() -> Self {
+        Self {
+            quality: 85,
+            subsampling: ChromaSubsampling::Yuv420,
+            optimize: false,
+        }
+    }
+}
+
+/// Compressor configurado, reutilizado entre quadros
+pub struct JpegEncoder {
+    compressor: Compressor,
+    options: JpegOptions,
+}
+
+impl JpegEncoder {
+    pub fn new(options: JpegOptions) -> Result<Self> {
+        let mut encoder = Self {
+            compressor: Compressor::new()?,
+            options,
+        };
+        encoder.set_options(options)?;
+        Ok(encoder)
+    }
+
+    pub fn options(&self) -> JpegOptions {
+        self.options
+    }
+
+    /// Troca qualidade, amostragem ou otimização para os próximos quadros
+    pub fn set_options(&mut self, options: JpegOptions) -> Result<()> {
+        self.compressor.set_quality(options.quality.clamp(1, 100) as i32)?;
+        self.compressor.set_subsamp(options.subsampling.subsamp())?;
+        self.compressor.set_optimize(options.optimize)?;
+        self.options = options;
+        Ok(())
+    }
+
+    /// Codifica o quadro em um JPEG em memória
+    pub fn encode(&mut self, frame: &Frame) -> Result<Vec<u8>> {
+        let (width, height) = (frame.width() as usize, frame.height() as usize);
+        if width == 0 || height == 0 {
+            return Err("Quadro vazio".into());
+        }
+
+        let converted;
+        let (frame, format) = match frame.format {
+            DXGI_FORMAT_B8G8R8A8_UNORM | DXGI_FORMAT_B8G8R8A8_UNORM_SRGB => (frame, PixelFormat::BGRA),
+            DXGI_FORMAT_R8G8B8A8_UNORM | DXGI_FORMAT_R8G8B8A8_UNORM_SRGB => (frame, PixelFormat::RGBA),
+            _ => {
+                converted = frame.to_color_space(ColorSpace::Srgb)?;
+                (&converted, PixelFormat::BGRA)
+            }
+        };
+        let pitch = frame.stride();
+        if frame.data.len() < pitch * height {
+            return Err("Buffer menor que o quadro".into());
+        }
+
+        let image = Image {
+            pixels: &frame.data[..pitch * height],
+            width,
+            pitch,
+            height,
+            format,
+        };
+        Ok(self.compressor.compress_to_vec(image)?)
+    }
+}
+
+/// Codifica o quadro em um JPEG em memória
+pub fn encode_jpeg(frame: &Frame, options: &JpegOptions) -> Result<Vec<u8>> {
+    JpegEncoder::new(*options)?.encode(frame)
+}
+
+/// Grava o quadro como JPEG em `path`
+pub fn save_jpeg(frame: &Frame, path: impl AsRef<Path>, options: &JpegOptions) -> Result<()> {
+    fs::write(path, encode_jpeg(frame, options)?)?;
+    Ok(())
+}
+
+/// Saída que codifica cada quadro e o entrega a `callback` com o quadro de origem
+pub struct JpegSink<F> {
+    encoder: JpegEncoder,
+    callback: F,
+}
+
+impl<F> JpegSink<F>
+where
+    F: FnMut(Vec<u8>, &Frame) -> Result<()> + Send,
+{
+    pub fn new(options: JpegOptions, callback: F) -> Result<Self> {
+        Ok(Self {
+            encoder: JpegEncoder::new(options)?,
+            callback,
+        })
+    }
+
+    pub fn encoder(&mut self) -> &mut JpegEncoder {
+        &mut self.encoder
+    }
+}
+
+impl<F> FrameSink for JpegSink<F>
+where
+    F: FnMut(Vec<u8>, &Frame) -> Result<()> + Send,
+{
+    fn on_frame(&mut self, frame: &Frame) -> Result<()> {
+        let jpeg = self.encoder.encode(frame)?;
+        (self.callback)(jpeg, frame)
+    }
+}
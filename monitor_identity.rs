@@ -0,0 +1,165 @@
+//! Identidade estável dos monitores físicos (APIs `DISPLAYCONFIG`)
+//!
+//! Os índices de adaptador/output do DXGI e os nomes GDI (`\\.\DISPLAY2`) mudam quando um
+//! monitor é desconectado, quando a ordem de detecção muda no boot ou quando o notebook vai para
+//! a dock. Uma configuração salva com eles passa a capturar outro monitor. `QueryDisplayConfig`
+//! e `DisplayConfigGetDeviceInfo` dão, para cada monitor ativo, o caminho do dispositivo (que
+//! inclui o id PnP do EDID e a instância da conexão) e o nome amigável lido do EDID
+//! ("DELL U2720Q").
+//!
+//! `display_name` é o nome amigável, com " #1", " #2"... quando há mais de um monitor com o
+//! mesmo nome, numerados na ordem dos caminhos de dispositivo para que a numeração não dependa
+//! da ordem de enumeração. Em modo clone um output DXGI mostra vários monitores; cada um tem a
+//! sua identidade e qualquer um deles localiza o output.
+
+use windows::Win32::Devices::Display::*;
+use windows::Win32::Foundation::{ERROR_INSUFFICIENT_BUFFER, ERROR_SUCCESS};
+
+use crate::error::CaptureResult;
+use crate::outputs::wide_to_string;
+
+/// Bit `edidIdsValid` de `DISPLAYCONFIG_TARGET_DEVICE_NAME_FLAGS`
+const EDID_IDS_VALID: u32 = 0x4;
+
+/// Monitor físico ativo
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MonitorIdentity {
+    /// Caminho do dispositivo (`\\?\DISPLAY#DELA0A5#...`): único e estável enquanto o monitor
+    /// continuar na mesma conexão
+    pub device_path: String,
+    /// Nome amigável do EDID (ex.: "DELL U2720Q"); vazio em alguns painéis internos
+    pub friendly_name: String,
+    /// Id PnP do fabricante decodificado do EDID (ex.: "DEL")
+    pub manufacturer: Option<String>,
+    /// Código do produto no EDID
+    pub product_code: Option<u16>,
+    /// Instância da conexão no adaptador (monitores iguais ligados a portas diferentes)
+    pub connector_instance: u32,
+    /// Nome único entre os monitores conectados (ex.: "DELL U2720Q #2"), para
+    /// `DxgiCapture::for_output_by_name`
+    pub display_name: String,
+    /// Nome GDI atual do output que mostra o monitor (`\\.\DISPLAY2`); muda com a topologia
+    pub gdi_device_name: String,
+}
+
+impl MonitorIdentity {
+    /// `name` é o `display_name` ou o `device_path` deste monitor (sem diferenciar maiúsculas)
+    pub fn matches(&self, name: &str) -> bool {
+        self.display_name.eq_ignore_ascii_case(name) || self.device_path.eq_ignore_ascii_case(name)
+    }
+}
+
+/// Identidades de todos os monitores ativos, ordenadas por `display_name`
+pub fn monitor_identities() -> CaptureResult<Vec<MonitorIdentity>> {
+    let mut paths = Vec::new();
+    let mut modes = Vec::new();
+    // A topologia pode mudar entre o cálculo dos tamanhos e a consulta
+    loop {
+        let mut path_count = 0;
+        let mut mode_count = 0;
+        unsafe { GetDisplayConfigBufferSizes(QDC_ONLY_ACTIVE_PATHS, &mut path_count, &mut mode_count)? };
+        paths.resize(path_count as usize, DISPLAYCONFIG_PATH_INFO::default());
+        modes.resize(mode_count as usize, DISPLAYCONFIG_MODE_INFO::default());
+        let result = unsafe {
+            QueryDisplayConfig(
+                QDC_ONLY_ACTIVE_PATHS,
+                &mut path_count,
+                paths.as_mut_ptr(),
+                &mut mode_count,
+                modes.as_mut_ptr(),
+                None,
+            )
+        };
+        if let Err(err) = result {
+            if err.code() == ERROR_INSUFFICIENT_BUFFER.to_hresult() {
+                continue;
+            }
+            return Err(err.into());
+        }
+        paths.truncate(path_count as usize);
+        break;
+    }
+
+    let mut identities: Vec<MonitorIdentity> = paths.iter().filter_map(query_identity).collect();
+    assign_display_names(&mut identities);
+    identities.sort_by(|a, b| a.display_name.cmp(&b.display_name));
+    Ok(identities)
+}
+
+/// Nome do monitor e do output de um caminho; `None` se o driver não responde
+fn query_identity(path: &DISPLAYCONFIG_PATH_INFO) -> Option<MonitorIdentity> {
+    let mut target = DISPLAYCONFIG_TARGET_DEVICE_NAME {
+        header: DISPLAYCONFIG_DEVICE_INFO_HEADER {
+            r#type: DISPLAYCONFIG_DEVICE_INFO_GET_TARGET_NAME,
+            size: std::mem::size_of::<DISPLAYCONFIG_TARGET_DEVICE_NAME>() as u32,
+            adapterId: path.targetInfo.adapterId,
+            id: path.targetInfo.id,
+        },
+        ..Default::default()
+    };
+    let mut source = DISPLAYCONFIG_SOURCE_DEVICE_NAME {
+        header: DISPLAYCONFIG_DEVICE_INFO_HEADER {
+            r#type: DISPLAYCONFIG_DEVICE_INFO_GET_SOURCE_NAME,
+            size: std::mem::size_of::<DISPLAYCONFIG_SOURCE_DEVICE_NAME>() as u32,
+            adapterId: path.sourceInfo.adapterId,
+            id: path.sourceInfo.id,
+        },
+        ..Default::default()
+    };
+    unsafe {
+        if DisplayConfigGetDeviceInfo(&mut target.header) != ERROR_SUCCESS.0 as i32
+            || DisplayConfigGetDeviceInfo(&mut source.header) != ERROR_SUCCESS.0 as i32
+        {
+            debug!("DisplayConfigGetDeviceInfo falhou para o alvo {}", path.targetInfo.id);
+            return None;
+        }
+    }
+
+    let edid_valid = unsafe { target.flags.Anonymous.value } & EDID_IDS_VALID != 0;
+    Some(MonitorIdentity {
+        device_path: wide_to_string(&target.monitorDevicePath),
+        friendly_name: wide_to_string(&target.monitorFriendlyDeviceName),
+        manufacturer: edid_valid.then(|| decode_manufacturer(target.edidManufactureId)),
+        product_code: edid_valid.then_some(target.edidProductCodeId),
+        connector_instance: target.connectorInstance,
+        display_name: String::new(),
+        gdi_device_name: wide_to_string(&source.viewGdiDeviceName),
+    })
+}
+
+/// Três letras comprimidas em 5 bits cada, com os bytes na ordem do EDID (big-endian)
+fn decode_manufacturer(id: u16) -> String {
+    let id = id.swap_bytes();
+    [10, 5, 0]
+        .iter()
+        .map(|shift| (b'A' - 1 + ((id >> shift) & 0x1F) as u8) as char)
+        .collect()
+}
+
+/// Preenche `display_name`, numerando os monitores de mesmo nome na ordem do `device_path`
+fn assign_display_names(identities: &mut [MonitorIdentity]) {
+    identities.sort_by(|a, b| a.device_path.cmp(&b.device_path));
+    let base_names: Vec<String> = identities.iter().map(base_name).collect();
+    for (index, identity) in identities.iter_mut().enumerate() {
+        let base = &base_names[index];
+        let same_name = base_names.iter().filter(|name| *name == base).count();
+        identity.display_name = if same_name > 1 {
+            let position = base_names[..index].iter().filter(|name| *name == base).count() + 1;
+            format!("{} #{}", base, position)
+        } else {
+            base.clone()
+        };
+    }
+}
+
+/// Nome amigável ou, sem ele, fabricante e produto do EDID (ex.: "SHP 14D1")
+fn base_name(identity: &MonitorIdentity) -> String {
+    if !identity.friendly_name.is_empty() {
+        return identity.friendly_name.clone();
+    }
+    match (&identity.manufacturer, identity.product_code) {
+        (Some(manufacturer), Some(product)) => format!("{} {:04X}", manufacturer, product),
+        _ => identity.gdi_device_name.clone(),
+    }
+}
@@ -0,0 +1,235 @@
+//! Exportação em NUT com vídeo bruto, para alimentar o ffmpeg sem conversão de cor
+//!
+//! Ao contrário do Y4M, os quadros BGRA/RGBA vão como foram capturados (fourcc `BGRA`/`RGBA`),
+//! sem a subamostragem do croma. Todos os quadros são chave e cada um é precedido de um
+//! syncpoint, como o ffmpeg faz com quadros maiores que `max_distance`.
+//!
+//! Exemplo: gravar em stdout e encadear com `ffmpeg -f nut -i - saida.mkv`.
+
+use std::io::{self, Write};
+
+use windows::Win32::Graphics::Dxgi::Common::DXGI_FORMAT;
+
+use crate::pixel_format::PixelFormat;
+use crate::Result;
+
+const FILE_ID: &[u8] = b"nut/multimedia container\0";
+const MAIN_STARTCODE: u64 = 0x7A56_1F5F_04AD + (((b'N' as u64) << 8 | b'M' as u64) << 48);
+const STREAM_STARTCODE: u64 = 0x1140_5BF2_F9DB + (((b'N' as u64) << 8 | b'S' as u64) << 48);
+const SYNCPOINT_STARTCODE: u64 = 0xE4AD_EECA_4569 + (((b'N' as u64) << 8 | b'K' as u64) << 48);
+
+const VERSION: u64 = 3;
+/// Distância máxima entre syncpoints anunciada aos leitores
+const MAX_DISTANCE: u64 = 32768;
+const MSB_PTS_SHIFT: u32 = 7;
+/// Pacotes maiores que isto levam também um checksum do cabeçalho
+const HEADER_CHECKSUM_THRESHOLD: u64 = 4096;
+
+const FLAG_KEY: u64 = 1;
+const FLAG_CODED_PTS: u64 = 8;
+const FLAG_SIZE_MSB: u64 = 32;
+const FLAG_CHECKSUM: u64 = 64;
+const FLAG_INVALID: u64 = 8192;
+/// Único código de quadro válido: chave, pts completo, tamanho explícito e checksum
+const FRAME_CODE: u8 = 0;
+
+/// Escreve quadros capturados como um fluxo NUT com uma trilha de vídeo bruto
+pub struct NutWriter<W: Write> {
+    writer: W,
+    fourcc: &'static [u8; 4],
+    width: u32,
+    height: u32,
+    frame_size: usize,
+    time_base: (u64, u64),
+    header_written: bool,
+    // Bytes escritos até agora, para os ponteiros entre syncpoints
+    position: u64,
+    last_syncpoint: Option<u64>,
+    frames: u64,
+}
+
+impl<W: Write> NutWriter<W> {
+    /// `format` precisa ser BGRA ou RGBA 8-bit
+    pub fn new(writer: W, format: DXGI_FORMAT, width: u32, height: u32, fps_num: u32, fps_den: u32) -> Result<Self> {
+        let fourcc = match PixelFormat::from_dxgi(format) {
+            Some(PixelFormat::Bgra8) => b"BGRA",
+            Some(PixelFormat::Rgba8) => b"RGBA",
+            _ => return Err(format!("Formato não suportado para NUT: {:?}", format).into()),
+        };
+        if width == 0 || height == 0 {
+            return Err("Dimensões inválidas para NUT".into());
+        }
+        if fps_num == 0 || fps_den == 0 {
+            return Err("Taxa de quadros inválida para NUT".into());
+        }
+
+        // Cada quadro dura uma unidade da base de tempo, que precisa estar reduzida
+        let divisor = gcd(fps_num as u64, fps_den as u64);
+        Ok(Self {
+            writer,
+            fourcc,
+            width,
+            height,
+            frame_size: PixelFormat::Bgra8.buffer_size(width, height),
+            time_base: (fps_den as u64 / divisor, fps_num as u64 / divisor),
+            header_written: false,
+            position: 0,
+            last_syncpoint: None,
+            frames: 0,
+        })
+    }
+
+    /// Escreve o quadro como veio da captura, com o pts do próximo quadro da sequência
+    pub fn write_frame(&mut self, pixels: &[u8]) -> Result<()> {
+        if pixels.len() != self.frame_size {
+            return Err(format!(
+                "Tamanho de quadro inválido: esperado {} bytes, recebido {}",
+                self.frame_size,
+                pixels.len()
+            )
+            .into());
+        }
+
+        if !self.header_written {
+            self.write_headers()?;
+            self.header_written = true;
+        }
+
+        let pts = self.frames;
+        let mut syncpoint = Vec::new();
+        put_v(&mut syncpoint, pts);
+        put_v(&mut syncpoint, self.last_syncpoint.map_or(0, |last| (self.position - last) >> 4));
+        self.last_syncpoint = Some(self.position);
+        self.write_packet(SYNCPOINT_STARTCODE, &syncpoint)?;
+
+        // O pts vai completo (somado a 1 << msb_pts_shift) para não depender do anterior
+        let mut header = vec![FRAME_CODE];
+        put_v(&mut header, pts + (1 << MSB_PTS_SHIFT));
+        put_v(&mut header, pixels.len() as u64);
+        let checksum = crc32(&header);
+        header.extend_from_slice(&checksum.to_be_bytes());
+        self.put(&header)?;
+        self.put(pixels)?;
+
+        self.frames += 1;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Descarrega o fluxo e devolve o escritor subjacente
+    pub fn into_inner(mut self) -> Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn write_headers(&mut self) -> io::Result<()> {
+        self.put(FILE_ID)?;
+
+        let mut main = Vec::new();
+        put_v(&mut main, VERSION);
+        put_v(&mut main, 1); // trilhas
+        put_v(&mut main, MAX_DISTANCE);
+        put_v(&mut main, 1); // bases de tempo
+        put_v(&mut main, self.time_base.0);
+        put_v(&mut main, self.time_base.1);
+        // Tabela de códigos de quadro: o 0 é o único válido; os outros 254 (todos menos 'N')
+        // são marcados inválidos
+        for (flags, size_mul, count) in [
+            (FLAG_KEY | FLAG_CODED_PTS | FLAG_SIZE_MSB | FLAG_CHECKSUM, 1, 1),
+            (FLAG_INVALID, 256, 254),
+        ] {
+            put_v(&mut main, flags);
+            put_v(&mut main, 6); // campos explícitos: pts, mul, trilha, lsb, reservados e contagem
+            put_s(&mut main, 0);
+            put_v(&mut main, size_mul);
+            put_v(&mut main, 0);
+            put_v(&mut main, 0);
+            put_v(&mut main, 0);
+            put_v(&mut main, count);
+        }
+        put_v(&mut main, 0); // nenhum cabeçalho de elisão
+        self.write_packet(MAIN_STARTCODE, &main)?;
+
+        let mut stream = Vec::new();
+        put_v(&mut stream, 0); // id da trilha
+        put_v(&mut stream, 0); // classe: vídeo
+        put_v(&mut stream, 4);
+        stream.extend_from_slice(self.fourcc);
+        put_v(&mut stream, 0); // base de tempo
+        put_v(&mut stream, MSB_PTS_SHIFT as u64);
+        put_v(&mut stream, 1); // max_pts_distance; irrelevante com checksum em todo quadro
+        put_v(&mut stream, 0); // atraso de decodificação
+        put_v(&mut stream, 0); // flags
+        put_v(&mut stream, 0); // dados específicos do codec
+        put_v(&mut stream, self.width as u64);
+        put_v(&mut stream, self.height as u64);
+        put_v(&mut stream, 1); // pixels quadrados
+        put_v(&mut stream, 1);
+        put_v(&mut stream, 0); // espaço de cor não informado
+        self.write_packet(STREAM_STARTCODE, &stream)
+    }
+
+    /// Pacote com startcode, tamanho e checksum do conteúdo
+    fn write_packet(&mut self, startcode: u64, payload: &[u8]) -> io::Result<()> {
+        let forward_ptr = payload.len() as u64 + 4;
+        let mut header = startcode.to_be_bytes().to_vec();
+        put_v(&mut header, forward_ptr);
+        if forward_ptr > HEADER_CHECKSUM_THRESHOLD {
+            let checksum = crc32(&header);
+            header.extend_from_slice(&checksum.to_be_bytes());
+        }
+
+        self.put(&header)?;
+        self.put(payload)?;
+        self.put(&crc32(payload).to_be_bytes())
+    }
+
+    fn put(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.writer.write_all(bytes)?;
+        self.position += bytes.len() as u64;
+        Ok(())
+    }
+}
+
+/// Inteiro sem sinal em grupos de 7 bits, do mais significativo para o menos
+fn put_v(buf: &mut Vec<u8>, value: u64) {
+    let mut groups = 1;
+    while groups < 10 && value >> (7 * groups) != 0 {
+        groups += 1;
+    }
+    for group in (1..groups).rev() {
+        buf.push(0x80 | ((value >> (7 * group)) & 0x7F) as u8);
+    }
+    buf.push((value & 0x7F) as u8);
+}
+
+/// Inteiro com sinal: positivos viram ímpares, zero e negativos viram pares
+fn put_s(buf: &mut Vec<u8>, value: i64) {
+    let magnitude = value.unsigned_abs();
+    put_v(buf, if value > 0 { 2 * magnitude - 1 } else { 2 * magnitude });
+}
+
+/// CRC-32 do NUT: polinômio 0x04C11DB7, sem reflexão, valor inicial 0
+fn crc32(data: &[u8]) -> u32 {
+    data.iter().fold(0u32, |crc, &byte| {
+        (0..8).fold(crc ^ ((byte as u32) << 24), |crc, _| {
+            if crc & 0x8000_0000 != 0 {
+                (crc << 1) ^ 0x04C1_1DB7
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
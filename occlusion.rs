@@ -0,0 +1,241 @@
+//! Detecção de janelas sobrepostas a uma janela alvo
+//!
+//! A Desktop Duplication captura o monitor já composto: ao gravar a região de uma janela,
+//! qualquer outra janela por cima (notificações, chats, o próprio gerenciador de tarefas)
+//! entra na gravação. `detect(hwnd)` percorre as janelas de nível superior acima do alvo
+//! na ordem z (`EnumWindows`) e informa onde cada uma cobre o alvo, para que o consumidor
+//! pause a gravação, avise o usuário ou mascare essas áreas.
+//!
+//! Janelas invisíveis, minimizadas, ocultas pelo DWM (cloaked, ex.: apps UWP suspensos ou de
+//! outras áreas de trabalho virtuais) e excluídas da captura (`WDA_EXCLUDEFROMCAPTURE`) não
+//! aparecem na imagem e são ignoradas. Os retângulos estão no desktop virtual, em pixels
+//! físicos para processos com DPI por monitor.
+
+use std::ffi::c_void;
+use std::mem;
+
+use windows::Win32::Foundation::{BOOL, ERROR_INVALID_WINDOW_HANDLE, HWND, LPARAM};
+use windows::Win32::Graphics::Dwm::{DwmGetWindowAttribute, DWMWA_CLOAKED};
+use windows::Win32::UI::WindowsAndMessaging::*;
+
+use crate::annotate::{annotate, Annotation, Color};
+use crate::coords::window_rect;
+use crate::error::CaptureResult;
+use crate::frame::Frame;
+use crate::geometry::{Point, Rect, Size};
+
+/// Janela que cobre parte do alvo
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Occluder {
+    pub hwnd: HWND,
+    pub process_id: u32,
+    /// Retângulo inteiro da janela, no desktop virtual
+    pub bounds: Rect,
+    /// Parte do alvo coberta por ela, no desktop virtual
+    pub covered: Rect,
+}
+
+/// Situação da janela alvo em um instante
+#[derive(Debug, Clone, PartialEq)]
+pub struct Occlusion {
+    /// Retângulo visível do alvo (sem a sombra invisível das bordas), no desktop virtual
+    pub target: Rect,
+    /// O alvo está minimizado ou invisível; nesse caso `occluders` fica vazio
+    pub hidden: bool,
+    /// Janelas acima do alvo que o cobrem, da mais alta para a mais baixa na ordem z
+    pub occluders: Vec<Occluder>,
+    /// Fração da área do alvo coberta por alguma janela (0.0 a 1.0), sem contar sobreposições duas vezes
+    pub covered_fraction: f64,
+}
+
+impl Occlusion {
+    /// Alguma parte do alvo está encoberta ou ele não está visível
+    pub fn is_occluded(&self) -> bool {
+        self.hidden || !self.occluders.is_empty()
+    }
+
+    /// Áreas cobertas em coordenadas do monitor com origem `output_origin`
+    /// (`DxgiCapture::output_origin`), prontas para comparar com a região capturada
+    pub fn regions(&self, output_origin: Point) -> Vec<Rect> {
+        self.occluders
+            .iter()
+            .filter_map(|occluder| {
+                let covered = occluder.covered;
+                Rect::from_virtual(Point::new(covered.left, covered.top), covered.size(), output_origin).ok()
+            })
+            .collect()
+    }
+
+    /// Pinta as áreas cobertas com `color` em um quadro capturado do monitor com origem
+    /// `output_origin`, para que o conteúdo das outras janelas não vaze na gravação
+    pub fn mask(&self, frame: &mut Frame, output_origin: Point, color: Color) -> crate::Result<()> {
+        let annotations: Vec<_> = self
+            .regions(output_origin)
+            .into_iter()
+            .filter_map(|region| region.intersect(&frame.region))
+            .map(|region| Annotation::Highlight {
+                rect: Rect::new(region.left - frame.region.left, region.top - frame.region.top, region.width, region.height),
+                color,
+            })
+            .collect();
+        if annotations.is_empty() {
+            return Ok(());
+        }
+        annotate(frame, &annotations)
+    }
+}
+
+/// Verifica quais janelas estão por cima de `target` e que parte dele cobrem
+pub fn detect(target: HWND) -> CaptureResult<Occlusion> {
+    unsafe {
+        if !IsWindow(target).as_bool() {
+            return Err(windows::core::Error::from(ERROR_INVALID_WINDOW_HANDLE.to_hresult()).into());
+        }
+    }
+    let root = unsafe { GetAncestor(target, GA_ROOT) };
+    let root = if root.0 == 0 { target } else { root };
+    let target_rect = window_rect(target)?;
+
+    if unsafe { !IsWindowVisible(root).as_bool() || IsIconic(root).as_bool() } || target_rect.is_empty() {
+        return Ok(Occlusion {
+            target: target_rect,
+            hidden: true,
+            occluders: Vec::new(),
+            covered_fraction: 0.0,
+        });
+    }
+
+    let mut occluders = Vec::new();
+    for hwnd in windows_above(root) {
+        if !appears_in_capture(hwnd) {
+            continue;
+        }
+        let Ok(bounds) = window_rect(hwnd) else {
+            continue;
+        };
+        if let Some(covered) = bounds.intersect(&target_rect) {
+            let mut process_id = 0;
+            unsafe {
+                GetWindowThreadProcessId(hwnd, Some(&mut process_id));
+            }
+            occluders.push(Occluder {
+                hwnd,
+                process_id,
+                bounds,
+                covered,
+            });
+        }
+    }
+
+    let covered: Vec<Rect> = occluders.iter().map(|occluder| occluder.covered).collect();
+    let covered_fraction = (union_area(&covered) as f64 / target_rect.size().area().max(1) as f64).min(1.0);
+    Ok(Occlusion {
+        target: target_rect,
+        hidden: false,
+        occluders,
+        covered_fraction,
+    })
+}
+
+/// Acompanha a oclusão de uma janela e informa só as mudanças
+#[derive(Debug)]
+pub struct OcclusionMonitor {
+    target: HWND,
+    last: Option<Occlusion>,
+}
+
+impl OcclusionMonitor {
+    pub fn new(target: HWND) -> Self {
+        Self { target, last: None }
+    }
+
+    pub fn target(&self) -> HWND {
+        self.target
+    }
+
+    /// Última situação observada
+    pub fn last(&self) -> Option<&Occlusion> {
+        self.last.as_ref()
+    }
+
+    /// Consulta a ordem z de novo; retorna `Some` na primeira chamada e quando a situação
+    /// mudou (janela nova por cima, movida, fechada, alvo minimizado)
+    pub fn poll(&mut self) -> CaptureResult<Option<&Occlusion>> {
+        let current = detect(self.target)?;
+        if self.last.as_ref() == Some(&current) {
+            return Ok(None);
+        }
+        if current.is_occluded() {
+            debug!(
+                "Janela {:?} coberta em {:.0}% por {} janela(s)",
+                self.target,
+                current.covered_fraction * 100.0,
+                current.occluders.len()
+            );
+        }
+        self.last = Some(current);
+        Ok(self.last.as_ref())
+    }
+}
+
+/// Janelas de nível superior acima de `root` na ordem z, da mais alta para a mais baixa
+fn windows_above(root: HWND) -> Vec<HWND> {
+    unsafe extern "system" fn collect(hwnd: HWND, lparam: LPARAM) -> BOOL {
+        let windows = &mut *(lparam.0 as *mut Vec<HWND>);
+        windows.push(hwnd);
+        BOOL(1)
+    }
+
+    let mut windows: Vec<HWND> = Vec::new();
+    unsafe {
+        // `EnumWindows` percorre do topo para o fundo da ordem z
+        if let Err(e) = EnumWindows(Some(collect), LPARAM(&mut windows as *mut Vec<HWND> as isize)) {
+            debug!("EnumWindows falhou: {}", e);
+        }
+    }
+    let position = windows.iter().position(|&hwnd| hwnd == root).unwrap_or(0);
+    windows.truncate(position);
+    windows
+}
+
+/// A janela é desenhada pelo DWM e aparece nas capturas
+fn appears_in_capture(hwnd: HWND) -> bool {
+    unsafe {
+        if !IsWindowVisible(hwnd).as_bool() || IsIconic(hwnd).as_bool() {
+            return false;
+        }
+
+        let mut cloaked = 0u32;
+        let attribute = &mut cloaked as *mut u32 as *mut c_void;
+        if DwmGetWindowAttribute(hwnd, DWMWA_CLOAKED, attribute, mem::size_of::<u32>() as u32).is_ok() && cloaked != 0 {
+            return false;
+        }
+
+        let mut affinity = 0u32;
+        if GetWindowDisplayAffinity(hwnd, &mut affinity).is_ok() && affinity == WDA_EXCLUDEFROMCAPTURE.0 {
+            return false;
+        }
+    }
+    true
+}
+
+/// Área da união dos retângulos, por compressão de coordenadas
+fn union_area(rects: &[Rect]) -> u64 {
+    let mut xs: Vec<i64> = rects.iter().flat_map(|rect| [rect.left as i64, rect.right()]).collect();
+    let mut ys: Vec<i64> = rects.iter().flat_map(|rect| [rect.top as i64, rect.bottom()]).collect();
+    xs.sort_unstable();
+    xs.dedup();
+    ys.sort_unstable();
+    ys.dedup();
+
+    let mut area = 0u64;
+    for x in xs.windows(2) {
+        for y in ys.windows(2) {
+            let cell = Rect::new(x[0] as i32, y[0] as i32, (x[1] - x[0]) as u32, (y[1] - y[0]) as u32);
+            if rects.iter().any(|rect| rect.contains_rect(&cell)) {
+                area += Size::new(cell.width, cell.height).area();
+            }
+        }
+    }
+    area
+}
@@ -0,0 +1,515 @@
+//! Quadros como tensores f32 para modelos ONNX (feature `onnx`)
+//!
+//! Modelos de visão esperam `[1, 3, H, W]` (NCHW) ou `[1, H, W, 3]` (NHWC) em f32, com os canais
+//! RGB ou BGR normalizados por média e desvio. `Frame::to_onnx_tensor` faz a conversão na CPU a
+//! partir de um quadro já capturado. `DxgiCapture::capture_tensor` faz tudo em um compute shader
+//! (recorte, escala bilinear para o tamanho de entrada do modelo, ordem dos canais e
+//! normalização) e só lê o tensor, bem menor que a região quando o modelo é pequeno. Com a
+//! feature `d3d12`, `D3D12Capture::capture_tensor_resource` entrega o tensor em um buffer D3D12
+//! para o DirectML, sem passar pela CPU.
+//!
+//! Quadros FP16 (scRGB) são levados para sRGB com os valores fora de 0..1 recortados, como em
+//! `Frame::to_color_space`; o alfa é descartado.
+
+use windows::core::Error;
+use windows::Win32::Foundation::E_FAIL;
+use windows::Win32::Graphics::Direct3D11::*;
+use windows::Win32::Graphics::Dxgi::Common::*;
+
+use crate::error::{CaptureError, CaptureResult};
+use crate::frame::Frame;
+use crate::geometry::{Rect, RectError, Size};
+use crate::gpu_stats::compile_compute_shader;
+use crate::outputs::ColorSpace;
+use crate::{DxgiCapture, Result};
+
+/// Cada thread gera um pixel do tensor com interpolação bilinear da região; `NHWC`, `BGR` e
+/// `LINEAR_INPUT` são definidos por variante e os parâmetros vêm em `Params`:
+/// [largura, altura, média (3), 1/desvio (3)]
+const TENSOR_SHADER: &str = r#"
+Texture2D<float4> Input : register(t0);
+StructuredBuffer<float> Params : register(t1);
+RWBuffer<float> Output : register(u0);
+
+float4 Tap(int2 position, int2 size)
+{
+    return Input[clamp(position, int2(0, 0), size - 1)];
+}
+
+[numthreads(16, 16, 1)]
+void main(uint3 id : SV_DispatchThreadID)
+{
+    uint2 outputSize = uint2((uint)Params[0], (uint)Params[1]);
+    if (id.x >= outputSize.x || id.y >= outputSize.y) {
+        return;
+    }
+
+    uint2 inputSize;
+    Input.GetDimensions(inputSize.x, inputSize.y);
+    int2 size = (int2)inputSize;
+    float2 p = (id.xy + 0.5) * (float2)inputSize / (float2)outputSize - 0.5;
+    int2 base = (int2)floor(p);
+    float2 f = p - base;
+    float4 top = lerp(Tap(base, size), Tap(base + int2(1, 0), size), f.x);
+    float4 bottom = lerp(Tap(base + int2(0, 1), size), Tap(base + int2(1, 1), size), f.x);
+    float3 rgb = saturate(lerp(top, bottom, f.y).rgb);
+#if LINEAR_INPUT
+    // scRGB linear para sRGB
+    rgb = rgb <= 0.0031308 ? rgb * 12.92 : 1.055 * pow(rgb, 1.0 / 2.4) - 0.055;
+#endif
+#if BGR
+    rgb = rgb.bgr;
+#endif
+    float3 value = (rgb - float3(Params[2], Params[3], Params[4])) * float3(Params[5], Params[6], Params[7]);
+
+    uint pixel = id.y * outputSize.x + id.x;
+#if NHWC
+    Output[pixel * 3] = value.x;
+    Output[pixel * 3 + 1] = value.y;
+    Output[pixel * 3 + 2] = value.z;
+#else
+    uint plane = outputSize.x * outputSize.y;
+    Output[pixel] = value.x;
+    Output[plane + pixel] = value.y;
+    Output[2 * plane + pixel] = value.z;
+#endif
+}
+"#;
+
+/// Ordem das dimensões do tensor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum TensorLayout {
+    /// `[1, 3, H, W]`: um plano por canal (a maioria dos modelos exportados do PyTorch)
+    #[default]
+    Nchw,
+    /// `[1, H, W, 3]`: canais intercalados (modelos do TensorFlow)
+    Nhwc,
+}
+
+impl TensorLayout {
+    /// Forma do tensor de uma imagem de `size`
+    pub fn shape(self, size: Size) -> [usize; 4] {
+        let (width, height) = (size.width as usize, size.height as usize);
+        match self {
+            TensorLayout::Nchw => [1, 3, height, width],
+            TensorLayout::Nhwc => [1, height, width, 3],
+        }
+    }
+}
+
+/// Ordem dos canais de cor no tensor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum ChannelOrder {
+    #[default]
+    Rgb,
+    /// Modelos treinados com imagens do OpenCV
+    Bgr,
+}
+
+/// `valor = (amostra - mean) / std`, com a amostra em 0..1; `mean` e `std` seguem a ordem dos
+/// canais do tensor
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Normalization {
+    pub mean: [f32; 3],
+    pub std: [f32; 3],
+    pub channel_order: ChannelOrder,
+}
+
+impl Default for Normalization {
+    fn default() -> Self {
+        Self::unit()
+    }
+}
+
+impl Normalization {
+    /// Valores em 0..1
+    pub fn unit() -> Self {
+        Self {
+            mean: [0.0; 3],
+            std: [1.0; 3],
+            channel_order: ChannelOrder::Rgb,
+        }
+    }
+
+    /// Valores em 0..255, como os bytes dos pixels
+    pub fn byte_range() -> Self {
+        Self {
+            std: [1.0 / 255.0; 3],
+            ..Self::unit()
+        }
+    }
+
+    /// Média e desvio do ImageNet (RGB), usados pelos modelos do torchvision
+    pub fn imagenet() -> Self {
+        Self {
+            mean: [0.485, 0.456, 0.406],
+            std: [0.229, 0.224, 0.225],
+            channel_order: ChannelOrder::Rgb,
+        }
+    }
+
+    pub fn with_channel_order(mut self, channel_order: ChannelOrder) -> Self {
+        self.channel_order = channel_order;
+        self
+    }
+
+    fn inverse_std(&self) -> [f32; 3] {
+        self.std.map(|std| if std != 0.0 { 1.0 / std } else { 0.0 })
+    }
+
+    fn apply(&self, [r, g, b]: [u8; 3], inverse_std: [f32; 3]) -> [f32; 3] {
+        let rgb = match self.channel_order {
+            ChannelOrder::Rgb => [r, g, b],
+            ChannelOrder::Bgr => [b, g, r],
+        };
+        [0, 1, 2].map(|c| (rgb[c] as f32 / 255.0 - self.mean[c]) * inverse_std[c])
+    }
+}
+
+/// Tensor f32 contíguo, pronto para `Ort::Value` ou `TensorFloat::CreateFromArray`
+#[derive(Debug, Clone, PartialEq)]
+pub struct OnnxTensor {
+    pub data: Vec<f32>,
+    pub shape: [usize; 4],
+    pub layout: TensorLayout,
+}
+
+impl OnnxTensor {
+    pub fn width(&self) -> usize {
+        match self.layout {
+            TensorLayout::Nchw => self.shape[3],
+            TensorLayout::Nhwc => self.shape[2],
+        }
+    }
+
+    pub fn height(&self) -> usize {
+        match self.layout {
+            TensorLayout::Nchw => self.shape[2],
+            TensorLayout::Nhwc => self.shape[1],
+        }
+    }
+
+    /// Forma com `i64`, como as APIs do ONNX Runtime pedem
+    pub fn shape_i64(&self) -> [i64; 4] {
+        self.shape.map(|dimension| dimension as i64)
+    }
+}
+
+impl Frame {
+    /// Tensor do quadro inteiro no tamanho dele (para outro tamanho, capture com
+    /// `DxgiCapture::capture_tensor` ou escale antes); quadros FP16 e R10G10B10A2 passam antes
+    /// por `to_color_space(ColorSpace::Srgb)`
+    pub fn to_onnx_tensor(&self, layout: TensorLayout, normalization: Normalization) -> Result<OnnxTensor> {
+        let converted;
+        let frame = match self.format {
+            DXGI_FORMAT_B8G8R8A8_UNORM | DXGI_FORMAT_R8G8B8A8_UNORM => self,
+            _ => {
+                converted = self.to_color_space(ColorSpace::Srgb)?;
+                &converted
+            }
+        };
+        let bgra = frame.format == DXGI_FORMAT_B8G8R8A8_UNORM;
+        let (width, height) = (frame.width() as usize, frame.height() as usize);
+        let plane = width * height;
+        let inverse_std = normalization.inverse_std();
+
+        let mut data = vec![0.0; plane * 3];
+        for (y, row) in frame.rows().enumerate() {
+            for (x, pixel) in row.chunks_exact(4).take(width).enumerate() {
+                let rgb = if bgra {
+                    [pixel[2], pixel[1], pixel[0]]
+                } else {
+                    [pixel[0], pixel[1], pixel[2]]
+                };
+                let value = normalization.apply(rgb, inverse_std);
+                let index = y * width + x;
+                match layout {
+                    TensorLayout::Nchw => {
+                        for (channel, value) in value.into_iter().enumerate() {
+                            data[channel * plane + index] = value;
+                        }
+                    }
+                    TensorLayout::Nhwc => data[index * 3..index * 3 + 3].copy_from_slice(&value),
+                }
+            }
+        }
+
+        Ok(OnnxTensor {
+            data,
+            shape: layout.shape(frame.size()),
+            layout,
+        })
+    }
+}
+
+impl DxgiCapture {
+    /// Tensor da região escalada (bilinear) para `size`, gerado por um compute shader; só o
+    /// tensor é lido para a CPU. Segue a `NoFramePolicy` de `capture_texture`
+    pub fn capture_tensor(
+        &mut self,
+        region: Rect,
+        size: Size,
+        layout: TensorLayout,
+        normalization: Normalization,
+    ) -> CaptureResult<OnnxTensor> {
+        let len = tensor_len(size)?;
+        let texture = self.capture_texture(region)?;
+        let resources = self.resources()?;
+        let pass = resources.tensor.get_or_insert_with(TensorPass::default);
+        let view = pass.output(&resources.device, len)?.view.clone();
+
+        self.write_tensor(&texture, size, layout, normalization, &view)?;
+        let resources = self.resources()?;
+        let output = resources
+            .tensor
+            .as_ref()
+            .and_then(|pass| pass.output.as_ref())
+            .ok_or(CaptureError::NotInitialized)?;
+        Ok(OnnxTensor {
+            data: output.read(&resources.context)?,
+            shape: layout.shape(size),
+            layout,
+        })
+    }
+
+    /// Grava em `target` (UAV `R32_FLOAT` com `size.width * size.height * 3` elementos) o tensor
+    /// de `texture`, obtida de `capture_texture`, sem esperar a GPU
+    pub(crate) fn write_tensor(
+        &mut self,
+        texture: &ID3D11Texture2D,
+        size: Size,
+        layout: TensorLayout,
+        normalization: Normalization,
+        target: &ID3D11UnorderedAccessView,
+    ) -> CaptureResult<()> {
+        let linear = linear_input(self.format())?;
+        let resources = self.resources()?;
+        let pass = resources.tensor.get_or_insert_with(TensorPass::default);
+        pass.run(&resources.device, &resources.context, texture, linear, size, layout, normalization, target)
+    }
+}
+
+/// Elementos do tensor de `size`
+pub(crate) fn tensor_len(size: Size) -> CaptureResult<usize> {
+    if size.is_empty() {
+        return Err(RectError::Empty.into());
+    }
+    Ok(size.width as usize * size.height as usize * 3)
+}
+
+/// O shader recebe scRGB linear (FP16) ou sRGB (8 bits); HDR10 (PQ) não é convertido na GPU
+fn linear_input(format: DXGI_FORMAT) -> CaptureResult<bool> {
+    match format {
+        DXGI_FORMAT_B8G8R8A8_UNORM | DXGI_FORMAT_R8G8B8A8_UNORM => Ok(false),
+        DXGI_FORMAT_R16G16B16A16_FLOAT => Ok(true),
+        _ => Err(CaptureError::Conversion(format!(
+            "GPU tensor does not support {:?}; use Frame::to_onnx_tensor",
+            format
+        ))),
+    }
+}
+
+/// Buffer `R32_FLOAT` do tensor gravado pelo shader e a staging para lê-lo
+struct TensorBuffer {
+    buffer: ID3D11Buffer,
+    view: ID3D11UnorderedAccessView,
+    staging: ID3D11Buffer,
+    len: usize,
+}
+
+impl TensorBuffer {
+    fn new(device: &ID3D11Device, len: usize) -> CaptureResult<Self> {
+        let mut desc = D3D11_BUFFER_DESC {
+            ByteWidth: (len * 4) as u32,
+            Usage: D3D11_USAGE_DEFAULT,
+            BindFlags: D3D11_BIND_UNORDERED_ACCESS.0 as u32,
+            CPUAccessFlags: 0,
+            MiscFlags: 0,
+            StructureByteStride: 0,
+        };
+
+        let mut buffer: Option<ID3D11Buffer> = None;
+        let mut staging: Option<ID3D11Buffer> = None;
+        unsafe {
+            device.CreateBuffer(&desc, None, Some(&mut buffer))?;
+            desc.Usage = D3D11_USAGE_STAGING;
+            desc.BindFlags = 0;
+            desc.CPUAccessFlags = D3D11_CPU_ACCESS_READ.0 as u32;
+            device.CreateBuffer(&desc, None, Some(&mut staging))?;
+        }
+
+        match (buffer, staging) {
+            (Some(buffer), Some(staging)) => Ok(Self {
+                view: float_view(device, &buffer, len)?,
+                buffer,
+                staging,
+                len,
+            }),
+            _ => Err(CaptureError::Windows(Error::from(E_FAIL))),
+        }
+    }
+
+    /// Copia o tensor para a CPU, aguardando o shader terminar
+    fn read(&self, context: &ID3D11DeviceContext) -> CaptureResult<Vec<f32>> {
+        let mut data = vec![0.0; self.len];
+        let mut mapped = D3D11_MAPPED_SUBRESOURCE::default();
+        unsafe {
+            context.CopyResource(&self.staging, &self.buffer);
+            context.Map(&self.staging, 0, D3D11_MAP_READ, 0, Some(&mut mapped))?;
+            if !mapped.pData.is_null() {
+                data.copy_from_slice(std::slice::from_raw_parts(mapped.pData as *const f32, self.len));
+            }
+            context.Unmap(&self.staging, 0);
+        }
+        Ok(data)
+    }
+}
+
+/// UAV tipada `R32_FLOAT` (`RWBuffer<float>`) sobre `len` elementos de `buffer`
+pub(crate) fn float_view(
+    device: &ID3D11Device,
+    buffer: &ID3D11Buffer,
+    len: usize,
+) -> CaptureResult<ID3D11UnorderedAccessView> {
+    let desc = D3D11_UNORDERED_ACCESS_VIEW_DESC {
+        Format: DXGI_FORMAT_R32_FLOAT,
+        ViewDimension: D3D11_UAV_DIMENSION_BUFFER,
+        Anonymous: D3D11_UNORDERED_ACCESS_VIEW_DESC_0 {
+            Buffer: D3D11_BUFFER_UAV {
+                FirstElement: 0,
+                NumElements: len as u32,
+                Flags: 0,
+            },
+        },
+    };
+    let mut view: Option<ID3D11UnorderedAccessView> = None;
+    unsafe {
+        device.CreateUnorderedAccessView(buffer, Some(&desc), Some(&mut view))?;
+    }
+    view.ok_or(CaptureError::Windows(Error::from(E_FAIL)))
+}
+
+/// Variantes do shader do tensor, compiladas no primeiro uso, com o buffer de saída e os
+/// parâmetros da última chamada
+#[derive(Default)]
+pub(crate) struct TensorPass {
+    shaders: Vec<((TensorLayout, ChannelOrder, bool), ID3D11ComputeShader)>,
+    output: Option<TensorBuffer>,
+    params: Option<([f32; 8], ID3D11ShaderResourceView)>,
+}
+
+impl TensorPass {
+    /// Buffer de saída de `len` elementos, recriado se o tamanho mudou
+    fn output(&mut self, device: &ID3D11Device, len: usize) -> CaptureResult<&TensorBuffer> {
+        if !self.output.as_ref().is_some_and(|output| output.len == len) {
+            self.output = None;
+            self.output = Some(TensorBuffer::new(device, len)?);
+        }
+        self.output.as_ref().ok_or(CaptureError::NotInitialized)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn run(
+        &mut self,
+        device: &ID3D11Device,
+        context: &ID3D11DeviceContext,
+        input: &ID3D11Texture2D,
+        linear: bool,
+        size: Size,
+        layout: TensorLayout,
+        normalization: Normalization,
+        target: &ID3D11UnorderedAccessView,
+    ) -> CaptureResult<()> {
+        let shader = self.shader(device, (layout, normalization.channel_order, linear))?;
+        let params = self.params(device, size, &normalization)?;
+
+        let mut input_view: Option<ID3D11ShaderResourceView> = None;
+        unsafe {
+            device.CreateShaderResourceView(input, None, Some(&mut input_view))?;
+        }
+        let input_view = input_view.ok_or(CaptureError::Windows(Error::from(E_FAIL)))?;
+
+        unsafe {
+            context.CSSetShader(&shader, None);
+            context.CSSetShaderResources(0, Some(&[Some(input_view), Some(params)]));
+            context.CSSetUnorderedAccessViews(0, 1, Some(&Some(target.clone())), None);
+            context.Dispatch(size.width.div_ceil(16), size.height.div_ceil(16), 1);
+
+            context.CSSetShaderResources(0, Some(&[None, None]));
+            context.CSSetUnorderedAccessViews(0, 1, Some(&None), None);
+            context.CSSetShader(None, None);
+        }
+
+        Ok(())
+    }
+
+    /// Parâmetros do shader em um buffer imutável, recriado só quando mudam
+    fn params(
+        &mut self,
+        device: &ID3D11Device,
+        size: Size,
+        normalization: &Normalization,
+    ) -> CaptureResult<ID3D11ShaderResourceView> {
+        let [mr, mg, mb] = normalization.mean;
+        let [sr, sg, sb] = normalization.inverse_std();
+        let values = [size.width as f32, size.height as f32, mr, mg, mb, sr, sg, sb];
+        if let Some((cached, view)) = &self.params {
+            if *cached == values {
+                return Ok(view.clone());
+            }
+        }
+
+        let desc = D3D11_BUFFER_DESC {
+            ByteWidth: (values.len() * 4) as u32,
+            Usage: D3D11_USAGE_IMMUTABLE,
+            BindFlags: D3D11_BIND_SHADER_RESOURCE.0 as u32,
+            CPUAccessFlags: 0,
+            MiscFlags: D3D11_RESOURCE_MISC_BUFFER_STRUCTURED.0 as u32,
+            StructureByteStride: 4,
+        };
+        let initial = D3D11_SUBRESOURCE_DATA {
+            pSysMem: values.as_ptr() as *const std::ffi::c_void,
+            ..Default::default()
+        };
+
+        let mut buffer: Option<ID3D11Buffer> = None;
+        let mut view: Option<ID3D11ShaderResourceView> = None;
+        unsafe {
+            device.CreateBuffer(&desc, Some(&initial), Some(&mut buffer))?;
+            let buffer = buffer.ok_or(CaptureError::Windows(Error::from(E_FAIL)))?;
+            device.CreateShaderResourceView(&buffer, None, Some(&mut view))?;
+        }
+        let view = view.ok_or(CaptureError::Windows(Error::from(E_FAIL)))?;
+        self.params = Some((values, view.clone()));
+        Ok(view)
+    }
+
+    fn shader(
+        &mut self,
+        device: &ID3D11Device,
+        key: (TensorLayout, ChannelOrder, bool),
+    ) -> CaptureResult<ID3D11ComputeShader> {
+        if let Some((_, shader)) = self.shaders.iter().find(|(cached, _)| *cached == key) {
+            return Ok(shader.clone());
+        }
+
+        let (layout, channel_order, linear) = key;
+        let source = format!(
+            "#define NHWC {}\n#define BGR {}\n#define LINEAR_INPUT {}\n{}",
+            (layout == TensorLayout::Nhwc) as u32,
+            (channel_order == ChannelOrder::Bgr) as u32,
+            linear as u32,
+            TENSOR_SHADER
+        );
+        let shader = compile_compute_shader(device, &source)?;
+        debug!("Shader de tensor compilado: {:?}", key);
+        self.shaders.push((key, shader.clone()));
+        Ok(shader)
+    }
+}
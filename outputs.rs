@@ -0,0 +1,354 @@
+//! Enumeração de adaptadores e monitores (outputs) DXGI
+
+use windows::core::*;
+use windows::Win32::Graphics::Dxgi::Common::*;
+use windows::Win32::Graphics::Dxgi::*;
+
+use crate::geometry::{Point, Size};
+use crate::error::{CaptureError, CaptureResult};
+use crate::monitor_identity::{monitor_identities, MonitorIdentity};
+
+/// Descrição de um monitor conectado a um adaptador
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OutputInfo {
+    pub adapter_index: u32,
+    pub output_index: u32,
+    /// Nome do adaptador (ex.: "NVIDIA GeForce RTX 3070")
+    pub adapter_name: String,
+    /// Nome do dispositivo GDI (ex.: `\\.\DISPLAY1`)
+    pub device_name: String,
+    /// Posição no desktop virtual
+    pub left: i32,
+    pub top: i32,
+    pub width: u32,
+    pub height: u32,
+    pub attached_to_desktop: bool,
+    /// Espaço de cor e luminância (`None` antes do Windows 10 1703)
+    pub color: Option<OutputColorInfo>,
+    /// Monitor físico, com o nome estável de `DxgiCapture::for_output_by_name` (`None` se o
+    /// driver não o informa); em modo clone, o primeiro dos monitores
+    pub identity: Option<MonitorIdentity>,
+}
+
+/// Espaço de cor ativo de um monitor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ColorSpace {
+    /// sRGB (gama 2.2, primárias BT.709): SDR
+    Srgb,
+    /// scRGB linear (primárias BT.709, valores além de 1.0)
+    ScRgb,
+    /// HDR10: PQ (ST 2084) com primárias BT.2020
+    Hdr10,
+    /// Outro `DXGI_COLOR_SPACE_TYPE`
+    Other(i32),
+}
+
+impl ColorSpace {
+    pub fn from_dxgi(color_space: DXGI_COLOR_SPACE_TYPE) -> Self {
+        match color_space {
+            DXGI_COLOR_SPACE_RGB_FULL_G22_NONE_P709 => ColorSpace::Srgb,
+            DXGI_COLOR_SPACE_RGB_FULL_G10_NONE_P709 => ColorSpace::ScRgb,
+            DXGI_COLOR_SPACE_RGB_FULL_G2084_NONE_P2020 => ColorSpace::Hdr10,
+            other => ColorSpace::Other(other.0),
+        }
+    }
+
+    pub fn to_dxgi(&self) -> DXGI_COLOR_SPACE_TYPE {
+        match self {
+            ColorSpace::Srgb => DXGI_COLOR_SPACE_RGB_FULL_G22_NONE_P709,
+            ColorSpace::ScRgb => DXGI_COLOR_SPACE_RGB_FULL_G10_NONE_P709,
+            ColorSpace::Hdr10 => DXGI_COLOR_SPACE_RGB_FULL_G2084_NONE_P2020,
+            ColorSpace::Other(value) => DXGI_COLOR_SPACE_TYPE(*value),
+        }
+    }
+
+    pub fn is_hdr(&self) -> bool {
+        matches!(self, ColorSpace::ScRgb | ColorSpace::Hdr10)
+    }
+
+    /// Espaço de cor dos pixels capturados em `format` de um monitor em `output`.
+    ///
+    /// Em formatos de 8 bits o DWM entrega a imagem já convertida para SDR, em FP16 a imagem é
+    /// scRGB linear e em R10G10B10A2 ela segue o sinal do monitor (HDR10 com HDR ativo).
+    pub fn of_capture(output: ColorSpace, format: DXGI_FORMAT) -> Self {
+        match format {
+            DXGI_FORMAT_B8G8R8A8_UNORM | DXGI_FORMAT_R8G8B8A8_UNORM => ColorSpace::Srgb,
+            DXGI_FORMAT_R16G16B16A16_FLOAT => ColorSpace::ScRgb,
+            DXGI_FORMAT_R10G10B10A2_UNORM if output == ColorSpace::Hdr10 => ColorSpace::Hdr10,
+            DXGI_FORMAT_R10G10B10A2_UNORM => ColorSpace::Srgb,
+            _ => output,
+        }
+    }
+
+    /// Códigos CICP da imagem em RGB de faixa completa, para o chunk `cICP` do PNG e caixas
+    /// `colr` do tipo `nclx`; `None` para espaços sem código
+    pub fn cicp(&self) -> Option<Cicp> {
+        let (color_primaries, transfer_characteristics) = match self {
+            ColorSpace::Srgb => (Cicp::PRIMARIES_BT709, Cicp::TRANSFER_SRGB),
+            ColorSpace::ScRgb => (Cicp::PRIMARIES_BT709, Cicp::TRANSFER_LINEAR),
+            ColorSpace::Hdr10 => (Cicp::PRIMARIES_BT2020, Cicp::TRANSFER_PQ),
+            ColorSpace::Other(_) => return None,
+        };
+        Some(Cicp {
+            color_primaries,
+            transfer_characteristics,
+            matrix_coefficients: Cicp::MATRIX_IDENTITY,
+            full_range: true,
+        })
+    }
+
+    /// Códigos CICP (VUI do H.264/HEVC) de um vídeo YUV de faixa limitada codificado a partir
+    /// deste espaço. scRGB não tem representação em vídeo: converta antes para sRGB ou HDR10
+    /// com `Frame::to_color_space`.
+    pub fn video_cicp(&self) -> Option<Cicp> {
+        let (color_primaries, transfer_characteristics, matrix_coefficients) = match self {
+            ColorSpace::Srgb => (Cicp::PRIMARIES_BT709, Cicp::TRANSFER_SRGB, Cicp::MATRIX_BT709),
+            ColorSpace::Hdr10 => (Cicp::PRIMARIES_BT2020, Cicp::TRANSFER_PQ, Cicp::MATRIX_BT2020_NCL),
+            ColorSpace::ScRgb | ColorSpace::Other(_) => return None,
+        };
+        Some(Cicp {
+            color_primaries,
+            transfer_characteristics,
+            matrix_coefficients,
+            full_range: false,
+        })
+    }
+}
+
+/// Códigos de espaço de cor da ITU-T H.273, os mesmos da VUI do H.264/HEVC, do `colr` do MP4 e
+/// dos campos `color_primaries`/`color_trc`/`colorspace` do ffmpeg
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Cicp {
+    pub color_primaries: u8,
+    pub transfer_characteristics: u8,
+    pub matrix_coefficients: u8,
+    pub full_range: bool,
+}
+
+impl Cicp {
+    pub const PRIMARIES_BT709: u8 = 1;
+    pub const PRIMARIES_BT2020: u8 = 9;
+    pub const TRANSFER_BT709: u8 = 1;
+    pub const TRANSFER_LINEAR: u8 = 8;
+    pub const TRANSFER_SRGB: u8 = 13;
+    pub const TRANSFER_PQ: u8 = 16;
+    pub const MATRIX_IDENTITY: u8 = 0;
+    pub const MATRIX_BT709: u8 = 1;
+    pub const MATRIX_BT601: u8 = 6;
+    pub const MATRIX_BT2020_NCL: u8 = 9;
+}
+
+/// Dados de cor de `IDXGIOutput6::GetDesc1`
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OutputColorInfo {
+    pub color_space: ColorSpace,
+    pub bits_per_color: u32,
+    /// Primárias e ponto branco em coordenadas CIE xy
+    pub red_primary: [f32; 2],
+    pub green_primary: [f32; 2],
+    pub blue_primary: [f32; 2],
+    pub white_point: [f32; 2],
+    /// Luminâncias em nits
+    pub min_luminance: f32,
+    pub max_luminance: f32,
+    pub max_full_frame_luminance: f32,
+}
+
+impl OutputColorInfo {
+    /// Lê os dados de cor do output; `None` se `IDXGIOutput6` não estiver disponível
+    pub(crate) fn query(output: &IDXGIOutput) -> Option<Self> {
+        let output6: IDXGIOutput6 = output.cast().ok()?;
+        let mut desc = DXGI_OUTPUT_DESC1::default();
+        unsafe {
+            output6.GetDesc1(&mut desc).ok()?;
+        }
+
+        Some(Self {
+            color_space: ColorSpace::from_dxgi(desc.ColorSpace),
+            bits_per_color: desc.BitsPerColor,
+            red_primary: desc.RedPrimary,
+            green_primary: desc.GreenPrimary,
+            blue_primary: desc.BluePrimary,
+            white_point: desc.WhitePoint,
+            min_luminance: desc.MinLuminance,
+            max_luminance: desc.MaxLuminance,
+            max_full_frame_luminance: desc.MaxFullFrameLuminance,
+        })
+    }
+
+    /// HDR ativo: vale a pena capturar em FP16 e aplicar tone mapping para SDR
+    pub fn is_hdr(&self) -> bool {
+        self.color_space.is_hdr()
+    }
+}
+
+/// Rotação do monitor aplicada pelo sistema à imagem do desktop
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Rotation {
+    Identity,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    /// O driver não informou
+    Unspecified,
+}
+
+impl Rotation {
+    pub fn from_dxgi(rotation: DXGI_MODE_ROTATION) -> Self {
+        match rotation {
+            DXGI_MODE_ROTATION_IDENTITY => Rotation::Identity,
+            DXGI_MODE_ROTATION_ROTATE90 => Rotation::Rotate90,
+            DXGI_MODE_ROTATION_ROTATE180 => Rotation::Rotate180,
+            DXGI_MODE_ROTATION_ROTATE270 => Rotation::Rotate270,
+            _ => Rotation::Unspecified,
+        }
+    }
+
+    /// Monitor em retrato: largura e altura do modo trocadas em relação ao desktop
+    pub fn swaps_axes(&self) -> bool {
+        matches!(self, Rotation::Rotate90 | Rotation::Rotate270)
+    }
+}
+
+/// Descrição da duplicação ativa (`DXGI_OUTDUPL_DESC`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DuplicationInfo {
+    /// Tamanho do modo de vídeo, antes da rotação
+    pub mode_size: Size,
+    /// Formato do modo de vídeo (pode diferir do formato negociado dos quadros)
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_support::dxgi_format"))]
+    pub mode_format: DXGI_FORMAT,
+    /// Taxa de atualização do modo como fração (ex.: 60000/1001); denominador 0 se o driver
+    /// não informar
+    pub refresh_numerator: u32,
+    pub refresh_denominator: u32,
+    pub rotation: Rotation,
+    /// A imagem do desktop fica em memória do sistema (lida com `MapDesktopSurface`)
+    pub desktop_in_system_memory: bool,
+}
+
+impl DuplicationInfo {
+    pub(crate) fn from_dxgi(desc: &DXGI_OUTDUPL_DESC) -> Self {
+        let mode = &desc.ModeDesc;
+        Self {
+            mode_size: Size::new(mode.Width, mode.Height),
+            mode_format: mode.Format,
+            refresh_numerator: mode.RefreshRate.Numerator,
+            refresh_denominator: mode.RefreshRate.Denominator,
+            rotation: Rotation::from_dxgi(desc.Rotation),
+            desktop_in_system_memory: desc.DesktopImageInSystemMemory.as_bool(),
+        }
+    }
+
+    /// Taxa de atualização em Hz, se o driver informou
+    pub fn refresh_rate(&self) -> Option<f64> {
+        (self.refresh_denominator != 0 && self.refresh_numerator != 0)
+            .then(|| self.refresh_numerator as f64 / self.refresh_denominator as f64)
+    }
+}
+
+impl OutputInfo {
+    /// Canto superior esquerdo do monitor no desktop virtual
+    pub fn origin(&self) -> Point {
+        Point::new(self.left, self.top)
+    }
+
+    pub fn size(&self) -> Size {
+        Size::new(self.width, self.height)
+    }
+}
+
+/// Converte um buffer UTF-16 terminado em NUL em `String`
+pub(crate) fn wide_to_string(wide: &[u16]) -> String {
+    let len = wide.iter().position(|&c| c == 0).unwrap_or(wide.len());
+    String::from_utf16_lossy(&wide[..len])
+}
+
+/// Lista todos os monitores de todos os adaptadores
+pub fn enumerate_outputs() -> CaptureResult<Vec<OutputInfo>> {
+    let factory: IDXGIFactory1 = unsafe { CreateDXGIFactory1()? };
+    let mut outputs = Vec::new();
+    let identities = monitor_identities().unwrap_or_else(|e| {
+        debug!("Identidade dos monitores indisponível: {}", e);
+        Vec::new()
+    });
+
+    let mut adapter_index = 0;
+    while let Ok(adapter) = unsafe { factory.EnumAdapters1(adapter_index) } {
+        let mut adapter_desc = DXGI_ADAPTER_DESC1::default();
+        unsafe {
+            adapter.GetDesc1(&mut adapter_desc)?;
+        }
+
+        let mut output_index = 0;
+        while let Ok(output) = unsafe { adapter.EnumOutputs(output_index) } {
+            let mut output_desc = DXGI_OUTPUT_DESC::default();
+            unsafe {
+                output.GetDesc(&mut output_desc)?;
+            }
+
+            let coords = output_desc.DesktopCoordinates;
+            let device_name = wide_to_string(&output_desc.DeviceName);
+            let identity = identities
+                .iter()
+                .find(|identity| identity.gdi_device_name.eq_ignore_ascii_case(&device_name))
+                .cloned();
+            outputs.push(OutputInfo {
+                adapter_index,
+                output_index,
+                adapter_name: wide_to_string(&adapter_desc.Description),
+                device_name,
+                left: coords.left,
+                top: coords.top,
+                width: (coords.right - coords.left) as u32,
+                height: (coords.bottom - coords.top) as u32,
+                attached_to_desktop: output_desc.AttachedToDesktop.as_bool(),
+                color: OutputColorInfo::query(&output),
+                identity,
+            });
+
+            output_index += 1;
+        }
+
+        adapter_index += 1;
+    }
+
+    Ok(outputs)
+}
+
+/// Localiza o adaptador e o output pelos índices
+pub(crate) fn find_output(adapter_index: u32, output_index: u32) -> CaptureResult<(IDXGIAdapter1, IDXGIOutput)> {
+    let factory: IDXGIFactory1 = unsafe { CreateDXGIFactory1()? };
+    let adapter = unsafe { factory.EnumAdapters1(adapter_index) }
+        .map_err(|_| CaptureError::AdapterNotFound { adapter: adapter_index })?;
+    let output = unsafe { adapter.EnumOutputs(output_index) }
+        .map_err(|_| CaptureError::OutputNotFound {
+            adapter: adapter_index,
+            output: output_index,
+        })?;
+
+    Ok((adapter, output))
+}
+
+/// Monitor pelo nome estável: `MonitorIdentity::display_name` (ex.: "DELL U2720Q #2") ou
+/// `device_path`, sem diferenciar maiúsculas. Em modo clone qualquer um dos monitores localiza o
+/// output que os mostra
+pub fn find_output_by_name(name: &str) -> CaptureResult<OutputInfo> {
+    let not_found = || CaptureError::OutputNameNotFound { name: name.to_owned() };
+    let identity = monitor_identities()?
+        .into_iter()
+        .find(|identity| identity.matches(name))
+        .ok_or_else(not_found)?;
+
+    enumerate_outputs()?
+        .into_iter()
+        .find(|output| output.device_name.eq_ignore_ascii_case(&identity.gdi_device_name))
+        .ok_or_else(not_found)
+}
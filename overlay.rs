@@ -0,0 +1,233 @@
+//! Overlay de depuração com FPS, latência e descartes desenhado sobre os quadros
+//!
+//! Útil para ajustar um pipeline de captura: o próprio vídeo mostra a taxa real, quanto
+//! tempo o quadro levou desde a apresentação e quantos quadros as filas perderam. O texto usa
+//! o mesmo renderizador GDI de `annotate`, então só quadros B8G8R8A8 e R8G8B8A8 recebem o
+//! overlay. Pode ser ligado e desligado a qualquer momento por um `OverlayToggle`.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use windows::Win32::System::Performance::{QueryPerformanceCounter, QueryPerformanceFrequency};
+
+use crate::annotate::{annotate, measure_text, Annotation, Color, LABEL_PADDING};
+use crate::frame::Frame;
+use crate::geometry::{Point, Rect};
+use crate::stream::StreamStats;
+use crate::Result;
+
+/// Distância entre o bloco de texto e a borda do quadro
+const OVERLAY_MARGIN: i32 = 8;
+/// Janela usada para medir a taxa de quadros
+const FPS_WINDOW: Duration = Duration::from_secs(1);
+/// Peso de cada nova medida na média móvel da latência
+const LATENCY_SMOOTHING: f64 = 0.1;
+
+/// Canto do quadro onde o overlay é desenhado
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum OverlayCorner {
+    #[default]
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// Aparência do overlay
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct OverlayOptions {
+    /// Estado inicial; depois pode ser alterado pelo `OverlayToggle`
+    pub enabled: bool,
+    pub corner: OverlayCorner,
+    /// Tamanho da fonte em pixels
+    pub size: u32,
+    pub color: Color,
+    /// Fundo atrás do texto, para leitura sobre qualquer conteúdo
+    pub background: Option<Color>,
+}
+
+impl Default for OverlayOptions {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            corner: OverlayCorner::TopLeft,
+            size: 16,
+            color: Color::WHITE,
+            background: Some(Color::BLACK.with_alpha(160)),
+        }
+    }
+}
+
+/// Liga e desliga o overlay de qualquer thread
+#[derive(Debug, Clone, Default)]
+pub struct OverlayToggle(Arc<AtomicBool>);
+
+impl OverlayToggle {
+    pub fn new(enabled: bool) -> Self {
+        Self(Arc::new(AtomicBool::new(enabled)))
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.0.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Inverte o estado e retorna o novo
+    pub fn toggle(&self) -> bool {
+        !self.0.fetch_xor(true, Ordering::Relaxed)
+    }
+}
+
+/// Mede a taxa e a latência dos quadros e desenha o overlay sobre eles
+#[derive(Debug)]
+pub struct StatsOverlay {
+    options: OverlayOptions,
+    toggle: OverlayToggle,
+    frames: VecDeque<Instant>,
+    // Média móvel da latência em ms
+    latency: Option<f64>,
+    frequency: i64,
+}
+
+impl StatsOverlay {
+    pub fn new(options: OverlayOptions) -> Self {
+        let toggle = OverlayToggle::new(options.enabled);
+        Self::with_toggle(options, toggle)
+    }
+
+    /// Usa um `OverlayToggle` já existente (`options.enabled` é ignorado)
+    pub fn with_toggle(options: OverlayOptions, toggle: OverlayToggle) -> Self {
+        let mut frequency = 0i64;
+        if unsafe { QueryPerformanceFrequency(&mut frequency) }.is_err() {
+            frequency = 0;
+        }
+        Self {
+            options,
+            toggle,
+            frames: VecDeque::new(),
+            latency: None,
+            frequency,
+        }
+    }
+
+    pub fn toggle(&self) -> OverlayToggle {
+        self.toggle.clone()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.toggle.is_enabled()
+    }
+
+    /// Quadros por segundo no último segundo
+    pub fn fps(&self) -> f64 {
+        match (self.frames.front(), self.frames.back()) {
+            (Some(first), Some(last)) if self.frames.len() > 1 => {
+                let span = last.duration_since(*first).as_secs_f64();
+                if span > 0.0 {
+                    (self.frames.len() - 1) as f64 / span
+                } else {
+                    0.0
+                }
+            }
+            _ => 0.0,
+        }
+    }
+
+    /// Tempo médio entre a apresentação do quadro e sua entrega ao overlay
+    pub fn latency(&self) -> Option<Duration> {
+        self.latency.map(|ms| Duration::from_secs_f64(ms / 1000.0))
+    }
+
+    /// Contabiliza o quadro e, se ligado, desenha as estatísticas sobre ele.
+    ///
+    /// Deve ser chamado para todos os quadros, mesmo com o overlay desligado, para que a taxa
+    /// esteja correta quando ele for ligado.
+    pub fn draw(&mut self, frame: &mut Frame, stats: &StreamStats) -> Result<()> {
+        self.record(frame);
+        if !self.is_enabled() {
+            return Ok(());
+        }
+
+        let lines = [
+            format!("{:.1} fps", self.fps()),
+            match self.latency {
+                Some(ms) => format!("latência {:.1} ms", ms),
+                None => "latência -".to_owned(),
+            },
+            format!("descartados {} · perdidos {} · erros {}", stats.dropped, stats.missed, stats.errors),
+            format!("quadro {}", frame.sequence),
+        ];
+
+        let mut sizes = Vec::with_capacity(lines.len());
+        for line in &lines {
+            sizes.push(measure_text(line, self.options.size)?);
+        }
+        let width = sizes.iter().map(|size| size.width).max().unwrap_or(0) as i32;
+        let line_height = sizes.iter().map(|size| size.height).max().unwrap_or(0) as i32;
+        let height = line_height * lines.len() as i32;
+
+        let inset = OVERLAY_MARGIN + LABEL_PADDING;
+        let (frame_width, frame_height) = (frame.width() as i32, frame.height() as i32);
+        let origin = match self.options.corner {
+            OverlayCorner::TopLeft => Point::new(inset, inset),
+            OverlayCorner::TopRight => Point::new(frame_width - inset - width, inset),
+            OverlayCorner::BottomLeft => Point::new(inset, frame_height - inset - height),
+            OverlayCorner::BottomRight => Point::new(frame_width - inset - width, frame_height - inset - height),
+        };
+
+        let mut annotations = Vec::with_capacity(lines.len() + 1);
+        if let Some(background) = self.options.background {
+            annotations.push(Annotation::Highlight {
+                rect: Rect::new(
+                    origin.x - LABEL_PADDING,
+                    origin.y - LABEL_PADDING,
+                    (width + 2 * LABEL_PADDING) as u32,
+                    (height + 2 * LABEL_PADDING) as u32,
+                ),
+                color: background,
+            });
+        }
+        for (row, text) in lines.into_iter().enumerate() {
+            annotations.push(Annotation::Text {
+                at: Point::new(origin.x, origin.y + row as i32 * line_height),
+                text,
+                color: self.options.color,
+                size: self.options.size,
+                background: None,
+            });
+        }
+
+        annotate(frame, &annotations)
+    }
+
+    fn record(&mut self, frame: &Frame) {
+        let now = Instant::now();
+        self.frames.push_back(now);
+        while self.frames.front().is_some_and(|first| now.duration_since(*first) > FPS_WINDOW) {
+            self.frames.pop_front();
+        }
+
+        // Quadros repetidos (sem apresentação nova) não dizem nada sobre a latência
+        if frame.present_time == 0 || self.frequency == 0 {
+            return;
+        }
+        let mut qpc = 0i64;
+        if unsafe { QueryPerformanceCounter(&mut qpc) }.is_err() || qpc < frame.present_time {
+            return;
+        }
+        let ms = (qpc - frame.present_time) as f64 * 1000.0 / self.frequency as f64;
+        self.latency = Some(match self.latency {
+            Some(average) => average + (ms - average) * LATENCY_SMOOTHING,
+            None => ms,
+        });
+    }
+}
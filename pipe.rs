@@ -0,0 +1,480 @@
+//! Protocolo de quadros sobre named pipe para IPC local entre serviço de captura e UI
+//!
+//! Fluxo: o cliente envia `Hello` (versão, formatos aceitos, FPS máximo); o servidor responde
+//! `Accept` com o formato escolhido ou `Reject`; em seguida vêm mensagens `Frame` com
+//! timestamp até um `Bye`. Cada mensagem é: tipo (u8), tamanho do payload (u32 LE), payload.
+//!
+//! Com a feature `encryption`, `PipeSession::encrypt` troca as mensagens `Frame` por
+//! `SealedFrame`, com os pixels cifrados por um `FrameCipher`; o cliente configurado com
+//! `PipeClient::decrypt` recusa quadros em claro, e um cliente sem chave recusa os cifrados.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::os::windows::io::{FromRawHandle, RawHandle};
+
+use windows::core::HSTRING;
+use windows::Win32::Foundation::ERROR_PIPE_CONNECTED;
+use windows::Win32::Graphics::Dxgi::Common::DXGI_FORMAT;
+use windows::Win32::Storage::FileSystem::PIPE_ACCESS_DUPLEX;
+use windows::Win32::System::Pipes::{
+    ConnectNamedPipe, CreateNamedPipeW, PIPE_READMODE_BYTE, PIPE_TYPE_BYTE, PIPE_UNLIMITED_INSTANCES, PIPE_WAIT,
+};
+
+#[cfg(feature = "encryption")]
+use crate::encryption::FrameCipher;
+use crate::Result;
+
+pub const PROTOCOL_VERSION: u16 = 1;
+
+/// Tamanho máximo aceito para um payload (protege contra dados corrompidos)
+const MAX_PAYLOAD: u32 = 256 * 1024 * 1024;
+const PIPE_BUFFER_SIZE: u32 = 1024 * 1024;
+
+const MSG_HELLO: u8 = 1;
+const MSG_ACCEPT: u8 = 2;
+const MSG_REJECT: u8 = 3;
+const MSG_FRAME: u8 = 4;
+const MSG_BYE: u8 = 5;
+const MSG_SEALED_FRAME: u8 = 6;
+
+/// Formato negociado do fluxo
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StreamFormat {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_support::dxgi_format"))]
+    pub format: DXGI_FORMAT,
+    pub width: u32,
+    pub height: u32,
+    pub fps: u32,
+}
+
+/// Mensagens do protocolo
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    Hello {
+        version: u16,
+        formats: Vec<DXGI_FORMAT>,
+        max_fps: u32,
+    },
+    Accept(StreamFormat),
+    Reject(String),
+    Frame {
+        sequence: u64,
+        timestamp: i64,
+        data: Vec<u8>,
+    },
+    /// Quadro com os pixels cifrados (`encryption::FrameCipher::seal`)
+    SealedFrame {
+        sequence: u64,
+        timestamp: i64,
+        data: Vec<u8>,
+    },
+    Bye,
+}
+
+/// Serializa uma mensagem no fluxo
+pub fn write_message<W: Write>(writer: &mut W, message: &Message) -> io::Result<()> {
+    let mut payload = Vec::new();
+    let kind = match message {
+        Message::Hello { version, formats, max_fps } => {
+            payload.extend_from_slice(&version.to_le_bytes());
+            payload.extend_from_slice(&max_fps.to_le_bytes());
+            payload.extend_from_slice(&(formats.len() as u32).to_le_bytes());
+            for format in formats {
+                payload.extend_from_slice(&format.0.to_le_bytes());
+            }
+            MSG_HELLO
+        }
+        Message::Accept(format) => {
+            payload.extend_from_slice(&format.format.0.to_le_bytes());
+            payload.extend_from_slice(&format.width.to_le_bytes());
+            payload.extend_from_slice(&format.height.to_le_bytes());
+            payload.extend_from_slice(&format.fps.to_le_bytes());
+            MSG_ACCEPT
+        }
+        Message::Reject(reason) => {
+            payload.extend_from_slice(reason.as_bytes());
+            MSG_REJECT
+        }
+        Message::Frame { sequence, timestamp, data } => return write_frame(writer, MSG_FRAME, *sequence, *timestamp, data),
+        Message::SealedFrame { sequence, timestamp, data } => {
+            return write_frame(writer, MSG_SEALED_FRAME, *sequence, *timestamp, data)
+        }
+        Message::Bye => MSG_BYE,
+    };
+
+    writer.write_all(&[kind])?;
+    writer.write_all(&(payload.len() as u32).to_le_bytes())?;
+    writer.write_all(&payload)?;
+    writer.flush()
+}
+
+/// Escreve uma mensagem `Frame` ou `SealedFrame` diretamente a partir dos pixels, sem copiar o quadro
+fn write_frame<W: Write>(writer: &mut W, kind: u8, sequence: u64, timestamp: i64, data: &[u8]) -> io::Result<()> {
+    writer.write_all(&[kind])?;
+    writer.write_all(&(16 + data.len() as u32).to_le_bytes())?;
+    writer.write_all(&sequence.to_le_bytes())?;
+    writer.write_all(&timestamp.to_le_bytes())?;
+    writer.write_all(data)?;
+    writer.flush()
+}
+
+/// Lê a próxima mensagem do fluxo
+pub fn read_message<R: Read>(reader: &mut R) -> Result<Message> {
+    let mut header = [0u8; 5];
+    reader.read_exact(&mut header)?;
+    let kind = header[0];
+    let length = u32::from_le_bytes([header[1], header[2], header[3], header[4]]);
+
+    if length > MAX_PAYLOAD {
+        return Err(format!("Mensagem grande demais no pipe: {} bytes", length).into());
+    }
+
+    let mut payload = vec![0u8; length as usize];
+    reader.read_exact(&mut payload)?;
+
+    let u32_at = |offset: usize| -> Result<u32> {
+        let bytes = payload.get(offset..offset + 4).ok_or("Mensagem truncada no pipe")?;
+        Ok(u32::from_le_bytes(bytes.try_into()?))
+    };
+
+    match kind {
+        MSG_HELLO => {
+            let version = u16::from_le_bytes(payload.get(0..2).ok_or("Mensagem truncada no pipe")?.try_into()?);
+            let max_fps = u32_at(2)?;
+            let count = u32_at(6)? as usize;
+            let formats = (0..count)
+                .map(|i| u32_at(10 + i * 4).map(DXGI_FORMAT))
+                .collect::<Result<Vec<_>>>()?;
+            Ok(Message::Hello { version, formats, max_fps })
+        }
+        MSG_ACCEPT => Ok(Message::Accept(StreamFormat {
+            format: DXGI_FORMAT(u32_at(0)?),
+            width: u32_at(4)?,
+            height: u32_at(8)?,
+            fps: u32_at(12)?,
+        })),
+        MSG_REJECT => Ok(Message::Reject(String::from_utf8_lossy(&payload).into_owned())),
+        MSG_FRAME | MSG_SEALED_FRAME => {
+            if payload.len() < 16 {
+                return Err("Mensagem truncada no pipe".into());
+            }
+            let sequence = u64::from_le_bytes(payload[0..8].try_into()?);
+            let timestamp = i64::from_le_bytes(payload[8..16].try_into()?);
+            payload.drain(..16);
+            if kind == MSG_SEALED_FRAME {
+                Ok(Message::SealedFrame { sequence, timestamp, data: payload })
+            } else {
+                Ok(Message::Frame { sequence, timestamp, data: payload })
+            }
+        }
+        MSG_BYE => Ok(Message::Bye),
+        other => Err(format!("Tipo de mensagem desconhecido no pipe: {}", other).into()),
+    }
+}
+
+/// Quadro recebido pelo pipe
+#[derive(Debug, Clone)]
+pub struct PipeFrame {
+    pub sequence: u64,
+    pub timestamp: i64,
+    pub data: Vec<u8>,
+}
+
+fn pipe_path(name: &str) -> String {
+    format!(r"\\.\pipe\{}", name)
+}
+
+/// Lado servidor: aceita clientes e negocia o formato
+pub struct PipeServer {
+    path: String,
+}
+
+impl PipeServer {
+    pub fn new(name: &str) -> Self {
+        Self { path: pipe_path(name) }
+    }
+
+    /// Aguarda um cliente e negocia o fluxo a partir do formato oferecido pela captura
+    pub fn accept(&self, offer: StreamFormat) -> Result<PipeSession> {
+        let handle = unsafe {
+            CreateNamedPipeW(
+                &HSTRING::from(self.path.as_str()),
+                PIPE_ACCESS_DUPLEX,
+                PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT,
+                PIPE_UNLIMITED_INSTANCES,
+                PIPE_BUFFER_SIZE,
+                PIPE_BUFFER_SIZE,
+                0,
+                None,
+            )
+        };
+        if handle.is_invalid() {
+            return Err(windows::core::Error::from_win32().into());
+        }
+
+        // A partir daqui o File é dono do handle e o fecha em qualquer caminho
+        let pipe = unsafe { File::from_raw_handle(handle.0 as RawHandle) };
+
+        if let Err(err) = unsafe { ConnectNamedPipe(handle, None) } {
+            // O cliente pode ter conectado entre a criação e o ConnectNamedPipe
+            if err.code() != ERROR_PIPE_CONNECTED.to_hresult() {
+                return Err(err.into());
+            }
+        }
+
+        let mut reader = BufReader::new(pipe.try_clone()?);
+        let mut writer = BufWriter::new(pipe);
+
+        let (version, formats, max_fps) = match read_message(&mut reader)? {
+            Message::Hello { version, formats, max_fps } => (version, formats, max_fps),
+            _ => return Err("Cliente do pipe não iniciou com Hello".into()),
+        };
+
+        if version != PROTOCOL_VERSION {
+            let reason = format!("Versão de protocolo não suportada: {}", version);
+            write_message(&mut writer, &Message::Reject(reason.clone()))?;
+            return Err(reason.into());
+        }
+
+        if !formats.contains(&offer.format) {
+            let reason = format!("Formato {:?} não aceito pelo cliente", offer.format);
+            write_message(&mut writer, &Message::Reject(reason.clone()))?;
+            return Err(reason.into());
+        }
+
+        let format = StreamFormat {
+            fps: if max_fps == 0 { offer.fps } else { offer.fps.min(max_fps) },
+            ..offer
+        };
+        write_message(&mut writer, &Message::Accept(format))?;
+
+        Ok(PipeSession {
+            writer,
+            format,
+            sequence: 0,
+            #[cfg(feature = "encryption")]
+            cipher: None,
+        })
+    }
+}
+
+/// Conexão aceita pelo servidor, usada para enviar quadros
+pub struct PipeSession {
+    writer: BufWriter<File>,
+    format: StreamFormat,
+    sequence: u64,
+    #[cfg(feature = "encryption")]
+    cipher: Option<FrameCipher>,
+}
+
+impl PipeSession {
+    pub fn format(&self) -> StreamFormat {
+        self.format
+    }
+
+    /// Cifra os quadros seguintes; o cliente precisa de `PipeClient::decrypt` com a mesma chave
+    #[cfg(feature = "encryption")]
+    pub fn encrypt(&mut self, cipher: FrameCipher) {
+        self.cipher = Some(cipher);
+    }
+
+    pub fn send_frame(&mut self, data: &[u8], timestamp: i64) -> Result<()> {
+        #[cfg(feature = "encryption")]
+        if let Some(cipher) = self.cipher.as_mut() {
+            let sealed = cipher.seal(self.sequence, timestamp, data)?;
+            write_frame(&mut self.writer, MSG_SEALED_FRAME, self.sequence, timestamp, &sealed)?;
+            self.sequence += 1;
+            return Ok(());
+        }
+
+        write_frame(&mut self.writer, MSG_FRAME, self.sequence, timestamp, data)?;
+        self.sequence += 1;
+        Ok(())
+    }
+
+    /// Encerra o fluxo de forma ordenada
+    pub fn close(mut self) -> Result<()> {
+        write_message(&mut self.writer, &Message::Bye)?;
+        Ok(())
+    }
+}
+
+/// Lado cliente: conecta, negocia e recebe quadros
+pub struct PipeClient {
+    reader: BufReader<File>,
+    writer: File,
+    format: StreamFormat,
+    #[cfg(feature = "encryption")]
+    cipher: Option<FrameCipher>,
+}
+
+impl PipeClient {
+    pub fn connect(name: &str, formats: &[DXGI_FORMAT], max_fps: u32) -> Result<Self> {
+        let pipe = OpenOptions::new().read(true).write(true).open(pipe_path(name))?;
+        let mut writer = pipe.try_clone()?;
+        let mut reader = BufReader::new(pipe);
+
+        write_message(
+            &mut writer,
+            &Message::Hello {
+                version: PROTOCOL_VERSION,
+                formats: formats.to_vec(),
+                max_fps,
+            },
+        )?;
+
+        match read_message(&mut reader)? {
+            Message::Accept(format) => Ok(Self {
+                reader,
+                writer,
+                format,
+                #[cfg(feature = "encryption")]
+                cipher: None,
+            }),
+            Message::Reject(reason) => Err(format!("Servidor recusou a conexão: {}", reason).into()),
+            _ => Err("Resposta inesperada do servidor do pipe".into()),
+        }
+    }
+
+    pub fn format(&self) -> StreamFormat {
+        self.format
+    }
+
+    /// Exige quadros cifrados e os decifra; quadros em claro passam a ser recusados
+    #[cfg(feature = "encryption")]
+    pub fn decrypt(&mut self, cipher: FrameCipher) {
+        self.cipher = Some(cipher);
+    }
+
+    /// Recebe o próximo quadro, ou `None` se o servidor encerrou o fluxo
+    pub fn recv_frame(&mut self) -> Result<Option<PipeFrame>> {
+        match read_message(&mut self.reader)? {
+            #[cfg(feature = "encryption")]
+            Message::Frame { .. } if self.cipher.is_some() => Err("Quadro em claro recebido em um fluxo cifrado".into()),
+            Message::Frame { sequence, timestamp, data } => Ok(Some(PipeFrame { sequence, timestamp, data })),
+            #[cfg(feature = "encryption")]
+            Message::SealedFrame { sequence, timestamp, data } => {
+                let cipher = self.cipher.as_mut().ok_or("Quadro cifrado recebido sem chave (PipeClient::decrypt)")?;
+                let data = cipher.open(sequence, timestamp, &data)?;
+                Ok(Some(PipeFrame { sequence, timestamp, data }))
+            }
+            #[cfg(not(feature = "encryption"))]
+            Message::SealedFrame { .. } => Err("Quadro cifrado recebido; é preciso a feature `encryption`".into()),
+            Message::Bye => Ok(None),
+            _ => Err("Mensagem inesperada no fluxo de quadros".into()),
+        }
+    }
+
+    /// Avisa o servidor que o cliente está saindo
+    pub fn close(mut self) -> Result<()> {
+        write_message(&mut self.writer, &Message::Bye)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use windows::Win32::Graphics::Dxgi::Common::{DXGI_FORMAT_B8G8R8A8_UNORM, DXGI_FORMAT_NV12, DXGI_FORMAT_R8G8B8A8_UNORM};
+
+    fn round_trip(message: &Message) -> Message {
+        let mut bytes = Vec::new();
+        write_message(&mut bytes, message).unwrap();
+        let mut reader = Cursor::new(bytes);
+        let decoded = read_message(&mut reader).unwrap();
+        assert_eq!(reader.position() as usize, reader.get_ref().len(), "sobraram bytes após {:?}", message);
+        decoded
+    }
+
+    #[test]
+    fn every_message_survives_a_round_trip() {
+        let messages = [
+            Message::Hello {
+                version: PROTOCOL_VERSION,
+                formats: vec![DXGI_FORMAT_B8G8R8A8_UNORM, DXGI_FORMAT_R8G8B8A8_UNORM, DXGI_FORMAT_NV12],
+                max_fps: 60,
+            },
+            Message::Hello { version: 7, formats: Vec::new(), max_fps: 0 },
+            Message::Accept(StreamFormat { format: DXGI_FORMAT_NV12, width: 1920, height: 1080, fps: 30 }),
+            Message::Reject("formato não suportado".to_string()),
+            Message::Reject(String::new()),
+            Message::Frame { sequence: 42, timestamp: -5, data: (0..=255).collect() },
+            Message::Frame { sequence: u64::MAX, timestamp: i64::MAX, data: Vec::new() },
+            Message::SealedFrame { sequence: 3, timestamp: 1_000_000, data: vec![0xAB; 33] },
+            Message::Bye,
+        ];
+        for message in &messages {
+            assert_eq!(&round_trip(message), message);
+        }
+    }
+
+    #[test]
+    fn messages_are_read_back_in_order_from_one_stream() {
+        let mut bytes = Vec::new();
+        write_message(&mut bytes, &Message::Frame { sequence: 1, timestamp: 10, data: vec![1, 2, 3] }).unwrap();
+        write_message(&mut bytes, &Message::Bye).unwrap();
+        let mut reader = Cursor::new(bytes);
+        assert_eq!(read_message(&mut reader).unwrap(), Message::Frame { sequence: 1, timestamp: 10, data: vec![1, 2, 3] });
+        assert_eq!(read_message(&mut reader).unwrap(), Message::Bye);
+        assert!(read_message(&mut reader).is_err(), "fim do fluxo deve ser erro");
+    }
+
+    #[test]
+    fn wire_layout_is_kind_then_little_endian_length() {
+        let mut bytes = Vec::new();
+        write_message(&mut bytes, &Message::Reject("no".to_string())).unwrap();
+        assert_eq!(bytes, [MSG_REJECT, 2, 0, 0, 0, b'n', b'o']);
+
+        bytes.clear();
+        write_message(&mut bytes, &Message::Frame { sequence: 1, timestamp: 2, data: vec![9] }).unwrap();
+        assert_eq!(bytes[0], MSG_FRAME);
+        assert_eq!(&bytes[1..5], &17u32.to_le_bytes());
+        assert_eq!(&bytes[5..13], &1u64.to_le_bytes());
+        assert_eq!(&bytes[13..21], &2i64.to_le_bytes());
+        assert_eq!(&bytes[21..], &[9]);
+    }
+
+    #[test]
+    fn truncated_payload_is_an_error() {
+        let mut bytes = Vec::new();
+        write_message(&mut bytes, &Message::Frame { sequence: 1, timestamp: 2, data: vec![0; 64] }).unwrap();
+        bytes.truncate(bytes.len() - 1);
+        assert!(read_message(&mut Cursor::new(bytes)).is_err());
+
+        // Cabeçalho incompleto
+        assert!(read_message(&mut Cursor::new(vec![MSG_BYE, 0, 0])).is_err());
+    }
+
+    #[test]
+    fn payload_shorter_than_its_fields_is_an_error() {
+        // Frame sem os 16 bytes de sequência e timestamp
+        let frame = [&[MSG_FRAME][..], &8u32.to_le_bytes(), &[0; 8]].concat();
+        assert!(read_message(&mut Cursor::new(frame)).is_err());
+
+        // Hello anunciando mais formatos do que o payload traz
+        let hello = [&[MSG_HELLO][..], &10u32.to_le_bytes(), &1u16.to_le_bytes(), &30u32.to_le_bytes(), &2u32.to_le_bytes()].concat();
+        assert!(read_message(&mut Cursor::new(hello)).is_err());
+
+        let accept = [&[MSG_ACCEPT][..], &12u32.to_le_bytes(), &[0; 12]].concat();
+        assert!(read_message(&mut Cursor::new(accept)).is_err());
+    }
+
+    #[test]
+    fn payload_larger_than_the_limit_is_rejected_before_allocating() {
+        // Só o cabeçalho: se o tamanho fosse aceito, read_exact falharia por falta de dados, não pelo limite
+        let header = [&[MSG_FRAME][..], &(MAX_PAYLOAD + 1).to_le_bytes()].concat();
+        let err = read_message(&mut Cursor::new(header)).unwrap_err();
+        assert!(err.to_string().contains("grande demais"), "{}", err);
+
+        let header = [&[MSG_FRAME][..], &u32::MAX.to_le_bytes()].concat();
+        assert!(read_message(&mut Cursor::new(header)).unwrap_err().to_string().contains("grande demais"));
+    }
+
+    #[test]
+    fn unknown_kind_is_an_error() {
+        let bytes = [&[0xEE][..], &0u32.to_le_bytes()].concat();
+        assert!(read_message(&mut Cursor::new(bytes)).is_err());
+    }
+}
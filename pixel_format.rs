@@ -0,0 +1,95 @@
+//! Descrição dos formatos de pixel e cálculo de stride e tamanho de buffer
+//!
+//! Os buffers do crate não têm padding entre linhas, então o stride é sempre o mínimo. Nos
+//! formatos planares (NV12, P010) o plano UV intercalado tem a mesma largura em bytes do
+//! plano Y e metade das linhas, e vem logo após ele.
+
+use windows::Win32::Graphics::Dxgi::Common::{
+    DXGI_FORMAT, DXGI_FORMAT_B8G8R8A8_UNORM, DXGI_FORMAT_NV12, DXGI_FORMAT_P010, DXGI_FORMAT_R10G10B10A2_UNORM,
+    DXGI_FORMAT_R16G16B16A16_FLOAT, DXGI_FORMAT_R8G8B8A8_UNORM,
+};
+
+/// Formatos de pixel produzidos pela captura e pelas conversões
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum PixelFormat {
+    /// B8G8R8A8_UNORM, o formato padrão da duplicação
+    Bgra8,
+    Rgba8,
+    /// R10G10B10A2_UNORM (10 bits por cor, 2 de alfa, em 4 bytes)
+    Rgb10A2,
+    /// R16G16B16A16_FLOAT, usado em monitores HDR
+    Rgba16Float,
+    /// Y 8-bit seguido de UV 8-bit intercalado em 2x2
+    Nv12,
+    /// Como NV12 com amostras de 16 bits (10 significativos)
+    P010,
+}
+
+impl PixelFormat {
+    pub fn from_dxgi(format: DXGI_FORMAT) -> Option<Self> {
+        match format {
+            DXGI_FORMAT_B8G8R8A8_UNORM => Some(PixelFormat::Bgra8),
+            DXGI_FORMAT_R8G8B8A8_UNORM => Some(PixelFormat::Rgba8),
+            DXGI_FORMAT_R10G10B10A2_UNORM => Some(PixelFormat::Rgb10A2),
+            DXGI_FORMAT_R16G16B16A16_FLOAT => Some(PixelFormat::Rgba16Float),
+            DXGI_FORMAT_NV12 => Some(PixelFormat::Nv12),
+            DXGI_FORMAT_P010 => Some(PixelFormat::P010),
+            _ => None,
+        }
+    }
+
+    /// Formato equivalente em DXGI, para texturas e cabeçalhos
+    pub fn to_dxgi(self) -> DXGI_FORMAT {
+        match self {
+            PixelFormat::Bgra8 => DXGI_FORMAT_B8G8R8A8_UNORM,
+            PixelFormat::Rgba8 => DXGI_FORMAT_R8G8B8A8_UNORM,
+            PixelFormat::Rgb10A2 => DXGI_FORMAT_R10G10B10A2_UNORM,
+            PixelFormat::Rgba16Float => DXGI_FORMAT_R16G16B16A16_FLOAT,
+            PixelFormat::Nv12 => DXGI_FORMAT_NV12,
+            PixelFormat::P010 => DXGI_FORMAT_P010,
+        }
+    }
+
+    /// Bytes por pixel; nos formatos planares, bytes por amostra do plano Y
+    pub fn bytes_per_pixel(self) -> u32 {
+        match self {
+            PixelFormat::Bgra8 | PixelFormat::Rgba8 | PixelFormat::Rgb10A2 => 4,
+            PixelFormat::Rgba16Float => 8,
+            PixelFormat::Nv12 => 1,
+            PixelFormat::P010 => 2,
+        }
+    }
+
+    /// Y e UV em planos separados
+    pub fn is_planar(self) -> bool {
+        matches!(self, PixelFormat::Nv12 | PixelFormat::P010)
+    }
+
+    /// Bytes de uma linha sem padding (nos planares, a mesma para os dois planos)
+    pub fn min_stride(self, width: u32) -> usize {
+        width as usize * self.bytes_per_pixel() as usize
+    }
+
+    /// Linhas do buffer, contando as do plano UV nos formatos planares
+    pub fn rows(self, height: u32) -> usize {
+        let height = height as usize;
+        if self.is_planar() {
+            height + height.div_ceil(2)
+        } else {
+            height
+        }
+    }
+
+    /// Tamanho em bytes de um quadro `width`x`height` sem padding
+    pub fn buffer_size(self, width: u32, height: u32) -> usize {
+        self.min_stride(width) * self.rows(height)
+    }
+}
+
+/// Formato para os cálculos de tamanho de um `DXGI_FORMAT` qualquer; formatos fora de
+/// `PixelFormat` são tratados como 4 bytes por pixel, como os da duplicação legada
+pub(crate) fn layout_of(format: DXGI_FORMAT) -> PixelFormat {
+    PixelFormat::from_dxgi(format).unwrap_or(PixelFormat::Bgra8)
+}
@@ -0,0 +1,240 @@
+//! Gatilhos por cor de pixels ou regiões pequenas
+//!
+//! Um `PixelWatcher` observa pontos da tela (um indicador de vida em um jogo, o LED de status
+//! de um painel, o botão que fica verde) e chama um callback quando a cor média da região
+//! cruza uma condição. A cada quadro só as regiões observadas são copiadas e lidas, lado a
+//! lado em uma única staging (`DxgiCapture::capture_patches`), então o custo é quase nulo
+//! mesmo com dezenas de pontos.
+//!
+//! As condições disparam na transição: `Matches` avisa quando a cor passa a coincidir, não a
+//! cada quadro em que continua igual.
+
+use std::fmt;
+
+use crate::annotate::Color;
+use crate::convert::rgba8_reader;
+use crate::error::{CaptureError, CaptureResult};
+use crate::frame::Frame;
+use crate::geometry::{Point, Rect};
+use crate::DxgiCapture;
+
+/// Quando o callback de uma região é chamado
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum PixelCondition {
+    /// A cor se afastou mais de `tolerance` (em algum canal) da cor do último disparo; a
+    /// primeira leitura só define a referência
+    Changes { tolerance: u8 },
+    /// A cor passou a ficar a até `tolerance` de `color` em todos os canais
+    Matches { color: Color, tolerance: u8 },
+    /// A cor passou a diferir de `reference` em mais de `delta` em algum canal
+    ExceedsDelta { reference: Color, delta: u8 },
+}
+
+impl PixelCondition {
+    /// A condição vale para `color`, dada a referência de `Changes`
+    fn holds(&self, color: Color, baseline: Color) -> bool {
+        match *self {
+            PixelCondition::Changes { tolerance } => distance(color, baseline) > tolerance,
+            PixelCondition::Matches { color: target, tolerance } => distance(color, target) <= tolerance,
+            PixelCondition::ExceedsDelta { reference, delta } => distance(color, reference) > delta,
+        }
+    }
+}
+
+/// Identificador de uma região observada, para `PixelWatcher::unwatch`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WatchId(u64);
+
+/// Disparo de uma condição
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PixelEvent {
+    pub id: WatchId,
+    /// Região observada, relativa ao monitor
+    pub region: Rect,
+    /// Cor média da região neste quadro (o alfa da área de trabalho é indefinido)
+    pub color: Color,
+    /// Cor média na leitura anterior; igual a `color` na primeira
+    pub previous: Color,
+    pub condition: PixelCondition,
+    /// `sequence` e `present_time` do quadro em que a condição disparou
+    pub sequence: u64,
+    pub present_time: i64,
+}
+
+type Callback = Box<dyn FnMut(&PixelEvent) + Send>;
+
+struct Watch {
+    id: WatchId,
+    region: Rect,
+    condition: PixelCondition,
+    callback: Callback,
+    // Última cor lida e a referência de `Changes` (cor do último disparo)
+    last: Option<Color>,
+    baseline: Option<Color>,
+    // A condição valia na leitura anterior
+    active: bool,
+}
+
+impl Watch {
+    /// Atualiza o estado com a cor lida e retorna o evento se a condição disparou
+    fn update(&mut self, color: Color, sequence: u64, present_time: i64) -> Option<PixelEvent> {
+        let previous = self.last.replace(color).unwrap_or(color);
+        let baseline = *self.baseline.get_or_insert(color);
+        let holds = self.condition.holds(color, baseline);
+        let fired = match self.condition {
+            // Cada mudança vira a nova referência, então mudanças seguidas disparam de novo
+            PixelCondition::Changes { .. } => holds,
+            _ => holds && !self.active,
+        };
+        self.active = holds;
+        if !fired {
+            return None;
+        }
+        if let PixelCondition::Changes { .. } = self.condition {
+            self.baseline = Some(color);
+        }
+
+        let event = PixelEvent {
+            id: self.id,
+            region: self.region,
+            color,
+            previous,
+            condition: self.condition,
+            sequence,
+            present_time,
+        };
+        (self.callback)(&event);
+        Some(event)
+    }
+}
+
+/// Observa regiões pequenas e chama callbacks quando suas cores cruzam uma condição
+#[derive(Default)]
+pub struct PixelWatcher {
+    watches: Vec<Watch>,
+    next_id: u64,
+}
+
+impl PixelWatcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Observa a cor média de `region` (relativa ao monitor); regiões de poucos pixels
+    /// resistem melhor a ruído de compressão e antialiasing que um pixel só
+    pub fn watch(&mut self, region: Rect, condition: PixelCondition, callback: impl FnMut(&PixelEvent) + Send + 'static) -> WatchId {
+        let id = WatchId(self.next_id);
+        self.next_id += 1;
+        self.watches.push(Watch {
+            id,
+            region,
+            condition,
+            callback: Box::new(callback),
+            last: None,
+            baseline: None,
+            active: false,
+        });
+        id
+    }
+
+    /// Observa um único pixel
+    pub fn watch_pixel(&mut self, point: Point, condition: PixelCondition, callback: impl FnMut(&PixelEvent) + Send + 'static) -> WatchId {
+        self.watch(Rect::new(point.x, point.y, 1, 1), condition, callback)
+    }
+
+    /// Deixa de observar a região; `false` se o id não existe mais
+    pub fn unwatch(&mut self, id: WatchId) -> bool {
+        let before = self.watches.len();
+        self.watches.retain(|watch| watch.id != id);
+        self.watches.len() != before
+    }
+
+    pub fn len(&self) -> usize {
+        self.watches.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.watches.is_empty()
+    }
+
+    /// Lê só as regiões observadas do quadro atual e chama os callbacks das condições que
+    /// dispararam, na ordem em que foram registradas.
+    ///
+    /// Sem quadro novo nada é lido e o retorno é vazio, já que nenhuma cor mudou.
+    pub fn poll(&mut self, capture: &mut DxgiCapture) -> CaptureResult<Vec<PixelEvent>> {
+        if self.watches.is_empty() {
+            return Ok(Vec::new());
+        }
+        let regions: Vec<Rect> = self.watches.iter().map(|watch| watch.region).collect();
+        let patches = match capture.capture_patches(&regions) {
+            Ok(patches) => patches,
+            Err(CaptureError::NoNewFrame) => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        let mut events = Vec::new();
+        for (watch, patch) in self.watches.iter_mut().zip(&patches) {
+            let color = average_color(patch, Rect::new(0, 0, patch.width(), patch.height()))?;
+            events.extend(watch.update(color, patch.sequence, patch.present_time));
+        }
+        Ok(events)
+    }
+
+    /// Avalia as regiões em um quadro já capturado (ex.: de um `CaptureStream`); regiões fora
+    /// de `frame.region` são ignoradas neste quadro
+    pub fn check(&mut self, frame: &Frame) -> CaptureResult<Vec<PixelEvent>> {
+        let mut events = Vec::new();
+        for watch in &mut self.watches {
+            if !frame.region.contains_rect(&watch.region) {
+                continue;
+            }
+            let local = Rect::new(
+                watch.region.left - frame.region.left,
+                watch.region.top - frame.region.top,
+                watch.region.width,
+                watch.region.height,
+            );
+            let color = average_color(frame, local)?;
+            events.extend(watch.update(color, frame.sequence, frame.present_time));
+        }
+        Ok(events)
+    }
+}
+
+impl fmt::Debug for PixelWatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PixelWatcher")
+            .field("regions", &self.watches.iter().map(|watch| watch.region).collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// Maior diferença entre os canais RGB
+fn distance(a: Color, b: Color) -> u8 {
+    a.r.abs_diff(b.r).max(a.g.abs_diff(b.g)).max(a.b.abs_diff(b.b))
+}
+
+/// Cor média de `area` (relativa ao quadro)
+fn average_color(frame: &Frame, area: Rect) -> CaptureResult<Color> {
+    let read = rgba8_reader(frame.format)
+        .ok_or_else(|| CaptureError::Conversion(format!("unsupported pixel format for color sampling: {:?}", frame.format)))?;
+    let stride = frame.stride();
+    let pixel_bytes = stride / frame.width().max(1) as usize;
+    let (left, top) = (area.left as usize, area.top as usize);
+
+    let mut sum = [0u64; 4];
+    for row in frame.data.chunks_exact(stride).skip(top).take(area.height as usize) {
+        let start = left * pixel_bytes;
+        for pixel in row[start..start + area.width as usize * pixel_bytes].chunks_exact(pixel_bytes) {
+            for (total, value) in sum.iter_mut().zip(read(pixel)) {
+                *total += value as u64;
+            }
+        }
+    }
+
+    let count = area.size().area().max(1);
+    let [r, g, b, a] = sum.map(|total| ((total + count / 2) / count) as u8);
+    Ok(Color::rgba(r, g, b, a))
+}
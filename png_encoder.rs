@@ -0,0 +1,131 @@
+//! Codificação de quadros em PNG (feature `png`)
+//!
+//! Para capturas de tela anexadas a alertas, relatórios e testes. O alfa da área de trabalho é
+//! indefinido, então a imagem sai em RGB. Quadros de 8 bits geram PNG de 8 bits; quadros FP16 e
+//! de 10 bits geram PNG de 16 bits por canal para não truncar a precisão: HDR (scRGB ou HDR10)
+//! sai em PQ com primárias BT.2020, marcado com o chunk `cICP`, e SDR sai em sRGB. Para um PNG
+//! de 8 bits de um quadro HDR, converta antes com `Frame::to_color_space(ColorSpace::Srgb)`.
+//!
+//! `load_png` faz o caminho inverso para imagens de referência de testes: lê um PNG como
+//! quadro R8G8B8A8 em sRGB.
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+use windows::Win32::Graphics::Dxgi::Common::{
+    DXGI_FORMAT_R10G10B10A2_UNORM, DXGI_FORMAT_R16G16B16A16_FLOAT, DXGI_FORMAT_R8G8B8A8_UNORM,
+};
+
+use crate::convert::{rgba8_reader, to_rgb16};
+use crate::frame::{Frame, FrameMetadata};
+use crate::geometry::Rect;
+use crate::outputs::ColorSpace;
+use crate::Result;
+
+/// Codifica o quadro em um PNG em memória
+pub fn encode_png(frame: &Frame) -> Result<Vec<u8>> {
+    let mut png = Vec::new();
+    write_png(frame, &mut png)?;
+    Ok(png)
+}
+
+/// Grava o quadro como PNG em `path`
+pub fn save_png(frame: &Frame, path: impl AsRef<Path>) -> Result<()> {
+    write_png(frame, BufWriter::new(File::create(path)?))
+}
+
+/// Escreve o PNG do quadro em `writer`
+pub fn write_png<W: Write>(frame: &Frame, writer: W) -> Result<()> {
+    if matches!(frame.format, DXGI_FORMAT_R16G16B16A16_FLOAT | DXGI_FORMAT_R10G10B10A2_UNORM) {
+        return write_png16(frame, writer);
+    }
+    let read = rgba8_reader(frame.format).ok_or_else(|| format!("Formato não suportado para PNG: {:?}", frame.format))?;
+    let (width, height) = (frame.width() as usize, frame.height() as usize);
+    let stride = frame.stride();
+    if width == 0 || height == 0 {
+        return Err("Quadro vazio".into());
+    }
+    if frame.data.len() < stride * height {
+        return Err("Buffer menor que o quadro".into());
+    }
+
+    let pixel_bytes = stride / width;
+    let mut rgb = Vec::with_capacity(width * height * 3);
+    for row in frame.data.chunks_exact(stride).take(height) {
+        for pixel in row.chunks_exact(pixel_bytes).take(width) {
+            let [r, g, b, _] = read(pixel);
+            rgb.extend_from_slice(&[r, g, b]);
+        }
+    }
+
+    let mut encoder = png::Encoder::new(writer, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    // Capturas de tela comprimem bem mesmo no nível rápido
+    encoder.set_compression(png::Compression::Fast);
+    if frame.color_space == ColorSpace::Srgb {
+        encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+    }
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&rgb)?;
+    writer.finish()?;
+    Ok(())
+}
+
+/// PNG de 16 bits por canal, em HDR10 para quadros HDR e em sRGB para os demais
+fn write_png16<W: Write>(frame: &Frame, writer: W) -> Result<()> {
+    let (width, height) = (frame.width(), frame.height());
+    if width == 0 || height == 0 {
+        return Err("Quadro vazio".into());
+    }
+    let target = if frame.color_space.is_hdr() { ColorSpace::Hdr10 } else { ColorSpace::Srgb };
+    let rgb = to_rgb16(&frame.data, frame.format, frame.color_space, target, width, height)?;
+
+    let mut encoder = png::Encoder::new(writer, width, height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Sixteen);
+    encoder.set_compression(png::Compression::Fast);
+    if target == ColorSpace::Srgb {
+        encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+    }
+
+    let mut writer = encoder.write_header()?;
+    if let Some(cicp) = target.cicp() {
+        // cICP (PNG 3ª edição) identifica o espaço; sem ele o HDR10 seria exibido como sRGB
+        let data = [cicp.color_primaries, cicp.transfer_characteristics, cicp.matrix_coefficients, cicp.full_range as u8];
+        writer.write_chunk(png::chunk::ChunkType(*b"cICP"), &data)?;
+    }
+    writer.write_image_data(&rgb)?;
+    writer.finish()?;
+    Ok(())
+}
+
+/// Lê um PNG como quadro R8G8B8A8 em sRGB. Paletas e tons de cinza são expandidos, PNGs de 16
+/// bits são reduzidos a 8 e imagens sem alfa recebem alfa opaco.
+pub fn load_png(path: impl AsRef<Path>) -> Result<Frame> {
+    let mut decoder = png::Decoder::new(BufReader::new(File::open(path)?));
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info()?;
+    let mut buffer = vec![0u8; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer)?;
+    let pixels = &buffer[..info.buffer_size()];
+
+    let data = match info.color_type {
+        png::ColorType::Rgba => pixels.to_vec(),
+        png::ColorType::Rgb => pixels.chunks_exact(3).flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255]).collect(),
+        png::ColorType::GrayscaleAlpha => pixels.chunks_exact(2).flat_map(|ga| [ga[0], ga[0], ga[0], ga[1]]).collect(),
+        png::ColorType::Grayscale => pixels.iter().flat_map(|&gray| [gray, gray, gray, 255]).collect(),
+        png::ColorType::Indexed => return Err("PNG indexado não expandido".into()),
+    };
+    Ok(Frame {
+        data,
+        region: Rect::new(0, 0, info.width, info.height),
+        format: DXGI_FORMAT_R8G8B8A8_UNORM,
+        color_space: ColorSpace::Srgb,
+        present_time: 0,
+        sequence: 0,
+        metadata: FrameMetadata::default(),
+    })
+}
@@ -0,0 +1,147 @@
+//! Prioridade de tempo real suave para threads de captura (MMCSS)
+//!
+//! Registrada no Multimedia Class Scheduler Service, a thread recebe prioridade alta em
+//! fatias garantidas mesmo com a CPU ocupada, o que reduz o jitter entre quadros em streaming.
+//! Se o serviço não estiver disponível (ex.: Windows Server sem o serviço, sandbox), a thread
+//! cai para `THREAD_PRIORITY_HIGHEST`; se nem isso for permitido, segue com a prioridade normal.
+
+use windows::core::HSTRING;
+use windows::Win32::Foundation::HANDLE;
+use windows::Win32::System::Threading::{
+    AvRevertMmThreadCharacteristics, AvSetMmThreadCharacteristicsW, AvSetMmThreadPriority, GetCurrentThread,
+    GetThreadPriority, SetThreadPriority, AVRT_PRIORITY, AVRT_PRIORITY_CRITICAL, AVRT_PRIORITY_HIGH,
+    AVRT_PRIORITY_LOW, AVRT_PRIORITY_NORMAL, THREAD_PRIORITY, THREAD_PRIORITY_HIGHEST,
+};
+
+/// Classe de tarefa do MMCSS (chaves em `...\Multimedia\SystemProfile\Tasks`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum MmcssTask {
+    /// "Capture": a classe pensada para captura de áudio e vídeo
+    #[default]
+    Capture,
+    /// "Games": prioridade de GPU mais alta, para capturas que acompanham jogos
+    Games,
+    /// "Playback"
+    Playback,
+}
+
+impl MmcssTask {
+    fn name(&self) -> &'static str {
+        match self {
+            MmcssTask::Capture => "Capture",
+            MmcssTask::Games => "Games",
+            MmcssTask::Playback => "Playback",
+        }
+    }
+}
+
+/// Prioridade relativa dentro da classe do MMCSS
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum MmcssPriority {
+    Low,
+    Normal,
+    #[default]
+    High,
+    Critical,
+}
+
+impl MmcssPriority {
+    fn avrt(&self) -> AVRT_PRIORITY {
+        match self {
+            MmcssPriority::Low => AVRT_PRIORITY_LOW,
+            MmcssPriority::Normal => AVRT_PRIORITY_NORMAL,
+            MmcssPriority::High => AVRT_PRIORITY_HIGH,
+            MmcssPriority::Critical => AVRT_PRIORITY_CRITICAL,
+        }
+    }
+}
+
+/// Pedido de prioridade para a thread de captura
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct RealtimePriority {
+    pub task: MmcssTask,
+    pub priority: MmcssPriority,
+}
+
+/// O que `boost_current_thread` conseguiu aplicar
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoostKind {
+    /// Registrada no MMCSS
+    Mmcss,
+    /// MMCSS indisponível; só a prioridade da thread foi elevada
+    ThreadPriority,
+    /// Nada foi alterado
+    None,
+}
+
+/// Prioridade elevada da thread atual; desfeita ao sair de escopo (na mesma thread)
+#[derive(Debug)]
+pub struct PriorityBoost {
+    mmcss: Option<HANDLE>,
+    previous_priority: Option<THREAD_PRIORITY>,
+}
+
+impl PriorityBoost {
+    pub fn kind(&self) -> BoostKind {
+        if self.mmcss.is_some() {
+            BoostKind::Mmcss
+        } else if self.previous_priority.is_some() {
+            BoostKind::ThreadPriority
+        } else {
+            BoostKind::None
+        }
+    }
+}
+
+/// Eleva a prioridade da thread atual conforme `request`, com os fallbacks descritos no módulo
+pub fn boost_current_thread(request: RealtimePriority) -> PriorityBoost {
+    let mut task_index = 0u32;
+    let task = HSTRING::from(request.task.name());
+    match unsafe { AvSetMmThreadCharacteristicsW(&task, &mut task_index) } {
+        Ok(handle) => {
+            if unsafe { AvSetMmThreadPriority(handle, request.priority.avrt()) }.is_err() {
+                debug!("MMCSS recusou a prioridade {:?}, mantendo a da classe", request.priority);
+            }
+            info!("Thread registrada no MMCSS como \"{}\" ({:?})", request.task.name(), request.priority);
+            return PriorityBoost {
+                mmcss: Some(handle),
+                previous_priority: None,
+            };
+        }
+        Err(e) => warn!("MMCSS indisponível ({}), elevando só a prioridade da thread", e),
+    }
+
+    let thread = unsafe { GetCurrentThread() };
+    let previous = THREAD_PRIORITY(unsafe { GetThreadPriority(thread) });
+    if unsafe { SetThreadPriority(thread, THREAD_PRIORITY_HIGHEST) }.is_ok() {
+        PriorityBoost {
+            mmcss: None,
+            previous_priority: Some(previous),
+        }
+    } else {
+        warn!("Não foi possível elevar a prioridade da thread de captura");
+        PriorityBoost {
+            mmcss: None,
+            previous_priority: None,
+        }
+    }
+}
+
+impl Drop for PriorityBoost {
+    fn drop(&mut self) {
+        unsafe {
+            if let Some(handle) = self.mmcss.take() {
+                let _ = AvRevertMmThreadCharacteristics(handle);
+            }
+            if let Some(previous) = self.previous_priority.take() {
+                let _ = SetThreadPriority(GetCurrentThread(), previous);
+            }
+        }
+    }
+}
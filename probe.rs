@@ -0,0 +1,265 @@
+//! Diagnóstico rápido do que a captura consegue fazer nesta máquina
+//!
+//! `probe()` tenta criar a duplicação em cada monitor com cada formato, anota o que falhou e
+//! por quê, e libera tudo antes de retornar. Serve a assistentes de configuração ("a captura vai
+//! funcionar aqui?") e a relatórios de suporte: `CapabilityReport` é serializável com a feature
+//! `serde` e `problems()` lista as falhas com a ação que costuma resolvê-las.
+//!
+//! As duplicações criadas pelo diagnóstico ocupam por um instante as vagas do monitor
+//! (`MAX_DUPLICATIONS_PER_OUTPUT`); não o execute em um laço junto com capturas ativas.
+
+use windows::core::*;
+use windows::Win32::Graphics::Direct3D::{D3D_DRIVER_TYPE_UNKNOWN, D3D_FEATURE_LEVEL_11_0};
+use windows::Win32::Graphics::Direct3D11::*;
+use windows::Win32::Graphics::Dxgi::*;
+
+use crate::capabilities::{capabilities, Capabilities, BUILD_GRAPHICS_CAPTURE};
+use crate::error::{CaptureError, CaptureResult};
+use crate::outputs::{wide_to_string, ColorSpace};
+use crate::pixel_format::PixelFormat;
+use crate::session::{self, SessionInfo};
+
+/// Formatos testados em `DuplicateOutput1`: os da captura e o R10G10B10A2 do HDR10
+const PROBED_FORMATS: [PixelFormat; 4] = [
+    PixelFormat::Bgra8,
+    PixelFormat::Rgba8,
+    PixelFormat::Rgba16Float,
+    PixelFormat::Rgb10A2,
+];
+
+/// Motivo de uma falha, com o código estável do `CaptureError` correspondente
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProbeFailure {
+    /// Código do `CaptureError` (ex.: `DXGI-E011`)
+    pub code: String,
+    pub hresult: Option<u32>,
+    /// Mensagem em inglês com o que fazer, como nos logs
+    pub message: String,
+    /// Conflito passageiro (outro aplicativo, UAC, sessão desconectada): tentar de novo depois
+    pub retry_later: bool,
+}
+
+impl ProbeFailure {
+    pub fn from_error(error: &CaptureError) -> Self {
+        let mut message = error.to_string();
+        // Notebooks híbridos: o monitor está na GPU integrada e o processo roda na dedicada
+        if error.hresult() == Some(DXGI_ERROR_UNSUPPORTED) && !session::is_remote_session() {
+            message.push_str(
+                "; on hybrid-GPU laptops run the application on the GPU that drives the monitor \
+                 (Settings > System > Display > Graphics, \"Power saving\")",
+            );
+        } else if matches!(error, CaptureError::DeviceCreation { .. }) {
+            message.push_str("; update the display driver or check that the GPU supports Direct3D 11");
+        }
+        Self {
+            code: error.code().to_owned(),
+            hresult: error.hresult().map(|hresult| hresult.0 as u32),
+            message,
+            retry_later: error.is_temporarily_unavailable(),
+        }
+    }
+}
+
+/// Resultado do diagnóstico de um monitor
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OutputProbe {
+    pub adapter_index: u32,
+    pub output_index: u32,
+    pub adapter_name: String,
+    pub device_name: String,
+    pub attached_to_desktop: bool,
+    pub hdr_active: bool,
+    /// Formatos aceitos pela duplicação (só B8G8R8A8 com a API legada)
+    pub formats: Vec<PixelFormat>,
+    /// Por que a duplicação não pôde ser criada; `None` se algum formato foi aceito
+    pub failure: Option<ProbeFailure>,
+}
+
+impl OutputProbe {
+    pub fn can_duplicate(&self) -> bool {
+        !self.formats.is_empty()
+    }
+
+    /// A duplicação entrega FP16 ou R10G10B10A2, preservando o HDR
+    pub fn hdr_formats(&self) -> bool {
+        self.formats.iter().any(|format| matches!(format, PixelFormat::Rgba16Float | PixelFormat::Rgb10A2))
+    }
+}
+
+/// Resultado de `probe()`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CapabilityReport {
+    pub capabilities: Capabilities,
+    /// Sessão do processo, se pôde ser consultada
+    pub session: Option<SessionInfo>,
+    /// A sessão não permite capturar (ex.: serviço na sessão 0); os monitores nem são testados
+    pub session_failure: Option<ProbeFailure>,
+    /// `Windows.Graphics.Capture` pode capturar monitores nesta sessão
+    pub graphics_capture: bool,
+    /// Por que `Windows.Graphics.Capture` não está disponível
+    pub graphics_capture_failure: Option<String>,
+    pub outputs: Vec<OutputProbe>,
+}
+
+impl CapabilityReport {
+    /// Algum monitor pode ser duplicado
+    pub fn can_capture(&self) -> bool {
+        self.outputs.iter().any(OutputProbe::can_duplicate)
+    }
+
+    /// Falhas encontradas, uma por linha, prontas para mostrar ao usuário ou anexar a um chamado
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if let Some(failure) = &self.session_failure {
+            problems.push(failure.message.clone());
+        }
+        if self.session_failure.is_none() && self.outputs.is_empty() {
+            problems.push("no outputs found; connect a monitor or check the display driver".to_owned());
+        }
+        for output in &self.outputs {
+            if let Some(failure) = &output.failure {
+                problems.push(format!("{} ({}): {}", output.device_name, output.adapter_name, failure.message));
+            } else if output.hdr_active && !output.hdr_formats() {
+                problems.push(format!(
+                    "{}: HDR is on but the duplication only delivers 8-bit formats; captures are tone mapped to SDR",
+                    output.device_name
+                ));
+            }
+        }
+        if let Some(reason) = &self.graphics_capture_failure {
+            problems.push(format!("Windows.Graphics.Capture: {}", reason));
+        }
+        problems
+    }
+}
+
+/// Testa a captura em todos os monitores sem manter recursos
+pub fn probe() -> CaptureResult<CapabilityReport> {
+    let capabilities = capabilities()?;
+    let session = SessionInfo::current().ok();
+    let session_failure = session::ensure_interactive().err().map(|e| ProbeFailure::from_error(&e));
+
+    let graphics_capture_failure = if !capabilities.graphics_capture {
+        Some(format!(
+            "requires Windows 10 build {} or later (this is build {})",
+            BUILD_GRAPHICS_CAPTURE, capabilities.os_version.build
+        ))
+    } else if session_failure.is_some() {
+        Some("not available outside the interactive user session".to_owned())
+    } else {
+        None
+    };
+
+    let mut outputs = Vec::new();
+    if session_failure.is_none() {
+        let factory: IDXGIFactory1 = unsafe { CreateDXGIFactory1()? };
+        let mut adapter_index = 0;
+        while let Ok(adapter) = unsafe { factory.EnumAdapters1(adapter_index) } {
+            probe_adapter(&adapter, adapter_index, &mut outputs)?;
+            adapter_index += 1;
+        }
+    }
+
+    Ok(CapabilityReport {
+        capabilities,
+        session,
+        session_failure,
+        graphics_capture: graphics_capture_failure.is_none(),
+        graphics_capture_failure,
+        outputs,
+    })
+}
+
+fn probe_adapter(adapter: &IDXGIAdapter1, adapter_index: u32, outputs: &mut Vec<OutputProbe>) -> CaptureResult<()> {
+    let mut adapter_desc = DXGI_ADAPTER_DESC1::default();
+    unsafe {
+        adapter.GetDesc1(&mut adapter_desc)?;
+    }
+    // Um dispositivo por adaptador, criado só se ele tiver monitores
+    let mut device: Option<CaptureResult<ID3D11Device>> = None;
+
+    let mut output_index = 0;
+    while let Ok(output) = unsafe { adapter.EnumOutputs(output_index) } {
+        let mut output_desc = DXGI_OUTPUT_DESC::default();
+        unsafe {
+            output.GetDesc(&mut output_desc)?;
+        }
+        let hdr_active = output.cast::<IDXGIOutput6>().is_ok_and(|output6| {
+            let mut desc = DXGI_OUTPUT_DESC1::default();
+            unsafe { output6.GetDesc1(&mut desc) }.is_ok() && ColorSpace::from_dxgi(desc.ColorSpace).is_hdr()
+        });
+
+        let (formats, failure) = match device.get_or_insert_with(|| create_device(adapter)) {
+            Ok(device) => probe_formats(&output, device),
+            Err(e) => (Vec::new(), Some(ProbeFailure::from_error(e))),
+        };
+
+        outputs.push(OutputProbe {
+            adapter_index,
+            output_index,
+            adapter_name: wide_to_string(&adapter_desc.Description),
+            device_name: wide_to_string(&output_desc.DeviceName),
+            attached_to_desktop: output_desc.AttachedToDesktop.as_bool(),
+            hdr_active,
+            formats,
+            failure,
+        });
+        output_index += 1;
+    }
+    Ok(())
+}
+
+fn create_device(adapter: &IDXGIAdapter1) -> CaptureResult<ID3D11Device> {
+    let mut device: Option<ID3D11Device> = None;
+    unsafe {
+        D3D11CreateDevice(
+            adapter,
+            D3D_DRIVER_TYPE_UNKNOWN,
+            None,
+            D3D11_CREATE_DEVICE_BGRA_SUPPORT,
+            Some(&[D3D_FEATURE_LEVEL_11_0]),
+            D3D11_SDK_VERSION,
+            Some(&mut device),
+            None,
+            None,
+        )
+    }
+    .map_err(|e| CaptureError::DeviceCreation { hresult: Some(e.code()) })?;
+    device.ok_or(CaptureError::DeviceCreation { hresult: None })
+}
+
+/// Formatos aceitos e, se nenhum foi, o motivo; cada duplicação é liberada logo em seguida
+fn probe_formats(output: &IDXGIOutput, device: &ID3D11Device) -> (Vec<PixelFormat>, Option<ProbeFailure>) {
+    let mut formats = Vec::new();
+    let mut last_error = None;
+
+    match output.cast::<IDXGIOutput5>() {
+        Ok(output5) => {
+            for format in PROBED_FORMATS {
+                match unsafe { output5.DuplicateOutput1(device, 0, &[format.to_dxgi()]) } {
+                    Ok(_duplication) => formats.push(format),
+                    Err(e) => {
+                        debug!("Diagnóstico: formato {:?} recusado: {}", format, e);
+                        last_error = Some(e.code());
+                        // Limite de duplicações ou desktop seguro: outro formato não ajuda
+                        if CaptureError::from_duplication(last_error).is_temporarily_unavailable() {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        Err(_) => match output.cast::<IDXGIOutput1>().map(|output1| unsafe { output1.DuplicateOutput(device) }) {
+            Ok(Ok(_duplication)) => formats.push(PixelFormat::Bgra8),
+            Ok(Err(e)) | Err(e) => last_error = Some(e.code()),
+        },
+    }
+
+    let failure = formats
+        .is_empty()
+        .then(|| ProbeFailure::from_error(&CaptureError::from_duplication(last_error)));
+    (formats, failure)
+}
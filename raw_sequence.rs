@@ -0,0 +1,564 @@
+//! Contêiner simples para gravar e reproduzir sequências de quadros brutos
+//!
+//! Layout (little-endian):
+//! - cabeçalho: magic `DXGIRAW\0`, versão (u16), reservado (u16), formato DXGI (u32),
+//!   largura (u32), altura (u32), tamanho do quadro em bytes (u64)
+//! - quadros: timestamp (i64) seguido de `frame_size` bytes
+//! - índice: para cada quadro, offset (u64) e timestamp (i64)
+//! - rodapé: número de quadros (u64), offset do índice (u64), magic `DXGIIDX\0`
+//!
+//! Gravações longas podem ser divididas em segmentos (`SegmentedRawWriter`), cada um uma
+//! sequência completa; uma queda de energia perde no máximo o segmento em andamento.
+
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use windows::Win32::Graphics::Dxgi::Common::DXGI_FORMAT;
+
+use crate::pixel_format::layout_of;
+use crate::recording::{Marker, RecordingControl};
+use crate::Result;
+
+const HEADER_MAGIC: &[u8; 8] = b"DXGIRAW\0";
+const FOOTER_MAGIC: &[u8; 8] = b"DXGIIDX\0";
+const VERSION: u16 = 1;
+const HEADER_LEN: u64 = 32;
+const FOOTER_LEN: u64 = 24;
+const RECORD_HEADER_LEN: u64 = 8;
+const INDEX_ENTRY_LEN: u64 = 16;
+
+/// Quantidade de quadros aguardando escrita antes de `write_frame` bloquear
+const WRITE_QUEUE_DEPTH: usize = 8;
+/// Extensão do segmento em gravação, trocada pela final quando ele é fechado
+const PARTIAL_EXTENSION: &str = "partial";
+
+/// Descrição dos quadros armazenados em uma sequência
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RawSequenceHeader {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_support::dxgi_format"))]
+    pub format: DXGI_FORMAT,
+    pub width: u32,
+    pub height: u32,
+    pub frame_size: u64,
+}
+
+impl RawSequenceHeader {
+    pub fn new(format: DXGI_FORMAT, width: u32, height: u32) -> Self {
+        Self {
+            format,
+            width,
+            height,
+            frame_size: layout_of(format).buffer_size(width, height) as u64,
+        }
+    }
+
+    fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(HEADER_MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        writer.write_all(&0u16.to_le_bytes())?;
+        writer.write_all(&self.format.0.to_le_bytes())?;
+        writer.write_all(&self.width.to_le_bytes())?;
+        writer.write_all(&self.height.to_le_bytes())?;
+        writer.write_all(&self.frame_size.to_le_bytes())
+    }
+
+    fn read_from<R: Read>(reader: &mut R) -> Result<Self> {
+        let mut buf = [0u8; HEADER_LEN as usize];
+        reader.read_exact(&mut buf)?;
+
+        if &buf[0..8] != HEADER_MAGIC {
+            return Err("Arquivo não é uma sequência bruta válida".into());
+        }
+
+        let version = u16::from_le_bytes([buf[8], buf[9]]);
+        if version != VERSION {
+            return Err(format!("Versão de sequência não suportada: {}", version).into());
+        }
+
+        let header = Self {
+            format: DXGI_FORMAT(u32::from_le_bytes(buf[12..16].try_into()?)),
+            width: u32::from_le_bytes(buf[16..20].try_into()?),
+            height: u32::from_le_bytes(buf[20..24].try_into()?),
+            frame_size: u64::from_le_bytes(buf[24..32].try_into()?),
+        };
+
+        // O tamanho do quadro vem do arquivo; só é aceito se bater com o formato e as dimensões
+        let layout = layout_of(header.format);
+        let expected = (layout.min_stride(header.width) as u64).checked_mul(layout.rows(header.height) as u64);
+        if expected != Some(header.frame_size) {
+            return Err(format!(
+                "Tamanho de quadro inconsistente no cabeçalho: {} bytes para {}x{}",
+                header.frame_size, header.width, header.height
+            )
+            .into());
+        }
+
+        Ok(header)
+    }
+}
+
+/// Quadro lido de uma sequência
+#[derive(Debug, Clone)]
+pub struct RawFrame {
+    pub timestamp: i64,
+    pub data: Vec<u8>,
+}
+
+/// Grava quadros em disco em uma thread dedicada, com escrita bufferizada.
+///
+/// Os marcadores de `add_marker` vão para `<arquivo>.markers.json` ao finalizar.
+pub struct RawSequenceWriter {
+    header: RawSequenceHeader,
+    path: PathBuf,
+    control: RecordingControl,
+    sender: Option<SyncSender<(i64, Vec<u8>)>>,
+    worker: Option<JoinHandle<io::Result<u64>>>,
+}
+
+impl RawSequenceWriter {
+    pub fn create<P: AsRef<Path>>(path: P, header: RawSequenceHeader) -> Result<Self> {
+        let mut file = BufWriter::new(File::create(&path)?);
+        header.write_to(&mut file)?;
+
+        let (sender, receiver) = mpsc::sync_channel(WRITE_QUEUE_DEPTH);
+        let worker = thread::spawn(move || write_loop(file, receiver));
+
+        Ok(Self {
+            header,
+            path: path.as_ref().to_path_buf(),
+            control: RecordingControl::new(),
+            sender: Some(sender),
+            worker: Some(worker),
+        })
+    }
+
+    pub fn header(&self) -> &RawSequenceHeader {
+        &self.header
+    }
+
+    /// Descarta os quadros recebidos até `resume`
+    pub fn pause(&mut self) {
+        self.control.pause();
+    }
+
+    /// Volta a gravar, removendo a pausa da linha do tempo
+    pub fn resume(&mut self) {
+        self.control.resume();
+    }
+
+    /// Marcador de capítulo no timestamp do último quadro gravado
+    pub fn add_marker(&mut self, label: &str) {
+        self.control.add_marker(label);
+    }
+
+    pub fn markers(&self) -> &[Marker] {
+        self.control.markers()
+    }
+
+    /// Enfileira um quadro para escrita; bloqueia apenas se a fila estiver cheia.
+    ///
+    /// Durante uma pausa o quadro é descartado.
+    pub fn write_frame(&mut self, data: &[u8], timestamp: i64) -> Result<()> {
+        if data.len() as u64 != self.header.frame_size {
+            return Err(format!(
+                "Tamanho de quadro inválido: esperado {} bytes, recebido {}",
+                self.header.frame_size,
+                data.len()
+            )
+            .into());
+        }
+
+        let sender = self.sender.as_ref().ok_or("Gravação já finalizada")?;
+        let Some(timestamp) = self.control.map(timestamp) else {
+            return Ok(());
+        };
+        if sender.send((timestamp, data.to_vec())).is_err() {
+            // A thread de escrita terminou; o erro real é obtido no join
+            return Err(self.join_worker().err().unwrap_or_else(|| "Thread de escrita encerrada".into()));
+        }
+
+        Ok(())
+    }
+
+    /// Escreve o índice e os marcadores, fecha o arquivo e retorna o número de quadros gravados
+    pub fn finish(mut self) -> Result<u64> {
+        let frames = self.join_worker()?;
+        self.control.write_sidecar(&self.path)?;
+        Ok(frames)
+    }
+
+    fn join_worker(&mut self) -> Result<u64> {
+        self.sender = None;
+        match self.worker.take() {
+            Some(worker) => match worker.join() {
+                Ok(result) => Ok(result?),
+                Err(_) => Err("Thread de escrita entrou em pânico".into()),
+            },
+            None => Err("Gravação já finalizada".into()),
+        }
+    }
+}
+
+impl Drop for RawSequenceWriter {
+    fn drop(&mut self) {
+        if self.worker.is_some() {
+            let _ = self.join_worker();
+            let _ = self.control.write_sidecar(&self.path);
+        }
+    }
+}
+
+fn write_loop(mut file: BufWriter<File>, receiver: Receiver<(i64, Vec<u8>)>) -> io::Result<u64> {
+    let mut index: Vec<(u64, i64)> = Vec::new();
+    let mut offset = HEADER_LEN;
+
+    for (timestamp, data) in receiver {
+        file.write_all(&timestamp.to_le_bytes())?;
+        file.write_all(&data)?;
+        index.push((offset, timestamp));
+        offset += RECORD_HEADER_LEN + data.len() as u64;
+    }
+
+    for &(frame_offset, timestamp) in &index {
+        file.write_all(&frame_offset.to_le_bytes())?;
+        file.write_all(&timestamp.to_le_bytes())?;
+    }
+
+    file.write_all(&(index.len() as u64).to_le_bytes())?;
+    file.write_all(&offset.to_le_bytes())?;
+    file.write_all(FOOTER_MAGIC)?;
+    file.flush()?;
+    file.get_ref().sync_all()?;
+
+    Ok(index.len() as u64)
+}
+
+/// Quando `SegmentedRawWriter` fecha o segmento atual e começa outro
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct SegmentPolicy {
+    /// Duração máxima de cada segmento
+    pub max_duration_secs: Option<u64>,
+    /// Tamanho máximo de cada arquivo, incluindo cabeçalho e índice
+    pub max_bytes: Option<u64>,
+}
+
+/// Grava uma sequência longa em vários arquivos, fechando um segmento a cada intervalo ou
+/// tamanho de `SegmentPolicy`.
+///
+/// Os segmentos se chamam `<nome>-00000.<ext>`, `<nome>-00001.<ext>`... a partir do caminho
+/// base. O segmento em andamento tem a extensão `.partial` e só recebe o nome final depois
+/// de o índice ser escrito e sincronizado em disco, então um arquivo com o nome final está
+/// sempre completo. Um `.partial` deixado por uma queda ainda pode ser lido (o índice é
+/// reconstruído) e entra em `list_segments`. Os marcadores valem para a gravação inteira e
+/// vão para `<base>.markers.json`.
+pub struct SegmentedRawWriter {
+    base: PathBuf,
+    header: RawSequenceHeader,
+    policy: SegmentPolicy,
+    control: RecordingControl,
+    current: Option<RawSequenceWriter>,
+    // Início e tamanho estimado do segmento atual
+    started: Instant,
+    bytes: u64,
+    index: u32,
+    finished: Vec<PathBuf>,
+}
+
+impl SegmentedRawWriter {
+    pub fn create<P: AsRef<Path>>(base: P, header: RawSequenceHeader, policy: SegmentPolicy) -> Result<Self> {
+        let mut writer = Self {
+            base: base.as_ref().to_path_buf(),
+            header,
+            policy,
+            control: RecordingControl::new(),
+            current: None,
+            started: Instant::now(),
+            bytes: 0,
+            index: 0,
+            finished: Vec::new(),
+        };
+        writer.start_segment()?;
+        Ok(writer)
+    }
+
+    pub fn header(&self) -> &RawSequenceHeader {
+        &self.header
+    }
+
+    /// Segmentos já fechados, em ordem
+    pub fn segments(&self) -> &[PathBuf] {
+        &self.finished
+    }
+
+    /// Descarta os quadros recebidos até `resume`
+    pub fn pause(&mut self) {
+        self.control.pause();
+    }
+
+    /// Volta a gravar, removendo a pausa da linha do tempo
+    pub fn resume(&mut self) {
+        self.control.resume();
+    }
+
+    /// Marcador de capítulo no timestamp do último quadro gravado
+    pub fn add_marker(&mut self, label: &str) {
+        self.control.add_marker(label);
+    }
+
+    pub fn markers(&self) -> &[Marker] {
+        self.control.markers()
+    }
+
+    /// Enfileira um quadro, abrindo um segmento novo antes se o atual atingiu o limite
+    pub fn write_frame(&mut self, data: &[u8], timestamp: i64) -> Result<()> {
+        let Some(timestamp) = self.control.map(timestamp) else {
+            return Ok(());
+        };
+        let record = RECORD_HEADER_LEN + self.header.frame_size;
+        let over_duration = self
+            .policy
+            .max_duration_secs
+            .is_some_and(|secs| self.started.elapsed() >= Duration::from_secs(secs));
+        // Cada quadro ocupa o registro e uma entrada no índice
+        let over_size = self
+            .policy
+            .max_bytes
+            .is_some_and(|max| self.bytes + record + INDEX_ENTRY_LEN + FOOTER_LEN > max);
+
+        if self.bytes > HEADER_LEN && (over_duration || over_size) {
+            self.finish_segment()?;
+            self.start_segment()?;
+        }
+
+        let current = self.current.as_mut().ok_or("Gravação já finalizada")?;
+        current.write_frame(data, timestamp)?;
+        self.bytes += record + INDEX_ENTRY_LEN;
+        Ok(())
+    }
+
+    /// Fecha o segmento atual, grava os marcadores e retorna todos os segmentos gravados
+    pub fn finish(mut self) -> Result<Vec<PathBuf>> {
+        self.finish_segment()?;
+        self.control.write_sidecar(&self.base)?;
+        Ok(std::mem::take(&mut self.finished))
+    }
+
+    fn start_segment(&mut self) -> Result<()> {
+        let path = partial_path(&segment_path(&self.base, self.index));
+        self.current = Some(RawSequenceWriter::create(&path, self.header)?);
+        self.started = Instant::now();
+        self.bytes = HEADER_LEN;
+        debug!("Segmento {} iniciado: {}", self.index, path.display());
+        Ok(())
+    }
+
+    /// Escreve o índice do segmento atual e o renomeia para o nome final
+    fn finish_segment(&mut self) -> Result<()> {
+        let Some(current) = self.current.take() else {
+            return Ok(());
+        };
+
+        let path = segment_path(&self.base, self.index);
+        current.finish()?;
+        fs::rename(partial_path(&path), &path)?;
+        self.finished.push(path);
+        self.index += 1;
+        Ok(())
+    }
+}
+
+impl Drop for SegmentedRawWriter {
+    fn drop(&mut self) {
+        if self.current.is_some() {
+            let _ = self.finish_segment();
+            let _ = self.control.write_sidecar(&self.base);
+        }
+    }
+}
+
+/// Caminho do segmento `index` a partir do caminho base
+fn segment_path(base: &Path, index: u32) -> PathBuf {
+    let stem = base.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+    let mut name = format!("{}-{:05}", stem, index);
+    if let Some(extension) = base.extension() {
+        name.push('.');
+        name.push_str(&extension.to_string_lossy());
+    }
+    base.with_file_name(name)
+}
+
+fn partial_path(path: &Path) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(".");
+    name.push(PARTIAL_EXTENSION);
+    PathBuf::from(name)
+}
+
+/// Segmentos gravados a partir do caminho base, em ordem, incluindo um `.partial` deixado
+/// por uma gravação interrompida
+pub fn list_segments<P: AsRef<Path>>(base: P) -> Result<Vec<PathBuf>> {
+    let base = base.as_ref();
+    let mut segments = Vec::new();
+    for index in 0.. {
+        let path = segment_path(base, index);
+        let partial = partial_path(&path);
+        if path.exists() {
+            segments.push(path);
+        } else if partial.exists() {
+            segments.push(partial);
+        } else {
+            break;
+        }
+    }
+    Ok(segments)
+}
+
+/// Junta segmentos (ou quaisquer sequências com o mesmo cabeçalho) em uma única sequência,
+/// retornando o número de quadros
+pub fn concat_segments<P: AsRef<Path>, Q: AsRef<Path>>(segments: &[P], output: Q) -> Result<u64> {
+    let first = segments.first().ok_or("Nenhum segmento para juntar")?;
+    let header = *RawSequenceReader::open(first)?.header();
+    let mut writer = RawSequenceWriter::create(output, header)?;
+
+    for segment in segments {
+        let mut reader = RawSequenceReader::open(segment)?;
+        if *reader.header() != header {
+            return Err(format!("Segmento com formato diferente: {}", segment.as_ref().display()).into());
+        }
+        while let Some(frame) = reader.next_frame()? {
+            writer.write_frame(&frame.data, frame.timestamp)?;
+        }
+    }
+
+    writer.finish()
+}
+
+/// Lê quadros de uma sequência gravada por `RawSequenceWriter`
+pub struct RawSequenceReader {
+    file: BufReader<File>,
+    header: RawSequenceHeader,
+    index: Vec<(u64, i64)>,
+    position: usize,
+}
+
+impl RawSequenceReader {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut file = BufReader::new(File::open(path)?);
+        let header = RawSequenceHeader::read_from(&mut file)?;
+        let index = Self::read_index(&mut file, &header)?;
+
+        Ok(Self {
+            file,
+            header,
+            index,
+            position: 0,
+        })
+    }
+
+    /// Lê o índice do rodapé; se a gravação foi interrompida, reconstrói varrendo os quadros
+    fn read_index(file: &mut BufReader<File>, header: &RawSequenceHeader) -> Result<Vec<(u64, i64)>> {
+        let file_len = file.seek(SeekFrom::End(0))?;
+
+        if file_len >= HEADER_LEN + FOOTER_LEN {
+            let mut footer = [0u8; FOOTER_LEN as usize];
+            file.seek(SeekFrom::End(-(FOOTER_LEN as i64)))?;
+            file.read_exact(&mut footer)?;
+
+            if &footer[16..24] == FOOTER_MAGIC {
+                let count = u64::from_le_bytes(footer[0..8].try_into()?);
+                let index_offset = u64::from_le_bytes(footer[8..16].try_into()?);
+
+                // O índice ocupa exatamente o espaço entre os quadros e o rodapé; um rodapé
+                // corrompido não pode fazer alocar nem ler além do arquivo
+                let index_end = count
+                    .checked_mul(INDEX_ENTRY_LEN)
+                    .and_then(|len| len.checked_add(index_offset))
+                    .and_then(|end| end.checked_add(FOOTER_LEN));
+                if index_offset < HEADER_LEN || index_end != Some(file_len) {
+                    return Err("Índice da sequência corrompido".into());
+                }
+
+                file.seek(SeekFrom::Start(index_offset))?;
+                let mut index = Vec::with_capacity(count as usize);
+                let mut entry = [0u8; INDEX_ENTRY_LEN as usize];
+                for _ in 0..count {
+                    file.read_exact(&mut entry)?;
+                    let offset = u64::from_le_bytes(entry[0..8].try_into()?);
+                    let record_end = offset
+                        .checked_add(RECORD_HEADER_LEN)
+                        .and_then(|start| start.checked_add(header.frame_size));
+                    if offset < HEADER_LEN || !record_end.is_some_and(|end| end <= index_offset) {
+                        return Err(format!("Quadro fora da sequência no índice: offset {}", offset).into());
+                    }
+                    index.push((offset, i64::from_le_bytes(entry[8..16].try_into()?)));
+                }
+                return Ok(index);
+            }
+        }
+
+        // Sem rodapé: os registros têm tamanho fixo, então basta percorrê-los
+        let record_len = RECORD_HEADER_LEN.saturating_add(header.frame_size);
+        let mut index = Vec::new();
+        let mut offset = HEADER_LEN;
+        let mut timestamp = [0u8; 8];
+
+        while offset.checked_add(record_len).is_some_and(|end| end <= file_len) {
+            file.seek(SeekFrom::Start(offset))?;
+            file.read_exact(&mut timestamp)?;
+            index.push((offset, i64::from_le_bytes(timestamp)));
+            offset += record_len;
+        }
+
+        Ok(index)
+    }
+
+    pub fn header(&self) -> &RawSequenceHeader {
+        &self.header
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Timestamp do quadro `index`, sem ler os pixels
+    pub fn timestamp(&self, index: usize) -> Option<i64> {
+        self.index.get(index).map(|&(_, timestamp)| timestamp)
+    }
+
+    /// Lê o quadro `index` e posiciona a leitura sequencial logo após ele
+    pub fn read_frame(&mut self, index: usize) -> Result<RawFrame> {
+        let &(offset, timestamp) = self.index.get(index).ok_or("Índice de quadro fora da sequência")?;
+
+        self.file.seek(SeekFrom::Start(offset + RECORD_HEADER_LEN))?;
+        let mut data = vec![0u8; self.header.frame_size as usize];
+        self.file.read_exact(&mut data)?;
+        self.position = index + 1;
+
+        Ok(RawFrame { timestamp, data })
+    }
+
+    /// Volta a leitura sequencial para o quadro `index`
+    pub fn seek(&mut self, index: usize) {
+        self.position = index.min(self.index.len());
+    }
+
+    /// Lê o próximo quadro da sequência, ou `None` ao chegar ao fim
+    pub fn next_frame(&mut self) -> Result<Option<RawFrame>> {
+        if self.position >= self.index.len() {
+            return Ok(None);
+        }
+        self.read_frame(self.position).map(Some)
+    }
+}
@@ -0,0 +1,140 @@
+//! Pausa, retomada e marcadores de capítulo para os gravadores
+//!
+//! `RecordingControl` fica entre os timestamps dos quadros e o gravador: durante a pausa os
+//! quadros são descartados, e na retomada a linha do tempo continua de onde parou, sem um
+//! buraco do tamanho da pausa. Funciona com qualquer unidade de timestamp (QPC ou 100 ns);
+//! os marcadores usam a mesma unidade do gravador.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::Result;
+
+/// Marcador de capítulo na posição `timestamp` da gravação
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Marker {
+    pub label: String,
+    pub timestamp: i64,
+}
+
+/// Estado de pausa e marcadores de uma gravação
+#[derive(Debug, Clone, Default)]
+pub struct RecordingControl {
+    paused: bool,
+    // Retomada pedida; o deslocamento é calculado no primeiro quadro seguinte
+    resuming: bool,
+    // Tempo total removido da linha do tempo pelas pausas
+    offset: i64,
+    last: Option<i64>,
+    last_interval: i64,
+    markers: Vec<Marker>,
+}
+
+impl RecordingControl {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Descarta os quadros seguintes até `resume`
+    pub fn pause(&mut self) {
+        if !self.paused {
+            debug!("Gravação pausada em {:?}", self.last);
+        }
+        self.paused = true;
+    }
+
+    /// Volta a gravar; o próximo quadro vem um intervalo de quadro depois do último gravado
+    pub fn resume(&mut self) {
+        if self.paused {
+            self.paused = false;
+            self.resuming = true;
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Timestamp de saída do quadro com timestamp de entrada `timestamp`, ou `None` se o
+    /// quadro deve ser descartado (gravação pausada)
+    pub fn map(&mut self, timestamp: i64) -> Option<i64> {
+        if self.paused {
+            return None;
+        }
+
+        if self.resuming {
+            self.resuming = false;
+            if let Some(last) = self.last {
+                // Repetir o último intervalo entre quadros no lugar da pausa
+                self.offset = timestamp - (last + self.last_interval.max(1));
+            }
+        }
+
+        let mapped = timestamp - self.offset;
+        if let Some(last) = self.last {
+            self.last_interval = mapped - last;
+        }
+        self.last = Some(mapped);
+        Some(mapped)
+    }
+
+    /// Último timestamp gravado (posição atual da gravação)
+    pub fn position(&self) -> i64 {
+        self.last.unwrap_or(0)
+    }
+
+    /// Registra um marcador na posição atual
+    pub fn add_marker(&mut self, label: &str) {
+        let timestamp = self.position();
+        debug!("Marcador \"{}\" em {}", label, timestamp);
+        self.markers.push(Marker {
+            label: label.to_owned(),
+            timestamp,
+        });
+    }
+
+    pub fn markers(&self) -> &[Marker] {
+        &self.markers
+    }
+
+    /// Grava os marcadores em um JSON ao lado da gravação (`<arquivo>.markers.json`), se houver
+    /// algum; retorna o caminho escrito
+    pub fn write_sidecar<P: AsRef<Path>>(&self, recording: P) -> Result<Option<PathBuf>> {
+        if self.markers.is_empty() {
+            return Ok(None);
+        }
+
+        let path = sidecar_path(recording.as_ref());
+        let entries: Vec<String> = self
+            .markers
+            .iter()
+            .map(|marker| format!("    {{ \"label\": \"{}\", \"timestamp\": {} }}", escape_json(&marker.label), marker.timestamp))
+            .collect();
+        fs::write(&path, format!("{{\n  \"markers\": [\n{}\n  ]\n}}\n", entries.join(",\n")))?;
+        Ok(Some(path))
+    }
+}
+
+/// Caminho do JSON de marcadores de uma gravação
+pub fn sidecar_path(recording: &Path) -> PathBuf {
+    let mut name = recording.as_os_str().to_os_string();
+    name.push(".markers.json");
+    PathBuf::from(name)
+}
+
+fn escape_json(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
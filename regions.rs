@@ -0,0 +1,101 @@
+//! Regiões nomeadas ("minimap", "chat", "toolbar")
+//!
+//! `RegionRegistry` guarda `RegionSpec`s por nome para que automações não espalhem constantes de
+//! retângulos pelo código; `DxgiCapture::capture_named` resolve o nome contra o monitor atual.
+//! Com a feature `regions` o registro é lido e gravado em TOML ou JSON (escolhido pela extensão),
+//! com as regiões escritas como no daemon:
+//!
+//! ```toml
+//! minimap = { left = 1600, top = 20, width = 300, height = 300 }
+//! chat = { bottom_left = { width = 500, height = 240, margin = 16 } }
+//! toolbar = { x = 0.0, y = 0.0, width = 100.0, height = 5.0 }
+//! ```
+
+use std::collections::BTreeMap;
+#[cfg(feature = "regions")]
+use std::fs;
+#[cfg(feature = "regions")]
+use std::path::Path;
+
+use crate::geometry::RegionSpec;
+#[cfg(feature = "regions")]
+use crate::Result;
+
+/// Regiões indexadas por nome, em ordem alfabética
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct RegionRegistry {
+    regions: BTreeMap<String, RegionSpec>,
+}
+
+impl RegionRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registra `spec` com o nome `name`, retornando a região que ele substituiu
+    pub fn insert(&mut self, name: impl Into<String>, spec: impl Into<RegionSpec>) -> Option<RegionSpec> {
+        self.regions.insert(name.into(), spec.into())
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<RegionSpec> {
+        self.regions.remove(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&RegionSpec> {
+        self.regions.get(name)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.regions.contains_key(name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.regions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+
+    /// Nomes e regiões em ordem alfabética
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &RegionSpec)> {
+        self.regions.iter().map(|(name, spec)| (name.as_str(), spec))
+    }
+}
+
+#[cfg(feature = "regions")]
+impl RegionRegistry {
+    /// Carrega o registro; o formato é escolhido pela extensão (`.toml` ou `.json`)
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Ok(serde_json::from_str(&text)?),
+            Some("toml") => Ok(toml::from_str(&text)?),
+            _ => Err(format!("Extensão de arquivo de regiões não suportada: {}", path.display()).into()),
+        }
+    }
+
+    /// Grava o registro no formato indicado pela extensão (`.toml` ou `.json`)
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let text = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => serde_json::to_string_pretty(self)?,
+            Some("toml") => toml::to_string_pretty(self)?,
+            _ => return Err(format!("Extensão de arquivo de regiões não suportada: {}", path.display()).into()),
+        };
+        fs::write(path, text)?;
+        Ok(())
+    }
+}
+
+impl<N: Into<String>, S: Into<RegionSpec>> FromIterator<(N, S)> for RegionRegistry {
+    fn from_iter<I: IntoIterator<Item = (N, S)>>(iter: I) -> Self {
+        Self {
+            regions: iter.into_iter().map(|(name, spec)| (name.into(), spec.into())).collect(),
+        }
+    }
+}
@@ -0,0 +1,212 @@
+//! Reprodução de sequências gravadas como fonte de quadros (feature `recording`)
+//!
+//! `ReplaySource` lê uma sequência do `RawSequenceWriter` e a entrega pelo mesmo
+//! `ScreenSource` da captura ao vivo, para desenvolver e testar encoders e detectores sem um
+//! monitor, com capturas reais gravadas antes. A reprodução é determinística: cada chamada de
+//! `capture_frame` entrega o próximo quadro gravado, com os pixels, a ordem e o present time
+//! (QPC) da gravação; com `ReplayPacing::RealTime` ela também espera os intervalos originais.
+//! Ao repetir (`looping`), os present times continuam crescendo a partir do fim da volta anterior.
+
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use windows::Win32::Graphics::Dxgi::Common::{
+    DXGI_FORMAT, DXGI_FORMAT_R10G10B10A2_UNORM, DXGI_FORMAT_R16G16B16A16_FLOAT,
+};
+
+use crate::error::{CaptureError, CaptureResult};
+use crate::frame::{Frame, FrameMetadata};
+use crate::geometry::{Rect, Size};
+use crate::outputs::ColorSpace;
+use crate::raw_sequence::{RawSequenceHeader, RawSequenceReader};
+use crate::source::{Backend, ScreenSource};
+use crate::timecode::Timecode;
+use crate::Result;
+
+/// Ritmo da entrega dos quadros
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum ReplayPacing {
+    /// Cada chamada entrega o próximo quadro na hora (testes e processamento em lote)
+    #[default]
+    Immediate,
+    /// Espera entre quadros os mesmos intervalos da gravação
+    RealTime,
+}
+
+/// Como a sequência é reproduzida
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct ReplayOptions {
+    pub pacing: ReplayPacing,
+    /// Recomeça do primeiro quadro no fim em vez de falhar com `CaptureError::ReplayFinished`
+    pub looping: bool,
+    /// Espaço de cor dos quadros; `None` deduz pelo formato (FP16 = scRGB, R10G10B10A2 = HDR10,
+    /// demais = sRGB), já que a sequência não o grava
+    pub color_space: Option<ColorSpace>,
+}
+
+impl Default for ReplayOptions {
+    fn default() -> Self {
+        Self {
+            pacing: ReplayPacing::Immediate,
+            looping: false,
+            color_space: None,
+        }
+    }
+}
+
+/// Fonte de quadros que reproduz uma sequência bruta gravada
+pub struct ReplaySource {
+    reader: RawSequenceReader,
+    options: ReplayOptions,
+    color_space: ColorSpace,
+    timecode: Timecode,
+    /// Quadros entregues, usado como `Frame::sequence`
+    delivered: u64,
+    /// Somado aos present times gravados a cada volta
+    loop_offset: i64,
+    /// Instante e present time do primeiro quadro entregue, referência do `RealTime`
+    pacing_origin: Option<(Instant, i64)>,
+}
+
+impl ReplaySource {
+    pub fn open(path: impl AsRef<Path>, options: ReplayOptions) -> Result<Self> {
+        Self::from_reader(RawSequenceReader::open(path)?, options)
+    }
+
+    pub fn from_reader(reader: RawSequenceReader, options: ReplayOptions) -> Result<Self> {
+        let color_space = options.color_space.unwrap_or(match reader.header().format {
+            DXGI_FORMAT_R16G16B16A16_FLOAT => ColorSpace::ScRgb,
+            DXGI_FORMAT_R10G10B10A2_UNORM => ColorSpace::Hdr10,
+            _ => ColorSpace::Srgb,
+        });
+        Ok(Self {
+            reader,
+            options,
+            color_space,
+            timecode: Timecode::new()?,
+            delivered: 0,
+            loop_offset: 0,
+            pacing_origin: None,
+        })
+    }
+
+    pub fn header(&self) -> &RawSequenceHeader {
+        self.reader.header()
+    }
+
+    pub fn options(&self) -> &ReplayOptions {
+        &self.options
+    }
+
+    /// Quadros na sequência
+    pub fn len(&self) -> usize {
+        self.reader.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.reader.is_empty()
+    }
+
+    /// Quadros entregues desde a abertura (contando as voltas)
+    pub fn delivered(&self) -> u64 {
+        self.delivered
+    }
+
+    /// O próximo quadro entregue será o `index` da sequência
+    pub fn seek(&mut self, index: usize) {
+        self.reader.seek(index);
+        self.pacing_origin = None;
+    }
+
+    /// Próximo quadro inteiro
+    pub fn next_frame(&mut self) -> CaptureResult<Frame> {
+        let raw = match self.reader.next_frame().map_err(replay_error)? {
+            Some(raw) => raw,
+            None if self.options.looping && !self.reader.is_empty() => {
+                self.loop_offset = self.loop_offset.wrapping_add(self.loop_duration());
+                self.reader.seek(0);
+                self.reader.next_frame().map_err(replay_error)?.ok_or(CaptureError::ReplayFinished {
+                    frames: self.delivered,
+                })?
+            }
+            None => return Err(CaptureError::ReplayFinished { frames: self.delivered }),
+        };
+        let present_time = raw.timestamp.wrapping_add(self.loop_offset);
+        self.wait_until(present_time);
+
+        let header = *self.reader.header();
+        let frame = Frame {
+            data: raw.data,
+            region: Rect::new(0, 0, header.width, header.height),
+            format: header.format,
+            color_space: self.color_space,
+            present_time,
+            sequence: self.delivered,
+            metadata: FrameMetadata {
+                accumulated_frames: 1,
+                ..FrameMetadata::default()
+            },
+        };
+        self.delivered += 1;
+        Ok(frame)
+    }
+
+    /// Duração de uma volta: do primeiro ao último present, mais o intervalo médio entre quadros
+    fn loop_duration(&self) -> i64 {
+        let last_index = self.reader.len().saturating_sub(1);
+        let (Some(first), Some(last)) = (self.reader.timestamp(0), self.reader.timestamp(last_index)) else {
+            return 0;
+        };
+        let span = last.wrapping_sub(first).max(0);
+        let frames = self.reader.len() as i64;
+        span + if frames > 1 { span / (frames - 1) } else { 1 }
+    }
+
+    fn wait_until(&mut self, present_time: i64) {
+        if self.options.pacing != ReplayPacing::RealTime {
+            return;
+        }
+        let &mut (origin, first) = self.pacing_origin.get_or_insert((Instant::now(), present_time));
+        let ticks = self.timecode.qpc_to_ticks(present_time.wrapping_sub(first).max(0));
+        let target = origin + Duration::from_nanos(ticks as u64 * 100);
+        let now = Instant::now();
+        if target > now {
+            thread::sleep(target - now);
+        }
+    }
+}
+
+fn replay_error(error: Box<dyn std::error::Error>) -> CaptureError {
+    CaptureError::Replay(error.to_string())
+}
+
+impl ScreenSource for ReplaySource {
+    fn backend(&self) -> Backend {
+        Backend::Replay
+    }
+
+    fn output_size(&self) -> Size {
+        let header = self.reader.header();
+        Size::new(header.width, header.height)
+    }
+
+    fn format(&self) -> DXGI_FORMAT {
+        self.reader.header().format
+    }
+
+    /// Próximo quadro gravado, recortado para `region`
+    fn capture_frame(&mut self, region: Rect) -> CaptureResult<Frame> {
+        region.validate_within(self.output_size())?;
+        let frame = self.next_frame()?;
+        if region == frame.region {
+            return Ok(frame);
+        }
+        let view = frame.crop(region).map_err(replay_error)?;
+        Ok(view.to_frame())
+    }
+}
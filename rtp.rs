@@ -0,0 +1,575 @@
+//! Saída de streaming H.264 via RTP (RFC 6184) e um servidor RTSP mínimo
+//!
+//! `RtpUdpSink` envia para um endpoint UDP fixo; `RtspServer` atende players como o VLC
+//! (`rtsp://host:porta/screen`) usando transporte RTP/UDP unicast.
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::Result;
+
+/// Tamanho máximo do payload RTP, abaixo do MTU típico de Ethernet
+pub const DEFAULT_MTU: usize = 1200;
+
+const RTP_HEADER_LEN: usize = 12;
+const PAYLOAD_TYPE: u8 = 96;
+const NAL_TYPE_FU_A: u8 = 28;
+const NAL_TYPE_SPS: u8 = 7;
+const NAL_TYPE_PPS: u8 = 8;
+
+/// Divide um fluxo Annex-B em unidades NAL (sem os start codes)
+pub fn split_annexb(data: &[u8]) -> Vec<&[u8]> {
+    let mut nals = Vec::new();
+    let mut start = None;
+    let mut i = 0;
+
+    while i + 3 <= data.len() {
+        let three_byte = data[i] == 0 && data[i + 1] == 0 && data[i + 2] == 1;
+        let four_byte = i + 4 <= data.len() && data[i] == 0 && data[i + 1] == 0 && data[i + 2] == 0 && data[i + 3] == 1;
+
+        if three_byte || four_byte {
+            if let Some(begin) = start {
+                nals.push(&data[begin..i]);
+            }
+            i += if four_byte { 4 } else { 3 };
+            start = Some(i);
+        } else {
+            i += 1;
+        }
+    }
+
+    if let Some(begin) = start {
+        if begin < data.len() {
+            nals.push(&data[begin..]);
+        }
+    } else if !data.is_empty() {
+        // Sem start code: tratar o buffer como uma única NAL
+        nals.push(data);
+    }
+
+    nals
+}
+
+/// Converte timestamps em unidades de 100 ns para o clock de vídeo RTP (90 kHz)
+pub fn rtp_timestamp(timestamp_100ns: i64) -> u32 {
+    (timestamp_100ns as i128 * 9 / 1000) as u32
+}
+
+/// Empacota unidades de acesso H.264 em pacotes RTP
+pub struct RtpPacketizer {
+    ssrc: u32,
+    sequence: u16,
+    mtu: usize,
+    sps: Option<Vec<u8>>,
+    pps: Option<Vec<u8>>,
+}
+
+impl RtpPacketizer {
+    pub fn new(mtu: usize) -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.subsec_nanos() ^ d.as_secs() as u32)
+            .unwrap_or(0x5eed);
+
+        Self {
+            ssrc: seed,
+            sequence: (seed >> 16) as u16,
+            mtu: mtu.max(RTP_HEADER_LEN + 3),
+            sps: None,
+            pps: None,
+        }
+    }
+
+    pub fn ssrc(&self) -> u32 {
+        self.ssrc
+    }
+
+    /// Gera os pacotes RTP de uma unidade de acesso Annex-B
+    pub fn packetize(&mut self, annexb: &[u8], timestamp: u32) -> Vec<Vec<u8>> {
+        let nals = split_annexb(annexb);
+        let max_payload = self.mtu - RTP_HEADER_LEN;
+        let mut packets = Vec::new();
+
+        for (index, nal) in nals.iter().enumerate() {
+            if nal.is_empty() {
+                continue;
+            }
+
+            match nal[0] & 0x1F {
+                NAL_TYPE_SPS => self.sps = Some(nal.to_vec()),
+                NAL_TYPE_PPS => self.pps = Some(nal.to_vec()),
+                _ => {}
+            }
+
+            let last_nal = index == nals.len() - 1;
+
+            if nal.len() <= max_payload {
+                // Pacote de NAL única
+                let mut packet = self.header(timestamp, last_nal);
+                packet.extend_from_slice(nal);
+                packets.push(packet);
+                continue;
+            }
+
+            // Fragmentação FU-A
+            let indicator = (nal[0] & 0xE0) | NAL_TYPE_FU_A;
+            let nal_type = nal[0] & 0x1F;
+            let chunks: Vec<&[u8]> = nal[1..].chunks(max_payload - 2).collect();
+
+            for (chunk_index, chunk) in chunks.iter().enumerate() {
+                let first = chunk_index == 0;
+                let last = chunk_index == chunks.len() - 1;
+                let fu_header = nal_type | if first { 0x80 } else { 0 } | if last { 0x40 } else { 0 };
+
+                let mut packet = self.header(timestamp, last_nal && last);
+                packet.push(indicator);
+                packet.push(fu_header);
+                packet.extend_from_slice(chunk);
+                packets.push(packet);
+            }
+        }
+
+        packets
+    }
+
+    fn header(&mut self, timestamp: u32, marker: bool) -> Vec<u8> {
+        let mut header = Vec::with_capacity(self.mtu);
+        header.push(0x80);
+        header.push(PAYLOAD_TYPE | if marker { 0x80 } else { 0 });
+        header.extend_from_slice(&self.sequence.to_be_bytes());
+        header.extend_from_slice(&timestamp.to_be_bytes());
+        header.extend_from_slice(&self.ssrc.to_be_bytes());
+        self.sequence = self.sequence.wrapping_add(1);
+        header
+    }
+
+    /// Descrição SDP do fluxo, incluindo SPS/PPS quando já observados
+    pub fn sdp(&self, address: &str) -> String {
+        let mut fmtp = String::from("packetization-mode=1");
+        if let (Some(sps), Some(pps)) = (&self.sps, &self.pps) {
+            if sps.len() >= 4 {
+                fmtp.push_str(&format!(";profile-level-id={:02X}{:02X}{:02X}", sps[1], sps[2], sps[3]));
+            }
+            fmtp.push_str(&format!(";sprop-parameter-sets={},{}", base64(sps), base64(pps)));
+        }
+
+        format!(
+            "v=0\r\n\
+             o=- {ssrc} 1 IN IP4 {address}\r\n\
+             s=dxgi_capture\r\n\
+             c=IN IP4 0.0.0.0\r\n\
+             t=0 0\r\n\
+             m=video 0 RTP/AVP {pt}\r\n\
+             a=rtpmap:{pt} H264/90000\r\n\
+             a=fmtp:{pt} {fmtp}\r\n\
+             a=control:track0\r\n",
+            ssrc = self.ssrc,
+            address = address,
+            pt = PAYLOAD_TYPE,
+            fmtp = fmtp,
+        )
+    }
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+
+    for chunk in data.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = ((b[0] as u32) << 16) | ((b[1] as u32) << 8) | b[2] as u32;
+        out.push(ALPHABET[(n >> 18) as usize & 63] as char);
+        out.push(ALPHABET[(n >> 12) as usize & 63] as char);
+        out.push(if chunk.len() > 1 { ALPHABET[(n >> 6) as usize & 63] as char } else { '=' });
+        out.push(if chunk.len() > 2 { ALPHABET[n as usize & 63] as char } else { '=' });
+    }
+
+    out
+}
+
+/// Envia o fluxo RTP para um endpoint UDP fixo
+pub struct RtpUdpSink {
+    socket: UdpSocket,
+    packetizer: RtpPacketizer,
+}
+
+impl RtpUdpSink {
+    pub fn connect<A: ToSocketAddrs>(destination: A) -> Result<Self> {
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        socket.connect(destination)?;
+
+        Ok(Self {
+            socket,
+            packetizer: RtpPacketizer::new(DEFAULT_MTU),
+        })
+    }
+
+    /// SDP para o receptor (por exemplo, salvo em um arquivo `.sdp` aberto no VLC)
+    pub fn sdp(&self) -> String {
+        self.packetizer.sdp("127.0.0.1")
+    }
+
+    /// Envia uma unidade de acesso Annex-B com timestamp em unidades de 100 ns
+    pub fn send(&mut self, annexb: &[u8], timestamp_100ns: i64) -> Result<()> {
+        for packet in self.packetizer.packetize(annexb, rtp_timestamp(timestamp_100ns)) {
+            self.socket.send(&packet)?;
+        }
+        Ok(())
+    }
+}
+
+struct RtspClient {
+    rtp_address: SocketAddr,
+    playing: bool,
+}
+
+struct RtspState {
+    packetizer: RtpPacketizer,
+    sessions: HashMap<u64, RtspClient>,
+    next_session: u64,
+}
+
+struct RtspShared {
+    state: Mutex<RtspState>,
+    socket: UdpSocket,
+    running: AtomicBool,
+}
+
+/// Servidor RTSP mínimo (OPTIONS/DESCRIBE/SETUP/PLAY/TEARDOWN) com RTP sobre UDP
+pub struct RtspServer {
+    shared: Arc<RtspShared>,
+    local_addr: SocketAddr,
+}
+
+impl RtspServer {
+    pub fn bind<A: ToSocketAddrs>(address: A) -> Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        let local_addr = listener.local_addr()?;
+        let socket = UdpSocket::bind((local_addr.ip(), 0))?;
+
+        let shared = Arc::new(RtspShared {
+            state: Mutex::new(RtspState {
+                packetizer: RtpPacketizer::new(DEFAULT_MTU),
+                sessions: HashMap::new(),
+                next_session: 1,
+            }),
+            socket,
+            running: AtomicBool::new(true),
+        });
+
+        let accept_shared = shared.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                if !accept_shared.running.load(Ordering::SeqCst) {
+                    break;
+                }
+                if let Ok(stream) = stream {
+                    let client_shared = accept_shared.clone();
+                    thread::spawn(move || {
+                        let _ = handle_rtsp_connection(stream, client_shared);
+                    });
+                }
+            }
+        });
+
+        Ok(Self { shared, local_addr })
+    }
+
+    /// URL a ser aberta pelos players
+    pub fn url(&self) -> String {
+        format!("rtsp://{}/screen", self.local_addr)
+    }
+
+    /// Número de sessões em reprodução
+    pub fn playing_sessions(&self) -> usize {
+        let state = self.shared.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.sessions.values().filter(|client| client.playing).count()
+    }
+
+    /// Envia uma unidade de acesso Annex-B para todas as sessões em reprodução
+    pub fn send(&self, annexb: &[u8], timestamp_100ns: i64) -> Result<()> {
+        let mut state = self.shared.state.lock().unwrap_or_else(|e| e.into_inner());
+        let packets = state.packetizer.packetize(annexb, rtp_timestamp(timestamp_100ns));
+
+        for client in state.sessions.values().filter(|client| client.playing) {
+            for packet in &packets {
+                // Um cliente inalcançável não deve interromper os demais
+                let _ = self.shared.socket.send_to(packet, client.rtp_address);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for RtspServer {
+    fn drop(&mut self) {
+        self.shared.running.store(false, Ordering::SeqCst);
+        // Desbloquear o accept
+        let _ = TcpStream::connect(self.local_addr);
+    }
+}
+
+fn handle_rtsp_connection(stream: TcpStream, shared: Arc<RtspShared>) -> Result<()> {
+    let peer = stream.peer_addr()?;
+    let local = stream.local_addr()?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+    let mut owned_sessions = Vec::new();
+
+    loop {
+        let mut request_line = String::new();
+        if reader.read_line(&mut request_line)? == 0 || !shared.running.load(Ordering::SeqCst) {
+            break;
+        }
+        let request_line = request_line.trim().to_string();
+        if request_line.is_empty() {
+            continue;
+        }
+
+        let mut headers = HashMap::new();
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+                break;
+            }
+            if let Some((key, value)) = line.split_once(':') {
+                headers.insert(key.trim().to_ascii_lowercase(), value.trim().to_string());
+            }
+        }
+
+        let mut parts = request_line.split_whitespace();
+        let method = parts.next().unwrap_or_default();
+        let url = parts.next().unwrap_or_default();
+        let cseq = headers.get("cseq").cloned().unwrap_or_else(|| "0".into());
+
+        let response = match method {
+            "OPTIONS" => rtsp_response(&cseq, "200 OK", &["Public: OPTIONS, DESCRIBE, SETUP, PLAY, TEARDOWN".into()], ""),
+            "DESCRIBE" => {
+                let sdp = {
+                    let state = shared.state.lock().unwrap_or_else(|e| e.into_inner());
+                    state.packetizer.sdp(&local.ip().to_string())
+                };
+                rtsp_response(
+                    &cseq,
+                    "200 OK",
+                    &[
+                        "Content-Type: application/sdp".into(),
+                        format!("Content-Base: {}/", url.trim_end_matches('/')),
+                    ],
+                    &sdp,
+                )
+            }
+            "SETUP" => {
+                let transport = headers.get("transport").cloned().unwrap_or_default();
+                let server_port = shared.socket.local_addr()?.port();
+                // O RTCP usa a porta seguinte à do RTP; sem ela o transporte não é aceito
+                let ports = parse_client_port(&transport).and_then(|rtp_port| {
+                    Some((rtp_port, rtp_port.checked_add(1)?, server_port.checked_add(1)?))
+                });
+                match ports {
+                    Some((rtp_port, rtcp_port, server_rtcp_port)) => {
+                        let mut state = shared.state.lock().unwrap_or_else(|e| e.into_inner());
+                        let session = state.next_session;
+                        state.next_session += 1;
+                        state.sessions.insert(
+                            session,
+                            RtspClient {
+                                rtp_address: SocketAddr::new(peer.ip(), rtp_port),
+                                playing: false,
+                            },
+                        );
+                        owned_sessions.push(session);
+
+                        rtsp_response(
+                            &cseq,
+                            "200 OK",
+                            &[
+                                format!(
+                                    "Transport: RTP/AVP;unicast;client_port={}-{};server_port={}-{};ssrc={:08X}",
+                                    rtp_port,
+                                    rtcp_port,
+                                    server_port,
+                                    server_rtcp_port,
+                                    state.packetizer.ssrc()
+                                ),
+                                format!("Session: {}", session),
+                            ],
+                            "",
+                        )
+                    }
+                    // Apenas RTP/UDP é suportado (sem interleaving em TCP), com uma porta livre depois da do RTP
+                    None => rtsp_response(&cseq, "461 Unsupported Transport", &[], ""),
+                }
+            }
+            "PLAY" | "TEARDOWN" => {
+                let session = headers
+                    .get("session")
+                    .and_then(|value| value.split(';').next())
+                    .and_then(|value| value.trim().parse::<u64>().ok());
+                let mut state = shared.state.lock().unwrap_or_else(|e| e.into_inner());
+
+                match session {
+                    Some(id) if state.sessions.contains_key(&id) => {
+                        if method == "PLAY" {
+                            if let Some(client) = state.sessions.get_mut(&id) {
+                                client.playing = true;
+                            }
+                            rtsp_response(&cseq, "200 OK", &[format!("Session: {}", id), "Range: npt=0.000-".into()], "")
+                        } else {
+                            state.sessions.remove(&id);
+                            rtsp_response(&cseq, "200 OK", &[format!("Session: {}", id)], "")
+                        }
+                    }
+                    _ => rtsp_response(&cseq, "454 Session Not Found", &[], ""),
+                }
+            }
+            _ => rtsp_response(&cseq, "501 Not Implemented", &[], ""),
+        };
+
+        writer.write_all(response.as_bytes())?;
+    }
+
+    // Conexão de controle encerrada: remover as sessões criadas por ela
+    let mut state = shared.state.lock().unwrap_or_else(|e| e.into_inner());
+    for session in owned_sessions {
+        state.sessions.remove(&session);
+    }
+
+    Ok(())
+}
+
+fn parse_client_port(transport: &str) -> Option<u16> {
+    transport
+        .split(';')
+        .find_map(|part| part.trim().strip_prefix("client_port="))
+        .and_then(|ports| ports.split('-').next())
+        .and_then(|port| port.parse().ok())
+}
+
+fn rtsp_response(cseq: &str, status: &str, headers: &[String], body: &str) -> String {
+    let mut response = format!("RTSP/1.0 {}\r\nCSeq: {}\r\n", status, cseq);
+    for header in headers {
+        response.push_str(header);
+        response.push_str("\r\n");
+    }
+    if !body.is_empty() {
+        response.push_str(&format!("Content-Length: {}\r\n", body.len()));
+    }
+    response.push_str("\r\n");
+    response.push_str(body);
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sequence_of(packet: &[u8]) -> u16 {
+        u16::from_be_bytes([packet[2], packet[3]])
+    }
+
+    fn has_marker(packet: &[u8]) -> bool {
+        packet[1] & 0x80 != 0
+    }
+
+    #[test]
+    fn split_annexb_handles_three_and_four_byte_start_codes() {
+        let data = [0, 0, 0, 1, 0x67, 1, 2, 0, 0, 1, 0x68, 3, 0, 0, 0, 1, 0x65, 4, 5];
+        assert_eq!(split_annexb(&data), vec![&[0x67, 1, 2][..], &[0x68, 3][..], &[0x65, 4, 5][..]]);
+    }
+
+    #[test]
+    fn split_annexb_without_start_code_is_a_single_nal() {
+        assert_eq!(split_annexb(&[0x65, 1, 2]), vec![&[0x65, 1, 2][..]]);
+        assert!(split_annexb(&[]).is_empty());
+        assert!(split_annexb(&[0, 0, 1]).is_empty());
+    }
+
+    #[test]
+    fn small_nals_go_in_single_packets_with_the_marker_on_the_last() {
+        let mut packetizer = RtpPacketizer::new(DEFAULT_MTU);
+        let access_unit = [0, 0, 0, 1, 0x67, 0x42, 0, 0x1F, 0, 0, 0, 1, 0x68, 0xCE, 0, 0, 0, 1, 0x65, 9, 9];
+        let packets = packetizer.packetize(&access_unit, 3000);
+
+        assert_eq!(packets.len(), 3);
+        assert_eq!(&packets[0][RTP_HEADER_LEN..], &[0x67, 0x42, 0, 0x1F]);
+        assert_eq!(&packets[2][RTP_HEADER_LEN..], &[0x65, 9, 9]);
+        assert_eq!(packets.iter().map(|p| has_marker(p)).collect::<Vec<_>>(), [false, false, true]);
+        for packet in &packets {
+            assert_eq!(packet[0], 0x80);
+            assert_eq!(packet[1] & 0x7F, PAYLOAD_TYPE);
+            assert_eq!(u32::from_be_bytes(packet[4..8].try_into().unwrap()), 3000);
+            assert_eq!(u32::from_be_bytes(packet[8..12].try_into().unwrap()), packetizer.ssrc());
+        }
+        assert_eq!(sequence_of(&packets[1]), sequence_of(&packets[0]).wrapping_add(1));
+        assert!(packetizer.sdp("127.0.0.1").contains("profile-level-id=42001F;sprop-parameter-sets=Z0IAHw==,aM4="));
+    }
+
+    #[test]
+    fn large_nal_is_fragmented_with_fu_a() {
+        // 10 bytes de payload: 2 de cabeçalho FU-A e 8 da NAL por pacote
+        let mut packetizer = RtpPacketizer::new(RTP_HEADER_LEN + 10);
+        let nal: Vec<u8> = std::iter::once(0x65).chain(1..=20).collect();
+        let mut access_unit = vec![0, 0, 0, 1];
+        access_unit.extend_from_slice(&nal);
+        let packets = packetizer.packetize(&access_unit, 0);
+
+        assert_eq!(packets.len(), 3);
+        let fu_headers: Vec<u8> = packets.iter().map(|p| p[RTP_HEADER_LEN + 1]).collect();
+        assert_eq!(fu_headers, [0x80 | 5, 5, 0x40 | 5]);
+        assert!(packets.iter().all(|p| p[RTP_HEADER_LEN] == (0x65 & 0xE0) | NAL_TYPE_FU_A));
+        assert_eq!(packets.iter().map(|p| has_marker(p)).collect::<Vec<_>>(), [false, false, true]);
+
+        let payload: Vec<u8> = packets.iter().flat_map(|p| p[RTP_HEADER_LEN + 2..].to_vec()).collect();
+        assert_eq!(payload, &nal[1..]);
+    }
+
+    #[test]
+    fn marker_is_only_on_the_last_fragment_of_the_access_unit() {
+        let mut packetizer = RtpPacketizer::new(RTP_HEADER_LEN + 10);
+        let mut access_unit = vec![0, 0, 0, 1, 0x65];
+        access_unit.extend(1..=20u8);
+        access_unit.extend([0, 0, 0, 1, 0x41, 7]);
+        let packets = packetizer.packetize(&access_unit, 0);
+
+        assert_eq!(packets.iter().map(|p| has_marker(p)).collect::<Vec<_>>(), [false, false, false, true]);
+    }
+
+    #[test]
+    fn sequence_wraps_around() {
+        let mut packetizer = RtpPacketizer::new(DEFAULT_MTU);
+        packetizer.sequence = u16::MAX;
+        let packets = packetizer.packetize(&[0, 0, 1, 0x41, 1, 0, 0, 1, 0x41, 2], 0);
+        assert_eq!(packets.iter().map(|p| sequence_of(p)).collect::<Vec<_>>(), [u16::MAX, 0]);
+    }
+
+    #[test]
+    fn base64_matches_rfc_4648() {
+        for (input, expected) in [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ] {
+            assert_eq!(base64(input.as_bytes()), expected);
+        }
+    }
+
+    #[test]
+    fn parse_client_port_reads_the_rtp_port() {
+        assert_eq!(parse_client_port("RTP/AVP;unicast;client_port=5000-5001"), Some(5000));
+        assert_eq!(parse_client_port("RTP/AVP; client_port=65535"), Some(65535));
+        assert_eq!(parse_client_port("RTP/AVP;unicast"), None);
+        assert_eq!(parse_client_port("RTP/AVP;client_port=abc-5001"), None);
+        assert_eq!(parse_client_port("RTP/AVP;client_port=70000-70001"), None);
+    }
+}
@@ -0,0 +1,172 @@
+//! Escala da região capturada na GPU com filtro escolhido
+//!
+//! O video processor de `capture_converted` usa o filtro do driver (em geral bilinear), que
+//! borra os pixels ao ampliar. Lupas e ferramentas de acessibilidade precisam de pixels
+//! nítidos (vizinho mais próximo) ou de uma ampliação suave de melhor qualidade (bicúbica);
+//! este compute shader faz as duas e a CPU só recebe a imagem final.
+
+use windows::Win32::Foundation::E_FAIL;
+use windows::Win32::Graphics::Direct3D11::*;
+use windows::Win32::Graphics::Dxgi::Common::DXGI_FORMAT_R16G16B16A16_FLOAT;
+
+use crate::error::{CaptureError, CaptureResult};
+use crate::gpu_stats::compile_compute_shader;
+
+/// Ponto de partida dos shaders; `FILTER`, `SATURATE` e `SWAP_RB` são definidos por variante
+const SCALE_SHADER: &str = r#"
+Texture2D<float4> Input : register(t0);
+RWTexture2D<float4> Output : register(u0);
+
+float4 Tap(int2 position, int2 size)
+{
+    return Input[clamp(position, int2(0, 0), size - 1)];
+}
+
+// Pesos de Catmull-Rom para as quatro amostras em torno de t (0..1)
+float4 CubicWeights(float t)
+{
+    float t2 = t * t;
+    float t3 = t2 * t;
+    return float4(
+        -0.5 * t3 + t2 - 0.5 * t,
+        1.5 * t3 - 2.5 * t2 + 1.0,
+        -1.5 * t3 + 2.0 * t2 + 0.5 * t,
+        0.5 * t3 - 0.5 * t2);
+}
+
+[numthreads(16, 16, 1)]
+void main(uint3 id : SV_DispatchThreadID)
+{
+    uint2 inputSize, outputSize;
+    Input.GetDimensions(inputSize.x, inputSize.y);
+    Output.GetDimensions(outputSize.x, outputSize.y);
+    if (id.x >= outputSize.x || id.y >= outputSize.y) {
+        return;
+    }
+
+    int2 size = (int2)inputSize;
+    float2 position = (id.xy + 0.5) * (float2)inputSize / (float2)outputSize;
+#if FILTER == 0
+    float4 color = Tap((int2)position, size);
+#elif FILTER == 1
+    float2 p = position - 0.5;
+    int2 base = (int2)floor(p);
+    float2 f = p - base;
+    float4 top = lerp(Tap(base, size), Tap(base + int2(1, 0), size), f.x);
+    float4 bottom = lerp(Tap(base + int2(0, 1), size), Tap(base + int2(1, 1), size), f.x);
+    float4 color = lerp(top, bottom, f.y);
+#else
+    float2 p = position - 0.5;
+    int2 base = (int2)floor(p);
+    float2 f = p - base;
+    float4 wx = CubicWeights(f.x);
+    float4 wy = CubicWeights(f.y);
+    float4 color = 0;
+    [unroll] for (int y = 0; y < 4; y++) {
+        float4 row = 0;
+        [unroll] for (int x = 0; x < 4; x++) {
+            row += Tap(base + int2(x - 1, y - 1), size) * wx[x];
+        }
+        color += row * wy[y];
+    }
+#endif
+#if SATURATE
+    // Catmull-Rom ultrapassa levemente 0..1 nas bordas de alto contraste
+    color = saturate(color);
+#endif
+#if SWAP_RB
+    color = color.bgra;
+#endif
+    Output[id.xy] = color;
+}
+"#;
+
+/// Filtro de `DxgiCapture::capture_scaled`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum ScaleFilter {
+    /// Vizinho mais próximo: cada pixel vira um bloco nítido (ampliações inteiras são exatas)
+    #[default]
+    Nearest,
+    Bilinear,
+    /// Catmull-Rom 4x4: ampliação suave sem o borrão do bilinear
+    Bicubic,
+}
+
+impl ScaleFilter {
+    fn index(&self) -> u32 {
+        match self {
+            ScaleFilter::Nearest => 0,
+            ScaleFilter::Bilinear => 1,
+            ScaleFilter::Bicubic => 2,
+        }
+    }
+}
+
+/// Variantes do shader de escala, compiladas no primeiro uso de cada combinação
+#[derive(Default)]
+pub(crate) struct ScalePass {
+    shaders: Vec<((ScaleFilter, bool, bool), ID3D11ComputeShader)>,
+}
+
+impl ScalePass {
+    /// Escala `input` inteira para o tamanho de `output` (que precisa aceitar UAV).
+    ///
+    /// Em saídas UNORM o resultado é limitado a 0..1; `swap_rb` troca vermelho e azul na
+    /// escrita, para gravar bytes BGRA em uma textura R8G8B8A8.
+    pub(crate) fn run(
+        &mut self,
+        device: &ID3D11Device,
+        context: &ID3D11DeviceContext,
+        filter: ScaleFilter,
+        swap_rb: bool,
+        input: &ID3D11Texture2D,
+        output: &ID3D11Texture2D,
+    ) -> CaptureResult<()> {
+        let mut desc = D3D11_TEXTURE2D_DESC::default();
+        unsafe { output.GetDesc(&mut desc) };
+        let saturate = desc.Format != DXGI_FORMAT_R16G16B16A16_FLOAT;
+        let shader = self.shader(device, (filter, saturate, swap_rb))?;
+
+        let mut input_view: Option<ID3D11ShaderResourceView> = None;
+        let mut output_view: Option<ID3D11UnorderedAccessView> = None;
+        unsafe {
+            device.CreateShaderResourceView(input, None, Some(&mut input_view))?;
+            device.CreateUnorderedAccessView(output, None, Some(&mut output_view))?;
+        }
+        let input_view = input_view.ok_or(CaptureError::Windows(windows::core::Error::from(E_FAIL)))?;
+
+        unsafe {
+            context.CSSetShader(&shader, None);
+            context.CSSetShaderResources(0, Some(&[Some(input_view)]));
+            context.CSSetUnorderedAccessViews(0, 1, Some(&output_view), None);
+            context.Dispatch(desc.Width.div_ceil(16), desc.Height.div_ceil(16), 1);
+
+            context.CSSetShaderResources(0, Some(&[None]));
+            context.CSSetUnorderedAccessViews(0, 1, Some(&None), None);
+            context.CSSetShader(None, None);
+        }
+
+        Ok(())
+    }
+
+    fn shader(&mut self, device: &ID3D11Device, key: (ScaleFilter, bool, bool)) -> CaptureResult<ID3D11ComputeShader> {
+        if let Some((_, shader)) = self.shaders.iter().find(|(cached, _)| *cached == key) {
+            return Ok(shader.clone());
+        }
+
+        let (filter, saturate, swap_rb) = key;
+        let source = format!(
+            "#define FILTER {}\n#define SATURATE {}\n#define SWAP_RB {}\n{}",
+            filter.index(),
+            saturate as u32,
+            swap_rb as u32,
+            SCALE_SHADER
+        );
+        let shader = compile_compute_shader(device, &source)?;
+        debug!("Shader de escala compilado: {:?}", key);
+        self.shaders.push((key, shader.clone()));
+        Ok(shader)
+    }
+}
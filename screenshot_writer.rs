@@ -0,0 +1,351 @@
+//! Gravação de capturas em segundo plano (features `png` e/ou `jpeg`)
+//!
+//! Comprimir um PNG 4K leva dezenas de milissegundos, tempo em que um laço de captura perderia
+//! quadros. `ScreenshotWriter::save` só entrega o quadro a um pequeno conjunto de threads, que
+//! comprimem e gravam em paralelo, e retorna na hora com o número do pedido.
+//!
+//! Ordem: cada imagem é gravada em um arquivo temporário ao lado do destino e movida para o
+//! nome final por uma única thread, na ordem dos pedidos, que em seguida chama o callback de
+//! conclusão. Assim os callbacks chegam na ordem de `save`, um arquivo nunca aparece pela metade
+//! e, se dois pedidos usam o mesmo caminho, o último vence. Com mais de `max_pending` pedidos em
+//! andamento o novo quadro é descartado (e o callback informa) em vez de bloquear a captura.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::frame::Frame;
+#[cfg(feature = "jpeg")]
+use crate::jpeg_encoder::{JpegEncoder, JpegOptions};
+#[cfg(feature = "png")]
+use crate::png_encoder::encode_png;
+use crate::Result;
+
+/// Formato de imagem de um pedido
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScreenshotFormat {
+    #[cfg(feature = "png")]
+    Png,
+    #[cfg(feature = "jpeg")]
+    Jpeg(JpegOptions),
+}
+
+/// Tamanho do conjunto de threads e da fila
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct ScreenshotWriterOptions {
+    /// Threads de compressão e gravação
+    pub workers: usize,
+    /// Pedidos em andamento a partir dos quais novos quadros são descartados
+    pub max_pending: usize,
+    /// Parâmetros usados por `save` para caminhos `.jpg`/`.jpeg`
+    #[cfg(feature = "jpeg")]
+    pub jpeg: JpegOptions,
+}
+
+impl Default for ScreenshotWriterOptions {
+    fn default() -> Self {
+        Self {
+            workers: 2,
+            max_pending: 32,
+            #[cfg(feature = "jpeg")]
+            jpeg: JpegOptions::default(),
+        }
+    }
+}
+
+/// Resultado de um pedido, entregue ao callback na ordem dos pedidos
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScreenshotCompletion {
+    /// Número retornado por `save`
+    pub id: u64,
+    pub path: PathBuf,
+    /// `Frame::sequence` do quadro gravado
+    pub sequence: u64,
+    /// Tamanho do arquivo gravado
+    pub bytes: usize,
+    /// Do pedido até o arquivo estar no lugar
+    pub elapsed: Duration,
+    /// Mensagem do erro de compressão ou gravação, ou do descarte por fila cheia
+    pub error: Option<String>,
+}
+
+impl ScreenshotCompletion {
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+type Callback = Box<dyn FnMut(&ScreenshotCompletion) + Send>;
+
+struct Job {
+    id: u64,
+    frame: Frame,
+    path: PathBuf,
+    format: std::result::Result<ScreenshotFormat, String>,
+    submitted: Instant,
+}
+
+/// Pedido terminado por uma thread de compressão, aguardando a vez de ser concluído
+struct Finished {
+    id: u64,
+    path: PathBuf,
+    sequence: u64,
+    submitted: Instant,
+    /// Arquivo temporário e tamanho, ou o erro
+    written: std::result::Result<(PathBuf, usize), String>,
+}
+
+/// Pedidos em andamento, para `pending` e `flush`
+#[derive(Default)]
+struct Pending {
+    count: Mutex<usize>,
+    drained: Condvar,
+}
+
+/// Grava capturas em PNG ou JPEG sem bloquear a thread que captura
+pub struct ScreenshotWriter {
+    options: ScreenshotWriterOptions,
+    jobs: Option<Sender<Job>>,
+    finished: Option<Sender<Finished>>,
+    pending: Arc<Pending>,
+    next_id: u64,
+    workers: Vec<JoinHandle<()>>,
+    completer: Option<JoinHandle<()>>,
+}
+
+impl ScreenshotWriter {
+    pub fn new(options: ScreenshotWriterOptions) -> Result<Self> {
+        Self::with_callback(options, |_: &ScreenshotCompletion| {})
+    }
+
+    /// Como `new`, chamando `callback` (na thread de conclusão) ao fim de cada pedido
+    pub fn with_callback<F>(options: ScreenshotWriterOptions, callback: F) -> Result<Self>
+    where
+        F: FnMut(&ScreenshotCompletion) + Send + 'static,
+    {
+        let (jobs, job_queue) = mpsc::channel::<Job>();
+        let (finished, finished_queue) = mpsc::channel::<Finished>();
+        let job_queue = Arc::new(Mutex::new(job_queue));
+        let pending = Arc::new(Pending::default());
+
+        let workers = (0..options.workers.max(1))
+            .map(|index| {
+                let (queue, finished) = (job_queue.clone(), finished.clone());
+                thread::Builder::new()
+                    .name(format!("screenshot-writer-{}", index))
+                    .spawn(move || run_worker(&queue, &finished))
+            })
+            .collect::<std::io::Result<Vec<_>>>()?;
+
+        let completer_pending = pending.clone();
+        let completer = thread::Builder::new()
+            .name("screenshot-completer".into())
+            .spawn(move || run_completer(finished_queue, Box::new(callback), &completer_pending))?;
+
+        Ok(Self {
+            options,
+            jobs: Some(jobs),
+            finished: Some(finished),
+            pending,
+            next_id: 0,
+            workers,
+            completer: Some(completer),
+        })
+    }
+
+    pub fn options(&self) -> &ScreenshotWriterOptions {
+        &self.options
+    }
+
+    /// Grava `frame` em `path` no formato da extensão (`.png`, `.jpg`/`.jpeg`) e retorna o número
+    /// do pedido; uma extensão desconhecida é informada ao callback como erro
+    pub fn save(&mut self, frame: Frame, path: impl AsRef<Path>) -> u64 {
+        let path = path.as_ref().to_path_buf();
+        let format = self.format_for(&path);
+        self.submit(frame, path, format)
+    }
+
+    /// Grava `frame` em `path` no formato indicado
+    pub fn save_as(&mut self, frame: Frame, path: impl AsRef<Path>, format: ScreenshotFormat) -> u64 {
+        self.submit(frame, path.as_ref().to_path_buf(), Ok(format))
+    }
+
+    /// Pedidos ainda não concluídos
+    pub fn pending(&self) -> usize {
+        *self.pending.count.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Aguarda a conclusão de todos os pedidos feitos até aqui
+    pub fn flush(&self) {
+        let mut count = self.pending.count.lock().unwrap_or_else(|e| e.into_inner());
+        while *count > 0 {
+            count = self.pending.drained.wait(count).unwrap_or_else(|e| e.into_inner());
+        }
+    }
+
+    fn format_for(&self, path: &Path) -> std::result::Result<ScreenshotFormat, String> {
+        let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or_default();
+        match extension.to_ascii_lowercase().as_str() {
+            #[cfg(feature = "png")]
+            "png" => Ok(ScreenshotFormat::Png),
+            #[cfg(feature = "jpeg")]
+            "jpg" | "jpeg" => Ok(ScreenshotFormat::Jpeg(self.options.jpeg)),
+            _ => Err(format!("Extensão sem formato de imagem disponível: {}", path.display())),
+        }
+    }
+
+    fn submit(&mut self, frame: Frame, path: PathBuf, format: std::result::Result<ScreenshotFormat, String>) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        let submitted = Instant::now();
+
+        let accepted = {
+            let mut count = self.pending.count.lock().unwrap_or_else(|e| e.into_inner());
+            *count += 1;
+            *count <= self.options.max_pending.max(1)
+        };
+        if !accepted {
+            warn!("Captura {} descartada: {} gravações em andamento", id, self.options.max_pending);
+            self.reject(id, frame.sequence, path, submitted, "Fila de gravação cheia".to_owned());
+            return id;
+        }
+
+        let job = Job {
+            id,
+            frame,
+            path,
+            format,
+            submitted,
+        };
+        if let Some(Err(mpsc::SendError(job))) = self.jobs.as_ref().map(|jobs| jobs.send(job)) {
+            self.reject(job.id, job.frame.sequence, job.path, submitted, "Threads de gravação encerradas".to_owned());
+        }
+        id
+    }
+
+    /// Conclui um pedido sem passar pelas threads de compressão, mantendo a ordem dos callbacks
+    fn reject(&self, id: u64, sequence: u64, path: PathBuf, submitted: Instant, error: String) {
+        if let Some(finished) = &self.finished {
+            let _ = finished.send(Finished {
+                id,
+                path,
+                sequence,
+                submitted,
+                written: Err(error),
+            });
+        }
+    }
+}
+
+impl Drop for ScreenshotWriter {
+    /// Os pedidos já feitos são gravados antes de as threads terminarem
+    fn drop(&mut self) {
+        self.jobs.take();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+        self.finished.take();
+        if let Some(completer) = self.completer.take() {
+            let _ = completer.join();
+        }
+    }
+}
+
+fn run_worker(queue: &Mutex<Receiver<Job>>, finished: &Sender<Finished>) {
+    #[cfg(feature = "jpeg")]
+    let mut jpeg: Option<JpegEncoder> = None;
+    loop {
+        let job = match queue.lock().unwrap_or_else(|e| e.into_inner()).recv() {
+            Ok(job) => job,
+            Err(_) => return,
+        };
+        let encoded = job.format.and_then(|format| {
+            let encoded: Result<Vec<u8>> = match format {
+                #[cfg(feature = "png")]
+                ScreenshotFormat::Png => encode_png(&job.frame),
+                #[cfg(feature = "jpeg")]
+                ScreenshotFormat::Jpeg(options) => encode_jpeg_reusing(&mut jpeg, &job.frame, options),
+            };
+            encoded.map_err(|error| error.to_string())
+        });
+        let written = encoded.and_then(|image| {
+            let temporary = temporary_path(&job.path, job.id);
+            fs::write(&temporary, &image).map_err(|error| error.to_string())?;
+            Ok((temporary, image.len()))
+        });
+        let _ = finished.send(Finished {
+            id: job.id,
+            path: job.path,
+            sequence: job.frame.sequence,
+            submitted: job.submitted,
+            written,
+        });
+    }
+}
+
+#[cfg(feature = "jpeg")]
+fn encode_jpeg_reusing(encoder: &mut Option<JpegEncoder>, frame: &Frame, options: JpegOptions) -> Result<Vec<u8>> {
+    let encoder = match encoder {
+        Some(encoder) => {
+            if encoder.options() != options {
+                encoder.set_options(options)?;
+            }
+            encoder
+        }
+        None => encoder.insert(JpegEncoder::new(options)?),
+    };
+    encoder.encode(frame)
+}
+
+/// Conclui os pedidos na ordem dos números: move o arquivo temporário e chama o callback
+fn run_completer(finished: Receiver<Finished>, mut callback: Callback, pending: &Pending) {
+    let mut waiting = BTreeMap::new();
+    let mut next = 0u64;
+    for done in finished {
+        waiting.insert(done.id, done);
+        while let Some(done) = waiting.remove(&next) {
+            next += 1;
+            let (bytes, error) = match done.written {
+                Ok((temporary, bytes)) => match fs::rename(&temporary, &done.path) {
+                    Ok(()) => (bytes, None),
+                    Err(error) => {
+                        let _ = fs::remove_file(&temporary);
+                        (0, Some(error.to_string()))
+                    }
+                },
+                Err(error) => (0, Some(error)),
+            };
+            if let Some(error) = &error {
+                warn!("Falha ao gravar {}: {}", done.path.display(), error);
+            }
+
+            callback(&ScreenshotCompletion {
+                id: done.id,
+                path: done.path,
+                sequence: done.sequence,
+                bytes,
+                elapsed: done.submitted.elapsed(),
+                error,
+            });
+
+            let mut count = pending.count.lock().unwrap_or_else(|e| e.into_inner());
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                pending.drained.notify_all();
+            }
+        }
+    }
+}
+
+/// Arquivo temporário no mesmo diretório do destino, para que a troca de nome seja atômica
+fn temporary_path(path: &Path, id: u64) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}.tmp", id));
+    path.with_file_name(name)
+}
@@ -0,0 +1,305 @@
+//! Captura com rolagem: monta uma imagem alta ("página inteira") a partir de capturas
+//! sucessivas de uma região enquanto o conteúdo rola
+//!
+//! A cada quadro o deslocamento vertical em relação ao anterior é estimado por correlação
+//! cruzada normalizada entre assinaturas das linhas (a luminância somada em faixas de colunas),
+//! e só as linhas novas são acrescentadas. Cabeçalhos e rodapés fixos da página podem ser
+//! excluídos da comparação com `fixed_top` e `fixed_bottom`: o cabeçalho entra uma vez no topo
+//! e o rodapé, uma vez no fim.
+
+use std::time::{Duration, Instant};
+
+use crate::error::CaptureError;
+use crate::frame::{Frame, FrameMetadata};
+use crate::geometry::{Rect, Size};
+use crate::pixel_format::PixelFormat;
+use crate::{DxgiCapture, Result};
+
+/// Faixas de colunas somadas na assinatura de cada linha
+const SIGNATURE_BANDS: usize = 16;
+/// Intervalo entre tentativas enquanto o conteúdo rolado não é apresentado
+const SETTLE_RETRY: Duration = Duration::from_millis(15);
+
+/// Opções de `ScrollCapture`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct ScrollOptions {
+    /// Linhas em comum exigidas entre dois quadros para aceitar um deslocamento
+    pub min_overlap: u32,
+    /// Correlação mínima (0 a 1) para aceitar um deslocamento
+    pub threshold: f64,
+    /// Linhas do topo da região que não rolam (cabeçalho fixo)
+    pub fixed_top: u32,
+    /// Linhas da base da região que não rolam (rodapé fixo, barra de status)
+    pub fixed_bottom: u32,
+    /// Altura máxima da imagem montada; ao atingi-la, `push` retorna `ScrollStep::Full`
+    pub max_height: u32,
+    /// Quanto `run` espera a tela mudar depois de cada rolagem
+    pub settle_timeout: Duration,
+}
+
+impl Default for ScrollOptions {
+    fn default() -> Self {
+        Self {
+            min_overlap: 32,
+            threshold: 0.97,
+            fixed_top: 0,
+            fixed_bottom: 0,
+            max_height: 32768,
+            settle_timeout: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Resultado de `ScrollCapture::push`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScrollStep {
+    /// Primeiro quadro da imagem
+    Started,
+    /// O conteúdo rolou e `rows` linhas novas foram acrescentadas
+    Appended { rows: u32 },
+    /// O conteúdo não mudou (fim da página ou a rolagem ainda não foi apresentada)
+    Unchanged,
+    /// Nenhum deslocamento explica o quadro novo (rolagem maior que a região, rolagem para
+    /// cima ou conteúdo que mudou); o quadro foi ignorado
+    NoOverlap,
+    /// `max_height` atingido; linhas além do limite foram descartadas
+    Full,
+}
+
+struct Previous {
+    frame: Frame,
+    signatures: Vec<[f64; SIGNATURE_BANDS]>,
+}
+
+/// Monta a imagem alta a partir de quadros de uma mesma região (B8G8R8A8 ou R8G8B8A8)
+pub struct ScrollCapture {
+    options: ScrollOptions,
+    data: Vec<u8>,
+    width: u32,
+    height: u32,
+    previous: Option<Previous>,
+}
+
+impl ScrollCapture {
+    pub fn new(options: ScrollOptions) -> Self {
+        Self {
+            options,
+            data: Vec::new(),
+            width: 0,
+            height: 0,
+            previous: None,
+        }
+    }
+
+    /// Altura montada até agora, sem o rodapé fixo
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Acrescenta o quadro, se ele continuar o anterior rolado para baixo
+    pub fn push(&mut self, frame: &Frame) -> Result<ScrollStep> {
+        let format = PixelFormat::from_dxgi(frame.format)
+            .filter(|format| matches!(format, PixelFormat::Bgra8 | PixelFormat::Rgba8))
+            .ok_or_else(|| format!("Formato não suportado na captura com rolagem: {:?}", frame.format))?;
+        let (width, height) = (frame.width(), frame.height());
+        if frame.data.len() < format.buffer_size(width, height) {
+            return Err("Buffer menor que o quadro".into());
+        }
+        let (top, bottom) = self.scrolling_rows(height)?;
+        let signatures = row_signatures(frame, top, bottom);
+
+        let Some(previous) = &self.previous else {
+            // Primeiro quadro: tudo menos o rodapé fixo
+            self.width = width;
+            self.append_rows(frame, 0, bottom);
+            self.previous = Some(Previous {
+                frame: frame.clone(),
+                signatures,
+            });
+            return Ok(ScrollStep::Started);
+        };
+        if previous.frame.format != frame.format || previous.frame.width() != width || previous.frame.height() != height {
+            return Err("Os quadros da captura com rolagem precisam ter o mesmo tamanho e formato".into());
+        }
+
+        let step = match find_scroll(&previous.signatures, &signatures, &self.options) {
+            Some(0) => ScrollStep::Unchanged,
+            Some(offset) => {
+                let appended = self.append_rows(frame, bottom - offset, bottom);
+                if appended < offset {
+                    ScrollStep::Full
+                } else {
+                    ScrollStep::Appended { rows: appended }
+                }
+            }
+            None => return Ok(ScrollStep::NoOverlap),
+        };
+
+        self.previous = Some(Previous {
+            frame: frame.clone(),
+            signatures,
+        });
+        Ok(step)
+    }
+
+    /// Captura `region`, chama `scroll` para rolar o conteúdo e repete até `scroll` retornar
+    /// `false`, o conteúdo parar de mudar ou `max_height` ser atingido.
+    ///
+    /// `scroll` pode enviar a roda do mouse, uma tecla Page Down ou um comando de automação; o
+    /// usuário também pode rolar manualmente com um `scroll` que só espera.
+    pub fn run<F>(capture: &mut DxgiCapture, region: Rect, options: ScrollOptions, mut scroll: F) -> Result<Frame>
+    where
+        F: FnMut() -> bool,
+    {
+        let settle = options.settle_timeout;
+        let mut assembler = Self::new(options);
+        assembler.push(&capture_settled(capture, region, settle)?)?;
+
+        while scroll() {
+            let frame = match capture_settled(capture, region, settle) {
+                Ok(frame) => frame,
+                // A rolagem não mudou nada na tela: fim da página
+                Err(e) if matches!(e.downcast_ref::<CaptureError>(), Some(CaptureError::NoNewFrame)) => break,
+                Err(e) => return Err(e),
+            };
+            match assembler.push(&frame)? {
+                ScrollStep::Unchanged | ScrollStep::Full => break,
+                ScrollStep::NoOverlap => warn!("Quadro sem sobreposição com o anterior ignorado na captura com rolagem"),
+                ScrollStep::Started | ScrollStep::Appended { .. } => {}
+            }
+        }
+
+        assembler.finish()
+    }
+
+    /// Imagem final, com o rodapé fixo do último quadro
+    pub fn finish(mut self) -> Result<Frame> {
+        let Some(previous) = self.previous.take() else {
+            return Err("Nenhum quadro na captura com rolagem".into());
+        };
+        let (_, bottom) = self.scrolling_rows(previous.frame.height())?;
+        self.append_rows(&previous.frame, bottom, previous.frame.height());
+
+        debug!("Captura com rolagem montada: {}x{}", self.width, self.height);
+        Ok(Frame {
+            data: self.data,
+            region: Rect::from_size(Size::new(self.width, self.height)),
+            format: previous.frame.format,
+            color_space: previous.frame.color_space,
+            present_time: previous.frame.present_time,
+            sequence: previous.frame.sequence,
+            metadata: FrameMetadata::default(),
+        })
+    }
+
+    /// Faixa de linhas que rola, entre o cabeçalho e o rodapé fixos
+    fn scrolling_rows(&self, height: u32) -> Result<(u32, u32)> {
+        let top = self.options.fixed_top;
+        let bottom = height.saturating_sub(self.options.fixed_bottom);
+        if bottom <= top || bottom - top <= self.options.min_overlap {
+            return Err("Cabeçalho e rodapé fixos não deixam linhas suficientes para comparar".into());
+        }
+        Ok((top, bottom))
+    }
+
+    /// Copia as linhas `from..to` do quadro para o fim da imagem, respeitando `max_height`;
+    /// retorna quantas foram copiadas
+    fn append_rows(&mut self, frame: &Frame, from: u32, to: u32) -> u32 {
+        let rows = to.saturating_sub(from).min(self.options.max_height.saturating_sub(self.height));
+        let row_bytes = PixelFormat::Bgra8.min_stride(frame.width());
+        let stride = frame.stride();
+        for y in from..from + rows {
+            let start = y as usize * stride;
+            self.data.extend_from_slice(&frame.data[start..start + row_bytes]);
+        }
+        self.height += rows;
+        rows
+    }
+}
+
+/// Captura a região esperando até `timeout` por um quadro novo
+fn capture_settled(capture: &mut DxgiCapture, region: Rect, timeout: Duration) -> Result<Frame> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        match capture.capture_frame(region) {
+            Err(CaptureError::NoNewFrame) if Instant::now() < deadline => {
+                if capture.cancellation().wait_timeout(SETTLE_RETRY) {
+                    return Err(CaptureError::Cancelled.into());
+                }
+            }
+            result => return Ok(result?),
+        }
+    }
+}
+
+/// Luminância de cada linha `top..bottom` somada em `SIGNATURE_BANDS` faixas de colunas
+fn row_signatures(frame: &Frame, top: u32, bottom: u32) -> Vec<[f64; SIGNATURE_BANDS]> {
+    let width = frame.width() as usize;
+    let stride = frame.stride();
+    // R e B trocam de lugar entre os formatos, mas a soma ponderada só precisa ser consistente
+    (top..bottom)
+        .map(|y| {
+            let row = &frame.data[y as usize * stride..];
+            let mut signature = [0.0; SIGNATURE_BANDS];
+            for (x, pixel) in row.chunks_exact(4).take(width).enumerate() {
+                let luma = 0.25 * pixel[0] as f64 + 0.5 * pixel[1] as f64 + 0.25 * pixel[2] as f64;
+                signature[x * SIGNATURE_BANDS / width.max(1)] += luma;
+            }
+            signature
+        })
+        .collect()
+}
+
+/// Deslocamento (linhas que o conteúdo subiu) com a maior correlação entre as assinaturas, se
+/// ela passar do limite; empates favorecem o menor deslocamento
+fn find_scroll(
+    previous: &[[f64; SIGNATURE_BANDS]],
+    current: &[[f64; SIGNATURE_BANDS]],
+    options: &ScrollOptions,
+) -> Option<u32> {
+    let rows = previous.len().min(current.len());
+    let min_overlap = (options.min_overlap as usize).max(1);
+    if rows < min_overlap {
+        return None;
+    }
+
+    // Nada mudou: evita que conteúdo repetitivo pareça rolado
+    if previous[..rows] == current[..rows] {
+        return Some(0);
+    }
+
+    let mut best: Option<(usize, f64)> = None;
+    for offset in 0..=rows - min_overlap {
+        // A linha `y` do quadro novo corresponde à linha `y + offset` do anterior
+        let score = correlation(&previous[offset..rows], &current[..rows - offset]);
+        if best.map_or(true, |(_, best_score)| score > best_score + 1e-9) {
+            best = Some((offset, score));
+        }
+    }
+
+    best.filter(|(_, score)| *score >= options.threshold)
+        .map(|(offset, _)| offset as u32)
+}
+
+/// Correlação cruzada normalizada entre duas sequências de assinaturas do mesmo tamanho;
+/// sequências constantes só se correlacionam se forem iguais
+fn correlation(a: &[[f64; SIGNATURE_BANDS]], b: &[[f64; SIGNATURE_BANDS]]) -> f64 {
+    let count = (a.len() * SIGNATURE_BANDS) as f64;
+    let mean = |values: &[[f64; SIGNATURE_BANDS]]| values.iter().flatten().sum::<f64>() / count;
+    let (mean_a, mean_b) = (mean(a), mean(b));
+
+    let (mut covariance, mut variance_a, mut variance_b) = (0.0, 0.0, 0.0);
+    for (x, y) in a.iter().flatten().zip(b.iter().flatten()) {
+        let (dx, dy) = (x - mean_a, y - mean_b);
+        covariance += dx * dy;
+        variance_a += dx * dx;
+        variance_b += dy * dy;
+    }
+
+    if variance_a == 0.0 || variance_b == 0.0 {
+        return if a == b { 1.0 } else { 0.0 };
+    }
+    covariance / (variance_a * variance_b).sqrt()
+}
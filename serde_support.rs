@@ -0,0 +1,15 @@
+//! Adaptadores serde para tipos do `windows` usados nas estruturas públicas (feature `serde`)
+
+/// Serializa `DXGI_FORMAT` pelo seu valor numérico
+pub(crate) mod dxgi_format {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use windows::Win32::Graphics::Dxgi::Common::DXGI_FORMAT;
+
+    pub fn serialize<S: Serializer>(format: &DXGI_FORMAT, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(format.0)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<DXGI_FORMAT, D::Error> {
+        u32::deserialize(deserializer).map(DXGI_FORMAT)
+    }
+}
@@ -0,0 +1,324 @@
+//! Contexto de sessão do processo e captura a partir de serviços
+//!
+//! A Desktop Duplication só funciona em um processo da sessão do usuário, na window station
+//! interativa (`WinSta0`). Serviços do Windows rodam na sessão 0, isolada desde o Vista, onde
+//! a criação da duplicação falha com erros pouco claros (`E_ACCESSDENIED`, nenhum monitor).
+//! `DxgiCapture` verifica o contexto na criação e retorna `CaptureError::NonInteractiveSession`.
+//!
+//! Para capturar a partir de um serviço, `launch_in_user_session` inicia um processo auxiliar
+//! na sessão do usuário conectado ao console, que captura e envia os quadros pelo protocolo de
+//! `pipe`; o serviço os recebe com `CompanionProcess::connect` (feature `streaming`).
+//!
+//! Em sessões de Área de Trabalho Remota a tela é desenhada pelo driver de vídeo remoto e a
+//! sessão pode ser desconectada a qualquer momento. `DxgiCapture` informa a desconexão como
+//! `CaptureError::SessionDisconnected`, deixa de tentar recriar a duplicação enquanto
+//! `session_state` indicar a sessão desconectada e a recria sozinho quando ela volta (pelo
+//! cliente remoto ou pelo console).
+
+use std::ffi::c_void;
+use std::iter;
+use std::mem;
+use std::ptr;
+#[cfg(feature = "streaming")]
+use std::thread;
+use std::time::Duration;
+#[cfg(feature = "streaming")]
+use std::time::Instant;
+
+use windows::core::{PCWSTR, PWSTR};
+use windows::Win32::Foundation::{CloseHandle, ERROR_NO_SUCH_LOGON_SESSION, HANDLE, STILL_ACTIVE, WAIT_OBJECT_0};
+#[cfg(feature = "streaming")]
+use windows::Win32::Graphics::Dxgi::Common::DXGI_FORMAT;
+use windows::Win32::System::Environment::{CreateEnvironmentBlock, DestroyEnvironmentBlock};
+use windows::Win32::System::RemoteDesktop::{
+    ProcessIdToSessionId, WTSActive, WTSConnectState, WTSConnected, WTSDisconnected, WTSFreeMemory,
+    WTSGetActiveConsoleSessionId, WTSQuerySessionInformationW, WTSQueryUserToken, WTS_CONNECTSTATE_CLASS,
+    WTS_CURRENT_SERVER_HANDLE,
+};
+use windows::Win32::System::StationsAndDesktops::{
+    GetProcessWindowStation, GetUserObjectInformationW, UOI_FLAGS, UOI_NAME, USEROBJECTFLAGS,
+};
+use windows::Win32::System::Threading::{
+    CreateProcessAsUserW, GetCurrentProcessId, GetExitCodeProcess, TerminateProcess, WaitForSingleObject,
+    CREATE_NO_WINDOW, CREATE_UNICODE_ENVIRONMENT, PROCESS_INFORMATION, STARTUPINFOW,
+};
+use windows::Win32::UI::WindowsAndMessaging::{GetSystemMetrics, SM_REMOTESESSION};
+
+use crate::error::{CaptureError, CaptureResult};
+#[cfg(feature = "streaming")]
+use crate::pipe::PipeClient;
+
+/// Window station com superfície visível (flag `WSF_VISIBLE` de `USEROBJECTFLAGS`)
+const WSF_VISIBLE: u32 = 0x0001;
+/// Intervalo entre tentativas de conectar ao pipe do processo auxiliar
+#[cfg(feature = "streaming")]
+const CONNECT_RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Sessão e window station do processo atual
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SessionInfo {
+    /// Sessão do Terminal Services (0 = serviços)
+    pub session_id: u32,
+    /// Nome da window station (`WinSta0` na sessão interativa)
+    pub window_station: String,
+    /// A window station tem superfície visível (aceita entrada e exibe janelas)
+    pub interactive: bool,
+    /// Sessão de Área de Trabalho Remota
+    pub remote: bool,
+}
+
+/// Estado da conexão de uma sessão
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum SessionState {
+    /// Usuário conectado (no console ou por um cliente remoto)
+    Active,
+    /// Cliente conectado, ainda sem usuário (ex.: na tela de logon)
+    Connected,
+    /// Usuário conectado, mas sem cliente; a tela não é desenhada
+    Disconnected,
+    /// Outros estados do Terminal Services (escutando, reiniciando...)
+    Other,
+}
+
+impl SessionInfo {
+    /// Contexto do processo atual
+    pub fn current() -> CaptureResult<Self> {
+        let mut session_id = 0;
+        unsafe { ProcessIdToSessionId(GetCurrentProcessId(), &mut session_id)? };
+
+        let station = unsafe { GetProcessWindowStation()? };
+        let station = HANDLE(station.0);
+        let mut name = [0u16; 256];
+        let mut flags = USEROBJECTFLAGS::default();
+        unsafe {
+            GetUserObjectInformationW(
+                station,
+                UOI_NAME,
+                Some(name.as_mut_ptr() as *mut c_void),
+                mem::size_of_val(&name) as u32,
+                None,
+            )?;
+            GetUserObjectInformationW(
+                station,
+                UOI_FLAGS,
+                Some(&mut flags as *mut USEROBJECTFLAGS as *mut c_void),
+                mem::size_of::<USEROBJECTFLAGS>() as u32,
+                None,
+            )?;
+        }
+        let len = name.iter().position(|&c| c == 0).unwrap_or(name.len());
+
+        Ok(Self {
+            session_id,
+            window_station: String::from_utf16_lossy(&name[..len]),
+            interactive: flags.dwFlags & WSF_VISIBLE != 0,
+            remote: is_remote_session(),
+        })
+    }
+
+    /// Processo na sessão 0, onde rodam os serviços
+    pub fn is_service_session(&self) -> bool {
+        self.session_id == 0
+    }
+
+    /// O contexto permite criar a duplicação
+    pub fn can_duplicate(&self) -> bool {
+        !self.is_service_session() && self.interactive && self.window_station.eq_ignore_ascii_case("WinSta0")
+    }
+}
+
+/// Falha com `NonInteractiveSession` se o processo não puder duplicar a tela; se o contexto
+/// não puder ser consultado a criação segue e a duplicação decide
+pub(crate) fn ensure_interactive() -> CaptureResult<()> {
+    match SessionInfo::current() {
+        Ok(session) if !session.can_duplicate() => {
+            warn!(
+                "Processo na sessão {} (window station {}): a duplicação exige a sessão interativa do usuário",
+                session.session_id, session.window_station
+            );
+            Err(CaptureError::NonInteractiveSession {
+                session_id: session.session_id,
+                window_station: session.window_station,
+            })
+        }
+        Ok(_) => Ok(()),
+        Err(e) => {
+            debug!("Contexto de sessão indisponível: {}", e);
+            Ok(())
+        }
+    }
+}
+
+/// Sessão do usuário conectado ao console físico, se houver alguém conectado
+pub fn active_console_session() -> Option<u32> {
+    match unsafe { WTSGetActiveConsoleSessionId() } {
+        u32::MAX => None,
+        session_id => Some(session_id),
+    }
+}
+
+/// O processo está em uma sessão de Área de Trabalho Remota
+pub fn is_remote_session() -> bool {
+    unsafe { GetSystemMetrics(SM_REMOTESESSION) != 0 }
+}
+
+/// Estado da conexão da sessão `session_id`
+// Os estados do WTS são constantes com nomes em CamelCase
+#[allow(non_upper_case_globals)]
+pub fn session_state(session_id: u32) -> CaptureResult<SessionState> {
+    let mut buffer = PWSTR::null();
+    let mut size = 0;
+    unsafe { WTSQuerySessionInformationW(WTS_CURRENT_SERVER_HANDLE, session_id, WTSConnectState, &mut buffer, &mut size)? };
+    let state = unsafe { *(buffer.0 as *const WTS_CONNECTSTATE_CLASS) };
+    unsafe { WTSFreeMemory(buffer.0 as *mut c_void) };
+
+    Ok(match state {
+        WTSActive => SessionState::Active,
+        WTSConnected => SessionState::Connected,
+        WTSDisconnected => SessionState::Disconnected,
+        _ => SessionState::Other,
+    })
+}
+
+/// A sessão do processo atual ainda está desconectada; na dúvida (falha na consulta) considera
+/// que não, para que a duplicação seja tentada
+pub(crate) fn current_session_disconnected() -> bool {
+    let mut session_id = 0;
+    if unsafe { ProcessIdToSessionId(GetCurrentProcessId(), &mut session_id) }.is_err() {
+        return false;
+    }
+    matches!(session_state(session_id), Ok(SessionState::Disconnected))
+}
+
+/// Processo auxiliar iniciado na sessão do usuário
+#[derive(Debug)]
+pub struct CompanionProcess {
+    handle: HANDLE,
+    process_id: u32,
+    session_id: u32,
+}
+
+/// Inicia `command_line` (executável e argumentos, como na linha de comando) na sessão do
+/// usuário do console, com o token e o ambiente desse usuário e sem janela de console.
+///
+/// Exige um processo com `SeTcbPrivilege`, como um serviço rodando como LocalSystem. O processo
+/// auxiliar normalmente cria um `DxgiCapture` e serve os quadros com `pipe::PipeServer`.
+pub fn launch_in_user_session(command_line: &str) -> CaptureResult<CompanionProcess> {
+    let session_id = active_console_session()
+        .ok_or_else(|| windows::core::Error::from(ERROR_NO_SUCH_LOGON_SESSION.to_hresult()))?;
+
+    let mut token = HANDLE::default();
+    unsafe { WTSQueryUserToken(session_id, &mut token)? };
+
+    let mut environment: *mut c_void = ptr::null_mut();
+    if let Err(e) = unsafe { CreateEnvironmentBlock(&mut environment, token, false) } {
+        unsafe {
+            let _ = CloseHandle(token);
+        }
+        return Err(e.into());
+    }
+
+    let mut desktop: Vec<u16> = "winsta0\\default".encode_utf16().chain(iter::once(0)).collect();
+    let mut command: Vec<u16> = command_line.encode_utf16().chain(iter::once(0)).collect();
+    let startup = STARTUPINFOW {
+        cb: mem::size_of::<STARTUPINFOW>() as u32,
+        lpDesktop: PWSTR(desktop.as_mut_ptr()),
+        ..Default::default()
+    };
+    let mut info = PROCESS_INFORMATION::default();
+
+    let created = unsafe {
+        CreateProcessAsUserW(
+            token,
+            PCWSTR::null(),
+            PWSTR(command.as_mut_ptr()),
+            None,
+            None,
+            false,
+            CREATE_UNICODE_ENVIRONMENT | CREATE_NO_WINDOW,
+            Some(environment as *const c_void),
+            PCWSTR::null(),
+            &startup,
+            &mut info,
+        )
+    };
+    unsafe {
+        let _ = DestroyEnvironmentBlock(environment);
+        let _ = CloseHandle(token);
+    }
+    created?;
+
+    unsafe {
+        let _ = CloseHandle(info.hThread);
+    }
+    info!("Processo auxiliar {} iniciado na sessão {}", info.dwProcessId, session_id);
+    Ok(CompanionProcess {
+        handle: info.hProcess,
+        process_id: info.dwProcessId,
+        session_id,
+    })
+}
+
+impl CompanionProcess {
+    pub fn process_id(&self) -> u32 {
+        self.process_id
+    }
+
+    pub fn session_id(&self) -> u32 {
+        self.session_id
+    }
+
+    /// Código de saída, ou `None` se o processo ainda está rodando
+    pub fn exit_code(&self) -> Option<u32> {
+        let mut code = 0;
+        unsafe { GetExitCodeProcess(self.handle, &mut code) }.ok()?;
+        (code != STILL_ACTIVE.0 as u32).then_some(code)
+    }
+
+    /// Espera até `timeout` o processo terminar; retorna o código de saída se terminou
+    pub fn wait(&self, timeout: Duration) -> Option<u32> {
+        let millis = timeout.as_millis().min(u32::MAX as u128 - 1) as u32;
+        if unsafe { WaitForSingleObject(self.handle, millis) } != WAIT_OBJECT_0 {
+            return None;
+        }
+        self.exit_code()
+    }
+
+    /// Encerra o processo à força
+    pub fn terminate(&self) -> CaptureResult<()> {
+        unsafe { TerminateProcess(self.handle, 1)? };
+        Ok(())
+    }
+
+    /// Conecta ao pipe `name` servido pelo processo auxiliar, tentando de novo até `timeout`
+    /// enquanto ele inicializa; falha logo se o processo terminar antes (feature `streaming`)
+    #[cfg(feature = "streaming")]
+    pub fn connect(&self, name: &str, formats: &[DXGI_FORMAT], max_fps: u32, timeout: Duration) -> crate::Result<PipeClient> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            match PipeClient::connect(name, formats, max_fps) {
+                Ok(client) => return Ok(client),
+                Err(e) if Instant::now() >= deadline => return Err(e),
+                Err(e) => {
+                    if let Some(code) = self.exit_code() {
+                        return Err(format!("Processo auxiliar terminou com código {} antes de abrir o pipe", code).into());
+                    }
+                    debug!("Pipe do processo auxiliar indisponível, tentando de novo: {}", e);
+                    thread::sleep(CONNECT_RETRY_INTERVAL);
+                }
+            }
+        }
+    }
+}
+
+impl Drop for CompanionProcess {
+    // Fecha só o handle; o processo auxiliar continua rodando
+    fn drop(&mut self) {
+        unsafe {
+            let _ = CloseHandle(self.handle);
+        }
+    }
+}
@@ -0,0 +1,144 @@
+//! Entrega de quadros para várias saídas em paralelo
+//!
+//! Cada `FrameSink` registrado roda em sua própria thread, com sua própria fila e
+//! `QueuePolicy`: um gravador lento acumula ou descarta quadros na sua fila sem atrasar a
+//! pré-visualização, e um erro em uma saída é contado e registrado sem afetar as outras.
+//! Só uma saída com `QueuePolicy::Blocking` pode segurar a captura, e de propósito.
+
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use crate::frame::Frame;
+use crate::stream::{FrameQueue, QueuePolicy, StreamStats};
+use crate::Result;
+
+/// Saída de quadros (gravador, pré-visualização, estatísticas...)
+pub trait FrameSink: Send {
+    /// Processa um quadro; um erro é registrado e o próximo quadro é entregue normalmente
+    fn on_frame(&mut self, frame: &Frame) -> Result<()>;
+
+    /// Chamado uma vez quando a captura termina, depois do último quadro da fila (descarregar
+    /// buffers, finalizar arquivos); um erro é registrado como o de `on_frame`
+    fn finish(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<F> FrameSink for F
+where
+    F: FnMut(&Frame) -> Result<()> + Send,
+{
+    fn on_frame(&mut self, frame: &Frame) -> Result<()> {
+        self(frame)
+    }
+}
+
+/// Contadores de uma saída registrada
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SinkStats {
+    pub name: String,
+    pub queue: StreamStats,
+    /// Quadros aguardando na fila da saída
+    pub queued: usize,
+    /// Mensagem do último erro de `on_frame`
+    pub last_error: Option<String>,
+    /// A thread da saída terminou (pânico em `on_frame`)
+    pub closed: bool,
+}
+
+struct SinkWorker {
+    name: String,
+    queue: Arc<FrameQueue>,
+    last_error: Arc<Mutex<Option<String>>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+/// Marca a fila como parada quando a thread da saída termina, inclusive por pânico,
+/// para que uma captura com `QueuePolicy::Blocking` não fique esperando para sempre
+struct StopOnExit(Arc<FrameQueue>);
+
+impl Drop for StopOnExit {
+    fn drop(&mut self) {
+        self.0.stop();
+    }
+}
+
+/// Conjunto de saídas que recebem os mesmos quadros
+#[derive(Default)]
+pub struct FanOut {
+    workers: Vec<SinkWorker>,
+}
+
+impl FanOut {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registra uma saída; `name` identifica a saída nos logs e em `stats`
+    pub fn add<S: FrameSink + 'static>(&mut self, name: &str, mut sink: S, policy: QueuePolicy) {
+        let queue = Arc::new(FrameQueue::new(policy));
+        let last_error = Arc::new(Mutex::new(None));
+
+        let (worker_queue, worker_error, worker_name) = (queue.clone(), last_error.clone(), name.to_owned());
+        let thread = thread::spawn(move || {
+            let _stop = StopOnExit(worker_queue.clone());
+            while let Some(frame) = worker_queue.recv() {
+                if let Err(error) = sink.on_frame(&frame) {
+                    warn!("[{}] Falha ao processar quadro {}: {}", worker_name, frame.sequence, error);
+                    worker_queue.count_error();
+                    *worker_error.lock().unwrap_or_else(|e| e.into_inner()) = Some(error.to_string());
+                }
+            }
+            if let Err(error) = sink.finish() {
+                warn!("[{}] Falha ao finalizar saída: {}", worker_name, error);
+                *worker_error.lock().unwrap_or_else(|e| e.into_inner()) = Some(error.to_string());
+            }
+        });
+
+        self.workers.push(SinkWorker {
+            name: name.to_owned(),
+            queue,
+            last_error,
+            thread: Some(thread),
+        });
+    }
+
+    /// Entrega o quadro à fila de cada saída
+    pub fn dispatch(&self, frame: &Arc<Frame>) {
+        for worker in &self.workers {
+            // `false` só para saídas encerradas, que simplesmente deixam de receber
+            worker.queue.push(frame.clone());
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.workers.is_empty()
+    }
+
+    pub fn stats(&self) -> Vec<SinkStats> {
+        self.workers
+            .iter()
+            .map(|worker| SinkStats {
+                name: worker.name.clone(),
+                queue: worker.queue.stats(),
+                queued: worker.queue.len(),
+                last_error: worker.last_error.lock().unwrap_or_else(|e| e.into_inner()).clone(),
+                closed: worker.queue.is_stopped(),
+            })
+            .collect()
+    }
+}
+
+impl Drop for FanOut {
+    /// Cada saída processa o que já está na sua fila antes de a thread terminar
+    fn drop(&mut self) {
+        for worker in &self.workers {
+            worker.queue.finish();
+        }
+        for worker in &mut self.workers {
+            if let Some(thread) = worker.thread.take() {
+                let _ = thread.join();
+            }
+        }
+    }
+}
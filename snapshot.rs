@@ -0,0 +1,101 @@
+//! Captura única de todos os monitores, com a descrição de cada um
+//!
+//! Para ferramentas de "captura de tela inteira": cada monitor ganha uma duplicação de vida
+//! curta em sua própria thread, então todos são capturados praticamente no mesmo instante e
+//! nenhuma duplicação fica aberta depois da chamada.
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::cancel::CancellationToken;
+use crate::config::{CaptureConfig, NoFramePolicy};
+use crate::error::{CaptureError, CaptureResult};
+use crate::frame::Frame;
+use crate::outputs::{enumerate_outputs, OutputInfo};
+use crate::DxgiCapture;
+
+/// Intervalo entre tentativas enquanto a duplicação recém-criada não entrega o primeiro quadro
+const FIRST_FRAME_RETRY: Duration = Duration::from_millis(10);
+
+/// Opções de `snapshot_all_with`
+#[derive(Debug, Clone)]
+pub struct SnapshotOptions {
+    /// Configuração base de cada capturador; o monitor e a `no_frame_policy` são definidos
+    /// por `snapshot_all_with`
+    pub config: CaptureConfig,
+    /// Prazo para o primeiro quadro de cada monitor
+    pub timeout: Duration,
+    /// Omite (com um aviso no log) os monitores que falharem, em vez de falhar a chamada inteira
+    pub skip_failed: bool,
+    /// Interrompe as esperas; os monitores ainda sem quadro falham com `CaptureError::Cancelled`
+    pub cancel: CancellationToken,
+}
+
+impl Default for SnapshotOptions {
+    fn default() -> Self {
+        Self {
+            config: CaptureConfig::default(),
+            timeout: Duration::from_secs(1),
+            skip_failed: true,
+            cancel: CancellationToken::new(),
+        }
+    }
+}
+
+/// Captura uma vez cada monitor ligado à área de trabalho, com as opções padrão
+pub fn snapshot_all() -> CaptureResult<Vec<(OutputInfo, Frame)>> {
+    snapshot_all_with(&SnapshotOptions::default())
+}
+
+/// Captura uma vez cada monitor ligado à área de trabalho, na ordem de `enumerate_outputs`
+pub fn snapshot_all_with(options: &SnapshotOptions) -> CaptureResult<Vec<(OutputInfo, Frame)>> {
+    let outputs: Vec<OutputInfo> = enumerate_outputs()?
+        .into_iter()
+        .filter(|output| output.attached_to_desktop)
+        .collect();
+
+    let results: Vec<CaptureResult<Frame>> = thread::scope(|scope| {
+        let workers: Vec<_> = outputs
+            .iter()
+            .map(|output| scope.spawn(move || snapshot_output(output, options)))
+            .collect();
+        workers
+            .into_iter()
+            .map(|worker| worker.join().unwrap_or(Err(CaptureError::NotInitialized)))
+            .collect()
+    });
+
+    let mut snapshots = Vec::with_capacity(outputs.len());
+    for (output, result) in outputs.into_iter().zip(results) {
+        match result {
+            Ok(frame) => snapshots.push((output, frame)),
+            Err(e) if options.skip_failed => warn!("Monitor {} ignorado no snapshot: {}", output.device_name, e),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(snapshots)
+}
+
+/// Captura o monitor inteiro com uma duplicação criada só para isso
+pub fn snapshot_output(output: &OutputInfo, options: &SnapshotOptions) -> CaptureResult<Frame> {
+    let config = CaptureConfig {
+        adapter_index: output.adapter_index,
+        output_index: output.output_index,
+        output_name: None,
+        no_frame_policy: NoFramePolicy::NoChange,
+        ..options.config.clone()
+    };
+    let mut capture = DxgiCapture::with_cancellation(config, options.cancel.clone())?;
+    let deadline = Instant::now() + options.timeout;
+
+    loop {
+        match capture.capture_frame(capture.bounds()) {
+            Err(CaptureError::NoNewFrame) if Instant::now() < deadline => {
+                if options.cancel.wait_timeout(FIRST_FRAME_RETRY) {
+                    return Err(CaptureError::Cancelled);
+                }
+            }
+            result => return result,
+        }
+    }
+}
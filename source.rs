@@ -0,0 +1,66 @@
+//! Fonte de quadros independente da API gráfica que consome a duplicação
+//!
+//! A duplicação é sempre feita em Direct3D 11; o backend decide onde os quadros ficam
+//! disponíveis para o aplicativo (D3D11 ou, com a feature `d3d12`, um dispositivo D3D12).
+//! Com a feature `recording`, `replay::ReplaySource` implementa o mesmo trait reproduzindo uma
+//! gravação, para testar o processamento sem captura ao vivo.
+
+use windows::Win32::Graphics::Dxgi::Common::DXGI_FORMAT;
+
+use crate::error::CaptureResult;
+use crate::frame::Frame;
+use crate::geometry::{Rect, Size};
+use crate::DxgiCapture;
+
+/// API gráfica em que os quadros capturados são entregues
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Backend {
+    /// `DxgiCapture`, direto no dispositivo da duplicação
+    #[default]
+    D3D11,
+    /// `d3d12::D3D12Capture`, com a superfície compartilhada com um dispositivo D3D12 (experimental)
+    #[cfg(feature = "d3d12")]
+    D3D12,
+    /// `replay::ReplaySource`, quadros de uma sequência bruta gravada, sem monitor nem GPU
+    #[cfg(feature = "recording")]
+    Replay,
+}
+
+/// Captura de quadros de um monitor, qualquer que seja o backend
+pub trait ScreenSource {
+    fn backend(&self) -> Backend;
+
+    /// Dimensões do monitor capturado
+    fn output_size(&self) -> Size;
+
+    /// Formato de pixel dos quadros
+    fn format(&self) -> DXGI_FORMAT;
+
+    /// Captura a região (relativa ao monitor) com os metadados do quadro
+    fn capture_frame(&mut self, region: Rect) -> CaptureResult<Frame>;
+
+    /// Retângulo cobrindo o monitor inteiro
+    fn bounds(&self) -> Rect {
+        Rect::from_size(self.output_size())
+    }
+}
+
+impl ScreenSource for DxgiCapture {
+    fn backend(&self) -> Backend {
+        Backend::D3D11
+    }
+
+    fn output_size(&self) -> Size {
+        DxgiCapture::output_size(self)
+    }
+
+    fn format(&self) -> DXGI_FORMAT {
+        DxgiCapture::format(self)
+    }
+
+    fn capture_frame(&mut self, region: Rect) -> CaptureResult<Frame> {
+        DxgiCapture::capture_frame(self, region)
+    }
+}
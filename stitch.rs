@@ -0,0 +1,169 @@
+//! Composição das capturas de vários monitores em uma única imagem
+//!
+//! Cada quadro é posicionado pelas coordenadas do seu monitor no desktop virtual, então
+//! monitores de resoluções diferentes ou desalinhados ficam como o usuário os vê. As áreas
+//! do retângulo envolvente que nenhum monitor cobre recebem a cor de fundo.
+
+use windows::Win32::Graphics::Dxgi::Common::DXGI_FORMAT_B8G8R8A8_UNORM;
+
+use crate::annotate::{annotate, Annotation, Color};
+use crate::convert::rgba8_reader;
+use crate::frame::{Frame, FrameMetadata};
+use crate::geometry::{Point, Rect};
+use crate::outputs::{ColorSpace, OutputInfo};
+use crate::pixel_format::PixelFormat;
+use crate::Result;
+
+/// Distância entre a etiqueta de um monitor e o canto dele
+const LABEL_MARGIN: i32 = 12;
+
+/// Opções de `stitch`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct StitchOptions {
+    /// Cor das áreas não cobertas por nenhum monitor
+    pub background: Color,
+    /// Contorno de cada monitor, com a espessura em pixels
+    pub boundaries: Option<(Color, u32)>,
+    /// Etiqueta com o nome do dispositivo (ex.: `\\.\DISPLAY1`) no canto de cada monitor,
+    /// com o tamanho da fonte em pixels
+    pub labels: Option<u32>,
+}
+
+impl Default for StitchOptions {
+    fn default() -> Self {
+        Self {
+            background: Color::BLACK,
+            boundaries: None,
+            labels: None,
+        }
+    }
+}
+
+/// Onde um monitor ficou na imagem composta
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StitchedOutput {
+    pub output: OutputInfo,
+    /// Área do monitor inteiro na imagem
+    pub rect: Rect,
+}
+
+/// Imagem composta e o mapeamento de volta para o desktop virtual
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stitched {
+    /// Quadro B8G8R8A8 cobrindo todos os monitores
+    pub frame: Frame,
+    /// Posição no desktop virtual do pixel (0, 0) da imagem
+    pub origin: Point,
+    pub outputs: Vec<StitchedOutput>,
+}
+
+impl Stitched {
+    /// Converte um ponto do desktop virtual para coordenadas da imagem
+    pub fn to_image(&self, virtual_point: Point) -> Point {
+        Point::new(
+            virtual_point.x.saturating_sub(self.origin.x),
+            virtual_point.y.saturating_sub(self.origin.y),
+        )
+    }
+}
+
+/// Compõe os quadros (ex.: de `snapshot_all`) em uma imagem B8G8R8A8.
+///
+/// Aceita quadros em B8G8R8A8, R8G8B8A8 ou R16G16B16A16_FLOAT (convertido para sRGB 8-bit);
+/// a região de cada quadro é relativa ao seu monitor, como em `capture_frame`.
+pub fn stitch(snapshots: &[(OutputInfo, Frame)], options: &StitchOptions) -> Result<Stitched> {
+    if snapshots.is_empty() {
+        return Err("Nenhum quadro para compor".into());
+    }
+
+    // Retângulo envolvente dos monitores, no desktop virtual
+    let (mut left, mut top, mut right, mut bottom) = (i64::MAX, i64::MAX, i64::MIN, i64::MIN);
+    for (output, _) in snapshots {
+        left = left.min(output.left as i64);
+        top = top.min(output.top as i64);
+        right = right.max(output.left as i64 + output.width as i64);
+        bottom = bottom.max(output.top as i64 + output.height as i64);
+    }
+    let bounds = Rect::from_edges(left as i32, top as i32, right as i32, bottom as i32)?;
+    let origin = Point::new(bounds.left, bounds.top);
+
+    let background = [options.background.b, options.background.g, options.background.r, 255];
+    let row_bytes = PixelFormat::Bgra8.min_stride(bounds.width);
+    let mut data = background.repeat(bounds.width as usize * bounds.height as usize);
+
+    let mut outputs = Vec::with_capacity(snapshots.len());
+    for (output, frame) in snapshots {
+        let read = rgba8_reader(frame.format)
+            .ok_or_else(|| format!("Formato não suportado na composição: {:?}", frame.format))?;
+        let stride = frame.stride();
+        if frame.data.len() < stride * frame.height() as usize {
+            return Err(format!("Quadro de {} menor que a região informada", output.device_name).into());
+        }
+
+        // Deslocamento do quadro na imagem; a região pode ser só parte do monitor
+        let x0 = (output.left as i64 - left + frame.region.left as i64) as usize;
+        let y0 = (output.top as i64 - top + frame.region.top as i64) as usize;
+        let pixel_bytes = stride / frame.width().max(1) as usize;
+        let width = (frame.width() as usize).min((bounds.width as usize).saturating_sub(x0));
+        let height = (frame.height() as usize).min((bounds.height as usize).saturating_sub(y0));
+        let rows = if width == 0 { 0 } else { height };
+
+        for y in 0..rows {
+            let src = &frame.data[y * stride..(y + 1) * stride];
+            let dst = &mut data[(y0 + y) * row_bytes + x0 * 4..];
+            for (x, out) in dst.chunks_exact_mut(4).take(width).enumerate() {
+                let [r, g, b, _] = read(&src[x * pixel_bytes..(x + 1) * pixel_bytes]);
+                out.copy_from_slice(&[b, g, r, 255]);
+            }
+        }
+
+        outputs.push(StitchedOutput {
+            output: output.clone(),
+            rect: Rect::new(
+                (output.left as i64 - left) as i32,
+                (output.top as i64 - top) as i32,
+                output.width,
+                output.height,
+            ),
+        });
+    }
+
+    let mut frame = Frame {
+        data,
+        region: Rect::from_size(bounds.size()),
+        format: DXGI_FORMAT_B8G8R8A8_UNORM,
+        color_space: ColorSpace::Srgb,
+        present_time: snapshots.iter().map(|(_, frame)| frame.present_time).max().unwrap_or(0),
+        sequence: 0,
+        metadata: FrameMetadata::default(),
+    };
+
+    let mut annotations = Vec::new();
+    for placed in &outputs {
+        if let Some((color, thickness)) = options.boundaries {
+            annotations.push(Annotation::Rect {
+                rect: placed.rect,
+                color,
+                thickness,
+            });
+        }
+        if let Some(size) = options.labels {
+            annotations.push(Annotation::Text {
+                at: Point::new(placed.rect.left + LABEL_MARGIN, placed.rect.top + LABEL_MARGIN),
+                text: placed.output.device_name.clone(),
+                color: Color::WHITE,
+                size,
+                background: Some(Color::BLACK.with_alpha(180)),
+            });
+        }
+    }
+    if !annotations.is_empty() {
+        annotate(&mut frame, &annotations)?;
+    }
+
+    debug!("{} monitores compostos em {}x{}", outputs.len(), bounds.width, bounds.height);
+    Ok(Stitched { frame, origin, outputs })
+}
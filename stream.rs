@@ -0,0 +1,534 @@
+//! Captura contínua em uma thread dedicada, com entrega por fila
+//!
+//! A política da fila decide o que acontece quando o consumidor é mais lento que a captura:
+//! uma pré-visualização quer sempre o quadro mais recente, enquanto um encoder não pode
+//! perder nenhum e prefere segurar a captura. As estatísticas contam o que foi descartado.
+
+use std::collections::VecDeque;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::bandwidth::{BandwidthController, BandwidthOptions};
+use crate::cancel::CancellationToken;
+#[cfg(feature = "input")]
+use crate::click_highlight::{ClickHighlightOptions, ClickHighlighter};
+use crate::config::{CaptureConfig, NoFramePolicy};
+use crate::error::{CaptureError, CaptureResult};
+use crate::events::{CaptureEvent, EventBus};
+use crate::frame::Frame;
+use crate::geometry::RegionSpec;
+#[cfg(feature = "input")]
+use crate::input::{InputOptions, InputRecorder};
+use crate::overlay::{OverlayOptions, OverlayToggle, StatsOverlay};
+use crate::priority::{boost_current_thread, RealtimePriority};
+use crate::sink::{FanOut, FrameSink, SinkStats};
+use crate::DxgiCapture;
+
+/// O que fazer com um quadro novo quando a fila está cheia
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum QueuePolicy {
+    /// Só o quadro mais recente; um quadro não consumido é substituído pelo próximo
+    #[default]
+    LatestOnly,
+    /// Até `n` quadros; cheia, descarta o mais antigo
+    Bounded(usize),
+    /// Até `n` quadros; cheia, a captura espera o consumidor e nenhum quadro é perdido
+    Blocking(usize),
+}
+
+impl QueuePolicy {
+    fn capacity(&self) -> usize {
+        match *self {
+            QueuePolicy::LatestOnly => 1,
+            QueuePolicy::Bounded(n) | QueuePolicy::Blocking(n) => n.max(1),
+        }
+    }
+}
+
+/// Contadores de uma fila de quadros (de um `CaptureStream` ou de um `FrameSink`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StreamStats {
+    /// Quadros novos oferecidos à fila
+    pub captured: u64,
+    /// Quadros retirados da fila pelo consumidor
+    pub delivered: u64,
+    /// Quadros descartados por fila cheia
+    pub dropped: u64,
+    /// Presents que a duplicação juntou sem a captura vê-los (`AccumulatedFrames` > 1): perdas
+    /// da captura, ao contrário de `dropped`, que são do consumidor. Só contados no stream
+    pub missed: u64,
+    /// Falhas da captura (perda de acesso, timeout...) no stream, ou de `on_frame` em uma
+    /// saída; em ambos os casos o processamento continua
+    pub errors: u64,
+    /// Tempo total que a captura passou esperando o consumidor (`QueuePolicy::Blocking`)
+    pub blocked: Duration,
+}
+
+/// Parâmetros de `CaptureStream::start`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct StreamOptions {
+    /// Região relativa ao monitor, resolvida a cada quadro; `None` captura o monitor inteiro
+    pub region: Option<RegionSpec>,
+    /// Taxa máxima de captura; 0 usa a taxa de atualização do monitor (`DxgiCapture::default_fps`)
+    pub fps: u32,
+    pub queue: QueuePolicy,
+    /// Registra a thread de captura no MMCSS para reduzir o jitter sob carga; `None` mantém a
+    /// prioridade normal
+    pub realtime: Option<RealtimePriority>,
+    /// Overlay de depuração com FPS, latência e descartes desenhado nos quadros entregues;
+    /// ligado e desligado em execução por `CaptureStream::overlay`
+    pub overlay: OverlayOptions,
+    /// Desenha um círculo nos cliques do mouse antes da entrega, para gravações de tutoriais
+    #[cfg(feature = "input")]
+    pub click_highlight: Option<ClickHighlightOptions>,
+    /// Ajusta o FPS da captura à banda da rede (ver `bandwidth`); o controlador fica em
+    /// `CaptureStream::bandwidth` para as saídas de rede informarem envios e RTT
+    pub bandwidth: Option<BandwidthOptions>,
+}
+
+impl Default for StreamOptions {
+    fn default() -> Self {
+        Self {
+            region: None,
+            fps: 0,
+            queue: QueuePolicy::LatestOnly,
+            realtime: None,
+            overlay: OverlayOptions::default(),
+            #[cfg(feature = "input")]
+            click_highlight: None,
+            bandwidth: None,
+        }
+    }
+}
+
+struct QueueState {
+    frames: VecDeque<Arc<Frame>>,
+    stats: StreamStats,
+    // O consumidor pediu para parar
+    stopped: bool,
+    // O produtor terminou
+    finished: bool,
+}
+
+/// Fila de quadros entre um produtor e um consumidor, com a política de `QueuePolicy`.
+///
+/// Os quadros são compartilhados por `Arc`, então a mesma captura pode ir para várias filas
+/// (o stream e cada `FrameSink`) sem cópia dos pixels.
+pub(crate) struct FrameQueue {
+    policy: QueuePolicy,
+    state: Mutex<QueueState>,
+    // Sinalizado quando um quadro entra na fila ou o produtor termina
+    available: Condvar,
+    // Sinalizado quando um quadro sai da fila ou o consumidor para
+    space: Condvar,
+}
+
+impl FrameQueue {
+    pub(crate) fn new(policy: QueuePolicy) -> Self {
+        Self {
+            policy,
+            state: Mutex::new(QueueState {
+                frames: VecDeque::with_capacity(policy.capacity()),
+                stats: StreamStats::default(),
+                stopped: false,
+                finished: false,
+            }),
+            available: Condvar::new(),
+            space: Condvar::new(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, QueueState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Enfileira o quadro conforme a política; `false` se o consumidor já parou
+    pub(crate) fn push(&self, frame: Arc<Frame>) -> bool {
+        let capacity = self.policy.capacity();
+        let mut state = self.lock();
+        state.stats.captured += 1;
+
+        if let QueuePolicy::Blocking(_) = self.policy {
+            let waiting = Instant::now();
+            while state.frames.len() >= capacity && !state.stopped {
+                state = self.space.wait(state).unwrap_or_else(|e| e.into_inner());
+            }
+            state.stats.blocked += waiting.elapsed();
+        } else {
+            while state.frames.len() >= capacity {
+                state.frames.pop_front();
+                state.stats.dropped += 1;
+            }
+        }
+
+        if state.stopped {
+            return false;
+        }
+
+        state.frames.push_back(frame);
+        self.available.notify_one();
+        true
+    }
+
+    fn pop(&self, state: &mut QueueState) -> Option<Arc<Frame>> {
+        let frame = state.frames.pop_front()?;
+        state.stats.delivered += 1;
+        self.space.notify_one();
+        Some(frame)
+    }
+
+    /// Próximo quadro, aguardando; `None` quando o produtor terminou e a fila esvaziou
+    pub(crate) fn recv(&self) -> Option<Arc<Frame>> {
+        let mut state = self.lock();
+        while state.frames.is_empty() && !state.finished {
+            state = self.available.wait(state).unwrap_or_else(|e| e.into_inner());
+        }
+        self.pop(&mut state)
+    }
+
+    pub(crate) fn recv_timeout(&self, timeout: Duration) -> Option<Arc<Frame>> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.lock();
+        while state.frames.is_empty() && !state.finished {
+            let remaining = deadline.checked_duration_since(Instant::now())?;
+            state = self.available.wait_timeout(state, remaining).unwrap_or_else(|e| e.into_inner()).0;
+        }
+        self.pop(&mut state)
+    }
+
+    pub(crate) fn try_recv(&self) -> Option<Arc<Frame>> {
+        let mut state = self.lock();
+        self.pop(&mut state)
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.lock().frames.len()
+    }
+
+    pub(crate) fn stats(&self) -> StreamStats {
+        self.lock().stats
+    }
+
+    pub(crate) fn count_error(&self) {
+        self.lock().stats.errors += 1;
+    }
+
+    pub(crate) fn count_missed(&self, presents: u32) {
+        self.lock().stats.missed += u64::from(presents);
+    }
+
+    /// Lado consumidor: não aceitar mais quadros e liberar um produtor bloqueado
+    pub(crate) fn stop(&self) {
+        self.lock().stopped = true;
+        self.space.notify_all();
+    }
+
+    pub(crate) fn is_stopped(&self) -> bool {
+        self.lock().stopped
+    }
+
+    /// Lado produtor: nenhum quadro novo virá
+    pub(crate) fn finish(&self) {
+        self.lock().finished = true;
+        self.available.notify_all();
+    }
+}
+
+/// Estado compartilhado com a thread de captura
+struct Shared {
+    queue: FrameQueue,
+    sinks: Mutex<FanOut>,
+    overlay: OverlayToggle,
+    bandwidth: Option<BandwidthController>,
+    events: EventBus,
+}
+
+/// Captura contínua em segundo plano; os quadros são lidos com `recv` e/ou entregues aos
+/// `FrameSink`s registrados com `add_sink`.
+///
+/// A `DxgiCapture` é criada e usada apenas dentro da thread de captura. Ao sair de escopo
+/// a captura é encerrada e a thread aguardada.
+pub struct CaptureStream {
+    shared: Arc<Shared>,
+    worker: Option<JoinHandle<()>>,
+    cancel: CancellationToken,
+}
+
+impl CaptureStream {
+    /// Cria a captura em uma thread nova e começa a enfileirar quadros.
+    ///
+    /// Com `NoFramePolicy::ZeroFill` (o padrão) só quadros novos entram na fila, como em
+    /// `NoChange`; `LastFrame` repete o último quadro e mantém a taxa constante.
+    pub fn start(config: CaptureConfig, options: StreamOptions) -> CaptureResult<Self> {
+        Self::start_with_cancellation(config, options, CancellationToken::new())
+    }
+
+    /// Como `start`, encerrando a captura quando `cancel` for cancelado (de qualquer thread).
+    ///
+    /// Um token já cancelado faz `start` falhar com `CaptureError::Cancelled`.
+    pub fn start_with_cancellation(
+        mut config: CaptureConfig,
+        options: StreamOptions,
+        cancel: CancellationToken,
+    ) -> CaptureResult<Self> {
+        if config.no_frame_policy == NoFramePolicy::ZeroFill {
+            config.no_frame_policy = NoFramePolicy::NoChange;
+        }
+
+        let shared = Arc::new(Shared {
+            queue: FrameQueue::new(options.queue),
+            sinks: Mutex::new(FanOut::new()),
+            overlay: OverlayToggle::new(options.overlay.enabled),
+            bandwidth: options.bandwidth.clone().map(BandwidthController::new),
+            events: EventBus::default(),
+        });
+
+        // Acorda a thread mesmo se estiver bloqueada em uma fila cheia (`QueuePolicy::Blocking`)
+        let weak = Arc::downgrade(&shared);
+        cancel.on_cancel(move || {
+            if let Some(shared) = weak.upgrade() {
+                shared.queue.stop();
+            }
+        });
+
+        let (ready_sender, ready) = mpsc::channel();
+        let worker_shared = shared.clone();
+        let worker_cancel = cancel.clone();
+        let worker =
+            thread::spawn(move || capture_loop(config, options, &worker_shared, worker_cancel, ready_sender));
+
+        // Erros de criação (monitor inexistente, região inválida) chegam aqui
+        match ready.recv() {
+            Ok(Ok(())) => Ok(Self {
+                shared,
+                worker: Some(worker),
+                cancel,
+            }),
+            Ok(Err(e)) => {
+                let _ = worker.join();
+                Err(e)
+            }
+            Err(_) => {
+                let _ = worker.join();
+                Err(CaptureError::NotInitialized)
+            }
+        }
+    }
+
+    /// Próximo quadro, aguardando a captura; `None` depois que o stream terminou e a fila esvaziou
+    pub fn recv(&self) -> Option<Arc<Frame>> {
+        self.shared.queue.recv()
+    }
+
+    /// Como `recv`, desistindo após `timeout`
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<Arc<Frame>> {
+        self.shared.queue.recv_timeout(timeout)
+    }
+
+    /// Quadro já enfileirado, sem esperar
+    pub fn try_recv(&self) -> Option<Arc<Frame>> {
+        self.shared.queue.try_recv()
+    }
+
+    /// Quadros aguardando consumo
+    pub fn queued(&self) -> usize {
+        self.shared.queue.len()
+    }
+
+    /// Contadores da fila de `recv`
+    pub fn stats(&self) -> StreamStats {
+        self.shared.queue.stats()
+    }
+
+    /// Registra uma saída que recebe cada quadro capturado em sua própria thread e fila
+    pub fn add_sink<S: FrameSink + 'static>(&self, name: &str, sink: S, policy: QueuePolicy) {
+        self.shared.sinks.lock().unwrap_or_else(|e| e.into_inner()).add(name, sink, policy);
+    }
+
+    /// Contadores e último erro de cada saída registrada
+    pub fn sink_stats(&self) -> Vec<SinkStats> {
+        self.shared.sinks.lock().unwrap_or_else(|e| e.into_inner()).stats()
+    }
+
+    /// Liga e desliga o overlay de estatísticas (`StreamOptions::overlay`)
+    pub fn overlay(&self) -> OverlayToggle {
+        self.shared.overlay.clone()
+    }
+
+    /// Controlador de banda de `StreamOptions::bandwidth`, com os parâmetros atuais em
+    /// `settings()`/`stats()`; as saídas de rede informam nele os envios e o RTT
+    pub fn bandwidth(&self) -> Option<BandwidthController> {
+        self.shared.bandwidth.clone()
+    }
+
+    /// Canal com os eventos de saúde da captura da thread (`DxgiCapture::subscribe_events`)
+    pub fn subscribe_events(&self) -> Receiver<CaptureEvent> {
+        self.shared.events.subscribe()
+    }
+
+    /// Pede o encerramento da captura; os quadros já enfileirados continuam disponíveis
+    pub fn stop(&self) {
+        self.cancel.cancel();
+        self.shared.queue.stop();
+    }
+
+    /// Token que encerra a captura como `stop`, para ser cancelado de outra thread sem
+    /// compartilhar o `CaptureStream`
+    pub fn stop_handle(&self) -> CancellationToken {
+        self.cancel.clone()
+    }
+}
+
+impl Drop for CaptureStream {
+    fn drop(&mut self) {
+        self.stop();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn capture_loop(
+    config: CaptureConfig,
+    options: StreamOptions,
+    shared: &Shared,
+    cancel: CancellationToken,
+    ready: mpsc::Sender<CaptureResult<()>>,
+) {
+    let _boost = options.realtime.map(boost_current_thread);
+    let resolve = |capture: &DxgiCapture| match &options.region {
+        Some(spec) => capture.resolve_region(spec),
+        None => Ok(capture.bounds()),
+    };
+    let created = DxgiCapture::with_cancellation(config, cancel.clone()).and_then(|capture| {
+        let region = resolve(&capture)?;
+        Ok((capture, region))
+    });
+
+    let (mut capture, mut region) = match created {
+        Ok(mut created) => {
+            created.0.share_events(shared.events.clone());
+            let _ = ready.send(Ok(()));
+            created
+        }
+        Err(e) => {
+            let _ = ready.send(Err(e));
+            shared.queue.finish();
+            return;
+        }
+    };
+
+    let max_fps = if options.fps == 0 { capture.default_fps() } else { options.fps };
+    info!("Stream iniciado: {:?} a {} fps, fila {:?}", region, max_fps, options.queue);
+    let mut interval = Duration::from_secs(1) / max_fps.max(1);
+    let bandwidth_sink = options.bandwidth.as_ref().and_then(|bandwidth| bandwidth.sink.clone());
+    let mut overlay = StatsOverlay::with_toggle(options.overlay.clone(), shared.overlay.clone());
+    #[cfg(feature = "input")]
+    let mut clicks = options.click_highlight.clone().and_then(|highlight| {
+        let input = InputOptions {
+            keyboard: false,
+            ..Default::default()
+        };
+        match InputRecorder::start(input) {
+            Ok(recorder) => Some((recorder, ClickHighlighter::new(highlight))),
+            Err(e) => {
+                warn!("Destaque de cliques desativado: {}", e);
+                None
+            }
+        }
+    });
+
+    while !shared.queue.is_stopped() {
+        let tick = Instant::now();
+
+        // A resolução pode ter mudado na última captura
+        match resolve(&capture) {
+            Ok(resolved) if resolved != region => {
+                debug!("Região do stream mudou de {:?} para {:?}", region, resolved);
+                region = resolved;
+            }
+            Ok(_) => {}
+            Err(e) => debug!("Região do stream inválida na resolução atual: {}", e),
+        }
+
+        match capture.capture_frame(region) {
+            Ok(mut frame) => {
+                if frame.metadata.missed_presents() > 0 {
+                    shared.queue.count_missed(frame.metadata.missed_presents());
+                }
+                #[cfg(feature = "input")]
+                if let Some((recorder, highlighter)) = &mut clicks {
+                    highlighter.push_events(&recorder.take_for_frame(&frame));
+                    if let Err(e) = highlighter.draw(&mut frame, capture.output_origin()) {
+                        debug!("Falha ao desenhar os cliques: {}", e);
+                    }
+                }
+                let stats = if overlay.is_enabled() {
+                    overlay_stats(shared)
+                } else {
+                    StreamStats::default()
+                };
+                if let Err(e) = overlay.draw(&mut frame, &stats) {
+                    warn!("Overlay desligado: {}", e);
+                    shared.overlay.set_enabled(false);
+                }
+                let frame = Arc::new(frame);
+                shared.sinks.lock().unwrap_or_else(|e| e.into_inner()).dispatch(&frame);
+                if !shared.queue.push(frame) {
+                    break;
+                }
+            }
+            Err(CaptureError::NoNewFrame) => {}
+            Err(CaptureError::Cancelled) => break,
+            Err(e) => {
+                shared.queue.count_error();
+                debug!("Falha na captura do stream: {}", e);
+            }
+        }
+
+        if let Some(controller) = &shared.bandwidth {
+            if let Some(fps) = adapt_bandwidth(shared, controller, bandwidth_sink.as_deref()) {
+                interval = Duration::from_secs(1) / fps.min(max_fps).max(1);
+            }
+        }
+
+        if let Some(remaining) = interval.checked_sub(tick.elapsed()) {
+            if cancel.wait_timeout(remaining) {
+                break;
+            }
+        }
+    }
+
+    debug!("Stream encerrado: {:?}", shared.queue.stats());
+    shared.queue.finish();
+}
+
+/// Informa a fila da saída de rede ao controlador e retorna o FPS novo quando ele mudou
+fn adapt_bandwidth(shared: &Shared, controller: &BandwidthController, sink: Option<&str>) -> Option<u32> {
+    if let Some(name) = sink {
+        let sinks = shared.sinks.lock().unwrap_or_else(|e| e.into_inner()).stats();
+        if let Some(stats) = sinks.iter().find(|stats| stats.name == name) {
+            controller.report_queue_depth(stats.queued);
+        }
+    }
+    let settings = controller.update()?;
+    debug!("Banda: {} fps, escala {:.2}, {} bps", settings.fps, settings.scale, settings.bitrate);
+    Some(settings.fps)
+}
+
+/// Descartes e erros do stream somados aos de todas as saídas
+fn overlay_stats(shared: &Shared) -> StreamStats {
+    let mut stats = shared.queue.stats();
+    for sink in shared.sinks.lock().unwrap_or_else(|e| e.into_inner()).stats() {
+        stats.dropped += sink.queue.dropped;
+        stats.errors += sink.queue.errors;
+    }
+    stats
+}
@@ -0,0 +1,471 @@
+//! Busca de uma imagem de referência na tela por correlação cruzada normalizada
+//!
+//! O primitivo básico de bots de automação: localizar um botão ou ícone na captura sem
+//! exportar quadros para o OpenCV. A pontuação de cada posição é a correlação (de -1 a 1)
+//! entre a luminância da referência e a da janela da tela, então pequenas variações de
+//! brilho e contraste não atrapalham.
+//!
+//! Na CPU a busca é feita primeiro em resolução reduzida e refinada perto dos candidatos, com
+//! o produto interno escrito para ser vetorizado (SSE/AVX) e as linhas divididas entre threads
+//! com a feature `rayon`. Na GPU um compute shader calcula todas as posições em resolução cheia.
+
+use std::ffi::c_void;
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use windows::core::Error;
+use windows::Win32::Foundation::E_FAIL;
+use windows::Win32::Graphics::Direct3D11::*;
+
+use crate::convert::rgba8_reader;
+use crate::error::{CaptureError, CaptureResult};
+use crate::frame::Frame;
+use crate::geometry::{Point, Rect};
+use crate::gpu_stats::{compile_compute_shader, dispatch_over_texture, ReadbackBuffer};
+
+/// Máximo de resultados de uma busca
+const MAX_MATCHES: usize = 256;
+/// Redução máxima da busca grosseira
+const MAX_PYRAMID_SCALE: usize = 4;
+/// Lado mínimo da referência na resolução reduzida
+const MIN_COARSE_SIDE: usize = 8;
+/// Quanto abaixo do limite um candidato da busca grosseira ainda é refinado
+const COARSE_SLACK: f32 = 0.15;
+/// Variância abaixo da qual uma janela é considerada lisa (sem correlação definida)
+const FLAT_VARIANCE: f64 = 1e-6;
+/// Pixels máximos da referência no shader; acima disso cada thread demoraria o bastante para
+/// arriscar o reset do driver (TDR) e a busca usa a CPU
+pub(crate) const GPU_MAX_TEMPLATE_PIXELS: usize = 64 * 64;
+
+/// Cada thread calcula a correlação de uma posição; a referência vem centrada na média, com
+/// o cabeçalho [largura, altura, norma, pixels]
+const TEMPLATE_SHADER: &str = r#"
+Texture2D<float4> Input : register(t0);
+StructuredBuffer<float> Needle : register(t1);
+RWStructuredBuffer<uint> Scores : register(u0);
+
+[numthreads(16, 16, 1)]
+void main(uint3 id : SV_DispatchThreadID)
+{
+    uint width, height;
+    Input.GetDimensions(width, height);
+    uint needleWidth = (uint)Needle[0];
+    uint needleHeight = (uint)Needle[1];
+    if (id.x + needleWidth > width || id.y + needleHeight > height) {
+        return;
+    }
+
+    float sum = 0.0, sumSq = 0.0, product = 0.0;
+    for (uint y = 0; y < needleHeight; y++) {
+        for (uint x = 0; x < needleWidth; x++) {
+            float value = dot(saturate(Input[id.xy + uint2(x, y)].rgb), float3(0.2126, 0.7152, 0.0722));
+            sum += value;
+            sumSq += value * value;
+            product += value * Needle[4 + y * needleWidth + x];
+        }
+    }
+
+    float variance = sumSq - sum * sum / Needle[3];
+    float score = variance > 1e-6 ? product / (sqrt(variance) * Needle[2]) : 0.0;
+    Scores[id.y * (width - needleWidth + 1) + id.x] = (uint)(saturate(score) * 65535.0 + 0.5);
+}
+"#;
+
+/// Posição em que a referência foi encontrada
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ImageMatch {
+    /// Área coberta pela referência (relativa ao monitor em `DxgiCapture::find_image`, ao
+    /// quadro em `find_in_frame`)
+    pub rect: Rect,
+    /// Correlação entre -1 e 1; 1 é uma cópia exata, a menos de brilho e contraste
+    pub score: f32,
+}
+
+impl ImageMatch {
+    /// Centro da área encontrada, o ponto a clicar
+    pub fn center(&self) -> Point {
+        Point::new(
+            self.rect.left + (self.rect.width / 2) as i32,
+            self.rect.top + (self.rect.height / 2) as i32,
+        )
+    }
+}
+
+/// Procura `needle` em `haystack` na CPU; resultados com correlação `>= threshold`, do melhor
+/// para o pior, sem sobreposições de mais de metade da referência
+pub fn find_in_frame(haystack: &Frame, needle: &Frame, threshold: f32) -> CaptureResult<Vec<ImageMatch>> {
+    let needle = Needle::from_frame(needle)?;
+    let haystack = Gray::from_frame(haystack)?;
+    needle.check_fits(haystack.width, haystack.height)?;
+    Ok(search(&haystack, &needle, threshold))
+}
+
+/// Imagem em luminância (0 a 255)
+pub(crate) struct Gray {
+    data: Vec<f32>,
+    width: usize,
+    height: usize,
+}
+
+impl Gray {
+    pub(crate) fn from_frame(frame: &Frame) -> CaptureResult<Self> {
+        let read = rgba8_reader(frame.format)
+            .ok_or_else(|| CaptureError::InvalidTemplate(format!("unsupported pixel format: {:?}", frame.format)))?;
+        let (width, height) = (frame.width() as usize, frame.height() as usize);
+        let stride = frame.stride();
+        if frame.data.len() < stride * height {
+            return Err(CaptureError::InvalidTemplate("buffer smaller than the frame".to_owned()));
+        }
+
+        let pixel_bytes = stride / width.max(1);
+        let mut data = Vec::with_capacity(width * height);
+        for row in frame.data.chunks_exact(stride).take(height) {
+            for pixel in row.chunks_exact(pixel_bytes).take(width) {
+                let [r, g, b, _] = read(pixel);
+                data.push(0.2126 * r as f32 + 0.7152 * g as f32 + 0.0722 * b as f32);
+            }
+        }
+        Ok(Self { data, width, height })
+    }
+
+    /// Média de blocos `scale` x `scale`
+    fn downscale(&self, scale: usize) -> Self {
+        let (width, height) = (self.width / scale, self.height / scale);
+        let area = (scale * scale) as f32;
+        let mut data = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = 0.0;
+                for row in 0..scale {
+                    let start = (y * scale + row) * self.width + x * scale;
+                    sum += self.data[start..start + scale].iter().sum::<f32>();
+                }
+                data.push(sum / area);
+            }
+        }
+        Self { data, width, height }
+    }
+}
+
+/// Referência em luminância centrada na média, com a norma pré-calculada
+pub(crate) struct Needle {
+    values: Vec<f32>,
+    width: usize,
+    height: usize,
+    norm: f64,
+}
+
+impl Needle {
+    pub(crate) fn from_frame(frame: &Frame) -> CaptureResult<Self> {
+        Self::from_gray(Gray::from_frame(frame)?)
+    }
+
+    fn from_gray(gray: Gray) -> CaptureResult<Self> {
+        if gray.data.is_empty() {
+            return Err(CaptureError::InvalidTemplate("empty image".to_owned()));
+        }
+        let mean = gray.data.iter().map(|&v| v as f64).sum::<f64>() / gray.data.len() as f64;
+        let values: Vec<f32> = gray.data.iter().map(|&v| (v as f64 - mean) as f32).collect();
+        let norm = values.iter().map(|&v| v as f64 * v as f64).sum::<f64>().sqrt();
+        if norm * norm / values.len() as f64 <= FLAT_VARIANCE {
+            return Err(CaptureError::InvalidTemplate(
+                "single-color image with no variation to correlate".to_owned(),
+            ));
+        }
+        Ok(Self {
+            values,
+            width: gray.width,
+            height: gray.height,
+            norm,
+        })
+    }
+
+    pub(crate) fn pixels(&self) -> usize {
+        self.values.len()
+    }
+
+    pub(crate) fn check_fits(&self, width: usize, height: usize) -> CaptureResult<()> {
+        if self.width > width || self.height > height {
+            return Err(CaptureError::InvalidTemplate(format!(
+                "template {}x{} larger than the region {}x{}",
+                self.width, self.height, width, height
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Somas acumuladas de valores e quadrados, para a média e a variância de qualquer janela
+struct Integral {
+    sum: Vec<f64>,
+    sum_sq: Vec<f64>,
+    stride: usize,
+}
+
+impl Integral {
+    fn new(gray: &Gray) -> Self {
+        let stride = gray.width + 1;
+        let mut sum = vec![0.0; stride * (gray.height + 1)];
+        let mut sum_sq = vec![0.0; stride * (gray.height + 1)];
+        for y in 0..gray.height {
+            let (mut row, mut row_sq) = (0.0, 0.0);
+            for x in 0..gray.width {
+                let value = gray.data[y * gray.width + x] as f64;
+                row += value;
+                row_sq += value * value;
+                sum[(y + 1) * stride + x + 1] = sum[y * stride + x + 1] + row;
+                sum_sq[(y + 1) * stride + x + 1] = sum_sq[y * stride + x + 1] + row_sq;
+            }
+        }
+        Self { sum, sum_sq, stride }
+    }
+
+    fn window(&self, table: &[f64], x: usize, y: usize, width: usize, height: usize) -> f64 {
+        let (top, bottom) = (y * self.stride, (y + height) * self.stride);
+        table[bottom + x + width] - table[top + x + width] - table[bottom + x] + table[top + x]
+    }
+
+    /// Soma dos quadrados dos desvios da janela
+    fn variance(&self, x: usize, y: usize, width: usize, height: usize) -> f64 {
+        let sum = self.window(&self.sum, x, y, width, height);
+        let sum_sq = self.window(&self.sum_sq, x, y, width, height);
+        sum_sq - sum * sum / (width * height) as f64
+    }
+}
+
+/// Produto interno com oito acumuladores independentes, o que permite ao compilador usar
+/// instruções SIMD no laço
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    let mut lanes = [0.0f32; 8];
+    let (chunks_a, chunks_b) = (a.chunks_exact(8), b.chunks_exact(8));
+    let tail: f32 = chunks_a
+        .remainder()
+        .iter()
+        .zip(chunks_b.remainder())
+        .map(|(x, y)| x * y)
+        .sum();
+    for (x, y) in chunks_a.zip(chunks_b) {
+        for lane in 0..8 {
+            lanes[lane] += x[lane] * y[lane];
+        }
+    }
+    lanes.iter().sum::<f32>() + tail
+}
+
+fn score_at(haystack: &Gray, integral: &Integral, needle: &Needle, x: usize, y: usize) -> f32 {
+    let variance = integral.variance(x, y, needle.width, needle.height);
+    if variance <= FLAT_VARIANCE {
+        return 0.0;
+    }
+    let mut product = 0.0;
+    for row in 0..needle.height {
+        let start = (y + row) * haystack.width + x;
+        product += dot(
+            &haystack.data[start..start + needle.width],
+            &needle.values[row * needle.width..(row + 1) * needle.width],
+        ) as f64;
+    }
+    (product / (variance.sqrt() * needle.norm)) as f32
+}
+
+/// Correlação de todas as posições, linha a linha (em paralelo com a feature `rayon`)
+fn score_map(haystack: &Gray, needle: &Needle) -> (Vec<f32>, usize) {
+    let integral = Integral::new(haystack);
+    let columns = haystack.width - needle.width + 1;
+    let rows = haystack.height - needle.height + 1;
+    let mut scores = vec![0.0f32; columns * rows];
+    let fill_row = |(y, row): (usize, &mut [f32])| {
+        for (x, score) in row.iter_mut().enumerate() {
+            *score = score_at(haystack, &integral, needle, x, y);
+        }
+    };
+
+    #[cfg(feature = "rayon")]
+    scores.par_chunks_mut(columns).enumerate().for_each(fill_row);
+    #[cfg(not(feature = "rayon"))]
+    scores.chunks_mut(columns).enumerate().for_each(fill_row);
+
+    (scores, columns)
+}
+
+/// Melhores posições do mapa acima de `threshold`
+fn pick_matches(scores: &[f32], columns: usize, threshold: f32, width: usize, height: usize) -> Vec<(usize, usize, f32)> {
+    let candidates = scores
+        .iter()
+        .enumerate()
+        .filter(|(_, &score)| score >= threshold)
+        .map(|(index, &score)| (index % columns, index / columns, score))
+        .collect();
+    suppress(candidates, width, height)
+}
+
+/// Ordena do melhor para o pior e descarta as posições que se sobrepõem a uma melhor em mais
+/// de metade da referência
+fn suppress(mut candidates: Vec<(usize, usize, f32)>, width: usize, height: usize) -> Vec<(usize, usize, f32)> {
+    candidates.sort_by(|a, b| b.2.total_cmp(&a.2));
+
+    let mut picked: Vec<(usize, usize, f32)> = Vec::new();
+    for candidate in candidates {
+        let overlaps = picked
+            .iter()
+            .any(|p| p.0.abs_diff(candidate.0) < width.div_ceil(2) && p.1.abs_diff(candidate.1) < height.div_ceil(2));
+        if !overlaps {
+            picked.push(candidate);
+            if picked.len() == MAX_MATCHES {
+                break;
+            }
+        }
+    }
+    picked
+}
+
+fn to_matches(picked: Vec<(usize, usize, f32)>, needle: &Needle) -> Vec<ImageMatch> {
+    picked
+        .into_iter()
+        .map(|(x, y, score)| ImageMatch {
+            rect: Rect::new(x as i32, y as i32, needle.width as u32, needle.height as u32),
+            score,
+        })
+        .collect()
+}
+
+/// Busca na CPU: grosseira em resolução reduzida quando a referência é grande o bastante,
+/// refinada em resolução cheia perto de cada candidato
+pub(crate) fn search(haystack: &Gray, needle: &Needle, threshold: f32) -> Vec<ImageMatch> {
+    let mut scale = 1;
+    while scale < MAX_PYRAMID_SCALE && needle.width.min(needle.height) / (scale * 2) >= MIN_COARSE_SIDE {
+        scale *= 2;
+    }
+
+    let reduced = Gray {
+        data: needle.values.clone(),
+        width: needle.width,
+        height: needle.height,
+    };
+    // A redução pode apagar os detalhes de uma referência fina; nesse caso, resolução cheia
+    let coarse_needle = match scale {
+        1 => None,
+        _ => Needle::from_gray(reduced.downscale(scale)).ok(),
+    };
+    let Some(coarse_needle) = coarse_needle else {
+        let (scores, columns) = score_map(haystack, needle);
+        return to_matches(pick_matches(&scores, columns, threshold, needle.width, needle.height), needle);
+    };
+
+    let (coarse_scores, coarse_columns) = score_map(&haystack.downscale(scale), &coarse_needle);
+    let coarse = pick_matches(
+        &coarse_scores,
+        coarse_columns,
+        threshold - COARSE_SLACK,
+        coarse_needle.width,
+        coarse_needle.height,
+    );
+
+    let integral = Integral::new(haystack);
+    let (max_x, max_y) = (haystack.width - needle.width, haystack.height - needle.height);
+    let mut refined = Vec::with_capacity(coarse.len());
+    for (cx, cy, _) in coarse {
+        let mut best: Option<(usize, usize, f32)> = None;
+        for y in (cy * scale).saturating_sub(scale)..=(cy * scale + scale).min(max_y) {
+            for x in (cx * scale).saturating_sub(scale)..=(cx * scale + scale).min(max_x) {
+                let score = score_at(haystack, &integral, needle, x, y);
+                if best.map_or(true, |b| score > b.2) {
+                    best = Some((x, y, score));
+                }
+            }
+        }
+        refined.extend(best.filter(|b| b.2 >= threshold));
+    }
+
+    // Candidatos vizinhos podem ter convergido para a mesma posição
+    to_matches(suppress(refined, needle.width, needle.height), needle)
+}
+
+/// Busca na GPU em resolução cheia, com o shader e o buffer de pontuações reutilizados
+pub(crate) struct TemplatePass {
+    shader: ID3D11ComputeShader,
+    // Pontuações de uma busca anterior, reaproveitadas enquanto o número de posições for o mesmo
+    scores: Option<(usize, ReadbackBuffer)>,
+}
+
+impl TemplatePass {
+    pub(crate) fn new(device: &ID3D11Device) -> CaptureResult<Self> {
+        Ok(Self {
+            shader: compile_compute_shader(device, TEMPLATE_SHADER)?,
+            scores: None,
+        })
+    }
+
+    /// Procura a referência em `input` (B8G8R8A8 ou R8G8B8A8, do tamanho `width` x `height`)
+    pub(crate) fn run(
+        &mut self,
+        device: &ID3D11Device,
+        context: &ID3D11DeviceContext,
+        input: &ID3D11Texture2D,
+        (width, height): (usize, usize),
+        needle: &Needle,
+        threshold: f32,
+    ) -> CaptureResult<Vec<ImageMatch>> {
+        needle.check_fits(width, height)?;
+        let columns = width - needle.width + 1;
+        let positions = columns * (height - needle.height + 1);
+        if self.scores.as_ref().map_or(true, |(len, _)| *len != positions) {
+            self.scores = Some((positions, ReadbackBuffer::new(device, positions)?));
+        }
+        let (_, scores) = self.scores.as_ref().ok_or(CaptureError::NotInitialized)?;
+
+        // O shader lê a textura normalizada (0 a 1); a correlação não muda com a escala
+        let mut values = vec![
+            needle.width as f32,
+            needle.height as f32,
+            (needle.norm / 255.0) as f32,
+            needle.pixels() as f32,
+        ];
+        values.extend(needle.values.iter().map(|v| v / 255.0));
+        let view = needle_view(device, &values)?;
+
+        scores.fill(context, 0);
+        unsafe {
+            context.CSSetShaderResources(1, Some(&[Some(view)]));
+        }
+        let dispatched = dispatch_over_texture(device, context, &self.shader, input, scores);
+        unsafe {
+            context.CSSetShaderResources(1, Some(&[None]));
+        }
+        dispatched?;
+
+        let mut raw = vec![0u32; positions];
+        scores.read(context, &mut raw)?;
+        let map: Vec<f32> = raw.into_iter().map(|score| score as f32 / 65535.0).collect();
+        Ok(to_matches(
+            pick_matches(&map, columns, threshold, needle.width, needle.height),
+            needle,
+        ))
+    }
+}
+
+/// Buffer estruturado imutável com a referência, visível ao shader no slot t1
+fn needle_view(device: &ID3D11Device, values: &[f32]) -> CaptureResult<ID3D11ShaderResourceView> {
+    let desc = D3D11_BUFFER_DESC {
+        ByteWidth: (values.len() * 4) as u32,
+        Usage: D3D11_USAGE_IMMUTABLE,
+        BindFlags: D3D11_BIND_SHADER_RESOURCE.0 as u32,
+        CPUAccessFlags: 0,
+        MiscFlags: D3D11_RESOURCE_MISC_BUFFER_STRUCTURED.0 as u32,
+        StructureByteStride: 4,
+    };
+    let initial = D3D11_SUBRESOURCE_DATA {
+        pSysMem: values.as_ptr() as *const c_void,
+        ..Default::default()
+    };
+
+    let mut buffer: Option<ID3D11Buffer> = None;
+    let mut view: Option<ID3D11ShaderResourceView> = None;
+    unsafe {
+        device.CreateBuffer(&desc, Some(&initial), Some(&mut buffer))?;
+        let buffer = buffer.ok_or(CaptureError::Windows(Error::from(E_FAIL)))?;
+        device.CreateShaderResourceView(&buffer, None, Some(&mut view))?;
+    }
+    view.ok_or(CaptureError::Windows(Error::from(E_FAIL)))
+}
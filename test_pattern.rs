@@ -0,0 +1,344 @@
+//! Padrão de teste em um monitor para validação de ponta a ponta
+//!
+//! `PatternWindow` mostra um padrão conhecido em uma janela sem bordas cobrindo o monitor e
+//! `verify_pattern` confere pixel a pixel um quadro capturado dele. Cada pixel codifica as
+//! próprias coordenadas, então deslocamentos, escalas, cortes errados e canais trocados
+//! aparecem como divergências. A verificação assume o monitor em SDR e sem gerenciamento
+//! automático de cor, que alterariam as cores apresentadas.
+
+use std::cell::RefCell;
+use std::mem;
+use std::sync::mpsc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use windows::core::w;
+use windows::Win32::Foundation::{HINSTANCE, HWND, LPARAM, LRESULT, WPARAM};
+use windows::Win32::Graphics::Dxgi::Common::{DXGI_FORMAT, DXGI_FORMAT_R16G16B16A16_FLOAT};
+use windows::Win32::Graphics::Gdi::*;
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::UI::HiDpi::{SetThreadDpiAwarenessContext, DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2};
+use windows::Win32::UI::WindowsAndMessaging::*;
+
+use crate::convert::rgba8_reader;
+use crate::error::CaptureError;
+use crate::frame::Frame;
+use crate::geometry::{Point, Size};
+use crate::outputs::{enumerate_outputs, ColorSpace, OutputInfo};
+use crate::pixel_format::PixelFormat;
+use crate::{DxgiCapture, Result};
+
+/// Intervalo entre capturas de `self_test` enquanto o padrão não aparece
+const SELF_TEST_RETRY: Duration = Duration::from_millis(16);
+
+thread_local! {
+    // Pixels BGRA do padrão da janela desta thread
+    static PATTERN: RefCell<Option<(Size, Vec<u8>)>> = RefCell::new(None);
+}
+
+/// Cor RGB do padrão na posição (`x`, `y`) do monitor.
+///
+/// Vermelho e verde variam a cada pixel em x e y (passos de 13 e 29 níveis, maiores que
+/// qualquer tolerância), e o azul distingue os blocos de 256 pixels.
+pub fn pattern_pixel(x: u32, y: u32) -> [u8; 3] {
+    let r = x.wrapping_mul(13) as u8;
+    let g = y.wrapping_mul(29) as u8;
+    let b = ((x >> 8) as u8).wrapping_mul(47) ^ ((y >> 8) as u8).wrapping_mul(101);
+    [r, g, b]
+}
+
+/// Padrão de um monitor de tamanho `size` em BGRA 8-bit, sem padding entre linhas
+pub fn render_pattern(size: Size) -> Vec<u8> {
+    let mut data = Vec::with_capacity(PixelFormat::Bgra8.buffer_size(size.width, size.height));
+    for y in 0..size.height {
+        for x in 0..size.width {
+            let [r, g, b] = pattern_pixel(x, y);
+            data.extend_from_slice(&[b, g, r, 255]);
+        }
+    }
+    data
+}
+
+/// Tolerância por canal (em níveis de 8 bits) das conversões de um formato de captura
+pub fn default_tolerance(format: DXGI_FORMAT) -> u8 {
+    match format {
+        // Linear em meia precisão: arredondamento na volta para sRGB
+        DXGI_FORMAT_R16G16B16A16_FLOAT => 2,
+        _ => 0,
+    }
+}
+
+/// Resultado de `verify_pattern`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PatternReport {
+    /// Pixels comparados
+    pub checked: u64,
+    /// Pixels com algum canal fora da tolerância
+    pub mismatched: u64,
+    /// Maior diferença encontrada em um canal
+    pub max_diff: u8,
+    /// Primeiro pixel divergente, em coordenadas do monitor
+    pub first_mismatch: Option<Point>,
+    pub tolerance: u8,
+}
+
+impl PatternReport {
+    pub fn passed(&self) -> bool {
+        self.checked > 0 && self.mismatched == 0
+    }
+}
+
+/// Compara um quadro capturado do monitor com o padrão, canal a canal e ignorando o alfa.
+///
+/// A região do quadro pode ser qualquer parte do monitor; aceita B8G8R8A8, R8G8B8A8 e
+/// R16G16B16A16_FLOAT (linear, convertido para sRGB antes da comparação; só confiável com o
+/// monitor em SDR).
+pub fn verify_pattern(frame: &Frame, tolerance: u8) -> Result<PatternReport> {
+    if frame.color_space == ColorSpace::Hdr10 {
+        return Err("O padrão de teste só pode ser verificado com o monitor em SDR".into());
+    }
+
+    let decode = rgba8_reader(frame.format)
+        .ok_or_else(|| format!("Formato não suportado na verificação do padrão: {:?}", frame.format))?;
+
+    let stride = frame.stride();
+    let pixel_bytes = stride / frame.width().max(1) as usize;
+    if frame.data.len() < stride * frame.height() as usize {
+        return Err("Quadro menor que a região informada".into());
+    }
+
+    let mut report = PatternReport {
+        tolerance,
+        ..Default::default()
+    };
+    for (row, line) in frame.data.chunks_exact(stride).take(frame.height() as usize).enumerate() {
+        let y = frame.region.top as u32 + row as u32;
+        for (column, pixel) in line.chunks_exact(pixel_bytes).enumerate() {
+            let x = frame.region.left as u32 + column as u32;
+            let expected = pattern_pixel(x, y);
+            let actual = decode(pixel);
+
+            let diff = (0..3).map(|i| expected[i].abs_diff(actual[i])).max().unwrap_or(0);
+            report.checked += 1;
+            report.max_diff = report.max_diff.max(diff);
+            if diff > tolerance {
+                report.mismatched += 1;
+                report.first_mismatch.get_or_insert(Point::new(x as i32, y as i32));
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+/// Mostra o padrão no monitor do capturador e verifica capturas dele até passar ou até
+/// `timeout`; retorna o relatório da última captura
+pub fn self_test(capture: &mut DxgiCapture, timeout: Duration) -> Result<PatternReport> {
+    let config = capture.config();
+    let output = enumerate_outputs()?
+        .into_iter()
+        .find(|output| output.adapter_index == config.adapter_index && output.output_index == config.output_index)
+        .ok_or(CaptureError::OutputNotFound {
+            adapter: config.adapter_index,
+            output: config.output_index,
+        })?;
+
+    let _window = PatternWindow::show(&output)?;
+    let tolerance = default_tolerance(capture.format());
+    let deadline = Instant::now() + timeout;
+    let mut report = PatternReport::default();
+
+    // Os primeiros quadros ainda podem ser anteriores à composição da janela
+    loop {
+        match capture.capture_frame(capture.bounds()) {
+            Ok(frame) => {
+                report = verify_pattern(&frame, tolerance)?;
+                if report.passed() {
+                    info!("Padrão de teste verificado em {} pixels", report.checked);
+                    return Ok(report);
+                }
+            }
+            Err(CaptureError::NoNewFrame) => {}
+            Err(e) if e.is_temporarily_unavailable() => {}
+            Err(e) => return Err(e.into()),
+        }
+
+        if Instant::now() >= deadline {
+            warn!(
+                "Padrão de teste divergente: {} de {} pixels (diferença máxima {}, primeiro em {:?})",
+                report.mismatched, report.checked, report.max_diff, report.first_mismatch
+            );
+            return Ok(report);
+        }
+        if capture.cancellation().wait_timeout(SELF_TEST_RETRY) {
+            return Err(CaptureError::Cancelled.into());
+        }
+    }
+}
+
+/// Janela sem bordas, sempre no topo, cobrindo um monitor com o padrão de teste.
+///
+/// A janela tem uma thread própria com o loop de mensagens e é fechada no `Drop`.
+pub struct PatternWindow {
+    hwnd: HWND,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl PatternWindow {
+    /// Cria a janela sobre o monitor e retorna depois que o padrão foi pintado
+    pub fn show(output: &OutputInfo) -> Result<Self> {
+        let (origin, size) = (output.origin(), output.size());
+        let (sender, receiver) = mpsc::channel();
+
+        let thread = thread::Builder::new()
+            .name("test-pattern".into())
+            .spawn(move || {
+                let hwnd = match unsafe { create_window(origin, size) } {
+                    Ok(hwnd) => hwnd,
+                    Err(e) => {
+                        let _ = sender.send(Err(e.to_string()));
+                        return;
+                    }
+                };
+                let _ = sender.send(Ok(hwnd));
+                unsafe { run_message_loop() };
+            })?;
+
+        match receiver.recv() {
+            Ok(Ok(hwnd)) => {
+                debug!("Padrão de teste {}x{} em {:?}", size.width, size.height, origin);
+                Ok(Self {
+                    hwnd,
+                    thread: Some(thread),
+                })
+            }
+            Ok(Err(e)) => {
+                let _ = thread.join();
+                Err(format!("Falha ao criar a janela do padrão de teste: {}", e).into())
+            }
+            Err(_) => {
+                let _ = thread.join();
+                Err("A thread do padrão de teste terminou antes de criar a janela".into())
+            }
+        }
+    }
+
+    pub fn hwnd(&self) -> HWND {
+        self.hwnd
+    }
+}
+
+impl Drop for PatternWindow {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = PostMessageW(self.hwnd, WM_CLOSE, WPARAM(0), LPARAM(0));
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Cria a janela do padrão na thread atual; as coordenadas são pixels físicos
+unsafe fn create_window(origin: Point, size: Size) -> windows::core::Result<HWND> {
+    SetThreadDpiAwarenessContext(DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2);
+    PATTERN.with(|pattern| *pattern.borrow_mut() = Some((size, render_pattern(size))));
+
+    let instance: HINSTANCE = GetModuleHandleW(None)?.into();
+    let class_name = w!("DxgiCaptureTestPattern");
+    let class = WNDCLASSW {
+        lpfnWndProc: Some(window_proc),
+        hInstance: instance,
+        hCursor: LoadCursorW(None, IDC_ARROW)?,
+        lpszClassName: class_name,
+        ..Default::default()
+    };
+    // Falha se a classe já foi registrada por outra janela do processo, o que não é um problema
+    RegisterClassW(&class);
+
+    let hwnd = CreateWindowExW(
+        WS_EX_TOPMOST | WS_EX_TOOLWINDOW | WS_EX_NOACTIVATE,
+        class_name,
+        w!("Test pattern"),
+        WS_POPUP,
+        origin.x,
+        origin.y,
+        size.width as i32,
+        size.height as i32,
+        HWND::default(),
+        HMENU::default(),
+        instance,
+        None,
+    );
+    if hwnd.0 == 0 {
+        return Err(windows::core::Error::from_win32());
+    }
+
+    ShowWindow(hwnd, SW_SHOWNOACTIVATE);
+    // Pinta de forma síncrona antes de avisar quem criou a janela
+    UpdateWindow(hwnd);
+    Ok(hwnd)
+}
+
+unsafe fn run_message_loop() {
+    let mut message = MSG::default();
+    while GetMessageW(&mut message, HWND::default(), 0, 0).as_bool() {
+        TranslateMessage(&message);
+        DispatchMessageW(&message);
+    }
+}
+
+unsafe extern "system" fn window_proc(hwnd: HWND, message: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    match message {
+        WM_PAINT => {
+            let mut paint = PAINTSTRUCT::default();
+            let dc = BeginPaint(hwnd, &mut paint);
+            PATTERN.with(|pattern| {
+                if let Some((size, data)) = pattern.borrow().as_ref() {
+                    paint_pattern(dc, *size, data);
+                }
+            });
+            EndPaint(hwnd, &paint);
+            LRESULT(0)
+        }
+        // O padrão cobre a janela inteira; apagar o fundo só causaria cintilação
+        WM_ERASEBKGND => LRESULT(1),
+        WM_DESTROY => {
+            PostQuitMessage(0);
+            LRESULT(0)
+        }
+        _ => DefWindowProcW(hwnd, message, wparam, lparam),
+    }
+}
+
+unsafe fn paint_pattern(dc: HDC, size: Size, data: &[u8]) {
+    let info = BITMAPINFO {
+        bmiHeader: BITMAPINFOHEADER {
+            biSize: mem::size_of::<BITMAPINFOHEADER>() as u32,
+            biWidth: size.width as i32,
+            // Altura negativa: linhas de cima para baixo
+            biHeight: -(size.height as i32),
+            biPlanes: 1,
+            biBitCount: 32,
+            biCompression: BI_RGB.0,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    SetDIBitsToDevice(
+        dc,
+        0,
+        0,
+        size.width,
+        size.height,
+        0,
+        0,
+        0,
+        size.height,
+        data.as_ptr().cast(),
+        &info,
+        DIB_RGB_COLORS,
+    );
+}
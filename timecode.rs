@@ -0,0 +1,121 @@
+//! Linha do tempo de mídia a partir dos present times (QPC) da duplicação
+//!
+//! O `LastPresentTime` indica quando o DWM apresentou cada quadro, então os intervalos entre
+//! quadros refletem a taxa real (variável) da tela, ao contrário do relógio de parede no
+//! momento da leitura.
+
+use std::time::Duration;
+
+use windows::Win32::System::Performance::{QueryPerformanceCounter, QueryPerformanceFrequency};
+
+use crate::Result;
+
+/// Unidades de 100 ns por segundo
+pub const TICKS_PER_SECOND: i64 = 10_000_000;
+
+/// Converte present times (QPC) em timestamps de 100 ns relativos ao primeiro quadro.
+///
+/// Os intervalos são acumulados com subtração circular, então um contador que dá a volta
+/// continua avançando; o resultado é sempre estritamente crescente, mesmo para present times
+/// repetidos, fora de ordem ou 0 (quadro sem present novo).
+#[derive(Debug, Clone)]
+pub struct Timecode {
+    frequency: i64,
+    // Último present time válido e os ticks de QPC acumulados até ele
+    last_qpc: Option<i64>,
+    elapsed_qpc: i128,
+    last: Option<i64>,
+}
+
+impl Timecode {
+    /// Usa a frequência do QPC do sistema
+    pub fn new() -> Result<Self> {
+        let mut frequency = 0i64;
+        unsafe {
+            QueryPerformanceFrequency(&mut frequency)?;
+        }
+        Ok(Self::with_frequency(frequency))
+    }
+
+    /// Usa uma frequência conhecida (ex.: gravada junto com present times salvos)
+    pub fn with_frequency(frequency: i64) -> Self {
+        Self {
+            frequency: frequency.max(1),
+            last_qpc: None,
+            elapsed_qpc: 0,
+            last: None,
+        }
+    }
+
+    /// Frequência do QPC em ticks por segundo
+    pub fn frequency(&self) -> i64 {
+        self.frequency
+    }
+
+    /// Timestamp em unidades de 100 ns do quadro apresentado em `present_time`; o primeiro é 0
+    pub fn timestamp(&mut self, present_time: i64) -> i64 {
+        if present_time != 0 {
+            if let Some(last_qpc) = self.last_qpc {
+                // Present times anteriores ao último não fazem a linha do tempo voltar
+                let delta = present_time.wrapping_sub(last_qpc);
+                if delta > 0 {
+                    self.elapsed_qpc += delta as i128;
+                    self.last_qpc = Some(present_time);
+                }
+            } else {
+                self.last_qpc = Some(present_time);
+            }
+        }
+
+        let mut timestamp = (self.elapsed_qpc * TICKS_PER_SECOND as i128 / self.frequency as i128) as i64;
+        if let Some(last) = self.last {
+            if timestamp <= last {
+                timestamp = last + 1;
+            }
+        }
+
+        self.last = Some(timestamp);
+        timestamp
+    }
+
+    /// Timestamp que `timestamp(present_time)` retornaria, sem avançar a linha do tempo.
+    ///
+    /// Antes do primeiro quadro retorna 0.
+    pub fn peek(&self, present_time: i64) -> i64 {
+        let delta = match self.last_qpc {
+            Some(last_qpc) if present_time != 0 => present_time.wrapping_sub(last_qpc).max(0),
+            _ => 0,
+        };
+        let timestamp = ((self.elapsed_qpc + delta as i128) * TICKS_PER_SECOND as i128 / self.frequency as i128) as i64;
+        self.last.map_or(timestamp, |last| timestamp.max(last))
+    }
+
+    /// Último timestamp retornado
+    pub fn last(&self) -> Option<i64> {
+        self.last
+    }
+
+    /// Converte uma duração em QPC para unidades de 100 ns com a frequência desta linha do tempo
+    pub fn qpc_to_ticks(&self, qpc: i64) -> i64 {
+        (qpc as i128 * TICKS_PER_SECOND as i128 / self.frequency as i128) as i64
+    }
+
+    /// Recomeça a linha do tempo: o próximo quadro volta a ser 0
+    pub fn reset(&mut self) {
+        *self = Self::with_frequency(self.frequency);
+    }
+}
+
+/// Tempo desde o present (QPC) `present_time` até agora; `None` se o present é desconhecido (0)
+pub fn present_age(present_time: i64) -> Option<Duration> {
+    if present_time == 0 {
+        return None;
+    }
+    let (mut now, mut frequency) = (0i64, 0i64);
+    unsafe {
+        QueryPerformanceCounter(&mut now).ok()?;
+        QueryPerformanceFrequency(&mut frequency).ok()?;
+    }
+    let elapsed = now.wrapping_sub(present_time).max(0) as u128;
+    Some(Duration::from_nanos((elapsed * 1_000_000_000 / frequency.max(1) as u128) as u64))
+}
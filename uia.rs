@@ -0,0 +1,63 @@
+//! Captura de controles individuais via UI Automation (feature `uia`)
+//!
+//! Frameworks de automação de testes localizam controles com `IUIAutomation` e precisam de
+//! um snapshot só daquele controle. O retângulo do elemento é lido com a thread em modo
+//! per-monitor DPI aware, então chega em pixels físicos do desktop virtual, como os da
+//! duplicação, independente do modo de DPI do processo.
+
+use windows::Win32::UI::Accessibility::IUIAutomationElement;
+use windows::Win32::UI::HiDpi::{SetThreadDpiAwarenessContext, DPI_AWARENESS_CONTEXT, DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2};
+
+use crate::error::CaptureResult;
+use crate::frame::Frame;
+use crate::geometry::{Point, Rect, RectError};
+use crate::DxgiCapture;
+
+/// Restaura o modo de DPI anterior da thread
+struct DpiAwarenessGuard(DPI_AWARENESS_CONTEXT);
+
+impl DpiAwarenessGuard {
+    fn per_monitor() -> Self {
+        Self(unsafe { SetThreadDpiAwarenessContext(DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2) })
+    }
+}
+
+impl Drop for DpiAwarenessGuard {
+    fn drop(&mut self) {
+        // Contexto nulo: a troca falhou (Windows anterior ao 10 1607) e não há o que restaurar
+        if !self.0.is_invalid() {
+            unsafe { SetThreadDpiAwarenessContext(self.0) };
+        }
+    }
+}
+
+/// Retângulo do elemento no desktop virtual, em pixels físicos
+pub fn element_screen_rect(element: &IUIAutomationElement) -> CaptureResult<Rect> {
+    let bounds = {
+        let _dpi = DpiAwarenessGuard::per_monitor();
+        unsafe { element.CurrentBoundingRectangle()? }
+    };
+    Ok(Rect::from_edges(bounds.left, bounds.top, bounds.right, bounds.bottom)?)
+}
+
+/// Região do elemento no monitor do capturador, cortada às bordas do monitor.
+///
+/// Falha com `RectError::Empty` para elementos sem área (ex.: ocultos) e com
+/// `RectError::OutOfBounds` se o elemento não aparece nesse monitor.
+pub fn element_region(capture: &DxgiCapture, element: &IUIAutomationElement) -> CaptureResult<Rect> {
+    let screen = element_screen_rect(element)?;
+    if screen.is_empty() {
+        return Err(RectError::Empty.into());
+    }
+
+    let region = Rect::from_virtual(Point::new(screen.left, screen.top), screen.size(), capture.output_origin())?;
+    let bounds = capture.output_size();
+    Ok(region.clamp_to(bounds).ok_or(RectError::OutOfBounds { region, bounds })?)
+}
+
+/// Captura só a região do elemento
+pub fn capture_element(capture: &mut DxgiCapture, element: &IUIAutomationElement) -> CaptureResult<Frame> {
+    let region = element_region(capture, element)?;
+    debug!("Capturando elemento de UI Automation em {:?}", region);
+    capture.capture_frame(region)
+}
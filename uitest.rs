@@ -0,0 +1,225 @@
+//! Testes de regressão visual de aplicativos desktop (feature `uitest`)
+//!
+//! `UiTestRunner::check` captura uma região nomeada (`DxgiCapture::capture_named`), compara
+//! com a imagem de referência `<baselines>/<nome>.png` pela diferença perceptual
+//! (`compare::compare_perceptual`) e registra o resultado. Em uma falha grava em `artifacts`
+//! a captura (`<nome>.actual.png`) e a imagem das diferenças (`<nome>.diff.png`) para anexar ao
+//! relatório do CI. Sem referência, ou com `update_baselines`, a captura vira a nova referência.
+//!
+//! Quadros HDR são levados para sRGB de 8 bits antes da comparação, como as referências.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use windows::Win32::Graphics::Dxgi::Common::{DXGI_FORMAT_B8G8R8A8_UNORM, DXGI_FORMAT_R8G8B8A8_UNORM};
+
+use crate::compare::{compare_perceptual_with_diff, DiffReport};
+use crate::frame::Frame;
+use crate::outputs::ColorSpace;
+use crate::png_encoder::{load_png, save_png};
+use crate::{DxgiCapture, Result};
+
+/// Onde ficam as referências e os artefatos, e quanto de diferença é aceito
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct UiTestOptions {
+    /// Diretório das imagens de referência, uma `<nome>.png` por região
+    pub baselines: PathBuf,
+    /// Diretório das capturas e imagens de diferenças das falhas
+    pub artifacts: PathBuf,
+    /// Distância perceptual a partir da qual um pixel difere (0.0 a 1.0, veja
+    /// `compare::compare_perceptual`)
+    pub threshold: f64,
+    /// Fração dos pixels que pode diferir sem falhar (0.0 = nenhum)
+    pub max_differing_ratio: f64,
+    /// Substitui as referências pelas capturas em vez de comparar (para aceitar uma mudança
+    /// intencional da interface)
+    pub update_baselines: bool,
+}
+
+impl Default for UiTestOptions {
+    fn default() -> Self {
+        Self {
+            baselines: PathBuf::from("tests/baselines"),
+            artifacts: PathBuf::from("target/uitest"),
+            threshold: 0.1,
+            max_differing_ratio: 0.0,
+            update_baselines: false,
+        }
+    }
+}
+
+/// Resultado de uma verificação
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum UiTestStatus {
+    /// Dentro da tolerância
+    Passed,
+    /// Mais pixels diferentes que `max_differing_ratio`
+    Failed,
+    /// A captura tem outro tamanho que a referência
+    SizeMismatch,
+    /// Não havia referência (ou `update_baselines`): a captura foi gravada como referência
+    BaselineCreated,
+}
+
+/// Uma região verificada e onde estão as imagens
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UiTestOutcome {
+    pub name: String,
+    pub status: UiTestStatus,
+    /// Comparação com a referência (`None` se não houve comparação)
+    pub report: Option<DiffReport>,
+    pub baseline: PathBuf,
+    /// Captura gravada na falha
+    pub actual: Option<PathBuf>,
+    /// Imagem das diferenças gravada na falha
+    pub diff: Option<PathBuf>,
+}
+
+impl UiTestOutcome {
+    /// Não é uma falha (referências criadas contam como aprovadas)
+    pub fn passed(&self) -> bool {
+        matches!(self.status, UiTestStatus::Passed | UiTestStatus::BaselineCreated)
+    }
+}
+
+/// Executa verificações e acumula os resultados
+#[derive(Debug, Clone, Default)]
+pub struct UiTestRunner {
+    options: UiTestOptions,
+    outcomes: Vec<UiTestOutcome>,
+}
+
+impl UiTestRunner {
+    pub fn new(options: UiTestOptions) -> Self {
+        Self {
+            options,
+            outcomes: Vec::new(),
+        }
+    }
+
+    pub fn options(&self) -> &UiTestOptions {
+        &self.options
+    }
+
+    /// Captura a região registrada como `name` e a compara com a referência de mesmo nome
+    pub fn check(&mut self, capture: &mut DxgiCapture, name: &str) -> Result<&UiTestOutcome> {
+        let frame = capture.capture_named(name)?;
+        self.check_frame(name, &frame)
+    }
+
+    /// Compara um quadro já capturado com a referência `name`
+    pub fn check_frame(&mut self, name: &str, frame: &Frame) -> Result<&UiTestOutcome> {
+        let frame = to_sdr8(frame)?;
+        let file = file_name(name);
+        let baseline = self.options.baselines.join(format!("{}.png", file));
+        let mut outcome = UiTestOutcome {
+            name: name.to_owned(),
+            status: UiTestStatus::Passed,
+            report: None,
+            baseline: baseline.clone(),
+            actual: None,
+            diff: None,
+        };
+
+        if self.options.update_baselines || !baseline.exists() {
+            save_into(&self.options.baselines, &baseline, &frame)?;
+            info!("Referência {} gravada em {}", name, baseline.display());
+            outcome.status = UiTestStatus::BaselineCreated;
+            return Ok(self.record(outcome));
+        }
+
+        let expected = load_png(&baseline)?;
+        if expected.size() != frame.size() {
+            outcome.status = UiTestStatus::SizeMismatch;
+            outcome.actual = Some(self.save_artifact(&file, "actual", &frame)?);
+            return Ok(self.record(outcome));
+        }
+
+        let (report, diff) = compare_perceptual_with_diff(&expected, &frame, self.options.threshold)?;
+        if report.differing_ratio() > self.options.max_differing_ratio {
+            outcome.status = UiTestStatus::Failed;
+            outcome.actual = Some(self.save_artifact(&file, "actual", &frame)?);
+            outcome.diff = Some(self.save_artifact(&file, "diff", &diff)?);
+        }
+        outcome.report = Some(report);
+        Ok(self.record(outcome))
+    }
+
+    fn record(&mut self, outcome: UiTestOutcome) -> &UiTestOutcome {
+        if !outcome.passed() {
+            warn!("Regressão visual em {}: {:?}", outcome.name, outcome.status);
+        }
+        self.outcomes.push(outcome);
+        &self.outcomes[self.outcomes.len() - 1]
+    }
+
+    fn save_artifact(&self, file: &str, kind: &str, frame: &Frame) -> Result<PathBuf> {
+        let path = self.options.artifacts.join(format!("{}.{}.png", file, kind));
+        save_into(&self.options.artifacts, &path, frame)?;
+        Ok(path)
+    }
+
+    pub fn outcomes(&self) -> &[UiTestOutcome] {
+        &self.outcomes
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &UiTestOutcome> {
+        self.outcomes.iter().filter(|outcome| !outcome.passed())
+    }
+
+    /// Nenhuma verificação falhou
+    pub fn all_passed(&self) -> bool {
+        self.failures().next().is_none()
+    }
+
+    /// Resumo para a saída de um teste: contagens e uma linha por falha
+    pub fn summary(&self) -> String {
+        let failed = self.failures().count();
+        let mut summary = format!("{} verificações, {} falhas", self.outcomes.len(), failed);
+        for outcome in self.failures() {
+            let _ = write!(summary, "\n  {}: ", outcome.name);
+            match (&outcome.status, &outcome.report) {
+                (UiTestStatus::Failed, Some(report)) => {
+                    let _ = write!(summary, "{:.2}% dos pixels diferentes", report.differing_ratio() * 100.0);
+                    if let Some(bounds) = report.diff_bounds {
+                        let _ = write!(summary, " em {}x{} ({}, {})", bounds.width, bounds.height, bounds.left, bounds.top);
+                    }
+                }
+                _ => {
+                    let _ = write!(summary, "tamanho diferente da referência {}", outcome.baseline.display());
+                }
+            }
+            if let Some(diff) = &outcome.diff {
+                let _ = write!(summary, ", diferenças em {}", diff.display());
+            }
+        }
+        summary
+    }
+}
+
+/// Grava `frame` em `path`, criando `dir` se preciso
+fn save_into(dir: &Path, path: &Path, frame: &Frame) -> Result<()> {
+    fs::create_dir_all(dir)?;
+    save_png(frame, path)
+}
+
+/// Quadros FP16 e de 10 bits levados para sRGB de 8 bits, como as referências
+fn to_sdr8(frame: &Frame) -> Result<Frame> {
+    if matches!(frame.format, DXGI_FORMAT_B8G8R8A8_UNORM | DXGI_FORMAT_R8G8B8A8_UNORM) {
+        return Ok(frame.clone());
+    }
+    frame.to_color_space(ColorSpace::Srgb)
+}
+
+/// Nome de região usável como nome de arquivo
+fn file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') { c } else { '_' })
+        .collect()
+}
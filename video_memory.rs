@@ -0,0 +1,129 @@
+//! Uso e orçamento de memória de vídeo do processo (`IDXGIAdapter3`, Windows 10+)
+//!
+//! O orçamento é quanto o sistema permite ao processo usar sem sofrer com paginação; ele
+//! encolhe quando outros aplicativos (jogos, encoders) pedem memória. Serviços de captura de
+//! longa duração podem consultar o uso ou aguardar as notificações de mudança do orçamento
+//! para liberar as texturas em cache antes que o driver comece a paginar.
+
+use std::time::Duration;
+
+use windows::core::ComInterface;
+use windows::Win32::Foundation::{CloseHandle, HANDLE, WAIT_OBJECT_0};
+use windows::Win32::Graphics::Dxgi::{
+    IDXGIAdapter1, IDXGIAdapter3, DXGI_MEMORY_SEGMENT_GROUP, DXGI_MEMORY_SEGMENT_GROUP_LOCAL,
+    DXGI_MEMORY_SEGMENT_GROUP_NON_LOCAL, DXGI_QUERY_VIDEO_MEMORY_INFO,
+};
+use windows::Win32::System::Threading::{CreateEventW, WaitForSingleObject};
+
+use crate::error::CaptureResult;
+
+/// Uso de um segmento de memória pelo processo, em bytes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VideoMemoryInfo {
+    /// Quanto o processo pode usar sem degradar o desempenho
+    pub budget: u64,
+    pub usage: u64,
+    /// Memória reservada pelo processo com `SetVideoMemoryReservation`
+    pub reservation: u64,
+    pub available_for_reservation: u64,
+}
+
+impl VideoMemoryInfo {
+    /// Fração do orçamento em uso (acima de 1.0 o sistema está paginando)
+    pub fn usage_ratio(&self) -> f64 {
+        if self.budget == 0 {
+            0.0
+        } else {
+            self.usage as f64 / self.budget as f64
+        }
+    }
+
+    pub fn over_budget(&self) -> bool {
+        self.usage > self.budget
+    }
+}
+
+/// Memória local (VRAM dedicada) e não local (memória do sistema acessível à GPU); em GPUs
+/// integradas quase tudo aparece como local
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VideoMemoryStatus {
+    pub local: VideoMemoryInfo,
+    pub non_local: VideoMemoryInfo,
+}
+
+/// Uso de memória do processo no adaptador; falha com `E_NOINTERFACE` antes do Windows 10
+pub(crate) fn query(adapter: &IDXGIAdapter1) -> CaptureResult<VideoMemoryStatus> {
+    query_status(&adapter.cast()?)
+}
+
+fn query_status(adapter: &IDXGIAdapter3) -> CaptureResult<VideoMemoryStatus> {
+    Ok(VideoMemoryStatus {
+        local: query_segment(adapter, DXGI_MEMORY_SEGMENT_GROUP_LOCAL)?,
+        non_local: query_segment(adapter, DXGI_MEMORY_SEGMENT_GROUP_NON_LOCAL)?,
+    })
+}
+
+fn query_segment(adapter: &IDXGIAdapter3, group: DXGI_MEMORY_SEGMENT_GROUP) -> CaptureResult<VideoMemoryInfo> {
+    let mut info = DXGI_QUERY_VIDEO_MEMORY_INFO::default();
+    unsafe { adapter.QueryVideoMemoryInfo(0, group, &mut info)? };
+    Ok(VideoMemoryInfo {
+        budget: info.Budget,
+        usage: info.CurrentUsage,
+        reservation: info.CurrentReservation,
+        available_for_reservation: info.AvailableForReservation,
+    })
+}
+
+/// Evento sinalizado pelo sistema a cada mudança do orçamento de memória de vídeo
+pub struct BudgetNotification {
+    adapter: IDXGIAdapter3,
+    event: HANDLE,
+    cookie: u32,
+}
+
+impl BudgetNotification {
+    pub(crate) fn register(adapter: &IDXGIAdapter1) -> CaptureResult<Self> {
+        let adapter: IDXGIAdapter3 = adapter.cast()?;
+        // Auto-reset: cada espera consome uma notificação
+        let event = unsafe { CreateEventW(None, false, false, None)? };
+        match unsafe { adapter.RegisterVideoMemoryBudgetChangeNotificationEvent(event) } {
+            Ok(cookie) => Ok(Self { adapter, event, cookie }),
+            Err(e) => {
+                unsafe {
+                    let _ = CloseHandle(event);
+                }
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Espera até `timeout` por uma mudança do orçamento; `true` se houve alguma
+    pub fn wait(&self, timeout: Duration) -> bool {
+        let millis = timeout.as_millis().min(u32::MAX as u128 - 1) as u32;
+        unsafe { WaitForSingleObject(self.event, millis) == WAIT_OBJECT_0 }
+    }
+
+    /// Se o orçamento mudou desde a última verificação, sem esperar
+    pub fn changed(&self) -> bool {
+        self.wait(Duration::ZERO)
+    }
+
+    /// Uso atual do processo no adaptador notificado
+    pub fn status(&self) -> CaptureResult<VideoMemoryStatus> {
+        query_status(&self.adapter)
+    }
+}
+
+impl Drop for BudgetNotification {
+    fn drop(&mut self) {
+        unsafe {
+            self.adapter.UnregisterVideoMemoryBudgetChangeNotification(self.cookie);
+            let _ = CloseHandle(self.event);
+        }
+    }
+}
+
+// O evento e a interface podem ser usados a partir de qualquer thread
+unsafe impl Send for BudgetNotification {}
@@ -0,0 +1,320 @@
+//! Conversão de formato e escala na GPU usando o video processor do D3D11
+
+use std::mem::ManuallyDrop;
+
+use windows::core::*;
+use windows::Win32::Foundation::{BOOL, RECT};
+use windows::Win32::Graphics::Direct3D11::*;
+use windows::Win32::Graphics::Dxgi::Common::*;
+
+use crate::geometry::{Rect, Size};
+use crate::Result;
+
+/// Espaço de cor da saída: YCbCr BT.709 em faixa limitada (16-235)
+const OUTPUT_COLOR_SPACE_BT709_LIMITED: u32 = (1 << 2) | (1 << 4);
+
+/// Como a região de origem ocupa o tamanho fixo da saída
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OutputFit {
+    /// Estica a região para o tamanho da saída, sem manter a proporção
+    #[default]
+    Stretch,
+    /// Escala mantendo a proporção, centraliza e preenche as bordas com a cor RGBA
+    Letterbox { color: [u8; 4] },
+}
+
+/// Tamanho e formato de uma saída do video processor (`DxgiCapture::capture_outputs`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OutputSpec {
+    pub size: Size,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_support::dxgi_format"))]
+    pub format: DXGI_FORMAT,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub fit: OutputFit,
+}
+
+impl OutputSpec {
+    pub fn new(size: Size, format: DXGI_FORMAT) -> Self {
+        Self { size, format, fit: OutputFit::Stretch }
+    }
+
+    /// Mantém a proporção da região e preenche as bordas com `color` (RGBA), para encoders e
+    /// modelos com tamanho de entrada fixo
+    pub fn with_letterbox(mut self, color: [u8; 4]) -> Self {
+        self.fit = OutputFit::Letterbox { color };
+        self
+    }
+}
+
+/// Retângulo centralizado de `output` com a proporção de `source`, arredondado para pixels pares
+/// (o plano UV dos formatos 4:2:0 tem metade da resolução)
+fn letterbox_rect(source: Size, output: Size) -> RECT {
+    let (source_width, source_height) = (source.width.max(1) as u64, source.height.max(1) as u64);
+    let (output_width, output_height) = (output.width as u64, output.height as u64);
+    // Limitada pela largura quando a origem é proporcionalmente mais larga que a saída
+    let (width, height) = if source_width * output_height > output_width * source_height {
+        (output.width, (source_height * output_width / source_width) as u32)
+    } else {
+        ((source_width * output_height / source_height) as u32, output.height)
+    };
+    let even = |value: u32, limit: u32| if value < limit { (value & !1).max(2.min(limit)) } else { limit };
+    let (width, height) = (even(width, output.width), even(height, output.height));
+    let left = (((output.width - width) / 2) & !1) as i32;
+    let top = (((output.height - height) / 2) & !1) as i32;
+    RECT {
+        left,
+        top,
+        right: left + width as i32,
+        bottom: top + height as i32,
+    }
+}
+
+/// Converte texturas RGB capturadas para outro formato/tamanho sem sair da GPU.
+///
+/// As texturas de saída formam um pool circular, para que consumidores assíncronos
+/// (como encoders de hardware) possam manter um quadro enquanto o próximo é convertido.
+pub struct VideoConverter {
+    device: ID3D11Device,
+    video_device: ID3D11VideoDevice,
+    video_context: ID3D11VideoContext,
+    enumerator: ID3D11VideoProcessorEnumerator,
+    processor: ID3D11VideoProcessor,
+    input_view: Option<(ID3D11Texture2D, ID3D11VideoProcessorInputView)>,
+    outputs: Vec<(ID3D11Texture2D, ID3D11VideoProcessorOutputView)>,
+    next_output: usize,
+    input_size: Size,
+    output_width: u32,
+    output_height: u32,
+    fit: OutputFit,
+}
+
+impl VideoConverter {
+    pub fn new(
+        device: &ID3D11Device,
+        context: &ID3D11DeviceContext,
+        input_size: Size,
+        output_size: Size,
+        output_format: DXGI_FORMAT,
+        pool_size: usize,
+    ) -> Result<Self> {
+        let video_device: ID3D11VideoDevice = device.cast()?;
+        let video_context: ID3D11VideoContext = context.cast()?;
+
+        let content_desc = D3D11_VIDEO_PROCESSOR_CONTENT_DESC {
+            InputFrameFormat: D3D11_VIDEO_FRAME_FORMAT_PROGRESSIVE,
+            InputFrameRate: DXGI_RATIONAL { Numerator: 60, Denominator: 1 },
+            InputWidth: input_size.width,
+            InputHeight: input_size.height,
+            OutputFrameRate: DXGI_RATIONAL { Numerator: 60, Denominator: 1 },
+            OutputWidth: output_size.width,
+            OutputHeight: output_size.height,
+            Usage: D3D11_VIDEO_USAGE_OPTIMAL_SPEED,
+        };
+
+        let (enumerator, processor) = unsafe {
+            let enumerator = video_device.CreateVideoProcessorEnumerator(&content_desc)?;
+
+            let support = enumerator.CheckVideoProcessorFormat(output_format)?;
+            if support & D3D11_VIDEO_PROCESSOR_FORMAT_SUPPORT_OUTPUT.0 as u32 == 0 {
+                return Err(format!("video processor does not support {:?} output", output_format).into());
+            }
+
+            let processor = video_device.CreateVideoProcessor(&enumerator, 0)?;
+            (enumerator, processor)
+        };
+
+        unsafe {
+            let output_color_space = D3D11_VIDEO_PROCESSOR_COLOR_SPACE {
+                _bitfield: OUTPUT_COLOR_SPACE_BT709_LIMITED,
+            };
+            let input_color_space = D3D11_VIDEO_PROCESSOR_COLOR_SPACE::default();
+            video_context.VideoProcessorSetOutputColorSpace(&processor, &output_color_space);
+            video_context.VideoProcessorSetStreamColorSpace(&processor, 0, &input_color_space);
+            video_context.VideoProcessorSetStreamFrameFormat(&processor, 0, D3D11_VIDEO_FRAME_FORMAT_PROGRESSIVE);
+        }
+
+        let mut converter = Self {
+            device: device.clone(),
+            video_device,
+            video_context,
+            enumerator,
+            processor,
+            input_view: None,
+            outputs: Vec::with_capacity(pool_size.max(1)),
+            next_output: 0,
+            input_size,
+            output_width: output_size.width,
+            output_height: output_size.height,
+            fit: OutputFit::Stretch,
+        };
+
+        for _ in 0..pool_size.max(1) {
+            let output = converter.create_output(output_format)?;
+            converter.outputs.push(output);
+        }
+
+        Ok(converter)
+    }
+
+    fn create_output(&self, format: DXGI_FORMAT) -> Result<(ID3D11Texture2D, ID3D11VideoProcessorOutputView)> {
+        let desc = D3D11_TEXTURE2D_DESC {
+            Width: self.output_width,
+            Height: self.output_height,
+            MipLevels: 1,
+            ArraySize: 1,
+            Format: format,
+            SampleDesc: DXGI_SAMPLE_DESC {
+                Count: 1,
+                Quality: 0,
+            },
+            Usage: D3D11_USAGE_DEFAULT,
+            BindFlags: D3D11_BIND_RENDER_TARGET.0 as u32,
+            CPUAccessFlags: 0,
+            MiscFlags: 0,
+        };
+
+        let view_desc = D3D11_VIDEO_PROCESSOR_OUTPUT_VIEW_DESC {
+            ViewDimension: D3D11_VPOV_DIMENSION_TEXTURE2D,
+            Anonymous: D3D11_VIDEO_PROCESSOR_OUTPUT_VIEW_DESC_0 {
+                Texture2D: D3D11_TEX2D_VPOV { MipSlice: 0 },
+            },
+        };
+
+        let mut texture: Option<ID3D11Texture2D> = None;
+        let mut view: Option<ID3D11VideoProcessorOutputView> = None;
+        unsafe {
+            self.device.CreateTexture2D(&desc, None, Some(&mut texture))?;
+            let texture = texture.as_ref().ok_or("failed to create the video processor output texture")?;
+            self.video_device
+                .CreateVideoProcessorOutputView(texture, &self.enumerator, &view_desc, Some(&mut view))?;
+        }
+
+        Ok((
+            texture.ok_or("failed to create the video processor output texture")?,
+            view.ok_or("failed to create the video processor output view")?,
+        ))
+    }
+
+    /// Espaços de cor da entrada e da saída no lugar de RGB sRGB para YCbCr BT.709 (ex.: HDR10 ou
+    /// scRGB para YCbCr PQ BT.2020 em P010); exige `ID3D11VideoContext1` (Windows 10)
+    pub fn set_color_spaces(&mut self, input: DXGI_COLOR_SPACE_TYPE, output: DXGI_COLOR_SPACE_TYPE) -> Result<()> {
+        let video_context: ID3D11VideoContext1 = self.video_context.cast()?;
+        unsafe {
+            video_context.VideoProcessorSetStreamColorSpace1(&self.processor, 0, input);
+            video_context.VideoProcessorSetOutputColorSpace1(&self.processor, output);
+        }
+        Ok(())
+    }
+
+    /// Modo de ajuste das próximas conversões; em `Letterbox` o video processor desenha a região
+    /// no retângulo centralizado e pinta o resto da saída com a cor de fundo, na mesma passada
+    pub fn set_fit(&mut self, fit: OutputFit) {
+        if fit == self.fit {
+            return;
+        }
+        if let OutputFit::Letterbox { color: [r, g, b, a] } = fit {
+            let color = D3D11_VIDEO_COLOR {
+                Anonymous: D3D11_VIDEO_COLOR_0 {
+                    RGBA: D3D11_VIDEO_COLOR_RGBA {
+                        R: r as f32 / 255.0,
+                        G: g as f32 / 255.0,
+                        B: b as f32 / 255.0,
+                        A: a as f32 / 255.0,
+                    },
+                },
+            };
+            // Em RGB o driver converte a cor para o espaço de cor da saída (ex.: YCbCr do NV12)
+            unsafe {
+                self.video_context.VideoProcessorSetOutputBackgroundColor(&self.processor, false, &color);
+            }
+        }
+        self.fit = fit;
+    }
+
+    /// Reutiliza a view de entrada quando a textura de origem é a mesma da chamada anterior
+    fn input_view(&mut self, input: &ID3D11Texture2D) -> Result<ID3D11VideoProcessorInputView> {
+        if let Some((texture, view)) = &self.input_view {
+            if texture == input {
+                return Ok(view.clone());
+            }
+        }
+
+        let view_desc = D3D11_VIDEO_PROCESSOR_INPUT_VIEW_DESC {
+            FourCC: 0,
+            ViewDimension: D3D11_VPIV_DIMENSION_TEXTURE2D,
+            Anonymous: D3D11_VIDEO_PROCESSOR_INPUT_VIEW_DESC_0 {
+                Texture2D: D3D11_TEX2D_VPIV { MipSlice: 0, ArraySlice: 0 },
+            },
+        };
+
+        let mut view: Option<ID3D11VideoProcessorInputView> = None;
+        unsafe {
+            self.video_device
+                .CreateVideoProcessorInputView(input, &self.enumerator, &view_desc, Some(&mut view))?;
+        }
+
+        let view = view.ok_or("failed to create the video processor input view")?;
+        self.input_view = Some((input.clone(), view.clone()));
+        Ok(view)
+    }
+
+    /// Converte `input` inteira para a próxima textura do pool
+    pub fn convert(&mut self, input: &ID3D11Texture2D) -> Result<ID3D11Texture2D> {
+        self.convert_rect(input, None)
+    }
+
+    /// Converte (e escala) apenas `source` de `input` para a próxima textura do pool
+    pub fn convert_rect(&mut self, input: &ID3D11Texture2D, source: Option<Rect>) -> Result<ID3D11Texture2D> {
+        let destination = match self.fit {
+            OutputFit::Stretch => None,
+            OutputFit::Letterbox { .. } => Some(letterbox_rect(
+                source.map_or(self.input_size, |rect| rect.size()),
+                Size::new(self.output_width, self.output_height),
+            )),
+        };
+        let source = match source {
+            Some(rect) => Some(RECT {
+                left: rect.left,
+                top: rect.top,
+                right: rect.right().try_into()?,
+                bottom: rect.bottom().try_into()?,
+            }),
+            None => None,
+        };
+        let input_view = self.input_view(input)?;
+        let (output, output_view) = self.outputs[self.next_output].clone();
+        self.next_output = (self.next_output + 1) % self.outputs.len();
+
+        let mut stream = D3D11_VIDEO_PROCESSOR_STREAM {
+            Enable: BOOL::from(true),
+            pInputSurface: ManuallyDrop::new(Some(input_view)),
+            ..Default::default()
+        };
+
+        let result = unsafe {
+            self.video_context.VideoProcessorSetStreamSourceRect(
+                &self.processor,
+                0,
+                source.is_some(),
+                source.as_ref().map(|rect| rect as *const RECT),
+            );
+            self.video_context.VideoProcessorSetStreamDestRect(
+                &self.processor,
+                0,
+                destination.is_some(),
+                destination.as_ref().map(|rect| rect as *const RECT),
+            );
+            self.video_context
+                .VideoProcessorBlt(&self.processor, &output_view, 0, std::slice::from_ref(&stream))
+        };
+
+        unsafe {
+            ManuallyDrop::drop(&mut stream.pInputSurface);
+        }
+
+        result?;
+        Ok(output)
+    }
+}
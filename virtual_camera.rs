@@ -0,0 +1,68 @@
+//! Saída como webcam virtual via softcam (feature `softcam`)
+//!
+//! Requer a `softcam.dll` registrada no sistema (`RegisterSoftcam.bat` do projeto softcam).
+//! Qualquer aplicativo de videoconferência passa a enxergar a região capturada como uma câmera.
+
+use std::ffi::c_void;
+use std::time::Duration;
+
+use windows::Win32::Graphics::Dxgi::Common::DXGI_FORMAT;
+
+use crate::convert::to_bgr24;
+use crate::Result;
+
+#[link(name = "softcam")]
+extern "C" {
+    fn scCreateCamera(width: i32, height: i32, framerate: f32) -> *mut c_void;
+    fn scDeleteCamera(camera: *mut c_void);
+    fn scSendFrame(camera: *mut c_void, image_bits: *const c_void);
+    fn scWaitForConnection(camera: *mut c_void, timeout: f32) -> bool;
+}
+
+/// Câmera virtual que recebe quadros capturados
+pub struct VirtualCamera {
+    camera: *mut c_void,
+    width: u32,
+    height: u32,
+    bgr: Vec<u8>,
+}
+
+impl VirtualCamera {
+    /// Cria a câmera; apenas uma instância pode existir por vez no sistema
+    pub fn new(width: u32, height: u32, frame_rate: f32) -> Result<Self> {
+        let camera = unsafe { scCreateCamera(width as i32, height as i32, frame_rate) };
+        if camera.is_null() {
+            return Err("Falha ao criar a câmera virtual (softcam registrado e não em uso?)".into());
+        }
+
+        Ok(Self {
+            camera,
+            width,
+            height,
+            bgr: vec![0u8; width as usize * height as usize * 3],
+        })
+    }
+
+    /// Aguarda um aplicativo abrir a câmera; `None` espera indefinidamente
+    pub fn wait_for_connection(&self, timeout: Option<Duration>) -> bool {
+        let timeout = timeout.map_or(0.0, |t| t.as_secs_f32());
+        unsafe { scWaitForConnection(self.camera, timeout) }
+    }
+
+    /// Envia um quadro BGRA/RGBA com as dimensões da câmera
+    pub fn send_frame(&mut self, pixels: &[u8], format: DXGI_FORMAT) -> Result<()> {
+        to_bgr24(pixels, format, self.width, self.height, &mut self.bgr)?;
+        unsafe {
+            scSendFrame(self.camera, self.bgr.as_ptr() as *const c_void);
+        }
+        Ok(())
+    }
+}
+
+impl Drop for VirtualCamera {
+    fn drop(&mut self) {
+        unsafe {
+            scDeleteCamera(self.camera);
+        }
+    }
+}
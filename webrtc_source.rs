@@ -0,0 +1,157 @@
+//! Fonte de vídeo WebRTC para compartilhamento de tela (feature `webrtc`)
+//!
+//! A captura é exposta como uma track H.264 do `webrtc-rs`. Os relatórios RTCP do receptor
+//! (perda e REMB) ajustam a escala e a taxa de quadros sugeridas em `settings()`, que o loop
+//! de captura/codificação consulta a cada quadro.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use bytes::Bytes;
+use webrtc::api::media_engine::MIME_TYPE_H264;
+use webrtc::media::Sample;
+use webrtc::peer_connection::RTCPeerConnection;
+use webrtc::rtcp::payload_feedbacks::receiver_estimated_maximum_bitrate::ReceiverEstimatedMaximumBitrate;
+use webrtc::rtcp::receiver_report::ReceiverReport;
+use webrtc::rtp_transceiver::rtp_codec::RTCRtpCodecCapability;
+use webrtc::track::track_local::track_local_static_sample::TrackLocalStaticSample;
+use webrtc::track::track_local::TrackLocal;
+
+use crate::Result;
+
+/// Degraus de qualidade, do melhor para o mais econômico: (escala, fps, bitrate)
+const QUALITY_LADDER: [(f32, u32, u32); 5] = [
+    (1.0, 60, 8_000_000),
+    (1.0, 30, 5_000_000),
+    (0.75, 30, 3_000_000),
+    (0.5, 30, 1_500_000),
+    (0.5, 15, 800_000),
+];
+
+/// Perda (fração de 0-255 do RTCP) acima da qual a qualidade é reduzida
+const LOSS_DEGRADE_THRESHOLD: u8 = 25;
+/// Perda abaixo da qual a qualidade pode voltar a subir
+const LOSS_RECOVER_THRESHOLD: u8 = 5;
+/// Relatórios limpos consecutivos necessários antes de subir um degrau
+const RECOVER_REPORTS: u32 = 5;
+
+/// Parâmetros que o loop de captura deve aplicar no momento
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StreamSettings {
+    /// Fator de escala aplicado à região capturada antes da codificação
+    pub scale: f32,
+    pub fps: u32,
+    pub bitrate: u32,
+}
+
+#[derive(Debug)]
+struct AdaptiveState {
+    level: usize,
+    clean_reports: u32,
+    remb_bitrate: Option<u32>,
+}
+
+impl AdaptiveState {
+    fn settings(&self) -> StreamSettings {
+        let (scale, fps, bitrate) = QUALITY_LADDER[self.level];
+        StreamSettings {
+            scale,
+            fps,
+            bitrate: self.remb_bitrate.map_or(bitrate, |remb| remb.min(bitrate)),
+        }
+    }
+
+    fn on_loss(&mut self, fraction_lost: u8) {
+        if fraction_lost > LOSS_DEGRADE_THRESHOLD {
+            self.level = (self.level + 1).min(QUALITY_LADDER.len() - 1);
+            self.clean_reports = 0;
+        } else if fraction_lost < LOSS_RECOVER_THRESHOLD {
+            self.clean_reports += 1;
+            if self.clean_reports >= RECOVER_REPORTS && self.level > 0 {
+                self.level -= 1;
+                self.clean_reports = 0;
+            }
+        }
+    }
+
+    fn on_remb(&mut self, bitrate: f32) {
+        let bitrate = (bitrate * 0.9) as u32;
+        self.remb_bitrate = Some(bitrate);
+
+        // Descer até o primeiro degrau que cabe na banda estimada
+        while self.level < QUALITY_LADDER.len() - 1 && QUALITY_LADDER[self.level].2 > bitrate {
+            self.level += 1;
+        }
+    }
+}
+
+/// Track de vídeo WebRTC alimentada com unidades de acesso H.264 em Annex-B
+pub struct WebRtcSource {
+    track: Arc<TrackLocalStaticSample>,
+    state: Arc<Mutex<AdaptiveState>>,
+}
+
+impl WebRtcSource {
+    /// Cria a track e a adiciona à conexão; deve ser chamado antes de gerar a oferta/resposta
+    pub async fn attach(peer_connection: &RTCPeerConnection) -> Result<Self> {
+        let track = Arc::new(TrackLocalStaticSample::new(
+            RTCRtpCodecCapability {
+                mime_type: MIME_TYPE_H264.to_owned(),
+                clock_rate: 90_000,
+                channels: 0,
+                sdp_fmtp_line: "level-asymmetry-allowed=1;packetization-mode=1;profile-level-id=42e01f".to_owned(),
+                rtcp_feedback: vec![],
+            },
+            "screen".to_owned(),
+            "dxgi_capture".to_owned(),
+        ));
+
+        let sender = peer_connection
+            .add_track(Arc::clone(&track) as Arc<dyn TrackLocal + Send + Sync>)
+            .await?;
+
+        let state = Arc::new(Mutex::new(AdaptiveState {
+            level: 0,
+            clean_reports: 0,
+            remb_bitrate: None,
+        }));
+
+        // Ler o RTCP do receptor para adaptar a qualidade
+        let rtcp_state = Arc::clone(&state);
+        tokio::spawn(async move {
+            while let Ok((packets, _)) = sender.read_rtcp().await {
+                let mut state = rtcp_state.lock().unwrap_or_else(|e| e.into_inner());
+                for packet in packets {
+                    let packet = packet.as_any();
+                    if let Some(report) = packet.downcast_ref::<ReceiverReport>() {
+                        for reception in &report.reports {
+                            state.on_loss(reception.fraction_lost);
+                        }
+                    } else if let Some(remb) = packet.downcast_ref::<ReceiverEstimatedMaximumBitrate>() {
+                        state.on_remb(remb.bitrate);
+                    }
+                }
+            }
+        });
+
+        Ok(Self { track, state })
+    }
+
+    /// Escala, FPS e bitrate sugeridos pelas condições atuais da rede
+    pub fn settings(&self) -> StreamSettings {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).settings()
+    }
+
+    /// Envia uma unidade de acesso com a duração do quadro
+    pub async fn write_frame(&self, annexb: &[u8], duration: Duration) -> Result<()> {
+        self.track
+            .write_sample(&Sample {
+                data: Bytes::copy_from_slice(annexb),
+                duration,
+                ..Default::default()
+            })
+            .await?;
+        Ok(())
+    }
+}
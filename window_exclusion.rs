@@ -0,0 +1,62 @@
+//! Exclusão das janelas do próprio processo da captura
+//!
+//! Uma janela de pré-visualização ou overlay que aparece no monitor capturado entra na
+//! própria captura e forma um "túnel de espelhos". Com `WDA_EXCLUDEFROMCAPTURE` o DWM
+//! omite a janela de toda captura de tela: a Desktop Duplication deste crate e também
+//! `Windows.Graphics.Capture`, PrintScreen e aplicativos de compartilhamento. A janela
+//! continua visível normalmente para o usuário.
+
+use windows::Win32::Foundation::HWND;
+use windows::Win32::UI::WindowsAndMessaging::*;
+
+use crate::capabilities::OsVersion;
+use crate::error::CaptureResult;
+
+/// Build do Windows 10 2004, a partir da qual existe `WDA_EXCLUDEFROMCAPTURE`
+pub const BUILD_EXCLUDE_FROM_CAPTURE: u32 = 19041;
+
+/// Como a janela passou a aparecer nas capturas
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exclusion {
+    /// Omitida: o conteúdo por trás dela aparece na captura
+    Excluded,
+    /// Windows anterior ao 10 2004: a janela aparece preenchida de preto (`WDA_MONITOR`)
+    Blacked,
+}
+
+/// Remove a janela das capturas de tela.
+///
+/// A janela precisa pertencer ao processo que chama (limitação de `SetWindowDisplayAffinity`).
+pub fn exclude_from_capture(hwnd: HWND) -> CaptureResult<Exclusion> {
+    if OsVersion::current().is_win10_build(BUILD_EXCLUDE_FROM_CAPTURE) {
+        unsafe { SetWindowDisplayAffinity(hwnd, WDA_EXCLUDEFROMCAPTURE)? };
+        debug!("Janela {:?} excluída da captura", hwnd);
+        Ok(Exclusion::Excluded)
+    } else {
+        unsafe { SetWindowDisplayAffinity(hwnd, WDA_MONITOR)? };
+        debug!("WDA_EXCLUDEFROMCAPTURE indisponível, janela {:?} aparecerá preta", hwnd);
+        Ok(Exclusion::Blacked)
+    }
+}
+
+/// Aplica `exclude_from_capture` a várias janelas, parando na primeira falha
+pub fn exclude_windows(hwnds: &[HWND]) -> CaptureResult<Exclusion> {
+    let mut exclusion = Exclusion::Excluded;
+    for &hwnd in hwnds {
+        exclusion = exclude_from_capture(hwnd)?;
+    }
+    Ok(exclusion)
+}
+
+/// Volta a mostrar a janela nas capturas
+pub fn include_in_capture(hwnd: HWND) -> CaptureResult<()> {
+    unsafe { SetWindowDisplayAffinity(hwnd, WDA_NONE)? };
+    Ok(())
+}
+
+/// A janela está excluída (ou escurecida) nas capturas
+pub fn is_excluded(hwnd: HWND) -> CaptureResult<bool> {
+    let mut affinity = 0u32;
+    unsafe { GetWindowDisplayAffinity(hwnd, &mut affinity)? };
+    Ok(affinity != WDA_NONE.0)
+}
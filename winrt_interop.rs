@@ -0,0 +1,118 @@
+//! Quadros como objetos WinRT (feature `winrt`)
+//!
+//! APIs do `Windows.Media`, o OCR (`Windows.Media.Ocr`) e o Windows ML recebem `SoftwareBitmap`
+//! ou `IDirect3DSurface`. `Frame::to_software_bitmap` copia os pixels para um bitmap do mesmo
+//! formato, e `direct3d_surface` embrulha uma textura da captura sem cópia, para consumidores que
+//! ficam na GPU (`VideoFrame::CreateWithDirect3D11Surface`, `MediaStreamSample`). O
+//! `IDirect3DDevice` de `DxgiCapture::winrt_device` é o dispositivo das texturas, para criar o
+//! `LearningModelDevice` ou o `MediaComposition` no mesmo adaptador.
+//!
+//! `SoftwareBitmap` não tem formato de ponto flutuante nem R10G10B10A2: quadros HDR são levados
+//! para B8G8R8A8 sRGB, recortados como em `Frame::to_color_space`. As superfícies mantêm o
+//! formato da textura. As chamadas exigem COM inicializado na thread (`ensure_com_initialized`).
+
+use windows::core::ComInterface;
+use windows::Graphics::DirectX::Direct3D11::{IDirect3DDevice, IDirect3DSurface};
+use windows::Graphics::Imaging::{BitmapAlphaMode, BitmapBufferAccessMode, BitmapPixelFormat, SoftwareBitmap};
+use windows::Win32::Graphics::Direct3D11::{ID3D11Device, ID3D11Texture2D};
+use windows::Win32::Graphics::Dxgi::Common::*;
+use windows::Win32::Graphics::Dxgi::{IDXGIDevice, IDXGISurface};
+use windows::Win32::System::WinRT::Direct3D11::{
+    CreateDirect3D11DeviceFromDXGIDevice, CreateDirect3D11SurfaceFromDXGISurface,
+};
+use windows::Win32::System::WinRT::IMemoryBufferByteAccess;
+
+use crate::error::{CaptureError, CaptureResult};
+use crate::frame::Frame;
+use crate::geometry::Rect;
+use crate::outputs::ColorSpace;
+use crate::{DxgiCapture, Result};
+
+impl Frame {
+    /// Cópia dos pixels em um `SoftwareBitmap`: B8G8R8A8, R8G8B8A8, NV12 e P010 mantêm o formato
+    /// (alfa pré-multiplicado, como o XAML e o `VideoFrame` esperam; o desktop é opaco), FP16 e
+    /// R10G10B10A2 viram B8G8R8A8 sRGB
+    pub fn to_software_bitmap(&self) -> Result<SoftwareBitmap> {
+        let converted;
+        let frame = match bitmap_format(self.format) {
+            Some(_) => self,
+            None => {
+                converted = self.to_color_space(ColorSpace::Srgb)?;
+                &converted
+            }
+        };
+        let format = bitmap_format(frame.format)
+            .ok_or_else(|| format!("Formato sem SoftwareBitmap: {:?}", frame.format))?;
+        let alpha = if matches!(format, BitmapPixelFormat::Bgra8 | BitmapPixelFormat::Rgba8) {
+            BitmapAlphaMode::Premultiplied
+        } else {
+            BitmapAlphaMode::Ignore
+        };
+
+        let bitmap = SoftwareBitmap::CreateWithAlpha(format, frame.width() as i32, frame.height() as i32, alpha)?;
+        {
+            let buffer = bitmap.LockBuffer(BitmapBufferAccessMode::Write)?;
+            let reference = buffer.CreateReference()?;
+            let mut data = std::ptr::null_mut();
+            let mut capacity = 0;
+            unsafe {
+                reference.cast::<IMemoryBufferByteAccess>()?.GetBuffer(&mut data, &mut capacity)?;
+            }
+            let destination = unsafe { std::slice::from_raw_parts_mut(data, capacity as usize) };
+
+            // Planos de `data` em sequência (Y e UV nos formatos planares), com as linhas sem padding
+            let row_bytes = frame.stride();
+            let mut rows = frame.data.chunks_exact(row_bytes.max(1));
+            for plane in 0..buffer.GetPlaneCount()? {
+                let description = buffer.GetPlaneDescription(plane)?;
+                // O plano UV tem metade das linhas
+                let plane_rows = if plane == 0 { frame.height() } else { frame.height().div_ceil(2) };
+                for (index, row) in rows.by_ref().take(plane_rows as usize).enumerate() {
+                    let start = description.StartIndex as usize + index * description.Stride as usize;
+                    destination[start..start + row_bytes].copy_from_slice(row);
+                }
+            }
+            // A referência precisa ser fechada antes do buffer, senão o bitmap continua travado
+            reference.Close()?;
+            buffer.Close()?;
+        }
+        Ok(bitmap)
+    }
+}
+
+impl DxgiCapture {
+    /// Dispositivo das texturas da captura como `IDirect3DDevice`; muda quando a duplicação é
+    /// reinicializada, como `device()`
+    pub fn winrt_device(&self) -> CaptureResult<IDirect3DDevice> {
+        direct3d_device(self.device().ok_or(CaptureError::NotInitialized)?)
+    }
+
+    /// `capture_texture` embrulhada como `IDirect3DSurface`, sem cópia. A textura é reutilizada
+    /// pela próxima captura: consumidores que guardam a superfície (filas do `Windows.Media`)
+    /// precisam copiá-la antes
+    pub fn capture_surface(&mut self, region: Rect) -> CaptureResult<IDirect3DSurface> {
+        direct3d_surface(&self.capture_texture(region)?)
+    }
+}
+
+/// Superfície WinRT que compartilha a memória de `texture`
+pub fn direct3d_surface(texture: &ID3D11Texture2D) -> CaptureResult<IDirect3DSurface> {
+    let surface: IDXGISurface = texture.cast()?;
+    Ok(unsafe { CreateDirect3D11SurfaceFromDXGISurface(&surface)? }.cast()?)
+}
+
+/// `IDirect3DDevice` WinRT para o mesmo dispositivo D3D11
+pub fn direct3d_device(device: &ID3D11Device) -> CaptureResult<IDirect3DDevice> {
+    let device: IDXGIDevice = device.cast()?;
+    Ok(unsafe { CreateDirect3D11DeviceFromDXGIDevice(&device)? }.cast()?)
+}
+
+fn bitmap_format(format: DXGI_FORMAT) -> Option<BitmapPixelFormat> {
+    match format {
+        DXGI_FORMAT_B8G8R8A8_UNORM => Some(BitmapPixelFormat::Bgra8),
+        DXGI_FORMAT_R8G8B8A8_UNORM => Some(BitmapPixelFormat::Rgba8),
+        DXGI_FORMAT_NV12 => Some(BitmapPixelFormat::Nv12),
+        DXGI_FORMAT_P010 => Some(BitmapPixelFormat::P010),
+        _ => None,
+    }
+}
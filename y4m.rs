@@ -0,0 +1,71 @@
+//! Exportação em YUV4MPEG2 (Y4M) para alimentar ferramentas externas como o ffmpeg
+//!
+//! Exemplo: gravar em stdout e encadear com `ffmpeg -i - saida.mp4`.
+
+use std::io::Write;
+
+use windows::Win32::Graphics::Dxgi::Common::DXGI_FORMAT;
+
+use crate::convert::{i420_size, to_i420};
+use crate::Result;
+
+/// Escreve quadros capturados como um fluxo Y4M 4:2:0
+pub struct Y4mWriter<W: Write> {
+    writer: W,
+    width: u32,
+    height: u32,
+    fps_num: u32,
+    fps_den: u32,
+    header_written: bool,
+    i420: Vec<u8>,
+}
+
+impl<W: Write> Y4mWriter<W> {
+    pub fn new(writer: W, width: u32, height: u32, fps_num: u32, fps_den: u32) -> Result<Self> {
+        if width == 0 || height == 0 {
+            return Err("Dimensões inválidas para Y4M".into());
+        }
+        if fps_num == 0 || fps_den == 0 {
+            return Err("Taxa de quadros inválida para Y4M".into());
+        }
+
+        Ok(Self {
+            writer,
+            width,
+            height,
+            fps_num,
+            fps_den,
+            header_written: false,
+            i420: vec![0u8; i420_size(width, height)],
+        })
+    }
+
+    /// Converte o quadro BGRA/RGBA para I420 e o escreve no fluxo
+    pub fn write_frame(&mut self, pixels: &[u8], format: DXGI_FORMAT) -> Result<()> {
+        to_i420(pixels, format, self.width, self.height, &mut self.i420)?;
+
+        if !self.header_written {
+            writeln!(
+                self.writer,
+                "YUV4MPEG2 W{} H{} F{}:{} Ip A1:1 C420jpeg XCOLORRANGE=LIMITED",
+                self.width, self.height, self.fps_num, self.fps_den
+            )?;
+            self.header_written = true;
+        }
+
+        self.writer.write_all(b"FRAME\n")?;
+        self.writer.write_all(&self.i420)?;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Descarrega o fluxo e devolve o escritor subjacente
+    pub fn into_inner(mut self) -> Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}